@@ -0,0 +1,48 @@
+//! Arbitrary replies to a query, one text frame per line, from a server
+//! that then hangs up: the client's stream must end without panicking.
+
+#![no_main]
+
+use futures_util::{SinkExt, StreamExt};
+use libfuzzer_sys::fuzz_target;
+use md_qa_client::{connect, StreamCollector};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let frames: Vec<String> = text.lines().map(str::to_string).collect();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async move {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://127.0.0.1:{}", listener.local_addr().unwrap().port());
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let _ = ws.next().await;
+            for frame in frames {
+                if ws.send(Message::Text(frame)).await.is_err() {
+                    return;
+                }
+            }
+            let _ = ws.close(None).await;
+        });
+
+        let client = connect(&url).await.unwrap();
+        let stream = client.query_stream("question", None);
+        let events: Vec<_> = tokio::time::timeout(Duration::from_secs(5), stream.collect())
+            .await
+            .expect("the stream ends once the server hangs up");
+        let mut collector = StreamCollector::new();
+        for event in events.iter().flatten() {
+            collector.push(event);
+        }
+        collector.answer_spans();
+    });
+});
@@ -0,0 +1,30 @@
+//! Arbitrary bytes as a text or binary (already decoded) server frame.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use md_qa_client::cite_answer;
+use md_qa_client::messages::{ServerFrame, ServerMessage};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        match ServerFrame::parse(text) {
+            Ok(frame) => {
+                let reparsed =
+                    ServerFrame::parse(&frame.to_text()).expect("parsed frames parse back");
+                if matches!(frame.message, ServerMessage::Unknown { .. }) {
+                    assert_eq!(reparsed, frame, "unknown messages are written back as read");
+                }
+                if let ServerMessage::StreamEnd { citations, .. } = &frame.message {
+                    cite_answer(text, citations);
+                }
+            }
+            Err(e) => {
+                let _ = e.to_string();
+            }
+        }
+    }
+    if let Ok(value) = serde_json::from_slice::<serde_json::Value>(data) {
+        let _ = ServerFrame::from_value(value);
+    }
+});
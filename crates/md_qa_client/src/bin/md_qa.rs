@@ -2,22 +2,132 @@
 //! Loads config when available, connects to WebSocket server, sends a query
 //! from a positional argument or stdin, and prints streamed answer/sources.
 
+use futures_util::StreamExt;
 use md_qa_client::config;
-use md_qa_client::StreamEvent;
+use md_qa_client::messages::{
+    ChunkCacheStats, ErrorCode, ErrorMessage, IndexEstimate, IndexInfo, IndexPlanMessage,
+    IndexProgressMessage, IndexSnapshotMessage, IndexVerifyMessage, Prefer, QueryFilters,
+    QueryMessage, QueryOptions, QueryStats, Source, Usage,
+};
+use md_qa_client::querylog::{self, LogEntry};
+use md_qa_client::{
+    embedding_cost, ClientBuilder, ClientError, Console, ExportFormat, HookError, HookFailure,
+    Hooks, PriceTable, RetryPolicy, StreamCollector, StreamEvent, LARGE_RELOAD_TOKENS,
+};
 use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::PathBuf;
 use std::process;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 struct CliOptions {
     config_path: Option<PathBuf>,
     question: Option<String>,
+    prefer: Option<Prefer>,
+    verify: bool,
+    query_options: QueryOptions,
+    /// Documents the question may draw on (`--filter`).
+    filters: QueryFilters,
+    /// Write the answer to a temporary Markdown file and open it in an editor.
+    edit: bool,
+    /// Print suggested follow-up questions under the answer.
+    followups: bool,
+    /// Print the token usage and server time of the answer.
+    stats: bool,
+    /// Print the model's reasoning, dimmed, before the answer.
+    thinking: bool,
+    /// Attach a `git diff` of the current repository to the question.
+    git_diff: Option<GitDiff>,
+    /// Named index to ask (`--index`) instead of `server.index_name`.
+    index: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Changes `--git-diff` attaches to the question.
+#[derive(Debug, Clone, PartialEq)]
+enum GitDiff {
+    /// What is staged for the next commit (`git diff --cached`).
+    Staged,
+    /// A revision range such as `main...HEAD` (`git diff RANGE`).
+    Range(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 enum CliCommand {
     Run(CliOptions),
-    PrintHelp { program_name: String },
+    ReportGaps {
+        config_path: Option<PathBuf>,
+    },
+    HistoryCost {
+        config_path: Option<PathBuf>,
+    },
+    HistoryExport {
+        config_path: Option<PathBuf>,
+        format: ExportFormat,
+    },
+    Status {
+        config_path: Option<PathBuf>,
+    },
+    /// The `index` commands below take the named index to act on
+    /// (`--index`); `None` is the server's configured index.
+    IndexStats {
+        config_path: Option<PathBuf>,
+        index: Option<String>,
+    },
+    IndexReload {
+        config_path: Option<PathBuf>,
+        index: Option<String>,
+        /// Start a large build without asking.
+        yes: bool,
+    },
+    IndexPlan {
+        config_path: Option<PathBuf>,
+        index: Option<String>,
+    },
+    IndexVerify {
+        config_path: Option<PathBuf>,
+        index: Option<String>,
+        remove: bool,
+    },
+    IndexSnapshot {
+        config_path: Option<PathBuf>,
+        index: Option<String>,
+        out: PathBuf,
+    },
+    IndexRestore {
+        config_path: Option<PathBuf>,
+        index: Option<String>,
+        file: PathBuf,
+    },
+    IndexList {
+        config_path: Option<PathBuf>,
+    },
+    IndexCreate {
+        config_path: Option<PathBuf>,
+        name: String,
+        directories: Vec<PathBuf>,
+        /// `--target-tokens`, `--overlap-tokens`, `--heading-level`; unset
+        /// ones are the server's.
+        chunking: config::ChunkSettings,
+        embedding_model: Option<String>,
+    },
+    IndexDelete {
+        config_path: Option<PathBuf>,
+        name: String,
+    },
+    Links {
+        config_path: Option<PathBuf>,
+        path: String,
+    },
+    NewConfig {
+        config_path: Option<PathBuf>,
+        template: PathBuf,
+        vars: Vec<(String, String)>,
+        force: bool,
+    },
+    PrintHelp {
+        program_name: String,
+    },
     PrintVersion,
 }
 
@@ -27,9 +137,64 @@ fn help_text(program_name: &str) -> String {
 
 Usage:
   {program_name} [OPTIONS] [QUESTION]
+  {program_name} [OPTIONS] report gaps
+  {program_name} [OPTIONS] history cost
+  {program_name} [OPTIONS] history export [--as FORMAT]
+  {program_name} [OPTIONS] status
+  {program_name} [OPTIONS] index stats
+  {program_name} [OPTIONS] index reload [--yes]
+  {program_name} [OPTIONS] index plan
+  {program_name} [OPTIONS] index verify [--remove]
+  {program_name} [OPTIONS] index snapshot --out <FILE>
+  {program_name} [OPTIONS] index restore <FILE>
+  {program_name} [OPTIONS] index list
+  {program_name} [OPTIONS] index create <NAME> --dir <DIR>... [--embedding-model <MODEL>]
+  {program_name} [OPTIONS] index delete <NAME>
+  {program_name} [OPTIONS] links <DOCUMENT>
+  {program_name} [OPTIONS] config new --template <PATH> [--var NAME=VALUE]...
 
 Options:
   -c, --config <PATH>  Optional config file path
+      --prefer <KIND>  Retrieval preference: code, prose or auto (default: server decides)
+      --verify         Ask the server to check that the answer is grounded in its sources
+      --top-k <N>      Number of chunks to retrieve (default: server decides)
+      --temperature <T>
+                       LLM sampling temperature (default: server decides)
+      --max-tokens <N> Upper bound on the answer length in tokens
+      --language <LANG>
+                       Answer in this language (e.g. en, de)
+      --model <NAME>   LLM to answer with (default: the server's model)
+      --candidates <N> Ask for N alternative answers and print the others
+                       under the first, for servers that support it
+      --filter <KIND:VALUE>
+                       Only answer from matching documents: dir:PREFIX,
+                       tag:NAME or glob:PATTERN (repeatable)
+      --edit           Write the answer to a temporary Markdown file, print
+                       its path and open it in $VISUAL or $EDITOR
+      --followups      Print suggested follow-up questions under the answer
+      --stats          Print the tokens the answer used and the server's time
+      --thinking       Print the model's reasoning (dimmed) before the answer,
+                       for servers that stream it
+      --git-diff[=RANGE]
+                       Attach the staged changes (or `git diff RANGE`) of the
+                       current repository to the question
+      --template <PATH>
+                       Config template for `config new`
+      --var <NAME=VALUE>
+                       Value for a template placeholder (repeatable)
+      --force          Let `config new` overwrite an existing config file
+      --as <FORMAT>    What `history export` writes: snippet (default) or issue
+      --remove         Let `index verify` drop the orphaned chunks it finds
+      --out <FILE>     Archive `index snapshot` writes (.tar.zst, .tar.gz or .tar)
+      --index <NAME>   Named index to ask, or to run an index command on
+                       (default: server.index_name for questions, the
+                       server's configured index for index commands)
+      --dir <DIR>      Directory `index create` indexes (repeatable)
+      --embedding-model <MODEL>
+                       Embedding model of `index create` (default: the server's)
+      --target-tokens <N>, --overlap-tokens <N>, --heading-level <N>
+                       Chunking of `index create` (default: the server's)
+  -y, --yes            Let `index reload` start a large build without asking
   -h, --help           Print help and exit
   -V, --version        Print version and exit
 
@@ -42,10 +207,96 @@ Config:
 Input:
   QUESTION: optional positional question to send.
   If QUESTION is omitted, reads one question from stdin (first line).
+
+Status:
+  `status` asks the server whether its index is loaded and prints the
+  reply with the request latency (plus the document count and index age
+  when the server reports them).
+
+Index:
+  `index stats` prints what the server's index holds (documents, chunks),
+  the embedding model it was built with, when it was built and its size
+  on disk, to sanity-check a setup. `index reload` asks the server to
+  pick up changed documents, shows its progress and then lists the
+  documents it could not index. When the reload would embed a lot (see
+  the Reports section for `prices`), it first prints the files, tokens,
+  estimated cost and time and asks whether to go ahead; --yes skips that. `index plan` lists the files the server
+  would index and the ones its ignore files (`.gitignore`, `.mdqaignore`
+  in each configured directory) leave out, without indexing anything.
+  `index verify` lists documents whose chunks are still in the index
+  although they were deleted, renamed or are now ignored; with --remove
+  the server drops those chunks (it also does so on each reload).
+  `index snapshot --out FILE` has the server package its index (vector
+  store, chunk texts, manifest and embeddings) into FILE, and `index
+  restore FILE` has a teammate's server load it instead of embedding the
+  same documents again. FILE is on the server's machine; a restore is
+  refused when the snapshot was embedded with another model.
+  `index create NAME --dir DIR` has the server build another index over
+  DIR, with its own chunking and embedding model, which --index NAME then
+  asks or runs the commands above on. `index list` prints the indexes and
+  whether they are ready; `index delete NAME` removes one and its files.
+
+Links:
+  `links` lists the indexed documents that DOCUMENT (a path as indexed)
+  links to and the ones that link to it.
+
+Templates:
+  `config new` fills the {{{{ NAME }}}} placeholders of a shared config
+  template with --var values, validates the result and writes it to the
+  config path (--config, MD_QA_CONFIG or ~/.md-qa/config.yaml).
+
+Hooks:
+  hooks.pre_query and hooks.post_answer in the config run shell commands
+  around each question: the first gets the question on stdin and may print
+  a replacement, the second gets {{question, answer, sources}} JSON.
+
+Reports:
+  Each question is logged to queries.jsonl next to the config file.
+  `report gaps` lists logged questions that got no sources or a server
+  error, i.e. topics the docs apparently don't cover.
+  `history cost` sums the tokens of the logged answers and estimates their
+  cost from the `prices` table of the config.
+  `history export` prints the last answered question with its answer and
+  sources as a Markdown snippet, or with --as issue as an issue body (e.g.
+  for `gh issue create --body-file -`); export.issue_template and
+  export.snippet_template in the config replace the built-in templates.
+
+Exit codes:
+    0  answer printed
+    1  bad config, empty question or no diff for --git-diff
+    2  invalid command-line usage
+    3  could not connect to the server
+    4  timed out waiting for the server
+    5  the server reported an error
+    6  connection lost or malformed server response
+    7  server is up but its index is not ready (status, or a query
+       still turned away after retrying)
+    8  a hook failed and hooks.on_failure is abort
+  130  interrupted with Ctrl-C (the server is told to stop the answer)
 "
     )
 }
 
+/// Value of an option given as `--name VALUE` or `--name=VALUE`.
+fn option_value(
+    flag: &str,
+    inline: Option<&str>,
+    args: &mut impl Iterator<Item = String>,
+    program_name: &str,
+) -> Result<String, String> {
+    let value = match inline {
+        Some(v) => Some(v.to_string()),
+        None => args.next(),
+    };
+    match value {
+        Some(v) if !v.is_empty() => Ok(v),
+        _ => Err(format!(
+            "Error: {flag} requires a value\n\n{}",
+            help_text(program_name)
+        )),
+    }
+}
+
 fn parse_cli_command_from<I, S>(args: I) -> Result<CliCommand, String>
 where
     I: IntoIterator<Item = S>,
@@ -55,29 +306,164 @@ where
     let program_name = args.next().unwrap_or_else(|| "md-qa".to_string());
     let mut config_path: Option<PathBuf> = None;
     let mut question: Option<String> = None;
+    let mut prefer: Option<Prefer> = None;
+    let mut verify = false;
+    let mut edit = false;
+    let mut followups = false;
+    let mut stats = false;
+    let mut thinking = false;
+    let mut git_diff: Option<GitDiff> = None;
+    let mut query_options = QueryOptions::default();
+    let mut filters = QueryFilters::default();
+    let mut report: Option<String> = None;
+    let mut template: Option<PathBuf> = None;
+    let mut vars: Vec<(String, String)> = Vec::new();
+    let mut force = false;
+    let mut export_as: Option<ExportFormat> = None;
+    let mut remove = false;
+    let mut yes = false;
+    let mut out: Option<PathBuf> = None;
+    // Snapshot file of `index restore`, index name of `index create/delete`
+    let mut target: Option<String> = None;
+    let mut index: Option<String> = None;
+    let mut directories: Vec<PathBuf> = Vec::new();
+    let mut chunking = config::ChunkSettings::default();
+    let mut embedding_model: Option<String> = None;
 
     while let Some(arg) = args.next() {
-        match arg.as_str() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        match flag {
             "-h" | "--help" => return Ok(CliCommand::PrintHelp { program_name }),
             "-V" | "--version" => return Ok(CliCommand::PrintVersion),
             "-c" | "--config" => {
-                let value = args.next().ok_or_else(|| {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                config_path = Some(PathBuf::from(value));
+            }
+            "--prefer" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                let parsed = value
+                    .parse()
+                    .map_err(|e| format!("Error: {e}\n\n{}", help_text(&program_name)))?;
+                prefer = Some(parsed);
+            }
+            "--verify" => verify = true,
+            "--edit" => edit = true,
+            "--followups" => followups = true,
+            "--stats" => stats = true,
+            "--thinking" => thinking = true,
+            "--git-diff" => {
+                git_diff = Some(match inline {
+                    None => GitDiff::Staged,
+                    Some(range) => {
+                        GitDiff::Range(option_value(flag, Some(range), &mut args, &program_name)?)
+                    }
+                });
+            }
+            "--top-k" | "--max-tokens" | "--temperature" | "--candidates" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                let invalid = || {
+                    format!(
+                        "Error: invalid {flag} value: {value}\n\n{}",
+                        help_text(&program_name)
+                    )
+                };
+                match flag {
+                    "--top-k" => {
+                        query_options.top_k = Some(value.parse().map_err(|_| invalid())?);
+                    }
+                    "--max-tokens" => {
+                        query_options.max_tokens = Some(value.parse().map_err(|_| invalid())?);
+                    }
+                    "--candidates" => {
+                        let n: u32 = value.parse().map_err(|_| invalid())?;
+                        if n == 0 {
+                            return Err(invalid());
+                        }
+                        query_options.candidates = Some(n);
+                    }
+                    _ => {
+                        let t: f32 = value.parse().map_err(|_| invalid())?;
+                        if !(0.0..=2.0).contains(&t) {
+                            return Err(invalid());
+                        }
+                        query_options.temperature = Some(t);
+                    }
+                }
+            }
+            "--language" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                query_options.language = Some(value);
+            }
+            "--model" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                query_options.model = Some(value);
+            }
+            "--filter" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                filters
+                    .add(&value)
+                    .map_err(|e| format!("Error: {e}\n\n{}", help_text(&program_name)))?;
+            }
+            "--template" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                template = Some(PathBuf::from(value));
+            }
+            "--var" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                let (name, value) = value.split_once('=').ok_or_else(|| {
                     format!(
-                        "Error: {arg} requires a value\n\n{}",
+                        "Error: --var expects NAME=VALUE, got {value}\n\n{}",
                         help_text(&program_name)
                     )
                 })?;
-                config_path = Some(PathBuf::from(value));
+                vars.push((name.trim().to_string(), value.to_string()));
             }
-            _ if arg.starts_with("--config=") => {
-                let (_, value) = arg.split_once('=').expect("checked with starts_with");
-                if value.is_empty() {
-                    return Err(format!(
-                        "Error: --config requires a value\n\n{}",
+            "--force" => force = true,
+            "--remove" => remove = true,
+            "--out" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                out = Some(PathBuf::from(value));
+            }
+            "-y" | "--yes" => yes = true,
+            "--index" => {
+                index = Some(option_value(flag, inline, &mut args, &program_name)?);
+            }
+            "--dir" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                directories.push(PathBuf::from(value));
+            }
+            "--embedding-model" => {
+                embedding_model = Some(option_value(flag, inline, &mut args, &program_name)?);
+            }
+            "--target-tokens" | "--overlap-tokens" | "--heading-level" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                let invalid = || {
+                    format!(
+                        "Error: invalid {flag} value: {value}\n\n{}",
                         help_text(&program_name)
-                    ));
+                    )
+                };
+                match flag {
+                    "--target-tokens" => {
+                        chunking.target_tokens = Some(value.parse().map_err(|_| invalid())?);
+                    }
+                    "--overlap-tokens" => {
+                        chunking.overlap_tokens = Some(value.parse().map_err(|_| invalid())?);
+                    }
+                    _ => {
+                        chunking.heading_level = Some(value.parse().map_err(|_| invalid())?);
+                    }
                 }
-                config_path = Some(PathBuf::from(value));
+            }
+            "--as" => {
+                let value = option_value(flag, inline, &mut args, &program_name)?;
+                let parsed = value
+                    .parse()
+                    .map_err(|e| format!("Error: {e}\n\n{}", help_text(&program_name)))?;
+                export_as = Some(parsed);
             }
             _ if arg.starts_with('-') => {
                 return Err(format!(
@@ -88,6 +474,24 @@ where
             _ => {
                 if question.is_none() {
                     question = Some(arg);
+                } else if matches!(
+                    question.as_deref(),
+                    Some("report")
+                        | Some("config")
+                        | Some("links")
+                        | Some("history")
+                        | Some("index")
+                ) && report.is_none()
+                {
+                    report = Some(arg);
+                } else if question.as_deref() == Some("index")
+                    && matches!(
+                        report.as_deref(),
+                        Some("restore") | Some("create") | Some("delete")
+                    )
+                    && target.is_none()
+                {
+                    target = Some(arg);
                 } else {
                     return Err(format!(
                         "Error: unexpected positional argument: {arg}\n\n{}",
@@ -98,9 +502,202 @@ where
         }
     }
 
+    if question.as_deref() == Some("config") {
+        return match (report.as_deref(), template) {
+            (Some("new"), Some(template)) => Ok(CliCommand::NewConfig {
+                config_path,
+                template,
+                vars,
+                force,
+            }),
+            (Some("new"), None) => Err(format!(
+                "Error: config new requires --template\n\n{}",
+                help_text(&program_name)
+            )),
+            (other, _) => Err(format!(
+                "Error: unknown config command: {}\n\n{}",
+                other.unwrap_or(""),
+                help_text(&program_name)
+            )),
+        };
+    }
+    if template.is_some() || !vars.is_empty() || force {
+        return Err(format!(
+            "Error: --template, --var and --force only apply to config new\n\n{}",
+            help_text(&program_name)
+        ));
+    }
+
+    if remove && !(question.as_deref() == Some("index") && report.as_deref() == Some("verify")) {
+        return Err(format!(
+            "Error: --remove only applies to index verify\n\n{}",
+            help_text(&program_name)
+        ));
+    }
+
+    if yes && !(question.as_deref() == Some("index") && report.as_deref() == Some("reload")) {
+        return Err(format!(
+            "Error: --yes only applies to index reload\n\n{}",
+            help_text(&program_name)
+        ));
+    }
+
+    if out.is_some()
+        && !(question.as_deref() == Some("index") && report.as_deref() == Some("snapshot"))
+    {
+        return Err(format!(
+            "Error: --out only applies to index snapshot\n\n{}",
+            help_text(&program_name)
+        ));
+    }
+
+    if (!directories.is_empty()
+        || chunking != config::ChunkSettings::default()
+        || embedding_model.is_some())
+        && !(question.as_deref() == Some("index") && report.as_deref() == Some("create"))
+    {
+        return Err(format!(
+            "Error: --dir, --embedding-model, --target-tokens, --overlap-tokens and \
+             --heading-level only apply to index create\n\n{}",
+            help_text(&program_name)
+        ));
+    }
+
+    let whole_server = matches!(
+        question.as_deref(),
+        Some("report") | Some("links") | Some("history") | Some("status")
+    ) || (question.as_deref() == Some("index")
+        && matches!(
+            report.as_deref(),
+            Some("list") | Some("create") | Some("delete")
+        ));
+    if index.is_some() && whole_server {
+        return Err(format!(
+            "Error: --index only applies to questions and index stats, reload, plan, \
+             verify, snapshot and restore\n\n{}",
+            help_text(&program_name)
+        ));
+    }
+
+    if question.as_deref() == Some("links") {
+        return match report {
+            Some(path) => Ok(CliCommand::Links { config_path, path }),
+            None => Err(format!(
+                "Error: links requires a document path\n\n{}",
+                help_text(&program_name)
+            )),
+        };
+    }
+
+    if let (Some("history"), Some(command)) = (question.as_deref(), report.as_deref()) {
+        return match command {
+            "cost" => Ok(CliCommand::HistoryCost { config_path }),
+            "export" => Ok(CliCommand::HistoryExport {
+                config_path,
+                format: export_as.unwrap_or(ExportFormat::Snippet),
+            }),
+            other => Err(format!(
+                "Error: unknown history command: {other}\n\n{}",
+                help_text(&program_name)
+            )),
+        };
+    }
+
+    if let (Some("index"), Some(command)) = (question.as_deref(), report.as_deref()) {
+        return match command {
+            "stats" => Ok(CliCommand::IndexStats { config_path, index }),
+            "reload" => Ok(CliCommand::IndexReload {
+                config_path,
+                index,
+                yes,
+            }),
+            "plan" => Ok(CliCommand::IndexPlan { config_path, index }),
+            "verify" => Ok(CliCommand::IndexVerify {
+                config_path,
+                index,
+                remove,
+            }),
+            "snapshot" => match out {
+                Some(out) => Ok(CliCommand::IndexSnapshot {
+                    config_path,
+                    index,
+                    out,
+                }),
+                None => Err(format!(
+                    "Error: index snapshot requires --out\n\n{}",
+                    help_text(&program_name)
+                )),
+            },
+            "restore" => match target {
+                Some(file) => Ok(CliCommand::IndexRestore {
+                    config_path,
+                    index,
+                    file: PathBuf::from(file),
+                }),
+                None => Err(format!(
+                    "Error: index restore requires a snapshot file\n\n{}",
+                    help_text(&program_name)
+                )),
+            },
+            "list" => Ok(CliCommand::IndexList { config_path }),
+            "create" => match (target, directories.is_empty()) {
+                (Some(name), false) => Ok(CliCommand::IndexCreate {
+                    config_path,
+                    name,
+                    directories,
+                    chunking,
+                    embedding_model,
+                }),
+                (None, _) => Err(format!(
+                    "Error: index create requires an index name\n\n{}",
+                    help_text(&program_name)
+                )),
+                (Some(_), true) => Err(format!(
+                    "Error: index create requires --dir\n\n{}",
+                    help_text(&program_name)
+                )),
+            },
+            "delete" => match target {
+                Some(name) => Ok(CliCommand::IndexDelete { config_path, name }),
+                None => Err(format!(
+                    "Error: index delete requires an index name\n\n{}",
+                    help_text(&program_name)
+                )),
+            },
+            other => Err(format!(
+                "Error: unknown index command: {other}\n\n{}",
+                help_text(&program_name)
+            )),
+        };
+    }
+
+    match report.as_deref() {
+        Some("gaps") => return Ok(CliCommand::ReportGaps { config_path }),
+        Some(other) => {
+            return Err(format!(
+                "Error: unknown report: {other}\n\n{}",
+                help_text(&program_name)
+            ));
+        }
+        None => {}
+    }
+    if question.as_deref() == Some("status") {
+        return Ok(CliCommand::Status { config_path });
+    }
+
     Ok(CliCommand::Run(CliOptions {
         config_path,
         question,
+        prefer,
+        verify,
+        query_options,
+        filters,
+        edit,
+        followups,
+        stats,
+        thinking,
+        git_diff,
+        index,
     }))
 }
 
@@ -108,6 +705,22 @@ fn parse_cli_command() -> Result<CliCommand, String> {
     parse_cli_command_from(std::env::args())
 }
 
+/// Config file that `load_runtime_config` reads, whether or not it exists:
+/// `--config`, else `MD_QA_CONFIG`, else `~/.md-qa/config.yaml`.
+fn config_file_path(cli_override_path: Option<PathBuf>) -> Option<PathBuf> {
+    let env_path = std::env::var("MD_QA_CONFIG").ok().map(PathBuf::from);
+    cli_override_path
+        .or(env_path)
+        .or_else(config::default_config_path)
+}
+
+/// Query log next to the config file that `load_runtime_config` would use
+/// (`~/.md-qa/queries.jsonl` when running on built-in defaults).
+fn query_log_path(cli_override_path: Option<PathBuf>) -> Option<PathBuf> {
+    let config_path = config_file_path(cli_override_path)?;
+    Some(querylog::path_for_config(&config_path))
+}
+
 fn load_runtime_config(cli_override_path: Option<PathBuf>) -> Result<config::Config, String> {
     let env_path = std::env::var("MD_QA_CONFIG").ok().map(PathBuf::from);
     let default_path = config::default_config_path();
@@ -120,131 +733,1256 @@ fn load_runtime_config_from_paths(
     default_path: Option<PathBuf>,
 ) -> Result<config::Config, String> {
     if let Some(path) = cli_override_path {
-        return config::load(&path).map_err(|e| {
-            format!(
-                "Error: failed to load config from {}: {}",
-                path.display(),
-                e
-            )
-        });
+        return load_config_file(&path);
     }
 
     if let Some(path) = env_path {
-        return config::load(&path).map_err(|e| {
-            format!(
-                "Error: failed to load config from {}: {}",
-                path.display(),
-                e
-            )
-        });
+        return load_config_file(&path);
     }
 
     if let Some(path) = default_path {
         if path.exists() {
-            return config::load(&path).map_err(|e| {
-                format!(
-                    "Error: failed to load config from {}: {}",
-                    path.display(),
-                    e
-                )
-            });
+            return load_config_file(&path);
         }
     }
 
     Ok(config::Config::default())
 }
 
+/// Load the config file at `path`, printing its warnings (e.g. duplicate
+/// keys) to stderr.
+fn load_config_file(path: &std::path::Path) -> Result<config::Config, String> {
+    let loaded = config::load_with_warnings(path).map_err(|e| {
+        format!(
+            "Error: failed to load config from {}: {}",
+            path.display(),
+            e
+        )
+    })?;
+    for warning in &loaded.warnings {
+        eprintln!("Warning: {}: {}", path.display(), warning);
+    }
+    Ok(loaded.config)
+}
+
 fn main() {
     match parse_cli_command() {
         Ok(CliCommand::PrintHelp { program_name }) => {
             print!("{}", help_text(&program_name));
-            return;
         }
         Ok(CliCommand::PrintVersion) => {
             println!("md-qa {}", env!("CARGO_PKG_VERSION"));
-            return;
         }
         Ok(CliCommand::Run(cli_options)) => run(cli_options),
+        Ok(CliCommand::ReportGaps { config_path }) => report_gaps(config_path),
+        Ok(CliCommand::HistoryCost { config_path }) => history_cost(config_path),
+        Ok(CliCommand::HistoryExport {
+            config_path,
+            format,
+        }) => history_export(config_path, format),
+        Ok(CliCommand::Status { config_path }) => status(config_path),
+        Ok(CliCommand::IndexStats { config_path, index }) => index_stats(config_path, index),
+        Ok(CliCommand::IndexReload {
+            config_path,
+            index,
+            yes,
+        }) => index_reload(config_path, index, yes),
+        Ok(CliCommand::IndexPlan { config_path, index }) => index_plan(config_path, index),
+        Ok(CliCommand::IndexVerify {
+            config_path,
+            index,
+            remove,
+        }) => index_verify(config_path, index, remove),
+        Ok(CliCommand::IndexSnapshot {
+            config_path,
+            index,
+            out,
+        }) => index_snapshot(config_path, index, &out),
+        Ok(CliCommand::IndexRestore {
+            config_path,
+            index,
+            file,
+        }) => index_restore(config_path, index, &file),
+        Ok(CliCommand::IndexList { config_path }) => index_list(config_path),
+        Ok(CliCommand::IndexCreate {
+            config_path,
+            name,
+            directories,
+            chunking,
+            embedding_model,
+        }) => index_create(
+            config_path,
+            &name,
+            &directories,
+            chunking,
+            embedding_model.as_deref(),
+        ),
+        Ok(CliCommand::IndexDelete { config_path, name }) => index_delete(config_path, &name),
+        Ok(CliCommand::Links { config_path, path }) => links(config_path, &path),
+        Ok(CliCommand::NewConfig {
+            config_path,
+            template,
+            vars,
+            force,
+        }) => new_config(config_path, &template, &vars, force),
         Err(message) => {
             eprintln!("{message}");
             process::exit(2);
         }
     }
-}
+}
+
+/// Exit code for a failed query (see "Exit codes" in the help text).
+fn exit_code(err: &ClientError) -> i32 {
+    match err {
+        ClientError::Connect(_) => 3,
+        ClientError::Timeout(_) => 4,
+        ClientError::ServerError(ErrorMessage {
+            code: Some(ErrorCode::IndexNotReady),
+            ..
+        }) => 7,
+        ClientError::ServerError(_) => 5,
+        ClientError::Io(_) | ClientError::Protocol(_) | ClientError::Closed => 6,
+    }
+}
+
+/// Print `err` and exit with its category's exit code.
+fn fail(err: ClientError) -> ! {
+    eprintln!("Error: {}", err);
+    process::exit(exit_code(&err));
+}
+
+/// Report a failed hook: exit with code 8 under `hooks.on_failure: abort`,
+/// else warn and carry on.
+fn hook_failed(hooks: &Hooks, err: HookError) {
+    if hooks.on_failure == HookFailure::Abort {
+        eprintln!("Error: {}", err);
+        process::exit(8);
+    }
+    eprintln!("Warning: {}", err);
+}
+
+fn new_config(
+    config_path: Option<PathBuf>,
+    template: &std::path::Path,
+    vars: &[(String, String)],
+    force: bool,
+) {
+    let target = config_file_path(config_path).unwrap_or_else(|| {
+        eprintln!("Error: no config path (pass --config or set MD_QA_CONFIG)");
+        process::exit(1);
+    });
+    let text = std::fs::read_to_string(template).unwrap_or_else(|e| {
+        eprintln!(
+            "Error: failed to read template {}: {}",
+            template.display(),
+            e
+        );
+        process::exit(1);
+    });
+    let rendered = config::render_template(&text, vars).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    });
+    if target.exists() && !force {
+        eprintln!(
+            "Error: {} already exists (pass --force to overwrite)",
+            target.display()
+        );
+        process::exit(1);
+    }
+    if let Err(e) = config::save_rendered(&target, &rendered) {
+        eprintln!("Error: failed to write {}: {}", target.display(), e);
+        process::exit(1);
+    }
+    println!("Wrote {}", target.display());
+}
+
+fn report_gaps(config_path: Option<PathBuf>) {
+    let entries = match query_log_path(config_path) {
+        Some(path) => querylog::load(&path).unwrap_or_else(|e| {
+            eprintln!("Error: failed to read query log {}: {}", path.display(), e);
+            process::exit(1);
+        }),
+        None => Vec::new(),
+    };
+    let gaps = querylog::gaps(&entries);
+    if gaps.is_empty() {
+        println!("No unanswered or unhelpful questions logged.");
+        return;
+    }
+    println!("Questions the docs may not cover:");
+    for gap in &gaps {
+        println!(
+            "  {} (asked {}, unanswered {}, unhelpful {})",
+            gap.question, gap.asked, gap.unanswered, gap.unhelpful
+        );
+    }
+}
+
+fn history_cost(config_path: Option<PathBuf>) {
+    let cfg = load_runtime_config(config_path.clone()).unwrap_or_else(|message| {
+        eprintln!("{message}");
+        process::exit(1);
+    });
+    let entries = match query_log_path(config_path) {
+        Some(path) => querylog::load(&path).unwrap_or_else(|e| {
+            eprintln!("Error: failed to read query log {}: {}", path.display(), e);
+            process::exit(1);
+        }),
+        None => Vec::new(),
+    };
+    let totals = querylog::usage(&entries, &cfg.prices, cfg.api.llm_model.as_deref());
+    if totals.answers == 0 {
+        println!("No token usage logged.");
+        return;
+    }
+    println!("Answers:           {}", totals.answers);
+    println!("Prompt tokens:     {}", totals.prompt_tokens);
+    println!("Completion tokens: {}", totals.completion_tokens);
+    println!("Estimated cost:    {:.4}", totals.cost);
+    if totals.unpriced > 0 {
+        println!(
+            "  ({} answers left out: their model has no entry in `prices`)",
+            totals.unpriced
+        );
+    }
+}
+
+/// Print the last answered question of the query log in `format`, e.g. to
+/// pipe into `gh issue create --body-file -`.
+fn history_export(config_path: Option<PathBuf>, format: ExportFormat) {
+    let cfg = load_runtime_config(config_path.clone()).unwrap_or_else(|message| {
+        eprintln!("{message}");
+        process::exit(1);
+    });
+    let entries = match query_log_path(config_path) {
+        Some(path) => querylog::load(&path).unwrap_or_else(|e| {
+            eprintln!("Error: failed to read query log {}: {}", path.display(), e);
+            process::exit(1);
+        }),
+        None => Vec::new(),
+    };
+    let Some(conversation) = querylog::last_answer(&entries) else {
+        eprintln!("Error: no answered question in the query log");
+        process::exit(1);
+    };
+    match format.render(&cfg.export, &conversation) {
+        Ok(text) => print!("{}", text),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Runtime and client builder for a one-off request (`status`, `links`),
+/// exiting with code 1 on a bad config.
+fn request_setup(config_path: Option<PathBuf>) -> (tokio::runtime::Runtime, ClientBuilder) {
+    let cfg = load_runtime_config(config_path).unwrap_or_else(|message| {
+        eprintln!("{message}");
+        process::exit(1);
+    });
+    let builder = ClientBuilder::from_config(&cfg)
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        })
+        .retry(RetryPolicy::transient());
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("Error: failed to create runtime: {}", e);
+            process::exit(1);
+        });
+    (rt, builder)
+}
+
+fn status(config_path: Option<PathBuf>) {
+    let (rt, builder) = request_setup(config_path);
+    let summary = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let summary = client.check_status().await.unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        summary
+    });
+    let mut details = Vec::new();
+    if let Some(documents) = summary.documents {
+        details.push(format!("{} documents", documents));
+    }
+    if let Some(age) = summary.index_age_secs {
+        details.push(format!("indexed {} ago", format_age(age)));
+    }
+    details.push(format!("{} ms", summary.latency_ms));
+    println!("Server: {} ({})", summary.status, details.join(", "));
+    if let Some(message) = &summary.message {
+        println!("{}", message);
+    }
+    if !summary.ready {
+        process::exit(7);
+    }
+}
+
+fn index_stats(config_path: Option<PathBuf>, index: Option<String>) {
+    let (rt, builder) = request_setup(config_path);
+    let stats = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let stats = client
+            .index_stats(index.as_deref())
+            .await
+            .unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        stats
+    });
+    let unknown = || "not reported".to_string();
+    println!("Index: {}", stats.index.as_deref().unwrap_or("default"));
+    println!("Documents: {}", stats.documents);
+    println!("Chunks: {}", stats.chunks);
+    println!(
+        "Embedding model: {}",
+        stats.embedding_model.unwrap_or_else(unknown)
+    );
+    let age = stats
+        .built_at
+        .map(|at| format!("{} ago", format_age(querylog::now().saturating_sub(at))));
+    println!("Built: {}", age.unwrap_or_else(unknown));
+    println!(
+        "Size on disk: {}",
+        stats.size_bytes.map(format_size).unwrap_or_else(unknown)
+    );
+    if let Some(cache) = &stats.chunk_cache {
+        println!("Chunk cache: {}", format_chunk_cache(cache));
+    }
+    if !stats.issues.is_empty() {
+        println!("Files with problems: {}", stats.issues.len());
+        for file in &stats.issues {
+            println!("  {}: {}", file.path, file.issue);
+        }
+    }
+}
+
+fn index_plan(config_path: Option<PathBuf>, index: Option<String>) {
+    let (rt, builder) = request_setup(config_path);
+    let plan = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let plan = client
+            .index_plan(index.as_deref())
+            .await
+            .unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        plan
+    });
+    print_index_plan(&mut io::stdout(), &plan);
+}
+
+/// The files `index plan` would index, then the ignored ones, then both
+/// counts.
+fn print_index_plan(out: &mut impl Write, plan: &IndexPlanMessage) {
+    for file in &plan.files {
+        let _ = writeln!(out, "{}", file);
+    }
+    if !plan.ignored.is_empty() {
+        let _ = writeln!(out, "\nIgnored:");
+        for file in &plan.ignored {
+            let _ = writeln!(out, "  {}", file);
+        }
+    }
+    let _ = writeln!(
+        out,
+        "\n{} files would be indexed, {} ignored",
+        plan.files.len(),
+        plan.ignored.len()
+    );
+}
+
+fn index_verify(config_path: Option<PathBuf>, index: Option<String>, remove: bool) {
+    let (rt, builder) = request_setup(config_path);
+    let report = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let report = client
+            .index_verify(index.as_deref(), remove)
+            .await
+            .unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        report
+    });
+    print_index_verify(&mut io::stdout(), &report);
+}
+
+/// The orphaned documents `index verify` found, with their chunk counts,
+/// and whether they were removed.
+fn print_index_verify(out: &mut impl Write, report: &IndexVerifyMessage) {
+    if report.orphans.is_empty() {
+        let _ = writeln!(out, "Index is consistent: no orphaned chunks");
+        return;
+    }
+    for orphan in &report.orphans {
+        let _ = writeln!(
+            out,
+            "{} ({}, {} chunks)",
+            orphan.path, orphan.reason, orphan.chunks
+        );
+    }
+    let chunks: u64 = report.orphans.iter().map(|orphan| orphan.chunks).sum();
+    let _ = writeln!(
+        out,
+        "\n{} orphaned chunks of {} documents {}",
+        chunks,
+        report.orphans.len(),
+        if report.removed {
+            "removed"
+        } else {
+            "found (run with --remove to drop them)"
+        }
+    );
+}
+
+/// `path` as the absolute path the server reads or writes, resolved
+/// against the current directory like other command-line paths.
+fn absolute_path(path: &std::path::Path) -> String {
+    std::path::absolute(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn index_snapshot(config_path: Option<PathBuf>, index: Option<String>, out: &std::path::Path) {
+    let (rt, builder) = request_setup(config_path);
+    let path = absolute_path(out);
+    let snapshot = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let snapshot = client
+            .index_snapshot(index.as_deref(), &path)
+            .await
+            .unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        snapshot
+    });
+    print_index_snapshot(&mut io::stdout(), &snapshot, false);
+}
+
+fn index_restore(config_path: Option<PathBuf>, index: Option<String>, file: &std::path::Path) {
+    let (rt, builder) = request_setup(config_path);
+    let path = absolute_path(file);
+    let restored = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let restored = client
+            .index_restore(index.as_deref(), &path)
+            .await
+            .unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        restored
+    });
+    print_index_snapshot(&mut io::stdout(), &restored, true);
+}
+
+/// The snapshot `index snapshot` wrote or `index restore` loaded.
+fn print_index_snapshot(out: &mut impl Write, snapshot: &IndexSnapshotMessage, restored: bool) {
+    let _ = writeln!(
+        out,
+        "{} {} chunks ({}) {} {} ({})",
+        if restored { "Restored" } else { "Wrote" },
+        snapshot.chunks,
+        snapshot.embedding_model,
+        if restored { "from" } else { "to" },
+        snapshot.path,
+        format_size(snapshot.size_bytes)
+    );
+    if snapshot.reindexing {
+        let _ = writeln!(
+            out,
+            "Reindexing documents that changed since the snapshot (see `index reload`)"
+        );
+    }
+}
+
+fn index_list(config_path: Option<PathBuf>) {
+    let (rt, builder) = request_setup(config_path);
+    let indexes = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let indexes = client.list_indexes().await.unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        indexes
+    });
+    print_indexes(&mut io::stdout(), &indexes);
+}
+
+fn index_create(
+    config_path: Option<PathBuf>,
+    name: &str,
+    directories: &[PathBuf],
+    chunking: config::ChunkSettings,
+    embedding_model: Option<&str>,
+) {
+    let (rt, builder) = request_setup(config_path);
+    let directories: Vec<String> = directories.iter().map(|dir| absolute_path(dir)).collect();
+    let chunking = config::ChunkingSection {
+        defaults: chunking,
+        ..Default::default()
+    };
+    let indexes = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let indexes = client
+            .create_index(
+                name,
+                &directories,
+                (!chunking.is_empty()).then_some(&chunking),
+                embedding_model,
+            )
+            .await
+            .unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        indexes
+    });
+    println!(
+        "Created index {} (the server builds it in the background)",
+        name
+    );
+    print_indexes(&mut io::stdout(), &indexes);
+}
+
+fn index_delete(config_path: Option<PathBuf>, name: &str) {
+    let (rt, builder) = request_setup(config_path);
+    let indexes = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let indexes = client.delete_index(name).await.unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        indexes
+    });
+    println!("Deleted index {}", name);
+    print_indexes(&mut io::stdout(), &indexes);
+}
+
+/// The indexes `index list` (and `create`, `delete`) got: name, status and
+/// embedding model, then the directories.
+fn print_indexes(out: &mut impl Write, indexes: &[IndexInfo]) {
+    for index in indexes {
+        let _ = writeln!(
+            out,
+            "{}{}: {}, {}",
+            index.name,
+            if index.default { " (default)" } else { "" },
+            index.status.replace('_', " "),
+            index.embedding_model
+        );
+        for directory in &index.directories {
+            let _ = writeln!(out, "  {}", directory);
+        }
+    }
+}
+
+/// How long `index reload` waits for progress before asking whether the
+/// server is still indexing (it sends none when nothing changed).
+const INDEX_STATUS_INTERVAL: Duration = Duration::from_secs(2);
+
+fn index_reload(config_path: Option<PathBuf>, index: Option<String>, yes: bool) {
+    let prices = load_runtime_config(config_path.clone())
+        .map(|cfg| cfg.prices)
+        .unwrap_or_default();
+    let (rt, builder) = request_setup(config_path);
+    let report = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        if !yes {
+            // Servers without estimates answer without one; just reload.
+            let estimate = match client.index_estimate(index.as_deref()).await {
+                Ok(plan) => plan.estimate,
+                Err(ClientError::ServerError(_)) => None,
+                Err(e) => fail(e),
+            };
+            if let Some(estimate) = estimate.filter(|e| e.tokens >= LARGE_RELOAD_TOKENS) {
+                eprint!("{}", format_index_estimate(&estimate, &prices));
+                if !confirm("Start the reload?") {
+                    let _ = client.close(1000, "").await;
+                    eprintln!("Reload cancelled");
+                    process::exit(1);
+                }
+            }
+        }
+        // Subscribe first, so no progress falls between the request and it.
+        let mut events = client.index_progress_events();
+        let started = client
+            .reload_index(index.as_deref())
+            .await
+            .unwrap_or_else(|e| fail(e));
+        if let Some(message) = &started.message {
+            eprintln!("{}", message);
+        }
+        let mut err = io::stderr();
+        let mut progress = ProgressLine::new(err.is_terminal());
+        let report = loop {
+            match tokio::time::timeout(INDEX_STATUS_INTERVAL, events.recv()).await {
+                Ok(Ok(update)) if update.finished => break Some(update),
+                Ok(Ok(update)) => {
+                    if let (Some(path), Some(error)) = (&update.path, &update.error) {
+                        progress.clear(&mut err);
+                        eprintln!("{}: {}", path, error);
+                    }
+                    progress.show(&mut err, "indexing", Some(&index_progress_text(&update)));
+                }
+                Ok(Err(RecvError::Lagged(_))) => {}
+                Ok(Err(RecvError::Closed)) => break None,
+                Err(_) => {
+                    let status = client.status().await.unwrap_or_else(|e| fail(e));
+                    if status.status != "indexing" {
+                        // The last message may have arrived with the status.
+                        break std::iter::from_fn(|| events.try_recv().ok())
+                            .find(|update| update.finished);
+                    }
+                }
+            }
+        };
+        progress.clear(&mut err);
+        let _ = client.close(1000, "").await;
+        report
+    });
+    match report {
+        Some(report) => print_index_report(&mut io::stdout(), &report),
+        None => println!("Index is up to date"),
+    }
+}
+
+/// What a large `index reload` would embed, printed before asking whether
+/// to start it: files, chunks and tokens, the cost from the `prices` table
+/// of the config and the time.
+fn format_index_estimate(estimate: &IndexEstimate, prices: &PriceTable) -> String {
+    let model = estimate.embedding_model.as_deref();
+    let mut text = format!(
+        "This reload {} {} files: {} chunks to embed",
+        if estimate.full_rebuild {
+            "rebuilds the index from"
+        } else {
+            "reads"
+        },
+        estimate.files.len(),
+        estimate.chunks
+    );
+    if estimate.cached_chunks > 0 {
+        text.push_str(&format!(" ({} more are cached)", estimate.cached_chunks));
+    }
+    text.push_str(&format!(
+        "\nTokens:         {} with {}\n",
+        estimate.tokens,
+        model.unwrap_or("the server's embedding model")
+    ));
+    match embedding_cost(prices, model, estimate.tokens) {
+        Some(cost) => text.push_str(&format!("Estimated cost: {:.4}\n", cost)),
+        None => text.push_str("Estimated cost: unknown (the model has no entry in `prices`)\n"),
+    }
+    text.push_str(&format!(
+        "Estimated time: about {}\n",
+        format_age(estimate.seconds.ceil() as u64)
+    ));
+    text
+}
+
+/// Ask a yes/no question on the terminal; anything but "y" or "yes" is no.
+/// Without a terminal to ask on, exit and point at `--yes`.
+fn confirm(question: &str) -> bool {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        eprintln!("Error: not asking without a terminal; pass --yes to go ahead");
+        process::exit(2);
+    }
+    eprint!("{} [y/N] ", question);
+    let _ = io::stderr().flush();
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line).unwrap_or(0);
+    matches!(line.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// One line of `index reload` progress: the documents read, then the
+/// chunks embedded once the server reports them.
+fn index_progress_text(progress: &IndexProgressMessage) -> String {
+    match (progress.chunks, progress.embedded) {
+        (Some(chunks), Some(embedded)) => format!(
+            "Embedding {} {}/{} chunks",
+            progress_bar(embedded, chunks),
+            embedded,
+            chunks
+        ),
+        _ => format!(
+            "Reading {} {}/{} documents",
+            progress_bar(progress.done, progress.total),
+            progress.done,
+            progress.total
+        ),
+    }
+}
+
+/// A 20-column text bar filled in proportion to `done` out of `total`.
+fn progress_bar(done: u64, total: u64) -> String {
+    const WIDTH: u64 = 20;
+    let filled = (done.min(total) * WIDTH)
+        .checked_div(total)
+        .unwrap_or(WIDTH);
+    format!(
+        "[{}{}]",
+        "#".repeat(filled as usize),
+        " ".repeat((WIDTH - filled) as usize)
+    )
+}
+
+/// The summary `index reload` prints once the server finished: how many
+/// documents and chunks it indexed and which documents failed, and why.
+fn print_index_report(out: &mut impl Write, report: &IndexProgressMessage) {
+    let indexed = report.done.saturating_sub(report.failed.len() as u64);
+    let _ = write!(out, "Indexed {} of {} documents", indexed, report.total);
+    if let Some(chunks) = report.chunks {
+        let _ = write!(out, " ({} chunks)", chunks);
+    }
+    let _ = writeln!(out);
+    if !report.failed.is_empty() {
+        let _ = writeln!(out, "Failed: {}", report.failed.len());
+        for file in &report.failed {
+            let _ = writeln!(out, "  {}: {}", file.path, file.issue);
+        }
+    }
+}
+
+/// `bytes` in the largest unit that keeps a whole part: "512 B", "1.5 KB",
+/// "12.3 MB" or "2.0 GB" (units of 1024).
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KB", "MB"] {
+        if size < 1024.0 {
+            return if unit == "B" {
+                format!("{} B", bytes)
+            } else {
+                format!("{:.1} {}", size, unit)
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} GB", size)
+}
+
+/// Hit rate and fill of the server's chunk cache: "67% hits (2 of 3
+/// lookups), 3 of 256 chunks cached".
+fn format_chunk_cache(cache: &ChunkCacheStats) -> String {
+    let lookups = cache.hits + cache.misses;
+    let rate = match cache.hit_rate {
+        Some(rate) => format!(
+            "{:.0}% hits ({} of {} lookups)",
+            rate * 100.0,
+            cache.hits,
+            lookups
+        ),
+        None => "no lookups yet".to_string(),
+    };
+    format!(
+        "{}, {} of {} chunks cached",
+        rate, cache.size, cache.capacity
+    )
+}
+
+/// Largest whole unit of `secs`: "45s", "12m", "3h" or "2d".
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// Longest diff `--git-diff` attaches; the rest is cut off.
+const MAX_GIT_DIFF_BYTES: usize = 64 * 1024;
+
+/// Output of `git diff` for `diff` in the current directory, cut to
+/// `MAX_GIT_DIFF_BYTES`. Errors when git fails or there are no changes.
+fn collect_git_diff(diff: &GitDiff) -> Result<String, String> {
+    let mut cmd = process::Command::new("git");
+    cmd.args(["diff", "--no-color", "--no-ext-diff"]);
+    match diff {
+        GitDiff::Staged => cmd.arg("--cached"),
+        GitDiff::Range(range) => cmd.arg(range),
+    };
+    let output = cmd
+        .output()
+        .map_err(|e| format!("failed to run git diff: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    if text.trim().is_empty() {
+        return Err(match diff {
+            GitDiff::Staged => {
+                "no staged changes to attach (stage them or pass --git-diff=RANGE)".to_string()
+            }
+            GitDiff::Range(range) => format!("no changes in {}", range),
+        });
+    }
+    if text.len() > MAX_GIT_DIFF_BYTES {
+        let mut end = MAX_GIT_DIFF_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str("\n[diff truncated]\n");
+    }
+    Ok(text)
+}
+
+fn run(cli_options: CliOptions) {
+    let log_path = query_log_path(cli_options.config_path.clone());
+    let cfg = match load_runtime_config(cli_options.config_path) {
+        Ok(c) => c,
+        Err(message) => {
+            eprintln!("{message}");
+            process::exit(1);
+        }
+    };
+
+    // Ride out a server restart instead of failing on the first refused connect.
+    let builder = match ClientBuilder::from_config(&cfg) {
+        Ok(b) => b.retry(RetryPolicy::transient()),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+    let index = cli_options
+        .index
+        .as_deref()
+        .or(cfg.server.index_name.as_deref());
+
+    let question = read_question(cli_options.question);
+
+    if question.is_empty() {
+        eprintln!("Error: no question provided (pass QUESTION argument or stdin)");
+        process::exit(1);
+    }
+    let attachment = cli_options.git_diff.as_ref().map(|diff| {
+        collect_git_diff(diff).unwrap_or_else(|message| {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        })
+    });
+
+    // Run the async query on a tokio runtime.
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("Error: failed to create runtime: {}", e);
+            process::exit(1);
+        });
+
+    let hooks = Hooks::from_config(&cfg.hooks);
+    rt.block_on(async {
+        let question = hooks.pre_query(&question).await.unwrap_or_else(|e| {
+            hook_failed(&hooks, e);
+            question.clone()
+        });
+        let client = match builder.connect().await {
+            Ok(c) => c,
+            Err(e) => fail(e),
+        };
+
+        let msg = QueryMessage::new(&question, index)
+            .with_prefer(cli_options.prefer)
+            .with_verify(cli_options.verify)
+            .with_options(cli_options.query_options.clone())
+            .with_filters(cli_options.filters.clone())
+            .with_attachment(attachment);
+        let stream = client.query_message_stream(&msg);
+        futures_util::pin_mut!(stream);
+
+        let answer_file = cli_options.edit.then(|| {
+            create_answer_file(&question).unwrap_or_else(|e| {
+                eprintln!("Error: failed to create answer file: {}", e);
+                process::exit(1);
+            })
+        });
+        let mut out: Box<dyn Write + '_> = match &answer_file {
+            Some((_, file)) => Box::new(file),
+            None => Box::new(Console::stdout()),
+        };
+
+        // The finished answer, for follow-up suggestions.
+        let mut answer = String::new();
+        let mut answer_sources = Vec::new();
+        // Presents the sources as the GUI does.
+        let mut collector = StreamCollector::with_retrieval(&cfg.retrieval);
+        let mut thinking =
+            ThinkingPrinter::new(answer_file.is_none() && io::stdout().is_terminal());
+        let mut progress = ProgressLine::new(io::stderr().is_terminal());
+
+        // Ctrl-C tells the server to stop generating before exiting.
+        let interrupted = tokio::signal::ctrl_c();
+        futures_util::pin_mut!(interrupted);
+
+        // Print chunks as they arrive so long answers render progressively.
+        loop {
+            let event = tokio::select! {
+                event = stream.next() => event,
+                _ = &mut interrupted => {
+                    let _ = client.cancel(msg.id).await;
+                    progress.clear(&mut io::stderr());
+                    let _ = writeln!(out);
+                    eprintln!("Interrupted");
+                    process::exit(130);
+                }
+            };
+            let event = match event {
+                Some(Ok(ev)) => ev,
+                Some(Err(e)) => fail(e),
+                None => break,
+            };
+            collector.push(&event);
+            if !matches!(event, StreamEvent::Thinking(_)) {
+                thinking.end(&mut out);
+            }
+            if !matches!(event, StreamEvent::Progress { .. }) {
+                progress.clear(&mut io::stderr());
+            }
+            match event {
+                // Messages from a newer server are skipped.
+                StreamEvent::StreamStart
+                | StreamEvent::IndexProgress { .. }
+                | StreamEvent::Unknown { .. } => {}
+                StreamEvent::Progress { status, message } => {
+                    progress.show(&mut io::stderr(), &status, message.as_deref());
+                }
+                StreamEvent::Thinking(text) => {
+                    if cli_options.thinking {
+                        thinking.print(&mut out, &text);
+                    }
+                }
+                StreamEvent::StreamChunk(chunk, meta) => {
+                    // Reasoning and preamble text stays out of the printed answer.
+                    if meta.is_answer() {
+                        let _ = write!(out, "{}", chunk);
+                        let _ = out.flush();
+                        answer.push_str(&chunk);
+                    }
+                }
+                StreamEvent::StreamEnd { sources, stats, .. } => {
+                    let log = log_path.as_deref();
+                    log_query(
+                        log,
+                        &question,
+                        index,
+                        Some(&answer),
+                        &sources,
+                        None,
+                        stats.usage.as_ref(),
+                    );
+                    print_sources(&mut out, &collector, cli_options.verify);
+                    if cli_options.query_options.candidates.is_some_and(|n| n > 1) {
+                        print_candidates(&mut out, &collector.candidates);
+                    }
+                    if cli_options.stats {
+                        print_stats(&mut out, &stats);
+                    }
+                    answer_sources = sources;
+                }
+                StreamEvent::Response {
+                    answer: full,
+                    sources,
+                } => {
+                    log_query(
+                        log_path.as_deref(),
+                        &question,
+                        index,
+                        Some(&full),
+                        &sources,
+                        None,
+                        None,
+                    );
+                    let _ = write!(out, "{}", full);
+                    print_sources(&mut out, &collector, cli_options.verify);
+                    if cli_options.stats {
+                        print_stats(&mut out, &QueryStats::default());
+                    }
+                    answer = full;
+                    answer_sources = sources;
+                }
+                StreamEvent::Error(msg) => {
+                    let log = log_path.as_deref();
+                    log_query(log, &question, index, None, &[], Some(&msg.message), None);
+                    fail(ClientError::ServerError(msg))
+                }
+            }
+        }
+        if let Err(e) = hooks.post_answer(&question, &answer, &answer_sources).await {
+            hook_failed(&hooks, e);
+        }
+        if cli_options.followups {
+            let suggestions = client
+                .suggest_followups(&question, &answer, &answer_sources, FOLLOWUP_COUNT)
+                .await;
+            print_followups(&mut out, suggestions);
+        }
+        let _ = client.close(1000, "").await;
+        drop(out);
+        if let Some((path, _)) = &answer_file {
+            println!("{}", path.display());
+            open_editor(path);
+        }
+    });
+}
+
+/// New temporary Markdown file for `--edit`, headed with the question.
+fn create_answer_file(question: &str) -> io::Result<(PathBuf, std::fs::File)> {
+    let path = std::env::temp_dir().join(format!(
+        "md-qa-answer-{}-{}.md",
+        querylog::now(),
+        process::id()
+    ));
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    writeln!(file, "# {}\n", question)?;
+    Ok((path, file))
+}
+
+/// Open `path` in `$VISUAL` or `$EDITOR` (which may include arguments, e.g.
+/// `code --wait`) and wait for it to exit. Does nothing when neither is set.
+fn open_editor(path: &std::path::Path) {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty());
+    let Some(editor) = editor else {
+        return;
+    };
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    if let Err(e) = process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+    {
+        eprintln!("Error: failed to start editor {}: {}", editor, e);
+    }
+}
+
+/// Finish an answer: newline after the text, then sources (grouped and
+/// limited as `collector` was configured) and the grounding result.
+fn print_sources(out: &mut impl Write, collector: &StreamCollector, verify: bool) {
+    let _ = writeln!(out);
+    if !collector.citations.is_empty() {
+        // Numbered like the answer's citation markers
+        let _ = writeln!(out, "\nReferences:");
+        for (number, src) in &collector.citations {
+            let _ = writeln!(out, "  [{}] {}", number, source_label(src));
+        }
+    }
+    if !collector.sources.is_empty() {
+        let groups: Vec<String> = collector
+            .source_groups
+            .iter()
+            .map(|group| group.to_string())
+            .collect();
+        if groups.is_empty() {
+            let _ = writeln!(out, "\nSources:");
+        } else {
+            let _ = writeln!(out, "\nSources: {}", groups.join(", "));
+        }
+        for src in &collector.sources {
+            let _ = writeln!(out, "  {}", source_label(src));
+        }
+        if collector.omitted_sources > 0 {
+            let _ = writeln!(out, "  and {} more", collector.omitted_sources);
+        }
+    }
+    if let Some(g) = &collector.grounding {
+        let _ = writeln!(out, "\nGrounding: {:.0}% supported", g.score * 100.0);
+        for sentence in &g.unsupported {
+            let _ = writeln!(out, "  unsupported: {}", sentence);
+        }
+    } else if verify {
+        let _ = writeln!(out, "\nGrounding: not reported by server");
+    }
+}
+
+/// `path:lines (heading)`, or without the heading when not reported.
+fn source_label(src: &Source) -> String {
+    match &src.heading {
+        Some(heading) => format!("{} ({})", src.location(), heading),
+        None => src.location(),
+    }
+}
+
+/// The alternative answers asked for with `--candidates`, numbered from 2
+/// (the first is the answer above), or a note that the server sent none.
+fn print_candidates(out: &mut impl Write, candidates: &[String]) {
+    let mut printed = false;
+    for (i, text) in candidates.iter().enumerate().skip(1) {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "\nCandidate {}:\n{}", i + 1, text.trim_end());
+        printed = true;
+    }
+    if !printed {
+        let _ = writeln!(out, "\nCandidates: not sent by server");
+    }
+}
+
+/// Reasoning printed by `--thinking`: dimmed when `dim` (stdout is a
+/// terminal), and set apart from the answer by a blank line.
+struct ThinkingPrinter {
+    dim: bool,
+    /// Whether reasoning was printed since the last other output.
+    open: bool,
+}
+
+impl ThinkingPrinter {
+    fn new(dim: bool) -> Self {
+        Self { dim, open: false }
+    }
+
+    fn print(&mut self, out: &mut impl Write, text: &str) {
+        if !self.open && self.dim {
+            let _ = write!(out, "\x1b[2m");
+        }
+        self.open = true;
+        let _ = write!(out, "{}", text);
+        let _ = out.flush();
+    }
+
+    /// Close printed reasoning before the answer (or anything else) follows.
+    fn end(&mut self, out: &mut impl Write) {
+        if !std::mem::take(&mut self.open) {
+            return;
+        }
+        if self.dim {
+            let _ = write!(out, "\x1b[0m");
+        }
+        let _ = writeln!(out, "\n");
+    }
+}
+
+/// What the server is doing before the answer starts (e.g. searching the
+/// documents), on one stderr line that is erased once anything else
+/// arrives, so a slow search doesn't look like a hang. Only shown when
+/// `enabled` (stderr is a terminal).
+struct ProgressLine {
+    enabled: bool,
+    shown: bool,
+}
+
+impl ProgressLine {
+    fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            shown: false,
+        }
+    }
 
-fn run(cli_options: CliOptions) {
-    let cfg = match load_runtime_config(cli_options.config_path) {
-        Ok(c) => c,
-        Err(message) => {
-            eprintln!("{message}");
-            process::exit(1);
+    fn show(&mut self, err: &mut impl Write, status: &str, message: Option<&str>) {
+        if !self.enabled {
+            return;
         }
-    };
+        let text = message.map_or_else(|| format!("{}...", status), str::to_string);
+        let _ = write!(err, "\r\x1b[2K{}", text);
+        let _ = err.flush();
+        self.shown = true;
+    }
 
-    let port = cfg.server.port.unwrap_or(8765);
-    let server_url = format!("ws://127.0.0.1:{}", port);
-    let index = cfg.server.index_name.as_deref();
+    fn clear(&mut self, err: &mut impl Write) {
+        if std::mem::take(&mut self.shown) {
+            let _ = write!(err, "\r\x1b[2K");
+            let _ = err.flush();
+        }
+    }
+}
 
-    let question = read_question(cli_options.question);
+/// Token usage and server time for `--stats`, with whichever parts the
+/// server reported.
+fn print_stats(out: &mut impl Write, stats: &QueryStats) {
+    let mut parts = Vec::new();
+    if let Some(usage) = &stats.usage {
+        let mut tokens = format!(
+            "{} prompt + {} completion tokens",
+            usage.prompt_tokens, usage.completion_tokens
+        );
+        if let Some(model) = &usage.model {
+            tokens.push_str(&format!(" ({})", model));
+        }
+        parts.push(tokens);
+    }
+    if let Some(ms) = stats.elapsed_ms {
+        parts.push(format!("{:.2} s", ms as f64 / 1000.0));
+    }
+    if parts.is_empty() {
+        let _ = writeln!(out, "\nStats: not reported by server");
+    } else {
+        let _ = writeln!(out, "\nStats: {}", parts.join(", "));
+    }
+}
 
-    if question.is_empty() {
-        eprintln!("Error: no question provided (pass QUESTION argument or stdin)");
-        process::exit(1);
+fn links(config_path: Option<PathBuf>, path: &str) {
+    let (rt, builder) = request_setup(config_path);
+    let links = rt.block_on(async {
+        let client = builder.connect().await.unwrap_or_else(|e| fail(e));
+        let links = client
+            .document_links(path)
+            .await
+            .unwrap_or_else(|e| fail(e));
+        let _ = client.close(1000, "").await;
+        links
+    });
+    for (title, paths) in [
+        ("Links to", &links.outbound),
+        ("Linked from", &links.inbound),
+    ] {
+        println!("{} ({}):", title, paths.len());
+        for path in paths {
+            println!("  {}", path);
+        }
     }
+}
 
-    // Run the async query on a tokio runtime.
-    let rt = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()
-        .unwrap_or_else(|e| {
-            eprintln!("Error: failed to create runtime: {}", e);
-            process::exit(1);
-        });
+/// Follow-up questions requested with `--followups`.
+const FOLLOWUP_COUNT: usize = 3;
 
-    rt.block_on(async {
-        let client = match md_qa_client::connect(&server_url).await {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("Error: connection failed: {}", e);
-                process::exit(1);
+/// List suggested follow-up questions, or note that the server has none.
+fn print_followups(out: &mut impl Write, suggestions: Result<Vec<String>, ClientError>) {
+    match suggestions {
+        Ok(questions) if !questions.is_empty() => {
+            let _ = writeln!(out, "\nFollow-up questions:");
+            for (i, question) in questions.iter().enumerate() {
+                let _ = writeln!(out, "  {}. {}", i + 1, question);
             }
-        };
+        }
+        Ok(_) => {}
+        Err(e) => {
+            let _ = writeln!(out, "\nFollow-up questions: not available ({})", e);
+        }
+    }
+}
 
-        let events = match client.query(&question, index).await {
-            Ok(ev) => ev,
-            Err(e) => {
-                eprintln!("Error: query failed: {}", e);
-                process::exit(1);
-            }
+/// Record the outcome of a query for `report gaps` and `history cost`. Logging is best-effort.
+fn log_query(
+    log_path: Option<&std::path::Path>,
+    question: &str,
+    index: Option<&str>,
+    answer: Option<&str>,
+    sources: &[Source],
+    error: Option<&str>,
+    usage: Option<&Usage>,
+) {
+    if let Some(path) = log_path {
+        let entry = LogEntry::Query {
+            timestamp: querylog::now(),
+            question: question.to_string(),
+            index: index.map(str::to_string),
+            sources: sources.len(),
+            error: error.map(str::to_string),
+            usage: usage.cloned(),
+            answer: answer.map(str::to_string),
+            source_paths: sources.iter().map(|s| s.path.clone()).collect(),
         };
-
-        let stdout = io::stdout();
-        let mut out = stdout.lock();
-
-        for event in &events {
-            match event {
-                StreamEvent::StreamStart => {}
-                StreamEvent::StreamChunk(chunk) => {
-                    let _ = write!(out, "{}", chunk);
-                    let _ = out.flush();
-                }
-                StreamEvent::StreamEnd(sources) => {
-                    // Newline after the answer text.
-                    let _ = writeln!(out);
-                    if !sources.is_empty() {
-                        let _ = writeln!(out, "\nSources:");
-                        for src in sources {
-                            let _ = writeln!(out, "  {}", src);
-                        }
-                    }
-                }
-                StreamEvent::Error(msg) => {
-                    eprintln!("Server error: {}", msg);
-                    process::exit(1);
-                }
-            }
-        }
-    });
+        let _ = querylog::append(path, &entry);
+    }
 }
 
 fn read_question(positional_question: Option<String>) -> String {
@@ -267,7 +2005,22 @@ fn read_question(positional_question: Option<String>) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{load_runtime_config_from_paths, parse_cli_command_from, CliCommand};
+    use super::{
+        exit_code, format_age, format_chunk_cache, format_index_estimate, format_size,
+        index_progress_text, load_runtime_config_from_paths, parse_cli_command_from,
+        print_candidates, print_index_plan, print_index_report, print_index_snapshot,
+        print_index_verify, print_indexes, print_sources, progress_bar, CliCommand, CliOptions,
+        GitDiff, ProgressLine, ThinkingPrinter,
+    };
+    use md_qa_client::messages::{
+        ChunkCacheStats, Citations, ErrorCode, ErrorMessage, FileEstimate, FileIssue,
+        IndexEstimate, IndexInfo, IndexPlanMessage, IndexProgressMessage, IndexSnapshotMessage,
+        IndexVerifyMessage, OrphanedFile, Prefer, QueryStats, Source,
+    };
+    use md_qa_client::{
+        config::ChunkSettings, ClientError, ExportFormat, Price, PriceTable, StreamCollector,
+        StreamEvent,
+    };
     use std::fs;
     use std::path::PathBuf;
 
@@ -369,6 +2122,784 @@ mod tests {
         assert!(err.contains("unexpected positional argument"));
     }
 
+    #[test]
+    fn prefer_flag_is_parsed() {
+        let parsed = parse_cli_command_from(["md-qa", "--prefer", "code", "what does foo() do?"])
+            .expect("parse should succeed");
+        match parsed {
+            CliCommand::Run(options) => {
+                assert_eq!(options.prefer, Some(Prefer::Code));
+                assert_eq!(options.question.as_deref(), Some("what does foo() do?"));
+            }
+            other => panic!("expected Run command, got {other:?}"),
+        }
+
+        let parsed =
+            parse_cli_command_from(["md-qa", "--prefer=prose"]).expect("parse should succeed");
+        assert!(matches!(
+            parsed,
+            CliCommand::Run(CliOptions {
+                prefer: Some(Prefer::Prose),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn verify_flag_is_parsed() {
+        let parsed =
+            parse_cli_command_from(["md-qa", "--verify", "hello"]).expect("parse should succeed");
+        match parsed {
+            CliCommand::Run(options) => {
+                assert!(options.verify);
+                assert_eq!(options.question.as_deref(), Some("hello"));
+            }
+            other => panic!("expected Run command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn index_age_uses_largest_whole_unit() {
+        assert_eq!(format_age(42), "42s");
+        assert_eq!(format_age(125), "2m");
+        assert_eq!(format_age(3 * 3600 + 59), "3h");
+        assert_eq!(format_age(2 * 86400), "2d");
+    }
+
+    #[test]
+    fn config_new_collects_template_and_vars() {
+        let parsed = parse_cli_command_from([
+            "md-qa",
+            "config",
+            "new",
+            "--template",
+            "team.yaml.tmpl",
+            "--var",
+            "repo=/src/app",
+            "--var=key=a=b",
+        ])
+        .expect("parse should succeed");
+        match parsed {
+            CliCommand::NewConfig {
+                template,
+                vars,
+                force,
+                ..
+            } => {
+                assert_eq!(template, PathBuf::from("team.yaml.tmpl"));
+                assert_eq!(
+                    vars,
+                    [
+                        ("repo".to_string(), "/src/app".to_string()),
+                        ("key".to_string(), "a=b".to_string())
+                    ]
+                );
+                assert!(!force);
+            }
+            other => panic!("expected NewConfig command, got {other:?}"),
+        }
+
+        let err = parse_cli_command_from(["md-qa", "config", "new"]).expect_err("no template");
+        assert!(err.contains("requires --template"));
+        let err = parse_cli_command_from(["md-qa", "--var", "a=b", "hello"])
+            .expect_err("--var without config new");
+        assert!(err.contains("only apply to config new"));
+    }
+
+    #[test]
+    fn edit_flag_is_parsed() {
+        let parsed =
+            parse_cli_command_from(["md-qa", "--edit", "hello"]).expect("parse should succeed");
+        assert!(matches!(
+            parsed,
+            CliCommand::Run(CliOptions { edit: true, .. })
+        ));
+    }
+
+    #[test]
+    fn links_subcommand_takes_a_document_path() {
+        let parsed =
+            parse_cli_command_from(["md-qa", "links", "docs/a.md"]).expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::Links {
+                config_path: None,
+                path: "docs/a.md".to_string(),
+            }
+        );
+        let err = parse_cli_command_from(["md-qa", "links"]).expect_err("path is required");
+        assert!(err.contains("links requires a document path"));
+    }
+
+    #[test]
+    fn followups_flag_is_parsed() {
+        let parsed = parse_cli_command_from(["md-qa", "--followups", "hello"])
+            .expect("parse should succeed");
+        assert!(matches!(
+            parsed,
+            CliCommand::Run(CliOptions {
+                followups: true,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn stats_flag_is_parsed() {
+        let parsed =
+            parse_cli_command_from(["md-qa", "--stats", "hello"]).expect("parse should succeed");
+        assert!(matches!(
+            parsed,
+            CliCommand::Run(CliOptions { stats: true, .. })
+        ));
+    }
+
+    #[test]
+    fn thinking_flag_is_parsed() {
+        let parsed =
+            parse_cli_command_from(["md-qa", "--thinking", "hello"]).expect("parse should succeed");
+        assert!(matches!(
+            parsed,
+            CliCommand::Run(CliOptions { thinking: true, .. })
+        ));
+    }
+
+    #[test]
+    fn thinking_is_dimmed_and_set_apart_from_the_answer() {
+        let print = |dim: bool| {
+            let mut out = Vec::new();
+            let mut thinking = ThinkingPrinter::new(dim);
+            thinking.end(&mut out);
+            thinking.print(&mut out, "Check the ");
+            thinking.print(&mut out, "config.");
+            thinking.end(&mut out);
+            thinking.end(&mut out);
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(print(false), "Check the config.\n\n");
+        assert_eq!(print(true), "\x1b[2mCheck the config.\x1b[0m\n\n");
+    }
+
+    #[test]
+    fn progress_line_is_replaced_and_erased() {
+        let mut err = Vec::new();
+        let mut progress = ProgressLine::new(true);
+        progress.clear(&mut err);
+        progress.show(&mut err, "retrieving", Some("Searching the documents"));
+        progress.show(&mut err, "reranking", None);
+        progress.clear(&mut err);
+        progress.clear(&mut err);
+        assert_eq!(
+            String::from_utf8(err).unwrap(),
+            "\r\x1b[2KSearching the documents\r\x1b[2Kreranking...\r\x1b[2K"
+        );
+
+        let mut err = Vec::new();
+        let mut progress = ProgressLine::new(false);
+        progress.show(&mut err, "retrieving", None);
+        progress.clear(&mut err);
+        assert!(err.is_empty());
+    }
+
+    #[test]
+    fn git_diff_flag_takes_an_optional_range() {
+        let git_diff = |args: &[&str]| match parse_cli_command_from(args.iter().copied()) {
+            Ok(CliCommand::Run(options)) => options.git_diff,
+            other => panic!("expected Run command, got {other:?}"),
+        };
+        assert_eq!(git_diff(&["md-qa", "hello"]), None);
+        assert_eq!(
+            git_diff(&["md-qa", "--git-diff", "hello"]),
+            Some(GitDiff::Staged)
+        );
+        assert_eq!(
+            git_diff(&["md-qa", "--git-diff=main...HEAD", "hello"]),
+            Some(GitDiff::Range("main...HEAD".to_string()))
+        );
+
+        let err =
+            parse_cli_command_from(["md-qa", "--git-diff=", "hello"]).expect_err("should fail");
+        assert!(err.contains("--git-diff requires a value"));
+    }
+
+    #[test]
+    fn query_option_flags_are_parsed() {
+        let parsed = parse_cli_command_from([
+            "md-qa",
+            "--top-k",
+            "8",
+            "--temperature=0.2",
+            "--max-tokens",
+            "300",
+            "--language",
+            "de",
+            "--model=gpt-4o-mini",
+            "--candidates",
+            "3",
+            "hello",
+        ])
+        .expect("parse should succeed");
+        match parsed {
+            CliCommand::Run(options) => {
+                assert_eq!(options.query_options.top_k, Some(8));
+                assert_eq!(options.query_options.temperature, Some(0.2));
+                assert_eq!(options.query_options.max_tokens, Some(300));
+                assert_eq!(options.query_options.language.as_deref(), Some("de"));
+                assert_eq!(options.query_options.model.as_deref(), Some("gpt-4o-mini"));
+                assert_eq!(options.query_options.candidates, Some(3));
+            }
+            other => panic!("expected Run command, got {other:?}"),
+        }
+
+        let err = parse_cli_command_from(["md-qa", "--top-k", "many"]).expect_err("should fail");
+        assert!(err.contains("invalid --top-k value"));
+        let err = parse_cli_command_from(["md-qa", "--temperature", "5"]).expect_err("should fail");
+        assert!(err.contains("invalid --temperature value"));
+        let err = parse_cli_command_from(["md-qa", "--candidates", "0"]).expect_err("should fail");
+        assert!(err.contains("invalid --candidates value"));
+    }
+
+    #[test]
+    fn candidates_are_numbered_after_the_answer() {
+        let mut out = Vec::new();
+        let candidates = ["Port 8765.", "", "It listens on 8765.\n"].map(String::from);
+        print_candidates(&mut out, &candidates);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\nCandidate 3:\nIt listens on 8765.\n"
+        );
+
+        let mut out = Vec::new();
+        print_candidates(&mut out, &[]);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\nCandidates: not sent by server\n"
+        );
+    }
+
+    #[test]
+    fn filter_flags_are_parsed() {
+        let parsed = parse_cli_command_from([
+            "md-qa",
+            "--filter",
+            "dir:docs/api",
+            "--filter=tag:security",
+            "--filter",
+            "glob:*.adr.md",
+            "--filter=dir:notes",
+            "hello",
+        ])
+        .expect("parse should succeed");
+        match parsed {
+            CliCommand::Run(options) => {
+                assert_eq!(options.filters.directories, ["docs/api", "notes"]);
+                assert_eq!(options.filters.tags, ["security"]);
+                assert_eq!(options.filters.globs, ["*.adr.md"]);
+            }
+            other => panic!("expected Run command, got {other:?}"),
+        }
+
+        let err =
+            parse_cli_command_from(["md-qa", "--filter", "docs/api"]).expect_err("should fail");
+        assert!(err.contains("invalid filter: docs/api"));
+        let err = parse_cli_command_from(["md-qa", "--filter", "tag:"]).expect_err("should fail");
+        assert!(err.contains("invalid filter"));
+    }
+
+    #[test]
+    fn invalid_prefer_value_returns_error() {
+        let err =
+            parse_cli_command_from(["md-qa", "--prefer", "tables"]).expect_err("parse should fail");
+        assert!(err.contains("invalid prefer value"));
+    }
+
+    #[test]
+    fn history_cost_subcommand_is_parsed() {
+        let parsed = parse_cli_command_from(["md-qa", "-c", "/tmp/c.yaml", "history", "cost"])
+            .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::HistoryCost {
+                config_path: Some(PathBuf::from("/tmp/c.yaml")),
+            }
+        );
+
+        let parsed = parse_cli_command_from(["md-qa", "history"]).expect("parse should succeed");
+        assert!(
+            matches!(parsed, CliCommand::Run(ref o) if o.question.as_deref() == Some("history"))
+        );
+
+        let err = parse_cli_command_from(["md-qa", "history", "nope"]).expect_err("should fail");
+        assert!(err.contains("unknown history command"));
+    }
+
+    #[test]
+    fn history_export_subcommand_is_parsed() {
+        let parsed = parse_cli_command_from(["md-qa", "history", "export", "--as=issue"])
+            .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::HistoryExport {
+                config_path: None,
+                format: ExportFormat::Issue,
+            }
+        );
+        let parsed =
+            parse_cli_command_from(["md-qa", "history", "export"]).expect("parse should succeed");
+        assert!(matches!(
+            parsed,
+            CliCommand::HistoryExport {
+                format: ExportFormat::Snippet,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn report_gaps_subcommand_is_parsed() {
+        let parsed = parse_cli_command_from(["md-qa", "-c", "/tmp/c.yaml", "report", "gaps"])
+            .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::ReportGaps {
+                config_path: Some(PathBuf::from("/tmp/c.yaml")),
+            }
+        );
+
+        let parsed = parse_cli_command_from(["md-qa", "report"]).expect("parse should succeed");
+        assert!(
+            matches!(parsed, CliCommand::Run(ref o) if o.question.as_deref() == Some("report"))
+        );
+
+        let err = parse_cli_command_from(["md-qa", "report", "nope"]).expect_err("should fail");
+        assert!(err.contains("unknown report"));
+    }
+
+    #[test]
+    fn status_subcommand_is_parsed() {
+        let parsed = parse_cli_command_from(["md-qa", "--config", "/tmp/c.yaml", "status"])
+            .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::Status {
+                config_path: Some(PathBuf::from("/tmp/c.yaml")),
+            }
+        );
+    }
+
+    #[test]
+    fn index_stats_subcommand_is_parsed() {
+        let parsed =
+            parse_cli_command_from(["md-qa", "index", "stats"]).expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexStats {
+                config_path: None,
+                index: None,
+            }
+        );
+        let parsed =
+            parse_cli_command_from(["md-qa", "index", "reload"]).expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexReload {
+                config_path: None,
+                index: None,
+                yes: false,
+            }
+        );
+        let parsed = parse_cli_command_from(["md-qa", "index", "reload", "--yes"])
+            .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexReload {
+                config_path: None,
+                index: None,
+                yes: true,
+            }
+        );
+        let error = parse_cli_command_from(["md-qa", "index", "plan", "-y"]).unwrap_err();
+        assert!(error.contains("--yes only applies"), "{error}");
+        let parsed =
+            parse_cli_command_from(["md-qa", "index", "plan"]).expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexPlan {
+                config_path: None,
+                index: None,
+            }
+        );
+        let parsed = parse_cli_command_from(["md-qa", "index", "verify", "--remove"])
+            .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexVerify {
+                config_path: None,
+                index: None,
+                remove: true,
+            }
+        );
+        let error = parse_cli_command_from(["md-qa", "index", "stats", "--remove"]).unwrap_err();
+        assert!(error.contains("--remove only applies"), "{error}");
+        let error = parse_cli_command_from(["md-qa", "index", "size"]).unwrap_err();
+        assert!(error.contains("unknown index command: size"), "{error}");
+    }
+
+    #[test]
+    fn index_snapshot_and_restore_take_a_file() {
+        let parsed =
+            parse_cli_command_from(["md-qa", "index", "snapshot", "--out", "docs.tar.zst"])
+                .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexSnapshot {
+                config_path: None,
+                index: None,
+                out: PathBuf::from("docs.tar.zst"),
+            }
+        );
+        let parsed = parse_cli_command_from(["md-qa", "index", "restore", "docs.tar.zst"])
+            .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexRestore {
+                config_path: None,
+                index: None,
+                file: PathBuf::from("docs.tar.zst"),
+            }
+        );
+        let error = parse_cli_command_from(["md-qa", "index", "snapshot"]).unwrap_err();
+        assert!(error.contains("requires --out"), "{error}");
+        let error = parse_cli_command_from(["md-qa", "index", "restore"]).unwrap_err();
+        assert!(error.contains("requires a snapshot file"), "{error}");
+        let error =
+            parse_cli_command_from(["md-qa", "index", "stats", "--out", "a.tar"]).unwrap_err();
+        assert!(error.contains("--out only applies"), "{error}");
+        let error = parse_cli_command_from(["md-qa", "index", "verify", "a.tar"]).unwrap_err();
+        assert!(
+            error.contains("unexpected positional argument: a.tar"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn named_indexes_are_created_deleted_and_picked_with_index() {
+        let parsed = parse_cli_command_from([
+            "md-qa",
+            "index",
+            "create",
+            "notes",
+            "--dir",
+            "notes",
+            "--dir=wiki",
+            "--embedding-model",
+            "local:bge-small",
+            "--target-tokens",
+            "300",
+        ])
+        .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexCreate {
+                config_path: None,
+                name: "notes".to_string(),
+                directories: vec![PathBuf::from("notes"), PathBuf::from("wiki")],
+                chunking: ChunkSettings {
+                    target_tokens: Some(300),
+                    ..Default::default()
+                },
+                embedding_model: Some("local:bge-small".to_string()),
+            }
+        );
+        let parsed = parse_cli_command_from(["md-qa", "index", "delete", "notes"])
+            .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexDelete {
+                config_path: None,
+                name: "notes".to_string(),
+            }
+        );
+        let parsed =
+            parse_cli_command_from(["md-qa", "index", "list"]).expect("parse should succeed");
+        assert_eq!(parsed, CliCommand::IndexList { config_path: None });
+        let parsed = parse_cli_command_from(["md-qa", "--index", "notes", "index", "stats"])
+            .expect("parse should succeed");
+        assert_eq!(
+            parsed,
+            CliCommand::IndexStats {
+                config_path: None,
+                index: Some("notes".to_string()),
+            }
+        );
+        let parsed = parse_cli_command_from(["md-qa", "--index=notes", "what is new?"])
+            .expect("parse should succeed");
+        assert!(matches!(
+            parsed,
+            CliCommand::Run(CliOptions { index: Some(ref name), .. }) if name == "notes"
+        ));
+
+        let error = parse_cli_command_from(["md-qa", "index", "create", "notes"]).unwrap_err();
+        assert!(error.contains("index create requires --dir"), "{error}");
+        let error =
+            parse_cli_command_from(["md-qa", "index", "create", "--dir", "notes"]).unwrap_err();
+        assert!(error.contains("requires an index name"), "{error}");
+        let error = parse_cli_command_from(["md-qa", "index", "delete"]).unwrap_err();
+        assert!(error.contains("requires an index name"), "{error}");
+        let error = parse_cli_command_from(["md-qa", "--dir", "notes", "hello"]).unwrap_err();
+        assert!(error.contains("only apply to index create"), "{error}");
+        let error = parse_cli_command_from([
+            "md-qa",
+            "index",
+            "create",
+            "a",
+            "--dir",
+            "a",
+            "--target-tokens",
+            "many",
+        ])
+        .unwrap_err();
+        assert!(error.contains("invalid --target-tokens value"), "{error}");
+        let error =
+            parse_cli_command_from(["md-qa", "--index", "notes", "index", "delete", "notes"])
+                .unwrap_err();
+        assert!(error.contains("--index only applies"), "{error}");
+        let error = parse_cli_command_from(["md-qa", "--index", "notes", "status"]).unwrap_err();
+        assert!(error.contains("--index only applies"), "{error}");
+    }
+
+    #[test]
+    fn indexes_are_listed_with_status_model_and_directories() {
+        let indexes = [
+            IndexInfo {
+                name: "docs".to_string(),
+                directories: vec!["/srv/docs".to_string()],
+                embedding_model: "text-embedding-3-small".to_string(),
+                default: true,
+                status: "ready".to_string(),
+            },
+            IndexInfo {
+                name: "notes".to_string(),
+                directories: vec!["/home/me/notes".to_string(), "/home/me/wiki".to_string()],
+                embedding_model: "local:bge-small".to_string(),
+                default: false,
+                status: "not_ready".to_string(),
+            },
+        ];
+        let mut out = Vec::new();
+        print_indexes(&mut out, &indexes);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "docs (default): ready, text-embedding-3-small\n  /srv/docs\n\
+             notes: not ready, local:bge-small\n  /home/me/notes\n  /home/me/wiki\n"
+        );
+    }
+
+    #[test]
+    fn index_progress_shows_documents_then_chunks() {
+        let mut progress = IndexProgressMessage {
+            done: 3,
+            total: 4,
+            path: Some("notes/a.md".to_string()),
+            error: None,
+            chunks: None,
+            embedded: None,
+            finished: false,
+            failed: Vec::new(),
+        };
+        assert_eq!(
+            index_progress_text(&progress),
+            "Reading [###############     ] 3/4 documents"
+        );
+        progress.chunks = Some(10);
+        progress.embedded = Some(10);
+        assert_eq!(
+            index_progress_text(&progress),
+            "Embedding [####################] 10/10 chunks"
+        );
+        assert_eq!(progress_bar(0, 0), format!("[{}]", "#".repeat(20)));
+    }
+
+    #[test]
+    fn index_plan_lists_indexed_then_ignored_files() {
+        let plan = IndexPlanMessage {
+            index: Some("docs".to_string()),
+            files: vec!["/docs/a.md".to_string(), "/docs/b.md".to_string()],
+            ignored: vec!["/docs/build/out.md".to_string()],
+            estimate: None,
+        };
+        let mut out = Vec::new();
+        print_index_plan(&mut out, &plan);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "/docs/a.md\n/docs/b.md\n\nIgnored:\n  /docs/build/out.md\n\n2 files would be indexed, 1 ignored\n"
+        );
+    }
+
+    #[test]
+    fn index_estimate_shows_tokens_cost_and_time() {
+        let mut estimate = IndexEstimate {
+            embedding_model: Some("text-embedding-3-small".to_string()),
+            full_rebuild: true,
+            files: vec![FileEstimate {
+                path: "/docs/a.md".to_string(),
+                chunks: 4000,
+                tokens: 2_000_000,
+            }],
+            chunks: 4000,
+            tokens: 2_000_000,
+            cached_chunks: 0,
+            seconds: 400.0,
+        };
+        let mut prices = PriceTable::new();
+        prices.insert(
+            "text-embedding-3-small".to_string(),
+            Price {
+                prompt: 0.02,
+                completion: 0.0,
+            },
+        );
+        assert_eq!(
+            format_index_estimate(&estimate, &prices),
+            "This reload rebuilds the index from 1 files: 4000 chunks to embed\n\
+             Tokens:         2000000 with text-embedding-3-small\n\
+             Estimated cost: 0.0400\n\
+             Estimated time: about 6m\n"
+        );
+
+        estimate.full_rebuild = false;
+        estimate.cached_chunks = 12;
+        estimate.embedding_model = Some("local-embedder".to_string());
+        let text = format_index_estimate(&estimate, &prices);
+        assert!(
+            text.starts_with(
+                "This reload reads 1 files: 4000 chunks to embed (12 more are cached)\n"
+            ),
+            "{text}"
+        );
+        assert!(text.contains("Estimated cost: unknown"), "{text}");
+    }
+
+    #[test]
+    fn index_snapshot_prints_the_archive_and_any_reindexing() {
+        let mut snapshot = IndexSnapshotMessage {
+            index: Some("docs".to_string()),
+            path: "/tmp/docs.tar.zst".to_string(),
+            chunks: 1200,
+            embedding_model: "text-embedding-3-small".to_string(),
+            size_bytes: 3 * 1024 * 1024,
+            reindexing: false,
+        };
+        let mut out = Vec::new();
+        print_index_snapshot(&mut out, &snapshot, false);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Wrote 1200 chunks (text-embedding-3-small) to /tmp/docs.tar.zst (3.0 MB)\n"
+        );
+        snapshot.reindexing = true;
+        let mut out = Vec::new();
+        print_index_snapshot(&mut out, &snapshot, true);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Restored 1200 chunks (text-embedding-3-small) from /tmp/docs.tar.zst (3.0 MB)\n\
+             Reindexing documents that changed since the snapshot (see `index reload`)\n"
+        );
+    }
+
+    #[test]
+    fn index_verify_lists_orphans_and_whether_they_were_removed() {
+        let mut report = IndexVerifyMessage {
+            index: Some("docs".to_string()),
+            orphans: vec![
+                OrphanedFile {
+                    path: "/docs/draft.md".to_string(),
+                    chunks: 1,
+                    reason: "excluded".to_string(),
+                },
+                OrphanedFile {
+                    path: "/docs/gone.md".to_string(),
+                    chunks: 2,
+                    reason: "missing".to_string(),
+                },
+            ],
+            removed: false,
+        };
+        let mut out = Vec::new();
+        print_index_verify(&mut out, &report);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "/docs/draft.md (excluded, 1 chunks)\n/docs/gone.md (missing, 2 chunks)\n\n\
+             3 orphaned chunks of 2 documents found (run with --remove to drop them)\n"
+        );
+        report.removed = true;
+        let mut out = Vec::new();
+        print_index_verify(&mut out, &report);
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .ends_with("3 orphaned chunks of 2 documents removed\n"));
+        report.orphans.clear();
+        let mut out = Vec::new();
+        print_index_verify(&mut out, &report);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Index is consistent: no orphaned chunks\n"
+        );
+    }
+
+    #[test]
+    fn index_report_lists_the_failed_documents() {
+        let report = IndexProgressMessage {
+            done: 4,
+            total: 4,
+            path: None,
+            error: None,
+            chunks: Some(12),
+            embedded: Some(12),
+            finished: true,
+            failed: vec![FileIssue {
+                path: "notes/big.md".to_string(),
+                issue: "skipped: file is too large".to_string(),
+            }],
+        };
+        let mut out = Vec::new();
+        print_index_report(&mut out, &report);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Indexed 3 of 4 documents (12 chunks)\nFailed: 1\n  notes/big.md: skipped: file is too large\n"
+        );
+    }
+
+    #[test]
+    fn chunk_cache_shows_its_hit_rate_and_fill() {
+        let mut cache = ChunkCacheStats {
+            size: 3,
+            capacity: 256,
+            hits: 2,
+            misses: 1,
+            hit_rate: Some(0.6667),
+        };
+        assert_eq!(
+            format_chunk_cache(&cache),
+            "67% hits (2 of 3 lookups), 3 of 256 chunks cached"
+        );
+        cache.hits = 0;
+        cache.misses = 0;
+        cache.hit_rate = None;
+        assert_eq!(
+            format_chunk_cache(&cache),
+            "no lookups yet, 3 of 256 chunks cached"
+        );
+    }
+
+    #[test]
+    fn sizes_are_shown_in_the_largest_whole_unit() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(12_900_000), "12.3 MB");
+        assert_eq!(format_size(3 << 30), "3.0 GB");
+        assert_eq!(format_size(5 << 40), "5120.0 GB");
+    }
+
     #[test]
     fn missing_default_config_uses_built_in_defaults() {
         let dir = tempfile::tempdir().expect("temp dir");
@@ -417,4 +2948,37 @@ mod tests {
         assert_eq!(cfg.server.port, Some(7777));
         assert_eq!(cfg.server.index_name.as_deref(), Some("from-env"));
     }
+
+    #[test]
+    fn index_not_ready_errors_exit_like_status() {
+        let not_ready = ErrorMessage::new("No index available").with_code(ErrorCode::IndexNotReady);
+        assert_eq!(exit_code(&ClientError::ServerError(not_ready)), 7);
+        let rate_limited = ErrorMessage::new("Slow down").with_code(ErrorCode::RateLimited);
+        assert_eq!(exit_code(&ClientError::ServerError(rate_limited)), 5);
+        assert_eq!(
+            exit_code(&ClientError::ServerError(ErrorMessage::new("bad"))),
+            5
+        );
+    }
+
+    #[test]
+    fn cited_sources_are_listed_by_number() {
+        let cited = Source {
+            heading: Some("Setup".into()),
+            ..Source::from("/b.md")
+        };
+        let mut collector = StreamCollector::new();
+        collector.push(&StreamEvent::StreamEnd {
+            sources: vec!["/a.md".into(), cited.clone()],
+            grounding: None,
+            citations: Citations::from([(2, cited), (1, "/a.md".into())]),
+            stats: QueryStats::default(),
+        });
+        let mut out = Vec::new();
+        print_sources(&mut out, &collector, false);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\n\nReferences:\n  [1] /a.md\n  [2] /b.md (Setup)\n\nSources:\n  /a.md\n  /b.md (Setup)\n"
+        );
+    }
 }
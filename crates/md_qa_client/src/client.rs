@@ -1,116 +1,2579 @@
 //! WebSocket client: connect, send query, receive stream (STREAM_START, STREAM_CHUNK, STREAM_END).
 
-use futures_util::{SinkExt, StreamExt};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, Stream, StreamExt};
 use std::collections::HashSet;
-use std::sync::Arc;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::{mpsc, oneshot, watch, OwnedMutexGuard};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::error::CapacityError;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
 use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::Connector;
 use tokio_tungstenite::MaybeTlsStream;
 use tokio_tungstenite::WebSocketStream;
 
-use crate::messages::{QueryMessage, ServerMessage};
+use crate::config::{ChunkingSection, ClientSection, Config, ConfigError, RetrievalSection};
+use crate::history::Turn;
+use crate::messages::{
+    CancelMessage, CapabilitiesMessage, ChunksMessage, Citations, CreateIndexMessage,
+    DeleteIndexMessage, DocumentsMessage, ErrorCode, ErrorMessage, GetChunksMessage,
+    GetIndexPlanMessage, GetIndexStatsMessage, GetIndexVerifyMessage, GetLinksMessage, Grounding,
+    IndexInfo, IndexPlanMessage, IndexProgressMessage, IndexSnapshotMessage, IndexStatsMessage,
+    IndexVerifyMessage, LinksMessage, ListDocumentsMessage, LogLine, NotificationMessage,
+    QueryMessage, QueryStats, ReloadMessage, RestoreIndexMessage, ServerFrame, ServerMessage,
+    SnapshotIndexMessage, Source, StatusMessage, SuggestFollowupsMessage, TailLogsMessage,
+};
+use crate::proxy::Proxy;
+use crate::wire::WireEncoding;
 
 /// Events received during a query stream (see docs/protocol.md).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum StreamEvent {
     StreamStart,
-    StreamChunk(String),
-    StreamEnd(Vec<String>),
-    Error(String),
+    /// A piece of text; see `ChunkMeta::is_answer` for whether it belongs
+    /// to the answer.
+    StreamChunk(String, ChunkMeta),
+    /// A piece of the model's reasoning, streamed before the answer by
+    /// backends that have a thinking phase. Doesn't end the query.
+    Thinking(String),
+    /// End of the answer: deduplicated sources, plus the grounding check
+    /// result when the query asked for verification, the sources behind
+    /// inline citation markers, and whatever token usage and timing the
+    /// server reports.
+    StreamEnd {
+        sources: Vec<Source>,
+        grounding: Option<Grounding>,
+        citations: Citations,
+        stats: QueryStats,
+    },
+    /// Whole answer from a server that replies with a single `response`
+    /// message instead of a stream; ends the query like `StreamEnd`.
+    Response {
+        answer: String,
+        sources: Vec<Source>,
+    },
+    /// What the server is doing before the answer starts, e.g. `status`
+    /// `"retrieving"` during a slow vector search. Doesn't end the query.
+    Progress {
+        status: String,
+        message: Option<String>,
+    },
+    /// The server is (re)building an index: `done` of `total` documents,
+    /// currently `path`. Doesn't end the query.
+    IndexProgress {
+        done: u64,
+        total: u64,
+        path: Option<String>,
+    },
+    /// A message of a `type` this client doesn't know (`payload` is the
+    /// whole message). Doesn't end the query.
+    Unknown {
+        typ: String,
+        payload: serde_json::Value,
+    },
+    Error(ErrorMessage),
+}
+
+impl StreamEvent {
+    /// Answer text this event carries: an answer chunk, or the whole
+    /// non-streamed RESPONSE.
+    pub fn answer_text(&self) -> Option<&str> {
+        match self {
+            StreamEvent::StreamChunk(chunk, meta) if meta.is_answer() => Some(chunk),
+            StreamEvent::Response { answer, .. } => Some(answer),
+            _ => None,
+        }
+    }
+}
+
+/// Optional details of a stream chunk, for servers that mark which text is
+/// reasoning or preamble, why generation stopped and which candidate answer
+/// the text belongs to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkMeta {
+    /// What the text is, e.g. `"answer"`, `"reasoning"` or `"preamble"`.
+    pub role: Option<String>,
+    /// Why generation stopped (e.g. `"stop"`, `"length"`), on the chunk
+    /// that ended it.
+    pub finish_reason: Option<String>,
+    /// Index of the candidate answer the text belongs to, when the query
+    /// asked for several (`QueryOptions::candidates`); 0 is the answer.
+    pub candidate: Option<u32>,
+}
+
+impl ChunkMeta {
+    /// Whether the chunk is part of the answer: answer text (see
+    /// `is_answer_text`) of the first candidate.
+    pub fn is_answer(&self) -> bool {
+        self.is_answer_text() && self.candidate.unwrap_or(0) == 0
+    }
+
+    /// Whether the chunk is answer text of any candidate: no role, or
+    /// `"answer"` / `"assistant"`.
+    pub fn is_answer_text(&self) -> bool {
+        matches!(self.role.as_deref(), None | Some("answer" | "assistant"))
+    }
+}
+
+/// Which of the two answers of `Client::compare_stream` an event belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Which way a protocol frame went, for `ClientBuilder::frame_tap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameDirection {
+    Sent,
+    Received,
+}
+
+/// Callback shown the text of every protocol message sent or received, e.g.
+/// to record a session. Called on the connection's reader and writer tasks,
+/// so it should return quickly.
+#[derive(Clone)]
+pub struct FrameTap(Arc<TapFn>);
+
+type TapFn = dyn Fn(FrameDirection, &str) + Send + Sync;
+
+impl FrameTap {
+    pub fn new(tap: impl Fn(FrameDirection, &str) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(tap))
+    }
+}
+
+impl std::fmt::Debug for FrameTap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FrameTap")
+    }
+}
+
+/// Connection lifecycle changes, broadcast by `Client::connection_events`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// A retried query re-established the connection.
+    Connected,
+    /// The connection was closed or failed.
+    Disconnected { reason: String },
+    /// A retried query is about to reconnect; `attempt` counts from 1.
+    Reconnecting { attempt: u32 },
+    /// The warm-up retrieval sent on connect (`ClientBuilder::warmup`)
+    /// finished after `elapsed`.
+    WarmedUp { elapsed: Duration },
+    /// The warm-up retrieval failed; the connection is still usable.
+    WarmupFailed { reason: String },
+}
+
+/// Lifecycle events buffered per subscriber before the oldest are dropped.
+const CONNECTION_EVENT_CAPACITY: usize = 16;
+
+/// Followed server log lines buffered per subscriber before the oldest are
+/// dropped.
+const LOG_LINE_CAPACITY: usize = 256;
+
+/// Index progress messages buffered per subscriber before the oldest are
+/// dropped.
+const INDEX_PROGRESS_CAPACITY: usize = 64;
+
+type WsStream = WebSocketStream<MaybeTlsStream<Transport>>;
+
+/// Byte stream under the WebSocket: TCP (direct or through a proxy), or a
+/// Unix domain socket for `unix://` URLs.
+enum Transport {
+    Tcp(tokio::net::TcpStream),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+}
+
+impl Transport {
+    #[cfg(unix)]
+    async fn unix(path: &str) -> std::io::Result<Self> {
+        Ok(Transport::Unix(
+            tokio::net::UnixStream::connect(path).await?,
+        ))
+    }
+
+    #[cfg(not(unix))]
+    async fn unix(_path: &str) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "unix:// URLs are only supported on Unix",
+        ))
+    }
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
 }
 
-type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+impl AsyncWrite for Transport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Transport::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(s) => Pin::new(s).poll_flush(cx),
+            #[cfg(unix)]
+            Transport::Unix(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(s) => Pin::new(s).poll_shutdown(cx),
+            #[cfg(unix)]
+            Transport::Unix(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
 
-fn deduplicate_sources(sources: Vec<String>) -> Vec<String> {
+/// Drop repeated sources, keeping the first (best-ranked) entry for each
+/// path; entries starting at different lines of one file are all kept.
+fn deduplicate_sources(sources: Vec<Source>) -> Vec<Source> {
     let mut seen = HashSet::new();
     let mut unique = Vec::new();
     for source in sources {
-        if seen.insert(source.clone()) {
+        if seen.insert((source.path.clone(), source.start_line)) {
             unique.push(source);
         }
     }
     unique
 }
 
+/// Timeouts and size limits applied by `connect_with_options` and the query
+/// methods. `None` disables the corresponding limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientOptions {
+    /// Limit on the TCP connect plus WebSocket handshake.
+    pub connect_timeout: Option<Duration>,
+    /// Limit between sending a query and receiving its first event.
+    pub first_event_timeout: Option<Duration>,
+    /// Limit on a whole query, from sending it until STREAM_END or ERROR.
+    pub stream_timeout: Option<Duration>,
+    /// Largest incoming frame or message, in bytes. A bigger one ends the
+    /// query with a `StreamEvent::Error` and closes the connection, since
+    /// the rest of it is still unread on the socket.
+    pub max_message_size: Option<usize>,
+    /// Most events `query` / `query_message` buffer for one answer. Past the
+    /// limit the rest of the answer is read and discarded, and the buffer
+    /// ends with a `StreamEvent::Error`.
+    pub max_buffered_events: Option<usize>,
+    /// Retries for transient connect and query failures (none by default).
+    pub retry: RetryPolicy,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Some(Duration::from_secs(10)),
+            first_event_timeout: Some(Duration::from_secs(30)),
+            stream_timeout: None,
+            max_message_size: Some(16 << 20),
+            max_buffered_events: Some(100_000),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// Retries for transient failures such as a server restart. `connect`
+/// retries the handshake; a query reconnects and is sent again only when the
+/// connection failed before its first event, so no answer is seen twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first. 1 disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each one after it.
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts.
+    pub max_backoff: Duration,
+    /// Retry connects that were refused, reset, timed out, or answered with
+    /// an HTTP 5xx by a proxy.
+    pub connect: bool,
+    /// Retry queries whose connection closed or failed before the first event.
+    pub connection_lost: bool,
+    /// Retry queries that hit `ClientOptions::first_event_timeout`.
+    pub first_event_timeout: bool,
+    /// Resend queries the server turned away with a transient `ErrorCode`
+    /// (index not ready, rate limited), on the same connection.
+    pub server_busy: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+            connect: true,
+            connection_lost: true,
+            first_event_timeout: false,
+            server_busy: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Four attempts over about two seconds: enough to ride out a server restart.
+    pub fn transient() -> Self {
+        Self {
+            max_attempts: 4,
+            server_busy: true,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry number `retry` (1 for the first retry).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Whether this policy retries after `error`.
+    pub fn is_retryable(&self, error: &ClientError) -> bool {
+        use tokio_tungstenite::tungstenite::Error;
+        match error {
+            ClientError::Connect(e) => {
+                self.connect
+                    && match e.as_ref() {
+                        Error::Io(_) => true,
+                        Error::Http(response) => response.status().is_server_error(),
+                        _ => false,
+                    }
+            }
+            ClientError::Timeout(TimeoutKind::Connect) => self.connect,
+            ClientError::Io(_) | ClientError::Closed => self.connection_lost,
+            ClientError::Timeout(TimeoutKind::FirstEvent) => self.first_event_timeout,
+            ClientError::ServerError(error) => self.retries_server_error(error),
+            _ => false,
+        }
+    }
+
+    /// Whether this policy resends a query the server answered with `error`.
+    pub fn retries_server_error(&self, error: &ErrorMessage) -> bool {
+        self.server_busy && error.is_transient()
+    }
+}
+
+/// Which `ClientOptions` limit expired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    Connect,
+    FirstEvent,
+    Stream,
+    /// `Client::ping` got no pong in time.
+    Ping,
+}
+
+impl std::fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeoutKind::Connect => write!(f, "connecting to the server"),
+            TimeoutKind::FirstEvent => write!(f, "waiting for the first answer event"),
+            TimeoutKind::Stream => write!(f, "waiting for the answer to finish"),
+            TimeoutKind::Ping => write!(f, "waiting for the server to answer a ping"),
+        }
+    }
+}
+
+/// Health snapshot of the server: its `status` reply plus how long the
+/// request took. Made by `Client::check_status` and by the background poll
+/// of `ClientBuilder::status_poll`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ServerSummary {
+    /// `"ready"`, `"indexing"`, or `"not_ready"`.
+    pub status: String,
+    pub ready: bool,
+    pub message: Option<String>,
+    /// Number of indexed documents, if the server reports it.
+    pub documents: Option<u64>,
+    /// Seconds since the index was built, if the server reports `indexed_at`.
+    pub index_age_secs: Option<u64>,
+    /// Round trip of the status request, in milliseconds.
+    pub latency_ms: u64,
+    /// Unix time of the check, in seconds.
+    pub checked_at: u64,
+}
+
+impl ServerSummary {
+    fn new(status: StatusMessage, latency: Duration) -> Self {
+        let checked_at = crate::querylog::now();
+        Self {
+            ready: status.is_ready(),
+            index_age_secs: status.indexed_at.map(|at| checked_at.saturating_sub(at)),
+            status: status.status,
+            message: status.message,
+            documents: status.documents,
+            latency_ms: latency.as_millis() as u64,
+            checked_at,
+        }
+    }
+}
+
+/// How long `Client::close` waits for the server to answer the Close frame.
+const CLOSE_REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// Connected WebSocket client.
 pub struct Client {
-    inner: Arc<tokio::sync::Mutex<WsStream>>,
+    link: Arc<Link>,
+    /// Settings the client was opened with, for options and reconnects.
+    builder: Arc<ClientBuilder>,
+    shared: Shared,
+}
+
+/// What the reader task picks up from frames that aren't replies (pongs and
+/// server notifications), and the connection state that it, queries and the
+/// keepalive task report lifecycle events for.
+struct SharedState {
+    last_pong: std::sync::Mutex<Option<std::time::Instant>>,
+    notifications: std::sync::Mutex<Vec<NotificationMessage>>,
+    index_progress: std::sync::Mutex<Option<IndexProgressMessage>>,
+    index_progress_events: tokio::sync::broadcast::Sender<IndexProgressMessage>,
+    connected: std::sync::atomic::AtomicBool,
+    /// Why the connection was lost, while it is down.
+    disconnect_reason: std::sync::Mutex<Option<String>>,
+    events: tokio::sync::broadcast::Sender<ConnectionEvent>,
+    log_lines: tokio::sync::broadcast::Sender<LogLine>,
+    summary: std::sync::Mutex<Option<ServerSummary>>,
+    capabilities: std::sync::Mutex<Option<CapabilitiesMessage>>,
+    frame_tap: Option<FrameTap>,
+}
+
+impl Default for SharedState {
+    fn default() -> Self {
+        Self {
+            last_pong: Default::default(),
+            notifications: Default::default(),
+            index_progress: Default::default(),
+            index_progress_events: tokio::sync::broadcast::channel(INDEX_PROGRESS_CAPACITY).0,
+            connected: std::sync::atomic::AtomicBool::new(true),
+            disconnect_reason: Default::default(),
+            events: tokio::sync::broadcast::channel(CONNECTION_EVENT_CAPACITY).0,
+            log_lines: tokio::sync::broadcast::channel(LOG_LINE_CAPACITY).0,
+            summary: Default::default(),
+            capabilities: Default::default(),
+            frame_tap: None,
+        }
+    }
+}
+
+type Shared = Arc<SharedState>;
+
+fn record_summary(shared: &Shared, summary: ServerSummary) {
+    if let Ok(mut latest) = shared.summary.lock() {
+        *latest = Some(summary);
+    }
+}
+
+fn send_event(shared: &Shared, event: ConnectionEvent) {
+    // No subscribers is fine.
+    let _ = shared.events.send(event);
+}
+
+fn record_connected(shared: &Shared) {
+    if !shared
+        .connected
+        .swap(true, std::sync::atomic::Ordering::SeqCst)
+    {
+        if let Ok(mut why) = shared.disconnect_reason.lock() {
+            *why = None;
+        }
+        send_event(shared, ConnectionEvent::Connected);
+    }
+}
+
+fn record_disconnected(shared: &Shared, reason: impl std::fmt::Display) {
+    if shared
+        .connected
+        .swap(false, std::sync::atomic::Ordering::SeqCst)
+    {
+        let reason = reason.to_string();
+        if let Ok(mut why) = shared.disconnect_reason.lock() {
+            *why = Some(reason.clone());
+        }
+        send_event(shared, ConnectionEvent::Disconnected { reason });
+    }
+}
+
+/// Report `error` as a disconnect if it means the connection is gone.
+fn record_error(shared: &Shared, error: &ClientError) {
+    if error.is_connection_lost() {
+        record_disconnected(shared, error);
+    }
+}
+
+fn record_frame(shared: &Shared, direction: FrameDirection, text: &str) {
+    if let Some(FrameTap(tap)) = &shared.frame_tap {
+        tap(direction, text);
+    }
+}
+
+fn record_pong(shared: &Shared) {
+    if let Ok(mut at) = shared.last_pong.lock() {
+        *at = Some(std::time::Instant::now());
+    }
+}
+
+/// Notifications kept until `Client::take_notifications`; older ones are
+/// dropped, so a server pushing them to an idle client can't use up memory.
+const MAX_PENDING_NOTIFICATIONS: usize = 256;
+
+fn record_notification(shared: &Shared, notification: NotificationMessage) {
+    if let Ok(mut pending) = shared.notifications.lock() {
+        if pending.len() == MAX_PENDING_NOTIFICATIONS {
+            pending.remove(0);
+        }
+        pending.push(notification);
+    }
+}
+
+fn record_log_line(shared: &Shared, line: String) {
+    // No subscribers is fine.
+    let _ = shared.log_lines.send(LogLine::new(line));
+}
+
+fn record_index_progress(shared: &Shared, progress: &IndexProgressMessage) {
+    if let Ok(mut latest) = shared.index_progress.lock() {
+        *latest = Some(progress.clone());
+    }
+    // No subscribers is fine.
+    let _ = shared.index_progress_events.send(progress.clone());
+}
+
+/// A frame for the writer task, and where to report whether it was written.
+type Outgoing = (Message, oneshot::Sender<Result<(), ClientError>>);
+
+/// Handle on the writer task of one connection. Pings and the Close frame go
+/// out through it without waiting for the query in flight.
+#[derive(Clone)]
+struct Writer {
+    frames: mpsc::Sender<Outgoing>,
+    /// Marked changed by the reader task on every pong.
+    pongs: watch::Receiver<()>,
+    encoding: SharedEncoding,
+}
+
+/// Encoding of one connection's frames: JSON until the server picks the
+/// encoding offered in a `capabilities` request (see `ClientBuilder::encoding`).
+#[derive(Default)]
+struct FrameEncoding {
+    current: WireEncoding,
+    /// Offered in the `capabilities` request awaiting its reply.
+    offered: Option<WireEncoding>,
+}
+
+type SharedEncoding = Arc<std::sync::Mutex<FrameEncoding>>;
+
+fn lock_encoding(encoding: &SharedEncoding) -> std::sync::MutexGuard<'_, FrameEncoding> {
+    encoding
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn current_encoding(encoding: &SharedEncoding) -> WireEncoding {
+    lock_encoding(encoding).current
+}
+
+impl Writer {
+    /// Queue `message` and wait until it is written to the socket.
+    async fn send(&self, message: Message) -> Result<(), ClientError> {
+        let (sent, written) = oneshot::channel();
+        self.frames
+            .send((message, sent))
+            .await
+            .map_err(|_| ClientError::Closed)?;
+        written.await.unwrap_or(Err(ClientError::Closed))
+    }
+}
+
+/// What the reader task hands to the query or request in flight.
+enum Incoming {
+    /// A server message, with the id of the query it answers if echoed.
+    Message(ServerMessage, Option<u64>),
+    /// A message over `ClientOptions::max_message_size`; the reader closed
+    /// the connection, since the rest of it is still unread on the socket.
+    TooLarge(CapacityError),
+    /// A frame that is not a protocol message, or the end of the connection.
+    Failed(ClientError),
+}
+
+/// Server messages read ahead of the query or request in flight; the reader
+/// task stops reading the socket while this many wait.
+const REPLY_BUFFER: usize = 256;
+
+/// Frames queued for the writer task before senders wait.
+const FRAME_BUFFER: usize = 32;
+
+/// One open WebSocket, split into a reader task and a writer task. Only
+/// query replies carry request ids, so whoever holds this (behind
+/// `Link::connection`) owns the replies: one query or request at a time.
+struct Connection {
+    writer: Writer,
+    replies: mpsc::Receiver<Incoming>,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+impl Connection {
+    fn start(ws: WsStream, shared: &Shared) -> Self {
+        let (sink, stream) = ws.split();
+        let (frames, outgoing) = mpsc::channel(FRAME_BUFFER);
+        let (replies_tx, replies) = mpsc::channel(REPLY_BUFFER);
+        let (pongs_tx, pongs) = watch::channel(());
+        let encoding = SharedEncoding::default();
+        tokio::spawn(write_frames(
+            sink,
+            outgoing,
+            encoding.clone(),
+            shared.clone(),
+        ));
+        let reader = tokio::spawn(read_frames(
+            stream,
+            frames.clone(),
+            replies_tx,
+            pongs_tx,
+            encoding.clone(),
+            shared.clone(),
+        ));
+        Self {
+            writer: Writer {
+                frames,
+                pongs,
+                encoding,
+            },
+            replies,
+            reader,
+        }
+    }
+
+    /// Drop replies that arrived between requests (e.g. the rest of an
+    /// answer that timed out), so the next request reads only its own.
+    fn discard_stale_replies(&mut self) {
+        while self.replies.try_recv().is_ok() {}
+    }
+
+    /// The next reply; `ClientError::Closed` once the reader task has ended.
+    async fn next_reply(&mut self) -> Incoming {
+        self.replies
+            .recv()
+            .await
+            .unwrap_or(Incoming::Failed(ClientError::Closed))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// The client's current connection; a retried query swaps in a new one.
+struct Link {
+    connection: Arc<tokio::sync::Mutex<Connection>>,
+    /// Writer of the current connection, reachable while a query holds
+    /// `connection`.
+    writer: std::sync::Mutex<Writer>,
+}
+
+impl Link {
+    fn new(connection: Connection) -> Self {
+        Self {
+            writer: std::sync::Mutex::new(connection.writer.clone()),
+            connection: Arc::new(tokio::sync::Mutex::new(connection)),
+        }
+    }
+
+    fn writer(&self) -> Writer {
+        self.writer
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Switch to a freshly opened WebSocket, once no query is using the old one.
+    async fn replace(&self, ws: WsStream, shared: &Shared) {
+        let connection = Connection::start(ws, shared);
+        let mut current = self.connection.lock().await;
+        *self
+            .writer
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = connection.writer.clone();
+        *current = connection;
+    }
+}
+
+/// Writer task of a connection: writes queued frames, as binary ones once
+/// `encoding` is, until every `Writer` is dropped.
+async fn write_frames(
+    mut sink: SplitSink<WsStream, Message>,
+    mut frames: mpsc::Receiver<Outgoing>,
+    encoding: SharedEncoding,
+    shared: Shared,
+) {
+    while let Some((message, sent)) = frames.recv().await {
+        let message = match message {
+            Message::Text(text) => {
+                record_frame(&shared, FrameDirection::Sent, &text);
+                match encode_frame(current_encoding(&encoding), text) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        let _ = sent.send(Err(e));
+                        continue;
+                    }
+                }
+            }
+            message => message,
+        };
+        let _ = sent.send(sink.send(message).await.map_err(ClientError::from));
+    }
+}
+
+/// The frame carrying JSON message `text` in `encoding`.
+fn encode_frame(encoding: WireEncoding, text: String) -> Result<Message, ClientError> {
+    if !encoding.is_binary() {
+        return Ok(Message::Text(text));
+    }
+    let value: serde_json::Value = serde_json::from_str(&text).map_err(ClientError::from)?;
+    let bytes = encoding
+        .encode(&value)
+        .map_err(|e| ClientError::Protocol(e.into()))?;
+    Ok(Message::Binary(bytes))
+}
+
+/// Read a binary frame in `encoding`, showing it to the frame tap as JSON.
+fn decode_frame(
+    shared: &Shared,
+    encoding: WireEncoding,
+    bytes: &[u8],
+) -> Result<ServerFrame, ClientError> {
+    let value = encoding
+        .decode(bytes)
+        .map_err(|e| ClientError::Protocol(format!("invalid {} frame: {}", encoding, e).into()))?;
+    if shared.frame_tap.is_some() {
+        record_frame(shared, FrameDirection::Received, &value.to_string());
+    }
+    ServerFrame::from_value(value).map_err(|e| ClientError::Protocol(Box::new(e)))
+}
+
+/// Reader task of a connection: records pongs, notifications, followed log
+/// lines and index progress as they arrive, hands every other server message (progress too)
+/// to the query or request in flight, and reports the end of the connection.
+/// Progress is only handed over while there is room: the server sends it to
+/// every connection, also idle ones nobody reads replies of, and waiting for
+/// room would stop pongs and notifications from being read.
+async fn read_frames(
+    mut stream: SplitStream<WsStream>,
+    frames: mpsc::Sender<Outgoing>,
+    replies: mpsc::Sender<Incoming>,
+    pongs: watch::Sender<()>,
+    encoding: SharedEncoding,
+    shared: Shared,
+) {
+    use tokio_tungstenite::tungstenite::Error;
+    loop {
+        let next = stream.next().await;
+        let frame = match &next {
+            Some(Ok(Message::Text(text))) => {
+                record_frame(&shared, FrameDirection::Received, text);
+                Some(ServerFrame::parse(text).map_err(|e| ClientError::Protocol(Box::new(e))))
+            }
+            Some(Ok(Message::Binary(bytes))) if current_encoding(&encoding).is_binary() => {
+                Some(decode_frame(&shared, current_encoding(&encoding), bytes))
+            }
+            _ => None,
+        };
+        let mut droppable = false;
+        let incoming = match (frame, next) {
+            (Some(Ok(frame)), _) => match frame {
+                ServerFrame {
+                    message: ServerMessage::Notification(notification),
+                    ..
+                } => {
+                    record_notification(&shared, notification);
+                    continue;
+                }
+                ServerFrame {
+                    message: ServerMessage::Log(log),
+                    ..
+                } => {
+                    record_log_line(&shared, log.line);
+                    continue;
+                }
+                ServerFrame { id, message } => {
+                    match &message {
+                        ServerMessage::IndexProgress(progress) => {
+                            record_index_progress(&shared, progress);
+                            droppable = true;
+                        }
+                        // The server's frames after this reply, and ours,
+                        // are in the encoding it picked, if it is the one
+                        // offered; an unasked-for switch is ignored.
+                        ServerMessage::Capabilities(capabilities) => {
+                            let mut encoding = lock_encoding(&encoding);
+                            let offered = encoding.offered.take();
+                            if let Some(picked) =
+                                capabilities.encoding.filter(|e| Some(*e) == offered)
+                            {
+                                encoding.current = picked;
+                            }
+                        }
+                        _ => {}
+                    }
+                    Incoming::Message(message, id)
+                }
+            },
+            (Some(Err(e)), _) => Incoming::Failed(e),
+            (None, Some(Ok(Message::Pong(_)))) => {
+                record_pong(&shared);
+                pongs.send_replace(());
+                continue;
+            }
+            (None, Some(Ok(Message::Close(_))) | None) => {
+                record_disconnected(&shared, ClientError::Closed);
+                let _ = replies.send(Incoming::Failed(ClientError::Closed)).await;
+                return;
+            }
+            (None, Some(Ok(_))) => continue,
+            (None, Some(Err(Error::Capacity(e)))) => {
+                // The oversized message is still on the socket, so the
+                // connection can't be reused.
+                let _ = frames.try_send((Message::Close(None), oneshot::channel().0));
+                record_disconnected(&shared, "server message too large");
+                let _ = replies.send(Incoming::TooLarge(e)).await;
+                return;
+            }
+            (None, Some(Err(e))) => {
+                let e = ClientError::from(e);
+                record_disconnected(&shared, &e);
+                let _ = replies.send(Incoming::Failed(e)).await;
+                return;
+            }
+        };
+        if droppable {
+            // Already recorded; a full queue means no one is reading it.
+            if let Err(mpsc::error::TrySendError::Closed(_)) = replies.try_send(incoming) {
+                return;
+            }
+            continue;
+        }
+        // Waits while the query in flight is behind, so a server sending
+        // faster than it is read is held up by TCP, not buffered here.
+        if replies.send(incoming).await.is_err() {
+            return;
+        }
+    }
 }
 
-/// Client connection error.
+/// Client error, categorised so callers can map failures to exit codes or
+/// connection states without parsing messages.
 #[derive(Debug)]
-pub struct ClientError(pub String);
+pub enum ClientError {
+    /// Could not reach the server or complete the WebSocket handshake
+    /// (DNS failure, connection refused, bad URL, TLS error).
+    Connect(Box<tokio_tungstenite::tungstenite::Error>),
+    /// Transport failure on an established connection.
+    Io(std::io::Error),
+    /// The server sent something that does not follow docs/protocol.md.
+    Protocol(Box<dyn std::error::Error + Send + Sync>),
+    /// The server answered with an ERROR message.
+    ServerError(ErrorMessage),
+    /// A `ClientOptions` timeout expired. After a query timeout the server
+    /// may still send the rest of that answer, so reconnect before reusing.
+    Timeout(TimeoutKind),
+    /// The connection closed before the answer finished.
+    Closed,
+}
+
+impl ClientError {
+    /// True when the connection can no longer be used and should be dropped.
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(
+            self,
+            ClientError::Connect(_)
+                | ClientError::Io(_)
+                | ClientError::Timeout(_)
+                | ClientError::Closed
+        )
+    }
+}
 
 impl std::fmt::Display for ClientError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            ClientError::Connect(e) => write!(f, "could not connect: {}", e),
+            ClientError::Io(e) => write!(f, "connection error: {}", e),
+            ClientError::Protocol(e) => write!(f, "protocol error: {}", e),
+            ClientError::ServerError(message) => write!(f, "server error: {}", message),
+            ClientError::Timeout(kind) => write!(f, "timed out {}", kind),
+            ClientError::Closed => write!(f, "connection closed by server"),
+        }
     }
 }
 
-impl std::error::Error for ClientError {}
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Connect(e) => Some(e.as_ref()),
+            ClientError::Io(e) => Some(e),
+            ClientError::Protocol(e) => Some(e.as_ref()),
+            ClientError::ServerError(_) | ClientError::Timeout(_) | ClientError::Closed => None,
+        }
+    }
+}
 
 impl From<tokio_tungstenite::tungstenite::Error> for ClientError {
     fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
-        ClientError(e.to_string())
+        use tokio_tungstenite::tungstenite::error::ProtocolError;
+        use tokio_tungstenite::tungstenite::Error;
+        match e {
+            Error::ConnectionClosed
+            | Error::AlreadyClosed
+            | Error::Protocol(ProtocolError::SendAfterClosing)
+            | Error::Protocol(ProtocolError::ResetWithoutClosingHandshake) => ClientError::Closed,
+            Error::Io(e) => ClientError::Io(e),
+            e => ClientError::Protocol(Box::new(e)),
+        }
     }
 }
 
 impl From<serde_json::Error> for ClientError {
     fn from(e: serde_json::Error) -> Self {
-        ClientError(e.to_string())
+        ClientError::Protocol(Box::new(e))
     }
 }
 
 impl From<String> for ClientError {
     fn from(s: String) -> Self {
-        ClientError(s)
+        ClientError::Protocol(s.into())
     }
 }
 
-/// Connect to the WebSocket server at `url` (e.g. `ws://localhost:8765`).
+/// Connect to the WebSocket server at `url` (e.g. `ws://localhost:8765`)
+/// with the default `ClientOptions`. Use `ClientBuilder` for anything more.
 pub async fn connect(url: &str) -> Result<Client, ClientError> {
-    let (ws_stream, _) = tokio_tungstenite::connect_async(url).await?;
-    Ok(Client {
-        inner: Arc::new(tokio::sync::Mutex::new(ws_stream)),
-    })
+    ClientBuilder::new(url).connect().await
+}
+
+/// Connect to the WebSocket server at `url` with explicit timeouts.
+pub async fn connect_with_options(
+    url: &str,
+    options: ClientOptions,
+) -> Result<Client, ClientError> {
+    ClientBuilder::new(url).options(options).connect().await
+}
+
+/// Connection settings beyond a bare URL (`ws://`, `wss://`, or `unix://` +
+/// socket path on Unix): extra handshake headers, TLS,
+/// proxy, timeouts, message size limit and keepalive.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    url: String,
+    headers: Vec<(String, String)>,
+    options: ClientOptions,
+    root_certificates: Vec<Vec<u8>>,
+    accept_invalid_certs: bool,
+    keepalive: Option<Duration>,
+    status_poll: Option<Duration>,
+    warmup: bool,
+    capabilities: bool,
+    encoding: WireEncoding,
+    frame_tap: Option<FrameTap>,
+    proxy: Option<Proxy>,
+    env_proxy: bool,
+    system_proxy: bool,
+}
+
+impl ClientBuilder {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            headers: Vec::new(),
+            options: ClientOptions::default(),
+            root_certificates: Vec::new(),
+            accept_invalid_certs: false,
+            keepalive: None,
+            status_poll: None,
+            warmup: false,
+            capabilities: false,
+            encoding: WireEncoding::Json,
+            frame_tap: None,
+            proxy: None,
+            env_proxy: true,
+            system_proxy: false,
+        }
+    }
+
+    /// Add a header to the WebSocket handshake request (e.g. `Authorization`).
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Connect and query timeouts.
+    pub fn options(mut self, options: ClientOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Builder for the server in `config`: its `server_url()` plus the TLS
+    /// settings of the `client` section.
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        Self::new(&config.server_url()).client_config(&config.client)
+    }
+
+    /// Apply the TLS and proxy settings of a config `client` section, reading
+    /// `ca_cert` from disk.
+    pub fn client_config(mut self, section: &ClientSection) -> Result<Self, ConfigError> {
+        if let Some(url) = &section.proxy {
+            self = self.proxy(Proxy::parse(url).map_err(ConfigError::Io)?);
+        }
+        if let Some(enabled) = section.system_proxy {
+            self = self.system_proxy(enabled);
+        }
+        if let Some(path) = &section.ca_cert {
+            let pem = std::fs::read(path)
+                .map_err(|e| ConfigError::Io(format!("failed to read ca_cert {}: {}", path, e)))?;
+            self = self.add_root_certificate(&pem);
+        }
+        Ok(self.danger_accept_invalid_certs(section.accept_invalid_certs))
+    }
+
+    /// Trust the PEM-encoded certificate(s) in `pem` (one certificate or a
+    /// bundle) as extra roots for `wss://` URLs, e.g. a private CA or a
+    /// server's self-signed certificate.
+    pub fn add_root_certificate(mut self, pem: &[u8]) -> Self {
+        self.root_certificates.push(pem.to_vec());
+        self
+    }
+
+    /// Skip certificate and hostname checks for `wss://` URLs. Only for
+    /// servers with self-signed certificates on a trusted network.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Retry transient connect and query failures (sets `ClientOptions::retry`).
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.options.retry = policy;
+        self
+    }
+
+    /// Largest incoming frame or message accepted, in bytes (sets
+    /// `ClientOptions::max_message_size`).
+    pub fn max_message_size(mut self, bytes: usize) -> Self {
+        self.options.max_message_size = Some(bytes);
+        self
+    }
+
+    /// Send a WebSocket ping every `interval` while no query is running, so
+    /// idle connections behind NAT or proxies stay open. Pongs are tracked
+    /// in `Client::last_pong`; a ping still unanswered at the next tick marks
+    /// the connection as lost (`ConnectionEvent::Disconnected`).
+    pub fn keepalive(mut self, interval: Duration) -> Self {
+        self.keepalive = Some(interval);
+        self
+    }
+
+    /// Check the server's `status` every `interval` (the first check one
+    /// interval after connecting, so it never holds up the first question)
+    /// while no query is running; the latest result is kept in
+    /// `Client::server_summary`. A check the server doesn't answer within a
+    /// few seconds is dropped without marking the connection lost. Use
+    /// `Client::check_status` for a reading right away.
+    pub fn status_poll(mut self, interval: Duration) -> Self {
+        self.status_poll = Some(interval);
+        self
+    }
+
+    /// Send a warm-up retrieval (`Client::warmup`) right after connecting,
+    /// so the first question doesn't pay for cold caches. Its timing is
+    /// reported as `ConnectionEvent::WarmedUp` (or `WarmupFailed`); a query
+    /// asked meanwhile waits for it.
+    pub fn warmup(mut self, enabled: bool) -> Self {
+        self.warmup = enabled;
+        self
+    }
+
+    /// Ask the server which optional features it supports before `connect`
+    /// returns, and keep the answer for `Client::capabilities`, so front ends
+    /// can hide what the server can't do. A server that doesn't answer within
+    /// a few seconds keeps its connection; its capabilities stay unknown.
+    pub fn capabilities(mut self, enabled: bool) -> Self {
+        self.capabilities = enabled;
+        self
+    }
+
+    /// Offer the server a binary `encoding` in a `capabilities` request
+    /// while connecting, to save parsing time on answers streamed in many
+    /// small chunks. Only encodings built in (see `WireEncoding::is_available`)
+    /// are offered; servers that don't support it keep JSON, and so does a
+    /// connection reopened by a retried query.
+    pub fn encoding(mut self, encoding: WireEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Show every protocol message sent or received to `tap`, across
+    /// reconnects.
+    pub fn frame_tap(mut self, tap: FrameTap) -> Self {
+        self.frame_tap = Some(tap);
+        self
+    }
+
+    /// Tunnel the connection through `proxy` (HTTP CONNECT or SOCKS5),
+    /// whatever the target host.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Whether to pick up a proxy from `HTTPS_PROXY` / `HTTP_PROXY` /
+    /// `ALL_PROXY` when none is set explicitly (default: true). See `Proxy::from_env`.
+    pub fn env_proxy(mut self, enabled: bool) -> Self {
+        self.env_proxy = enabled;
+        self
+    }
+
+    /// Whether to fall back to the operating system's proxy settings when no
+    /// proxy is set explicitly or in the environment (default: false). See
+    /// `Proxy::from_system`.
+    pub fn system_proxy(mut self, enabled: bool) -> Self {
+        self.system_proxy = enabled;
+        self
+    }
+
+    /// Open the connection, retrying as `ClientOptions::retry` allows.
+    pub async fn connect(self) -> Result<Client, ClientError> {
+        let retry = self.options.retry;
+        let mut attempt = 1;
+        let ws_stream = loop {
+            match self.open().await {
+                Ok(stream) => break stream,
+                Err(e) if attempt < retry.max_attempts && retry.is_retryable(&e) => {
+                    tokio::time::sleep(retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        let shared = Arc::new(SharedState {
+            frame_tap: self.frame_tap.clone(),
+            ..SharedState::default()
+        });
+        let link = Arc::new(Link::new(Connection::start(ws_stream, &shared)));
+        let offer = Some(self.encoding).filter(|e| e.is_binary() && e.is_available());
+        if self.capabilities || offer.is_some() {
+            let mut guard = link.connection.lock().await;
+            let limit = self
+                .options
+                .first_event_timeout
+                .map_or(CAPABILITIES_TIMEOUT, |t| t.min(CAPABILITIES_TIMEOUT));
+            // Servers that predate the request answer with an error, and busy
+            // ones may not answer in time; their capabilities stay unknown.
+            match ask_capabilities(&mut guard, &shared, &self.options, offer, Some(limit)).await {
+                Err(e) if e.is_connection_lost() && !matches!(e, ClientError::Timeout(_)) => {
+                    return Err(e)
+                }
+                _ => {}
+            }
+        }
+        if let Some(interval) = self.keepalive {
+            tokio::spawn(keepalive(Arc::downgrade(&link), shared.clone(), interval));
+        }
+        if let Some(interval) = self.status_poll {
+            let weak = Arc::downgrade(&link);
+            tokio::spawn(status_poll(weak, shared.clone(), interval));
+        }
+        if self.warmup {
+            let (link, shared, options) = (link.clone(), shared.clone(), self.options);
+            tokio::spawn(async move {
+                let mut guard = link.connection.lock().await;
+                let event = match warm_up(&mut guard, &shared, &options).await {
+                    Ok(elapsed) => ConnectionEvent::WarmedUp { elapsed },
+                    Err(e) => ConnectionEvent::WarmupFailed {
+                        reason: e.to_string(),
+                    },
+                };
+                send_event(&shared, event);
+            });
+        }
+        Ok(Client {
+            link,
+            builder: Arc::new(self),
+            shared,
+        })
+    }
+
+    /// One connect and handshake attempt.
+    async fn open(&self) -> Result<WsStream, ClientError> {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+        use tokio_tungstenite::tungstenite::Error;
+
+        let connect_error = |e: Error| ClientError::Connect(Box::new(e));
+        // `unix:///path/to.sock`: handshake as ws://localhost over the socket.
+        let unix_path = self.url.strip_prefix("unix://");
+        let mut request = unix_path
+            .map_or(self.url.as_str(), |_| "ws://localhost/")
+            .into_client_request()
+            .map_err(connect_error)?;
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| connect_error(Error::HttpFormat(e.into())))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| connect_error(Error::HttpFormat(e.into())))?;
+            request.headers_mut().append(name, value);
+        }
+
+        let config = self.options.max_message_size.map(|bytes| WebSocketConfig {
+            max_message_size: Some(bytes),
+            max_frame_size: Some(bytes),
+            ..WebSocketConfig::default()
+        });
+        let connector = if self.accept_invalid_certs || !self.root_certificates.is_empty() {
+            let tls_error = |e: native_tls::Error| connect_error(Error::Tls(e.into()));
+            let mut tls = native_tls::TlsConnector::builder();
+            for pem in self
+                .root_certificates
+                .iter()
+                .flat_map(|b| split_pem_bundle(b))
+            {
+                tls.add_root_certificate(
+                    native_tls::Certificate::from_pem(pem).map_err(tls_error)?,
+                );
+            }
+            if self.accept_invalid_certs {
+                tls.danger_accept_invalid_certs(true)
+                    .danger_accept_invalid_hostnames(true);
+            }
+            Some(Connector::NativeTls(tls.build().map_err(tls_error)?))
+        } else {
+            None
+        };
+
+        let uri = request.uri();
+        let scheme = uri.scheme_str().unwrap_or("ws").to_string();
+        let host = uri.host().unwrap_or_default().to_string();
+        let port = uri
+            .port_u16()
+            .unwrap_or(if scheme == "wss" { 443 } else { 80 });
+        let proxy = match &self.proxy {
+            _ if unix_path.is_some() => None,
+            Some(proxy) => Some(proxy.clone()),
+            None if self.env_proxy => Proxy::from_env(&scheme, &host)
+                .map_err(|e| connect_error(Error::Io(std::io::Error::other(e))))?,
+            None => None,
+        };
+        let proxy = match proxy {
+            None if self.system_proxy && unix_path.is_none() => {
+                Proxy::from_system(&scheme, &host).await
+            }
+            proxy => proxy,
+        };
+        let handshake = async {
+            let stream = match (unix_path, proxy) {
+                (Some(path), _) => Transport::unix(path).await?,
+                (None, Some(proxy)) => Transport::Tcp(proxy.connect(&host, port).await?),
+                (None, None) => {
+                    let addr = (host.trim_matches(['[', ']']), port);
+                    Transport::Tcp(tokio::net::TcpStream::connect(addr).await?)
+                }
+            };
+            tokio_tungstenite::client_async_tls_with_config(request, stream, config, connector)
+                .await
+        };
+        let result = match self.options.connect_timeout {
+            Some(limit) => tokio::time::timeout(limit, handshake)
+                .await
+                .map_err(|_| ClientError::Timeout(TimeoutKind::Connect))?,
+            None => handshake.await,
+        };
+        let (ws_stream, _) = result.map_err(connect_error)?;
+        Ok(ws_stream)
+    }
+}
+
+/// Split a PEM bundle into single certificates (native-tls parses one at a
+/// time). Input without an END marker is returned whole so parsing reports it.
+fn split_pem_bundle(pem: &[u8]) -> Vec<&[u8]> {
+    const END: &[u8] = b"-----END CERTIFICATE-----";
+    let mut certs = Vec::new();
+    let mut rest = pem;
+    while let Some(pos) = rest.windows(END.len()).position(|w| w == END) {
+        certs.push(&rest[..pos + END.len()]);
+        rest = &rest[pos + END.len()..];
+    }
+    if certs.is_empty() {
+        certs.push(pem);
+    }
+    certs
+}
+
+/// Ping the server every `interval` until the client is dropped; the reader
+/// task records the pongs. Skips a tick while a query runs (the server may
+/// not read the ping before the answer is done), and idles while the
+/// connection is down so pings resume if a retried query reconnects. A ping
+/// sent on the previous tick and still unanswered means the server stopped
+/// responding.
+async fn keepalive(link: Weak<Link>, shared: Shared, interval: Duration) {
+    let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+    let mut unanswered: Option<std::time::Instant> = None;
+    loop {
+        ticker.tick().await;
+        let Some(link) = link.upgrade() else {
+            return;
+        };
+        let Ok(_idle) = link.connection.try_lock() else {
+            unanswered = None;
+            continue;
+        };
+        if !shared.connected.load(std::sync::atomic::Ordering::SeqCst) {
+            unanswered = None;
+            continue;
+        }
+        if let Some(sent) = unanswered {
+            let last_pong = shared.last_pong.lock().ok().and_then(|at| *at);
+            if last_pong.is_none_or(|at| at < sent) {
+                record_disconnected(
+                    &shared,
+                    format!("no pong from the server for {:?}", sent.elapsed()),
+                );
+                unanswered = None;
+                continue;
+            }
+        }
+        unanswered = Some(std::time::Instant::now());
+        let _ = link.writer().send(Message::Ping(Vec::new())).await;
+    }
+}
+
+/// Check the server's status every `interval` until the client is dropped,
+/// skipping ticks while a query holds the socket or the connection is down.
+async fn status_poll(link: Weak<Link>, shared: Shared, interval: Duration) {
+    let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+    let limit = STATUS_POLL_TIMEOUT.min(interval);
+    loop {
+        ticker.tick().await;
+        let Some(link) = link.upgrade() else {
+            return;
+        };
+        if !shared.connected.load(std::sync::atomic::Ordering::SeqCst) {
+            continue;
+        }
+        // A query (or another request) holds the connection: skip this tick.
+        let Ok(mut guard) = link.connection.try_lock() else {
+            continue;
+        };
+        let started = Instant::now();
+        let request = STATUS_REQUEST.to_string();
+        if let Ok(status) = probe(&mut guard, &shared, limit, request, pick_status).await {
+            record_summary(&shared, ServerSummary::new(status, started.elapsed()));
+        }
+    }
+}
+
+/// Longest a background status check waits for its reply (or the poll
+/// interval, if shorter). A busy server missing it is not a lost connection.
+const STATUS_POLL_TIMEOUT: Duration = Duration::from_secs(5);
+
+const STATUS_REQUEST: &str = r#"{"type":"status"}"#;
+
+fn pick_status(msg: ServerMessage) -> Option<StatusMessage> {
+    match msg {
+        ServerMessage::Status(status) => Some(status),
+        _ => None,
+    }
+}
+
+/// Time a status request on `connection`.
+async fn check_status(
+    connection: &mut Connection,
+    shared: &Shared,
+    options: &ClientOptions,
+) -> Result<ServerSummary, ClientError> {
+    let started = Instant::now();
+    let status = exchange(
+        connection,
+        shared,
+        options,
+        STATUS_REQUEST.to_string(),
+        pick_status,
+    )
+    .await?;
+    Ok(ServerSummary::new(status, started.elapsed()))
+}
+
+const WARMUP_REQUEST: &str = r#"{"type":"warmup"}"#;
+
+const CAPABILITIES_REQUEST: &str = r#"{"type":"capabilities"}"#;
+
+const LIST_INDEXES_REQUEST: &str = r#"{"type":"list_indexes"}"#;
+
+fn pick_indexes(msg: ServerMessage) -> Option<Vec<IndexInfo>> {
+    match msg {
+        ServerMessage::Indexes(indexes) => Some(indexes.indexes),
+        _ => None,
+    }
+}
+
+/// A `capabilities` request, offering `encoding` if set.
+fn capabilities_request(encoding: Option<WireEncoding>) -> String {
+    match encoding {
+        Some(encoding) => serde_json::json!({
+            "type": "capabilities",
+            "encodings": [encoding],
+        })
+        .to_string(),
+        None => CAPABILITIES_REQUEST.to_string(),
+    }
+}
+
+fn pick_capabilities(msg: ServerMessage) -> Option<CapabilitiesMessage> {
+    match msg {
+        ServerMessage::Capabilities(capabilities) => Some(capabilities),
+        _ => None,
+    }
+}
+
+/// Time a warm-up request on `connection`.
+async fn warm_up(
+    connection: &mut Connection,
+    shared: &Shared,
+    options: &ClientOptions,
+) -> Result<Duration, ClientError> {
+    let started = Instant::now();
+    exchange(
+        connection,
+        shared,
+        options,
+        WARMUP_REQUEST.to_string(),
+        pick_status,
+    )
+    .await?;
+    Ok(started.elapsed())
+}
+
+/// Longest `ClientBuilder::connect` waits for the server's capabilities (or
+/// the first-event timeout, if shorter) before leaving them unknown.
+const CAPABILITIES_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Ask for the server's capabilities on `connection`, offering `encoding`,
+/// and keep the answer. With `within` set the request is a `probe` with
+/// that limit, else an `exchange`.
+async fn ask_capabilities(
+    connection: &mut Connection,
+    shared: &Shared,
+    options: &ClientOptions,
+    encoding: Option<WireEncoding>,
+    within: Option<Duration>,
+) -> Result<CapabilitiesMessage, ClientError> {
+    lock_encoding(&connection.writer.encoding).offered = encoding;
+    let request = capabilities_request(encoding);
+    let capabilities = match within {
+        Some(limit) => probe(connection, shared, limit, request, pick_capabilities).await?,
+        None => exchange(connection, shared, options, request, pick_capabilities).await?,
+    };
+    if let Ok(mut cached) = shared.capabilities.lock() {
+        *cached = Some(capabilities.clone());
+    }
+    Ok(capabilities)
+}
+
+/// Send a single request on `connection` and wait for the reply `pick`
+/// accepts, up to the first-event timeout. An `error` reply becomes
+/// `ClientError::ServerError`.
+async fn exchange<T>(
+    connection: &mut Connection,
+    shared: &Shared,
+    options: &ClientOptions,
+    json: String,
+    pick: impl Fn(ServerMessage) -> Option<T>,
+) -> Result<T, ClientError> {
+    let result = exchange_within(connection, options.first_event_timeout, json, pick).await;
+    if let Err(e) = &result {
+        record_error(shared, e);
+    }
+    result
+}
+
+/// Like `exchange`, but waits at most `limit` for the reply, for background
+/// requests: a late reply is a `TimeoutKind::FirstEvent` error, but doesn't
+/// mark the connection lost.
+async fn probe<T>(
+    connection: &mut Connection,
+    shared: &Shared,
+    limit: Duration,
+    json: String,
+    pick: impl Fn(ServerMessage) -> Option<T>,
+) -> Result<T, ClientError> {
+    let result = exchange_within(connection, Some(limit), json, pick).await;
+    match &result {
+        Err(ClientError::Timeout(_)) => {}
+        Err(e) => record_error(shared, e),
+        Ok(_) => {}
+    }
+    result
+}
+
+/// Send `json` on `connection` and wait up to `limit` for the reply `pick`
+/// accepts, leaving errors for the caller to record.
+async fn exchange_within<T>(
+    connection: &mut Connection,
+    limit: Option<Duration>,
+    json: String,
+    pick: impl Fn(ServerMessage) -> Option<T>,
+) -> Result<T, ClientError> {
+    connection.discard_stale_replies();
+    connection.writer.send(Message::Text(json)).await?;
+    let reply = async {
+        loop {
+            match connection.next_reply().await {
+                Incoming::Message(ServerMessage::Error(error), _) => {
+                    return Err(ClientError::ServerError(error))
+                }
+                Incoming::Message(msg, _) => {
+                    if let Some(reply) = pick(msg) {
+                        return Ok(reply);
+                    }
+                }
+                Incoming::TooLarge(e) => {
+                    return Err(tokio_tungstenite::tungstenite::Error::Capacity(e).into())
+                }
+                Incoming::Failed(e) => return Err(e),
+            }
+        }
+    };
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, reply)
+            .await
+            .unwrap_or(Err(ClientError::Timeout(TimeoutKind::FirstEvent))),
+        None => reply.await,
+    }
 }
 
 impl Client {
-    /// Send a query and collect stream events until STREAM_END or ERROR.
+    /// When the server last answered a ping, or `None` if no pong has been
+    /// seen yet. With `ClientBuilder::keepalive` set, a value older than a
+    /// few intervals means the connection is probably dead.
+    pub fn last_pong(&self) -> Option<std::time::Instant> {
+        self.shared.last_pong.lock().ok().and_then(|at| *at)
+    }
+
+    /// Notifications the server has pushed since the last call (e.g. after
+    /// reindexing changed documents), the latest 256 at most. The
+    /// connection's reader task picks them up as they arrive, during queries
+    /// or between them.
+    pub fn take_notifications(&self) -> Vec<NotificationMessage> {
+        self.shared
+            .notifications
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default()
+    }
+
+    /// The latest index build progress the server reported, if any (kept
+    /// after the build finishes, so `finished` means it completed).
+    pub fn index_progress(&self) -> Option<IndexProgressMessage> {
+        self.shared
+            .index_progress
+            .lock()
+            .ok()
+            .and_then(|p| p.clone())
+    }
+
+    /// False once the connection is known to be lost: a query, request or
+    /// `ping` failed on it, or (with `ClientBuilder::keepalive`) the server
+    /// stopped answering pings. A retried query that reconnects sets it again.
+    pub fn is_connected(&self) -> bool {
+        self.shared
+            .connected
+            .load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Why the connection was lost, while `is_connected` is false.
+    pub fn disconnect_reason(&self) -> Option<String> {
+        self.shared
+            .disconnect_reason
+            .lock()
+            .ok()
+            .and_then(|why| why.clone())
+    }
+
+    /// Check that the server is still there: send a WebSocket ping and wait
+    /// up to `timeout` for the pong. Returns the round-trip time. Works while
+    /// a query is streaming, as the ping does not wait for it. A failed probe
+    /// marks the connection as lost.
+    pub async fn ping(&self, timeout: Duration) -> Result<Duration, ClientError> {
+        let writer = self.link.writer();
+        let mut pongs = writer.pongs.clone();
+        pongs.borrow_and_update();
+        let started = Instant::now();
+        let probe = async {
+            writer.send(Message::Ping(Vec::new())).await?;
+            pongs.changed().await.map_err(|_| ClientError::Closed)?;
+            Ok(started.elapsed())
+        };
+        let result = tokio::time::timeout(timeout, probe)
+            .await
+            .unwrap_or(Err(ClientError::Timeout(TimeoutKind::Ping)));
+        if let Err(e) = &result {
+            record_error(&self.shared, e);
+        }
+        result
+    }
+
+    /// Subscribe to connection lifecycle events from now on: disconnects
+    /// (seen by the connection's reader task, queries, requests, `close` or
+    /// the keepalive task) and the
+    /// reconnects of retried queries. The initial connect is reported by
+    /// `ClientBuilder::connect` itself. A subscriber that falls more than a
+    /// few events behind loses the oldest ones.
+    pub fn connection_events(&self) -> tokio::sync::broadcast::Receiver<ConnectionEvent> {
+        self.shared.events.subscribe()
+    }
+
+    /// Subscribe to the index build progress the server reports from now on,
+    /// e.g. subscribe, then `reload_index` and follow the build until a
+    /// message is `finished`. A subscriber that falls more than 64 messages
+    /// behind loses the oldest.
+    pub fn index_progress_events(&self) -> tokio::sync::broadcast::Receiver<IndexProgressMessage> {
+        self.shared.index_progress_events.subscribe()
+    }
+
+    /// Subscribe to the server log lines followed with `tail_logs` from now
+    /// on; subscribe before asking, so no line falls between the two. A
+    /// subscriber that falls more than 256 lines behind loses the oldest.
+    pub fn log_lines(&self) -> tokio::sync::broadcast::Receiver<LogLine> {
+        self.shared.log_lines.subscribe()
+    }
+
+    /// Close the connection with the WebSocket close handshake: send a Close
+    /// frame with `code` (1000 for a normal closure) and `reason`, then wait
+    /// briefly for the server's Close reply. A query still streaming ends
+    /// with `ClientError::Closed`.
+    pub async fn close(self, code: u16, reason: &str) -> Result<(), ClientError> {
+        let frame = CloseFrame {
+            code: CloseCode::from(code),
+            reason: reason.to_string().into(),
+        };
+        record_disconnected(&self.shared, "closed by client");
+        self.link.writer().send(Message::Close(Some(frame))).await?;
+        // The reader task ends once the server answers with its Close frame.
+        let drain = async {
+            let mut connection = self.link.connection.lock().await;
+            while connection.replies.recv().await.is_some() {}
+        };
+        let _ = tokio::time::timeout(CLOSE_REPLY_TIMEOUT, drain).await;
+        Ok(())
+    }
+
+    /// Ask the server whether its index is ready.
+    pub async fn status(&self) -> Result<StatusMessage, ClientError> {
+        self.request(STATUS_REQUEST.to_string(), pick_status).await
+    }
+
+    /// Ask for the server's status now and time the request. The result also
+    /// becomes the latest `server_summary`.
+    pub async fn check_status(&self) -> Result<ServerSummary, ClientError> {
+        let mut guard = self.link.connection.lock().await;
+        let summary = check_status(&mut guard, &self.shared, &self.builder.options).await?;
+        record_summary(&self.shared, summary.clone());
+        Ok(summary)
+    }
+
+    /// The latest status snapshot from `check_status` or the background poll
+    /// (`ClientBuilder::status_poll`), if there is one yet.
+    pub fn server_summary(&self) -> Option<ServerSummary> {
+        self.shared.summary.lock().ok().and_then(|s| s.clone())
+    }
+
+    /// Ask the server which optional features it supports. The answer is
+    /// kept for `capabilities`. Servers that predate the request answer with
+    /// an error.
+    pub async fn fetch_capabilities(&self) -> Result<CapabilitiesMessage, ClientError> {
+        let mut guard = self.link.connection.lock().await;
+        ask_capabilities(&mut guard, &self.shared, &self.builder.options, None, None).await
+    }
+
+    /// Encoding of the current connection's frames (see
+    /// `ClientBuilder::encoding`).
+    pub fn encoding(&self) -> WireEncoding {
+        current_encoding(&self.link.writer().encoding)
+    }
+
+    /// The features the server reported on connect (see
+    /// `ClientBuilder::capabilities`) or to `fetch_capabilities`, or `None`
+    /// when they were not asked for or the server did not say.
+    pub fn capabilities(&self) -> Option<CapabilitiesMessage> {
+        self.shared.capabilities.lock().ok().and_then(|c| c.clone())
+    }
+
+    /// Whether the server supports `feature` (e.g. `"cancel"`). Assumed
+    /// true unless the server listed its features without it.
+    pub fn supports(&self, feature: &str) -> bool {
+        self.capabilities()
+            .is_none_or(|capabilities| capabilities.supports(feature))
+    }
+
+    /// One page (zero-based) of the documents in `index`, with chunk counts.
+    pub async fn list_documents(
+        &self,
+        index: Option<&str>,
+        page: usize,
+        page_size: usize,
+    ) -> Result<DocumentsMessage, ClientError> {
+        let json = serde_json::to_string(&ListDocumentsMessage::new(index, page, page_size))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::Documents(documents) => Some(documents),
+            _ => None,
+        })
+        .await
+    }
+
+    /// The indexed chunks of the document at `path`.
+    pub async fn document_chunks(&self, path: &str) -> Result<ChunksMessage, ClientError> {
+        let json = serde_json::to_string(&GetChunksMessage::new(path))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::Chunks(chunks) => Some(chunks),
+            _ => None,
+        })
+        .await
+    }
+
+    /// The documents that the document at `path` links to, and those that
+    /// link to it.
+    pub async fn document_links(&self, path: &str) -> Result<LinksMessage, ClientError> {
+        let json = serde_json::to_string(&GetLinksMessage::new(path))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::Links(links) => Some(links),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Document and chunk counts, embedding model, build time and size on
+    /// disk of `index` (the server's default index when `None`). Servers
+    /// without index stats answer with an error.
+    pub async fn index_stats(&self, index: Option<&str>) -> Result<IndexStatsMessage, ClientError> {
+        let json = serde_json::to_string(&GetIndexStatsMessage::new(index))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::IndexStats(stats) => Some(stats),
+            _ => None,
+        })
+        .await
+    }
+
+    /// The files a build of `index` (the server's default index when `None`)
+    /// would read and the ones its ignore files leave out, without indexing
+    /// anything. Servers without index plans answer with an error.
+    pub async fn index_plan(&self, index: Option<&str>) -> Result<IndexPlanMessage, ClientError> {
+        let json = serde_json::to_string(&GetIndexPlanMessage::new(index, false))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::IndexPlan(plan) => Some(plan),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Like `index_plan`, plus an estimate of what a reload of `index`
+    /// would embed (files, chunks, tokens and a rough time), to confirm a
+    /// large build first. The server reads and chunks the files for it, so
+    /// this takes longer. Servers without estimates leave
+    /// `IndexPlanMessage::estimate` empty.
+    pub async fn index_estimate(
+        &self,
+        index: Option<&str>,
+    ) -> Result<IndexPlanMessage, ClientError> {
+        let json = serde_json::to_string(&GetIndexPlanMessage::new(index, true))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::IndexPlan(plan) => Some(plan),
+            _ => None,
+        })
+        .await
+    }
+
+    /// The files whose chunks are still in `index` (the server's default
+    /// index when `None`) although it no longer covers them, e.g. deleted
+    /// ones; with `remove` the server also drops those chunks. Servers
+    /// without index verification answer with an error.
+    pub async fn index_verify(
+        &self,
+        index: Option<&str>,
+        remove: bool,
+    ) -> Result<IndexVerifyMessage, ClientError> {
+        let json = serde_json::to_string(&GetIndexVerifyMessage::new(index, remove))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::IndexVerify(report) => Some(report),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Have the server write `index` (its default index when `None`) to a
+    /// snapshot archive at `path`, an absolute path on the server's machine
+    /// ending in `.tar.zst`, `.tar.gz` or `.tar`, for teammates to restore
+    /// instead of embedding the same documents. Servers without snapshots
+    /// answer with an error.
+    pub async fn index_snapshot(
+        &self,
+        index: Option<&str>,
+        path: &str,
+    ) -> Result<IndexSnapshotMessage, ClientError> {
+        let json = serde_json::to_string(&SnapshotIndexMessage::new(index, path))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::IndexSnapshot(snapshot) => Some(snapshot),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Have the server replace `index` with the snapshot archive at `path`
+    /// on its machine and load it. It refuses snapshots embedded with
+    /// another model than its own, and then reloads to pick up documents
+    /// that differ from the snapshot's.
+    pub async fn index_restore(
+        &self,
+        index: Option<&str>,
+        path: &str,
+    ) -> Result<IndexSnapshotMessage, ClientError> {
+        let json = serde_json::to_string(&RestoreIndexMessage::new(index, path))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::IndexRestore(restored) => Some(restored),
+            _ => None,
+        })
+        .await
+    }
+
+    /// The indexes queries and index requests can name: the server's
+    /// configured one first, then those made with `create_index`. Servers
+    /// without named indexes answer with an error.
+    pub async fn list_indexes(&self) -> Result<Vec<IndexInfo>, ClientError> {
+        self.request(LIST_INDEXES_REQUEST.to_string(), pick_indexes)
+            .await
+    }
+
+    /// Have the server add the named index `name` over `directories` (on its
+    /// machine), with its own chunking and embedding model; `None` ones are
+    /// the server's. It builds the index in the background (status
+    /// "indexing") and keeps it across restarts. Returns the indexes there
+    /// are now.
+    pub async fn create_index(
+        &self,
+        name: &str,
+        directories: &[String],
+        chunking: Option<&ChunkingSection>,
+        embedding_model: Option<&str>,
+    ) -> Result<Vec<IndexInfo>, ClientError> {
+        let json = serde_json::to_string(&CreateIndexMessage::new(
+            name,
+            directories,
+            chunking,
+            embedding_model,
+        ))?;
+        self.request(json, pick_indexes).await
+    }
+
+    /// Have the server remove the named index `name` and its files. The
+    /// configured index can't be deleted. Returns the indexes left.
+    pub async fn delete_index(&self, name: &str) -> Result<Vec<IndexInfo>, ClientError> {
+        let json = serde_json::to_string(&DeleteIndexMessage::new(name))?;
+        self.request(json, pick_indexes).await
+    }
+
+    /// Have the server run one retrieval (embedding and index search, no
+    /// LLM call) to load its caches, and return how long it took. Servers
+    /// without warm-up answer with an error.
+    pub async fn warmup(&self) -> Result<Duration, ClientError> {
+        let mut guard = self.link.connection.lock().await;
+        warm_up(&mut guard, &self.shared, &self.builder.options).await
+    }
+
+    /// Ask the server to re-index `index` (its default index when `None`)
+    /// from the Markdown files on disk. Returns the server's status once the
+    /// reload has started; it is done when `status` reports "ready" again.
+    pub async fn reload_index(&self, index: Option<&str>) -> Result<StatusMessage, ClientError> {
+        let json = serde_json::to_string(&ReloadMessage::new(index))?;
+        self.request(json, pick_status).await
+    }
+
+    /// Ask the server for `count` follow-up questions to `answer`, grounded
+    /// in its `sources`. Servers without suggestions answer with an error.
+    pub async fn suggest_followups(
+        &self,
+        question: &str,
+        answer: &str,
+        sources: &[Source],
+        count: usize,
+    ) -> Result<Vec<String>, ClientError> {
+        let msg = SuggestFollowupsMessage::new(question, answer, sources, count);
+        let json = serde_json::to_string(&msg)?;
+        self.request(json, |msg| match msg {
+            ServerMessage::Followups(followups) => Some(followups.questions),
+            _ => None,
+        })
+        .await
+    }
+
+    /// The last `lines` lines of the server log, oldest first, with their
+    /// severity. With `follow` the server goes on sending every new line,
+    /// which arrive through `log_lines`, until asked again without `follow`
+    /// or the connection ends. Servers without a log tail answer with an
+    /// error.
+    pub async fn tail_logs(&self, lines: usize, follow: bool) -> Result<Vec<LogLine>, ClientError> {
+        let json = serde_json::to_string(&TailLogsMessage::new(lines, follow))?;
+        self.request(json, |msg| match msg {
+            ServerMessage::Logs(logs) => Some(logs.lines.into_iter().map(LogLine::new).collect()),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Send a single request and wait for its reply (see `exchange`).
+    async fn request<T>(
+        &self,
+        json: String,
+        pick: impl Fn(ServerMessage) -> Option<T>,
+    ) -> Result<T, ClientError> {
+        let mut guard = self.link.connection.lock().await;
+        exchange(&mut guard, &self.shared, &self.builder.options, json, pick).await
+    }
+
+    /// Send a query and collect stream events until STREAM_END, RESPONSE, or ERROR.
     pub async fn query(
         &self,
         question: &str,
         index: Option<&str>,
     ) -> Result<Vec<StreamEvent>, ClientError> {
-        let mut guard = self.inner.lock().await;
-        let msg = QueryMessage::new(question, index);
-        let json = serde_json::to_string(&msg).map_err(ClientError::from)?;
-        guard.send(Message::Text(json)).await?;
+        self.query_message(&QueryMessage::new(question, index))
+            .await
+    }
+
+    /// Like `query`, for a follow-up: `history` (the earlier turns of the
+    /// conversation, oldest first) is sent along so the answer can build on
+    /// them.
+    pub async fn query_with_history(
+        &self,
+        question: &str,
+        index: Option<&str>,
+        history: &[Turn],
+    ) -> Result<Vec<StreamEvent>, ClientError> {
+        self.query_message(&QueryMessage::new(question, index).with_history(history))
+            .await
+    }
 
+    /// Like `query`, but sends a fully built `QueryMessage` (e.g. with a
+    /// retrieval preference or `QueryOptions`).
+    pub async fn query_message(
+        &self,
+        msg: &QueryMessage<'_>,
+    ) -> Result<Vec<StreamEvent>, ClientError> {
+        let stream = self.query_message_stream(msg);
+        futures_util::pin_mut!(stream);
+        let limit = self.builder.options.max_buffered_events;
         let mut events = Vec::new();
-        while let Some(item) = guard.next().await {
-            let message = item.map_err(|e| ClientError(e.to_string()))?;
-            let text = match message {
-                Message::Text(t) => t,
-                Message::Close(_) => break,
-                _ => continue,
-            };
-            let value: serde_json::Value =
-                serde_json::from_str(&text).map_err(ClientError::from)?;
-            let server_msg = ServerMessage::from_json(&value).map_err(ClientError::from)?;
-            match server_msg {
-                ServerMessage::StreamStart => events.push(StreamEvent::StreamStart),
-                ServerMessage::StreamChunk(chunk) => events.push(StreamEvent::StreamChunk(chunk)),
-                ServerMessage::StreamEnd(sources) => {
-                    events.push(StreamEvent::StreamEnd(deduplicate_sources(sources)));
-                    break;
+        let mut discarded = 0;
+        while let Some(event) = stream.next().await {
+            let event = event?;
+            if limit.is_some_and(|max| events.len() >= max) {
+                // Keep reading so the connection stays in step with the server.
+                discarded += 1;
+                continue;
+            }
+            events.push(event);
+        }
+        if discarded > 0 {
+            events.push(StreamEvent::Error(ErrorMessage::new(format!(
+                "answer exceeded the {}-event buffer; {} events were discarded",
+                events.len(),
+                discarded
+            ))));
+        }
+        Ok(events)
+    }
+
+    /// Send a query and yield stream events as they arrive. The stream ends
+    /// after STREAM_END, RESPONSE, ERROR, or the first error (including `ClientError::Closed`
+    /// when the server hangs up mid-answer).
+    pub fn query_stream(
+        &self,
+        question: &str,
+        index: Option<&str>,
+    ) -> impl Stream<Item = Result<StreamEvent, ClientError>> + Send + 'static {
+        self.query_message_stream(&QueryMessage::new(question, index))
+    }
+
+    /// Send a query and call `on_event` with each stream event as it arrives,
+    /// for integrations that would rather not poll a `Stream`. Returns after
+    /// STREAM_END, RESPONSE, or ERROR, or with the first `ClientError`.
+    pub async fn query_with(
+        &self,
+        question: &str,
+        index: Option<&str>,
+        mut on_event: impl FnMut(StreamEvent),
+    ) -> Result<(), ClientError> {
+        let stream = self.query_stream(question, index);
+        futures_util::pin_mut!(stream);
+        while let Some(event) = stream.next().await {
+            on_event(event?);
+        }
+        Ok(())
+    }
+
+    /// Like `query_stream`, but sends a fully built `QueryMessage`.
+    pub fn query_message_stream(
+        &self,
+        msg: &QueryMessage<'_>,
+    ) -> impl Stream<Item = Result<StreamEvent, ClientError>> + Send + 'static {
+        let json = serde_json::to_string(msg).map_err(ClientError::from);
+        self.query_json_stream(json, msg.id)
+    }
+
+    /// Ask the same question two ways at once, e.g. with a different
+    /// `QueryOptions::model` each: `left` runs on this connection and `right`
+    /// on a second one opened with the same settings (the protocol answers
+    /// one query per connection at a time). The second connection only
+    /// carries that answer: it sends no warm-up, keepalive pings, status
+    /// polls or capabilities request, and is closed with a normal Close frame
+    /// once the answer is done (or just dropped, with the stream, before).
+    /// Events of both answers are yielded as they arrive, tagged with their
+    /// `Side`; each side ends on its own STREAM_END, RESPONSE, ERROR or
+    /// first error.
+    pub fn compare_stream(
+        &self,
+        left: &QueryMessage<'_>,
+        right: &QueryMessage<'_>,
+    ) -> impl Stream<Item = (Side, Result<StreamEvent, ClientError>)> + Send + 'static {
+        let left = self
+            .query_message_stream(left)
+            .map(|event| (Side::Left, event));
+        let (json, id) = (
+            serde_json::to_string(right).map_err(ClientError::from),
+            right.id,
+        );
+        let mut builder = (*self.builder).clone();
+        builder.keepalive = None;
+        builder.status_poll = None;
+        builder.warmup = false;
+        builder.capabilities = false;
+        let right = futures_util::stream::once(async move {
+            match builder.connect().await {
+                Ok(client) => {
+                    let answer = client.query_json_stream(json, id);
+                    let close = futures_util::stream::once(async move {
+                        let _ = client.close(1000, "").await;
+                    })
+                    .filter_map(|()| async { None });
+                    answer.chain(close).left_stream()
+                }
+                Err(e) => futures_util::stream::once(async { Err(e) }).right_stream(),
+            }
+        })
+        .flatten()
+        .map(|event| (Side::Right, event));
+        futures_util::stream::select(left, right)
+    }
+
+    /// Tell the server to stop generating the answer to the query with id
+    /// `request_id`, e.g. when the user presses stop. Can be called while
+    /// that query's stream is being read: the server ends it with an ERROR
+    /// reply. Replies still in flight for a dropped stream are skipped by the
+    /// next query.
+    pub async fn cancel(&self, request_id: u64) -> Result<(), ClientError> {
+        let json = serde_json::to_string(&CancelMessage::new(request_id))?;
+        self.link.writer().send(Message::Text(json)).await
+    }
+
+    /// Send the serialized query `json` and yield the events of its answer,
+    /// skipping replies tagged with an id other than `id`.
+    fn query_json_stream(
+        &self,
+        json: Result<String, ClientError>,
+        id: u64,
+    ) -> impl Stream<Item = Result<StreamEvent, ClientError>> + Send + 'static {
+        let state = QueryState::Send(
+            self.link.clone(),
+            json,
+            id,
+            self.builder.clone(),
+            self.shared.clone(),
+        );
+        futures_util::stream::unfold(state, |state| async move {
+            match state {
+                QueryState::Send(link, json, id, builder, shared) => {
+                    let json = match json {
+                        Ok(json) => json,
+                        Err(e) => return Some((Err(e), QueryState::Done)),
+                    };
+                    let retry = builder.options.retry;
+                    let mut attempt = 1;
+                    let mut reconnect = false;
+                    loop {
+                        let next = if !reconnect {
+                            send_query(&link, &json, id, &builder.options, &shared).await
+                        } else {
+                            let event = ConnectionEvent::Reconnecting {
+                                attempt: attempt - 1,
+                            };
+                            send_event(&shared, event);
+                            match builder.open().await {
+                                Ok(stream) => {
+                                    link.replace(stream, &shared).await;
+                                    record_connected(&shared);
+                                    send_query(&link, &json, id, &builder.options, &shared).await
+                                }
+                                Err(e) => Some((Err(e), QueryState::Done)),
+                            }
+                        };
+                        if attempt >= retry.max_attempts {
+                            return next;
+                        }
+                        match &next {
+                            Some((Err(e), _)) if retry.is_retryable(e) => reconnect = true,
+                            // The server is fine, just busy: ask again on the same connection.
+                            Some((Ok(StreamEvent::Error(e)), _))
+                                if retry.retries_server_error(e) =>
+                            {
+                                reconnect = false
+                            }
+                            _ => return next,
+                        }
+                        tokio::time::sleep(retry.backoff(attempt)).await;
+                        attempt += 1;
+                    }
+                }
+                QueryState::Read(guard, id, deadlines, shared) => {
+                    next_stream_event(guard, id, deadlines, shared).await
+                }
+                QueryState::Done => None,
+            }
+        })
+    }
+}
+
+/// Send `json` (the query with id `id`) and read up to its first stream event.
+async fn send_query(
+    link: &Link,
+    json: &str,
+    id: u64,
+    options: &ClientOptions,
+    shared: &Shared,
+) -> Option<(Result<StreamEvent, ClientError>, QueryState)> {
+    let mut guard = link.connection.clone().lock_owned().await;
+    guard.discard_stale_replies();
+    if let Err(e) = guard.writer.send(Message::Text(json.to_string())).await {
+        record_error(shared, &e);
+        return Some((Err(e), QueryState::Done));
+    }
+    let now = Instant::now();
+    let deadlines = Deadlines {
+        first_event: options.first_event_timeout.map(|limit| now + limit),
+        stream: options.stream_timeout.map(|limit| now + limit),
+    };
+    next_stream_event(guard, id, deadlines, shared.clone()).await
+}
+
+/// Progress of a `query_message_stream` (with the query's id); the
+/// connection's replies stay locked until the query finishes.
+enum QueryState {
+    Send(
+        Arc<Link>,
+        Result<String, ClientError>,
+        u64,
+        Arc<ClientBuilder>,
+        Shared,
+    ),
+    Read(OwnedMutexGuard<Connection>, u64, Deadlines, Shared),
+    Done,
+}
+
+/// Absolute deadlines for the query in flight; `first_event` is cleared once an event arrives.
+#[derive(Clone, Copy)]
+struct Deadlines {
+    first_event: Option<Instant>,
+    stream: Option<Instant>,
+}
+
+impl Deadlines {
+    /// The deadline that expires first, and which timeout it represents.
+    fn next(&self) -> Option<(Instant, TimeoutKind)> {
+        let first = self.first_event.map(|at| (at, TimeoutKind::FirstEvent));
+        let stream = self.stream.map(|at| (at, TimeoutKind::Stream));
+        match (first, stream) {
+            (Some(a), Some(b)) => Some(if b.0 < a.0 { b } else { a }),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Read replies until the next stream event, reporting a lost connection.
+async fn next_stream_event(
+    guard: OwnedMutexGuard<Connection>,
+    id: u64,
+    deadlines: Deadlines,
+    shared: Shared,
+) -> Option<(Result<StreamEvent, ClientError>, QueryState)> {
+    let next = read_stream_event(guard, id, deadlines, shared.clone()).await;
+    if let Some((Err(e), _)) = &next {
+        record_error(&shared, e);
+    }
+    next
+}
+
+/// Read replies until the next stream event of query `id`. Replies tagged
+/// with another id are left over from an earlier, abandoned query.
+async fn read_stream_event(
+    mut guard: OwnedMutexGuard<Connection>,
+    id: u64,
+    mut deadlines: Deadlines,
+    shared: Shared,
+) -> Option<(Result<StreamEvent, ClientError>, QueryState)> {
+    loop {
+        let reply = match deadlines.next() {
+            Some((at, kind)) => match tokio::time::timeout_at(at, guard.next_reply()).await {
+                Ok(reply) => reply,
+                Err(_) => return Some((Err(ClientError::Timeout(kind)), QueryState::Done)),
+            },
+            None => guard.next_reply().await,
+        };
+        let server_msg = match reply {
+            Incoming::Message(_, Some(reply_to)) if reply_to != id => continue,
+            Incoming::Message(message, _) => message,
+            Incoming::TooLarge(e) => {
+                let message = format!("server message too large: {}", e);
+                let event = StreamEvent::Error(ErrorMessage::new(message));
+                return Some((Ok(event), QueryState::Done));
+            }
+            Incoming::Failed(e) => return Some((Err(e), QueryState::Done)),
+        };
+        let event = match server_msg {
+            ServerMessage::StreamStart => StreamEvent::StreamStart,
+            ServerMessage::StreamChunk {
+                chunk,
+                role,
+                finish_reason,
+                candidate,
+            } => StreamEvent::StreamChunk(
+                chunk,
+                ChunkMeta {
+                    role,
+                    finish_reason,
+                    candidate,
+                },
+            ),
+            ServerMessage::StreamThinking { chunk } => StreamEvent::Thinking(chunk),
+            ServerMessage::StreamEnd {
+                sources,
+                grounding,
+                citations,
+                usage,
+                elapsed_ms,
+            } => {
+                let event = StreamEvent::StreamEnd {
+                    sources: deduplicate_sources(sources),
+                    grounding,
+                    citations,
+                    stats: QueryStats { usage, elapsed_ms },
+                };
+                return Some((Ok(event), QueryState::Done));
+            }
+            ServerMessage::Error(error) => {
+                return Some((Ok(StreamEvent::Error(error)), QueryState::Done));
+            }
+            ServerMessage::Response { answer, sources } => {
+                let event = StreamEvent::Response {
+                    answer,
+                    sources: deduplicate_sources(sources),
+                };
+                return Some((Ok(event), QueryState::Done));
+            }
+            ServerMessage::IndexProgress(progress) => StreamEvent::IndexProgress {
+                done: progress.done,
+                total: progress.total,
+                path: progress.path,
+            },
+            ServerMessage::Unknown { typ, payload } => StreamEvent::Unknown { typ, payload },
+            // Index states are answers to `status` requests, not progress.
+            ServerMessage::Status(status) if !status.is_index_state() => StreamEvent::Progress {
+                status: status.status,
+                message: status.message,
+            },
+            // Notifications and log lines are recorded by the reader task.
+            ServerMessage::Notification(_)
+            | ServerMessage::Log(_)
+            | ServerMessage::Logs(_)
+            | ServerMessage::Status(_)
+            | ServerMessage::Documents(_)
+            | ServerMessage::Chunks(_)
+            | ServerMessage::Links(_)
+            | ServerMessage::IndexStats(_)
+            | ServerMessage::IndexPlan(_)
+            | ServerMessage::IndexVerify(_)
+            | ServerMessage::IndexSnapshot(_)
+            | ServerMessage::IndexRestore(_)
+            | ServerMessage::Indexes(_)
+            | ServerMessage::Followups(_)
+            | ServerMessage::Capabilities(_) => continue,
+        };
+        deadlines.first_event = None;
+        return Some((Ok(event), QueryState::Read(guard, id, deadlines, shared)));
+    }
+}
+
+/// Sources of an answer under one directory (`StreamCollector::source_groups`).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SourceGroup {
+    /// The deepest directory all sources share, joined with the top-level
+    /// directory below it, e.g. `docs/design`; `.` for relative paths
+    /// without a shared directory.
+    pub directory: String,
+    pub count: usize,
+}
+
+impl std::fmt::Display for SourceGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.directory, self.count)
+    }
+}
+
+/// Group `sources` by top-level directory below the deepest directory they
+/// all share, largest group first (ties in order of first appearance).
+pub fn group_sources(sources: &[Source]) -> Vec<SourceGroup> {
+    use std::path::{Component, Path};
+
+    let dirs: Vec<Vec<Component<'_>>> = sources
+        .iter()
+        .map(|source| {
+            let path = Path::new(&source.path);
+            path.parent().unwrap_or(path).components().collect()
+        })
+        .collect();
+    let first = dirs.first().map_or(&[][..], Vec::as_slice);
+    let shared = dirs.iter().fold(first.len(), |shared, dir| {
+        shared.min(first.iter().zip(dir).take_while(|(a, b)| a == b).count())
+    });
+    let base = match shared.checked_sub(1).map(|last| first[last]) {
+        Some(Component::Normal(name)) => Some(name.to_string_lossy()),
+        _ => None,
+    };
+    let mut groups: Vec<SourceGroup> = Vec::new();
+    for dir in &dirs {
+        let below = dir.get(shared).map(|c| c.as_os_str().to_string_lossy());
+        let directory = match (&base, below) {
+            (Some(base), Some(below)) => format!("{}/{}", base, below),
+            (Some(base), None) => base.to_string(),
+            (None, Some(below)) => below.into_owned(),
+            (None, None) => ".".to_string(),
+        };
+        match groups.iter_mut().find(|g| g.directory == directory) {
+            Some(group) => group.count += 1,
+            None => groups.push(SourceGroup {
+                directory,
+                count: 1,
+            }),
+        }
+    }
+    groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+    groups
+}
+
+/// A piece of an answer: text, or a citation marker such as `[2]` with the
+/// source the `stream_end` citation map gives for it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AnswerSpan {
+    Text { text: String },
+    Citation { number: u32, source: Source },
+}
+
+/// Split `answer` at markers `[N]` whose number is in `citations`, so front
+/// ends can make them clickable. Other bracketed text (unknown numbers, links,
+/// `[x]` checkboxes) stays part of the surrounding text.
+pub fn cite_answer(answer: &str, citations: &Citations) -> Vec<AnswerSpan> {
+    let mut spans = Vec::new();
+    let mut text_start = 0;
+    let mut search = 0;
+    while let Some(open) = answer[search..].find('[').map(|i| search + i) {
+        search = open + 1;
+        let Some(len) = answer[search..].find(']') else {
+            break;
+        };
+        let digits = &answer[search..search + len];
+        let cited = if digits.bytes().all(|b| b.is_ascii_digit()) {
+            digits
+                .parse()
+                .ok()
+                .and_then(|number| Some((number, citations.get(&number)?)))
+        } else {
+            None
+        };
+        let Some((number, source)) = cited else {
+            continue;
+        };
+        if open > text_start {
+            spans.push(AnswerSpan::Text {
+                text: answer[text_start..open].to_string(),
+            });
+        }
+        spans.push(AnswerSpan::Citation {
+            number,
+            source: source.clone(),
+        });
+        search += len + 1;
+        text_start = search;
+    }
+    if text_start < answer.len() {
+        spans.push(AnswerSpan::Text {
+            text: answer[text_start..].to_string(),
+        });
+    }
+    spans
+}
+
+/// Candidate answers a `StreamCollector` keeps; text of later candidates is
+/// dropped, so a server can't make it allocate without bound.
+const MAX_CANDIDATES: usize = 16;
+
+/// Folds stream events into the assembled answer, sources, and error so the
+/// CLI and GUI present a finished reply the same way.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamCollector {
+    /// All answer chunks concatenated.
+    pub answer: String,
+    /// Thinking events and chunks outside the answer (reasoning, preamble)
+    /// concatenated.
+    pub reasoning: String,
+    /// Answer text of each candidate by index (the first is `answer`), when
+    /// the server streamed candidates; empty otherwise.
+    pub candidates: Vec<String>,
+    /// Sources from STREAM_END (or RESPONSE), up to `retrieval.max_sources`.
+    pub sources: Vec<Source>,
+    /// Sources left out of `sources` by `retrieval.max_sources`.
+    pub omitted_sources: usize,
+    /// All sources by top-level directory, largest group first, with
+    /// `retrieval.group_sources`; else empty.
+    pub source_groups: Vec<SourceGroup>,
+    /// Grounding check result from STREAM_END, if requested.
+    pub grounding: Option<Grounding>,
+    /// Sources behind the answer's citation markers, from STREAM_END; not
+    /// limited by `retrieval.max_sources`.
+    pub citations: Citations,
+    /// Token usage and timing from STREAM_END, if reported.
+    pub stats: QueryStats,
+    /// Error message from the server, if any.
+    pub error: Option<String>,
+    /// Machine-readable reason of `error`, if the server gave one.
+    pub error_code: Option<ErrorCode>,
+    retrieval: RetrievalSection,
+}
+
+impl StreamCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A collector presenting sources as the `retrieval` section of the
+    /// config asks.
+    pub fn with_retrieval(retrieval: &RetrievalSection) -> Self {
+        Self {
+            retrieval: retrieval.clone(),
+            ..Self::default()
+        }
+    }
+
+    fn set_sources(&mut self, sources: &[Source]) {
+        let shown = self.retrieval.max_sources.unwrap_or(usize::MAX);
+        self.sources = sources.iter().take(shown).cloned().collect();
+        self.omitted_sources = sources.len() - self.sources.len();
+        self.source_groups = if self.retrieval.group_sources {
+            group_sources(sources)
+        } else {
+            Vec::new()
+        };
+    }
+
+    fn push_candidate(&mut self, candidate: usize, chunk: &str) {
+        if candidate >= MAX_CANDIDATES {
+            return;
+        }
+        if self.candidates.len() <= candidate {
+            self.candidates.resize(candidate + 1, String::new());
+        }
+        self.candidates[candidate].push_str(chunk);
+    }
+
+    /// The answer split at its citation markers (see `cite_answer`).
+    pub fn answer_spans(&self) -> Vec<AnswerSpan> {
+        cite_answer(&self.answer, &self.citations)
+    }
+
+    pub fn push(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::StreamStart
+            | StreamEvent::Progress { .. }
+            | StreamEvent::IndexProgress { .. }
+            | StreamEvent::Unknown { .. } => {}
+            StreamEvent::StreamChunk(chunk, meta) if meta.is_answer_text() => {
+                if let Some(candidate) = meta.candidate {
+                    self.push_candidate(candidate as usize, chunk);
                 }
-                ServerMessage::Error(message) => {
-                    events.push(StreamEvent::Error(message));
-                    break;
+                if meta.is_answer() {
+                    self.answer.push_str(chunk)
                 }
-                ServerMessage::Status { .. } | ServerMessage::Response { .. } => {}
+            }
+            StreamEvent::StreamChunk(chunk, _) | StreamEvent::Thinking(chunk) => {
+                self.reasoning.push_str(chunk)
+            }
+            StreamEvent::StreamEnd {
+                sources,
+                grounding,
+                citations,
+                stats,
+            } => {
+                self.set_sources(sources);
+                self.grounding = grounding.clone();
+                self.citations = citations.clone();
+                self.stats = stats.clone();
+            }
+            StreamEvent::Response { answer, sources } => {
+                self.answer = answer.clone();
+                self.set_sources(sources);
+            }
+            StreamEvent::Error(error) => {
+                self.error = Some(error.message.clone());
+                self.error_code = error.code;
             }
         }
-        Ok(events)
     }
 }
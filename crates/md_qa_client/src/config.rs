@@ -1,8 +1,16 @@
 //! Client config load/save for `~/.md-qa/config.yaml`.
 //! Schema matches docs/protocol.md (api.*, server.*).
 
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
+use serde::de::{self, DeserializeSeed, Deserializer, Visitor};
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Value};
+
+use crate::usage::PriceTable;
+
 /// API section (base_url, api_key, embedding_model, llm_model).
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct ApiSection {
@@ -16,7 +24,8 @@ pub struct ApiSection {
     pub llm_model: Option<String>,
 }
 
-/// Server section (port, directories, reload_interval, index_name).
+/// Server section (port, directories, reload_interval, index_name, max_file_size_mb,
+/// docs_language, index_drafts, warmup).
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct ServerSection {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -27,6 +36,183 @@ pub struct ServerSection {
     pub reload_interval: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub index_name: Option<String>,
+    /// Markdown files larger than this many megabytes are not indexed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_file_size_mb: Option<u64>,
+    /// Primary language of the documents (e.g. `"en"`). When set, the server
+    /// translates questions in other languages into it before retrieval and
+    /// answers them in their own language.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs_language: Option<String>,
+    /// Also index files whose front matter sets `draft: true`; they are
+    /// skipped by default.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub index_drafts: bool,
+    /// Have the GUI send a warm-up retrieval after connecting, so the first
+    /// question doesn't pay for cold caches. Only read by the Rust clients.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub warmup: bool,
+}
+
+/// Client section (url, ca_cert, accept_invalid_certs, proxy, system_proxy, status_interval). Only read by the
+/// Rust clients; the server ignores it.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ClientSection {
+    /// Server URL (`ws://` or `wss://`); defaults to `ws://127.0.0.1:{server.port}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// PEM file with extra root certificates trusted for `wss://` URLs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<String>,
+    /// Skip certificate checks for `wss://` URLs (self-signed servers).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub accept_invalid_certs: bool,
+    /// Proxy URL (`http://`, `socks5://`); overrides `HTTPS_PROXY` / `ALL_PROXY`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Use the operating system's proxy settings when neither `proxy` nor a
+    /// proxy variable is set; the GUI does by default, the CLI only when true.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_proxy: Option<bool>,
+    /// Seconds between the GUI's background `status` checks; default 30.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_interval: Option<u64>,
+}
+
+impl ClientSection {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Hooks section (pre_query, post_answer, timeout, on_failure): user
+/// commands the Rust clients run around each question. The server ignores it.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HooksSection {
+    /// Shell command run before a question is sent, with the question on
+    /// stdin; non-empty output replaces the question.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_query: Option<String>,
+    /// Shell command run after an answer, with `{question, answer, sources}`
+    /// JSON on stdin.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_answer: Option<String>,
+    /// Seconds a hook may run before it is killed; default 10.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// What a failing hook does to the question; default `warn`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<HookFailure>,
+}
+
+impl HooksSection {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Export section (issue_template, snippet_template): template files for
+/// `history export` and the GUI's export. Unset ones use built-in templates.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExportSection {
+    /// Template for a GitHub/GitLab issue body (`--as issue`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue_template: Option<String>,
+    /// Template for a Markdown snippet (`--as snippet`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet_template: Option<String>,
+}
+
+impl ExportSection {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Retrieval section (max_sources, group_sources, chunking): how the Rust
+/// clients present the sources of an answer, and how the server cuts
+/// documents into chunks. The server only reads `chunking`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RetrievalSection {
+    /// Sources listed under an answer; the rest are only counted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_sources: Option<usize>,
+    /// Summarize the sources by top-level directory, e.g.
+    /// `docs/design (3), docs/api (2)`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub group_sources: bool,
+    #[serde(default, skip_serializing_if = "ChunkingSection::is_empty")]
+    pub chunking: ChunkingSection,
+}
+
+/// Chunking parameters (target_tokens, overlap_tokens, heading_level) the
+/// server builds its index with. Unset ones use the server's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ChunkSettings {
+    /// Chunk size in estimated tokens (a CJK character counts as one, other
+    /// text as one per four characters).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_tokens: Option<u32>,
+    /// Tokens adjacent chunks share; less than `target_tokens`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlap_tokens: Option<u32>,
+    /// Split at headings of this level or higher (1-6) before paragraphs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_level: Option<u8>,
+}
+
+/// `retrieval.chunking`: chunking parameters, and per directory the ones
+/// that differ for the files under it (e.g. CJK documents, API reference
+/// pages). Only read by the server.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ChunkingSection {
+    #[serde(flatten)]
+    pub defaults: ChunkSettings,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub directories: BTreeMap<String, ChunkSettings>,
+}
+
+impl ChunkSettings {
+    /// Add what is out of range to `problems`, naming the values by `key`.
+    fn check(&self, key: &str, problems: &mut Vec<String>) {
+        if self.target_tokens == Some(0) {
+            problems.push(format!("{}.target_tokens must be positive", key));
+        }
+        if matches!(self.heading_level, Some(level) if !(1..=6).contains(&level)) {
+            problems.push(format!("{}.heading_level must be between 1 and 6", key));
+        }
+        if let (Some(target), Some(overlap)) = (self.target_tokens, self.overlap_tokens) {
+            if overlap >= target {
+                problems.push(format!(
+                    "{}.overlap_tokens must be less than target_tokens",
+                    key
+                ));
+            }
+        }
+    }
+}
+
+impl ChunkingSection {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl RetrievalSection {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Failure policy for hooks (`hooks.on_failure`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookFailure {
+    /// Report the failure and carry on with the unchanged question or answer.
+    #[default]
+    Warn,
+    /// Fail the question.
+    Abort,
 }
 
 /// Full config matching docs/protocol.md schema.
@@ -36,6 +222,155 @@ pub struct Config {
     pub api: ApiSection,
     #[serde(default)]
     pub server: ServerSection,
+    #[serde(default, skip_serializing_if = "ClientSection::is_empty")]
+    pub client: ClientSection,
+    #[serde(default, skip_serializing_if = "HooksSection::is_empty")]
+    pub hooks: HooksSection,
+    #[serde(default, skip_serializing_if = "ExportSection::is_empty")]
+    pub export: ExportSection,
+    #[serde(default, skip_serializing_if = "RetrievalSection::is_empty")]
+    pub retrieval: RetrievalSection,
+    /// Model prices per million tokens, for cost estimates.
+    #[serde(default, skip_serializing_if = "PriceTable::is_empty")]
+    pub prices: PriceTable,
+}
+
+impl Config {
+    /// URL the clients connect to: `client.url`, else `ws://127.0.0.1:{server.port}`.
+    pub fn server_url(&self) -> String {
+        match &self.client.url {
+            Some(url) => url.clone(),
+            None => format!("ws://127.0.0.1:{}", self.server.port.unwrap_or(8765)),
+        }
+    }
+
+    /// Check the rules of the docs/protocol.md field summary: `api.base_url`
+    /// and `api.api_key` are set, the port, reload interval, hook timeout
+    /// and source limit are non-zero, chunking parameters are in range,
+    /// prices are not negative, and
+    /// `client.url` is a `ws://`, `wss://` or `unix://` URL.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let blank = |value: &Option<String>| value.as_deref().is_none_or(|v| v.trim().is_empty());
+        let mut problems = Vec::new();
+        if blank(&self.api.base_url) {
+            problems.push("api.base_url is required".to_string());
+        }
+        if blank(&self.api.api_key) {
+            problems.push("api.api_key is required".to_string());
+        }
+        if self.server.port == Some(0) {
+            problems.push("server.port must be between 1 and 65535".to_string());
+        }
+        if self.server.reload_interval == Some(0) {
+            problems.push("server.reload_interval must be positive".to_string());
+        }
+        if self.server.max_file_size_mb == Some(0) {
+            problems.push("server.max_file_size_mb must be positive".to_string());
+        }
+        if self
+            .server
+            .docs_language
+            .as_deref()
+            .is_some_and(|language| language.trim().is_empty())
+        {
+            problems.push("server.docs_language must not be empty".to_string());
+        }
+        if self.hooks.timeout == Some(0) {
+            problems.push("hooks.timeout must be positive".to_string());
+        }
+        if self.retrieval.max_sources == Some(0) {
+            problems.push("retrieval.max_sources must be positive".to_string());
+        }
+        let chunking = &self.retrieval.chunking;
+        chunking.defaults.check("retrieval.chunking", &mut problems);
+        for (directory, settings) in &chunking.directories {
+            let key = format!("retrieval.chunking.directories.{}", directory);
+            settings.check(&key, &mut problems);
+        }
+        for (model, price) in &self.prices {
+            if price.prompt < 0.0 || price.completion < 0.0 {
+                problems.push(format!("prices.{} must not be negative", model));
+            }
+        }
+        if let Some(url) = &self.client.url {
+            if !["ws://", "wss://", "unix://"]
+                .iter()
+                .any(|scheme| url.starts_with(scheme))
+            {
+                problems.push(format!(
+                    "client.url {} is not a ws://, wss:// or unix:// URL",
+                    url
+                ));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems.join("; ")))
+        }
+    }
+}
+
+/// Names of the `{{ name }}` placeholders in a config template, in order of
+/// first appearance.
+pub fn template_variables(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        let name = after[..end].trim();
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+        rest = &after[end + 2..];
+    }
+    names
+}
+
+/// Fill the `{{ name }}` placeholders of a config template from `vars` and
+/// validate the result. Returns the rendered YAML as written, so comments and
+/// sections only the server reads survive. Every placeholder needs a value;
+/// a later duplicate in `vars` wins.
+pub fn render_template(template: &str, vars: &[(String, String)]) -> Result<String, ConfigError> {
+    let rendered = fill_template(template, vars)?;
+    parse(&rendered)?.config.validate()?;
+    Ok(rendered)
+}
+
+/// Fill the `{{ name }}` placeholders of `template` from `vars`, without
+/// checking what the result is.
+pub(crate) fn fill_template(
+    template: &str,
+    vars: &[(String, String)],
+) -> Result<String, ConfigError> {
+    let value = |name: &str| vars.iter().rev().find(|(k, _)| k == name).map(|(_, v)| v);
+    let missing: Vec<String> = template_variables(template)
+        .into_iter()
+        .filter(|name| value(name).is_none())
+        .collect();
+    if !missing.is_empty() {
+        return Err(ConfigError::Invalid(format!(
+            "no value for template variable(s): {}",
+            missing.join(", ")
+        )));
+    }
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| ConfigError::Invalid("unclosed {{ in template".into()))?;
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(value(after[..end].trim()).map_or("", String::as_str));
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
 }
 
 /// Returns the default config file path: `~/.md-qa/config.yaml` (platform-specific).
@@ -59,10 +394,189 @@ fn home_dir() -> Option<PathBuf> {
     None
 }
 
+/// Largest config file `load` reads. Anything bigger is not a config (e.g.
+/// `--config` pointing at a log file) and would only be slow to parse.
+pub const MAX_CONFIG_BYTES: u64 = 1024 * 1024;
+
+/// A loaded config plus the problems that did not stop it from loading.
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: Config,
+    /// E.g. a key given twice in one mapping, of which the last value is used.
+    pub warnings: Vec<String>,
+}
+
 /// Load config from a YAML file. Path is typically `~/.md-qa/config.yaml`.
+/// Warnings are dropped; see `load_with_warnings`.
 pub fn load(path: &Path) -> Result<Config, ConfigError> {
+    load_with_warnings(path).map(|loaded| loaded.config)
+}
+
+/// Load config from a YAML file, keeping the warnings of `parse`.
+pub fn load_with_warnings(path: &Path) -> Result<LoadedConfig, ConfigError> {
+    let bytes = std::fs::metadata(path)
+        .map_err(|e| ConfigError::Io(e.to_string()))?
+        .len();
+    if bytes > MAX_CONFIG_BYTES {
+        return Err(ConfigError::TooLarge {
+            bytes,
+            limit: MAX_CONFIG_BYTES,
+        });
+    }
     let contents = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(e.to_string()))?;
-    serde_yaml::from_str(&contents).map_err(|e| ConfigError::Io(e.to_string()))
+    parse(&contents)
+}
+
+/// Parse config YAML. Anchors and aliases are expanded and `<<` merge keys
+/// applied. A key repeated within one mapping keeps its last value and adds
+/// a warning, instead of failing the whole file as serde_yaml would.
+pub fn parse(contents: &str) -> Result<LoadedConfig, ConfigError> {
+    let warnings = RefCell::new(Vec::new());
+    let seed = LenientValue {
+        path: String::new(),
+        warnings: &warnings,
+    };
+    let mut value = seed
+        .deserialize(serde_yaml::Deserializer::from_str(contents))
+        .map_err(ConfigError::parse)?;
+    let unmerged = value.clone();
+    value.apply_merge().map_err(ConfigError::parse)?;
+    let warnings = warnings.into_inner();
+
+    let config = if warnings.is_empty() && value == unmerged {
+        // Read as written, so schema errors name the line.
+        serde_yaml::from_str(contents).map_err(|e| ConfigError::Invalid(e.to_string()))?
+    } else {
+        // Lines of the rewritten document would not match the file.
+        let resolved = serde_yaml::to_string(&value).map_err(ConfigError::parse)?;
+        serde_yaml::from_str(&resolved).map_err(|e| {
+            let message = e.to_string();
+            let unlocated = match e.location() {
+                Some(_) => message.rsplit_once(" at line ").map(|(m, _)| m.to_string()),
+                None => None,
+            };
+            ConfigError::Invalid(unlocated.unwrap_or(message))
+        })?
+    };
+    Ok(LoadedConfig { config, warnings })
+}
+
+/// Builds a `Value` like its own `Deserialize` impl does, except that a
+/// repeated mapping key replaces the earlier value and is recorded in
+/// `warnings`. `path` is the dotted key path of the value, for the warning.
+struct LenientValue<'a> {
+    path: String,
+    warnings: &'a RefCell<Vec<String>>,
+}
+
+impl<'a> LenientValue<'a> {
+    fn child(&self, path: String) -> LenientValue<'a> {
+        LenientValue {
+            path,
+            warnings: self.warnings,
+        }
+    }
+
+    fn key_path(&self, key: &Value) -> String {
+        let name = match key {
+            Value::String(s) => s.clone(),
+            other => serde_yaml::to_string(other)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        };
+        if self.path.is_empty() {
+            name
+        } else {
+            format!("{}.{}", self.path, name)
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for LenientValue<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for LenientValue<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("any YAML value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) =
+            seq.next_element_seed(self.child(format!("{}[{}]", self.path, items.len())))?
+        {
+            items.push(item);
+        }
+        Ok(Value::Sequence(items))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut mapping = Mapping::new();
+        while let Some(key) = map.next_key::<Value>()? {
+            let path = self.key_path(&key);
+            let value = map.next_value_seed(self.child(path.clone()))?;
+            if mapping.insert(key, value).is_some() {
+                self.warnings
+                    .borrow_mut()
+                    .push(format!("duplicate key `{}`; the last value is used", path));
+            }
+        }
+        Ok(Value::Mapping(mapping))
+    }
+
+    fn visit_enum<A: de::EnumAccess<'de>>(self, data: A) -> Result<Value, A::Error> {
+        let (tag, variant) = data.variant::<String>()?;
+        let value =
+            de::VariantAccess::newtype_variant_seed(variant, self.child(self.path.clone()))?;
+        Ok(Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag),
+            value,
+        })))
+    }
 }
 
 /// Save config to a YAML file. Creates parent directory if missing.
@@ -76,16 +590,57 @@ pub fn save(path: &Path, config: &Config) -> Result<(), ConfigError> {
     std::fs::write(path, contents).map_err(|e| ConfigError::Io(e.to_string()))
 }
 
+/// Save already rendered YAML (e.g. from `render_template`) as written.
+/// Creates the parent directory if missing.
+pub fn save_rendered(path: &Path, contents: &str) -> Result<(), ConfigError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| ConfigError::Io(e.to_string()))?;
+    }
+    std::fs::write(path, contents).map_err(|e| ConfigError::Io(e.to_string()))
+}
+
 /// Config load/save error.
 #[derive(Debug)]
 pub enum ConfigError {
     Io(String),
+    /// The file is not valid YAML. `line` and `column` (1-based) point at
+    /// the problem when known.
+    Parse {
+        message: String,
+        line: Option<usize>,
+        column: Option<usize>,
+    },
+    /// The file is bigger than `MAX_CONFIG_BYTES`.
+    TooLarge {
+        bytes: u64,
+        limit: u64,
+    },
+    /// The config (or a template rendering of it) breaks the schema rules.
+    Invalid(String),
+}
+
+impl ConfigError {
+    fn parse(e: serde_yaml::Error) -> Self {
+        let location = e.location();
+        ConfigError::Parse {
+            message: e.to_string(),
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+        }
+    }
 }
 
 impl std::fmt::Display for ConfigError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ConfigError::Io(s) => write!(f, "IO error: {}", s),
+            ConfigError::Parse { message, .. } => write!(f, "invalid YAML: {}", message),
+            ConfigError::TooLarge { bytes, limit } => write!(
+                f,
+                "config file is {} bytes, more than the limit of {} bytes",
+                bytes, limit
+            ),
+            ConfigError::Invalid(s) => write!(f, "invalid config: {}", s),
         }
     }
 }
@@ -0,0 +1,220 @@
+//! Console output for the CLI. Answer text is flushed as it streams and
+//! written in an encoding the terminal can show: on Windows, ANSI escape
+//! sequences are enabled on the console, and output redirected from a
+//! Windows-1252 console (e.g. piped in PowerShell) is re-encoded to match.
+
+use std::io::{self, Write};
+
+/// Byte encoding of console output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// Windows-1252; characters it lacks are written as `?`.
+    Windows1252,
+}
+
+/// Characters of Windows-1252 bytes 0x80..=0x9F; the rest of the upper half
+/// matches Latin-1. `None` marks the five unassigned bytes.
+const WINDOWS_1252_HIGH: [Option<char>; 32] = [
+    Some('€'),
+    None,
+    Some('‚'),
+    Some('ƒ'),
+    Some('„'),
+    Some('…'),
+    Some('†'),
+    Some('‡'),
+    Some('ˆ'),
+    Some('‰'),
+    Some('Š'),
+    Some('‹'),
+    Some('Œ'),
+    None,
+    Some('Ž'),
+    None,
+    None,
+    Some('‘'),
+    Some('’'),
+    Some('“'),
+    Some('”'),
+    Some('•'),
+    Some('–'),
+    Some('—'),
+    Some('˜'),
+    Some('™'),
+    Some('š'),
+    Some('›'),
+    Some('œ'),
+    None,
+    Some('ž'),
+    Some('Ÿ'),
+];
+
+impl Encoding {
+    /// Encoding for a Windows console code page: Windows-1252 for 1252,
+    /// otherwise UTF-8.
+    pub fn from_code_page(code_page: u32) -> Self {
+        match code_page {
+            1252 => Encoding::Windows1252,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    /// Append `text` in this encoding to `out`.
+    pub fn encode(self, text: &str, out: &mut Vec<u8>) {
+        match self {
+            Encoding::Utf8 => out.extend_from_slice(text.as_bytes()),
+            Encoding::Windows1252 => out.extend(text.chars().map(windows_1252_byte)),
+        }
+    }
+}
+
+fn windows_1252_byte(c: char) -> u8 {
+    match u32::from(c) {
+        code @ (0..=0x7F | 0xA0..=0xFF) => code as u8,
+        _ => WINDOWS_1252_HIGH
+            .iter()
+            .position(|&high| high == Some(c))
+            .map_or(b'?', |i| 0x80 + i as u8),
+    }
+}
+
+/// A writer that re-encodes UTF-8 text to an `Encoding` and flushes after
+/// every write, so each streamed chunk shows up at once.
+pub struct Console<W: Write> {
+    out: W,
+    encoding: Encoding,
+    ansi: bool,
+    /// Start of a UTF-8 sequence split across writes.
+    pending: Vec<u8>,
+}
+
+impl Console<io::Stdout> {
+    /// Standard output, set up for the terminal it is attached to.
+    pub fn stdout() -> Self {
+        let (encoding, ansi) = sys::setup_stdout();
+        Self::new(io::stdout(), encoding).with_ansi(ansi)
+    }
+}
+
+impl<W: Write> Console<W> {
+    pub fn new(out: W, encoding: Encoding) -> Self {
+        Self {
+            out,
+            encoding,
+            ansi: false,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn with_ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Whether the terminal interprets ANSI escape sequences (colours,
+    /// cursor movement).
+    pub fn ansi(&self) -> bool {
+        self.ansi
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Write for Console<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.encoding == Encoding::Utf8 {
+            self.out.write_all(buf)?;
+            self.out.flush()?;
+            return Ok(buf.len());
+        }
+        self.pending.extend_from_slice(buf);
+        let mut encoded = Vec::with_capacity(self.pending.len());
+        let mut rest = &self.pending[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    self.encoding.encode(text, &mut encoded);
+                    rest = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    let valid = std::str::from_utf8(valid).expect("checked by from_utf8");
+                    self.encoding.encode(valid, &mut encoded);
+                    match e.error_len() {
+                        Some(len) => {
+                            encoded.push(b'?');
+                            rest = &after[len..];
+                        }
+                        // Incomplete sequence; the next write has the rest.
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.pending = rest.to_vec();
+        self.out.write_all(&encoded)?;
+        self.out.flush()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::Encoding;
+    use std::ffi::c_void;
+
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleMode(console: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: *mut c_void, mode: u32) -> i32;
+        fn GetConsoleOutputCP() -> u32;
+    }
+
+    /// On a console, turn on ANSI escape processing; the standard library
+    /// writes UTF-16 to consoles, so the text stays UTF-8. Redirected output
+    /// follows the console code page.
+    pub fn setup_stdout() -> (Encoding, bool) {
+        // SAFETY: Win32 calls on this process's standard output handle; an
+        // invalid or non-console handle makes GetConsoleMode fail.
+        unsafe {
+            let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+            let mut mode = 0;
+            if !handle.is_null() && GetConsoleMode(handle, &mut mode) != 0 {
+                let ansi = mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                    || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0;
+                return (Encoding::Utf8, ansi);
+            }
+            (Encoding::from_code_page(GetConsoleOutputCP()), false)
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod sys {
+    use super::Encoding;
+    use std::io::IsTerminal;
+
+    /// Unix terminals take UTF-8 and ANSI escapes as they are.
+    pub fn setup_stdout() -> (Encoding, bool) {
+        (Encoding::Utf8, std::io::stdout().is_terminal())
+    }
+}
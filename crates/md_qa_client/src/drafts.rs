@@ -0,0 +1,64 @@
+//! Unsent chat input (`drafts.json`, next to the config file), kept per chat
+//! session so a long question survives an app or machine restart.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::querylog;
+
+/// A question typed but not sent yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Draft {
+    /// Chat session the draft belongs to, e.g. `"chat"`.
+    pub session: String,
+    pub text: String,
+    /// Unix time in seconds of the last edit.
+    pub updated: u64,
+}
+
+/// Drafts path for a config file: `drafts.json` in the same directory.
+pub fn path_for_config(config_path: &Path) -> PathBuf {
+    config_path.with_file_name("drafts.json")
+}
+
+/// Read all drafts from `path`, most recently edited first. A missing file,
+/// or one that does not parse, has no drafts.
+pub fn load(path: &Path) -> std::io::Result<Vec<Draft>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut drafts: Vec<Draft> = serde_json::from_str(&contents).unwrap_or_default();
+    drafts.sort_by_key(|d| std::cmp::Reverse(d.updated));
+    Ok(drafts)
+}
+
+/// The draft of `session` in `path`, if it has one.
+pub fn get(path: &Path, session: &str) -> std::io::Result<Option<Draft>> {
+    Ok(load(path)?.into_iter().find(|d| d.session == session))
+}
+
+/// Store `text` as the draft of `session`, replacing its previous one; blank
+/// text (e.g. after the question was sent) removes the draft. The file is
+/// replaced as a whole, so a crash mid-write keeps the previous drafts.
+pub fn save(path: &Path, session: &str, text: &str) -> std::io::Result<()> {
+    let mut drafts = load(path)?;
+    drafts.retain(|d| d.session != session);
+    if !text.trim().is_empty() {
+        drafts.insert(
+            0,
+            Draft {
+                session: session.to_string(),
+                text: text.to_string(),
+                updated: querylog::now(),
+            },
+        );
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let partial = path.with_extension("json.tmp");
+    std::fs::write(&partial, serde_json::to_vec_pretty(&drafts)?)?;
+    std::fs::rename(&partial, path)
+}
@@ -0,0 +1,115 @@
+//! Export of a conversation for sharing: a pre-filled GitHub/GitLab issue
+//! body (e.g. to report a gap in the docs) or a Markdown snippet for the
+//! clipboard. Both come from templates with `{{ question }}`, `{{ answer }}`,
+//! `{{ sources }}` and `{{ conversation }}` placeholders; the `export`
+//! section of the config can replace the built-in ones.
+
+use crate::config::{self, ConfigError, ExportSection};
+use crate::history::Conversation;
+
+/// Built-in template for `ExportFormat::Issue`.
+pub const DEFAULT_ISSUE_TEMPLATE: &str = "### Question
+
+{{ question }}
+
+### Answer
+
+{{ answer }}
+
+### Sources
+
+{{ sources }}
+
+<details>
+<summary>Full conversation</summary>
+
+{{ conversation }}
+</details>
+
+### What the docs are missing
+
+<!-- What was wrong, missing or hard to find? -->
+";
+
+/// Built-in template for `ExportFormat::Snippet`.
+pub const DEFAULT_SNIPPET_TEMPLATE: &str = "**Q:** {{ question }}
+
+{{ answer }}
+
+Sources:
+{{ sources }}
+";
+
+/// What to export a conversation as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Body of a GitHub/GitLab issue.
+    Issue,
+    /// Markdown snippet to paste elsewhere.
+    Snippet,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "issue" => Ok(ExportFormat::Issue),
+            "snippet" => Ok(ExportFormat::Snippet),
+            _ => Err(format!(
+                "invalid export format: {} (expected issue or snippet)",
+                s
+            )),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// Template for this format: the file configured in `section`, else the
+    /// built-in one.
+    pub fn template(self, section: &ExportSection) -> Result<String, ConfigError> {
+        let (path, default) = match self {
+            ExportFormat::Issue => (&section.issue_template, DEFAULT_ISSUE_TEMPLATE),
+            ExportFormat::Snippet => (&section.snippet_template, DEFAULT_SNIPPET_TEMPLATE),
+        };
+        match path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| ConfigError::Io(format!("failed to read {}: {}", path, e))),
+            None => Ok(default.to_string()),
+        }
+    }
+
+    /// Render the active path of `conversation` with this format's template.
+    pub fn render(
+        self,
+        section: &ExportSection,
+        conversation: &Conversation,
+    ) -> Result<String, ConfigError> {
+        render(&self.template(section)?, conversation)
+    }
+}
+
+/// Fill `template` from the active path of `conversation`: `question`,
+/// `answer` and `sources` (a Markdown list) of its last turn, and the whole
+/// path as Markdown in `conversation`. Other placeholders are an error.
+pub fn render(template: &str, conversation: &Conversation) -> Result<String, ConfigError> {
+    let path = conversation.active_path();
+    let last = path.last();
+    let sources = match last {
+        Some(turn) if !turn.sources.is_empty() => turn
+            .sources
+            .iter()
+            .map(|source| format!("- {}", source))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => "_None_".to_string(),
+    };
+    let vars = [
+        ("question", last.map_or("", |turn| turn.question.trim())),
+        ("answer", last.map_or("", |turn| turn.answer.trim())),
+        ("sources", &sources),
+        ("conversation", conversation.to_markdown().trim_end()),
+    ]
+    .map(|(name, value)| (name.to_string(), value.to_string()));
+    config::fill_template(template, &vars)
+}
@@ -0,0 +1,276 @@
+//! Conversation history modelled as a tree of question/answer turns.
+//! Editing or regenerating a past turn forks a sibling branch instead of
+//! overwriting it; export renders only the active path (root → active leaf).
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Usage;
+use crate::usage::{PriceTable, UsageTotals};
+
+/// Index of a turn within a `Conversation`.
+pub type TurnId = usize;
+
+/// One question/answer exchange in the conversation tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    pub id: TurnId,
+    /// Parent turn; `None` for turns at the root of the conversation.
+    pub parent: Option<TurnId>,
+    pub question: String,
+    pub answer: String,
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Tokens the answer used, when the server reported them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// The connection to the server was lost before the answer finished;
+    /// `answer` is what arrived until then.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+}
+
+/// Summary of one branch (a leaf turn and the path leading to it).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Branch {
+    /// Leaf turn that ends this branch.
+    pub leaf: TurnId,
+    /// Number of turns from the root to the leaf (inclusive).
+    pub depth: usize,
+    /// Question of the leaf turn, for display in branch pickers.
+    pub question: String,
+    /// True when this branch is the active one.
+    pub active: bool,
+}
+
+/// History operation error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryError {
+    UnknownTurn(TurnId),
+    NotALeaf(TurnId),
+}
+
+impl std::fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryError::UnknownTurn(id) => write!(f, "unknown turn: {}", id),
+            HistoryError::NotALeaf(id) => write!(f, "turn {} is not the end of a branch", id),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+/// Characters of each earlier answer kept in `Conversation::retrieval_context`.
+const CONTEXT_ANSWER_CHARS: usize = 200;
+
+/// Tree of turns plus the currently active leaf.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Conversation {
+    turns: Vec<Turn>,
+    active_leaf: Option<TurnId>,
+}
+
+impl Conversation {
+    pub const fn new() -> Self {
+        Self {
+            turns: Vec::new(),
+            active_leaf: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    pub fn get(&self, id: TurnId) -> Option<&Turn> {
+        self.turns.get(id)
+    }
+
+    /// Leaf of the active branch, or `None` for an empty conversation.
+    pub fn active_leaf(&self) -> Option<TurnId> {
+        self.active_leaf
+    }
+
+    /// Append a turn after the active leaf and make it the new active leaf.
+    pub fn push(&mut self, question: &str, answer: &str, sources: Vec<String>) -> TurnId {
+        self.insert(self.active_leaf, question, answer, sources)
+    }
+
+    /// Add a sibling of turn `id` (same parent) and make it the active leaf.
+    /// Used for edit/regenerate: the original turn and its descendants stay
+    /// reachable as a separate branch.
+    pub fn fork(
+        &mut self,
+        id: TurnId,
+        question: &str,
+        answer: &str,
+        sources: Vec<String>,
+    ) -> Result<TurnId, HistoryError> {
+        let parent = self.get(id).ok_or(HistoryError::UnknownTurn(id))?.parent;
+        Ok(self.insert(parent, question, answer, sources))
+    }
+
+    /// Make the branch ending at leaf `id` the active one.
+    pub fn switch_to(&mut self, id: TurnId) -> Result<(), HistoryError> {
+        if self.get(id).is_none() {
+            return Err(HistoryError::UnknownTurn(id));
+        }
+        if self.turns.iter().any(|t| t.parent == Some(id)) {
+            return Err(HistoryError::NotALeaf(id));
+        }
+        self.active_leaf = Some(id);
+        Ok(())
+    }
+
+    /// Record the token usage reported for turn `id`.
+    pub fn set_usage(&mut self, id: TurnId, usage: Usage) -> Result<(), HistoryError> {
+        let turn = self
+            .turns
+            .get_mut(id)
+            .ok_or(HistoryError::UnknownTurn(id))?;
+        turn.usage = Some(usage);
+        Ok(())
+    }
+
+    /// Mark the answer of turn `id` as cut off by a lost connection.
+    pub fn set_interrupted(&mut self, id: TurnId) -> Result<(), HistoryError> {
+        let turn = self
+            .turns
+            .get_mut(id)
+            .ok_or(HistoryError::UnknownTurn(id))?;
+        turn.interrupted = true;
+        Ok(())
+    }
+
+    /// Usage and estimated cost of every turn, on all branches: abandoned
+    /// branches were paid for too.
+    pub fn usage(&self, prices: &PriceTable, default_model: Option<&str>) -> UsageTotals {
+        let mut totals = UsageTotals::default();
+        for usage in self.turns.iter().filter_map(|t| t.usage.as_ref()) {
+            totals.add(usage, prices, default_model);
+        }
+        totals
+    }
+
+    /// Turns from the root to the active leaf, in order.
+    pub fn active_path(&self) -> Vec<&Turn> {
+        let mut path = Vec::new();
+        let mut current = self.active_leaf;
+        while let Some(id) = current {
+            let turn = &self.turns[id];
+            path.push(turn);
+            current = turn.parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// The last `max_turns` turns on the path ending at `leaf`, oldest first.
+    pub fn recent_turns(&self, leaf: Option<TurnId>, max_turns: usize) -> Vec<&Turn> {
+        let mut recent = Vec::new();
+        let mut current = leaf;
+        while let Some(turn) = current.and_then(|id| self.get(id)) {
+            if recent.len() == max_turns {
+                break;
+            }
+            recent.push(turn);
+            current = turn.parent;
+        }
+        recent.reverse();
+        recent
+    }
+
+    /// Condensed form of the last `max_turns` turns on the path ending at
+    /// `leaf` (each question plus the start of its answer), for building a
+    /// retrieval query for a follow-up. `None` when there is no history.
+    pub fn retrieval_context(&self, leaf: Option<TurnId>, max_turns: usize) -> Option<String> {
+        let recent = self.recent_turns(leaf, max_turns);
+        if recent.is_empty() {
+            return None;
+        }
+        let lines: Vec<String> = recent
+            .iter()
+            .map(|turn| {
+                let answer: String = turn.answer.chars().take(CONTEXT_ANSWER_CHARS).collect();
+                format!("Q: {}\nA: {}", turn.question.trim(), answer.trim())
+            })
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    /// All branches (one per leaf turn), ordered by leaf creation.
+    pub fn branches(&self) -> Vec<Branch> {
+        let mut has_children = vec![false; self.turns.len()];
+        for turn in &self.turns {
+            if let Some(parent) = turn.parent {
+                has_children[parent] = true;
+            }
+        }
+        self.turns
+            .iter()
+            .filter(|t| !has_children[t.id])
+            .map(|t| Branch {
+                leaf: t.id,
+                depth: self.depth(t.id),
+                question: t.question.clone(),
+                active: self.active_leaf == Some(t.id),
+            })
+            .collect()
+    }
+
+    /// Render the active path as Markdown (other branches are omitted).
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for turn in self.active_path() {
+            out.push_str("## Q: ");
+            out.push_str(turn.question.trim());
+            out.push_str("\n\n");
+            out.push_str(turn.answer.trim());
+            if turn.interrupted {
+                out.push_str(" _(interrupted)_");
+            }
+            out.push('\n');
+            if !turn.sources.is_empty() {
+                out.push_str("\nSources:\n");
+                for src in &turn.sources {
+                    out.push_str("- ");
+                    out.push_str(src);
+                    out.push('\n');
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn insert(
+        &mut self,
+        parent: Option<TurnId>,
+        question: &str,
+        answer: &str,
+        sources: Vec<String>,
+    ) -> TurnId {
+        let id = self.turns.len();
+        self.turns.push(Turn {
+            id,
+            parent,
+            question: question.to_string(),
+            answer: answer.to_string(),
+            sources,
+            usage: None,
+            interrupted: false,
+        });
+        self.active_leaf = Some(id);
+        id
+    }
+
+    fn depth(&self, id: TurnId) -> usize {
+        let mut depth = 0;
+        let mut current = Some(id);
+        while let Some(i) = current {
+            depth += 1;
+            current = self.turns[i].parent;
+        }
+        depth
+    }
+}
@@ -0,0 +1,155 @@
+//! User hook scripts run around a question (`hooks.pre_query` and
+//! `hooks.post_answer` in the config), for custom logging, notifications or
+//! rewriting questions without changing the clients.
+
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{HookFailure, HooksSection};
+use crate::messages::Source;
+
+/// Limit on a hook's run time unless the config sets `hooks.timeout`.
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The configured hooks; either may be unset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hooks {
+    pub pre_query: Option<String>,
+    pub post_answer: Option<String>,
+    pub timeout: Duration,
+    pub on_failure: HookFailure,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self::from_config(&HooksSection::default())
+    }
+}
+
+impl Hooks {
+    pub fn from_config(section: &HooksSection) -> Self {
+        Self {
+            pre_query: section.pre_query.clone(),
+            post_answer: section.post_answer.clone(),
+            timeout: section
+                .timeout
+                .map_or(DEFAULT_HOOK_TIMEOUT, Duration::from_secs),
+            on_failure: section.on_failure.unwrap_or_default(),
+        }
+    }
+
+    /// Run `pre_query` with `question` on stdin. Returns the question to
+    /// send: the hook's trimmed output, or `question` when it prints nothing
+    /// (or no hook is set).
+    pub async fn pre_query(&self, question: &str) -> Result<String, HookError> {
+        let Some(command) = &self.pre_query else {
+            return Ok(question.to_string());
+        };
+        let output = run("pre_query", command, question.as_bytes(), self.timeout).await?;
+        let rewritten = output.trim();
+        Ok(if rewritten.is_empty() {
+            question.to_string()
+        } else {
+            rewritten.to_string()
+        })
+    }
+
+    /// Run `post_answer` with the question, answer and source paths as a
+    /// JSON object on stdin. Its output is ignored.
+    pub async fn post_answer(
+        &self,
+        question: &str,
+        answer: &str,
+        sources: &[Source],
+    ) -> Result<(), HookError> {
+        let Some(command) = &self.post_answer else {
+            return Ok(());
+        };
+        let input = serde_json::json!({
+            "question": question,
+            "answer": answer,
+            "sources": sources.iter().map(|s| &s.path).collect::<Vec<_>>(),
+        });
+        run(
+            "post_answer",
+            command,
+            input.to_string().as_bytes(),
+            self.timeout,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Run `command` through the platform shell with `input` on stdin and
+/// return its stdout.
+async fn run(
+    hook: &'static str,
+    command: &str,
+    input: &[u8],
+    timeout: Duration,
+) -> Result<String, HookError> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = tokio::process::Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| HookError::Spawn(hook, e))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let run = async {
+        // A hook that exits without reading its input is fine.
+        let _ = stdin.write_all(input).await;
+        drop(stdin);
+        child.wait_with_output().await
+    };
+    let output = match tokio::time::timeout(timeout, run).await {
+        Ok(output) => output.map_err(|e| HookError::Spawn(hook, e))?,
+        Err(_) => return Err(HookError::Timeout(hook, timeout)),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(HookError::Failed(hook, output.status, stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A hook that could not run, failed or took too long; the first field
+/// names the hook (`"pre_query"` or `"post_answer"`).
+#[derive(Debug)]
+pub enum HookError {
+    Spawn(&'static str, std::io::Error),
+    /// Non-zero exit, with what the hook wrote to stderr.
+    Failed(&'static str, ExitStatus, String),
+    Timeout(&'static str, Duration),
+}
+
+impl std::fmt::Display for HookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookError::Spawn(hook, e) => write!(f, "{} hook could not run: {}", hook, e),
+            HookError::Failed(hook, status, stderr) if stderr.is_empty() => {
+                write!(f, "{} hook failed ({})", hook, status)
+            }
+            HookError::Failed(hook, status, stderr) => {
+                write!(f, "{} hook failed ({}): {}", hook, status, stderr)
+            }
+            HookError::Timeout(hook, limit) => {
+                write!(f, "{} hook timed out after {}s", hook, limit.as_secs())
+            }
+        }
+    }
+}
+
+impl std::error::Error for HookError {}
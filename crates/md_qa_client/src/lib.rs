@@ -1,9 +1,74 @@
 //! Shared Markdown Q&A client library (config, WebSocket protocol, stream handling).
 //! Used by the Tauri GUI and the Rust TUI.
+//!
+//! Cargo features pick how much of it to build:
+//!
+//! - `protocol`: `messages`, `wire`, `history` and `usage`, the types of the
+//!   protocol; needs only serde.
+//! - `core`: `protocol` plus `config`, `console`, `drafts`, `export` and
+//!   `querylog`, for tools that read the config and local files without an
+//!   async runtime.
+//! - `transport` (default): `core` plus the async WebSocket client on tokio
+//!   and tungstenite, and `service`, `hooks`, `proxy` and `testing` on top
+//!   of it.
 
+#[cfg(feature = "transport")]
 pub mod client;
+#[cfg(feature = "core")]
 pub mod config;
+#[cfg(feature = "core")]
+pub mod console;
+#[cfg(feature = "core")]
+pub mod drafts;
+#[cfg(feature = "core")]
+pub mod export;
+#[cfg(feature = "protocol")]
+pub mod history;
+#[cfg(feature = "transport")]
+pub mod hooks;
+#[cfg(feature = "protocol")]
 pub mod messages;
+#[cfg(feature = "transport")]
+pub mod proxy;
+#[cfg(feature = "core")]
+pub mod querylog;
+#[cfg(feature = "transport")]
+pub mod service;
+#[cfg(feature = "transport")]
+pub mod testing;
+#[cfg(feature = "protocol")]
+pub mod usage;
+#[cfg(feature = "protocol")]
+pub mod wire;
 
-pub use client::{connect, Client, ClientError, StreamEvent};
-pub use config::{default_config_path, ApiSection, Config, ConfigError, ServerSection};
+#[cfg(feature = "transport")]
+pub use client::{
+    cite_answer, connect, connect_with_options, group_sources, AnswerSpan, ChunkMeta, Client,
+    ClientBuilder, ClientError, ClientOptions, ConnectionEvent, FrameDirection, FrameTap,
+    RetryPolicy, ServerSummary, Side, SourceGroup, StreamCollector, StreamEvent, TimeoutKind,
+};
+#[cfg(feature = "core")]
+pub use config::{
+    default_config_path, ApiSection, ChunkSettings, ChunkingSection, ClientSection, Config,
+    ConfigError, ExportSection, HookFailure, HooksSection, RetrievalSection, ServerSection,
+};
+#[cfg(feature = "core")]
+pub use console::{Console, Encoding};
+#[cfg(feature = "core")]
+pub use drafts::Draft;
+#[cfg(feature = "core")]
+pub use export::ExportFormat;
+#[cfg(feature = "protocol")]
+pub use history::{Branch, Conversation, HistoryError, Turn, TurnId};
+#[cfg(feature = "transport")]
+pub use hooks::{HookError, Hooks};
+#[cfg(feature = "transport")]
+pub use proxy::{Proxy, ProxyKind};
+#[cfg(feature = "core")]
+pub use querylog::{Gap, LogEntry};
+#[cfg(feature = "transport")]
+pub use service::{AnswerStream, QaService, QaServiceBuilder};
+#[cfg(feature = "protocol")]
+pub use usage::{embedding_cost, Price, PriceTable, UsageTotals, LARGE_RELOAD_TOKENS};
+#[cfg(feature = "protocol")]
+pub use wire::WireEncoding;
@@ -1,116 +1,1561 @@
 //! WebSocket message types matching docs/protocol.md. Client ↔ server JSON.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::config::ChunkingSection;
+use crate::wire::WireEncoding;
+
+/// Retrieval preference hint: favour code-block chunks, prose chunks, or let the server decide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Prefer {
+    Code,
+    Prose,
+    Auto,
+}
+
+impl std::str::FromStr for Prefer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "code" => Ok(Prefer::Code),
+            "prose" => Ok(Prefer::Prose),
+            "auto" => Ok(Prefer::Auto),
+            _ => Err(format!(
+                "invalid prefer value: {} (expected code, prose or auto)",
+                s
+            )),
+        }
+    }
+}
+
+/// Per-question retrieval and generation tuning. Unset fields are left out
+/// of the query, so the server uses its defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryOptions {
+    /// Number of chunks to retrieve.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// LLM sampling temperature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Upper bound on the answer length, in tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Language to answer in (e.g. `"en"`, `"de"`), whatever the question's language.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// LLM to answer with, instead of the server's configured model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Number of alternative answers to stream, for servers that support
+    /// candidates (see `ChunkMeta::candidate`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<u32>,
+}
+
+/// Which documents a question may draw on. Within a kind any entry may
+/// match; every kind that is set must match. Empty means all documents.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryFilters {
+    /// Directory prefixes, e.g. `docs/api`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directories: Vec<String>,
+    /// Tags from the documents' front matter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Filename globs, e.g. `*.adr.md`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub globs: Vec<String>,
+}
+
+impl QueryFilters {
+    pub fn is_empty(&self) -> bool {
+        self.directories.is_empty() && self.tags.is_empty() && self.globs.is_empty()
+    }
+
+    /// Add a filter written as `dir:PREFIX`, `tag:NAME` or `glob:PATTERN`
+    /// (the CLI's `--filter`).
+    pub fn add(&mut self, filter: &str) -> Result<(), String> {
+        let invalid = || {
+            format!(
+                "invalid filter: {} (expected dir:PREFIX, tag:NAME or glob:PATTERN)",
+                filter
+            )
+        };
+        let (kind, value) = filter.split_once(':').ok_or_else(invalid)?;
+        if value.is_empty() {
+            return Err(invalid());
+        }
+        let list = match kind {
+            "dir" => &mut self.directories,
+            "tag" => &mut self.tags,
+            "glob" => &mut self.globs,
+            _ => return Err(invalid()),
+        };
+        list.push(value.to_string());
+        Ok(())
+    }
+}
+
+/// Token usage and server-side time of one answer, from STREAM_END; each
+/// part is `None` when the server does not report it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryStats {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Milliseconds from the server receiving the query to the end of the
+    /// answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+}
+
+impl QueryStats {
+    pub fn is_empty(&self) -> bool {
+        self.usage.is_none() && self.elapsed_ms.is_none()
+    }
+}
+
 /// Client → server: query message.
 #[derive(Debug, Clone, Serialize)]
 pub struct QueryMessage<'a> {
     #[serde(rename = "type")]
     pub typ: &'static str,
+    /// Unique per message (see `next_query_id`); servers echo it on their
+    /// replies so a late reply to an earlier query can be told apart.
+    pub id: u64,
     pub question: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub index: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefer: Option<Prefer>,
+    /// Ask the server to run a grounding check on the answer (see `Grounding`).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub verify: bool,
+    #[serde(flatten)]
+    pub options: QueryOptions,
+    /// Documents to answer from; all when empty.
+    #[serde(skip_serializing_if = "QueryFilters::is_empty")]
+    pub filters: QueryFilters,
+    /// Text to retrieve chunks with instead of `question`, e.g. the question
+    /// plus condensed earlier turns so follow-ups find the right documents.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retrieval_query: Option<String>,
+    /// Earlier question/answer pairs of the conversation, oldest first, so
+    /// the answer can refer back to them ("expand on point 2").
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<HistoryTurn>,
+    /// Material the question is about (e.g. a diff), shown to the model
+    /// next to the retrieved chunks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<String>,
+}
+
+/// One earlier exchange sent in `QueryMessage::history`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryTurn {
+    pub question: String,
+    pub answer: String,
+}
+
+impl From<&crate::history::Turn> for HistoryTurn {
+    fn from(turn: &crate::history::Turn) -> Self {
+        Self {
+            question: turn.question.clone(),
+            answer: turn.answer.clone(),
+        }
+    }
+}
+
+/// A fresh `QueryMessage::id`, counting up from 1 for this process.
+pub fn next_query_id() -> u64 {
+    static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+    NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
 }
 
 impl<'a> QueryMessage<'a> {
     pub fn new(question: &'a str, index: Option<&'a str>) -> Self {
         Self {
             typ: "query",
+            id: next_query_id(),
             question,
             index,
+            prefer: None,
+            verify: false,
+            options: QueryOptions::default(),
+            filters: QueryFilters::default(),
+            retrieval_query: None,
+            history: Vec::new(),
+            attachment: None,
+        }
+    }
+
+    pub fn with_prefer(mut self, prefer: Option<Prefer>) -> Self {
+        self.prefer = prefer;
+        self
+    }
+
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    pub fn with_options(mut self, options: QueryOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_filters(mut self, filters: QueryFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Retrieve with the question preceded by `context` (see
+    /// `Conversation::retrieval_context`); no-op for `None`.
+    pub fn with_history_context(mut self, context: Option<String>) -> Self {
+        self.retrieval_query = context.map(|c| format!("{}\nQ: {}", c, self.question));
+        self
+    }
+
+    pub fn with_attachment(mut self, attachment: Option<String>) -> Self {
+        self.attachment = attachment;
+        self
+    }
+
+    /// Send `turns` (oldest first) as the conversation so far.
+    pub fn with_history<'t>(
+        mut self,
+        turns: impl IntoIterator<Item = &'t crate::history::Turn>,
+    ) -> Self {
+        self.history = turns.into_iter().map(HistoryTurn::from).collect();
+        self
+    }
+}
+
+/// Client → server: one page of the documents in an index.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListDocumentsMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<&'a str>,
+    /// Zero-based page number.
+    pub page: usize,
+    pub page_size: usize,
+}
+
+impl<'a> ListDocumentsMessage<'a> {
+    pub fn new(index: Option<&'a str>, page: usize, page_size: usize) -> Self {
+        Self {
+            typ: "list_documents",
+            index,
+            page,
+            page_size,
+        }
+    }
+}
+
+/// Client → server: the indexed chunks of one document.
+#[derive(Debug, Clone, Serialize)]
+pub struct GetChunksMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub path: &'a str,
+}
+
+impl<'a> GetChunksMessage<'a> {
+    pub fn new(path: &'a str) -> Self {
+        Self {
+            typ: "get_chunks",
+            path,
+        }
+    }
+}
+
+/// Client → server: the links to and from one document.
+#[derive(Debug, Clone, Serialize)]
+pub struct GetLinksMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub path: &'a str,
+}
+
+impl<'a> GetLinksMessage<'a> {
+    pub fn new(path: &'a str) -> Self {
+        Self {
+            typ: "get_links",
+            path,
+        }
+    }
+}
+
+/// Client → server: ask for follow-up questions to an answer, grounded in
+/// its sources.
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestFollowupsMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub question: &'a str,
+    pub answer: &'a str,
+    /// Paths of the answer's sources.
+    pub sources: Vec<&'a str>,
+    /// Number of questions wanted.
+    pub count: usize,
+}
+
+impl<'a> SuggestFollowupsMessage<'a> {
+    pub fn new(question: &'a str, answer: &'a str, sources: &'a [Source], count: usize) -> Self {
+        Self {
+            typ: "suggest_followups",
+            question,
+            answer,
+            sources: sources.iter().map(|s| s.path.as_str()).collect(),
+            count,
+        }
+    }
+}
+
+/// Client → server: stop generating the answer to the query with id
+/// `request_id` (its `QueryMessage::id`).
+#[derive(Debug, Clone, Serialize)]
+pub struct CancelMessage {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub request_id: u64,
+}
+
+impl CancelMessage {
+    pub fn new(request_id: u64) -> Self {
+        Self {
+            typ: "cancel",
+            request_id,
+        }
+    }
+}
+
+/// Client → server: rebuild an index from the current Markdown files, e.g.
+/// after editing them, without restarting the server.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<&'a str>,
+}
+
+impl<'a> ReloadMessage<'a> {
+    pub fn new(index: Option<&'a str>) -> Self {
+        Self {
+            typ: "reload",
+            index,
+        }
+    }
+}
+
+/// Client → server: counts, model, age and size of an index.
+#[derive(Debug, Clone, Serialize)]
+pub struct GetIndexStatsMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<&'a str>,
+}
+
+impl<'a> GetIndexStatsMessage<'a> {
+    pub fn new(index: Option<&'a str>) -> Self {
+        Self {
+            typ: "index_stats",
+            index,
+        }
+    }
+}
+
+/// Client → server: which files a build of an index would read, to test
+/// ignore files, and with `estimate` what a reload would embed.
+#[derive(Debug, Clone, Serialize)]
+pub struct GetIndexPlanMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub estimate: bool,
+}
+
+impl<'a> GetIndexPlanMessage<'a> {
+    pub fn new(index: Option<&'a str>, estimate: bool) -> Self {
+        Self {
+            typ: "index_plan",
+            index,
+            estimate,
+        }
+    }
+}
+
+/// Client → server: chunks of files that are no longer indexed (deleted,
+/// renamed or now ignored), and with `remove` drop them.
+#[derive(Debug, Clone, Serialize)]
+pub struct GetIndexVerifyMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub remove: bool,
+}
+
+impl<'a> GetIndexVerifyMessage<'a> {
+    pub fn new(index: Option<&'a str>, remove: bool) -> Self {
+        Self {
+            typ: "index_verify",
+            index,
+            remove,
+        }
+    }
+}
+
+/// Client → server: write the index to a snapshot archive at `path` on
+/// the server's machine, for teammates to restore.
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotIndexMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<&'a str>,
+    pub path: &'a str,
+}
+
+impl<'a> SnapshotIndexMessage<'a> {
+    pub fn new(index: Option<&'a str>, path: &'a str) -> Self {
+        Self {
+            typ: "index_snapshot",
+            index,
+            path,
+        }
+    }
+}
+
+/// Client → server: replace the index with the snapshot archive at `path`
+/// on the server's machine.
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreIndexMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<&'a str>,
+    pub path: &'a str,
+}
+
+impl<'a> RestoreIndexMessage<'a> {
+    pub fn new(index: Option<&'a str>, path: &'a str) -> Self {
+        Self {
+            typ: "index_restore",
+            index,
+            path,
+        }
+    }
+}
+
+/// Client → server: add the named index `name` over `directories` on the
+/// server's machine, with its own chunking and embedding model; unset ones
+/// are the server's.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateIndexMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub name: &'a str,
+    pub directories: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunking: Option<&'a ChunkingSection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<&'a str>,
+}
+
+impl<'a> CreateIndexMessage<'a> {
+    pub fn new(
+        name: &'a str,
+        directories: &'a [String],
+        chunking: Option<&'a ChunkingSection>,
+        embedding_model: Option<&'a str>,
+    ) -> Self {
+        Self {
+            typ: "index_create",
+            name,
+            directories,
+            chunking,
+            embedding_model,
+        }
+    }
+}
+
+/// Client → server: remove the named index `name` and its files.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeleteIndexMessage<'a> {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub name: &'a str,
+}
+
+impl<'a> DeleteIndexMessage<'a> {
+    pub fn new(name: &'a str) -> Self {
+        Self {
+            typ: "index_delete",
+            name,
+        }
+    }
+}
+
+/// Client → server: the last `lines` lines of the server log, and with
+/// `follow` every line logged after them (as `log` messages) until a
+/// `tail_logs` without `follow` or the end of the connection.
+#[derive(Debug, Clone, Serialize)]
+pub struct TailLogsMessage {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub lines: usize,
+    pub follow: bool,
+}
+
+impl TailLogsMessage {
+    pub fn new(lines: usize, follow: bool) -> Self {
+        Self {
+            typ: "tail_logs",
+            lines,
+            follow,
         }
     }
 }
 
-/// Server → client: stream chunk.
-#[derive(Debug, Clone, Deserialize)]
+/// Grounding check result: how well the answer is supported by the retrieved chunks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub struct StreamChunkMessage {
-    pub chunk: String,
+pub struct Grounding {
+    /// Fraction of answer claims supported by the sources, 0.0–1.0.
+    pub score: f64,
+    /// Answer sentences the check found unsupported.
+    #[serde(default)]
+    pub unsupported: Vec<String>,
+}
+
+/// Sources cited by inline markers like `[1]` in an answer's chunks, keyed
+/// by the marker's number.
+pub type Citations = BTreeMap<u32, Source>;
+
+/// Token counts of one answer, as reported by the LLM API.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(alias = "promptTokens")]
+    pub prompt_tokens: u64,
+    #[serde(alias = "completionTokens")]
+    pub completion_tokens: u64,
+    /// Model that produced the answer; the configured `api.llm_model` when
+    /// the server does not say.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// A document an answer drew on. Servers send either a bare path or an
+/// object with the details below; both deserialize into this.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SourceRepr")]
+pub struct Source {
+    pub path: String,
+    /// Heading of the matched chunk, if reported.
+    pub heading: Option<String>,
+    /// Retrieval similarity score (higher is more relevant), if reported.
+    pub score: Option<f64>,
+    /// Excerpt of the matched chunk, if reported.
+    pub snippet: Option<String>,
+    /// First line of the matched chunk in the file (1-based), if reported.
+    pub start_line: Option<u32>,
+    /// Last line of the matched chunk (inclusive), if reported.
+    pub end_line: Option<u32>,
+}
+
+impl Source {
+    /// `path`, `path:START` or `path:START-END`, the form editors and
+    /// terminals accept for jumping to a line.
+    pub fn location(&self) -> String {
+        match (self.start_line, self.end_line) {
+            (Some(start), Some(end)) if end > start => format!("{}:{}-{}", self.path, start, end),
+            (Some(start), _) => format!("{}:{}", self.path, start),
+            (None, _) => self.path.clone(),
+        }
+    }
+}
+
+/// A source equals a path when it is that bare path, with no details.
+impl PartialEq<&str> for Source {
+    fn eq(&self, path: &&str) -> bool {
+        let Source {
+            path: own,
+            heading,
+            score,
+            snippet,
+            start_line,
+            end_line,
+        } = self;
+        own == path
+            && heading.is_none()
+            && score.is_none()
+            && snippet.is_none()
+            && start_line.is_none()
+            && end_line.is_none()
+    }
+}
+
+impl From<&str> for Source {
+    fn from(path: &str) -> Self {
+        Source::from(path.to_string())
+    }
+}
+
+impl From<String> for Source {
+    fn from(path: String) -> Self {
+        Source {
+            path,
+            heading: None,
+            score: None,
+            snippet: None,
+            start_line: None,
+            end_line: None,
+        }
+    }
+}
+
+/// Wire forms of a `Source`. Objects may name the path `source` or `file`;
+/// anything else is kept as its JSON text rather than failing the message.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SourceRepr {
+    Path(String),
+    Detailed {
+        #[serde(alias = "source", alias = "file")]
+        path: String,
+        #[serde(default)]
+        heading: Option<String>,
+        #[serde(default)]
+        score: Option<f64>,
+        #[serde(default)]
+        snippet: Option<String>,
+        #[serde(default, alias = "startLine")]
+        start_line: Option<u32>,
+        #[serde(default, alias = "endLine")]
+        end_line: Option<u32>,
+    },
+    Other(serde_json::Value),
+}
+
+impl From<SourceRepr> for Source {
+    fn from(repr: SourceRepr) -> Self {
+        match repr {
+            SourceRepr::Path(path) => Source::from(path),
+            SourceRepr::Detailed {
+                path,
+                heading,
+                score,
+                snippet,
+                start_line,
+                end_line,
+            } => Source {
+                path,
+                heading,
+                score,
+                snippet,
+                start_line,
+                end_line,
+            },
+            SourceRepr::Other(value) => Source::from(value.to_string()),
+        }
+    }
 }
 
-/// Server → client: stream end with sources.
-#[derive(Debug, Clone, Deserialize)]
+/// Why a request failed, in an `error` message, so clients can tell
+/// errors worth retrying from ones the user has to fix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub struct StreamEndMessage {
-    pub sources: Vec<String>,
+pub enum ErrorCode {
+    /// The index is still loading (or there is none yet).
+    IndexNotReady,
+    /// The LLM or embedding API turned the server away for now.
+    RateLimited,
+    /// The LLM or embedding API rejected the server's credentials.
+    AuthFailed,
+    Internal,
+}
+
+impl ErrorCode {
+    /// Whether the same request may succeed if sent again a little later.
+    pub fn is_transient(self) -> bool {
+        matches!(self, ErrorCode::IndexNotReady | ErrorCode::RateLimited)
+    }
 }
 
-/// Server → client: error.
-#[derive(Debug, Clone, Deserialize)]
+/// Server → client: a request failed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct ErrorMessage {
     pub message: String,
+    /// Codes this client doesn't know (e.g. from a newer server) read as `None`.
+    #[serde(
+        default,
+        deserialize_with = "known_variant",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub code: Option<ErrorCode>,
+}
+
+impl ErrorMessage {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            code: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Whether the request may succeed if sent again (see
+    /// `ErrorCode::is_transient`).
+    pub fn is_transient(&self) -> bool {
+        self.code.is_some_and(ErrorCode::is_transient)
+    }
+}
+
+impl std::fmt::Display for ErrorMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A string naming an enum variant, or `None` when this client doesn't know
+/// the name (e.g. it is from a newer server).
+fn known_variant<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    let name = Option::<String>::deserialize(deserializer)?;
+    Ok(name.and_then(|name| serde_json::from_value(serde_json::Value::String(name)).ok()))
+}
+
+/// A citation map keyed by marker numbers as strings (JSON object keys),
+/// skipping keys that aren't numbers.
+fn citation_map<'de, D>(deserializer: D) -> Result<Citations, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let map = BTreeMap::<String, Source>::deserialize(deserializer)?;
+    Ok(map
+        .into_iter()
+        .filter_map(|(number, source)| Some((number.parse().ok()?, source)))
+        .collect())
 }
 
 /// Server → client: status response.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct StatusMessage {
+    /// `"ready"`, `"indexing"`, or `"not_ready"`; during a query, what the
+    /// server is doing before the answer starts (e.g. `"retrieving"`).
     pub status: String,
     #[serde(default)]
     pub message: Option<String>,
+    /// Number of indexed documents, if the server reports it.
+    #[serde(default)]
+    pub documents: Option<u64>,
+    /// Unix time the index was last built or reloaded, if the server reports it.
+    #[serde(default, alias = "indexedAt")]
+    pub indexed_at: Option<u64>,
 }
 
-/// Server → client: non-streaming response (optional).
-#[derive(Debug, Clone, Deserialize)]
+impl StatusMessage {
+    /// Whether the server has an index loaded and can answer queries.
+    pub fn is_ready(&self) -> bool {
+        self.status == "ready"
+    }
+
+    /// Whether this reports the state of the index, as a reply to `status`
+    /// does, rather than the progress of a query.
+    pub fn is_index_state(&self) -> bool {
+        matches!(self.status.as_str(), "ready" | "indexing" | "not_ready")
+    }
+}
+
+/// One indexed document in a `documents` reply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub struct ResponseMessage {
-    pub answer: String,
-    pub sources: Vec<serde_json::Value>,
+pub struct DocumentInfo {
+    pub path: String,
+    /// Number of chunks the document was split into.
+    pub chunks: usize,
+    /// Unix time the document was last indexed, if known.
+    #[serde(default, alias = "indexedAt")]
+    pub indexed_at: Option<u64>,
 }
 
-/// One server message; discriminator is JSON "type" field.
-#[derive(Debug, Clone)]
+/// Server → client: one page of indexed documents.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DocumentsMessage {
+    #[serde(default)]
+    pub index: Option<String>,
+    pub page: usize,
+    /// Number of documents in the whole index.
+    pub total: usize,
+    pub documents: Vec<DocumentInfo>,
+}
+
+/// One chunk of an indexed document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ChunkInfo {
+    pub text: String,
+    /// Heading the chunk falls under, if the chunker recorded one.
+    #[serde(default)]
+    pub heading: Option<String>,
+}
+
+/// Server → client: the chunks of one document, in document order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ChunksMessage {
+    pub path: String,
+    pub chunks: Vec<ChunkInfo>,
+}
+
+/// Server → client: a document's place in the link graph built at indexing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct LinksMessage {
+    pub path: String,
+    /// Indexed documents this one links to.
+    #[serde(default)]
+    pub outbound: Vec<String>,
+    /// Indexed documents that link to this one.
+    #[serde(default)]
+    pub inbound: Vec<String>,
+}
+
+/// Server → client: suggested follow-up questions, in reply to `suggest_followups`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct FollowupsMessage {
+    pub questions: Vec<String>,
+}
+
+/// Server → client: the optional protocol features the server supports, in
+/// reply to `capabilities` (e.g. `"cancel"`, `"history"`, `"list_indexes"`,
+/// `"compression"`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CapabilitiesMessage {
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Encoding the server switched to after this reply, when the request
+    /// offered `encodings`; unknown ones read as `None`.
+    #[serde(
+        default,
+        deserialize_with = "known_variant",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub encoding: Option<WireEncoding>,
+}
+
+impl CapabilitiesMessage {
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+/// Server → client: what an index holds and how it was built, in reply to
+/// `index_stats`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct IndexStatsMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    pub documents: u64,
+    pub chunks: u64,
+    /// Embedding model the index was built with, if the server reports it.
+    #[serde(
+        default,
+        alias = "embeddingModel",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub embedding_model: Option<String>,
+    /// Unix time the index was last built or updated, if known.
+    #[serde(default, alias = "builtAt", skip_serializing_if = "Option::is_none")]
+    pub built_at: Option<u64>,
+    /// Size of the index files on disk, in bytes, if known.
+    #[serde(default, alias = "sizeBytes", skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+    /// Files the server had problems reading when it indexed them, e.g. ones
+    /// that were not UTF-8 and were decoded as another encoding.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<FileIssue>,
+    /// How often queries found their chunks in the server's cache of
+    /// recently retrieved ones, if the server reports it.
+    #[serde(default, alias = "chunkCache", skip_serializing_if = "Option::is_none")]
+    pub chunk_cache: Option<ChunkCacheStats>,
+}
+
+/// The server's in-memory cache of recently retrieved chunks, in
+/// `IndexStatsMessage::chunk_cache`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ChunkCacheStats {
+    /// Chunks cached now.
+    pub size: u64,
+    /// Most chunks the cache keeps.
+    pub capacity: u64,
+    /// Lookups since the server started that found their chunk cached.
+    pub hits: u64,
+    pub misses: u64,
+    /// `hits / (hits + misses)`, or `None` before the first lookup.
+    #[serde(default, alias = "hitRate")]
+    pub hit_rate: Option<f64>,
+}
+
+/// Server → client: the files a build of an index would read and the ones
+/// its ignore files (`.gitignore`, `.mdqaignore`) leave out, in reply to
+/// `index_plan`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct IndexPlanMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub ignored: Vec<String>,
+    /// What a reload would embed, when asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<IndexEstimate>,
+}
+
+/// What a reload of an index would embed, in `IndexPlanMessage::estimate`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct IndexEstimate {
+    #[serde(default, alias = "embeddingModel")]
+    pub embedding_model: Option<String>,
+    /// Whether the reload rebuilds the index rather than updating the
+    /// changed files.
+    #[serde(default, alias = "fullRebuild")]
+    pub full_rebuild: bool,
+    /// The files to read, with what each would embed.
+    #[serde(default)]
+    pub files: Vec<FileEstimate>,
+    /// Chunks to embed; cached ones are left out.
+    #[serde(default)]
+    pub chunks: u64,
+    /// Tokens those chunks bill.
+    #[serde(default)]
+    pub tokens: u64,
+    /// Chunks whose embeddings are cached, which cost nothing.
+    #[serde(default, alias = "cachedChunks")]
+    pub cached_chunks: u64,
+    /// Rough time to embed the chunks.
+    #[serde(default)]
+    pub seconds: f64,
+}
+
+/// One file listed in `IndexEstimate::files`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEstimate {
+    pub path: String,
+    #[serde(default)]
+    pub chunks: u64,
+    #[serde(default)]
+    pub tokens: u64,
+}
+
+/// Server → client: the files whose chunks are still in an index although
+/// it no longer covers them, in reply to `index_verify`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct IndexVerifyMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    #[serde(default)]
+    pub orphans: Vec<OrphanedFile>,
+    /// Whether the orphaned chunks were removed.
+    #[serde(default)]
+    pub removed: bool,
+}
+
+/// Server → client: the snapshot archive written, in reply to
+/// `index_snapshot`, or read, in reply to `index_restore`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct IndexSnapshotMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    pub path: String,
+    #[serde(default)]
+    pub chunks: u64,
+    #[serde(default, alias = "embeddingModel")]
+    pub embedding_model: String,
+    #[serde(default, alias = "sizeBytes")]
+    pub size_bytes: u64,
+    /// After a restore: whether the server started a reload to pick up
+    /// documents that differ from the snapshot's.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reindexing: bool,
+}
+
+/// One index listed in `IndexesMessage::indexes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct IndexInfo {
+    pub name: String,
+    #[serde(default)]
+    pub directories: Vec<String>,
+    #[serde(default, alias = "embeddingModel")]
+    pub embedding_model: String,
+    /// Whether this is the server's configured index, used when a request
+    /// names none.
+    #[serde(default)]
+    pub default: bool,
+    /// "ready", "indexing" or "not_ready".
+    #[serde(default)]
+    pub status: String,
+}
+
+/// Server → client: the indexes there are, the configured one first, in
+/// reply to `list_indexes`, `index_create` and `index_delete`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct IndexesMessage {
+    pub indexes: Vec<IndexInfo>,
+}
+
+/// One file listed in `IndexVerifyMessage::orphans`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrphanedFile {
+    pub path: String,
+    pub chunks: u64,
+    /// "missing" (deleted or renamed) or "excluded" (ignored, or outside
+    /// the configured directories).
+    pub reason: String,
+}
+
+/// One file listed in `IndexStatsMessage::issues` or
+/// `IndexProgressMessage::failed`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileIssue {
+    pub path: String,
+    /// What went wrong, e.g. "not UTF-8; decoded as Latin-1".
+    pub issue: String,
+}
+
+/// Server → client, unsolicited: how far an index (re)build has got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct IndexProgressMessage {
+    /// Documents indexed so far.
+    pub done: u64,
+    /// Documents to index in this build.
+    pub total: u64,
+    /// Document being indexed, if the server reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Why `path` could not be indexed, if it couldn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Chunks to embed, once the documents are read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<u64>,
+    /// Chunks embedded so far.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedded: Option<u64>,
+    /// Set on the last message of a build.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub finished: bool,
+    /// Documents that could not be indexed, on the last message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<FileIssue>,
+}
+
+/// Server → client: the latest lines of the server log, oldest first, in
+/// reply to `tail_logs`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct LogsMessage {
+    #[serde(default)]
+    pub lines: Vec<String>,
+}
+
+/// Server → client, unsolicited: one line logged by the server after a
+/// `tail_logs` with `follow`. A record with a traceback is one line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct LogMessage {
+    pub line: String,
+}
+
+/// Severity of a server log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl LogLevel {
+    /// The level named by a log line: its first word that is a level name
+    /// in capitals, such as `WARNING` in the server's
+    /// `[2024-05-01 10:00:00] p42 {server.py:12} WARNING - message`. Only
+    /// the first line of a record is read; `None` when it names no level.
+    pub fn parse(line: &str) -> Option<Self> {
+        let first = line.lines().next()?;
+        first.split_whitespace().find_map(|word| {
+            match word.trim_matches(|c: char| !c.is_ascii_alphabetic()) {
+                "DEBUG" => Some(LogLevel::Debug),
+                "INFO" => Some(LogLevel::Info),
+                "WARNING" | "WARN" => Some(LogLevel::Warning),
+                "ERROR" => Some(LogLevel::Error),
+                "CRITICAL" | "FATAL" => Some(LogLevel::Critical),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// One server log line with the severity read from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogLine {
+    pub text: String,
+    pub level: Option<LogLevel>,
+}
+
+impl LogLine {
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            level: LogLevel::parse(&text),
+            text,
+        }
+    }
+}
+
+/// Server → client, unsolicited: documents changed after a reindex.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct NotificationMessage {
+    /// Paths of the documents that were added, changed or removed.
+    #[serde(default)]
+    pub documents: Vec<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// One server message, tagged by its JSON `type` field. Serializes to the
+/// same wire form it parses, so test servers can send these too. Types and
+/// fields with several words also parse in camelCase (`streamEnd`,
+/// `finishReason`), as some servers send them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum ServerMessage {
+    #[serde(alias = "streamStart")]
     StreamStart,
-    StreamChunk(String),
-    StreamEnd(Vec<String>),
-    Error(String),
-    Status { status: String, message: Option<String> },
-    Response { answer: String, sources: Vec<serde_json::Value> },
+    #[serde(alias = "streamChunk")]
+    StreamChunk {
+        chunk: String,
+        /// What the text is (`"answer"`, `"reasoning"`, `"preamble"`), if marked.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        role: Option<String>,
+        /// Why generation stopped, on the chunk that ended it.
+        #[serde(
+            default,
+            alias = "finishReason",
+            skip_serializing_if = "Option::is_none"
+        )]
+        finish_reason: Option<String>,
+        /// Which candidate answer the text belongs to, when the query asked
+        /// for several.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        candidate: Option<u32>,
+    },
+    /// Reasoning the model streams before its answer; not part of the answer.
+    #[serde(alias = "streamThinking")]
+    StreamThinking {
+        chunk: String,
+    },
+    #[serde(alias = "streamEnd")]
+    StreamEnd {
+        sources: Vec<Source>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        grounding: Option<Grounding>,
+        #[serde(
+            default,
+            deserialize_with = "citation_map",
+            skip_serializing_if = "BTreeMap::is_empty"
+        )]
+        citations: Citations,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
+        #[serde(default, alias = "elapsedMs", skip_serializing_if = "Option::is_none")]
+        elapsed_ms: Option<u64>,
+    },
+    Error(ErrorMessage),
+    Status(StatusMessage),
+    Documents(DocumentsMessage),
+    Chunks(ChunksMessage),
+    Links(LinksMessage),
+    Followups(FollowupsMessage),
+    Capabilities(CapabilitiesMessage),
+    Notification(NotificationMessage),
+    #[serde(alias = "indexProgress")]
+    IndexProgress(IndexProgressMessage),
+    #[serde(alias = "indexStats")]
+    IndexStats(IndexStatsMessage),
+    #[serde(alias = "indexPlan")]
+    IndexPlan(IndexPlanMessage),
+    #[serde(alias = "indexVerify")]
+    IndexVerify(IndexVerifyMessage),
+    #[serde(alias = "indexSnapshot")]
+    IndexSnapshot(IndexSnapshotMessage),
+    #[serde(alias = "indexRestore")]
+    IndexRestore(IndexSnapshotMessage),
+    Indexes(IndexesMessage),
+    Logs(LogsMessage),
+    Log(LogMessage),
+    /// Non-streaming answer (optional in the protocol).
+    Response {
+        answer: String,
+        sources: Vec<Source>,
+    },
+    /// A message whose `type` this client doesn't know, e.g. from a newer
+    /// server; `payload` is the whole message. Made by `ServerFrame::parse`;
+    /// `to_text` writes `payload` back, serde can't serialize it.
+    #[serde(skip)]
+    Unknown {
+        typ: String,
+        payload: serde_json::Value,
+    },
 }
 
 impl ServerMessage {
-    pub fn from_json(value: &serde_json::Value) -> Result<Self, String> {
-        let typ = value
-            .get("type")
-            .and_then(|t| t.as_str())
-            .ok_or("missing type")?;
-        match typ {
-            "stream_start" => Ok(ServerMessage::StreamStart),
-            "stream_chunk" => {
-                let m: StreamChunkMessage =
-                    serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
-                Ok(ServerMessage::StreamChunk(m.chunk))
-            }
-            "stream_end" => {
-                let m: StreamEndMessage =
-                    serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
-                Ok(ServerMessage::StreamEnd(m.sources))
+    /// Answer text, unmarked.
+    pub fn stream_chunk(chunk: impl Into<String>) -> Self {
+        ServerMessage::StreamChunk {
+            chunk: chunk.into(),
+            role: None,
+            finish_reason: None,
+            candidate: None,
+        }
+    }
+
+    /// Reasoning text streamed before the answer.
+    pub fn stream_thinking(chunk: impl Into<String>) -> Self {
+        ServerMessage::StreamThinking {
+            chunk: chunk.into(),
+        }
+    }
+
+    /// End of an answer citing `sources`, without grounding or stats.
+    pub fn stream_end<S: Into<Source>>(sources: impl IntoIterator<Item = S>) -> Self {
+        ServerMessage::StreamEnd {
+            sources: sources.into_iter().map(Into::into).collect(),
+            grounding: None,
+            citations: Citations::new(),
+            usage: None,
+            elapsed_ms: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        ServerMessage::Error(ErrorMessage::new(message))
+    }
+
+    /// Status reply with only `status` (`"ready"`, `"indexing"`, ...) set.
+    pub fn status(status: impl Into<String>) -> Self {
+        ServerMessage::Status(StatusMessage {
+            status: status.into(),
+            message: None,
+            documents: None,
+            indexed_at: None,
+        })
+    }
+
+    /// Frame this message as a reply to query `id`.
+    pub fn reply_to(self, id: u64) -> ServerFrame {
+        ServerFrame {
+            id: Some(id),
+            message: self,
+        }
+    }
+
+    /// `start`, one chunk per item of `chunks`, then `end`: a whole streamed answer.
+    pub fn stream<C: Into<String>>(
+        chunks: impl IntoIterator<Item = C>,
+        end: ServerMessage,
+    ) -> Vec<Self> {
+        std::iter::once(ServerMessage::StreamStart)
+            .chain(chunks.into_iter().map(ServerMessage::stream_chunk))
+            .chain(std::iter::once(end))
+            .collect()
+    }
+
+    /// The JSON text frame for this message; an `Unknown` one's payload as
+    /// it was received.
+    pub fn to_text(&self) -> String {
+        match self {
+            ServerMessage::Unknown { payload, .. } => payload.to_string(),
+            message => serde_json::to_string(message).expect("server messages serialize"),
+        }
+    }
+}
+
+/// A `ServerMessage` as framed on the wire: replies to a query carry its
+/// `QueryMessage::id` when the server echoes it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerFrame {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub message: ServerMessage,
+}
+
+/// The `type` of every `ServerMessage` this client reads, camelCase aliases
+/// included, e.g. for generating test frames. `ServerFrame::parse` asks
+/// serde rather than this list which types are known.
+pub const MESSAGE_TYPES: [&str; 32] = [
+    "stream_start",
+    "streamStart",
+    "stream_chunk",
+    "streamChunk",
+    "stream_thinking",
+    "streamThinking",
+    "stream_end",
+    "streamEnd",
+    "error",
+    "status",
+    "documents",
+    "chunks",
+    "links",
+    "followups",
+    "capabilities",
+    "notification",
+    "index_progress",
+    "indexProgress",
+    "index_stats",
+    "indexStats",
+    "index_plan",
+    "indexPlan",
+    "index_verify",
+    "indexVerify",
+    "index_snapshot",
+    "indexSnapshot",
+    "index_restore",
+    "indexRestore",
+    "indexes",
+    "logs",
+    "log",
+    "response",
+];
+
+/// Longest payload a `MessageError` keeps, in bytes.
+const MAX_ERROR_PAYLOAD: usize = 1024;
+
+/// Most top-level fields `ServerFrame::parse` probes to name the invalid one;
+/// each probe parses the whole message again.
+const MAX_PROBED_FIELDS: usize = 32;
+
+impl From<ServerMessage> for ServerFrame {
+    fn from(message: ServerMessage) -> Self {
+        ServerFrame { id: None, message }
+    }
+}
+
+impl ServerFrame {
+    /// Read a message decoded from a binary frame; like `parse` otherwise.
+    pub fn from_value(value: serde_json::Value) -> Result<Self, MessageError> {
+        match Self::deserialize(&value) {
+            Ok(frame) => Ok(frame),
+            // Unknown types and the details of the error, as for text.
+            Err(_) => Self::diagnose(&value.to_string(), value),
+        }
+    }
+
+    /// The JSON text frame, `id` included when set. An `Unknown` message is
+    /// written as received, with this frame's `id`.
+    pub fn to_text(&self) -> String {
+        match &self.message {
+            ServerMessage::Unknown { payload, .. } => {
+                let mut payload = payload.clone();
+                if let (Some(object), Some(id)) = (payload.as_object_mut(), self.id) {
+                    object.insert("id".into(), id.into());
+                }
+                payload.to_string()
             }
-            "error" => {
-                let m: ErrorMessage =
-                    serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
-                Ok(ServerMessage::Error(m.message))
+            _ => serde_json::to_string(self).expect("server frames serialize"),
+        }
+    }
+
+    /// Parse one text frame, saying which message type and field were wrong
+    /// when it doesn't match the protocol. A `type` this client doesn't know
+    /// parses as `ServerMessage::Unknown`, so newer servers can add messages.
+    pub fn parse(text: &str) -> Result<Self, MessageError> {
+        // Frames that follow the protocol are read in one pass; only the
+        // rest are read again to say what is wrong with them.
+        match serde_json::from_str(text) {
+            Ok(frame) => Ok(frame),
+            Err(_) => {
+                let value = serde_json::from_str(text)
+                    .map_err(|e| MessageError::new(text, None, None, e))?;
+                Self::diagnose(text, value)
             }
-            "status" => {
-                let m: StatusMessage =
-                    serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
-                Ok(ServerMessage::Status {
-                    status: m.status,
-                    message: m.message,
-                })
+        }
+    }
+
+    /// `text`, read as `value`, as an unknown message, or the error saying
+    /// what is wrong with it.
+    fn diagnose(text: &str, value: serde_json::Value) -> Result<Self, MessageError> {
+        let Some(object) = value.as_object() else {
+            return Err(MessageError::new(
+                text,
+                None,
+                None,
+                "expected a JSON object",
+            ));
+        };
+        let message_type = match object.get("type") {
+            Some(serde_json::Value::String(typ)) => typ.clone(),
+            Some(_) => {
+                return Err(MessageError::new(
+                    text,
+                    None,
+                    Some("type"),
+                    "expected a string",
+                ))
             }
-            "response" => {
-                let m: ResponseMessage =
-                    serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
-                Ok(ServerMessage::Response {
-                    answer: m.answer,
-                    sources: m.sources,
-                })
+            None => return Err(MessageError::new(text, None, Some("type"), "missing field")),
+        };
+        if !is_known_type(&message_type) {
+            let id = object.get("id").and_then(serde_json::Value::as_u64);
+            let message = ServerMessage::Unknown {
+                typ: message_type,
+                payload: value,
+            };
+            return Ok(Self { id, message });
+        }
+        let reason = match Self::deserialize(&value) {
+            Ok(frame) => return Ok(frame),
+            Err(e) => e.to_string(),
+        };
+        if let Some(field) = missing_field(&reason) {
+            let field = field.to_string();
+            return Err(MessageError::new(
+                text,
+                Some(&message_type),
+                Some(&field),
+                "missing field",
+            ));
+        }
+        // The tagged enum loses the field path, so find the field whose
+        // removal gets rid of the error (or only leaves it missing). No
+        // message in the protocol has that many fields, so a peer sending
+        // thousands doesn't get thousands of reparses.
+        let field = object
+            .keys()
+            .filter(|key| key.as_str() != "type")
+            .take(MAX_PROBED_FIELDS)
+            .find(|key| {
+                let mut probe = object.clone();
+                probe.remove(key.as_str());
+                match serde_json::from_value::<Self>(probe.into()) {
+                    Ok(_) => true,
+                    Err(e) => missing_field(&e.to_string()) == Some(key.as_str()),
+                }
+            });
+        Err(MessageError::new(
+            text,
+            Some(&message_type),
+            field.map(String::as_str),
+            reason,
+        ))
+    }
+}
+
+/// Whether `typ` is the tag (or an alias) of a `ServerMessage` variant.
+/// Asks the derived deserializer, so new variants and aliases are known
+/// without being listed anywhere else.
+fn is_known_type(typ: &str) -> bool {
+    let probe = serde_json::json!({ "type": typ });
+    match ServerMessage::deserialize(&probe) {
+        Ok(_) => true,
+        Err(e) => !e.to_string().starts_with("unknown variant"),
+    }
+}
+
+/// The field named by serde's "missing field `x`" message.
+fn missing_field(reason: &str) -> Option<&str> {
+    reason.strip_prefix("missing field `")?.split('`').next()
+}
+
+/// A server message that doesn't match the protocol: which message type and
+/// field were wrong, and (in debug builds) the message itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageError {
+    /// `type` of the message, when it names a known one.
+    pub message_type: Option<String>,
+    /// Top-level field that was missing or invalid.
+    pub field: Option<String>,
+    pub reason: String,
+    /// The raw message, cut to 1 KiB; only kept in debug builds.
+    pub payload: Option<String>,
+}
+
+impl MessageError {
+    fn new(
+        text: &str,
+        message_type: Option<&str>,
+        field: Option<&str>,
+        reason: impl ToString,
+    ) -> Self {
+        let payload = cfg!(debug_assertions).then(|| {
+            let mut end = text.len().min(MAX_ERROR_PAYLOAD);
+            while !text.is_char_boundary(end) {
+                end -= 1;
             }
-            _ => Err(format!("unknown type: {}", typ)),
+            text[..end].to_string()
+        });
+        Self {
+            message_type: message_type.map(str::to_string),
+            field: field.map(str::to_string),
+            reason: reason.to_string(),
+            payload,
         }
     }
 }
+
+impl std::fmt::Display for MessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.message_type {
+            Some(typ) => write!(f, "invalid `{}` message", typ)?,
+            None => write!(f, "invalid server message")?,
+        }
+        if let Some(field) = &self.field {
+            write!(f, ": field `{}`", field)?;
+        }
+        write!(f, ": {}", self.reason)?;
+        if let Some(payload) = &self.payload {
+            write!(f, " (payload: {})", payload)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MessageError {}
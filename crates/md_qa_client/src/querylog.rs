@@ -0,0 +1,205 @@
+//! Local query log (`queries.jsonl`, next to the config file) and the
+//! "gaps" report built from it: questions that came back without sources,
+//! failed on the server, or were rated unhelpful.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::history::Conversation;
+use crate::messages::Usage;
+use crate::usage::{PriceTable, UsageTotals};
+
+/// One line of the query log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LogEntry {
+    /// A question that got an answer or a server error.
+    Query {
+        /// Unix time in seconds.
+        timestamp: u64,
+        question: String,
+        #[serde(default)]
+        index: Option<String>,
+        /// Number of sources returned with STREAM_END.
+        sources: usize,
+        /// Server error message, when the query failed.
+        #[serde(default)]
+        error: Option<String>,
+        /// Tokens the answer used, when the server reported them.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
+        /// Answer text, for `history export`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        answer: Option<String>,
+        /// Paths of the sources returned with STREAM_END.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        source_paths: Vec<String>,
+    },
+    /// User rating of an answer to `question`.
+    Feedback {
+        timestamp: u64,
+        question: String,
+        helpful: bool,
+    },
+}
+
+/// A question the docs apparently do not cover, aggregated over the log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gap {
+    /// Question as most recently asked.
+    pub question: String,
+    /// Times the question was asked.
+    pub asked: usize,
+    /// Times it came back without sources or with a server error.
+    pub unanswered: usize,
+    /// Times an answer to it was rated unhelpful.
+    pub unhelpful: usize,
+    /// Unix time of the most recent query or rating.
+    pub last_seen: u64,
+}
+
+/// Query log path for a config file: `queries.jsonl` in the same directory.
+pub fn path_for_config(config_path: &Path) -> PathBuf {
+    config_path.with_file_name("queries.jsonl")
+}
+
+/// Current Unix time in seconds, for `LogEntry` timestamps.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Append one entry to the log at `path`, creating the file and its directory if needed.
+pub fn append(path: &Path, entry: &LogEntry) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
+
+/// Read all entries from the log at `path`. A missing file is an empty log;
+/// lines that do not parse are skipped.
+pub fn load(path: &Path) -> std::io::Result<Vec<LogEntry>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Group entries by question (case and whitespace insensitive) and keep the
+/// ones with at least one unanswered or unhelpful outcome, worst first.
+pub fn gaps(entries: &[LogEntry]) -> Vec<Gap> {
+    let mut by_question: HashMap<String, Gap> = HashMap::new();
+    for entry in entries {
+        let (question, timestamp) = match entry {
+            LogEntry::Query {
+                question,
+                timestamp,
+                ..
+            }
+            | LogEntry::Feedback {
+                question,
+                timestamp,
+                ..
+            } => (question, *timestamp),
+        };
+        let gap = by_question
+            .entry(normalize(question))
+            .or_insert_with(|| Gap {
+                question: String::new(),
+                asked: 0,
+                unanswered: 0,
+                unhelpful: 0,
+                last_seen: 0,
+            });
+        if timestamp >= gap.last_seen {
+            gap.last_seen = timestamp;
+            gap.question = question.trim().to_string();
+        }
+        match entry {
+            LogEntry::Query { sources, error, .. } => {
+                gap.asked += 1;
+                if *sources == 0 || error.is_some() {
+                    gap.unanswered += 1;
+                }
+            }
+            LogEntry::Feedback { helpful, .. } => {
+                if !helpful {
+                    gap.unhelpful += 1;
+                }
+            }
+        }
+    }
+    let mut gaps: Vec<Gap> = by_question
+        .into_values()
+        .filter(|g| g.unanswered + g.unhelpful > 0)
+        .collect();
+    gaps.sort_by(|a, b| {
+        (b.unanswered + b.unhelpful)
+            .cmp(&(a.unanswered + a.unhelpful))
+            .then(b.last_seen.cmp(&a.last_seen))
+    });
+    gaps
+}
+
+/// Usage and estimated cost of all logged answers, as `history cost` shows.
+pub fn usage(
+    entries: &[LogEntry],
+    prices: &PriceTable,
+    default_model: Option<&str>,
+) -> UsageTotals {
+    let mut totals = UsageTotals::default();
+    for entry in entries {
+        if let LogEntry::Query {
+            usage: Some(usage), ..
+        } = entry
+        {
+            totals.add(usage, prices, default_model);
+        }
+    }
+    totals
+}
+
+/// The most recent logged question that got an answer, as a one-turn
+/// conversation for `history export`.
+pub fn last_answer(entries: &[LogEntry]) -> Option<Conversation> {
+    entries.iter().rev().find_map(|entry| match entry {
+        LogEntry::Query {
+            question,
+            answer: Some(answer),
+            error: None,
+            source_paths,
+            ..
+        } => {
+            let mut conversation = Conversation::new();
+            conversation.push(question, answer, source_paths.clone());
+            Some(conversation)
+        }
+        _ => None,
+    })
+}
+
+fn normalize(question: &str) -> String {
+    question
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches('?')
+        .to_lowercase()
+}
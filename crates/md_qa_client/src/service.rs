@@ -0,0 +1,237 @@
+//! High-level API for programs that embed md-qa: configure a `QaService`
+//! once, then `ask` questions. The service resolves the config file, opens
+//! (and reopens) the connection, retries transient failures and keeps the
+//! conversation so follow-up questions carry the earlier turns.
+
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+
+use futures_util::{Stream, StreamExt};
+
+use crate::client::{
+    Client, ClientBuilder, ClientError, RetryPolicy, StreamCollector, StreamEvent,
+};
+use crate::config::{self, Config, ConfigError, RetrievalSection};
+use crate::history::Conversation;
+use crate::messages::{ErrorMessage, QueryMessage, QueryOptions};
+
+/// Earlier turns sent along with a follow-up question.
+const HISTORY_TURNS: usize = 3;
+
+/// Ask questions against an md-qa server. Connects on the first `ask` and
+/// reconnects after the connection is lost.
+pub struct QaService {
+    builder: ClientBuilder,
+    index: Option<String>,
+    options: QueryOptions,
+    keep_history: bool,
+    retrieval: RetrievalSection,
+    client: tokio::sync::Mutex<Option<Client>>,
+    history: Arc<Mutex<Conversation>>,
+}
+
+impl QaService {
+    /// Settings for a service; see `QaServiceBuilder`.
+    pub fn builder() -> QaServiceBuilder {
+        QaServiceBuilder::default()
+    }
+
+    /// A service for the config the CLI would use without `--config`:
+    /// `MD_QA_CONFIG`, else `~/.md-qa/config.yaml`, else built-in defaults.
+    pub fn from_default_config() -> Result<Self, ConfigError> {
+        Self::builder().build()
+    }
+
+    /// Send `question` and return its answer as it streams. With history
+    /// enabled (the default), the recent turns of the conversation are sent
+    /// along and the answer becomes a new turn once it is complete.
+    pub async fn ask(&self, question: &str) -> Result<AnswerStream, ClientError> {
+        let mut msg =
+            QueryMessage::new(question, self.index.as_deref()).with_options(self.options.clone());
+        if self.keep_history {
+            let history = self.history();
+            let leaf = history.active_leaf();
+            msg = msg
+                .with_history_context(history.retrieval_context(leaf, HISTORY_TURNS))
+                .with_history(history.recent_turns(leaf, HISTORY_TURNS));
+        }
+
+        let mut client = self.client.lock().await;
+        if !client.as_ref().is_some_and(Client::is_connected) {
+            *client = Some(self.builder.clone().connect().await?);
+        }
+        let events = client
+            .as_ref()
+            .expect("connected above")
+            .query_message_stream(&msg);
+        Ok(AnswerStream {
+            events: Box::pin(events),
+            question: question.to_string(),
+            collector: StreamCollector::with_retrieval(&self.retrieval),
+            history: self.keep_history.then(|| self.history.clone()),
+        })
+    }
+
+    /// The conversation so far.
+    pub fn history(&self) -> Conversation {
+        self.history
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Forget the conversation; the next question starts a new one.
+    pub fn clear_history(&self) {
+        *self.history.lock().unwrap_or_else(PoisonError::into_inner) = Conversation::new();
+    }
+}
+
+/// Settings of a `QaService`. Unset values come from the config file.
+#[derive(Debug, Clone, Default)]
+pub struct QaServiceBuilder {
+    config_path: Option<PathBuf>,
+    config: Option<Config>,
+    url: Option<String>,
+    index: Option<String>,
+    options: QueryOptions,
+    retry: Option<RetryPolicy>,
+    keep_history: Option<bool>,
+}
+
+impl QaServiceBuilder {
+    /// Read this config file instead of looking one up.
+    pub fn config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// Use this config instead of reading a file.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Connect to `url` instead of the configured server.
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    /// Index to ask; the server's default when unset.
+    pub fn index(mut self, index: &str) -> Self {
+        self.index = Some(index.to_string());
+        self
+    }
+
+    /// Options sent with every question.
+    pub fn query_options(mut self, options: QueryOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Retries for connects and lost connections; default
+    /// `RetryPolicy::transient`.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Whether to keep the conversation and send it with follow-ups;
+    /// default true. Without it every question stands alone.
+    pub fn history(mut self, enabled: bool) -> Self {
+        self.keep_history = Some(enabled);
+        self
+    }
+
+    /// Resolve the config and create the service. Does not connect yet.
+    pub fn build(self) -> Result<QaService, ConfigError> {
+        let config = match (self.config, self.config_path) {
+            (Some(config), _) => config,
+            (None, Some(path)) => config::load(&path)?,
+            (None, None) => default_config()?,
+        };
+        let builder = match &self.url {
+            Some(url) => ClientBuilder::new(url).client_config(&config.client)?,
+            None => ClientBuilder::from_config(&config)?,
+        };
+        Ok(QaService {
+            builder: builder.retry(self.retry.unwrap_or_else(RetryPolicy::transient)),
+            index: self.index,
+            options: self.options,
+            keep_history: self.keep_history.unwrap_or(true),
+            retrieval: config.retrieval,
+            client: tokio::sync::Mutex::new(None),
+            history: Arc::new(Mutex::new(Conversation::new())),
+        })
+    }
+}
+
+/// `MD_QA_CONFIG`, else `~/.md-qa/config.yaml` if it exists, else defaults.
+fn default_config() -> Result<Config, ConfigError> {
+    if let Some(path) = std::env::var_os("MD_QA_CONFIG") {
+        return config::load(PathBuf::from(path).as_path());
+    }
+    match config::default_config_path() {
+        Some(path) if path.exists() => config::load(&path),
+        _ => Ok(Config::default()),
+    }
+}
+
+/// The answer to one `QaService::ask`, as a stream of events. Ends after
+/// STREAM_END, RESPONSE, ERROR or the first error.
+pub struct AnswerStream {
+    events: Pin<Box<dyn Stream<Item = Result<StreamEvent, ClientError>> + Send>>,
+    question: String,
+    collector: StreamCollector,
+    /// Where the finished answer is recorded, unless history is off.
+    history: Option<Arc<Mutex<Conversation>>>,
+}
+
+impl AnswerStream {
+    /// The question this answers.
+    pub fn question(&self) -> &str {
+        &self.question
+    }
+
+    /// Read the rest of the answer and return all of it. An ERROR reply
+    /// becomes `ClientError::ServerError`.
+    pub async fn finish(mut self) -> Result<StreamCollector, ClientError> {
+        while let Some(event) = self.next().await {
+            event?;
+        }
+        match self.collector.error.take() {
+            Some(message) => Err(ClientError::ServerError(ErrorMessage {
+                message,
+                code: self.collector.error_code,
+            })),
+            None => Ok(self.collector),
+        }
+    }
+}
+
+impl Stream for AnswerStream {
+    type Item = Result<StreamEvent, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let next = futures_util::ready!(this.events.as_mut().poll_next(cx));
+        if let Some(Ok(event)) = &next {
+            this.collector.push(event);
+            let complete = matches!(
+                event,
+                StreamEvent::StreamEnd { .. } | StreamEvent::Response { .. }
+            );
+            if let (true, Some(history)) = (complete, &this.history) {
+                let sources = this.collector.sources.iter().map(|s| s.path.clone());
+                history.lock().unwrap_or_else(PoisonError::into_inner).push(
+                    &this.question,
+                    &this.collector.answer,
+                    sources.collect(),
+                );
+            }
+        }
+        Poll::Ready(next)
+    }
+}
@@ -0,0 +1,3 @@
+//! Helpers for testing md-qa servers against this client.
+
+pub mod conformance;
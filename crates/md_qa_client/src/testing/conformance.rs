@@ -0,0 +1,364 @@
+//! Protocol conformance checks for server implementors: `run` connects to a
+//! server, goes through a scripted sequence of exchanges (docs/protocol.md)
+//! and reports, for each protocol feature, whether the server's replies work
+//! with this client.
+//!
+//! ```no_run
+//! # async fn check() {
+//! let report = md_qa_client::testing::conformance::run("ws://127.0.0.1:8765").await;
+//! print!("{}", report);
+//! assert!(report.passed());
+//! # }
+//! ```
+//!
+//! Requests that change what the server holds (`reload`) or that need a
+//! query cut off mid-answer (`cancel`) are not sent.
+
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::client::{
+    Client, ClientBuilder, ClientError, ClientOptions, StreamCollector, StreamEvent,
+};
+use crate::messages::QueryMessage;
+
+/// The features checked, in the order they run.
+pub const FEATURES: [&str; 11] = [
+    "connect",
+    "ping",
+    "capabilities",
+    "status",
+    "list_documents",
+    "get_chunks",
+    "get_links",
+    "index_stats",
+    "query",
+    "query_validation",
+    "suggest_followups",
+];
+
+/// What came of one check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Pass,
+    Fail,
+    /// The server answered a request for an optional feature with an error.
+    Unsupported,
+    /// Not checked, because something it needs was not there (e.g. a ready
+    /// index for `query`).
+    Skipped,
+}
+
+/// One protocol feature and how the server handled it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    /// One of `FEATURES`: a request type, `"connect"` for the handshake,
+    /// `"ping"` for WebSocket pings or `"query_validation"` for a query with
+    /// a blank question, which must be refused.
+    pub feature: &'static str,
+    pub outcome: Outcome,
+    /// Why the check did not pass, or what the server reported.
+    pub detail: Option<String>,
+}
+
+/// Outcome of `run`: one check per entry of `FEATURES`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub url: String,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Whether no check failed. Unsupported optional features and skipped
+    /// checks do not count as failures.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.outcome != Outcome::Fail)
+    }
+
+    /// The check of `feature`.
+    pub fn check(&self, feature: &str) -> Option<&Check> {
+        self.checks.iter().find(|c| c.feature == feature)
+    }
+
+    fn count(&self, outcome: Outcome) -> usize {
+        self.checks.iter().filter(|c| c.outcome == outcome).count()
+    }
+
+    fn record(&mut self, feature: &'static str, outcome: Outcome, detail: Option<String>) {
+        self.checks.push(Check {
+            feature,
+            outcome,
+            detail,
+        });
+    }
+
+    /// Skip the checks not run yet, e.g. after the connection was lost.
+    fn skip_rest(&mut self, why: &str) {
+        for feature in &FEATURES[self.checks.len()..] {
+            self.record(feature, Outcome::Skipped, Some(why.to_string()));
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Conformance of {}:", self.url)?;
+        for check in &self.checks {
+            let label = match check.outcome {
+                Outcome::Pass => "PASS",
+                Outcome::Fail => "FAIL",
+                Outcome::Unsupported => "UNSUPPORTED",
+                Outcome::Skipped => "SKIPPED",
+            };
+            match &check.detail {
+                Some(detail) => writeln!(f, "  {:<11}  {}: {}", label, check.feature, detail)?,
+                None => writeln!(f, "  {:<11}  {}", label, check.feature)?,
+            }
+        }
+        writeln!(
+            f,
+            "{} passed, {} failed, {} unsupported, {} skipped",
+            self.count(Outcome::Pass),
+            self.count(Outcome::Fail),
+            self.count(Outcome::Unsupported),
+            self.count(Outcome::Skipped)
+        )
+    }
+}
+
+/// Settings of a conformance run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Question of the `query` check; should be answerable from the
+    /// server's documents.
+    pub question: String,
+    /// Limit on the connect and on each exchange, including the whole
+    /// answer to the question.
+    pub timeout: Duration,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            question: "What are these documents about?".into(),
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Check the server at `url` with the default `Options`.
+pub async fn run(url: &str) -> Report {
+    run_with(url, &Options::default()).await
+}
+
+/// Check the server at `url`: connect, then send each request of `FEATURES`
+/// in turn. Checks that need the connection are skipped once it is lost.
+pub async fn run_with(url: &str, options: &Options) -> Report {
+    let mut report = Report {
+        url: url.to_string(),
+        checks: Vec::new(),
+    };
+    let client_options = ClientOptions {
+        connect_timeout: Some(options.timeout),
+        first_event_timeout: Some(options.timeout),
+        stream_timeout: Some(options.timeout),
+        ..ClientOptions::default()
+    };
+    let client = match ClientBuilder::new(url)
+        .options(client_options)
+        .connect()
+        .await
+    {
+        Ok(client) => client,
+        Err(e) => {
+            report.record("connect", Outcome::Fail, Some(e.to_string()));
+            report.skip_rest("not connected");
+            return report;
+        }
+    };
+    report.record("connect", Outcome::Pass, None);
+
+    let mut found = Found::default();
+    for feature in &FEATURES[1..] {
+        if !client.is_connected() {
+            report.skip_rest("connection lost");
+            break;
+        }
+        let (outcome, detail) = check(feature, &client, options, &mut found).await;
+        report.record(feature, outcome, detail);
+    }
+    let _ = client.close(1000, "conformance checks done").await;
+    report
+}
+
+/// What earlier checks found out that later ones need.
+#[derive(Default)]
+struct Found {
+    /// Why the index cannot answer, when `status` says it is not ready.
+    not_ready: Option<String>,
+    /// A document listed by `list_documents`.
+    document: Option<String>,
+    /// The answer of the `query` check.
+    answer: Option<StreamCollector>,
+}
+
+async fn check(
+    feature: &str,
+    client: &Client,
+    options: &Options,
+    found: &mut Found,
+) -> (Outcome, Option<String>) {
+    match feature {
+        "ping" => required(
+            client
+                .ping(options.timeout)
+                .await
+                .map(|rtt| Some(format!("{} ms", rtt.as_millis()))),
+        ),
+        "capabilities" => optional(client.fetch_capabilities().await.map(|c| {
+            Some(match c.features.is_empty() {
+                true => "no optional features".into(),
+                false => c.features.join(", "),
+            })
+        })),
+        "status" => match client.status().await {
+            Ok(status) if !status.is_index_state() => (
+                Outcome::Fail,
+                Some(format!("unknown status {:?}", status.status)),
+            ),
+            Ok(status) => {
+                if !status.is_ready() {
+                    found.not_ready = Some(format!("index is {}", status.status));
+                }
+                (Outcome::Pass, Some(status.status))
+            }
+            Err(e) => required(Err(e)),
+        },
+        "list_documents" => optional(client.list_documents(None, 0, 10).await.map(|page| {
+            found.document = page.documents.first().map(|d| d.path.clone());
+            Some(format!("{} documents", page.total))
+        })),
+        "get_chunks" => match found.document.clone() {
+            Some(path) => optional(
+                client
+                    .document_chunks(&path)
+                    .await
+                    .map(|c| Some(format!("{} chunks of {}", c.chunks.len(), path))),
+            ),
+            None => skipped("no document listed"),
+        },
+        "get_links" => match found.document.clone() {
+            Some(path) => optional(client.document_links(&path).await.map(|links| {
+                Some(format!(
+                    "{} outbound, {} inbound",
+                    links.outbound.len(),
+                    links.inbound.len()
+                ))
+            })),
+            None => skipped("no document listed"),
+        },
+        "index_stats" => optional(client.index_stats(None).await.map(|stats| {
+            Some(format!(
+                "{} documents, {} chunks",
+                stats.documents, stats.chunks
+            ))
+        })),
+        "query" => {
+            if let Some(why) = &found.not_ready {
+                return skipped(why);
+            }
+            let events = client
+                .query_message(&QueryMessage::new(&options.question, None))
+                .await;
+            match events
+                .map_err(|e| e.to_string())
+                .and_then(|e| check_answer(&e))
+            {
+                Ok(answer) => {
+                    let detail = format!(
+                        "{} characters, {} sources",
+                        answer.answer.chars().count(),
+                        answer.sources.len()
+                    );
+                    found.answer = Some(answer);
+                    (Outcome::Pass, Some(detail))
+                }
+                Err(why) => (Outcome::Fail, Some(why)),
+            }
+        }
+        "query_validation" => match client.query("  ", None).await {
+            Ok(events) if matches!(events.last(), Some(StreamEvent::Error(_))) => {
+                (Outcome::Pass, None)
+            }
+            Ok(_) => (Outcome::Fail, Some("a blank question was answered".into())),
+            Err(ClientError::ServerError(_)) => (Outcome::Pass, None),
+            Err(e) => required(Err(e)),
+        },
+        "suggest_followups" => match &found.answer {
+            Some(answer) => optional(
+                client
+                    .suggest_followups(&options.question, &answer.answer, &answer.sources, 3)
+                    .await
+                    .map(|questions| Some(format!("{} questions", questions.len()))),
+            ),
+            None => skipped("no answer to follow up"),
+        },
+        other => unreachable!("no check for {}", other),
+    }
+}
+
+/// Outcome of a request every server must answer.
+fn required(result: Result<Option<String>, ClientError>) -> (Outcome, Option<String>) {
+    match result {
+        Ok(detail) => (Outcome::Pass, detail),
+        Err(e) => (Outcome::Fail, Some(e.to_string())),
+    }
+}
+
+/// Outcome of a request for an optional feature, which servers without it
+/// answer with an error.
+fn optional(result: Result<Option<String>, ClientError>) -> (Outcome, Option<String>) {
+    match result {
+        Err(ClientError::ServerError(e)) => (Outcome::Unsupported, Some(e.message)),
+        result => required(result),
+    }
+}
+
+fn skipped(why: &str) -> (Outcome, Option<String>) {
+    (Outcome::Skipped, Some(why.to_string()))
+}
+
+/// Check that a query's events make a complete answer: `stream_start`
+/// before the first chunk and `stream_end` last, or a single `response`,
+/// with some answer text.
+fn check_answer(events: &[StreamEvent]) -> Result<StreamCollector, String> {
+    let start = events
+        .iter()
+        .position(|e| matches!(e, StreamEvent::StreamStart));
+    let first_chunk = events
+        .iter()
+        .position(|e| matches!(e, StreamEvent::StreamChunk(..)));
+    match events.last() {
+        Some(StreamEvent::Error(e)) => return Err(format!("server error: {}", e)),
+        Some(StreamEvent::StreamEnd { .. }) if start.is_none() => {
+            return Err("stream_end without stream_start".into())
+        }
+        Some(StreamEvent::StreamEnd { .. } | StreamEvent::Response { .. }) => {}
+        _ => return Err("the answer did not end with stream_end or response".into()),
+    }
+    if first_chunk
+        .zip(start)
+        .is_some_and(|(chunk, start)| chunk < start)
+    {
+        return Err("stream_chunk before stream_start".into());
+    }
+    let mut collector = StreamCollector::new();
+    events.iter().for_each(|e| collector.push(e));
+    if collector.answer.trim().is_empty() {
+        return Err("empty answer".into());
+    }
+    Ok(collector)
+}
@@ -0,0 +1,77 @@
+//! Token usage of answers and its estimated cost, priced with the `prices`
+//! table of the config, so API spend can be attributed to conversations.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Usage;
+
+/// Price of a model per million tokens, in whatever currency the table
+/// uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Price {
+    #[serde(default)]
+    pub prompt: f64,
+    #[serde(default)]
+    pub completion: f64,
+}
+
+impl Price {
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt
+            + usage.completion_tokens as f64 * self.completion)
+            / 1_000_000.0
+    }
+}
+
+/// Prices by model name (`prices` in the config).
+pub type PriceTable = BTreeMap<String, Price>;
+
+/// Reloads that would embed at least this many tokens are large enough that
+/// `md-qa index reload` and the GUI's Reindex button show their estimated
+/// cost and time and ask before starting them.
+pub const LARGE_RELOAD_TOKENS: u64 = 1_000_000;
+
+/// Estimated cost of embedding `tokens` with `model`, which is billed at the
+/// model's `prompt` price; `None` if the table has no price for it.
+pub fn embedding_cost(prices: &PriceTable, model: Option<&str>, tokens: u64) -> Option<f64> {
+    let price = prices.get(model?)?;
+    Some(tokens as f64 * price.prompt / 1_000_000.0)
+}
+
+/// Token counts and estimated cost summed over several answers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    /// Answers that reported usage.
+    pub answers: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Estimated cost of the answers whose model has a price.
+    pub cost: f64,
+    /// Answers whose model has no price; `cost` leaves them out.
+    pub unpriced: usize,
+}
+
+impl UsageTotals {
+    /// Add one answer. Its model is `usage.model`, else `default_model`
+    /// (the configured `api.llm_model`).
+    pub fn add(&mut self, usage: &Usage, prices: &PriceTable, default_model: Option<&str>) {
+        self.answers += 1;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+        match usage
+            .model
+            .as_deref()
+            .or(default_model)
+            .and_then(|model| prices.get(model))
+        {
+            Some(price) => self.cost += price.cost(usage),
+            None => self.unpriced += 1,
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
@@ -0,0 +1,100 @@
+//! Wire encodings of the protocol. Messages are JSON text frames unless the
+//! client asks for a binary encoding in its `capabilities` request and the
+//! server picks one it supports: after the server's reply, both sides send
+//! the same messages as binary frames in that encoding. MessagePack and CBOR
+//! are behind the `msgpack` and `cbor` cargo features.
+
+use serde::{Deserialize, Serialize};
+
+/// How protocol messages are encoded on the wire.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WireEncoding {
+    /// JSON text frames; every server speaks it.
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    /// MessagePack binary frames (cargo feature `msgpack`).
+    #[serde(rename = "msgpack")]
+    MessagePack,
+    /// CBOR binary frames (cargo feature `cbor`).
+    #[serde(rename = "cbor")]
+    Cbor,
+}
+
+impl std::str::FromStr for WireEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(WireEncoding::Json),
+            "msgpack" => Ok(WireEncoding::MessagePack),
+            "cbor" => Ok(WireEncoding::Cbor),
+            _ => Err(format!(
+                "invalid encoding: {} (expected json, msgpack or cbor)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for WireEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WireEncoding::Json => "json",
+            WireEncoding::MessagePack => "msgpack",
+            WireEncoding::Cbor => "cbor",
+        })
+    }
+}
+
+impl WireEncoding {
+    /// Whether this build can speak the encoding.
+    pub fn is_available(self) -> bool {
+        match self {
+            WireEncoding::Json => true,
+            WireEncoding::MessagePack => cfg!(feature = "msgpack"),
+            WireEncoding::Cbor => cfg!(feature = "cbor"),
+        }
+    }
+
+    /// Whether messages go out as binary frames.
+    pub fn is_binary(self) -> bool {
+        self != WireEncoding::Json
+    }
+
+    /// Encode one message.
+    pub fn encode(self, message: &serde_json::Value) -> Result<Vec<u8>, String> {
+        match self {
+            WireEncoding::Json => serde_json::to_vec(message).map_err(|e| e.to_string()),
+            #[cfg(feature = "msgpack")]
+            WireEncoding::MessagePack => {
+                rmp_serde::to_vec_named(message).map_err(|e| e.to_string())
+            }
+            #[cfg(feature = "cbor")]
+            WireEncoding::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::ser::into_writer(message, &mut bytes).map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unavailable()),
+        }
+    }
+
+    /// Decode one message.
+    pub fn decode(self, bytes: &[u8]) -> Result<serde_json::Value, String> {
+        match self {
+            WireEncoding::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "msgpack")]
+            WireEncoding::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "cbor")]
+            WireEncoding::Cbor => ciborium::de::from_reader(bytes).map_err(|e| e.to_string()),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unavailable()),
+        }
+    }
+
+    fn unavailable(self) -> String {
+        format!("{0} encoding is not built in (cargo feature `{0}`)", self)
+    }
+}
@@ -1,6 +1,6 @@
 //! Integration tests for config load/save. Run with `cargo test`; they fail until task 2.3.
 
-use md_qa_client::{config, Config};
+use md_qa_client::{config, ChunkSettings, Config, HookFailure, Price};
 use predicates::prelude::*;
 
 #[test]
@@ -141,3 +141,319 @@ fn default_config_path_uses_home_directory() {
     let expected = dir.path().join(".md-qa").join("config.yaml");
     assert_eq!(path, expected);
 }
+
+#[test]
+fn client_section_sets_server_url_and_is_omitted_when_empty() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        r#"
+server:
+  port: 9000
+client:
+  url: "wss://qa.example.com"
+  ca_cert: "/etc/md-qa/ca.pem"
+"#,
+    )
+    .unwrap();
+
+    let mut cfg = config::load(&config_path).expect("load should succeed");
+    assert_eq!(cfg.server_url(), "wss://qa.example.com");
+    assert_eq!(cfg.client.ca_cert.as_deref(), Some("/etc/md-qa/ca.pem"));
+    assert!(!cfg.client.accept_invalid_certs);
+
+    cfg.client = Default::default();
+    assert_eq!(cfg.server_url(), "ws://127.0.0.1:9000");
+    config::save(&config_path, &cfg).unwrap();
+    let saved = std::fs::read_to_string(&config_path).unwrap();
+    assert!(
+        !saved.contains("client"),
+        "empty client section should be omitted"
+    );
+}
+
+#[test]
+fn hooks_section_parses_and_validates() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        r#"
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+hooks:
+  pre_query: "./expand-acronyms.sh"
+  post_answer: "notify-send md-qa done"
+  on_failure: abort
+  timeout: 0
+"#,
+    )
+    .unwrap();
+
+    let cfg = config::load(&config_path).expect("load should succeed");
+    assert_eq!(cfg.hooks.pre_query.as_deref(), Some("./expand-acronyms.sh"));
+    assert_eq!(cfg.hooks.on_failure, Some(HookFailure::Abort));
+    let err = cfg.validate().expect_err("a zero timeout is invalid");
+    assert!(err.to_string().contains("hooks.timeout must be positive"));
+
+    let empty = Config::default();
+    config::save(&config_path, &empty).unwrap();
+    let saved = std::fs::read_to_string(&config_path).unwrap();
+    assert!(
+        !saved.contains("hooks"),
+        "empty hooks section should be omitted"
+    );
+}
+
+#[test]
+fn prices_parse_and_reject_negative_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        r#"
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+prices:
+  gpt-4o-mini:
+    prompt: 0.15
+    completion: 0.6
+  local:
+    prompt: -1
+"#,
+    )
+    .unwrap();
+
+    let cfg = config::load(&config_path).expect("load should succeed");
+    assert_eq!(
+        cfg.prices["gpt-4o-mini"],
+        Price {
+            prompt: 0.15,
+            completion: 0.6
+        }
+    );
+    assert_eq!(cfg.prices["local"].completion, 0.0);
+    let err = cfg.validate().expect_err("a negative price is invalid");
+    assert!(err
+        .to_string()
+        .contains("prices.local must not be negative"));
+
+    config::save(&config_path, &Config::default()).unwrap();
+    let saved = std::fs::read_to_string(&config_path).unwrap();
+    assert!(
+        !saved.contains("prices"),
+        "empty price table should be omitted"
+    );
+}
+
+#[test]
+fn retrieval_chunking_round_trips_and_rejects_bad_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        r#"
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+retrieval:
+  max_sources: 5
+  chunking:
+    target_tokens: 400
+    heading_level: 2
+    directories:
+      /docs/zh:
+        target_tokens: 150
+        overlap_tokens: 20
+      /docs/api:
+        heading_level: 9
+"#,
+    )
+    .unwrap();
+
+    let cfg = config::load(&config_path).expect("load should succeed");
+    let chunking = &cfg.retrieval.chunking;
+    assert_eq!(
+        chunking.defaults,
+        ChunkSettings {
+            target_tokens: Some(400),
+            overlap_tokens: None,
+            heading_level: Some(2),
+        }
+    );
+    assert_eq!(chunking.directories["/docs/zh"].overlap_tokens, Some(20));
+    let err = cfg.validate().expect_err("heading level 9 is invalid");
+    assert!(err.to_string().contains(
+        "retrieval.chunking.directories./docs/api.heading_level must be between 1 and 6"
+    ));
+
+    config::save(&config_path, &cfg).unwrap();
+    let reloaded = config::load(&config_path).unwrap();
+    assert_eq!(reloaded.retrieval, cfg.retrieval);
+}
+
+#[test]
+fn duplicate_keys_warn_and_the_last_value_wins() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        r#"
+api:
+  base_url: "https://old.example.com/v1"
+  api_key: "test-key"
+  base_url: "https://api.example.com/v1"
+server:
+  port: 9000
+server:
+  port: 9001
+"#,
+    )
+    .unwrap();
+
+    let loaded = config::load_with_warnings(&config_path).expect("load should succeed");
+    assert_eq!(
+        loaded.config.api.base_url.as_deref(),
+        Some("https://api.example.com/v1")
+    );
+    assert_eq!(loaded.config.api.api_key.as_deref(), Some("test-key"));
+    assert_eq!(loaded.config.server.port, Some(9001));
+    assert_eq!(
+        loaded.warnings,
+        [
+            "duplicate key `api.base_url`; the last value is used",
+            "duplicate key `server`; the last value is used",
+        ]
+    );
+    // `load` keeps the config and drops the warnings.
+    assert_eq!(config::load(&config_path).unwrap().server.port, Some(9001));
+}
+
+#[test]
+fn anchors_aliases_and_merge_keys_are_resolved() {
+    let loaded = config::parse(
+        r#"
+defaults: &defaults
+  base_url: "https://api.example.com/v1"
+  api_key: "shared-key"
+api:
+  <<: *defaults
+  llm_model: "gpt-4o-mini"
+server:
+  directories: &dirs
+    - /docs
+  index_name: default
+client:
+  url: "ws://127.0.0.1:8765"
+"#,
+    )
+    .expect("parse should succeed");
+    assert!(loaded.warnings.is_empty(), "{:?}", loaded.warnings);
+    let api = &loaded.config.api;
+    assert_eq!(api.base_url.as_deref(), Some("https://api.example.com/v1"));
+    assert_eq!(api.api_key.as_deref(), Some("shared-key"));
+    assert_eq!(api.llm_model.as_deref(), Some("gpt-4o-mini"));
+    assert_eq!(loaded.config.server.directories, ["/docs"]);
+}
+
+#[test]
+fn load_reports_typed_errors() {
+    let err = config::parse(
+        "api:
+  base_url: [unclosed
+",
+    )
+    .expect_err("bad YAML");
+    match err {
+        config::ConfigError::Parse { line, .. } => assert!(line.is_some()),
+        other => panic!("expected a parse error, got {other:?}"),
+    }
+
+    let err = config::parse(
+        "server:
+  port: 80
+  port: high
+",
+    )
+    .expect_err("bad port");
+    let message = err.to_string();
+    assert!(message.contains("server.port"), "{message}");
+    assert!(
+        !message.contains("line"),
+        "lines of the rewritten YAML: {message}"
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    let huge = format!("# {}\n", "x".repeat(config::MAX_CONFIG_BYTES as usize));
+    std::fs::write(&config_path, huge).unwrap();
+    match config::load(&config_path).expect_err("too large") {
+        config::ConfigError::TooLarge { limit, .. } => assert_eq!(limit, config::MAX_CONFIG_BYTES),
+        other => panic!("expected TooLarge, got {other:?}"),
+    }
+}
+
+const TEAM_TEMPLATE: &str = r#"# Team defaults
+api:
+  base_url: "https://llm.example.com/v1"
+  api_key: "{{ api_key }}"
+server:
+  port: 8765
+  directories:
+    - "{{repo}}/docs"
+    - "{{repo}}/handbook"
+"#;
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn template_renders_variables_and_keeps_comments() {
+    assert_eq!(
+        config::template_variables(TEAM_TEMPLATE),
+        ["api_key", "repo"]
+    );
+
+    let rendered = config::render_template(
+        TEAM_TEMPLATE,
+        &vars(&[("repo", "/src/app"), ("api_key", "k1")]),
+    )
+    .expect("template should render");
+    assert!(rendered.starts_with("# Team defaults\n"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested").join("config.yaml");
+    config::save_rendered(&path, &rendered).unwrap();
+    let cfg = config::load(&path).unwrap();
+    assert_eq!(cfg.api.api_key.as_deref(), Some("k1"));
+    assert_eq!(
+        cfg.server.directories,
+        vec!["/src/app/docs", "/src/app/handbook"]
+    );
+}
+
+#[test]
+fn template_reports_missing_variables_and_invalid_results() {
+    let err = config::render_template(TEAM_TEMPLATE, &vars(&[("repo", "/src/app")]))
+        .expect_err("api_key has no value");
+    assert!(
+        err.to_string().contains("api_key"),
+        "unexpected error: {err}"
+    );
+
+    let err = config::render_template(
+        "api:\n  base_url: \"{{url}}\"\nserver:\n  port: 0\n",
+        &vars(&[("url", "https://llm.example.com")]),
+    )
+    .expect_err("missing key and bad port should fail validation");
+    let message = err.to_string();
+    assert!(message.contains("api.api_key is required"), "{message}");
+    assert!(message.contains("server.port"), "{message}");
+}
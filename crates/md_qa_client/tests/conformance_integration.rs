@@ -0,0 +1,145 @@
+//! Integration tests for the protocol conformance checks, against scripted
+//! servers that follow the protocol and that break it.
+
+use futures_util::{SinkExt, StreamExt};
+use md_qa_client::testing::conformance::{self, Outcome, FEATURES};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Serve one connection, answering each request with `reply(request)`.
+async fn serve(reply: fn(&Value) -> Vec<Value>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            let Message::Text(text) = message else {
+                continue;
+            };
+            let request: Value = serde_json::from_str(&text).unwrap();
+            for mut frame in reply(&request) {
+                if let Some(id) = request.get("id") {
+                    frame["id"] = id.clone();
+                }
+                ws.send(Message::Text(frame.to_string())).await.unwrap();
+            }
+        }
+    });
+    url
+}
+
+fn error(message: &str) -> Vec<Value> {
+    vec![json!({"type": "error", "message": message})]
+}
+
+/// A server with the required requests and some optional ones.
+fn conforming(request: &Value) -> Vec<Value> {
+    match request["type"].as_str().unwrap() {
+        "capabilities" => vec![json!({"type": "capabilities", "features": ["cancel"]})],
+        "status" => vec![json!({"type": "status", "status": "ready", "documents": 1})],
+        "list_documents" => vec![json!({
+            "type": "documents",
+            "page": 0,
+            "total": 1,
+            "documents": [{"path": "/docs/a.md", "chunks": 2}],
+        })],
+        "get_chunks" => vec![json!({
+            "type": "chunks",
+            "path": "/docs/a.md",
+            "chunks": [{"text": "Port 8765."}, {"text": "TLS is optional."}],
+        })],
+        "index_stats" => vec![json!({
+            "type": "index_stats",
+            "index": "default",
+            "documents": 1,
+            "chunks": 2,
+        })],
+        "query" if request["question"].as_str().unwrap().trim().is_empty() => {
+            error("Question must not be empty")
+        }
+        "query" => vec![
+            json!({"type": "stream_start"}),
+            json!({"type": "stream_chunk", "chunk": "Use port 8765."}),
+            json!({"type": "stream_end", "sources": ["/docs/a.md"]}),
+        ],
+        "suggest_followups" => vec![json!({
+            "type": "followups",
+            "questions": ["How do I enable TLS?"],
+        })],
+        other => error(&format!("Unknown message type: {}", other)),
+    }
+}
+
+#[tokio::test]
+async fn conforming_server_passes_with_unsupported_features_listed() {
+    let url = serve(conforming).await;
+    let report = conformance::run(&url).await;
+
+    assert!(report.passed(), "{}", report);
+    let features: Vec<&str> = report.checks.iter().map(|c| c.feature).collect();
+    assert_eq!(features, FEATURES);
+    let links = report.check("get_links").unwrap();
+    assert_eq!(links.outcome, Outcome::Unsupported);
+    assert_eq!(
+        links.detail.as_deref(),
+        Some("Unknown message type: get_links")
+    );
+    let outcomes: Vec<Outcome> = report.checks.iter().map(|c| c.outcome).collect();
+    assert_eq!(outcomes.iter().filter(|&&o| o == Outcome::Pass).count(), 10);
+    assert_eq!(
+        report.check("get_chunks").unwrap().detail.as_deref(),
+        Some("2 chunks of /docs/a.md")
+    );
+
+    let printed = report.to_string();
+    assert!(printed.contains("  UNSUPPORTED  get_links: Unknown message type"));
+    assert!(printed.ends_with("10 passed, 0 failed, 1 unsupported, 0 skipped\n"));
+}
+
+/// A server that streams without `stream_start`, answers blank questions and
+/// knows no optional requests.
+fn broken(request: &Value) -> Vec<Value> {
+    match request["type"].as_str().unwrap() {
+        "status" => vec![json!({"type": "status", "status": "ready"})],
+        "query" => vec![
+            json!({"type": "stream_chunk", "chunk": "Use port 8765."}),
+            json!({"type": "stream_end", "sources": []}),
+        ],
+        other => error(&format!("Unknown message type: {}", other)),
+    }
+}
+
+#[tokio::test]
+async fn broken_answers_fail_and_dependent_checks_are_skipped() {
+    let url = serve(broken).await;
+    let report = conformance::run(&url).await;
+
+    assert!(!report.passed());
+    let outcome = |feature| report.check(feature).unwrap().outcome;
+    assert_eq!(outcome("status"), Outcome::Pass);
+    assert_eq!(outcome("list_documents"), Outcome::Unsupported);
+    assert_eq!(outcome("get_chunks"), Outcome::Skipped);
+    assert_eq!(outcome("query"), Outcome::Fail);
+    assert_eq!(
+        report.check("query").unwrap().detail.as_deref(),
+        Some("stream_end without stream_start")
+    );
+    assert_eq!(outcome("query_validation"), Outcome::Fail);
+    assert_eq!(outcome("suggest_followups"), Outcome::Skipped);
+}
+
+#[tokio::test]
+async fn unreachable_server_skips_everything_after_connect() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    drop(listener);
+
+    let report = conformance::run(&url).await;
+    assert_eq!(report.checks[0].outcome, Outcome::Fail);
+    assert!(report.checks[1..]
+        .iter()
+        .all(|c| c.outcome == Outcome::Skipped && c.detail.as_deref() == Some("not connected")));
+}
@@ -0,0 +1,69 @@
+//! Integration tests for console output: re-encoding for Windows-1252
+//! consoles and flushing each streamed chunk.
+
+use md_qa_client::{Console, Encoding};
+use std::io::Write;
+
+/// Records what was written and how often it was flushed.
+#[derive(Default)]
+struct Recorder {
+    bytes: Vec<u8>,
+    flushes: usize,
+}
+
+impl Write for Recorder {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.bytes.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.flushes += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn windows_1252_encoding_maps_typographic_characters() {
+    let mut out = Vec::new();
+    Encoding::Windows1252.encode("café “quoted” – 5 € … 🙂", &mut out);
+    assert_eq!(out, b"caf\xE9 \x93quoted\x94 \x96 5 \x80 \x85 ?".to_vec());
+
+    let mut out = Vec::new();
+    Encoding::Utf8.encode("café 🙂", &mut out);
+    assert_eq!(out, "café 🙂".as_bytes());
+}
+
+#[test]
+fn code_page_selects_the_encoding() {
+    assert_eq!(Encoding::from_code_page(1252), Encoding::Windows1252);
+    assert_eq!(Encoding::from_code_page(65001), Encoding::Utf8);
+    assert_eq!(Encoding::from_code_page(0), Encoding::Utf8);
+}
+
+#[test]
+fn console_flushes_every_chunk() {
+    let mut console = Console::new(Recorder::default(), Encoding::Utf8);
+    for chunk in ["Streaming ", "answer", "."] {
+        write!(console, "{}", chunk).unwrap();
+    }
+    let recorder = console.into_inner();
+    assert_eq!(recorder.bytes, b"Streaming answer.");
+    assert_eq!(recorder.flushes, 3);
+}
+
+#[test]
+fn console_reencodes_characters_split_across_writes() {
+    let mut console = Console::new(Recorder::default(), Encoding::Windows1252);
+    let text = "naïve €".as_bytes();
+    // Split inside both multi-byte sequences.
+    let (first, rest) = text.split_at(3);
+    let (second, third) = rest.split_at(5);
+    console.write_all(first).unwrap();
+    console.write_all(second).unwrap();
+    console.write_all(third).unwrap();
+    // Invalid UTF-8 becomes `?`.
+    console.write_all(b" \xFF!").unwrap();
+    assert_eq!(console.encoding(), Encoding::Windows1252);
+    assert_eq!(console.into_inner().bytes, b"na\xEFve \x80 ?!".to_vec());
+}
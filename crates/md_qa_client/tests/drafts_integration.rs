@@ -0,0 +1,43 @@
+//! Integration tests for unsent chat drafts.
+
+use md_qa_client::drafts;
+
+#[test]
+fn drafts_are_kept_per_session_until_cleared() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = drafts::path_for_config(&dir.path().join("nested").join("config.yaml"));
+    assert_eq!(path.file_name().unwrap(), "drafts.json");
+    assert!(drafts::load(&path).unwrap().is_empty());
+
+    drafts::save(&path, "chat", "How do I rotate\nthe signing keys?").unwrap();
+    drafts::save(&path, "compare", "Which model is better?").unwrap();
+    drafts::save(&path, "chat", "How do I rotate the signing keys?").unwrap();
+
+    let chat = drafts::get(&path, "chat").unwrap().unwrap();
+    assert_eq!(chat.text, "How do I rotate the signing keys?");
+    let sessions: Vec<String> = drafts::load(&path)
+        .unwrap()
+        .into_iter()
+        .map(|d| d.session)
+        .collect();
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.contains(&"compare".to_string()));
+
+    drafts::save(&path, "chat", "  ").unwrap();
+    assert_eq!(drafts::get(&path, "chat").unwrap(), None);
+    assert_eq!(drafts::load(&path).unwrap().len(), 1);
+}
+
+#[test]
+fn corrupt_drafts_file_has_no_drafts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("drafts.json");
+    std::fs::write(&path, "{not json").unwrap();
+
+    assert!(drafts::load(&path).unwrap().is_empty());
+    drafts::save(&path, "chat", "Still saved").unwrap();
+    assert_eq!(
+        drafts::get(&path, "chat").unwrap().unwrap().text,
+        "Still saved"
+    );
+}
@@ -0,0 +1,69 @@
+//! Integration tests for exporting a conversation as an issue body or a
+//! Markdown snippet.
+
+use md_qa_client::export::{self, DEFAULT_SNIPPET_TEMPLATE};
+use md_qa_client::{Conversation, ExportFormat, ExportSection};
+
+fn conversation() -> Conversation {
+    let mut conversation = Conversation::new();
+    conversation.push("How do I deploy?", "Run `make deploy`.", vec![]);
+    conversation.push(
+        "And roll back?",
+        " Use `make rollback`. \n",
+        vec!["docs/deploy.md".into(), "docs/ops.md".into()],
+    );
+    conversation
+}
+
+#[test]
+fn issue_body_has_the_last_answer_and_the_whole_conversation() {
+    let body = ExportFormat::Issue
+        .render(&ExportSection::default(), &conversation())
+        .unwrap();
+    assert!(body.starts_with(
+        "### Question\n\nAnd roll back?\n\n### Answer\n\nUse `make rollback`.\n\n### Sources\n\n- docs/deploy.md\n- docs/ops.md\n"
+    ));
+    assert!(body.contains("## Q: How do I deploy?\n\nRun `make deploy`.\n"));
+    assert!(body.contains("### What the docs are missing"));
+}
+
+#[test]
+fn snippet_uses_the_configured_template() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("snippet.md");
+    std::fs::write(&template, "Q: {{question}}\nA: {{ answer }}\n").unwrap();
+    let section = ExportSection {
+        snippet_template: Some(template.to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+    let snippet = ExportFormat::Snippet
+        .render(&section, &conversation())
+        .unwrap();
+    assert_eq!(snippet, "Q: And roll back?\nA: Use `make rollback`.\n");
+
+    let mut single = Conversation::new();
+    single.push("What is X?", "X is Y.", vec![]);
+    assert_eq!(
+        export::render(DEFAULT_SNIPPET_TEMPLATE, &single).unwrap(),
+        "**Q:** What is X?\n\nX is Y.\n\nSources:\n_None_\n"
+    );
+}
+
+#[test]
+fn unknown_placeholders_and_missing_templates_are_errors() {
+    let err = export::render("{{ title }}", &conversation()).unwrap_err();
+    assert!(err.to_string().contains("title"), "{}", err);
+
+    let section = ExportSection {
+        issue_template: Some("/nonexistent/issue.md".into()),
+        ..Default::default()
+    };
+    let err = ExportFormat::Issue
+        .render(&section, &conversation())
+        .unwrap_err();
+    assert!(err.to_string().contains("/nonexistent/issue.md"), "{}", err);
+    assert_eq!(
+        "pdf".parse::<ExportFormat>().unwrap_err(),
+        "invalid export format: pdf (expected issue or snippet)"
+    );
+}
@@ -0,0 +1,158 @@
+//! Integration tests for the conversation history tree: forking on
+//! edit/regenerate, branch listing, switching, and active-path export.
+
+use md_qa_client::messages::Usage;
+use md_qa_client::{Conversation, HistoryError, Price, PriceTable};
+
+#[test]
+fn push_extends_active_branch() {
+    let mut conv = Conversation::new();
+    let a = conv.push("first?", "one", vec!["/a.md".into()]);
+    let b = conv.push("second?", "two", vec![]);
+
+    assert_eq!(conv.active_leaf(), Some(b));
+    let path: Vec<_> = conv.active_path().iter().map(|t| t.id).collect();
+    assert_eq!(path, vec![a, b]);
+    assert_eq!(conv.branches().len(), 1);
+}
+
+#[test]
+fn fork_creates_sibling_branch_without_overwriting() {
+    let mut conv = Conversation::new();
+    let a = conv.push("first?", "one", vec![]);
+    let b = conv.push("second?", "two", vec![]);
+    let edited = conv
+        .fork(b, "second, edited?", "two again", vec![])
+        .expect("fork should succeed");
+
+    assert_eq!(conv.get(b).unwrap().answer, "two");
+    assert_eq!(conv.get(edited).unwrap().parent, Some(a));
+    assert_eq!(conv.active_leaf(), Some(edited));
+
+    let branches = conv.branches();
+    assert_eq!(branches.len(), 2);
+    assert!(branches.iter().any(|br| br.leaf == b && !br.active));
+    assert!(branches.iter().any(|br| br.leaf == edited && br.active));
+}
+
+#[test]
+fn switch_to_changes_active_path() {
+    let mut conv = Conversation::new();
+    let a = conv.push("q1", "a1", vec![]);
+    let b = conv.push("q2", "a2", vec![]);
+    let c = conv.fork(b, "q2'", "a2'", vec![]).unwrap();
+
+    conv.switch_to(b).expect("switch to leaf should succeed");
+    let path: Vec<_> = conv.active_path().iter().map(|t| t.id).collect();
+    assert_eq!(path, vec![a, b]);
+
+    assert_eq!(conv.switch_to(a), Err(HistoryError::NotALeaf(a)));
+    assert_eq!(conv.switch_to(99), Err(HistoryError::UnknownTurn(99)));
+    assert!(conv.fork(99, "q", "a", vec![]).is_err());
+
+    conv.switch_to(c).unwrap();
+    assert_eq!(conv.active_leaf(), Some(c));
+}
+
+#[test]
+fn markdown_export_renders_only_active_path() {
+    let mut conv = Conversation::new();
+    conv.push("What is Rust?", "A language.", vec!["/rust.md".into()]);
+    let b = conv.push("Is it fast?", "Old answer.", vec![]);
+    conv.fork(b, "Is it fast?", "New answer.", vec![]).unwrap();
+
+    let md = conv.to_markdown();
+    assert!(md.contains("## Q: What is Rust?"));
+    assert!(md.contains("- /rust.md"));
+    assert!(md.contains("New answer."));
+    assert!(!md.contains("Old answer."));
+}
+
+#[test]
+fn interrupted_turns_are_marked() {
+    let mut conv = Conversation::new();
+    let a = conv.push("What is Rust?", "A language.", vec![]);
+    let b = conv.push("Is it fast?", "It is", vec![]);
+    conv.set_interrupted(b).unwrap();
+    assert_eq!(conv.set_interrupted(9), Err(HistoryError::UnknownTurn(9)));
+
+    assert!(!conv.get(a).unwrap().interrupted);
+    assert!(conv.get(b).unwrap().interrupted);
+    let md = conv.to_markdown();
+    assert!(md.contains("A language.\n"));
+    assert!(md.contains("It is _(interrupted)_\n"));
+}
+
+#[test]
+fn retrieval_context_condenses_recent_turns() {
+    let mut conv = Conversation::new();
+    assert_eq!(conv.retrieval_context(conv.active_leaf(), 2), None);
+
+    conv.push("What is foo?", "Foo is a tool.", vec![]);
+    let b = conv.push("Who wrote it?", &"x".repeat(500), vec![]);
+    let c = conv.push("Is it maintained?", "Yes.", vec![]);
+
+    let context = conv.retrieval_context(Some(c), 2).unwrap();
+    assert!(!context.contains("What is foo?"));
+    assert!(context.starts_with("Q: Who wrote it?\nA: xxx"));
+    assert!(context.ends_with("Q: Is it maintained?\nA: Yes."));
+    // Long answers are cut down to their start.
+    assert!(context.len() < 300);
+
+    let parent_context = conv
+        .retrieval_context(conv.get(b).unwrap().parent, 3)
+        .unwrap();
+    assert_eq!(parent_context, "Q: What is foo?\nA: Foo is a tool.");
+
+    let msg = md_qa_client::messages::QueryMessage::new("And the license?", None)
+        .with_history_context(Some(parent_context));
+    assert_eq!(
+        msg.retrieval_query.as_deref(),
+        Some("Q: What is foo?\nA: Foo is a tool.\nQ: And the license?")
+    );
+
+    let recent = conv.recent_turns(Some(c), 2);
+    let questions: Vec<&str> = recent.iter().map(|t| t.question.as_str()).collect();
+    assert_eq!(questions, vec!["Who wrote it?", "Is it maintained?"]);
+    let msg = md_qa_client::messages::QueryMessage::new("Is it free?", None).with_history(recent);
+    assert_eq!(msg.history.len(), 2);
+    assert_eq!(msg.history[1].answer, "Yes.");
+}
+
+#[test]
+fn usage_counts_turns_on_every_branch() {
+    let mut conv = Conversation::new();
+    let a = conv.push("first?", "one", vec![]);
+    let b = conv.push("second?", "two", vec![]);
+    let edited = conv
+        .fork(b, "second, edited?", "two again", vec![])
+        .unwrap();
+    let usage = Usage {
+        prompt_tokens: 1_000_000,
+        completion_tokens: 100_000,
+        model: None,
+    };
+    for id in [a, b, edited] {
+        conv.set_usage(id, usage.clone()).unwrap();
+    }
+    assert_eq!(
+        conv.set_usage(9, usage.clone()),
+        Err(HistoryError::UnknownTurn(9))
+    );
+    assert_eq!(conv.get(a).unwrap().usage, Some(usage));
+
+    let prices = PriceTable::from([(
+        "m".to_string(),
+        Price {
+            prompt: 1.0,
+            completion: 2.0,
+        },
+    )]);
+    let totals = conv.usage(&prices, Some("m"));
+    assert_eq!(totals.answers, 3);
+    assert_eq!(totals.prompt_tokens, 3_000_000);
+    assert!((totals.cost - 3.6).abs() < 1e-9);
+
+    let unpriced = conv.usage(&prices, None);
+    assert_eq!((unpriced.unpriced, unpriced.cost), (3, 0.0));
+}
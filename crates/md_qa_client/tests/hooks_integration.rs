@@ -0,0 +1,73 @@
+//! Integration tests for hook scripts: real `sh` commands, no mocks.
+#![cfg(unix)]
+
+use md_qa_client::config::HooksSection;
+use md_qa_client::messages::Source;
+use md_qa_client::{HookError, Hooks};
+
+fn hooks(pre_query: Option<&str>, post_answer: Option<&str>) -> Hooks {
+    Hooks::from_config(&HooksSection {
+        pre_query: pre_query.map(str::to_string),
+        post_answer: post_answer.map(str::to_string),
+        ..HooksSection::default()
+    })
+}
+
+#[tokio::test]
+async fn pre_query_output_replaces_the_question() {
+    let upper = hooks(Some("tr a-z A-Z"), None);
+    assert_eq!(
+        upper.pre_query("what is rrf?").await.unwrap(),
+        "WHAT IS RRF?"
+    );
+
+    // No output, or no hook at all, keeps the question.
+    let silent = hooks(Some("cat > /dev/null"), None);
+    assert_eq!(silent.pre_query("as is").await.unwrap(), "as is");
+    assert_eq!(Hooks::default().pre_query("as is").await.unwrap(), "as is");
+}
+
+#[tokio::test]
+async fn post_answer_gets_the_exchange_as_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("answer.json");
+    let command = format!("cat > '{}'", out.display());
+    let hooks = hooks(None, Some(&command));
+
+    let sources = [Source {
+        heading: Some("Ports".into()),
+        ..Source::from("/docs/a.md")
+    }];
+    hooks
+        .post_answer("Which port?", "8765.", &sources)
+        .await
+        .unwrap();
+
+    let written: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+    assert_eq!(
+        written,
+        serde_json::json!({
+            "question": "Which port?",
+            "answer": "8765.",
+            "sources": ["/docs/a.md"]
+        })
+    );
+}
+
+#[tokio::test]
+async fn failing_and_slow_hooks_are_errors() {
+    let failing = hooks(Some("echo 'no network' >&2; exit 3"), None);
+    let err = failing.pre_query("q").await.unwrap_err();
+    assert!(matches!(err, HookError::Failed("pre_query", _, _)));
+    assert!(err.to_string().contains("no network"), "{err}");
+
+    let slow = Hooks {
+        timeout: std::time::Duration::from_millis(100),
+        ..hooks(None, Some("sleep 5"))
+    };
+    let started = std::time::Instant::now();
+    let err = slow.post_answer("q", "a", &[]).await.unwrap_err();
+    assert!(matches!(err, HookError::Timeout("post_answer", _)));
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+}
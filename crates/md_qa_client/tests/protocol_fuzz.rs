@@ -0,0 +1,441 @@
+//! Property tests with arbitrary and mangled server messages: parsing them
+//! never panics or takes long, and a query's stream always ends. The
+//! cargo-fuzz targets in `fuzz/` run the same checks without a fixed seed.
+
+use futures_util::{SinkExt, StreamExt};
+use md_qa_client::messages::{ServerFrame, ServerMessage};
+use md_qa_client::{cite_answer, connect, ClientError, StreamCollector, StreamEvent};
+use serde_json::{Map, Value};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+
+const TYPES: [&str; 23] = [
+    "stream_start",
+    "stream_chunk",
+    "stream_thinking",
+    "stream_end",
+    "response",
+    "error",
+    "status",
+    "documents",
+    "chunks",
+    "links",
+    "followups",
+    "capabilities",
+    "notification",
+    "index_progress",
+    "index_stats",
+    "index_plan",
+    "index_verify",
+    "index_snapshot",
+    "index_restore",
+    "indexes",
+    "logs",
+    "log",
+    "not_a_type",
+];
+
+const FIELDS: [&str; 29] = [
+    "id",
+    "chunk",
+    "role",
+    "candidate",
+    "finish_reason",
+    "sources",
+    "path",
+    "heading",
+    "start_line",
+    "end_line",
+    "grounding",
+    "score",
+    "citations",
+    "usage",
+    "prompt_tokens",
+    "elapsed_ms",
+    "message",
+    "code",
+    "status",
+    "documents",
+    "done",
+    "total",
+    "chunks",
+    "size_bytes",
+    "features",
+    "encoding",
+    "answer",
+    "lines",
+    "line",
+];
+
+/// Strings the parser treats specially, plus some it shouldn't.
+const STRINGS: [&str; 8] = [
+    "",
+    "1",
+    "[1]",
+    "rate_limited",
+    "msgpack",
+    "answer",
+    "/docs/a.md",
+    "unknown variant `x`",
+];
+
+fn arbitrary_value(rng: &mut fastrand::Rng, depth: u32) -> Value {
+    match rng.u8(..if depth == 0 { 6 } else { 8 }) {
+        0 => Value::Null,
+        1 => Value::Bool(rng.bool()),
+        2 => match rng.u8(..4) {
+            0 => Value::from(rng.u64(..)),
+            1 => Value::from(rng.i64(..0)),
+            2 => Value::from(f64::from(rng.i32(..)) / 8.0),
+            _ => Value::from(u64::from(u32::MAX) + rng.u64(..3)),
+        },
+        3 => Value::from(rng.u8(..10)),
+        4 => Value::from(STRINGS[rng.usize(..STRINGS.len())]),
+        5 => {
+            let len = rng.usize(..8);
+            Value::from((0..len).map(|_| rng.char(..)).collect::<String>())
+        }
+        6 => (0..rng.usize(..4))
+            .map(|_| arbitrary_value(rng, depth - 1))
+            .collect(),
+        _ => Value::Object(arbitrary_object(rng, depth - 1)),
+    }
+}
+
+/// An object with some protocol fields (and usually a message type) set to
+/// arbitrary values.
+fn arbitrary_object(rng: &mut fastrand::Rng, depth: u32) -> Map<String, Value> {
+    let mut object = Map::new();
+    for _ in 0..rng.usize(..6) {
+        let field = FIELDS[rng.usize(..FIELDS.len())];
+        object.insert(field.into(), arbitrary_value(rng, depth));
+    }
+    if rng.u8(..8) != 0 {
+        let typ = TYPES[rng.usize(..TYPES.len())];
+        object.insert("type".into(), Value::from(typ));
+    }
+    object
+}
+
+/// A text frame: an arbitrary object, sometimes cut short or with a byte
+/// replaced so it is no longer valid JSON.
+fn arbitrary_frame(rng: &mut fastrand::Rng) -> String {
+    let mut text = Value::Object(arbitrary_object(rng, 3)).to_string();
+    match rng.u8(..8) {
+        0 => {
+            let mut end = rng.usize(..=text.len());
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+        }
+        1 if !text.is_empty() => {
+            let at = rng.usize(..text.len());
+            if text.is_char_boundary(at) && text.is_char_boundary(at + 1) {
+                text.replace_range(at..at + 1, &rng.char(..).to_string());
+            }
+        }
+        _ => {}
+    }
+    text
+}
+
+#[test]
+fn parsing_arbitrary_frames_never_panics() {
+    let mut rng = fastrand::Rng::with_seed(1291);
+    for _ in 0..20_000 {
+        let text = arbitrary_frame(&mut rng);
+        match ServerFrame::parse(&text) {
+            Ok(frame) => {
+                if let ServerMessage::Unknown { .. } = frame.message {
+                    assert_eq!(ServerFrame::parse(&frame.to_text()).unwrap(), frame);
+                    continue;
+                }
+                let reparsed = ServerFrame::parse(&frame.to_text())
+                    .unwrap_or_else(|e| panic!("{} does not parse back: {}", text, e));
+                assert_eq!(
+                    std::mem::discriminant(&reparsed.message),
+                    std::mem::discriminant(&frame.message),
+                    "{}",
+                    text
+                );
+                if let ServerMessage::StreamEnd { citations, .. } = frame.message {
+                    let _ = cite_answer("see [1] and [4294967296] [", &citations);
+                }
+            }
+            Err(e) => {
+                let _ = e.to_string();
+            }
+        }
+    }
+}
+
+#[test]
+fn binary_frames_parse_like_text_frames() {
+    let mut rng = fastrand::Rng::with_seed(1292);
+    for _ in 0..5_000 {
+        let text = Value::Object(arbitrary_object(&mut rng, 3)).to_string();
+        let value: Value = serde_json::from_str(&text).unwrap();
+        match (ServerFrame::from_value(value), ServerFrame::parse(&text)) {
+            (Ok(binary), Ok(frame)) => assert_eq!(binary, frame, "{}", text),
+            (Err(binary), Err(frame)) => assert_eq!(binary, frame, "{}", text),
+            (binary, frame) => panic!("{}: {:?} but {:?}", text, binary, frame),
+        }
+    }
+}
+
+#[test]
+fn known_message_types_are_never_read_as_unknown() {
+    for typ in md_qa_client::messages::MESSAGE_TYPES {
+        let text = serde_json::json!({ "type": typ }).to_string();
+        match ServerFrame::parse(&text) {
+            Ok(frame) => assert!(
+                !matches!(frame.message, ServerMessage::Unknown { .. }),
+                "{}",
+                text
+            ),
+            Err(e) => assert_eq!(e.message_type.as_deref(), Some(typ), "{}", text),
+        }
+    }
+}
+
+/// Position of the message's variant; the match has no wildcard, so a new
+/// variant doesn't compile until it has a sample below.
+fn variant_index(message: &ServerMessage) -> usize {
+    match message {
+        ServerMessage::StreamStart => 0,
+        ServerMessage::StreamChunk { .. } => 1,
+        ServerMessage::StreamThinking { .. } => 2,
+        ServerMessage::StreamEnd { .. } => 3,
+        ServerMessage::Error(_) => 4,
+        ServerMessage::Status(_) => 5,
+        ServerMessage::Documents(_) => 6,
+        ServerMessage::Chunks(_) => 7,
+        ServerMessage::Links(_) => 8,
+        ServerMessage::Followups(_) => 9,
+        ServerMessage::Capabilities(_) => 10,
+        ServerMessage::Notification(_) => 11,
+        ServerMessage::IndexProgress(_) => 12,
+        ServerMessage::IndexStats(_) => 13,
+        ServerMessage::IndexPlan(_) => 14,
+        ServerMessage::IndexVerify(_) => 15,
+        ServerMessage::IndexSnapshot(_) => 16,
+        ServerMessage::IndexRestore(_) => 17,
+        ServerMessage::Indexes(_) => 18,
+        ServerMessage::Logs(_) => 19,
+        ServerMessage::Log(_) => 20,
+        ServerMessage::Response { .. } => 21,
+        ServerMessage::Unknown { .. } => usize::MAX,
+    }
+}
+
+/// `index_stats` as `indexStats`.
+fn camel_case(typ: &str) -> String {
+    let mut words = typ.split('_');
+    let mut camel = words.next().unwrap_or_default().to_string();
+    for word in words {
+        let mut chars = word.chars();
+        camel.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        camel.push_str(chars.as_str());
+    }
+    camel
+}
+
+#[test]
+fn every_message_type_and_alias_round_trips_through_parse() {
+    let samples = [
+        r#"{"type":"stream_start"}"#,
+        r#"{"type":"stream_chunk","chunk":"a"}"#,
+        r#"{"type":"stream_thinking","chunk":"a"}"#,
+        r#"{"type":"stream_end","sources":["a.md"]}"#,
+        r#"{"type":"error","message":"no"}"#,
+        r#"{"type":"status","status":"ready"}"#,
+        r#"{"type":"documents","page":0,"total":0,"documents":[]}"#,
+        r#"{"type":"chunks","path":"a.md","chunks":[]}"#,
+        r#"{"type":"links","path":"a.md"}"#,
+        r#"{"type":"followups","questions":[]}"#,
+        r#"{"type":"capabilities"}"#,
+        r#"{"type":"notification","documents":["a.md"]}"#,
+        r#"{"type":"index_progress","done":1,"total":2}"#,
+        r#"{"type":"index_stats","documents":1,"chunks":2}"#,
+        r#"{"type":"index_plan","files":[]}"#,
+        r#"{"type":"index_verify","orphans":[]}"#,
+        r#"{"type":"index_snapshot","path":"/tmp/a.tar"}"#,
+        r#"{"type":"index_restore","path":"/tmp/a.tar","reindexing":true}"#,
+        r#"{"type":"indexes","indexes":[{"name":"docs","default":true}]}"#,
+        r#"{"type":"logs","lines":[]}"#,
+        r#"{"type":"log","line":"x"}"#,
+        r#"{"type":"response","answer":"a","sources":[]}"#,
+    ];
+    let mut covered = vec![false; samples.len()];
+    for sample in samples {
+        let message = ServerFrame::parse(sample).unwrap().message;
+        let index = variant_index(&message);
+        assert!(index < samples.len(), "{} parsed as {:?}", sample, message);
+        covered[index] = true;
+
+        // The tag it is written with is listed and reads back the same.
+        let text = message.to_text();
+        let typ = serde_json::from_str::<Value>(&text).unwrap()["type"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(
+            md_qa_client::messages::MESSAGE_TYPES.contains(&typ.as_str()),
+            "{} is not in MESSAGE_TYPES",
+            typ
+        );
+        assert_eq!(ServerFrame::parse(&text).unwrap().message, message);
+
+        // So does its camelCase alias, when it has words to join.
+        let camel = camel_case(&typ);
+        if camel != typ {
+            assert!(
+                md_qa_client::messages::MESSAGE_TYPES.contains(&camel.as_str()),
+                "{} is not in MESSAGE_TYPES",
+                camel
+            );
+            let aliased = text.replacen(&format!(r#""{}""#, typ), &format!(r#""{}""#, camel), 1);
+            assert_eq!(
+                ServerFrame::parse(&aliased).unwrap().message,
+                message,
+                "{}",
+                aliased
+            );
+        }
+    }
+    assert!(covered.iter().all(|&c| c), "a variant has no sample");
+}
+
+#[test]
+fn invalid_frames_with_many_fields_parse_quickly() {
+    let mut object = Map::new();
+    object.insert("type".into(), Value::from("stream_end"));
+    object.insert("sources".into(), Value::from(5));
+    for i in 0..20_000 {
+        object.insert(format!("k{}", i), Value::from(i));
+    }
+    let text = Value::Object(object).to_string();
+
+    let start = Instant::now();
+    let error = ServerFrame::parse(&text).unwrap_err();
+    assert_eq!(error.message_type.as_deref(), Some("stream_end"));
+    assert!(
+        start.elapsed() < Duration::from_secs(5),
+        "took {:?}",
+        start.elapsed()
+    );
+}
+
+/// Answer every query on each accepted connection with `frames` (an `{id}`
+/// in them is replaced by the query's id), then hang up.
+async fn serve_frames(listener: TcpListener, rounds: Vec<Vec<String>>) {
+    for frames in rounds {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+        let Some(Ok(Message::Text(query))) = ws_stream.next().await else {
+            continue;
+        };
+        let query: Value = serde_json::from_str(&query).unwrap();
+        let id = query["id"].to_string();
+        for frame in frames {
+            let frame = frame.replace("{id}", &id);
+            if ws_stream.send(Message::Text(frame)).await.is_err() {
+                break;
+            }
+        }
+        let _ = ws_stream.close(None).await;
+    }
+}
+
+#[tokio::test]
+async fn query_stream_ends_for_arbitrary_replies() {
+    let mut rng = fastrand::Rng::with_seed(1293);
+    let rounds: Vec<Vec<String>> = (0..40)
+        .map(|_| {
+            (0..rng.usize(..12))
+                .map(|_| {
+                    let mut object = arbitrary_object(&mut rng, 2);
+                    match rng.u8(..3) {
+                        0 => object.insert("id".into(), Value::from("{id}")),
+                        1 => object.remove("id"),
+                        _ => None,
+                    };
+                    Value::Object(object)
+                        .to_string()
+                        .replace("\"{id}\"", "{id}")
+                })
+                .collect()
+        })
+        .collect();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    let server = tokio::spawn(serve_frames(listener, rounds.clone()));
+
+    for frames in &rounds {
+        let client = connect(&url).await.expect("connect should succeed");
+        let stream = client.query_stream("question", None);
+        let items: Vec<Result<StreamEvent, ClientError>> =
+            tokio::time::timeout(Duration::from_secs(10), stream.collect())
+                .await
+                .unwrap_or_else(|_| panic!("query did not end for {:?}", frames));
+        // Only the last item may end the answer.
+        for item in &items[..items.len().saturating_sub(1)] {
+            assert!(
+                matches!(
+                    item,
+                    Ok(StreamEvent::StreamStart
+                        | StreamEvent::StreamChunk(..)
+                        | StreamEvent::Thinking(..)
+                        | StreamEvent::Progress { .. }
+                        | StreamEvent::IndexProgress { .. }
+                        | StreamEvent::Unknown { .. })
+                ),
+                "{:?} before the end of {:?}",
+                item,
+                frames
+            );
+        }
+        let mut collector = StreamCollector::new();
+        for event in items.iter().flatten() {
+            collector.push(event);
+        }
+        let _ = collector.answer_spans();
+    }
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn server_cannot_flood_or_switch_an_idle_client() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    let (query_tx, query_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+        // An encoding the client never offered.
+        let switch = r#"{"type":"capabilities","features":[],"encoding":"msgpack"}"#;
+        ws_stream.send(Message::Text(switch.into())).await.unwrap();
+        for i in 0..300 {
+            let notification = format!(r#"{{"type":"notification","documents":["/{}.md"]}}"#, i);
+            ws_stream.send(Message::Text(notification)).await.unwrap();
+        }
+        let query = ws_stream.next().await.unwrap().unwrap();
+        let _ = query_tx.send(query.is_text());
+        let end = ServerMessage::stream_end(["/a.md"]).to_text();
+        ws_stream.send(Message::Text(end)).await.unwrap();
+        while ws_stream.next().await.is_some() {}
+    });
+
+    let client = connect(&url).await.expect("connect should succeed");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    client.query("question", None).await.unwrap();
+    assert!(query_rx.await.unwrap(), "query should still be JSON text");
+
+    let notifications = client.take_notifications();
+    assert_eq!(notifications.len(), 256);
+    assert_eq!(notifications[255].documents, ["/299.md"]);
+}
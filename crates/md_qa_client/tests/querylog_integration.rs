@@ -0,0 +1,134 @@
+//! Integration tests for the local query log and the gaps report.
+
+use md_qa_client::messages::Usage;
+use md_qa_client::querylog::{self, LogEntry};
+use md_qa_client::{Price, PriceTable};
+
+fn query(timestamp: u64, question: &str, sources: usize, error: Option<&str>) -> LogEntry {
+    LogEntry::Query {
+        timestamp,
+        question: question.into(),
+        index: None,
+        sources,
+        error: error.map(str::to_string),
+        usage: None,
+        answer: None,
+        source_paths: Vec::new(),
+    }
+}
+
+#[test]
+fn append_and_load_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = querylog::path_for_config(&dir.path().join("nested").join("config.yaml"));
+    assert_eq!(path.file_name().unwrap(), "queries.jsonl");
+    assert!(querylog::load(&path).unwrap().is_empty());
+
+    let entries = vec![
+        query(1, "What is X?", 2, None),
+        LogEntry::Feedback {
+            timestamp: 2,
+            question: "What is X?".into(),
+            helpful: false,
+        },
+    ];
+    for entry in &entries {
+        querylog::append(&path, entry).unwrap();
+    }
+    std::fs::write(
+        &path,
+        std::fs::read_to_string(&path).unwrap() + "not json\n",
+    )
+    .unwrap();
+
+    assert_eq!(querylog::load(&path).unwrap(), entries);
+}
+
+#[test]
+fn gaps_group_questions_and_rank_worst_first() {
+    let entries = vec![
+        query(1, "How do I deploy?", 0, None),
+        query(2, "how do I   deploy", 0, None),
+        query(3, "What is X?", 3, None),
+        query(4, "Where are the logs?", 0, Some("Server not ready.")),
+        LogEntry::Feedback {
+            timestamp: 5,
+            question: "What is X?".into(),
+            helpful: true,
+        },
+    ];
+
+    let gaps = querylog::gaps(&entries);
+    assert_eq!(gaps.len(), 2, "answered-and-helpful questions are not gaps");
+    assert_eq!(gaps[0].question, "how do I   deploy");
+    assert_eq!(gaps[0].asked, 2);
+    assert_eq!(gaps[0].unanswered, 2);
+    assert_eq!(gaps[1].question, "Where are the logs?");
+}
+
+#[test]
+fn unhelpful_feedback_makes_answered_question_a_gap() {
+    let entries = vec![
+        query(1, "What is X?", 3, None),
+        LogEntry::Feedback {
+            timestamp: 2,
+            question: "What is X?".into(),
+            helpful: false,
+        },
+    ];
+
+    let gaps = querylog::gaps(&entries);
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(gaps[0].unanswered, 0);
+    assert_eq!(gaps[0].unhelpful, 1);
+    assert_eq!(gaps[0].last_seen, 2);
+}
+
+#[test]
+fn usage_sums_logged_answers_and_prices_known_models() {
+    let usage = |prompt_tokens, completion_tokens, model: Option<&str>| Usage {
+        prompt_tokens,
+        completion_tokens,
+        model: model.map(str::to_string),
+    };
+    let mut entries = vec![
+        query(1, "a", 1, None),
+        query(2, "b", 1, None),
+        query(3, "c", 1, None),
+        query(4, "d", 0, Some("Server not ready.")),
+    ];
+    let usages = [
+        usage(2_000_000, 1_000_000, None),
+        usage(1_000_000, 0, Some("big")),
+        usage(100, 50, Some("local")),
+    ];
+    for (entry, logged) in entries.iter_mut().zip(usages) {
+        if let LogEntry::Query { usage, .. } = entry {
+            *usage = Some(logged);
+        }
+    }
+    let prices = PriceTable::from([
+        (
+            "small".to_string(),
+            Price {
+                prompt: 0.5,
+                completion: 1.0,
+            },
+        ),
+        (
+            "big".to_string(),
+            Price {
+                prompt: 10.0,
+                completion: 30.0,
+            },
+        ),
+    ]);
+
+    let totals = querylog::usage(&entries, &prices, Some("small"));
+    assert_eq!(totals.answers, 3);
+    assert_eq!(totals.prompt_tokens, 3_000_100);
+    assert_eq!(totals.completion_tokens, 1_000_050);
+    assert_eq!(totals.total_tokens(), 4_000_150);
+    assert_eq!(totals.unpriced, 1, "no price for `local`");
+    assert!((totals.cost - 12.0).abs() < 1e-9);
+}
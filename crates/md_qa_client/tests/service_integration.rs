@@ -0,0 +1,125 @@
+//! Integration tests for the `QaService` facade against an in-process
+//! WebSocket server. No mocks.
+
+use futures_util::{SinkExt, StreamExt};
+use md_qa_client::{ClientError, Config, QaService, StreamEvent};
+use tokio::net::TcpListener;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Accept `connections` connections one after another and answer each
+/// query on them with "About {question}", passing the query on to the
+/// returned receiver. The question "fail" gets an ERROR reply instead, and
+/// the connection is dropped after answering "close".
+async fn spawn_server(
+    connections: usize,
+) -> (u16, tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        for _ in 0..connections {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(tcp_stream).await.unwrap();
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let query: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let question = query["question"].as_str().unwrap().to_string();
+                let id = query["id"].clone();
+                tx.send(query).unwrap();
+                let answer = if question == "fail" {
+                    vec![
+                        serde_json::json!({"type": "error", "id": id, "message": "Index not ready"}),
+                    ]
+                } else {
+                    vec![
+                        serde_json::json!({"type": "stream_chunk", "id": id, "chunk": format!("About {}", question)}),
+                        serde_json::json!({"type": "stream_end", "id": id, "sources": ["/docs/a.md"]}),
+                    ]
+                };
+                for frame in answer {
+                    ws.send(Message::Text(frame.to_string())).await.unwrap();
+                }
+                if question == "close" {
+                    break;
+                }
+            }
+        }
+    });
+    (port, rx)
+}
+
+fn service(port: u16) -> QaService {
+    QaService::builder()
+        .config(Config::default())
+        .url(&format!("ws://127.0.0.1:{}", port))
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn follow_up_questions_carry_the_conversation() {
+    let (port, mut queries) = spawn_server(1).await;
+    let service = service(port);
+
+    let first = service.ask("setup").await.unwrap().finish().await.unwrap();
+    assert_eq!(first.answer, "About setup");
+    assert!(queries.recv().await.unwrap().get("history").is_none());
+
+    // The answer can also be read event by event.
+    let mut stream = service.ask("point 2").await.unwrap();
+    assert_eq!(stream.question(), "point 2");
+    let mut events = Vec::new();
+    while let Some(event) = stream.next().await {
+        events.push(event.unwrap());
+    }
+    assert_eq!(events[0].answer_text(), Some("About point 2"));
+    assert!(matches!(events[1], StreamEvent::StreamEnd { .. }));
+    let follow_up = queries.recv().await.unwrap();
+    assert_eq!(
+        follow_up["history"],
+        serde_json::json!([{"question": "setup", "answer": "About setup"}])
+    );
+
+    let history = service.history();
+    let turns: Vec<_> = history
+        .active_path()
+        .iter()
+        .map(|t| t.question.clone())
+        .collect();
+    assert_eq!(turns, ["setup", "point 2"]);
+    assert_eq!(history.active_path()[0].sources, ["/docs/a.md"]);
+
+    service.clear_history();
+    service.ask("again").await.unwrap().finish().await.unwrap();
+    assert!(queries.recv().await.unwrap().get("history").is_none());
+}
+
+#[tokio::test]
+async fn reconnects_after_the_connection_is_lost() {
+    let (port, mut queries) = spawn_server(2).await;
+    let service = QaService::builder()
+        .config(Config::default())
+        .url(&format!("ws://127.0.0.1:{}", port))
+        .history(false)
+        .build()
+        .unwrap();
+
+    service.ask("close").await.unwrap().finish().await.unwrap();
+    queries.recv().await.unwrap();
+    // Give the client a moment to notice the server hung up.
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let answer = service.ask("next").await.unwrap().finish().await.unwrap();
+    assert_eq!(answer.answer, "About next");
+    assert_eq!(queries.recv().await.unwrap()["question"], "next");
+    assert!(service.history().is_empty());
+
+    let err = service
+        .ask("fail")
+        .await
+        .unwrap()
+        .finish()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::ServerError(m) if m.message == "Index not ready"));
+}
@@ -0,0 +1,92 @@
+//! Integration tests for `wss://` connections against an in-process TLS
+//! WebSocket server using the certificates in tests/fixtures/tls.
+
+use md_qa_client::messages::{Citations, QueryStats};
+use md_qa_client::{ClientBuilder, ClientError, Config, StreamEvent};
+use tokio::net::TcpListener;
+
+const CA_PEM: &[u8] = include_bytes!("fixtures/tls/ca.pem");
+const SERVER_PEM: &[u8] = include_bytes!("fixtures/tls/server.pem");
+const SERVER_KEY: &[u8] = include_bytes!("fixtures/tls/server.key");
+
+/// Spawn a TLS WebSocket server that answers one query; returns its `wss://` URL.
+async fn spawn_wss_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let identity = native_tls::Identity::from_pkcs8(SERVER_PEM, SERVER_KEY).unwrap();
+    let acceptor =
+        tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let Ok(tls_stream) = acceptor.accept(tcp_stream).await else {
+            return;
+        };
+        let mut ws_stream = tokio_tungstenite::accept_async(tls_stream).await.unwrap();
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+        let _ = ws_stream.next().await;
+        for frame in [
+            r#"{"type":"stream_start"}"#,
+            r#"{"type":"stream_end","sources":["/secure.md"]}"#,
+        ] {
+            ws_stream.send(Message::Text(frame.into())).await.unwrap();
+        }
+    });
+    format!("wss://localhost:{}", port)
+}
+
+async fn ask(builder: ClientBuilder) -> Vec<StreamEvent> {
+    let client = builder.connect().await.expect("connect should succeed");
+    client
+        .query("question", None)
+        .await
+        .expect("query should succeed")
+}
+
+#[tokio::test]
+async fn wss_trusts_ca_cert_from_config() {
+    let url = spawn_wss_server().await;
+    let dir = tempfile::tempdir().unwrap();
+    let ca_path = dir.path().join("ca.pem");
+    std::fs::write(&ca_path, CA_PEM).unwrap();
+
+    let mut cfg = Config::default();
+    cfg.client.url = Some(url);
+    cfg.client.ca_cert = Some(ca_path.to_string_lossy().into_owned());
+    let events = ask(ClientBuilder::from_config(&cfg).unwrap()).await;
+
+    assert!(events.contains(&StreamEvent::StreamEnd {
+        sources: vec!["/secure.md".into()],
+        grounding: None,
+        citations: Citations::new(),
+        stats: QueryStats::default(),
+    }));
+}
+
+#[tokio::test]
+async fn wss_rejects_unknown_issuer_by_default() {
+    let url = spawn_wss_server().await;
+
+    let err = match ClientBuilder::new(&url).connect().await {
+        Ok(_) => panic!("untrusted certificate should be rejected"),
+        Err(e) => e,
+    };
+    assert!(matches!(err, ClientError::Connect(_)));
+}
+
+#[tokio::test]
+async fn wss_accepts_self_signed_when_allowed() {
+    let url = spawn_wss_server().await;
+
+    let events = ask(ClientBuilder::new(&url).danger_accept_invalid_certs(true)).await;
+    assert_eq!(events[0], StreamEvent::StreamStart);
+}
+
+#[test]
+fn missing_ca_cert_file_is_a_config_error() {
+    let mut cfg = Config::default();
+    cfg.client.ca_cert = Some("/nonexistent/ca.pem".into());
+
+    let err = ClientBuilder::from_config(&cfg).expect_err("missing file should fail");
+    assert!(err.to_string().contains("ca_cert"));
+}
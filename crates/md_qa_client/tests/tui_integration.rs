@@ -3,7 +3,6 @@
 //! WebSocket server. No mocks. Tests should fail until task 4.2 implementation.
 
 use assert_cmd::cargo::cargo_bin_cmd;
-use assert_cmd::Command;
 use predicates::prelude::*;
 use std::io::Write as _;
 use std::net::TcpListener as StdTcpListener;
@@ -31,6 +30,22 @@ fn write_config(dir: &tempfile::TempDir, port: u16) -> std::path::PathBuf {
 /// message then replies with STREAM_START, one STREAM_CHUNK, and STREAM_END.
 /// Returns a join handle; drops the listener when the handle is dropped.
 fn spawn_test_server(port: u16) -> std::thread::JoinHandle<()> {
+    spawn_server_ending_with(
+        port,
+        r#"{"type":"stream_end","sources":["/docs/a.md","/docs/b.md"]}"#,
+    )
+}
+
+/// Like `spawn_test_server`, but STREAM_END also reports the token usage and
+/// the server time `--stats` prints and the query log keeps.
+fn spawn_stats_server(port: u16) -> std::thread::JoinHandle<()> {
+    spawn_server_ending_with(
+        port,
+        r#"{"type":"stream_end","sources":["/docs/a.md","/docs/b.md"],"usage":{"prompt_tokens":120,"completion_tokens":30},"elapsed_ms":2350}"#,
+    )
+}
+
+fn spawn_server_ending_with(port: u16, stream_end: &'static str) -> std::thread::JoinHandle<()> {
     std::thread::spawn(move || {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
@@ -63,12 +78,7 @@ fn spawn_test_server(port: u16) -> std::thread::JoinHandle<()> {
                 ))
                 .await
                 .unwrap();
-            write
-                .send(Message::Text(
-                    r#"{"type":"stream_end","sources":["/docs/a.md","/docs/b.md"]}"#.into(),
-                ))
-                .await
-                .unwrap();
+            write.send(Message::Text(stream_end.into())).await.unwrap();
 
             // Small delay so the client can read before we drop.
             tokio::time::sleep(std::time::Duration::from_millis(200)).await;
@@ -93,7 +103,7 @@ fn tui_prints_streamed_answer_and_sources() {
     std::thread::sleep(std::time::Duration::from_millis(100));
 
     // Run the binary, passing the config path and a question on stdin.
-    let mut cmd = Command::from(cargo_bin_cmd!("md-qa"));
+    let mut cmd = cargo_bin_cmd!("md-qa");
     cmd.arg("--config")
         .arg(&config_path)
         .write_stdin("What is the answer?\n");
@@ -102,7 +112,61 @@ fn tui_prints_streamed_answer_and_sources() {
         .success()
         .stdout(predicate::str::contains("Test answer."))
         .stdout(predicate::str::contains("/docs/a.md"))
-        .stdout(predicate::str::contains("/docs/b.md"));
+        .stdout(predicate::str::contains("/docs/b.md"))
+        .stdout(predicate::str::contains("Stats:").not());
+}
+
+#[test]
+fn tui_stats_prints_usage_and_server_time() {
+    let port = free_port();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+    let _server = spawn_stats_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .arg("--stats")
+        .arg("What is the answer?");
+
+    cmd.assert().success().stdout(predicate::str::ends_with(
+        "\nStats: 120 prompt + 30 completion tokens, 2.35 s\n",
+    ));
+}
+
+#[test]
+fn tui_edit_writes_answer_file_and_opens_editor() {
+    let port = free_port();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+    let _server = spawn_test_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // `cat` stands in for the editor: it prints the file it is given.
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .arg("--edit")
+        .arg("What is the answer?")
+        .env("TMPDIR", dir.path())
+        .env_remove("VISUAL")
+        .env("EDITOR", "cat");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+
+    let path = stdout
+        .lines()
+        .find(|line| line.ends_with(".md") && line.contains("md-qa-answer-"))
+        .expect("answer file path should be printed");
+    let contents = std::fs::read_to_string(path).unwrap();
+    assert!(contents.starts_with("# What is the answer?\n"));
+    assert!(contents.contains("Test answer."));
+    assert!(contents.contains("/docs/a.md"));
+    assert!(
+        stdout.contains("Test answer."),
+        "editor should see the answer"
+    );
 }
 
 #[test]
@@ -115,7 +179,7 @@ fn tui_with_config_env_var() {
     std::thread::sleep(std::time::Duration::from_millis(100));
 
     // Use MD_QA_CONFIG env var instead of --config flag.
-    let mut cmd = Command::from(cargo_bin_cmd!("md-qa"));
+    let mut cmd = cargo_bin_cmd!("md-qa");
     cmd.env("MD_QA_CONFIG", &config_path)
         .write_stdin("What is the answer?\n");
 
@@ -134,7 +198,7 @@ fn tui_with_positional_question_argument() {
     std::thread::sleep(std::time::Duration::from_millis(100));
 
     // Provide question as a positional argument (no stdin piping).
-    let mut cmd = Command::from(cargo_bin_cmd!("md-qa"));
+    let mut cmd = cargo_bin_cmd!("md-qa");
     cmd.arg("--config")
         .arg(&config_path)
         .arg("What is the answer?");
@@ -151,7 +215,7 @@ fn tui_server_down_shows_error() {
     let dir = tempfile::tempdir().unwrap();
     let config_path = write_config(&dir, port);
 
-    let mut cmd = Command::from(cargo_bin_cmd!("md-qa"));
+    let mut cmd = cargo_bin_cmd!("md-qa");
     cmd.arg("--config")
         .arg(&config_path)
         .write_stdin("hello\n");
@@ -161,3 +225,581 @@ fn tui_server_down_shows_error() {
         .failure()
         .stderr(predicate::str::is_match("(?i)(connect|error|refused|disconnected)").unwrap());
 }
+
+#[test]
+fn tui_server_down_exits_with_connect_code() {
+    let port = free_port();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config").arg(&config_path).arg("hello");
+
+    cmd.assert()
+        .code(3)
+        .stderr(predicate::str::contains("could not connect"));
+}
+
+#[test]
+fn tui_logs_queries_next_to_config() {
+    let port = free_port();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+
+    let _server = spawn_stats_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .arg("What is the answer?");
+    cmd.assert().success();
+
+    let log = std::fs::read_to_string(dir.path().join("queries.jsonl")).unwrap();
+    assert!(log.contains(r#""question":"What is the answer?""#));
+    assert!(log.contains(r#""sources":2"#));
+    assert!(log.contains(r#""usage":{"prompt_tokens":120,"completion_tokens":30"#));
+    assert!(log.contains(r#""answer":"Test answer.""#));
+    assert!(log.contains(r#""source_paths":["/docs/a.md","/docs/b.md"]"#));
+}
+
+#[test]
+fn tui_history_export_renders_the_last_answer() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    let template = dir.path().join("snippet.md");
+    std::fs::write(&template, "{{ question }} => {{ answer }}\n{{ sources }}\n").unwrap();
+    std::fs::write(
+        &config_path,
+        format!("export:\n  snippet_template: {}\n", template.display()),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("queries.jsonl"),
+        concat!(
+            r#"{"kind":"query","timestamp":1,"question":"How to deploy?","sources":1,"answer":"Run make.","source_paths":["/docs/deploy.md"]}"#,
+            "\n",
+            r#"{"kind":"query","timestamp":2,"question":"Broken?","sources":0,"error":"Index not ready"}"#,
+            "\n",
+        ),
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .args(["history", "export"]);
+    cmd.assert()
+        .success()
+        .stdout("How to deploy? => Run make.\n- /docs/deploy.md\n");
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .args(["history", "export", "--as", "issue"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "### Question\n\nHow to deploy?\n",
+        ))
+        .stdout(predicate::str::contains(
+            "### Sources\n\n- /docs/deploy.md\n",
+        ));
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.args(["history", "export", "--as", "pdf"]);
+    cmd.assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid export format: pdf"));
+}
+
+#[test]
+fn tui_report_gaps_lists_unanswered_questions() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, free_port());
+    std::fs::write(
+        dir.path().join("queries.jsonl"),
+        concat!(
+            r#"{"kind":"query","timestamp":1,"question":"How do I deploy?","sources":0}"#,
+            "\n",
+            r#"{"kind":"query","timestamp":2,"question":"What is X?","sources":3}"#,
+            "\n",
+        ),
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .arg("report")
+        .arg("gaps");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("How do I deploy?"))
+        .stdout(predicate::str::contains("What is X?").not());
+}
+
+#[test]
+fn tui_history_cost_sums_logged_usage() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        "api:\n  llm_model: gpt-4o-mini\nprices:\n  gpt-4o-mini:\n    prompt: 0.15\n    completion: 0.6\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("queries.jsonl"),
+        concat!(
+            r#"{"kind":"query","timestamp":1,"question":"a","sources":1,"usage":{"prompt_tokens":1000000,"completion_tokens":500000}}"#,
+            "\n",
+            r#"{"kind":"query","timestamp":2,"question":"b","sources":1,"usage":{"prompt_tokens":10,"completion_tokens":5,"model":"local"}}"#,
+            "\n",
+            r#"{"kind":"query","timestamp":3,"question":"c","sources":0}"#,
+            "\n",
+        ),
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .arg("history")
+        .arg("cost");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Answers:           2"))
+        .stdout(predicate::str::contains("Prompt tokens:     1000010"))
+        .stdout(predicate::str::contains("Estimated cost:    0.4500"))
+        .stdout(predicate::str::contains("1 answers left out"));
+}
+
+#[test]
+fn tui_warns_about_duplicate_config_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        "api:\n  llm_model: old\n  llm_model: new\nserver:\n  port: 8765\n",
+    )
+    .unwrap();
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .arg("history")
+        .arg("cost");
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "Warning: {}: duplicate key `api.llm_model`; the last value is used",
+            config_path.display()
+        )));
+}
+
+#[test]
+fn tui_followups_prints_suggested_questions() {
+    let port = free_port();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+
+    let (request_tx, request_rx) = std::sync::mpsc::channel();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let _ = ws.next().await;
+            for frame in [
+                r#"{"type":"stream_start"}"#,
+                r#"{"type":"stream_chunk","chunk":"Test answer."}"#,
+                r#"{"type":"stream_end","sources":["/docs/a.md"]}"#,
+            ] {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            let request = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            request_tx.send(request).unwrap();
+            ws.send(Message::Text(
+                r#"{"type":"followups","questions":["How is a.md built?","What else links to it?"]}"#
+                    .into(),
+            ))
+            .await
+            .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .arg("--followups")
+        .arg("What is the answer?");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Follow-up questions:"))
+        .stdout(predicate::str::contains("  1. How is a.md built?"))
+        .stdout(predicate::str::contains("  2. What else links to it?"));
+
+    let request: serde_json::Value = serde_json::from_str(&request_rx.recv().unwrap()).unwrap();
+    assert_eq!(
+        request,
+        serde_json::json!({
+            "type": "suggest_followups",
+            "question": "What is the answer?",
+            "answer": "Test answer.",
+            "sources": ["/docs/a.md"],
+            "count": 3
+        })
+    );
+}
+
+/// A git repository in `dir` with `notes.md` committed and an edit staged.
+fn init_repo_with_staged_change(dir: &std::path::Path) {
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    std::fs::write(dir.join("notes.md"), "Cache for 5 minutes.\n").unwrap();
+    git(&["add", "notes.md"]);
+    git(&[
+        "-c",
+        "user.name=Test",
+        "-c",
+        "user.email=test@example.com",
+        "commit",
+        "-qm",
+        "init",
+    ]);
+    std::fs::write(dir.join("notes.md"), "Cache for 1 hour.\n").unwrap();
+    git(&["add", "notes.md"]);
+}
+
+#[test]
+fn tui_git_diff_attaches_staged_changes() {
+    let port = free_port();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+    let repo = tempfile::tempdir().unwrap();
+    init_repo_with_staged_change(repo.path());
+
+    let (query_tx, query_rx) = std::sync::mpsc::channel();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let query = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            query_tx.send(query).unwrap();
+            let end = r#"{"type":"stream_end","sources":[]}"#;
+            ws.send(Message::Text(end.into())).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.current_dir(repo.path())
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--git-diff")
+        .arg("Does this contradict the design docs?");
+    cmd.assert().success();
+
+    let query: serde_json::Value = serde_json::from_str(&query_rx.recv().unwrap()).unwrap();
+    let attachment = query["attachment"].as_str().unwrap();
+    assert!(attachment.contains("diff --git a/notes.md b/notes.md"));
+    assert!(attachment.contains("-Cache for 5 minutes."));
+    assert!(attachment.contains("+Cache for 1 hour."));
+
+    // An empty range is refused before connecting.
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.current_dir(repo.path())
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--git-diff=HEAD...HEAD")
+        .arg("Anything?");
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("no changes in HEAD...HEAD"));
+}
+
+#[cfg(unix)]
+#[test]
+fn tui_runs_hooks_around_the_question() {
+    let port = free_port();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+    let answer_log = dir.path().join("answer.json");
+    let mut f = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&config_path)
+        .unwrap();
+    writeln!(
+        f,
+        "hooks:\n  pre_query: \"tr a-z A-Z\"\n  post_answer: \"cat > '{}'; exit 1\"\n  on_failure: abort",
+        answer_log.display()
+    )
+    .unwrap();
+
+    let (query_tx, query_rx) = std::sync::mpsc::channel();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let query = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            query_tx.send(query).unwrap();
+            for frame in [
+                r#"{"type":"stream_chunk","chunk":"Test answer."}"#,
+                r#"{"type":"stream_end","sources":["/docs/a.md"]}"#,
+            ] {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // The answer is printed before the failing post_answer hook aborts.
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config").arg(&config_path).arg("what is rrf?");
+    cmd.assert()
+        .code(8)
+        .stdout(predicate::str::contains("Test answer."))
+        .stderr(predicate::str::contains("post_answer hook failed"));
+
+    let query: serde_json::Value = serde_json::from_str(&query_rx.recv().unwrap()).unwrap();
+    assert_eq!(query["question"], "WHAT IS RRF?");
+    let logged: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&answer_log).unwrap()).unwrap();
+    assert_eq!(logged["answer"], "Test answer.");
+    assert_eq!(logged["sources"], serde_json::json!(["/docs/a.md"]));
+}
+
+#[cfg(unix)]
+#[test]
+fn tui_ctrl_c_cancels_the_query() {
+    use std::io::Read as _;
+
+    let port = free_port();
+    let (cancel_tx, cancel_rx) = std::sync::mpsc::channel();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let query = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            let id = serde_json::from_str::<serde_json::Value>(&query).unwrap()["id"].clone();
+            let chunk = serde_json::json!({"type": "stream_chunk", "id": id, "chunk": "Partial"});
+            ws.send(Message::Text(chunk.to_string())).await.unwrap();
+            // Never finishes the answer; waits for the cancel instead.
+            let cancel = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            cancel_tx.send((id, cancel)).unwrap();
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("md-qa"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("a long question")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut printed = Vec::new();
+    while !String::from_utf8_lossy(&printed).contains("Partial") {
+        let mut buf = [0; 64];
+        let n = stdout.read(&mut buf).unwrap();
+        assert!(n > 0, "md-qa exited before printing the chunk");
+        printed.extend_from_slice(&buf[..n]);
+    }
+    let killed = std::process::Command::new("kill")
+        .arg("-INT")
+        .arg(child.id().to_string())
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(130));
+    let (id, cancel) = cancel_rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .unwrap();
+    let cancel: serde_json::Value = serde_json::from_str(&cancel).unwrap();
+    assert_eq!(cancel["type"], "cancel");
+    assert_eq!(cancel["request_id"], id);
+}
+
+#[test]
+fn tui_status_reports_unready_index() {
+    let port = free_port();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let _ = ws.next().await;
+            ws.send(Message::Text(
+                r#"{"type":"status","status":"not_ready","message":"Server loading indexes"}"#
+                    .into(),
+            ))
+            .await
+            .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config").arg(&config_path).arg("status");
+
+    cmd.assert()
+        .code(7)
+        .stdout(predicate::str::contains("Server: not_ready"))
+        .stdout(predicate::str::contains("Server loading indexes"));
+}
+
+#[test]
+fn tui_links_lists_inbound_and_outbound_documents() {
+    let port = free_port();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = write_config(&dir, port);
+
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let request = ws.next().await.unwrap().unwrap();
+            assert_eq!(
+                request.to_text().unwrap(),
+                r#"{"type":"get_links","path":"/docs/a.md"}"#
+            );
+            ws.send(Message::Text(
+                r#"{"type":"links","path":"/docs/a.md","outbound":["/docs/b.md"],"inbound":["/docs/c.md","/docs/d.md"]}"#
+                    .into(),
+            ))
+            .await
+            .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .arg("links")
+        .arg("/docs/a.md");
+
+    cmd.assert().success().stdout(predicate::str::diff(
+        "Links to (1):\n  /docs/b.md\nLinked from (2):\n  /docs/c.md\n  /docs/d.md\n",
+    ));
+}
+
+#[test]
+fn tui_config_new_renders_template() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("team.yaml.tmpl");
+    std::fs::write(
+        &template,
+        "api:\n  base_url: https://llm.example.com/v1\n  api_key: \"{{key}}\"\nserver:\n  directories: [\"{{ repo }}\"]\n",
+    )
+    .unwrap();
+    let config_path = dir.path().join("md-qa").join("config.yaml");
+
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .args(["config", "new", "--template"])
+        .arg(&template)
+        .args(["--var", "repo=/src/app", "--var", "key=k1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote"));
+    let written = std::fs::read_to_string(&config_path).unwrap();
+    assert!(written.contains("api_key: \"k1\""));
+    assert!(written.contains("[\"/src/app\"]"));
+
+    // An existing config is only replaced with --force.
+    let mut cmd = cargo_bin_cmd!("md-qa");
+    cmd.arg("--config")
+        .arg(&config_path)
+        .args(["config", "new", "--template"])
+        .arg(&template)
+        .args(["--var", "repo=/other", "--var", "key=k2"]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("--force"));
+}
@@ -1,8 +1,18 @@
 //! Integration tests for WebSocket client: connect, send query, receive stream.
 //! Uses a minimal in-process WebSocket server (no mocks). Fail until task 3.3.
 
-use md_qa_client::{connect, StreamEvent};
+use md_qa_client::messages::{
+    Citations, ErrorCode, ErrorMessage, Grounding, QueryFilters, QueryMessage, QueryOptions,
+    QueryStats, ServerFrame, ServerMessage, Source, Usage,
+};
+use md_qa_client::{
+    cite_answer, connect, connect_with_options, group_sources, AnswerSpan, ChunkMeta,
+    ClientBuilder, ClientError, ClientOptions, ConnectionEvent, Conversation, FrameDirection,
+    FrameTap, RetrievalSection, RetryPolicy, Side, StreamCollector, StreamEvent, TimeoutKind,
+    WireEncoding,
+};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio_tungstenite::accept_async;
 
@@ -55,7 +65,7 @@ async fn connect_and_receive_stream() {
     let chunks: Vec<String> = events
         .iter()
         .filter_map(|e| {
-            if let StreamEvent::StreamChunk(s) = e {
+            if let StreamEvent::StreamChunk(s, _) = e {
                 Some(s.clone())
             } else {
                 None
@@ -66,10 +76,10 @@ async fn connect_and_receive_stream() {
     assert_eq!(chunks.join(""), "Hello.");
     let end_events: Vec<_> = events
         .iter()
-        .filter(|e| matches!(e, StreamEvent::StreamEnd(_)))
+        .filter(|e| matches!(e, StreamEvent::StreamEnd { .. }))
         .collect();
     assert_eq!(end_events.len(), 1);
-    if let StreamEvent::StreamEnd(sources) = &end_events[0] {
+    if let StreamEvent::StreamEnd { sources, .. } = &end_events[0] {
         assert_eq!(sources.as_slice(), ["/a.md", "/b.md"]);
     }
 }
@@ -117,14 +127,69 @@ async fn stream_end_sources_are_deduplicated() {
 
     let end_events: Vec<_> = events
         .iter()
-        .filter(|e| matches!(e, StreamEvent::StreamEnd(_)))
+        .filter(|e| matches!(e, StreamEvent::StreamEnd { .. }))
         .collect();
     assert_eq!(end_events.len(), 1);
-    if let StreamEvent::StreamEnd(sources) = &end_events[0] {
+    if let StreamEvent::StreamEnd { sources, .. } = &end_events[0] {
         assert_eq!(sources.as_slice(), ["/a.md", "/b.md"]);
     }
 }
 
+#[tokio::test]
+async fn stream_end_accepts_structured_sources() {
+    use futures_util::{SinkExt, StreamExt};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        let stream_end = r#"{"type":"stream_end","sources":[
+            {"path":"/a.md","heading":"Install","score":0.82,"snippet":"Run cargo install.",
+             "start_line":12,"end_line":30},
+            "/b.md",
+            {"file":"/c.md"},
+            {"path":"/a.md","heading":"Upgrade","start_line":31,"end_line":40},
+            "/b.md"
+        ]}"#;
+        ws.send(tokio_tungstenite::tungstenite::Message::Text(
+            stream_end.into(),
+        ))
+        .await
+        .unwrap();
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let events = client.query("How to install?", None).await.unwrap();
+    let StreamEvent::StreamEnd { sources, .. } = &events[0] else {
+        panic!("expected StreamEnd, got {:?}", events);
+    };
+    assert_eq!(
+        sources.as_slice(),
+        [
+            Source {
+                path: "/a.md".into(),
+                heading: Some("Install".into()),
+                score: Some(0.82),
+                snippet: Some("Run cargo install.".into()),
+                start_line: Some(12),
+                end_line: Some(30),
+            },
+            "/b.md".into(),
+            "/c.md".into(),
+            Source {
+                heading: Some("Upgrade".into()),
+                start_line: Some(31),
+                end_line: Some(40),
+                ..Source::from("/a.md")
+            },
+        ]
+    );
+    assert_eq!(sources[0].location(), "/a.md:12-30");
+    assert_eq!(sources[1].location(), "/b.md");
+}
+
 #[tokio::test]
 async fn receive_error_message() {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -159,7 +224,7 @@ async fn receive_error_message() {
         .iter()
         .filter_map(|e| {
             if let StreamEvent::Error(s) = e {
-                Some(s.as_str())
+                Some(s.message.as_str())
             } else {
                 None
             }
@@ -168,3 +233,2571 @@ async fn receive_error_message() {
     assert_eq!(err_events.len(), 1);
     assert_eq!(err_events[0], "Server not ready.");
 }
+
+#[test]
+fn invalid_server_messages_name_the_type_and_field() {
+    let err = ServerFrame::parse(r#"{"type":"stream_end","sources":"a.md"}"#).unwrap_err();
+    assert_eq!(err.message_type.as_deref(), Some("stream_end"));
+    assert_eq!(err.field.as_deref(), Some("sources"));
+    assert!(
+        err.reason.starts_with("invalid type: string"),
+        "{}",
+        err.reason
+    );
+    assert!(err
+        .to_string()
+        .starts_with("invalid `stream_end` message: field `sources`: invalid type"));
+
+    let err = ServerFrame::parse(r#"{"type":"stream_chunk","chunk":"x","role":1}"#).unwrap_err();
+    assert_eq!(err.field.as_deref(), Some("role"));
+    let err = ServerFrame::parse(r#"{"type":"stream_chunk","id":"q1","chunk":"x"}"#).unwrap_err();
+    assert_eq!(err.field.as_deref(), Some("id"));
+
+    let err = ServerFrame::parse(r#"{"type":"error"}"#).unwrap_err();
+    assert_eq!(
+        (err.field.as_deref(), err.reason.as_str()),
+        (Some("message"), "missing field")
+    );
+    let err = ServerFrame::parse(r#"{"chunk":"x"}"#).unwrap_err();
+    assert_eq!(err.field.as_deref(), Some("type"));
+
+    // Debug builds (the tests) keep the payload for diagnosis.
+    let err = ServerFrame::parse("[1, 2]").unwrap_err();
+    assert_eq!(err.reason, "expected a JSON object");
+    assert_eq!(err.payload.as_deref(), Some("[1, 2]"));
+    assert!(err.to_string().ends_with("(payload: [1, 2])"));
+}
+
+#[test]
+fn camel_case_server_messages_parse_like_snake_case() {
+    let frame =
+        ServerFrame::parse(r#"{"type":"streamChunk","id":2,"chunk":"x","finishReason":"stop"}"#)
+            .unwrap();
+    assert_eq!(frame.id, Some(2));
+    assert!(matches!(
+        frame.message,
+        ServerMessage::StreamChunk { finish_reason: Some(ref r), .. } if r == "stop"
+    ));
+
+    let end = r#"{"type":"streamEnd","elapsedMs":12,
+        "sources":[{"path":"a.md","startLine":3,"endLine":5}],
+        "usage":{"promptTokens":10,"completionTokens":4}}"#;
+    let ServerMessage::StreamEnd {
+        sources,
+        usage,
+        elapsed_ms,
+        ..
+    } = ServerFrame::parse(end).unwrap().message
+    else {
+        panic!("not a stream_end");
+    };
+    assert_eq!(sources[0].location(), "a.md:3-5");
+    assert_eq!(
+        usage.map(|u| (u.prompt_tokens, u.completion_tokens)),
+        Some((10, 4))
+    );
+    assert_eq!(elapsed_ms, Some(12));
+
+    let stats = r#"{"type":"indexStats","documents":1,"chunks":2,"sizeBytes":64,"builtAt":9,
+        "chunkCache":{"size":3,"capacity":256,"hits":2,"misses":1,"hitRate":0.6667}}"#;
+    let ServerMessage::IndexStats(stats) = ServerFrame::parse(stats).unwrap().message else {
+        panic!("not index_stats");
+    };
+    assert_eq!((stats.size_bytes, stats.built_at), (Some(64), Some(9)));
+    assert_eq!(
+        stats.chunk_cache.as_ref().map(|cache| cache.hit_rate),
+        Some(Some(0.6667))
+    );
+    // They are sent back in the protocol's own form.
+    assert!(ServerMessage::IndexStats(stats)
+        .to_text()
+        .contains(r#""type":"index_stats""#));
+}
+
+#[tokio::test]
+async fn malformed_server_message_fails_the_query_with_its_field() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        use futures_util::{SinkExt, StreamExt};
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        let frame = r#"{"type":"stream_end","sources":[],"usage":{"prompt_tokens":-1}}"#;
+        ws.send(tokio_tungstenite::tungstenite::Message::Text(frame.into()))
+            .await
+            .unwrap();
+        let _ = ws.next().await;
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let err = client.query("question", None).await.unwrap_err();
+    assert!(matches!(err, ClientError::Protocol(_)), "{:?}", err);
+    assert!(
+        err.to_string()
+            .starts_with("protocol error: invalid `stream_end` message: field `usage`"),
+        "{}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn unknown_message_types_are_passed_on_without_ending_the_query() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        use futures_util::{SinkExt, StreamExt};
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        for frame in [
+            r#"{"type":"stream_chunk","chunk":"Hello"}"#,
+            r#"{"type":"citation_preview","path":"a.md"}"#,
+            r#"{"type":"stream_end","sources":["a.md"]}"#,
+        ] {
+            ws.send(tokio_tungstenite::tungstenite::Message::Text(frame.into()))
+                .await
+                .unwrap();
+        }
+        let _ = ws.next().await;
+    });
+
+    let frame = ServerFrame::parse(r#"{"type":"answer","id":3,"text":"x"}"#).unwrap();
+    assert_eq!(frame.id, Some(3));
+    assert_eq!(
+        frame.message,
+        ServerMessage::Unknown {
+            typ: "answer".into(),
+            payload: serde_json::json!({"type": "answer", "id": 3, "text": "x"}),
+        }
+    );
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let events = client.query("question", None).await.unwrap();
+    assert_eq!(events.len(), 3, "{:?}", events);
+    assert_eq!(
+        events[1],
+        StreamEvent::Unknown {
+            typ: "citation_preview".into(),
+            payload: serde_json::json!({"type": "citation_preview", "path": "a.md"}),
+        }
+    );
+    let mut collector = StreamCollector::new();
+    events.iter().for_each(|event| collector.push(event));
+    assert_eq!(
+        (collector.answer.as_str(), collector.error),
+        ("Hello", None)
+    );
+}
+
+#[tokio::test]
+async fn verify_query_receives_grounding() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let ws_stream = accept_async(tcp_stream).await.unwrap();
+        let (mut write, mut read) = ws_stream.split();
+        use futures_util::SinkExt;
+        use futures_util::StreamExt;
+        let query = read.next().await.unwrap().unwrap().into_text().unwrap();
+        let query: serde_json::Value = serde_json::from_str(&query).unwrap();
+        assert_eq!(query["verify"], true);
+        let stream_end = r#"{"type":"stream_end","sources":["/a.md"],"grounding":{"score":0.5,"unsupported":["The moon is cheese."]}}"#;
+        for frame in [r#"{"type":"stream_start"}"#, stream_end] {
+            write
+                .send(tokio_tungstenite::tungstenite::Message::Text(frame.into()))
+                .await
+                .unwrap();
+        }
+    });
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let client = connect(&url).await.expect("connect should succeed");
+    let msg = QueryMessage::new("Is the moon cheese?", None).with_verify(true);
+    let events = client
+        .query_message(&msg)
+        .await
+        .expect("query should succeed");
+
+    let grounding = events
+        .iter()
+        .find_map(|e| match e {
+            StreamEvent::StreamEnd { grounding, .. } => grounding.clone(),
+            _ => None,
+        })
+        .expect("stream_end should carry grounding");
+    assert_eq!(grounding.score, 0.5);
+    assert_eq!(grounding.unsupported, vec!["The moon is cheese."]);
+}
+
+#[tokio::test]
+async fn query_options_are_sent_with_the_query() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (query_tx, query_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        use futures_util::{SinkExt, StreamExt};
+        let query = ws.next().await.unwrap().unwrap().into_text().unwrap();
+        query_tx.send(query).unwrap();
+        let end = r#"{"type":"stream_end","sources":[]}"#;
+        ws.send(tokio_tungstenite::tungstenite::Message::Text(end.into()))
+            .await
+            .unwrap();
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let options = QueryOptions {
+        top_k: Some(3),
+        language: Some("de".to_string()),
+        ..QueryOptions::default()
+    };
+    let mut filters = QueryFilters::default();
+    filters.add("dir:docs/api").unwrap();
+    filters.add("glob:*.md").unwrap();
+    assert!(filters.add("docs").is_err());
+    let msg = QueryMessage::new("Wie?", None)
+        .with_options(options)
+        .with_filters(filters);
+    client.query_message(&msg).await.unwrap();
+
+    let query: serde_json::Value = serde_json::from_str(&query_rx.await.unwrap()).unwrap();
+    assert_eq!(
+        query,
+        serde_json::json!({
+            "type": "query",
+            "id": msg.id,
+            "question": "Wie?",
+            "top_k": 3,
+            "language": "de",
+            "filters": {"directories": ["docs/api"], "globs": ["*.md"]}
+        })
+    );
+}
+
+#[tokio::test]
+async fn compare_stream_runs_both_queries_at_once() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    // Neither side answers before both queries have arrived, so the
+    // comparison only finishes if they run in parallel.
+    let both_asked = std::sync::Arc::new(tokio::sync::Barrier::new(2));
+    let warmups = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counted = warmups.clone();
+    let closes = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let closed = closes.clone();
+    tokio::spawn(async move {
+        for _ in 0..2 {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let (both_asked, warmups) = (both_asked.clone(), counted.clone());
+            let closes = closed.clone();
+            tokio::spawn(async move {
+                let mut ws = accept_async(tcp_stream).await.unwrap();
+                let query = loop {
+                    let text = ws.next().await.unwrap().unwrap().into_text().unwrap();
+                    let message: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    if message["type"] != "warmup" {
+                        break message;
+                    }
+                    warmups.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let ready = r#"{"type":"status","status":"ready"}"#;
+                    ws.send(Message::Text(ready.into())).await.unwrap();
+                };
+                both_asked.wait().await;
+                let chunk = serde_json::json!({"type": "stream_chunk", "chunk": query["model"]});
+                ws.send(Message::Text(chunk.to_string())).await.unwrap();
+                let end = r#"{"type":"stream_end","sources":[]}"#;
+                ws.send(Message::Text(end.into())).await.unwrap();
+                while let Some(Ok(message)) = ws.next().await {
+                    if message.to_text().is_ok_and(|text| text.contains("warmup")) {
+                        warmups.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                    if let Message::Close(Some(frame)) = &message {
+                        assert_eq!(u16::from(frame.code), 1000);
+                        closes.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                }
+            });
+        }
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .warmup(true)
+        .connect()
+        .await
+        .unwrap();
+    let mut events = client.connection_events();
+    assert!(matches!(
+        events.recv().await,
+        Ok(ConnectionEvent::WarmedUp { .. })
+    ));
+    let ask = |model: &str| {
+        QueryMessage::new("Which is better?", None).with_options(QueryOptions {
+            model: Some(model.to_string()),
+            ..QueryOptions::default()
+        })
+    };
+    let stream = client.compare_stream(&ask("small"), &ask("large"));
+    let events: Vec<_> = tokio::time::timeout(Duration::from_secs(5), stream.collect())
+        .await
+        .expect("both queries should be answered");
+
+    let side = |side: Side| -> Vec<StreamEvent> {
+        events
+            .iter()
+            .filter(|(s, _)| *s == side)
+            .map(|(_, event)| event.as_ref().unwrap().clone())
+            .collect()
+    };
+    let end = StreamEvent::StreamEnd {
+        sources: vec![],
+        grounding: None,
+        citations: Citations::new(),
+        stats: QueryStats::default(),
+    };
+    assert_eq!(
+        side(Side::Left),
+        vec![
+            StreamEvent::StreamChunk("small".into(), ChunkMeta::default()),
+            end.clone()
+        ]
+    );
+    assert_eq!(
+        side(Side::Right),
+        vec![
+            StreamEvent::StreamChunk("large".into(), ChunkMeta::default()),
+            end
+        ]
+    );
+    // Only this connection warmed the server up, not the second one.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(warmups.load(std::sync::atomic::Ordering::SeqCst), 1);
+    // The second connection was closed once its answer ended; this one is
+    // still open.
+    assert_eq!(closes.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn a_flooding_server_waits_for_a_slow_reader() {
+    use futures_util::{SinkExt, StreamExt};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio_tungstenite::tungstenite::Message;
+
+    const FRAMES: usize = 4_000;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let sent = std::sync::Arc::new(AtomicUsize::new(0));
+    let counted = sent.clone();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        // 64 MB of answer, far more than the socket buffers hold.
+        let chunk = serde_json::json!({"type": "stream_chunk", "chunk": "x".repeat(16 << 10)});
+        for _ in 0..FRAMES {
+            if ws.send(Message::Text(chunk.to_string())).await.is_err() {
+                return;
+            }
+            counted.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let stream = client.query_stream("Flood?", None);
+    futures_util::pin_mut!(stream);
+    assert!(matches!(
+        stream.next().await,
+        Some(Ok(StreamEvent::StreamChunk(..)))
+    ));
+
+    // The answer isn't read any further: the reader task stops taking
+    // frames off the socket once its buffer is full, so the server blocks
+    // instead of the client holding the whole answer in memory.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let stalled = sent.load(Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(sent.load(Ordering::SeqCst), stalled);
+    assert!(stalled < FRAMES / 2, "server sent {} frames", stalled);
+}
+
+#[tokio::test]
+async fn late_replies_to_an_abandoned_query_are_ignored() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let mut ids = Vec::new();
+        while ids.len() < 2 {
+            let query = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            let query: serde_json::Value = serde_json::from_str(&query).unwrap();
+            ids.push(query["id"].as_u64().unwrap());
+        }
+        let (first, second) = (ids[0], ids[1]);
+        // The rest of the first answer only arrives after the second query.
+        for frame in [
+            serde_json::json!({"type": "stream_chunk", "id": first, "chunk": "stale"}),
+            serde_json::json!({"type": "stream_end", "id": first, "sources": []}),
+            serde_json::json!({"type": "stream_chunk", "id": second, "chunk": "fresh"}),
+            serde_json::json!({"type": "stream_end", "id": second, "sources": []}),
+        ] {
+            ws.send(Message::Text(frame.to_string())).await.unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    {
+        // Send the first query, then give up on it before any reply.
+        let stream = client.query_stream("First?", None);
+        futures_util::pin_mut!(stream);
+        let waited = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+        assert!(waited.is_err());
+    }
+
+    let events = client.query("Second?", None).await.unwrap();
+    assert_eq!(
+        events,
+        vec![
+            StreamEvent::StreamChunk("fresh".into(), ChunkMeta::default()),
+            StreamEvent::StreamEnd {
+                sources: vec![],
+                grounding: None,
+                citations: Citations::new(),
+                stats: QueryStats::default(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn cancel_stops_a_streaming_answer() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let query = ws.next().await.unwrap().unwrap().into_text().unwrap();
+        let id = serde_json::from_str::<serde_json::Value>(&query).unwrap()["id"].clone();
+        let chunk = serde_json::json!({"type": "stream_chunk", "id": id, "chunk": "Partial"});
+        ws.send(Message::Text(chunk.to_string())).await.unwrap();
+        // The cancel arrives while the answer is still being generated.
+        let cancel = ws.next().await.unwrap().unwrap().into_text().unwrap();
+        cancel_tx.send((id.clone(), cancel)).unwrap();
+        let stopped = serde_json::json!({"type": "error", "id": id, "message": "Query cancelled"});
+        ws.send(Message::Text(stopped.to_string())).await.unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let msg = QueryMessage::new("Long question?", None);
+    let stream = client.query_message_stream(&msg);
+    futures_util::pin_mut!(stream);
+    let first = stream.next().await.unwrap().unwrap();
+    assert_eq!(first.answer_text(), Some("Partial"));
+    client.cancel(msg.id).await.unwrap();
+
+    let (id, cancel) = cancel_rx.await.unwrap();
+    let cancel: serde_json::Value = serde_json::from_str(&cancel).unwrap();
+    assert_eq!(
+        cancel,
+        serde_json::json!({"type": "cancel", "request_id": id})
+    );
+    let last = stream.next().await.unwrap().unwrap();
+    assert_eq!(
+        last,
+        StreamEvent::Error(ErrorMessage::new("Query cancelled"))
+    );
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn query_with_history_sends_earlier_turns() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (query_tx, query_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        use futures_util::{SinkExt, StreamExt};
+        let query = ws.next().await.unwrap().unwrap().into_text().unwrap();
+        query_tx.send(query).unwrap();
+        let end = r#"{"type":"stream_end","sources":[]}"#;
+        ws.send(tokio_tungstenite::tungstenite::Message::Text(end.into()))
+            .await
+            .unwrap();
+    });
+
+    let mut conv = Conversation::new();
+    conv.push("How do I set it up?", "1. Install.\n2. Configure.", vec![]);
+    let turns: Vec<_> = conv.active_path().into_iter().cloned().collect();
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    client
+        .query_with_history("Expand on point 2", None, &turns)
+        .await
+        .unwrap();
+
+    let mut query: serde_json::Value = serde_json::from_str(&query_rx.await.unwrap()).unwrap();
+    let id = query.as_object_mut().unwrap().remove("id");
+    assert!(id.is_some_and(|id| id.is_u64()));
+    assert_eq!(
+        query,
+        serde_json::json!({
+            "type": "query",
+            "question": "Expand on point 2",
+            "history": [
+                {"question": "How do I set it up?", "answer": "1. Install.\n2. Configure."}
+            ]
+        })
+    );
+}
+
+#[test]
+fn server_messages_round_trip_through_the_wire_form() {
+    let chunk = ServerMessage::StreamChunk {
+        chunk: "Hello.".into(),
+        role: None,
+        finish_reason: None,
+        candidate: None,
+    };
+    assert_eq!(
+        serde_json::to_string(&chunk).unwrap(),
+        r#"{"type":"stream_chunk","chunk":"Hello."}"#
+    );
+    let end = ServerMessage::StreamEnd {
+        sources: vec!["/a.md".into()],
+        grounding: Some(Grounding {
+            score: 0.5,
+            unsupported: vec!["claim".into()],
+        }),
+        citations: Citations::from([(1, "/a.md".into())]),
+        usage: Some(Usage {
+            prompt_tokens: 1200,
+            completion_tokens: 300,
+            model: Some("gpt-4o-mini".into()),
+        }),
+        elapsed_ms: Some(2350),
+    };
+    let json = serde_json::to_string(&end).unwrap();
+    assert_eq!(serde_json::from_str::<ServerMessage>(&json).unwrap(), end);
+
+    let parsed: ServerMessage =
+        serde_json::from_str(r#"{"type":"error","message":"not ready"}"#).unwrap();
+    assert_eq!(parsed, ServerMessage::Error(ErrorMessage::new("not ready")));
+
+    let parsed: ServerMessage =
+        serde_json::from_str(r#"{"type":"error","message":"Slow down","code":"rate_limited"}"#)
+            .unwrap();
+    let rate_limited = ErrorMessage::new("Slow down").with_code(ErrorCode::RateLimited);
+    assert_eq!(parsed, ServerMessage::Error(rate_limited.clone()));
+    assert_eq!(
+        serde_json::to_string(&ServerMessage::Error(rate_limited)).unwrap(),
+        r#"{"type":"error","message":"Slow down","code":"rate_limited"}"#
+    );
+    // Codes from a newer server are dropped, not an error.
+    let parsed: ServerMessage =
+        serde_json::from_str(r#"{"type":"error","message":"x","code":"quota_exceeded"}"#).unwrap();
+    assert_eq!(parsed, ServerMessage::Error(ErrorMessage::new("x")));
+    assert!(serde_json::from_str::<ServerMessage>(r#"{"type":"bogus"}"#).is_err());
+    assert!(serde_json::from_str::<ServerMessage>(r#"{"chunk":"x"}"#).is_err());
+}
+
+#[tokio::test]
+async fn chunk_roles_keep_reasoning_out_of_the_answer() {
+    use futures_util::{SinkExt, StreamExt};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        for frame in [
+            r#"{"type":"stream_chunk","chunk":"Checking the docs. ","role":"reasoning"}"#,
+            r#"{"type":"stream_chunk","chunk":"Use port 8765.","role":"answer","finish_reason":"stop"}"#,
+            r#"{"type":"stream_end","sources":[]}"#,
+        ] {
+            ws.send(tokio_tungstenite::tungstenite::Message::Text(frame.into()))
+                .await
+                .unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let events = client.query("Which port?", None).await.unwrap();
+    let reasoning = ChunkMeta {
+        role: Some("reasoning".into()),
+        finish_reason: None,
+        candidate: None,
+    };
+    let answer = ChunkMeta {
+        role: Some("answer".into()),
+        finish_reason: Some("stop".into()),
+        candidate: None,
+    };
+    assert_eq!(
+        events[..2],
+        [
+            StreamEvent::StreamChunk("Checking the docs. ".into(), reasoning),
+            StreamEvent::StreamChunk("Use port 8765.".into(), answer),
+        ]
+    );
+    assert_eq!(events[0].answer_text(), None);
+    assert_eq!(events[1].answer_text(), Some("Use port 8765."));
+
+    let mut collector = StreamCollector::new();
+    events.iter().for_each(|e| collector.push(e));
+    assert_eq!(collector.answer, "Use port 8765.");
+    assert_eq!(collector.reasoning, "Checking the docs. ");
+}
+
+#[tokio::test]
+async fn candidate_answers_are_collected_apart_from_the_answer() {
+    use futures_util::{SinkExt, StreamExt};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (query_tx, query_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let query = ws.next().await.unwrap().unwrap();
+        let _ = query_tx.send(query.into_text().unwrap());
+        for frame in [
+            r#"{"type":"stream_chunk","chunk":"Use port ","candidate":0}"#,
+            r#"{"type":"stream_chunk","chunk":"It listens on 8765.","candidate":1}"#,
+            r#"{"type":"stream_chunk","chunk":"8765.","candidate":0}"#,
+            r#"{"type":"stream_chunk","chunk":"Weighing both. ","role":"reasoning","candidate":1}"#,
+            r#"{"type":"stream_chunk","chunk":"Port 8765.","candidate":2}"#,
+            r#"{"type":"stream_end","sources":[]}"#,
+        ] {
+            ws.send(tokio_tungstenite::tungstenite::Message::Text(frame.into()))
+                .await
+                .unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let msg = QueryMessage::new("Which port?", None).with_options(QueryOptions {
+        candidates: Some(3),
+        ..QueryOptions::default()
+    });
+    let events = client.query_message(&msg).await.unwrap();
+    let query: serde_json::Value = serde_json::from_str(&query_rx.await.unwrap()).unwrap();
+    assert_eq!(query["candidates"], 3);
+    assert_eq!(events[1].answer_text(), None);
+
+    let mut collector = StreamCollector::new();
+    events.iter().for_each(|e| collector.push(e));
+    assert_eq!(collector.answer, "Use port 8765.");
+    assert_eq!(
+        collector.candidates,
+        ["Use port 8765.", "It listens on 8765.", "Port 8765."]
+    );
+    assert_eq!(collector.reasoning, "Weighing both. ");
+}
+
+#[tokio::test]
+async fn thinking_streams_before_the_answer_without_joining_it() {
+    use futures_util::{SinkExt, StreamExt};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        for message in [
+            ServerMessage::stream_thinking("The port is in "),
+            ServerMessage::stream_thinking("the config."),
+            ServerMessage::stream_chunk("Use port 8765."),
+            ServerMessage::stream_end(["/docs/config.md"]),
+        ] {
+            ws.send(tokio_tungstenite::tungstenite::Message::Text(
+                message.to_text(),
+            ))
+            .await
+            .unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let events = client.query("Which port?", None).await.unwrap();
+    assert_eq!(
+        events[..2],
+        [
+            StreamEvent::Thinking("The port is in ".into()),
+            StreamEvent::Thinking("the config.".into()),
+        ]
+    );
+    assert_eq!(events[0].answer_text(), None);
+
+    let mut collector = StreamCollector::new();
+    events.iter().for_each(|e| collector.push(e));
+    assert_eq!(collector.answer, "Use port 8765.");
+    assert_eq!(collector.reasoning, "The port is in the config.");
+}
+
+#[tokio::test]
+async fn retrieval_progress_arrives_before_the_stream() {
+    use futures_util::{SinkExt, StreamExt};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        for frame in [
+            r#"{"type":"status","status":"retrieving","message":"Searching the documents"}"#,
+            // An index state is not query progress.
+            r#"{"type":"status","status":"ready"}"#,
+            r#"{"type":"stream_start"}"#,
+            r#"{"type":"stream_chunk","chunk":"Use port 8765."}"#,
+            r#"{"type":"stream_end","sources":[]}"#,
+        ] {
+            ws.send(tokio_tungstenite::tungstenite::Message::Text(frame.into()))
+                .await
+                .unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let events = client.query("Which port?", None).await.unwrap();
+    assert_eq!(
+        events[..2],
+        [
+            StreamEvent::Progress {
+                status: "retrieving".into(),
+                message: Some("Searching the documents".into()),
+            },
+            StreamEvent::StreamStart,
+        ]
+    );
+
+    let mut collector = StreamCollector::new();
+    events.iter().for_each(|e| collector.push(e));
+    assert_eq!(collector.answer, "Use port 8765.");
+}
+
+#[test]
+fn collector_limits_and_groups_sources() {
+    let sources = [
+        "/repo/docs/design/a.md",
+        "/repo/docs/api/b.md",
+        "/repo/docs/design/c.md",
+        "/repo/docs/index.md",
+        "/repo/docs/design/d.md",
+        "/repo/docs/api/e.md",
+    ]
+    .map(Source::from);
+    let end = StreamEvent::StreamEnd {
+        sources: sources.to_vec(),
+        grounding: None,
+        citations: Citations::new(),
+        stats: QueryStats::default(),
+    };
+
+    let mut collector = StreamCollector::new();
+    collector.push(&end);
+    assert_eq!(collector.sources.len(), 6);
+    assert_eq!(collector.omitted_sources, 0);
+    assert!(collector.source_groups.is_empty());
+
+    let retrieval = RetrievalSection {
+        max_sources: Some(2),
+        group_sources: true,
+        ..RetrievalSection::default()
+    };
+    let mut collector = StreamCollector::with_retrieval(&retrieval);
+    collector.push(&end);
+    assert_eq!(collector.sources, sources[..2]);
+    assert_eq!(collector.omitted_sources, 4);
+    let groups: Vec<String> = collector
+        .source_groups
+        .iter()
+        .map(|group| group.to_string())
+        .collect();
+    assert_eq!(groups, ["docs/design (3)", "docs/api (2)", "docs (1)"]);
+
+    let relative = ["a.md", "guide/b.md"].map(Source::from);
+    let groups = group_sources(&relative);
+    assert_eq!(groups[0].directory, ".");
+    assert_eq!(groups[1].directory, "guide");
+}
+
+#[test]
+fn collector_splits_answer_at_citation_markers() {
+    let frame = ServerFrame::parse(
+        r#"{"type":"stream_end","sources":["/a.md","/b.md"],"citations":{"1":"/a.md","2":{"path":"/b.md","start_line":4}}}"#,
+    )
+    .unwrap();
+    let ServerMessage::StreamEnd { citations, .. } = frame.message else {
+        panic!("expected stream_end, got {:?}", frame.message);
+    };
+    assert_eq!(citations[&2].location(), "/b.md:4");
+
+    let mut collector = StreamCollector::new();
+    for chunk in ["Use [x] boxes [1", "]. See [2][9] and [1]"] {
+        collector.push(&StreamEvent::StreamChunk(
+            chunk.into(),
+            ChunkMeta::default(),
+        ));
+    }
+    collector.push(&StreamEvent::StreamEnd {
+        sources: vec!["/a.md".into(), "/b.md".into()],
+        grounding: None,
+        citations: citations.clone(),
+        stats: QueryStats::default(),
+    });
+    let cite = |number: u32| AnswerSpan::Citation {
+        number,
+        source: citations[&number].clone(),
+    };
+    let text = |text: &str| AnswerSpan::Text { text: text.into() };
+    assert_eq!(
+        collector.answer_spans(),
+        [
+            text("Use [x] boxes "),
+            cite(1),
+            text(". See "),
+            cite(2),
+            text("[9] and "),
+            cite(1),
+        ]
+    );
+    assert_eq!(
+        cite_answer("No markers [1]", &Citations::new()),
+        [text("No markers [1]")]
+    );
+}
+
+#[tokio::test]
+async fn test_server_can_send_typed_messages() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        for _ in 0..2 {
+            let Some(Ok(Message::Text(query))) = ws.next().await else {
+                return;
+            };
+            let id = serde_json::from_str::<serde_json::Value>(&query).unwrap()["id"]
+                .as_u64()
+                .unwrap();
+            let end = ServerMessage::stream_end(["/a.md"]);
+            for message in ServerMessage::stream(["Typed", "."], end) {
+                let frame = message.reply_to(id).to_text();
+                ws.send(Message::Text(frame)).await.unwrap();
+            }
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let expected = [
+        StreamEvent::StreamStart,
+        StreamEvent::StreamChunk("Typed".into(), ChunkMeta::default()),
+        StreamEvent::StreamChunk(".".into(), ChunkMeta::default()),
+        StreamEvent::StreamEnd {
+            sources: vec!["/a.md".into()],
+            grounding: None,
+            citations: Citations::new(),
+            stats: QueryStats::default(),
+        },
+    ];
+    for _ in 0..2 {
+        let events = client.query("question", None).await.unwrap();
+        assert_eq!(events, expected);
+    }
+
+    for message in [
+        ServerMessage::status("ready"),
+        ServerMessage::error("Typed."),
+    ] {
+        let frame = ServerFrame::from(message);
+        assert_eq!(ServerFrame::parse(&frame.to_text()).unwrap(), frame);
+    }
+}
+
+#[tokio::test]
+async fn query_stream_yields_events_before_stream_end() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let ws_stream = accept_async(tcp_stream).await.unwrap();
+        let (mut write, mut read) = ws_stream.split();
+        use futures_util::SinkExt;
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::Message;
+        let _ = read.next().await;
+        for frame in [
+            r#"{"type":"stream_start"}"#,
+            r#"{"type":"stream_chunk","chunk":"Hello."}"#,
+        ] {
+            write.send(Message::Text(frame.into())).await.unwrap();
+        }
+        // Hold STREAM_END back until the client has seen the chunk.
+        let _ = release_rx.await;
+        write
+            .send(Message::Text(
+                r#"{"type":"stream_end","sources":["/a.md"]}"#.into(),
+            ))
+            .await
+            .unwrap();
+    });
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let client = connect(&url).await.expect("connect should succeed");
+    let stream = client.query_stream("What is the answer?", None);
+    futures_util::pin_mut!(stream);
+
+    use futures_util::StreamExt;
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        StreamEvent::StreamStart
+    );
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        StreamEvent::StreamChunk("Hello.".into(), ChunkMeta::default())
+    );
+    release_tx.send(()).unwrap();
+    match stream.next().await.unwrap().unwrap() {
+        StreamEvent::StreamEnd { sources, .. } => assert_eq!(sources, [Source::from("/a.md")]),
+        other => panic!("expected StreamEnd, got {other:?}"),
+    }
+    assert!(
+        stream.next().await.is_none(),
+        "stream should end after STREAM_END"
+    );
+}
+
+#[tokio::test]
+async fn query_with_calls_back_for_each_event() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        for frame in [
+            r#"{"type":"stream_start"}"#,
+            r#"{"type":"stream_chunk","chunk":"Hello."}"#,
+            r#"{"type":"stream_end","sources":["/a.md"]}"#,
+        ] {
+            ws.send(Message::Text(frame.into())).await.unwrap();
+        }
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let mut collector = StreamCollector::new();
+    let mut count = 0;
+    client
+        .query_with("What is the answer?", None, |event| {
+            collector.push(&event);
+            count += 1;
+        })
+        .await
+        .expect("query should succeed");
+    assert_eq!(count, 3);
+    assert_eq!(collector.answer, "Hello.");
+    assert_eq!(collector.sources, [Source::from("/a.md")]);
+}
+
+#[tokio::test]
+async fn stream_end_stats_carry_usage_and_server_time() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        for frame in [
+            r#"{"type":"stream_end","sources":[],"usage":{"prompt_tokens":120,"completion_tokens":30},"elapsed_ms":2350}"#,
+            r#"{"type":"stream_end","sources":[],"elapsed_ms":800}"#,
+        ] {
+            let _ = ws.next().await;
+            ws.send(Message::Text(frame.into())).await.unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let stats = |events: Vec<StreamEvent>| match events.last() {
+        Some(StreamEvent::StreamEnd { stats, .. }) => stats.clone(),
+        other => panic!("expected StreamEnd, got {other:?}"),
+    };
+    let full = stats(client.query("First?", None).await.unwrap());
+    assert_eq!(
+        full,
+        QueryStats {
+            usage: Some(Usage {
+                prompt_tokens: 120,
+                completion_tokens: 30,
+                model: None,
+            }),
+            elapsed_ms: Some(2350),
+        }
+    );
+    let timing_only = stats(client.query("Second?", None).await.unwrap());
+    assert_eq!(timing_only.usage, None);
+    assert_eq!(timing_only.elapsed_ms, Some(800));
+
+    let mut collector = StreamCollector::new();
+    collector.push(&StreamEvent::StreamEnd {
+        sources: vec![],
+        grounding: None,
+        citations: Citations::new(),
+        stats: full.clone(),
+    });
+    assert_eq!(collector.stats, full);
+}
+
+#[tokio::test]
+async fn non_streaming_response_is_surfaced() {
+    use futures_util::{SinkExt, StreamExt};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        let response = r#"{"type":"response","answer":"All at once.","sources":["/a.md",{"path":"/b.md","score":0.9},"/a.md"]}"#;
+        ws.send(tokio_tungstenite::tungstenite::Message::Text(
+            response.into(),
+        ))
+        .await
+        .unwrap();
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let events = client.query("Why?", None).await.unwrap();
+    assert_eq!(
+        events,
+        vec![StreamEvent::Response {
+            answer: "All at once.".to_string(),
+            sources: vec![
+                "/a.md".into(),
+                Source {
+                    score: Some(0.9),
+                    ..Source::from("/b.md")
+                }
+            ],
+        }]
+    );
+
+    let mut collector = StreamCollector::new();
+    events.iter().for_each(|e| collector.push(e));
+    assert_eq!(collector.answer, "All at once.");
+    assert_eq!(collector.sources.len(), 2);
+}
+
+/// Server that answers with `frames` and then keeps the socket open without sending more.
+async fn stalling_server(frames: &'static [&'static str]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let ws_stream = accept_async(tcp_stream).await.unwrap();
+        let (mut write, mut read) = ws_stream.split();
+        use futures_util::SinkExt;
+        use futures_util::StreamExt;
+        let _ = read.next().await;
+        for frame in frames {
+            write
+                .send(tokio_tungstenite::tungstenite::Message::Text(
+                    frame.to_string(),
+                ))
+                .await
+                .unwrap();
+        }
+        std::future::pending::<()>().await;
+    });
+    format!("ws://127.0.0.1:{}", port)
+}
+
+#[tokio::test]
+async fn silent_server_hits_first_event_timeout() {
+    let url = stalling_server(&[]).await;
+    let options = ClientOptions {
+        first_event_timeout: Some(Duration::from_millis(200)),
+        ..ClientOptions::default()
+    };
+    let client = connect_with_options(&url, options)
+        .await
+        .expect("connect should succeed");
+
+    let err = client
+        .query("question", None)
+        .await
+        .expect_err("query should time out");
+    assert!(matches!(err, ClientError::Timeout(TimeoutKind::FirstEvent)));
+}
+
+#[tokio::test]
+async fn stalled_stream_hits_stream_timeout() {
+    let url = stalling_server(&[r#"{"type":"stream_start"}"#]).await;
+    let options = ClientOptions {
+        first_event_timeout: Some(Duration::from_secs(5)),
+        stream_timeout: Some(Duration::from_millis(300)),
+        ..ClientOptions::default()
+    };
+    let client = connect_with_options(&url, options)
+        .await
+        .expect("connect should succeed");
+    let stream = client.query_stream("question", None);
+    futures_util::pin_mut!(stream);
+
+    use futures_util::StreamExt;
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        StreamEvent::StreamStart
+    );
+    let err = stream
+        .next()
+        .await
+        .unwrap()
+        .expect_err("stream should time out");
+    assert!(matches!(err, ClientError::Timeout(TimeoutKind::Stream)));
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn refused_connection_is_a_connect_error() {
+    let port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+
+    let err = match connect(&format!("ws://127.0.0.1:{}", port)).await {
+        Ok(_) => panic!("connect to a closed port should fail"),
+        Err(e) => e,
+    };
+    assert!(matches!(err, ClientError::Connect(_)));
+    assert!(err.is_connection_lost());
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+fn quick_retry(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        initial_backoff: Duration::from_millis(50),
+        ..RetryPolicy::default()
+    }
+}
+
+#[tokio::test]
+async fn connect_retries_until_server_starts() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        ws.send(Message::Text(
+            r#"{"type":"stream_end","sources":["/a.md"]}"#.into(),
+        ))
+        .await
+        .unwrap();
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .retry(quick_retry(10))
+        .connect()
+        .await
+        .expect("connect should succeed once the server is up");
+    let events = client.query("question", None).await.unwrap();
+    assert!(matches!(events.last(), Some(StreamEvent::StreamEnd { .. })));
+}
+
+#[tokio::test]
+async fn query_reconnects_when_connection_drops_before_first_event() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (question_tx, question_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        // First connection: drop the query unanswered, as a restarting server would.
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        drop(ws);
+
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let Some(Ok(Message::Text(query))) = ws.next().await else {
+            panic!("expected the query to be sent again");
+        };
+        let _ = question_tx.send(query);
+        ws.send(Message::Text(
+            r#"{"type":"stream_end","sources":["/a.md"]}"#.into(),
+        ))
+        .await
+        .unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .retry(quick_retry(3))
+        .connect()
+        .await
+        .unwrap();
+    let mut lifecycle = client.connection_events();
+    let events = client.query("question", None).await.unwrap();
+    assert_eq!(
+        events,
+        [StreamEvent::StreamEnd {
+            sources: vec!["/a.md".into()],
+            grounding: None,
+            citations: Citations::new(),
+            stats: QueryStats::default(),
+        }]
+    );
+    let resent: serde_json::Value = serde_json::from_str(&question_rx.await.unwrap()).unwrap();
+    assert_eq!(resent["question"], "question");
+
+    assert!(matches!(
+        lifecycle.try_recv(),
+        Ok(ConnectionEvent::Disconnected { .. })
+    ));
+    assert_eq!(
+        lifecycle.try_recv(),
+        Ok(ConnectionEvent::Reconnecting { attempt: 1 })
+    );
+    assert_eq!(lifecycle.try_recv(), Ok(ConnectionEvent::Connected));
+    assert!(lifecycle.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn close_reports_disconnected_event() {
+    use futures_util::StreamExt;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let mut lifecycle = client.connection_events();
+    client.close(1000, "").await.unwrap();
+    assert_eq!(
+        lifecycle.recv().await,
+        Ok(ConnectionEvent::Disconnected {
+            reason: "closed by client".to_string()
+        })
+    );
+}
+
+#[test]
+fn retry_policy_backoff_doubles_up_to_the_cap() {
+    let policy = RetryPolicy {
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(350),
+        ..RetryPolicy::default()
+    };
+    assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    assert_eq!(policy.backoff(2), Duration::from_millis(200));
+    assert_eq!(policy.backoff(3), Duration::from_millis(350));
+    assert_eq!(policy.backoff(40), Duration::from_millis(350));
+    assert!(policy.is_retryable(&ClientError::Closed));
+    assert!(!policy.is_retryable(&ClientError::ServerError(ErrorMessage::new("bad"))));
+    assert!(!policy.is_retryable(&ClientError::Timeout(TimeoutKind::FirstEvent)));
+}
+
+#[tokio::test]
+async fn query_is_resent_while_server_is_busy() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        // One connection throughout: a busy server is not reconnected to.
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let replies = [
+            r#"{"type":"error","message":"Indexes are still loading","code":"index_not_ready"}"#,
+            r#"{"type":"error","message":"Slow down","code":"rate_limited"}"#,
+            r#"{"type":"stream_end","sources":[]}"#,
+            r#"{"type":"error","message":"Bad key","code":"auth_failed"}"#,
+        ];
+        for reply in replies {
+            let _ = ws.next().await;
+            ws.send(Message::Text(reply.into())).await.unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .retry(RetryPolicy {
+            server_busy: true,
+            ..quick_retry(3)
+        })
+        .connect()
+        .await
+        .unwrap();
+    let events = client.query("What is this?", None).await.unwrap();
+    assert!(
+        matches!(events[..], [StreamEvent::StreamEnd { .. }]),
+        "{events:?}"
+    );
+
+    // Errors the user has to fix come back at once.
+    let events = client.query("What is this?", None).await.unwrap();
+    let [StreamEvent::Error(error)] = &events[..] else {
+        panic!("expected the error, got {events:?}");
+    };
+    assert_eq!(error.code, Some(ErrorCode::AuthFailed));
+    assert!(!error.is_transient());
+    let mut collector = StreamCollector::new();
+    collector.push(&events[0]);
+    assert_eq!(collector.error.as_deref(), Some("Bad key"));
+    assert_eq!(collector.error_code, Some(ErrorCode::AuthFailed));
+}
+
+#[tokio::test]
+async fn server_hanging_up_mid_answer_is_closed() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+        use futures_util::SinkExt;
+        use futures_util::StreamExt;
+        let _ = ws_stream.next().await;
+        ws_stream
+            .send(tokio_tungstenite::tungstenite::Message::Text(
+                r#"{"type":"stream_start"}"#.into(),
+            ))
+            .await
+            .unwrap();
+        ws_stream.close(None).await.unwrap();
+    });
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let client = connect(&url).await.expect("connect should succeed");
+    let err = client
+        .query("question", None)
+        .await
+        .expect_err("query should fail when the server hangs up");
+    assert!(matches!(err, ClientError::Closed));
+}
+
+#[tokio::test]
+async fn builder_sends_extra_handshake_headers() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (header_tx, header_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        // The callback signature (and its large error type) is fixed by tungstenite.
+        #[allow(clippy::result_large_err)]
+        let callback = |req: &tokio_tungstenite::tungstenite::handshake::server::Request, resp| {
+            let token = req
+                .headers()
+                .get("authorization")
+                .map(|v| v.to_str().unwrap().to_string());
+            header_tx.send(token).unwrap();
+            Ok(resp)
+        };
+        let _ws = tokio_tungstenite::accept_hdr_async(tcp_stream, callback)
+            .await
+            .unwrap();
+    });
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    ClientBuilder::new(&url)
+        .header("Authorization", "Bearer secret")
+        .connect()
+        .await
+        .expect("connect should succeed");
+    assert_eq!(header_rx.await.unwrap().as_deref(), Some("Bearer secret"));
+}
+
+#[tokio::test]
+async fn builder_rejects_invalid_header() {
+    let err = match ClientBuilder::new("ws://127.0.0.1:1")
+        .header("bad header", "x")
+        .connect()
+        .await
+    {
+        Ok(_) => panic!("invalid header name should fail"),
+        Err(e) => e,
+    };
+    assert!(matches!(err, ClientError::Connect(_)));
+}
+
+#[tokio::test]
+async fn builder_enforces_max_message_size() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+        use futures_util::SinkExt;
+        use futures_util::StreamExt;
+        let _ = ws_stream.next().await;
+        let chunk = format!(
+            r#"{{"type":"stream_chunk","chunk":"{}"}}"#,
+            "x".repeat(4096)
+        );
+        let _ = ws_stream
+            .send(tokio_tungstenite::tungstenite::Message::Text(chunk))
+            .await;
+        std::future::pending::<()>().await;
+    });
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let client = ClientBuilder::new(&url)
+        .max_message_size(1024)
+        .connect()
+        .await
+        .expect("connect should succeed");
+    let events = client
+        .query("question", None)
+        .await
+        .expect("oversized message ends the answer with an error event");
+    match events.as_slice() {
+        [StreamEvent::Error(message)] => {
+            assert!(message.message.contains("too large"), "{message}")
+        }
+        other => panic!("expected one error event, got {:?}", other),
+    }
+    let err = client
+        .query("again", None)
+        .await
+        .expect_err("connection is closed after an oversized message");
+    assert!(matches!(err, ClientError::Closed), "{:?}", err);
+}
+
+#[tokio::test]
+async fn event_buffer_limit_discards_the_rest_of_the_answer() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+        while ws.next().await.is_some() {
+            ws.send(Message::Text(r#"{"type":"stream_start"}"#.into()))
+                .await
+                .unwrap();
+            for _ in 0..5 {
+                ws.send(Message::Text(
+                    r#"{"type":"stream_chunk","chunk":"x"}"#.into(),
+                ))
+                .await
+                .unwrap();
+            }
+            ws.send(Message::Text(
+                r#"{"type":"stream_end","sources":[]}"#.into(),
+            ))
+            .await
+            .unwrap();
+        }
+    });
+
+    let options = ClientOptions {
+        max_buffered_events: Some(3),
+        ..ClientOptions::default()
+    };
+    let client = connect_with_options(&format!("ws://127.0.0.1:{}", port), options)
+        .await
+        .unwrap();
+    let events = client.query("question", None).await.unwrap();
+    assert_eq!(events.len(), 4);
+    match &events[3] {
+        StreamEvent::Error(message) => assert!(message.message.contains("4 events were discarded")),
+        other => panic!("expected overflow error, got {:?}", other),
+    }
+
+    // The discarded tail was drained, so the next answer starts cleanly.
+    let events = client.query("again", None).await.unwrap();
+    assert_eq!(events[0], StreamEvent::StreamStart);
+}
+
+#[tokio::test]
+async fn builder_keepalive_pings_idle_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (ping_tx, ping_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+        use futures_util::StreamExt;
+        let frame = ws_stream.next().await.unwrap().unwrap();
+        ping_tx.send(frame.is_ping()).unwrap();
+    });
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let _client = ClientBuilder::new(&url)
+        .keepalive(Duration::from_millis(100))
+        .connect()
+        .await
+        .expect("connect should succeed");
+    let got_ping = tokio::time::timeout(Duration::from_secs(5), ping_rx)
+        .await
+        .expect("server should hear from an idle client")
+        .unwrap();
+    assert!(got_ping);
+}
+
+#[tokio::test]
+async fn keepalive_records_last_pong() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+        use futures_util::StreamExt;
+        // Reading lets tungstenite answer each ping with a pong.
+        while let Some(Ok(_)) = ws_stream.next().await {}
+    });
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let client = ClientBuilder::new(&url)
+        .keepalive(Duration::from_millis(50))
+        .connect()
+        .await
+        .expect("connect should succeed");
+    assert!(client.last_pong().is_none());
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while client.last_pong().is_none() {
+        assert!(tokio::time::Instant::now() < deadline, "no pong recorded");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Server that completes the handshake and then never reads, so pings go unanswered.
+async fn unresponsive_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let _ws_stream = accept_async(tcp_stream).await.unwrap();
+        std::future::pending::<()>().await;
+    });
+    format!("ws://127.0.0.1:{}", port)
+}
+
+#[tokio::test]
+async fn keepalive_flags_server_that_stops_answering_pings() {
+    let url = unresponsive_server().await;
+    let client = ClientBuilder::new(&url)
+        .keepalive(Duration::from_millis(50))
+        .connect()
+        .await
+        .expect("connect should succeed");
+    let mut lifecycle = client.connection_events();
+    assert!(client.is_connected());
+
+    let event = tokio::time::timeout(Duration::from_secs(5), lifecycle.recv())
+        .await
+        .expect("keepalive should notice the dead connection")
+        .unwrap();
+    let ConnectionEvent::Disconnected { reason } = event else {
+        panic!("expected a disconnect, got {:?}", event);
+    };
+    assert!(reason.contains("no pong"), "unexpected reason: {}", reason);
+    assert!(!client.is_connected());
+    assert_eq!(client.disconnect_reason(), Some(reason));
+}
+
+#[tokio::test]
+async fn ping_probes_liveness() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws_stream = accept_async(tcp_stream).await.unwrap();
+        use futures_util::StreamExt;
+        while let Some(Ok(_)) = ws_stream.next().await {}
+    });
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    client
+        .ping(Duration::from_secs(5))
+        .await
+        .expect("live server should answer the ping");
+    assert!(client.last_pong().is_some());
+
+    let client = connect(&unresponsive_server().await).await.unwrap();
+    let err = client
+        .ping(Duration::from_millis(100))
+        .await
+        .expect_err("silent server should time out");
+    assert!(matches!(err, ClientError::Timeout(TimeoutKind::Ping)));
+    assert!(!client.is_connected());
+    assert!(client.disconnect_reason().is_some());
+}
+
+#[tokio::test]
+async fn ping_runs_while_a_query_streams() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        ws.send(Message::Text(r#"{"type":"stream_start"}"#.into()))
+            .await
+            .unwrap();
+        // Finish the answer only after the client pinged mid-stream; the
+        // pong goes out ahead of stream_end.
+        while let Some(Ok(frame)) = ws.next().await {
+            if frame.is_ping() {
+                break;
+            }
+        }
+        ws.send(Message::Text(
+            r#"{"type":"stream_end","sources":[]}"#.into(),
+        ))
+        .await
+        .unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let stream = client.query_stream("question", None);
+    futures_util::pin_mut!(stream);
+    assert_eq!(
+        stream.next().await.unwrap().unwrap(),
+        StreamEvent::StreamStart
+    );
+    tokio::time::timeout(Duration::from_secs(5), client.ping(Duration::from_secs(5)))
+        .await
+        .expect("ping should not wait for the query")
+        .expect("server should answer the ping");
+    assert!(matches!(
+        stream.next().await.unwrap().unwrap(),
+        StreamEvent::StreamEnd { .. }
+    ));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn connect_over_unix_socket() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("md-qa.sock");
+    let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+    tokio::spawn(async move {
+        let (unix_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(unix_stream).await.unwrap();
+        let _ = ws.next().await;
+        for frame in [
+            r#"{"type":"stream_start"}"#,
+            r#"{"type":"stream_chunk","chunk":"Over a socket."}"#,
+            r#"{"type":"stream_end","sources":["/a.md"]}"#,
+        ] {
+            ws.send(Message::Text(frame.into())).await.unwrap();
+        }
+    });
+
+    let url = format!("unix://{}", socket.display());
+    let client = connect(&url).await.expect("connect should succeed");
+    let events = client.query("Where?", None).await.unwrap();
+    assert_eq!(
+        events[1],
+        StreamEvent::StreamChunk("Over a socket.".to_string(), ChunkMeta::default())
+    );
+}
+
+#[tokio::test]
+async fn status_returns_server_readiness() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        ws.send(Message::Text(
+            r#"{"type":"status","status":"indexing","message":"Server reloading indexes"}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let _ = ws.next().await;
+        ws.send(Message::Text(r#"{"type":"error","message":"busy"}"#.into()))
+            .await
+            .unwrap();
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let status = client.status().await.expect("status should succeed");
+    assert!(!status.is_ready());
+    assert_eq!(status.status, "indexing");
+    assert_eq!(status.documents, None);
+
+    match client.status().await {
+        Err(ClientError::ServerError(error)) => assert_eq!(error.message, "busy"),
+        other => panic!("expected server error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn reload_index_asks_the_server_to_reindex() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, request_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        if let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+        }
+        ws.send(Message::Text(
+            r#"{"type":"status","status":"indexing","message":"Reindexing started"}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let _ = ws.next().await;
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let status = client
+        .reload_index(Some("docs"))
+        .await
+        .expect("reload should succeed");
+    assert_eq!(status.status, "indexing");
+    assert_eq!(status.message.as_deref(), Some("Reindexing started"));
+    let request: serde_json::Value = serde_json::from_str(&request_rx.await.unwrap()).unwrap();
+    assert_eq!(
+        request,
+        serde_json::json!({"type": "reload", "index": "docs"})
+    );
+}
+
+#[tokio::test]
+async fn index_stats_reports_what_the_index_holds() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, request_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        if let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+        }
+        ws.send(Message::Text(
+            r#"{"type":"index_stats","index":"docs","documents":12,"chunks":340,"embedding_model":"text-embedding-3-small","built_at":1700000000,"issues":[{"path":"/docs/a.md","issue":"not UTF-8; decoded as GBK"}]}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let _ = ws.next().await;
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let stats = client
+        .index_stats(Some("docs"))
+        .await
+        .expect("index stats should succeed");
+    assert_eq!(stats.documents, 12);
+    assert_eq!(stats.chunks, 340);
+    assert_eq!(
+        stats.embedding_model.as_deref(),
+        Some("text-embedding-3-small")
+    );
+    assert_eq!(stats.built_at, Some(1_700_000_000));
+    assert_eq!(stats.size_bytes, None);
+    assert_eq!(stats.issues.len(), 1);
+    assert_eq!(stats.issues[0].path, "/docs/a.md");
+    assert_eq!(stats.issues[0].issue, "not UTF-8; decoded as GBK");
+    let request: serde_json::Value = serde_json::from_str(&request_rx.await.unwrap()).unwrap();
+    assert_eq!(
+        request,
+        serde_json::json!({"type": "index_stats", "index": "docs"})
+    );
+}
+
+#[tokio::test]
+async fn index_plan_lists_the_files_the_server_would_index() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, request_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        if let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+        }
+        ws.send(Message::Text(
+            r#"{"type":"index_plan","index":"docs","files":["/docs/a.md"],"ignored":["/docs/build/out.md"]}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let _ = ws.next().await;
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let plan = client
+        .index_plan(None)
+        .await
+        .expect("index plan should succeed");
+    assert_eq!(plan.index.as_deref(), Some("docs"));
+    assert_eq!(plan.files, ["/docs/a.md"]);
+    assert_eq!(plan.ignored, ["/docs/build/out.md"]);
+    let request: serde_json::Value = serde_json::from_str(&request_rx.await.unwrap()).unwrap();
+    assert_eq!(request, serde_json::json!({"type": "index_plan"}));
+}
+
+#[tokio::test]
+async fn index_estimate_asks_what_a_reload_would_embed() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, request_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        if let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+        }
+        ws.send(Message::Text(
+            r#"{"type":"index_plan","index":"docs","files":["/docs/a.md"],"ignored":[],"estimate":{"embedding_model":"text-embedding-3-small","full_rebuild":false,"files":[{"path":"/docs/a.md","chunks":3,"tokens":900}],"chunks":3,"tokens":900,"cached_chunks":2,"seconds":1.0}}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let _ = ws.next().await;
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let plan = client
+        .index_estimate(None)
+        .await
+        .expect("index estimate should succeed");
+    let estimate = plan.estimate.expect("the reply should carry an estimate");
+    assert_eq!(
+        estimate.embedding_model.as_deref(),
+        Some("text-embedding-3-small")
+    );
+    assert!(!estimate.full_rebuild);
+    assert_eq!(estimate.files.len(), 1);
+    assert_eq!(estimate.files[0].path, "/docs/a.md");
+    assert_eq!(estimate.files[0].tokens, 900);
+    assert_eq!((estimate.chunks, estimate.tokens), (3, 900));
+    assert_eq!(estimate.cached_chunks, 2);
+    assert_eq!(estimate.seconds, 1.0);
+    let request: serde_json::Value = serde_json::from_str(&request_rx.await.unwrap()).unwrap();
+    assert_eq!(
+        request,
+        serde_json::json!({"type": "index_plan", "estimate": true})
+    );
+}
+
+#[tokio::test]
+async fn index_verify_reports_orphans_and_asks_for_removal() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, request_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        if let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+        }
+        ws.send(Message::Text(
+            r#"{"type":"index_verify","index":"docs","orphans":[{"path":"/docs/gone.md","chunks":2,"reason":"missing"}],"removed":true}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let _ = ws.next().await;
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let report = client
+        .index_verify(None, true)
+        .await
+        .expect("index verify should succeed");
+    assert!(report.removed);
+    assert_eq!(report.orphans.len(), 1);
+    assert_eq!(report.orphans[0].path, "/docs/gone.md");
+    assert_eq!(report.orphans[0].chunks, 2);
+    assert_eq!(report.orphans[0].reason, "missing");
+    let request: serde_json::Value = serde_json::from_str(&request_rx.await.unwrap()).unwrap();
+    assert_eq!(
+        request,
+        serde_json::json!({"type": "index_verify", "remove": true})
+    );
+}
+
+#[tokio::test]
+async fn index_restore_sends_the_snapshot_path() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, request_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        if let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+        }
+        ws.send(Message::Text(
+            r#"{"type":"index_restore","index":"docs","path":"/tmp/docs.tar.zst","chunks":12,"embedding_model":"text-embedding-3-small","size_bytes":4096,"reindexing":true}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let _ = ws.next().await;
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let restored = client
+        .index_restore(None, "/tmp/docs.tar.zst")
+        .await
+        .expect("index restore should succeed");
+    assert_eq!(restored.chunks, 12);
+    assert_eq!(restored.embedding_model, "text-embedding-3-small");
+    assert_eq!(restored.size_bytes, 4096);
+    assert!(restored.reindexing);
+    let request: serde_json::Value = serde_json::from_str(&request_rx.await.unwrap()).unwrap();
+    assert_eq!(
+        request,
+        serde_json::json!({"type": "index_restore", "path": "/tmp/docs.tar.zst"})
+    );
+}
+
+#[tokio::test]
+async fn create_index_sends_its_settings_and_returns_the_indexes() {
+    use futures_util::{SinkExt, StreamExt};
+    use md_qa_client::config::{ChunkSettings, ChunkingSection};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, request_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        if let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+        }
+        ws.send(Message::Text(
+            r#"{"type":"indexes","indexes":[{"name":"docs","directories":["/srv/docs"],"embedding_model":"text-embedding-3-small","default":true,"status":"ready"},{"name":"notes","directories":["/home/me/notes"],"embedding_model":"local:bge-small","default":false,"status":"indexing"}]}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let _ = ws.next().await;
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let chunking = ChunkingSection {
+        defaults: ChunkSettings {
+            target_tokens: Some(300),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let indexes = client
+        .create_index(
+            "notes",
+            &["/home/me/notes".to_string()],
+            Some(&chunking),
+            Some("local:bge-small"),
+        )
+        .await
+        .expect("index create should succeed");
+    assert_eq!(indexes.len(), 2);
+    assert!(indexes[0].default);
+    assert_eq!(indexes[1].name, "notes");
+    assert_eq!(indexes[1].status, "indexing");
+    let request: serde_json::Value = serde_json::from_str(&request_rx.await.unwrap()).unwrap();
+    assert_eq!(
+        request,
+        serde_json::json!({
+            "type": "index_create",
+            "name": "notes",
+            "directories": ["/home/me/notes"],
+            "chunking": {"target_tokens": 300},
+            "embedding_model": "local:bge-small",
+        })
+    );
+}
+
+#[tokio::test]
+async fn tail_logs_returns_recent_lines_and_follows_new_ones() {
+    use futures_util::{SinkExt, StreamExt};
+    use md_qa_client::messages::{LogLevel, LogLine};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, request_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        if let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+        }
+        let frames = [
+            r#"{"type":"logs","lines":["[t] p1 {server.py:1} INFO - Server started","[t] p1 {index_manager.py:9} WARNING - No documents found"]}"#,
+            r#"{"type":"log","line":"[t] p1 {server.py:2} ERROR - Index build failed\nTraceback (most recent call last):"}"#,
+        ];
+        for frame in frames {
+            ws.send(Message::Text(frame.into())).await.unwrap();
+        }
+        let _ = ws.next().await;
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    let mut followed = client.log_lines();
+    let lines = client.tail_logs(50, true).await.unwrap();
+    let levels: Vec<_> = lines.iter().map(|line| line.level).collect();
+    assert_eq!(levels, [Some(LogLevel::Info), Some(LogLevel::Warning)]);
+    assert!(lines[1].text.ends_with("No documents found"));
+
+    let line = tokio::time::timeout(Duration::from_secs(5), followed.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(line.level, Some(LogLevel::Error));
+    assert!(line.text.contains("\nTraceback"));
+    let request: serde_json::Value = serde_json::from_str(&request_rx.await.unwrap()).unwrap();
+    assert_eq!(
+        request,
+        serde_json::json!({"type": "tail_logs", "lines": 50, "follow": true})
+    );
+
+    assert_eq!(
+        LogLine::new("Traceback (most recent call last):").level,
+        None
+    );
+    assert_eq!(
+        LogLine::new("WARN: disk [CRITICAL]").level,
+        Some(LogLevel::Warning)
+    );
+}
+
+#[tokio::test]
+async fn warmup_on_connect_reports_its_timing() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, mut request_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let mut warmups = 0;
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+            // Cold caches: the first warm-up is slow, a repeated one fails.
+            warmups += 1;
+            let reply = if warmups == 1 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                r#"{"type":"status","status":"ready","message":"Warm-up complete"}"#
+            } else {
+                r#"{"type":"error","message":"No index available"}"#
+            };
+            ws.send(Message::Text(reply.into())).await.unwrap();
+        }
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .warmup(true)
+        .connect()
+        .await
+        .unwrap();
+    let mut events = client.connection_events();
+    let Ok(ConnectionEvent::WarmedUp { elapsed }) = events.recv().await else {
+        panic!("expected the warm-up timing");
+    };
+    assert!(elapsed >= Duration::from_millis(50), "{:?}", elapsed);
+    assert_eq!(request_rx.recv().await.unwrap(), r#"{"type":"warmup"}"#);
+
+    let err = client.warmup().await.unwrap_err();
+    assert!(matches!(err, ClientError::ServerError(m) if m.message == "No index available"));
+}
+
+#[tokio::test]
+async fn capabilities_are_asked_for_on_connect() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (request_tx, mut request_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            request_tx.send(text.to_string()).unwrap();
+            let reply = if text.contains(r#""type":"capabilities""#) {
+                r#"{"type":"capabilities","features":["history","compression"]}"#
+            } else {
+                r#"{"type":"stream_end","sources":[]}"#
+            };
+            ws.send(Message::Text(reply.into())).await.unwrap();
+        }
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .capabilities(true)
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(
+        request_rx.recv().await.unwrap(),
+        r#"{"type":"capabilities"}"#
+    );
+    let capabilities = client
+        .capabilities()
+        .expect("capabilities should be cached");
+    assert_eq!(capabilities.features, vec!["history", "compression"]);
+    assert!(client.supports("history"));
+    assert!(!client.supports("cancel"));
+
+    let events = client.query("What is this?", None).await.unwrap();
+    assert!(matches!(events.last(), Some(StreamEvent::StreamEnd { .. })));
+}
+
+#[tokio::test]
+async fn binary_encoding_is_negotiated_when_built_in() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    for encoding in [WireEncoding::MessagePack, WireEncoding::Cbor] {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (request_tx, mut request_rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (tcp_stream, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(tcp_stream).await.unwrap();
+            let mut binary = false;
+            while let Some(Ok(frame)) = ws.next().await {
+                let request = match frame {
+                    Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                    Message::Binary(bytes) => encoding.decode(&bytes).unwrap(),
+                    _ => continue,
+                };
+                request_tx.send(request.clone()).unwrap();
+                if request["type"] == "capabilities" {
+                    // The reply is still JSON; what follows is binary.
+                    let reply = format!(r#"{{"type":"capabilities","encoding":"{}"}}"#, encoding);
+                    ws.send(Message::Text(reply)).await.unwrap();
+                    binary = true;
+                    continue;
+                }
+                let end = ServerMessage::stream_end(["/a.md"]);
+                for reply in ServerMessage::stream(["Compact", "."], end) {
+                    let frame = if binary {
+                        let value = serde_json::to_value(&reply).unwrap();
+                        Message::Binary(encoding.encode(&value).unwrap())
+                    } else {
+                        Message::Text(reply.to_text())
+                    };
+                    ws.send(frame).await.unwrap();
+                }
+            }
+        });
+
+        let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+            .encoding(encoding)
+            .connect()
+            .await
+            .unwrap();
+        let events = client.query("What is this?", None).await.unwrap();
+        let mut collector = StreamCollector::new();
+        events.iter().for_each(|event| collector.push(event));
+        assert_eq!(collector.answer, "Compact.");
+        assert_eq!(collector.sources, [Source::from("/a.md")]);
+        let first = request_rx.recv().await.unwrap();
+        if encoding.is_available() {
+            assert_eq!(
+                first["encodings"],
+                serde_json::json!([encoding.to_string()])
+            );
+            assert_eq!(client.encoding(), encoding);
+            let query = request_rx.recv().await.unwrap();
+            assert_eq!(query["question"], "What is this?");
+        } else {
+            // Not built in: nothing is offered and the client stays on JSON.
+            assert_eq!(first["type"], "query");
+            assert_eq!(client.encoding(), WireEncoding::Json);
+        }
+    }
+}
+
+#[tokio::test]
+async fn capabilities_of_older_servers_are_unknown() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        while let Some(Ok(_)) = ws.next().await {
+            let reply = r#"{"type":"error","message":"Unknown message type: capabilities"}"#;
+            ws.send(Message::Text(reply.into())).await.unwrap();
+        }
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .capabilities(true)
+        .connect()
+        .await
+        .expect("connect should not fail on an older server");
+    assert!(client.capabilities().is_none());
+    assert!(client.supports("cancel"), "unknown features are assumed");
+
+    let err = client.fetch_capabilities().await.unwrap_err();
+    assert!(matches!(err, ClientError::ServerError(m) if m.message.contains("capabilities")));
+}
+
+#[tokio::test]
+async fn capabilities_a_busy_server_does_not_send_are_unknown() {
+    use futures_util::StreamExt;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        while let Some(Ok(_)) = ws.next().await {}
+    });
+
+    let options = ClientOptions {
+        first_event_timeout: Some(Duration::from_millis(100)),
+        ..ClientOptions::default()
+    };
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .options(options)
+        .capabilities(true)
+        .connect()
+        .await
+        .expect("connect should not wait for the capabilities");
+    assert!(client.capabilities().is_none());
+    assert!(client.is_connected(), "{:?}", client.disconnect_reason());
+}
+
+#[tokio::test]
+async fn frame_tap_sees_every_message() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        while let Some(Ok(_)) = ws.next().await {
+            let reply = r#"{"type":"status","status":"ready"}"#;
+            ws.send(Message::Text(reply.into())).await.unwrap();
+        }
+    });
+
+    let frames = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = frames.clone();
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .frame_tap(FrameTap::new(move |direction, text| {
+            seen.lock().unwrap().push((direction, text.to_string()));
+        }))
+        .connect()
+        .await
+        .unwrap();
+    client.status().await.unwrap();
+
+    assert_eq!(
+        *frames.lock().unwrap(),
+        [
+            (FrameDirection::Sent, r#"{"type":"status"}"#.to_string()),
+            (
+                FrameDirection::Received,
+                r#"{"type":"status","status":"ready"}"#.to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn status_poll_keeps_latest_summary() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        while let Some(Ok(_)) = ws.next().await {
+            let indexed_at = md_qa_client::querylog::now() - 120;
+            let reply = format!(
+                r#"{{"type":"status","status":"ready","documents":12,"indexed_at":{}}}"#,
+                indexed_at
+            );
+            ws.send(Message::Text(reply)).await.unwrap();
+        }
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .status_poll(Duration::from_millis(50))
+        .connect()
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let summary = client
+        .server_summary()
+        .expect("poll should have checked the status");
+    assert!(summary.ready);
+    assert_eq!(summary.documents, Some(12));
+    let age = summary.index_age_secs.expect("index age should be known");
+    assert!((120..125).contains(&age), "unexpected index age {age}");
+
+    let checked = client.check_status().await.unwrap();
+    assert_eq!(client.server_summary(), Some(checked));
+}
+
+#[tokio::test]
+async fn missed_status_polls_keep_the_connection() {
+    use futures_util::StreamExt;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        // Too busy to answer status requests.
+        while let Some(Ok(_)) = ws.next().await {}
+    });
+
+    let options = ClientOptions {
+        first_event_timeout: Some(Duration::from_millis(20)),
+        ..ClientOptions::default()
+    };
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .options(options)
+        .status_poll(Duration::from_millis(50))
+        .connect()
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert_eq!(client.server_summary(), None);
+    assert!(client.is_connected(), "{:?}", client.disconnect_reason());
+}
+
+#[tokio::test]
+async fn close_performs_close_handshake() {
+    use futures_util::StreamExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (close_tx, close_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            if let Message::Close(frame) = message {
+                let frame = frame.unwrap();
+                let _ = close_tx.send((u16::from(frame.code), frame.reason.to_string()));
+                // Keep polling so tungstenite sends the Close reply.
+                while ws.next().await.is_some() {}
+                return;
+            }
+        }
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    client
+        .close(1000, "bye")
+        .await
+        .expect("close should succeed");
+    assert_eq!(close_rx.await.unwrap(), (1000, "bye".to_string()));
+}
+
+#[tokio::test]
+async fn index_progress_is_streamed_and_kept() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        let _ = ws.next().await;
+        for frame in [
+            r#"{"type":"index_progress","done":1,"total":2,"path":"/docs/a.md"}"#,
+            r#"{"type":"index_progress","done":2,"total":2}"#,
+            r#"{"type":"error","message":"index not ready"}"#,
+        ] {
+            ws.send(Message::Text(frame.into())).await.unwrap();
+        }
+        while ws.next().await.is_some() {}
+    });
+
+    let client = connect(&format!("ws://127.0.0.1:{}", port)).await.unwrap();
+    assert_eq!(client.index_progress(), None);
+    let events = client.query("question", None).await.unwrap();
+    assert_eq!(
+        events,
+        [
+            StreamEvent::IndexProgress {
+                done: 1,
+                total: 2,
+                path: Some("/docs/a.md".into()),
+            },
+            StreamEvent::IndexProgress {
+                done: 2,
+                total: 2,
+                path: None,
+            },
+            StreamEvent::Error(ErrorMessage::new("index not ready")),
+        ]
+    );
+    let latest = client.index_progress().unwrap();
+    assert_eq!((latest.done, latest.total), (2, 2));
+}
+
+#[tokio::test]
+async fn index_progress_to_an_idle_client_does_not_stall_its_reader() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    // More than the replies the reader queues for a request in flight.
+    const FRAMES: u64 = 600;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        for done in 1..=FRAMES {
+            let frame = format!(
+                r#"{{"type":"index_progress","done":{},"total":{}}}"#,
+                done, FRAMES
+            );
+            ws.send(Message::Text(frame)).await.unwrap();
+        }
+        ws.send(Message::Text(
+            r#"{"type":"notification","documents":["/docs/b.md"]}"#.into(),
+        ))
+        .await
+        .unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            if message.is_text() {
+                ws.send(Message::Text(
+                    r#"{"type":"status","status":"ready","message":"Ready"}"#.into(),
+                ))
+                .await
+                .unwrap();
+            }
+        }
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .keepalive(Duration::from_millis(50))
+        .connect()
+        .await
+        .unwrap();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while client.index_progress().map(|p| p.done) != Some(FRAMES) {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "reader stalled at {:?}",
+            client.index_progress().map(|p| p.done)
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(client.last_pong().is_some(), "pongs should still be read");
+    assert_eq!(client.take_notifications().len(), 1);
+    // The progress nobody read doesn't get in the way of the next request.
+    assert_eq!(client.status().await.unwrap().status, "ready");
+}
+
+#[tokio::test]
+async fn keepalive_collects_notifications_between_queries() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(tcp_stream).await.unwrap();
+        ws.send(Message::Text(
+            r#"{"type":"notification","documents":["/docs/b.md"]}"#.into(),
+        ))
+        .await
+        .unwrap();
+        while ws.next().await.is_some() {}
+    });
+
+    let client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", port))
+        .keepalive(Duration::from_millis(50))
+        .connect()
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let notifications = client.take_notifications();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].documents, ["/docs/b.md"]);
+    assert_eq!(notifications[0].message, None);
+}
@@ -1,10 +1,31 @@
 //! Tauri commands for config load/save and WebSocket connection management.
 //! The Tauri `#[command]` wrappers delegate to testable plain functions.
 
-use md_qa_client::config::{self, ApiSection, Config, ServerSection};
+use futures_util::StreamExt;
+use md_qa_client::config::{
+    self, ApiSection, ChunkingSection, ClientSection, Config, ExportSection, HookFailure,
+    HooksSection, RetrievalSection, ServerSection,
+};
+use md_qa_client::history::{Branch, Conversation, HistoryError, Turn, TurnId};
+use md_qa_client::messages::{
+    CapabilitiesMessage, ChunksMessage, DocumentsMessage, ErrorCode, Grounding, IndexEstimate,
+    IndexInfo, IndexProgressMessage, IndexStatsMessage, LinksMessage, LogLine, NotificationMessage,
+    QueryFilters, QueryMessage, QueryOptions, QueryStats, Source, StatusMessage,
+};
+use md_qa_client::querylog::{self, Gap, LogEntry};
+use md_qa_client::{
+    drafts, embedding_cost, AnswerSpan, ConnectionEvent, Draft, ExportFormat, HookError, Hooks,
+    PriceTable, RetryPolicy, ServerSummary, Side, SourceGroup, StreamCollector, UsageTotals,
+    LARGE_RELOAD_TOKENS,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock};
+use tauri::{AppHandle, Emitter};
+
+use crate::recorder;
 
 // ── Global runtime and connection state (single connection for the GUI) ─
 use std::sync::OnceLock;
@@ -21,6 +42,13 @@ fn global_runtime() -> &'static tokio::runtime::Runtime {
 
 static CONNECTION: Mutex<Option<md_qa_client::Client>> = Mutex::new(None);
 
+/// URL of the connection the user asked for, so a watcher can reconnect
+/// after the server restarts; cleared by `do_disconnect`.
+static SERVER_URL: Mutex<Option<String>> = Mutex::new(None);
+
+/// Set while a watcher reconnects after the server went away.
+static RESTARTING: AtomicBool = AtomicBool::new(false);
+
 /// JSON-friendly config form values sent to/from the frontend.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ConfigForm {
@@ -78,7 +106,16 @@ impl From<ConfigForm> for Config {
                 directories: f.directories,
                 reload_interval: Some(f.reload_interval),
                 index_name: Some(f.index_name),
+                max_file_size_mb: None,
+                docs_language: None,
+                index_drafts: false,
+                warmup: false,
             },
+            client: ClientSection::default(),
+            hooks: HooksSection::default(),
+            export: ExportSection::default(),
+            retrieval: RetrievalSection::default(),
+            prices: PriceTable::default(),
         }
     }
 }
@@ -103,147 +140,2062 @@ pub fn do_load_config(path: &str) -> Result<ConfigForm, String> {
 }
 
 /// Save form values to `path` as YAML. Creates parent dirs if needed.
+/// The `client`, `hooks`, `export`, `retrieval` and `prices` sections and
+/// `server.warmup`, `server.max_file_size_mb`, `server.docs_language` and
+/// `server.index_drafts` are not on the form, so existing ones are kept.
 pub fn do_save_config(path: &str, form: &ConfigForm) -> Result<(), String> {
-    let cfg: Config = form.clone().into();
+    let mut cfg: Config = form.clone().into();
+    if let Ok(existing) = config::load(std::path::Path::new(path)) {
+        cfg.client = existing.client;
+        cfg.hooks = existing.hooks;
+        cfg.export = existing.export;
+        cfg.retrieval = existing.retrieval;
+        cfg.prices = existing.prices;
+        cfg.server.warmup = existing.server.warmup;
+        cfg.server.max_file_size_mb = existing.server.max_file_size_mb;
+        cfg.server.docs_language = existing.server.docs_language;
+        cfg.server.index_drafts = existing.server.index_drafts;
+    }
     config::save(std::path::Path::new(path), &cfg).map_err(|e| e.to_string())
 }
 
+/// Quiet time after the last `do_queue_config_save` before the config is
+/// written, so autosaving on every keystroke writes once.
+pub const CONFIG_SAVE_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Event emitted after a queued config save was written (payload: `ConfigSaved`).
+pub const CONFIG_SAVED_EVENT: &str = "config://saved";
+
+/// Outcome of a queued config save.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigSaved {
+    pub path: String,
+    /// Why the file could not be written, if it couldn't.
+    pub error: Option<String>,
+}
+
+/// A config save waiting out `CONFIG_SAVE_DEBOUNCE`, with everyone waiting
+/// for it, including callers whose forms it replaced.
+struct PendingSave {
+    generation: u64,
+    form: ConfigForm,
+    waiters: Vec<tokio::sync::oneshot::Sender<Result<(), String>>>,
+}
+
+static PENDING_SAVES: Mutex<BTreeMap<String, PendingSave>> = Mutex::new(BTreeMap::new());
+
+static SAVE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Held while a queued save writes, so writes never interleave.
+static SAVE_WRITES: Mutex<()> = Mutex::new(());
+
+/// Save `form` to `path` like `do_save_config`, after `CONFIG_SAVE_DEBOUNCE`
+/// without a newer save to the same path. A newer save replaces this one
+/// (last write wins); the receiver then gets the newer one's outcome.
+/// `on_saved` is called only if this save is the one written.
+pub fn do_queue_config_save(
+    path: &str,
+    form: ConfigForm,
+    on_saved: impl FnOnce(ConfigSaved) + Send + 'static,
+) -> tokio::sync::oneshot::Receiver<Result<(), String>> {
+    let (done, outcome) = tokio::sync::oneshot::channel();
+    let generation = SAVE_GENERATION.fetch_add(1, Ordering::SeqCst);
+    {
+        let mut pending = PENDING_SAVES.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut waiters = pending
+            .remove(path)
+            .map(|replaced| replaced.waiters)
+            .unwrap_or_default();
+        waiters.push(done);
+        let save = PendingSave {
+            generation,
+            form,
+            waiters,
+        };
+        pending.insert(path.to_string(), save);
+    }
+    let path = path.to_string();
+    global_runtime().spawn(async move {
+        tokio::time::sleep(CONFIG_SAVE_DEBOUNCE).await;
+        let written = tokio::task::spawn_blocking(move || write_queued_save(path, generation));
+        if let Ok(Some(saved)) = written.await {
+            on_saved(saved);
+        }
+    });
+    outcome
+}
+
+/// Write the save queued for `path` if it is still `generation`.
+fn write_queued_save(path: String, generation: u64) -> Option<ConfigSaved> {
+    let _writing = SAVE_WRITES.lock().unwrap_or_else(PoisonError::into_inner);
+    let save = {
+        let mut pending = PENDING_SAVES.lock().unwrap_or_else(PoisonError::into_inner);
+        match pending.get(&path) {
+            Some(save) if save.generation == generation => pending.remove(&path)?,
+            _ => return None,
+        }
+    };
+    let result = do_save_config(&path, &save.form);
+    for waiter in save.waiters {
+        let _ = waiter.send(result.clone());
+    }
+    Some(ConfigSaved {
+        path,
+        error: result.err(),
+    })
+}
+
+/// Placeholder names in the config template at `template_path`, so
+/// onboarding can ask for their values.
+pub fn do_template_variables(template_path: &str) -> Result<Vec<String>, String> {
+    let template = std::fs::read_to_string(template_path).map_err(|e| e.to_string())?;
+    Ok(config::template_variables(&template))
+}
+
+/// Render the config template at `template_path` with `vars`, validate it
+/// and write it to `path`. An existing file is only replaced with `overwrite`.
+pub fn do_create_config_from_template(
+    template_path: &str,
+    vars: &[(String, String)],
+    path: &str,
+    overwrite: bool,
+) -> Result<ConfigForm, String> {
+    let template = std::fs::read_to_string(template_path).map_err(|e| e.to_string())?;
+    let rendered = config::render_template(&template, vars).map_err(|e| e.to_string())?;
+    let path = std::path::Path::new(path);
+    if path.exists() && !overwrite {
+        return Err(format!("{} already exists", path.display()));
+    }
+    config::save_rendered(path, &rendered).map_err(|e| e.to_string())?;
+    do_load_config(&path.to_string_lossy())
+}
+
 // ── Connection status ───────────────────────────────────────────────
 
 /// Connection status returned to the frontend.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ConnectionStatus {
-    /// "connected", "disconnected", or "error"
+    /// "connected", "disconnected", "reconnecting", or "error"
     pub state: String,
-    /// Error message when state is "error" or "disconnected".
+    /// Error message when state is "error" or "disconnected"; the attempt
+    /// number when "reconnecting"; the warm-up timing (or why it failed)
+    /// when "connected" after a warm-up.
     pub message: Option<String>,
+    /// Set on the one status a watcher reports after the server went away
+    /// (e.g. restarted): "connected" again, or "disconnected" when it did
+    /// not come back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<ServerRestart>,
+}
+
+/// What a server restart cut off, and the server's state afterwards.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServerRestart {
+    /// Why the old connection ended.
+    pub reason: String,
+    /// Turns whose answers were cut off, kept in the history marked as
+    /// interrupted.
+    pub interrupted: Vec<TurnId>,
+    /// Server status checked on the new connection.
+    pub summary: Option<ServerSummary>,
+}
+
+impl From<ConnectionEvent> for ConnectionStatus {
+    fn from(event: ConnectionEvent) -> Self {
+        let (state, message) = match event {
+            ConnectionEvent::Connected => ("connected", None),
+            ConnectionEvent::Disconnected { reason } => ("disconnected", Some(reason)),
+            ConnectionEvent::Reconnecting { attempt } => {
+                ("reconnecting", Some(format!("attempt {}", attempt)))
+            }
+            ConnectionEvent::WarmedUp { elapsed } => (
+                "connected",
+                Some(format!("warmed up in {} ms", elapsed.as_millis())),
+            ),
+            ConnectionEvent::WarmupFailed { reason } => {
+                ("connected", Some(format!("warm-up failed: {}", reason)))
+            }
+        };
+        ConnectionStatus {
+            state: state.into(),
+            message,
+            restart: None,
+        }
+    }
+}
+
+/// Event emitted when the connection's state changes (payload: `ConnectionStatus`).
+pub const CONNECTION_STATUS_EVENT: &str = "connection://status";
+
+/// Ping interval that keeps the GUI's long-lived connection open between questions.
+const KEEPALIVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Seconds between background status checks for the status bar, unless the
+/// config sets `client.status_interval`.
+const DEFAULT_STATUS_INTERVAL_SECS: u64 = 30;
+
+/// The config file, if one can be loaded, else the defaults (always the
+/// defaults in safe mode).
+fn current_config() -> Config {
+    if safe_mode().is_some() {
+        return Config::default();
+    }
+    resolve_config_path(None)
+        .ok()
+        .and_then(|path| config::load(&path).ok())
+        .unwrap_or_default()
 }
 
 /// Attempt to connect to the WebSocket server at `url`.
+/// `wss://` URLs use the `ca_cert` / `accept_invalid_certs` settings of the config file
+/// on top of the OS trust store. Without a configured or environment proxy, the OS
+/// proxy settings apply unless the config sets `client.system_proxy: false`.
+/// With `server.warmup: true` a warm-up retrieval follows the connect; its
+/// timing arrives as a "connected" status event.
 /// Returns a `ConnectionStatus` (never an Err — connection failure is reported in the status).
 pub fn do_connect(url: &str) -> Result<ConnectionStatus, String> {
+    let cfg = current_config();
+    let section = cfg.client;
+    let status_interval = section
+        .status_interval
+        .unwrap_or(DEFAULT_STATUS_INTERVAL_SECS)
+        .max(1);
+    let mut builder = md_qa_client::ClientBuilder::new(url).system_proxy(true);
+    if let Some(tap) = recorder::frame_tap() {
+        builder = builder.frame_tap(tap);
+    }
+    let builder = match builder.client_config(&section) {
+        Ok(builder) => builder
+            .keepalive(KEEPALIVE_INTERVAL)
+            .status_poll(std::time::Duration::from_secs(status_interval))
+            .warmup(cfg.server.warmup)
+            .retry(RetryPolicy::transient()),
+        Err(e) => {
+            return Ok(ConnectionStatus {
+                state: "disconnected".into(),
+                message: Some(e.to_string()),
+                restart: None,
+            })
+        }
+    };
     let rt = global_runtime();
-    let result = rt.block_on(md_qa_client::connect(url));
+    let result = rt.block_on(builder.connect());
 
     match result {
         Ok(client) => {
             let mut guard = CONNECTION.lock().map_err(|e| e.to_string())?;
             *guard = Some(client);
+            *SERVER_URL.lock().map_err(|e| e.to_string())? = Some(url.to_string());
             Ok(ConnectionStatus {
                 state: "connected".into(),
                 message: None,
+                restart: None,
             })
         }
         Err(e) => Ok(ConnectionStatus {
             state: "disconnected".into(),
             message: Some(e.to_string()),
+            restart: None,
         }),
     }
 }
 
-/// Disconnect the current WebSocket connection (if any). Safe to call when not connected.
+/// Disconnect the current WebSocket connection (if any) with a close handshake.
+/// Safe to call when not connected.
 pub fn do_disconnect() {
-    if let Ok(mut guard) = CONNECTION.lock() {
-        *guard = None;
+    if let Ok(mut url) = SERVER_URL.lock() {
+        *url = None;
     }
+    let client = CONNECTION.lock().ok().and_then(|mut guard| guard.take());
+    if let Some(client) = client {
+        let _ = global_runtime().block_on(client.close(1000, "client disconnected"));
+    }
+}
+
+fn connection_events() -> Result<tokio::sync::broadcast::Receiver<ConnectionEvent>, String> {
+    Ok(CONNECTION
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+        .ok_or("Not connected")?
+        .connection_events())
+}
+
+/// Call `on_status` for each lifecycle change of the current connection
+/// (disconnects, and reconnects of retried queries) until it is dropped.
+/// When the server goes away without `do_disconnect` (e.g. it restarted),
+/// the watcher reconnects instead and reports the outcome as one status
+/// with `restart` set, then goes on watching the new connection.
+/// Does nothing in safe mode.
+pub fn do_watch_connection(
+    mut on_status: impl FnMut(ConnectionStatus) + Send + 'static,
+) -> Result<(), String> {
+    if safe_mode().is_some() {
+        return Ok(());
+    }
+    let mut events = connection_events()?;
+    // A thread rather than a task: reconnecting blocks on the runtime.
+    std::thread::spawn(move || {
+        use tokio::sync::broadcast::error::RecvError;
+        loop {
+            match global_runtime().block_on(events.recv()) {
+                Ok(ConnectionEvent::Disconnected { reason }) if server_url().is_some() => {
+                    let status = recover_from_restart(reason);
+                    let reconnected = status.state == "connected";
+                    on_status(status);
+                    match connection_events() {
+                        Ok(next) if reconnected => events = next,
+                        _ => break,
+                    }
+                }
+                Ok(event) => on_status(event.into()),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+    Ok(())
 }
 
-/// Check if a connection is currently held.
+fn server_url() -> Option<String> {
+    SERVER_URL.lock().ok().and_then(|url| url.clone())
+}
+
+/// Reconnect attempts after the server went away, each with the usual
+/// connect retries, before giving up.
+const RESTART_ATTEMPTS: u32 = 5;
+
+/// Pause between reconnect attempts after the server went away.
+const RESTART_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How long a watcher waits for the queries a restart cut off to be
+/// recorded, before stopping them and again before going on without them.
+const RESTART_QUERY_WAIT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Wait until no query holds `QUERIES`. A query still reading from a
+/// connection nobody has seen fail yet would hold it for good, so after
+/// `RESTART_QUERY_WAIT` the running queries are stopped, and after as long
+/// again the wait gives up.
+fn wait_for_queries() {
+    for stop in [false, true] {
+        if stop {
+            STOP.notify_waiters();
+        }
+        let deadline = std::time::Instant::now() + RESTART_QUERY_WAIT;
+        loop {
+            match QUERIES.try_write() {
+                Err(std::sync::TryLockError::WouldBlock) => {}
+                _ => return,
+            }
+            if std::time::Instant::now() >= deadline {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+}
+
+/// Reply to commands that need the server while a watcher reconnects.
+pub const SERVER_RESTARTING: &str = "Reconnecting to the server";
+
+/// Handle the server going away: wait (a bounded time) for the queries it
+/// cut off to be recorded as interrupted, reconnect to the same URL and check the server's
+/// status again. Commands that need the server meanwhile fail with
+/// `SERVER_RESTARTING` instead of a connection error each.
+fn recover_from_restart(reason: String) -> ConnectionStatus {
+    RESTARTING.store(true, Ordering::SeqCst);
+    wait_for_queries();
+    let interrupted = INTERRUPTED_TURNS
+        .lock()
+        .map(|mut turns| std::mem::take(&mut *turns))
+        .unwrap_or_default();
+    let mut status = ConnectionStatus {
+        state: "disconnected".into(),
+        message: Some(reason.clone()),
+        restart: None,
+    };
+    for attempt in 1..=RESTART_ATTEMPTS {
+        // Stop once the user disconnects.
+        let Some(url) = server_url() else { break };
+        if attempt > 1 {
+            std::thread::sleep(RESTART_RETRY_DELAY);
+        }
+        status = do_connect(&url).unwrap_or_else(|e| ConnectionStatus {
+            state: "disconnected".into(),
+            message: Some(e),
+            restart: None,
+        });
+        if status.state == "connected" {
+            break;
+        }
+    }
+    let summary = match CONNECTION.lock() {
+        Ok(guard) if status.state == "connected" => guard
+            .as_ref()
+            .and_then(|client| global_runtime().block_on(client.check_status()).ok()),
+        _ => None,
+    };
+    RESTARTING.store(false, Ordering::SeqCst);
+    status.restart = Some(ServerRestart {
+        reason,
+        interrupted,
+        summary,
+    });
+    status
+}
+
+/// Check if a connection is currently held and not known to be dead (the
+/// keepalive watchdog flags servers that stop answering pings).
 pub fn is_connected() -> bool {
     CONNECTION
         .lock()
-        .map(|g| g.is_some())
+        .map(|g| g.as_ref().is_some_and(|client| client.is_connected()))
         .unwrap_or(false)
 }
 
+/// How long `connection_status` waits for the server to answer its ping.
+pub const LIVENESS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// State of the held connection, probed with a ping that must be answered
+/// within `timeout`. A dead connection is dropped and reported as
+/// "disconnected" with the reason. While a query holds the connection it is
+/// reported as "connected" without probing.
+pub fn do_connection_status(timeout: std::time::Duration) -> ConnectionStatus {
+    let disconnected = |message: Option<String>| ConnectionStatus {
+        state: "disconnected".into(),
+        message,
+        restart: None,
+    };
+    if RESTARTING.load(Ordering::SeqCst) {
+        return ConnectionStatus {
+            state: "reconnecting".into(),
+            message: Some(SERVER_RESTARTING.into()),
+            restart: None,
+        };
+    }
+    let mut guard = match CONNECTION.try_lock() {
+        Ok(guard) => guard,
+        Err(std::sync::TryLockError::WouldBlock) => {
+            return ConnectionStatus {
+                state: "connected".into(),
+                message: None,
+                restart: None,
+            }
+        }
+        Err(e) => return disconnected(Some(e.to_string())),
+    };
+    let Some(client) = guard.as_ref() else {
+        return disconnected(None);
+    };
+    let probe = if client.is_connected() {
+        global_runtime()
+            .block_on(client.ping(timeout))
+            .map_err(|e| e.to_string())
+    } else {
+        Err(client
+            .disconnect_reason()
+            .unwrap_or_else(|| "connection lost".into()))
+    };
+    match probe {
+        Ok(_) => ConnectionStatus {
+            state: "connected".into(),
+            message: None,
+            restart: None,
+        },
+        Err(reason) => {
+            *guard = None;
+            disconnected(Some(reason))
+        }
+    }
+}
+
+/// Run a request on the current connection, dropping it if it turns out dead.
+fn with_connection<T>(
+    request: impl FnOnce(&md_qa_client::Client) -> Result<T, md_qa_client::ClientError>,
+) -> Result<T, String> {
+    if RESTARTING.load(Ordering::SeqCst) {
+        return Err(SERVER_RESTARTING.into());
+    }
+    let mut guard = CONNECTION.lock().map_err(|e| e.to_string())?;
+    let client = guard.as_ref().ok_or("Not connected")?;
+    request(client).map_err(|e| {
+        if e.is_connection_lost() {
+            *guard = None;
+        }
+        e.to_string()
+    })
+}
+
+/// Ask the connected server whether its index is loaded (and how many
+/// documents it holds, if reported).
+pub fn do_server_status() -> Result<StatusMessage, String> {
+    with_connection(|client| global_runtime().block_on(client.status()))
+}
+
+/// Ask the connected server to re-index `index` (its default when `None`)
+/// after Markdown files were edited. Returns the server's status once the
+/// reload has started.
+pub fn do_reload_index(index: Option<&str>) -> Result<StatusMessage, String> {
+    with_connection(|client| global_runtime().block_on(client.reload_index(index)))
+}
+
+/// What a reindex would embed, with its cost priced by the `prices` table of
+/// the config file, so the Reindex button can ask before a large build.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReloadEstimate {
+    #[serde(flatten)]
+    pub estimate: IndexEstimate,
+    /// `None` when the embedding model has no entry in `prices`.
+    pub cost: Option<f64>,
+    /// Whether the reload embeds at least `LARGE_RELOAD_TOKENS` tokens and
+    /// should be confirmed.
+    pub large: bool,
+}
+
+/// Estimate what re-indexing `index` (the server's default when `None`)
+/// would embed. `None` when the server predates estimates.
+pub fn do_index_estimate(index: Option<&str>) -> Result<Option<ReloadEstimate>, String> {
+    let estimate = with_connection(|client| {
+        let plan = global_runtime().block_on(client.index_estimate(index));
+        match plan {
+            Ok(plan) => Ok(plan.estimate),
+            Err(md_qa_client::ClientError::ServerError(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    })?;
+    let prices = current_config().prices;
+    Ok(estimate.map(|estimate| ReloadEstimate {
+        cost: embedding_cost(
+            &prices,
+            estimate.embedding_model.as_deref(),
+            estimate.tokens,
+        ),
+        large: estimate.tokens >= LARGE_RELOAD_TOKENS,
+        estimate,
+    }))
+}
+
+/// Counts, embedding model, build time and size on disk of `index` (the
+/// server's default when `None`), for the settings page.
+pub fn do_index_stats(index: Option<&str>) -> Result<IndexStatsMessage, String> {
+    with_connection(|client| global_runtime().block_on(client.index_stats(index)))
+}
+
+/// Optional features of the connected server (`cancel`, `history`, ...), so
+/// the frontend can hide what it can't do. Asked for once per connection;
+/// `None` when the server predates the request.
+pub fn do_server_capabilities() -> Result<Option<CapabilitiesMessage>, String> {
+    with_connection(|client| {
+        if let Some(capabilities) = client.capabilities() {
+            return Ok(Some(capabilities));
+        }
+        match global_runtime().block_on(client.fetch_capabilities()) {
+            Ok(capabilities) => Ok(Some(capabilities)),
+            Err(md_qa_client::ClientError::ServerError(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    })
+}
+
+/// Document-change notifications pushed by the server since the last call,
+/// so the frontend can offer to re-ask the last question (via `regenerate`).
+/// Returns nothing while a query holds the connection; poll again later.
+pub fn do_take_notifications() -> Vec<NotificationMessage> {
+    match CONNECTION.try_lock() {
+        Ok(guard) => guard
+            .as_ref()
+            .map(|client| client.take_notifications())
+            .unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Latest background status check of the connected server (ready, document
+/// count, index age, latency) for the status bar. `None` when not connected,
+/// before the first check, or while a query holds the connection.
+pub fn do_server_summary() -> Option<ServerSummary> {
+    CONNECTION
+        .try_lock()
+        .ok()?
+        .as_ref()
+        .and_then(|client| client.server_summary())
+}
+
+/// Latest index build progress pushed by the server (e.g. while it rebuilds
+/// after a config change), for a progress bar. `None` when not connected,
+/// before the server reported any, or while a query holds the connection.
+pub fn do_index_progress() -> Option<IndexProgressMessage> {
+    CONNECTION
+        .try_lock()
+        .ok()?
+        .as_ref()
+        .and_then(|client| client.index_progress())
+}
+
+/// Event emitted for each index build progress message the server pushes
+/// (payload: `IndexProgressMessage`); the last one of a build has
+/// `finished` set and lists the documents that could not be indexed.
+pub const INDEX_PROGRESS_EVENT: &str = "index://progress";
+
+/// Bumped by each `do_watch_index_progress`, so only the latest call's
+/// watcher passes progress on.
+static INDEX_PROGRESS_WATCH: AtomicU64 = AtomicU64::new(0);
+
+/// Call `on_progress` with every index build progress message the server
+/// pushes, also after a restart watcher reconnected, until `do_disconnect`
+/// or the next call.
+pub fn do_watch_index_progress(
+    mut on_progress: impl FnMut(IndexProgressMessage) + Send + 'static,
+) -> Result<(), String> {
+    let generation = INDEX_PROGRESS_WATCH.fetch_add(1, Ordering::SeqCst) + 1;
+    let mut events = with_connection(|client| Ok(client.index_progress_events()))?;
+    std::thread::spawn(move || {
+        use tokio::sync::broadcast::error::RecvError;
+        loop {
+            let progress = match global_runtime().block_on(events.recv()) {
+                Ok(progress) => progress,
+                Err(RecvError::Lagged(_)) => continue,
+                // The connection was dropped: follow the one that replaced it.
+                Err(RecvError::Closed) => match CONNECTION.lock() {
+                    Ok(guard) => match guard.as_ref() {
+                        Some(client) => {
+                            events = client.index_progress_events();
+                            continue;
+                        }
+                        None => break,
+                    },
+                    Err(_) => break,
+                },
+            };
+            if INDEX_PROGRESS_WATCH.load(Ordering::SeqCst) != generation {
+                break;
+            }
+            on_progress(progress);
+        }
+    });
+    Ok(())
+}
+
+/// Event emitted for each server log line followed with `tail_server_logs`
+/// (payload: `LogLine`).
+pub const SERVER_LOG_EVENT: &str = "server://log";
+
+/// Bumped by each `do_tail_server_logs`, so only the latest call's watcher
+/// passes lines on.
+static LOG_TAIL: AtomicU64 = AtomicU64::new(0);
+
+/// The last `lines` lines of the server log with their severity, for a log
+/// panel that shows why the index is not ready. With `follow`, `on_line` is
+/// called with every line the server logs afterwards, until the next call
+/// or the end of the connection. Servers without a log tail answer with an
+/// error.
+pub fn do_tail_server_logs(
+    lines: usize,
+    follow: bool,
+    mut on_line: impl FnMut(LogLine) + Send + 'static,
+) -> Result<Vec<LogLine>, String> {
+    let generation = LOG_TAIL.fetch_add(1, Ordering::SeqCst) + 1;
+    let (recent, mut followed) = with_connection(|client| {
+        let followed = client.log_lines();
+        let recent = global_runtime().block_on(client.tail_logs(lines, follow))?;
+        Ok((recent, followed))
+    })?;
+    if follow {
+        std::thread::spawn(move || {
+            use tokio::sync::broadcast::error::RecvError;
+            loop {
+                let line = match global_runtime().block_on(followed.recv()) {
+                    Ok(line) => line,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                if LOG_TAIL.load(Ordering::SeqCst) != generation {
+                    break;
+                }
+                on_line(line);
+            }
+        });
+    }
+    Ok(recent)
+}
+
+// ── Startup self-check ──────────────────────────────────────────────────
+
+/// One step of the startup self-check.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CheckStep {
+    /// "config", "directories", "server", or "index".
+    pub step: String,
+    /// "ok", "failed", or "skipped" (an earlier step failed).
+    pub state: String,
+    /// What went wrong, or a detail such as the document count.
+    pub message: Option<String>,
+}
+
+/// Event emitted with the startup checklist (payload: `Vec<CheckStep>`).
+pub const STARTUP_CHECK_EVENT: &str = "startup://checklist";
+
+/// Self-check steps, in the order they run.
+const STARTUP_STEPS: [&str; 4] = ["config", "directories", "server", "index"];
+
+/// Check, in order, that the config file is readable, its directories exist,
+/// the server is reachable (leaving the GUI connected) and its index is
+/// ready. Stops at the first failure; the steps after it are "skipped".
+/// In safe mode every step is skipped, so the GUI does not connect.
+pub fn do_startup_check() -> Vec<CheckStep> {
+    let mut steps = Vec::new();
+    if safe_mode().is_none() {
+        run_startup_steps(&mut steps);
+    }
+    for step in &STARTUP_STEPS[steps.len()..] {
+        steps.push(CheckStep {
+            step: step.to_string(),
+            state: "skipped".into(),
+            message: None,
+        });
+    }
+    steps
+}
+
+/// Run the self-check steps until one fails, recording each in `steps`.
+fn run_startup_steps(steps: &mut Vec<CheckStep>) -> Option<()> {
+    let loaded = resolve_config_path(None).and_then(|path| {
+        config::load_with_warnings(&path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))
+    });
+    // Warnings (e.g. duplicate keys) do not fail the step but are shown.
+    let cfg = match loaded {
+        Ok(loaded) => {
+            let warnings = (!loaded.warnings.is_empty()).then(|| loaded.warnings.join("; "));
+            record_step(steps, Ok(warnings)).map(|_| loaded.config)?
+        }
+        Err(e) => return record_step(steps, Err(e)),
+    };
+
+    let directories = if cfg.client.url.is_some() {
+        Ok(Some(
+            "not checked: client.url names a separately run server".into(),
+        ))
+    } else if cfg.server.directories.is_empty() {
+        Err("no directories configured".to_string())
+    } else {
+        let missing: Vec<&str> = cfg
+            .server
+            .directories
+            .iter()
+            .map(String::as_str)
+            .filter(|dir| !std::path::Path::new(dir).is_dir())
+            .collect();
+        match missing.as_slice() {
+            [] => Ok(None),
+            missing => Err(format!("not found: {}", missing.join(", "))),
+        }
+    };
+    record_step(steps, directories)?;
+
+    let reachable = do_connect(&cfg.server_url()).and_then(|status| match status.state.as_str() {
+        "connected" => Ok(None),
+        _ => Err(status.message.unwrap_or_else(|| "not reachable".into())),
+    });
+    record_step(steps, reachable)?;
+
+    let index = do_server_status().and_then(|status| match status.is_ready() {
+        true => Ok(status.documents.map(|n| format!("{} documents", n))),
+        false => Err(status
+            .message
+            .unwrap_or_else(|| format!("index is {}", status.status))),
+    });
+    record_step(steps, index)
+}
+
+/// Record the next step's outcome; `None` if it failed.
+fn record_step(steps: &mut Vec<CheckStep>, result: Result<Option<String>, String>) -> Option<()> {
+    let (state, message) = match &result {
+        Ok(detail) => ("ok", detail.clone()),
+        Err(e) => ("failed", Some(e.clone())),
+    };
+    steps.push(CheckStep {
+        step: STARTUP_STEPS[steps.len()].to_string(),
+        state: state.into(),
+        message,
+    });
+    result.ok().map(|_| ())
+}
+
+// ── Safe mode ───────────────────────────────────────────────────────────
+
+/// How the GUI was started with `--safe-mode`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SafeMode {
+    /// Where the config file was moved, if there was one.
+    pub config_backup: Option<String>,
+}
+
+static SAFE_MODE: Mutex<Option<SafeMode>> = Mutex::new(None);
+
+/// The safe mode the GUI runs in, if it was started with `--safe-mode`.
+pub fn safe_mode() -> Option<SafeMode> {
+    SAFE_MODE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Switch to safe mode, for when a broken config keeps the GUI from
+/// starting: default settings, no startup check or connection watcher, and
+/// the config file at `config_path` moved aside to `<name>.bad` (replacing
+/// an earlier backup) so a fixed one can be saved in its place. Safe mode is
+/// on even if the file could not be moved.
+pub fn do_enter_safe_mode(config_path: Option<&std::path::Path>) -> Result<SafeMode, String> {
+    *SAFE_MODE.lock().unwrap_or_else(PoisonError::into_inner) = Some(SafeMode {
+        config_backup: None,
+    });
+    let Some(path) = config_path.filter(|path| path.is_file()) else {
+        return Ok(SafeMode {
+            config_backup: None,
+        });
+    };
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bad");
+    let backup = path.with_file_name(name);
+    let _ = std::fs::remove_file(&backup);
+    std::fs::rename(path, &backup)
+        .map_err(|e| format!("cannot move {} aside: {}", path.display(), e))?;
+    let mode = SafeMode {
+        config_backup: Some(backup.display().to_string()),
+    };
+    *SAFE_MODE.lock().unwrap_or_else(PoisonError::into_inner) = Some(mode.clone());
+    Ok(mode)
+}
+
+// ── Index browser ───────────────────────────────────────────────────────
+
+/// Documents per page in the index browser.
+pub const DOCUMENTS_PAGE_SIZE: usize = 50;
+
+/// One page (zero-based) of the documents in `index`, with chunk counts and
+/// last-indexed times.
+pub fn do_list_indexed_documents(
+    index: Option<&str>,
+    page: usize,
+) -> Result<DocumentsMessage, String> {
+    with_connection(|client| {
+        global_runtime().block_on(client.list_documents(index, page, DOCUMENTS_PAGE_SIZE))
+    })
+}
+
+/// The indexes the server has, its configured one first, for the index
+/// picker.
+pub fn do_list_indexes() -> Result<Vec<IndexInfo>, String> {
+    with_connection(|client| global_runtime().block_on(client.list_indexes()))
+}
+
+/// Add the named index `name` over `directories` (on the server's machine),
+/// with its own chunking and embedding model; `None` ones are the server's.
+/// The server builds it in the background. Returns the indexes there are now.
+pub fn do_create_index(
+    name: &str,
+    directories: &[String],
+    chunking: Option<&ChunkingSection>,
+    embedding_model: Option<&str>,
+) -> Result<Vec<IndexInfo>, String> {
+    with_connection(|client| {
+        global_runtime().block_on(client.create_index(name, directories, chunking, embedding_model))
+    })
+}
+
+/// Remove the named index `name` and its files. Returns the indexes left.
+pub fn do_delete_index(name: &str) -> Result<Vec<IndexInfo>, String> {
+    with_connection(|client| global_runtime().block_on(client.delete_index(name)))
+}
+
+/// The indexed chunks of the document at `path`.
+pub fn do_get_document_chunks(path: &str) -> Result<ChunksMessage, String> {
+    with_connection(|client| global_runtime().block_on(client.document_chunks(path)))
+}
+
+/// The documents that the document at `path` links to and those linking to
+/// it, for navigating from a cited source.
+pub fn do_get_document_links(path: &str) -> Result<LinksMessage, String> {
+    with_connection(|client| global_runtime().block_on(client.document_links(path)))
+}
+
 // ── Chat query ──────────────────────────────────────────────────────────
 
+static HISTORY: Mutex<Conversation> = Mutex::new(Conversation::new());
+
 /// Result of a chat query returned to the frontend.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ChatReply {
     /// Full assembled answer text (all stream chunks concatenated).
     pub answer: String,
-    /// Source file paths returned with STREAM_END.
-    pub sources: Vec<String>,
+    /// Reasoning the model streamed before answering, shown collapsed.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reasoning: String,
+    /// Every candidate answer, the first being `answer`, when the query asked
+    /// for several (`QueryOptions::candidates`) and the server sent them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<String>,
+    /// `answer` split at its citation markers, each with the cited source,
+    /// so they can be rendered as links; a single text span when the server
+    /// sent no citations.
+    #[serde(default)]
+    pub answer_spans: Vec<AnswerSpan>,
+    /// Sources returned with STREAM_END, with heading, score, snippet and
+    /// line range when the server reports them, up to `retrieval.max_sources`.
+    pub sources: Vec<Source>,
+    /// Sources left out of `sources` by `retrieval.max_sources`.
+    #[serde(default)]
+    pub omitted_sources: usize,
+    /// All sources by top-level directory, with `retrieval.group_sources`.
+    #[serde(default)]
+    pub source_groups: Vec<SourceGroup>,
     /// Error message from the server, if any.
     pub error: Option<String>,
+    /// Machine-readable reason of `error`, so the UI can suggest a fix.
+    #[serde(default)]
+    pub error_code: Option<ErrorCode>,
+    /// History turn recording this exchange (`None` when the server errored).
+    pub turn_id: Option<TurnId>,
+    /// Grounding check result, present when the query was sent with `verify`.
+    pub grounding: Option<Grounding>,
+    /// Suggested follow-up questions, when requested and the server offers them.
+    #[serde(default)]
+    pub followups: Vec<String>,
+    /// Problems that did not stop the question, e.g. a failed hook under
+    /// `hooks.on_failure: warn`.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Tokens the answer used and the server's time, as far as the server
+    /// reported them.
+    #[serde(default)]
+    pub stats: QueryStats,
+}
+
+/// Event emitted for each answer chunk while a reply streams in.
+pub const CHAT_CHUNK_EVENT: &str = "chat://chunk";
+
+/// Payload of `CHAT_CHUNK_EVENT`, with generation-speed metadata so the UI
+/// can show a live indicator.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChatChunk {
+    pub chunk: String,
+    /// Words in this chunk (whitespace-separated).
+    pub words: usize,
+    /// Estimated tokens in the answer so far (about four characters each;
+    /// the server does not report token counts).
+    pub tokens: usize,
+    /// Milliseconds since the query was sent.
+    pub elapsed_ms: u64,
+    /// Estimated tokens per second since the first chunk arrived; 0 for the
+    /// first chunk.
+    pub tokens_per_sec: f64,
+}
+
+/// Timing of one streamed answer, turning its chunks into `ChatChunk`s.
+struct ChunkMeter {
+    started: std::time::Instant,
+    /// When the first chunk arrived and the tokens it brought.
+    first_chunk: Option<(std::time::Instant, usize)>,
+    chars: usize,
+}
+
+impl ChunkMeter {
+    fn new() -> Self {
+        Self {
+            started: std::time::Instant::now(),
+            first_chunk: None,
+            chars: 0,
+        }
+    }
+
+    fn chunk(&mut self, text: &str) -> ChatChunk {
+        let now = std::time::Instant::now();
+        self.chars += text.chars().count();
+        let tokens = self.chars.div_ceil(4);
+        let (first_at, first_tokens) = *self.first_chunk.get_or_insert((now, tokens));
+        let generating = now.duration_since(first_at).as_secs_f64();
+        let tokens_per_sec = if generating > 0.0 {
+            (tokens - first_tokens) as f64 / generating
+        } else {
+            0.0
+        };
+        ChatChunk {
+            chunk: text.to_string(),
+            words: text.split_whitespace().count(),
+            tokens,
+            elapsed_ms: now.duration_since(self.started).as_millis() as u64,
+            tokens_per_sec,
+        }
+    }
+}
+
+/// Hook commands (`hooks` section) of the config file, if one can be loaded;
+/// none in safe mode, so a broken config's hooks never run.
+fn configured_hooks() -> Hooks {
+    Hooks::from_config(&current_config().hooks)
+}
+
+/// Apply `hooks.on_failure` to a hook's result: an error under `abort`,
+/// else `None` with the failure added to `warnings`.
+fn hook_outcome<T>(
+    hooks: &Hooks,
+    result: Result<T, HookError>,
+    warnings: &mut Vec<String>,
+) -> Result<Option<T>, String> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if hooks.on_failure == HookFailure::Abort => Err(e.to_string()),
+        Err(e) => {
+            warnings.push(e.to_string());
+            Ok(None)
+        }
+    }
 }
 
-/// Send a query over the current connection. Returns the assembled reply.
+/// Woken by `stop_query` to stop the query that is running.
+static STOP: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+/// `ChatReply::error` of a query stopped with `stop_query`.
+pub const STOPPED: &str = "Stopped";
+
+/// `ChatReply::error` of a query cut off by the server going away; its
+/// turn keeps the partial answer and is marked as interrupted.
+pub const INTERRUPTED: &str = "Interrupted";
+
+/// Held (shared) by each query until its turn is recorded, so a watcher
+/// reconnecting after a server restart can wait for the queries it cut off.
+static QUERIES: RwLock<()> = RwLock::new(());
+
+/// Interrupted turns not yet reported in a `ServerRestart`.
+static INTERRUPTED_TURNS: Mutex<Vec<TurnId>> = Mutex::new(Vec::new());
+
+/// Stop the query that is running, if any: the server is told to stop
+/// generating, and the query returns what was answered so far with the
+/// error `STOPPED`.
+pub fn do_stop_query() {
+    STOP.notify_waiters();
+}
+
+/// Run a query over the current connection, calling `on_chunk` as chunks arrive,
+/// and assemble the reply (not yet recorded in history). The configured
+/// `pre_query` hook may rewrite the question sent; `post_answer` sees the answer.
+/// Returns the question as sent along with the reply: it is the one the query
+/// log and the history record, so a turn shows what was actually answered.
+fn run_query(
+    msg: &QueryMessage<'_>,
+    on_chunk: &mut dyn FnMut(&ChatChunk),
+) -> Result<(String, ChatReply), String> {
+    let hooks = configured_hooks();
+    let mut warnings = Vec::new();
+    let rewritten = global_runtime().block_on(hooks.pre_query(msg.question));
+    let question =
+        hook_outcome(&hooks, rewritten, &mut warnings)?.unwrap_or_else(|| msg.question.to_string());
+    let mut msg = msg.clone();
+    msg.question = &question;
+
+    if RESTARTING.load(Ordering::SeqCst) {
+        return Err(SERVER_RESTARTING.into());
+    }
+    let mut guard = CONNECTION.lock().map_err(|e| e.to_string())?;
+    let client = guard.as_mut().ok_or("Not connected")?;
+
+    let mut collector = StreamCollector::with_retrieval(&current_config().retrieval);
+    let mut meter = ChunkMeter::new();
+    let result = global_runtime().block_on(async {
+        // Registered before the query is sent, so no stop request is missed.
+        let stop = STOP.notified();
+        futures_util::pin_mut!(stop);
+        stop.as_mut().enable();
+        let stream = client.query_message_stream(&msg);
+        futures_util::pin_mut!(stream);
+        loop {
+            let event = tokio::select! {
+                event = stream.next() => event,
+                _ = &mut stop => {
+                    // The rest of the answer is skipped by the next query.
+                    let _ = client.cancel(msg.id).await;
+                    return Ok(true);
+                }
+            };
+            let Some(event) = event else { break };
+            let event = event?;
+            // A non-streaming `response` reaches the UI as one chunk.
+            if let Some(chunk) = event.answer_text() {
+                on_chunk(&meter.chunk(chunk));
+            }
+            collector.push(&event);
+        }
+        Ok::<_, md_qa_client::ClientError>(false)
+    });
+    let stopped = match result {
+        Ok(stopped) => stopped,
+        // Close frame or EOF mid-answer: the server went away, e.g. to restart.
+        Err(md_qa_client::ClientError::Closed | md_qa_client::ClientError::Io(_)) => {
+            *guard = None;
+            collector.error = Some(INTERRUPTED.to_string());
+            false
+        }
+        Err(e) => {
+            // Drop a dead connection so connection_status reports "disconnected".
+            if e.is_connection_lost() {
+                *guard = None;
+            }
+            return Err(e.to_string());
+        }
+    };
+    drop(guard);
+    if stopped {
+        // Not an answer: neither logged nor passed to the post_answer hook.
+        let reply = ChatReply {
+            answer: collector.answer,
+            reasoning: collector.reasoning,
+            candidates: collector.candidates,
+            answer_spans: Vec::new(),
+            sources: Vec::new(),
+            omitted_sources: 0,
+            source_groups: Vec::new(),
+            error: Some(STOPPED.to_string()),
+            error_code: None,
+            turn_id: None,
+            grounding: None,
+            followups: Vec::new(),
+            warnings,
+            stats: QueryStats::default(),
+        };
+        return Ok((question, reply));
+    }
+    log_query(&msg, &collector);
+    if collector.error.is_none() {
+        let answered = global_runtime().block_on(hooks.post_answer(
+            &question,
+            &collector.answer,
+            &collector.sources,
+        ));
+        hook_outcome(&hooks, answered, &mut warnings)?;
+    }
+
+    let reply = ChatReply {
+        answer_spans: collector.answer_spans(),
+        answer: collector.answer,
+        reasoning: collector.reasoning,
+        candidates: collector.candidates,
+        sources: collector.sources,
+        omitted_sources: collector.omitted_sources,
+        source_groups: collector.source_groups,
+        error: collector.error,
+        error_code: collector.error_code,
+        turn_id: None,
+        grounding: collector.grounding,
+        followups: Vec::new(),
+        warnings,
+        stats: collector.stats,
+    };
+    Ok((question, reply))
+}
+
+/// Run a blocking backend function off the IPC thread, so events it emits
+/// reach the webview while it is still running.
+async fn run_blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    global_runtime()
+        .spawn_blocking(f)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Paths of `sources`, as recorded in the conversation history.
+fn source_paths(sources: &[Source]) -> Vec<String> {
+    sources.iter().map(|s| s.path.clone()).collect()
+}
+
+/// Send a query over the current connection. Returns the assembled reply and
+/// appends it to the active branch of the conversation history; an answer
+/// cut off by the server going away is kept too, with the error
+/// `INTERRUPTED`.
 pub fn do_send_query(question: &str, index: Option<&str>) -> Result<ChatReply, String> {
+    do_send_query_message(&QueryMessage::new(question, index), |_| {})
+}
+
+/// Earlier turns included in the retrieval query and history of a follow-up question.
+const HISTORY_CONTEXT_TURNS: usize = 3;
+
+/// Attach the turns up to `leaf` to a follow-up: condensed for its retrieval
+/// query, in full as its conversation history.
+fn with_recent_turns<'a>(
+    msg: QueryMessage<'a>,
+    leaf: Option<TurnId>,
+) -> Result<QueryMessage<'a>, String> {
+    let history = HISTORY.lock().map_err(|e| e.to_string())?;
+    Ok(msg
+        .with_history_context(history.retrieval_context(leaf, HISTORY_CONTEXT_TURNS))
+        .with_history(history.recent_turns(leaf, HISTORY_CONTEXT_TURNS)))
+}
+
+/// Like `do_send_query`, but sends a fully built `QueryMessage` (e.g. with `verify` set)
+/// and calls `on_chunk` for each answer chunk as it arrives. Follow-ups carry
+/// the recent turns of the active branch (for retrieval and as history).
+pub fn do_send_query_message(
+    msg: &QueryMessage<'_>,
+    mut on_chunk: impl FnMut(&ChatChunk),
+) -> Result<ChatReply, String> {
+    let mut msg = msg.clone();
+    if msg.retrieval_query.is_none() && msg.history.is_empty() {
+        let leaf = HISTORY.lock().map_err(|e| e.to_string())?.active_leaf();
+        msg = with_recent_turns(msg, leaf)?;
+    }
+    let _running = QUERIES.read().map_err(|e| e.to_string())?;
+    let (question, mut reply) = run_query(&msg, &mut on_chunk)?;
+    if is_recorded(&reply) {
+        let mut history = HISTORY.lock().map_err(|e| e.to_string())?;
+        let sources = source_paths(&reply.sources);
+        let id = history.push(&question, &reply.answer, sources);
+        finish_turn(&mut history, id, &mut reply)?;
+    }
+    Ok(reply)
+}
+
+/// Whether `reply` gets a turn in the history: answers, including ones the
+/// server going away cut off.
+fn is_recorded(reply: &ChatReply) -> bool {
+    reply.error.is_none() || reply.error.as_deref() == Some(INTERRUPTED)
+}
+
+/// Record the token usage and interruption of `reply` on its new turn `id`.
+fn finish_turn(
+    history: &mut Conversation,
+    id: TurnId,
+    reply: &mut ChatReply,
+) -> Result<(), String> {
+    if let Some(usage) = &reply.stats.usage {
+        history
+            .set_usage(id, usage.clone())
+            .map_err(|e| e.to_string())?;
+    }
+    if reply.error.as_deref() == Some(INTERRUPTED) {
+        history.set_interrupted(id).map_err(|e| e.to_string())?;
+        INTERRUPTED_TURNS
+            .lock()
+            .map_err(|e| e.to_string())?
+            .push(id);
+    }
+    reply.turn_id = Some(id);
+    Ok(())
+}
+
+// ── Answer comparison ───────────────────────────────────────────────────
+
+/// Both answers of a side-by-side comparison. Neither is recorded in the
+/// conversation history; an error on one side leaves the other intact.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ComparisonReply {
+    pub left: ChatReply,
+    pub right: ChatReply,
+}
+
+/// Event emitted for each answer chunk of either side while a comparison streams in.
+pub const COMPARE_CHUNK_EVENT: &str = "compare://chunk";
+
+/// Payload of `COMPARE_CHUNK_EVENT`: a `ChatChunk` plus the answer it belongs to.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ComparisonChunk {
+    pub side: Side,
+    #[serde(flatten)]
+    pub chunk: ChatChunk,
+}
+
+/// Ask `question` with two option sets (e.g. a different `model` each) at
+/// the same time: the left answer streams over the current connection, the
+/// right one over a second connection opened for it. Calls `on_chunk` for
+/// each chunk of either answer as it arrives. An answer cut off by the
+/// server going away ends with the error `INTERRUPTED`.
+pub fn do_compare_query(
+    question: &str,
+    index: Option<&str>,
+    left: QueryOptions,
+    right: QueryOptions,
+    mut on_chunk: impl FnMut(&ComparisonChunk),
+) -> Result<ComparisonReply, String> {
+    if RESTARTING.load(Ordering::SeqCst) {
+        return Err(SERVER_RESTARTING.into());
+    }
+    let _running = QUERIES.read().map_err(|e| e.to_string())?;
     let mut guard = CONNECTION.lock().map_err(|e| e.to_string())?;
     let client = guard.as_mut().ok_or("Not connected")?;
 
-    let rt = global_runtime();
-    let events = rt.block_on(client.query(question, index)).map_err(|e| e.to_string())?;
+    let left = QueryMessage::new(question, index).with_options(left);
+    let right = QueryMessage::new(question, index).with_options(right);
+    let retrieval = current_config().retrieval;
+    let mut sides = [Side::Left, Side::Right].map(|side| {
+        let collector = StreamCollector::with_retrieval(&retrieval);
+        (side, collector, ChunkMeter::new(), None)
+    });
+    global_runtime().block_on(async {
+        let stream = client.compare_stream(&left, &right);
+        futures_util::pin_mut!(stream);
+        while let Some((side, event)) = stream.next().await {
+            let (_, collector, meter, failure) = &mut sides[side as usize];
+            match event {
+                Ok(event) => {
+                    if let Some(chunk) = event.answer_text() {
+                        let chunk = meter.chunk(chunk);
+                        on_chunk(&ComparisonChunk { side, chunk });
+                    }
+                    collector.push(&event);
+                }
+                Err(e) => *failure = Some(e),
+            }
+        }
+    });
+
+    let [left, right] = sides.map(|(side, collector, _, failure)| {
+        let answer_spans = collector.answer_spans();
+        let error = match failure {
+            // Close frame or EOF mid-answer: the server went away, e.g. to restart.
+            Some(md_qa_client::ClientError::Closed | md_qa_client::ClientError::Io(_)) => {
+                if side == Side::Left {
+                    *guard = None;
+                }
+                Some(INTERRUPTED.to_string())
+            }
+            // Drop a dead connection so connection_status reports "disconnected".
+            Some(e) => {
+                if side == Side::Left && e.is_connection_lost() {
+                    *guard = None;
+                }
+                Some(e.to_string())
+            }
+            None => collector.error,
+        };
+        ChatReply {
+            answer: collector.answer,
+            reasoning: collector.reasoning,
+            candidates: collector.candidates,
+            answer_spans,
+            sources: collector.sources,
+            omitted_sources: collector.omitted_sources,
+            source_groups: collector.source_groups,
+            error,
+            error_code: collector.error_code,
+            turn_id: None,
+            grounding: collector.grounding,
+            followups: Vec::new(),
+            warnings: Vec::new(),
+            stats: collector.stats,
+        }
+    });
+    Ok(ComparisonReply { left, right })
+}
+
+/// Follow-up questions offered under an answer.
+const FOLLOWUP_COUNT: usize = 3;
 
-    let mut answer = String::new();
-    let mut sources = Vec::new();
-    let mut error = None;
+/// Fill `reply.followups` with questions suggested for the answer to
+/// `question`. Suggestions are optional: when the server has none (or fails
+/// to make them) the list stays empty.
+pub fn do_suggest_followups(question: &str, reply: &mut ChatReply) {
+    if reply.error.is_some() {
+        return;
+    }
+    reply.followups = with_connection(|client| {
+        global_runtime().block_on(client.suggest_followups(
+            question,
+            &reply.answer,
+            &reply.sources,
+            FOLLOWUP_COUNT,
+        ))
+    })
+    .unwrap_or_default();
+}
 
-    for event in events {
-        match event {
-            md_qa_client::StreamEvent::StreamStart => {}
-            md_qa_client::StreamEvent::StreamChunk(chunk) => answer.push_str(&chunk),
-            md_qa_client::StreamEvent::StreamEnd(srcs) => sources = srcs,
-            md_qa_client::StreamEvent::Error(msg) => error = Some(msg),
+/// Ask `question` in place of turn `turn_id`, forking a new branch next to it.
+pub fn do_edit_query(
+    turn_id: TurnId,
+    question: &str,
+    index: Option<&str>,
+    mut on_chunk: impl FnMut(&ChatChunk),
+) -> Result<ChatReply, String> {
+    let parent = {
+        let history = HISTORY.lock().map_err(|e| e.to_string())?;
+        history
+            .get(turn_id)
+            .ok_or_else(|| HistoryError::UnknownTurn(turn_id).to_string())?
+            .parent
+    };
+    let msg = with_recent_turns(QueryMessage::new(question, index), parent)?;
+    let _running = QUERIES.read().map_err(|e| e.to_string())?;
+    let (question, mut reply) = run_query(&msg, &mut on_chunk)?;
+    if is_recorded(&reply) {
+        let mut history = HISTORY.lock().map_err(|e| e.to_string())?;
+        let id = history
+            .fork(
+                turn_id,
+                &question,
+                &reply.answer,
+                source_paths(&reply.sources),
+            )
+            .map_err(|e| e.to_string())?;
+        finish_turn(&mut history, id, &mut reply)?;
+    }
+    Ok(reply)
+}
+
+/// Re-ask the question of turn `turn_id`, forking a new branch next to it.
+/// The turn records the question as sent, so a `pre_query` hook gets the
+/// question it rewrote before.
+pub fn do_regenerate(
+    turn_id: TurnId,
+    index: Option<&str>,
+    on_chunk: impl FnMut(&ChatChunk),
+) -> Result<ChatReply, String> {
+    let question = {
+        let history = HISTORY.lock().map_err(|e| e.to_string())?;
+        history
+            .get(turn_id)
+            .map(|t| t.question.clone())
+            .ok_or_else(|| HistoryError::UnknownTurn(turn_id).to_string())?
+    };
+    do_edit_query(turn_id, &question, index, on_chunk)
+}
+
+// ── Query log (gaps report) ─────────────────────────────────────────────
+
+static QUERY_LOG: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Set where answered queries and ratings are logged (`None` disables logging).
+/// The app sets this to `queries.jsonl` next to the config file on startup.
+/// Nothing is logged in safe mode.
+pub fn set_query_log_path(path: Option<PathBuf>) {
+    if let Ok(mut log) = QUERY_LOG.lock() {
+        *log = path;
+    }
+}
+
+fn query_log_path() -> Option<PathBuf> {
+    if safe_mode().is_some() {
+        return None;
+    }
+    QUERY_LOG.lock().ok().and_then(|log| log.clone())
+}
+
+/// Record a finished query in the query log. Logging is best-effort.
+fn log_query(msg: &QueryMessage<'_>, collector: &StreamCollector) {
+    if let Some(path) = query_log_path() {
+        let entry = LogEntry::Query {
+            timestamp: querylog::now(),
+            question: msg.question.to_string(),
+            index: msg.index.map(str::to_string),
+            sources: collector.sources.len(),
+            error: collector.error.clone(),
+            usage: collector.stats.usage.clone(),
+            answer: collector.error.is_none().then(|| collector.answer.clone()),
+            source_paths: collector.sources.iter().map(|s| s.path.clone()).collect(),
+        };
+        let _ = querylog::append(&path, &entry);
+    }
+}
+
+/// Record whether the answer of turn `turn_id` was helpful.
+pub fn do_rate_answer(turn_id: TurnId, helpful: bool) -> Result<(), String> {
+    let question = {
+        let history = HISTORY.lock().map_err(|e| e.to_string())?;
+        history
+            .get(turn_id)
+            .map(|t| t.question.clone())
+            .ok_or_else(|| HistoryError::UnknownTurn(turn_id).to_string())?
+    };
+    let path = query_log_path().ok_or("Query log is disabled")?;
+    let entry = LogEntry::Feedback {
+        timestamp: querylog::now(),
+        question,
+        helpful,
+    };
+    querylog::append(&path, &entry).map_err(|e| e.to_string())
+}
+
+/// Logged questions that got no sources, a server error, or an unhelpful rating.
+pub fn do_report_gaps() -> Result<Vec<Gap>, String> {
+    match query_log_path() {
+        Some(path) => {
+            let entries = querylog::load(&path).map_err(|e| e.to_string())?;
+            Ok(querylog::gaps(&entries))
         }
+        None => Ok(Vec::new()),
     }
+}
 
-    Ok(ChatReply {
-        answer,
-        sources,
-        error,
-    })
+/// Tokens used by the conversation (every branch) and their estimated cost,
+/// priced with the `prices` table of the config file.
+pub fn do_conversation_cost() -> Result<UsageTotals, String> {
+    let cfg = current_config();
+    let history = HISTORY.lock().map_err(|e| e.to_string())?;
+    Ok(history.usage(&cfg.prices, cfg.api.llm_model.as_deref()))
+}
+
+/// List all branches of the conversation history.
+pub fn do_list_branches() -> Result<Vec<Branch>, String> {
+    let history = HISTORY.lock().map_err(|e| e.to_string())?;
+    Ok(history.branches())
+}
+
+/// Make the branch ending at `leaf` active. Returns the turns on the new active path.
+pub fn do_switch_branch(leaf: TurnId) -> Result<Vec<Turn>, String> {
+    let mut history = HISTORY.lock().map_err(|e| e.to_string())?;
+    history.switch_to(leaf).map_err(|e| e.to_string())?;
+    Ok(history.active_path().into_iter().cloned().collect())
+}
+
+/// Export the active branch of the conversation as Markdown.
+pub fn do_export_conversation() -> Result<String, String> {
+    let history = HISTORY.lock().map_err(|e| e.to_string())?;
+    Ok(history.to_markdown())
+}
+
+/// Export the active branch of the conversation as `format` (`"issue"` or
+/// `"snippet"`) with the templates of the config's `export` section, e.g. to
+/// copy a pre-filled issue body.
+pub fn do_export_as(format: &str) -> Result<String, String> {
+    let format: ExportFormat = format.parse()?;
+    let cfg = current_config();
+    let history = HISTORY.lock().map_err(|e| e.to_string())?;
+    if history.is_empty() {
+        return Err("Nothing to export yet".into());
+    }
+    format
+        .render(&cfg.export, &history)
+        .map_err(|e| e.to_string())
+}
+
+/// Drop all conversation history (e.g. when the user starts a new chat).
+pub fn do_clear_history() {
+    if let Ok(mut history) = HISTORY.lock() {
+        *history = Conversation::new();
+    }
+    if let Ok(mut turns) = INTERRUPTED_TURNS.lock() {
+        turns.clear();
+    }
+}
+
+// ── Drafts (unsent chat input) ──────────────────────────────────────────
+
+static DRAFTS: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Quiet time after the last `do_queue_draft_save` of a session before its
+/// draft is written, so saving on every keystroke writes once.
+pub const DRAFT_SAVE_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Drafts waiting out `DRAFT_SAVE_DEBOUNCE`, by session, with their save
+/// generation.
+static PENDING_DRAFTS: Mutex<BTreeMap<String, (u64, String)>> = Mutex::new(BTreeMap::new());
+
+/// Held while the drafts file is read or written, so an update never
+/// overwrites another.
+static DRAFT_WRITES: Mutex<()> = Mutex::new(());
+
+/// Set where unsent chat input is kept (`None` disables drafts).
+/// The app sets this to `drafts.json` next to the config file on startup.
+/// The drafts file is neither read nor written in safe mode.
+pub fn set_drafts_path(path: Option<PathBuf>) {
+    if let Ok(mut drafts) = DRAFTS.lock() {
+        *drafts = path;
+    }
+}
+
+fn drafts_path() -> Option<PathBuf> {
+    if safe_mode().is_some() {
+        return None;
+    }
+    DRAFTS.lock().ok().and_then(|drafts| drafts.clone())
+}
+
+/// Keep `text` as the unsent input of `session`, written after
+/// `DRAFT_SAVE_DEBOUNCE` without a newer edit of the same session. Blank
+/// text drops the draft, e.g. once the question was sent.
+pub fn do_queue_draft_save(session: &str, text: &str) {
+    let generation = SAVE_GENERATION.fetch_add(1, Ordering::SeqCst);
+    PENDING_DRAFTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(session.to_string(), (generation, text.to_string()));
+    let session = session.to_string();
+    global_runtime().spawn(async move {
+        tokio::time::sleep(DRAFT_SAVE_DEBOUNCE).await;
+        let _ = tokio::task::spawn_blocking(move || write_queued_draft(&session, generation)).await;
+    });
+}
+
+/// Write the draft queued for `session` if it is still `generation`.
+/// Writing is best-effort; the draft stays in the file as last written.
+fn write_queued_draft(session: &str, generation: u64) {
+    let _writing = DRAFT_WRITES.lock().unwrap_or_else(PoisonError::into_inner);
+    let text = {
+        let mut pending = PENDING_DRAFTS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match pending.get(session) {
+            Some((queued, _)) if *queued == generation => pending.remove(session),
+            _ => None,
+        }
+    };
+    if let (Some((_, text)), Some(path)) = (text, drafts_path()) {
+        let _ = drafts::save(&path, session, &text);
+    }
+}
+
+/// Unsent drafts of all sessions, most recently edited first, including
+/// edits not written yet.
+pub fn do_list_drafts() -> Result<Vec<Draft>, String> {
+    let _reading = DRAFT_WRITES.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut saved = match drafts_path() {
+        Some(path) => drafts::load(&path).map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    let pending = PENDING_DRAFTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    for (session, (_, text)) in pending {
+        saved.retain(|d| d.session != session);
+        if !text.trim().is_empty() {
+            let updated = querylog::now();
+            saved.insert(
+                0,
+                Draft {
+                    session,
+                    text,
+                    updated,
+                },
+            );
+        }
+    }
+    Ok(saved)
+}
+
+/// The unsent draft of `session`, to restore into its input on launch.
+pub fn do_load_draft(session: &str) -> Result<Option<Draft>, String> {
+    Ok(do_list_drafts()?.into_iter().find(|d| d.session == session))
 }
 
 // ── Tauri command wrappers ──────────────────────────────────────────────
+//
+// Each wrapper goes through `recorder::command`, and events through `emit`,
+// so a session recorded with `MD_QA_RECORD` has everything `replay` needs.
+
+/// Emit `event` to the frontend, recording it when a session is recorded.
+fn emit<T: Serialize + Clone>(app: &AppHandle, event: &str, payload: T) {
+    recorder::event(event, &payload);
+    let _ = app.emit(event, payload);
+}
 
 #[tauri::command]
 pub fn get_config_path() -> Result<String, String> {
-    let p = resolve_config_path(None)?;
-    p.to_str()
-        .map(|s| s.to_string())
-        .ok_or_else(|| "Config path is not valid UTF-8".into())
+    let path = resolve_config_path(None).and_then(|p| {
+        p.to_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| "Config path is not valid UTF-8".into())
+    });
+    recorder::command("get_config_path", serde_json::json!({}), path)
+}
+
+/// Safe mode the GUI runs in, so it can say so and skip restoring drafts.
+#[tauri::command]
+pub fn get_safe_mode() -> Option<SafeMode> {
+    recorder::command("get_safe_mode", serde_json::json!({}), Ok(safe_mode())).unwrap_or_default()
 }
 
 #[tauri::command]
 pub fn load_config(path: String) -> Result<ConfigForm, String> {
-    do_load_config(&path)
+    let args = serde_json::json!({ "path": path });
+    recorder::command("load_config", args, do_load_config(&path))
 }
 
+/// Queue a config save (see `do_queue_config_save`), emit `CONFIG_SAVED_EVENT`
+/// once written and return its outcome.
 #[tauri::command]
-pub fn save_config(path: String, form: ConfigForm) -> Result<(), String> {
-    do_save_config(&path, &form)
+pub async fn save_config(app: AppHandle, path: String, form: ConfigForm) -> Result<(), String> {
+    let args = serde_json::json!({ "path": path, "form": form });
+    let saved = do_queue_config_save(&path, form, move |saved| {
+        emit(&app, CONFIG_SAVED_EVENT, saved)
+    });
+    let result = saved
+        .await
+        .unwrap_or_else(|_| Err("Config save was dropped".into()));
+    recorder::command("save_config", args, result)
 }
 
 #[tauri::command]
-pub fn connect_server(url: String) -> Result<ConnectionStatus, String> {
-    do_connect(&url)
+pub fn template_variables(template: String) -> Result<Vec<String>, String> {
+    let args = serde_json::json!({ "template": template });
+    recorder::command("template_variables", args, do_template_variables(&template))
 }
 
 #[tauri::command]
-pub fn disconnect_server() -> Result<(), String> {
-    do_disconnect();
-    Ok(())
+pub fn create_config_from_template(
+    template: String,
+    vars: std::collections::HashMap<String, String>,
+    path: String,
+    overwrite: Option<bool>,
+) -> Result<ConfigForm, String> {
+    let args = serde_json::json!({
+        "template": template,
+        "vars": vars,
+        "path": path,
+        "overwrite": overwrite,
+    });
+    let vars: Vec<(String, String)> = vars.into_iter().collect();
+    let form = do_create_config_from_template(&template, &vars, &path, overwrite.unwrap_or(false));
+    recorder::command("create_config_from_template", args, form)
 }
 
 #[tauri::command]
-pub fn send_query(question: String, index: Option<String>) -> Result<ChatReply, String> {
-    do_send_query(&question, index.as_deref())
+pub fn connect_server(app: AppHandle, url: String) -> Result<ConnectionStatus, String> {
+    let args = serde_json::json!({ "url": url });
+    let status = do_connect(&url).and_then(|status| {
+        if status.state == "connected" {
+            let watcher = app.clone();
+            do_watch_connection(move |status| emit(&watcher, CONNECTION_STATUS_EVENT, status))?;
+            do_watch_index_progress(move |progress| emit(&app, INDEX_PROGRESS_EVENT, progress))?;
+        }
+        Ok(status)
+    });
+    recorder::command("connect_server", args, status)
 }
 
+/// Run the startup self-check, emit it as `STARTUP_CHECK_EVENT` and return it.
+/// A reachable server stays connected and is watched like `connect_server`.
 #[tauri::command]
-pub fn connection_status() -> ConnectionStatus {
-    if is_connected() {
-        ConnectionStatus {
-            state: "connected".into(),
-            message: None,
+pub async fn startup_check(app: AppHandle) -> Result<Vec<CheckStep>, String> {
+    let steps = run_blocking(|| Ok(do_startup_check())).await;
+    let steps = steps.and_then(|steps| {
+        if steps.iter().any(|s| s.step == "server" && s.state == "ok") {
+            let watcher = app.clone();
+            do_watch_connection(move |status| emit(&watcher, CONNECTION_STATUS_EVENT, status))?;
+            let watcher = app.clone();
+            do_watch_index_progress(move |progress| {
+                emit(&watcher, INDEX_PROGRESS_EVENT, progress)
+            })?;
         }
-    } else {
-        ConnectionStatus {
-            state: "disconnected".into(),
-            message: None,
+        emit(&app, STARTUP_CHECK_EVENT, steps.clone());
+        Ok(steps)
+    });
+    recorder::command("startup_check", serde_json::json!({}), steps)
+}
+
+#[tauri::command]
+pub async fn disconnect_server() -> Result<(), String> {
+    let done = run_blocking(|| {
+        do_disconnect();
+        Ok(())
+    })
+    .await;
+    recorder::command("disconnect_server", serde_json::json!({}), done)
+}
+
+#[tauri::command]
+pub async fn server_status() -> Result<StatusMessage, String> {
+    let status = run_blocking(do_server_status).await;
+    recorder::command("server_status", serde_json::json!({}), status)
+}
+
+#[tauri::command]
+pub async fn reload_index(index: Option<String>) -> Result<StatusMessage, String> {
+    let args = serde_json::json!({ "index": index });
+    let status = run_blocking(move || do_reload_index(index.as_deref())).await;
+    recorder::command("reload_index", args, status)
+}
+
+#[tauri::command]
+pub async fn index_estimate(index: Option<String>) -> Result<Option<ReloadEstimate>, String> {
+    let args = serde_json::json!({ "index": index });
+    let estimate = run_blocking(move || do_index_estimate(index.as_deref())).await;
+    recorder::command("index_estimate", args, estimate)
+}
+
+#[tauri::command]
+pub async fn index_stats(index: Option<String>) -> Result<IndexStatsMessage, String> {
+    let args = serde_json::json!({ "index": index });
+    let stats = run_blocking(move || do_index_stats(index.as_deref())).await;
+    recorder::command("index_stats", args, stats)
+}
+
+#[tauri::command]
+pub fn get_server_summary() -> Option<ServerSummary> {
+    let summary = Ok(do_server_summary());
+    recorder::command("get_server_summary", serde_json::json!({}), summary).unwrap_or_default()
+}
+
+#[tauri::command]
+pub async fn get_capabilities() -> Result<Option<CapabilitiesMessage>, String> {
+    let capabilities = run_blocking(do_server_capabilities).await;
+    recorder::command("get_capabilities", serde_json::json!({}), capabilities)
+}
+
+#[tauri::command]
+pub fn get_index_progress() -> Option<IndexProgressMessage> {
+    let progress = Ok(do_index_progress());
+    recorder::command("get_index_progress", serde_json::json!({}), progress).unwrap_or_default()
+}
+
+/// Return the latest server log lines and, with `follow`, emit each new one
+/// as `SERVER_LOG_EVENT`.
+#[tauri::command]
+pub async fn tail_server_logs(
+    app: AppHandle,
+    lines: usize,
+    follow: bool,
+) -> Result<Vec<LogLine>, String> {
+    let args = serde_json::json!({ "lines": lines, "follow": follow });
+    let recent = run_blocking(move || {
+        do_tail_server_logs(lines, follow, move |line| {
+            emit(&app, SERVER_LOG_EVENT, line)
+        })
+    })
+    .await;
+    recorder::command("tail_server_logs", args, recent)
+}
+
+#[tauri::command]
+pub fn pending_notifications() -> Vec<NotificationMessage> {
+    let notifications = Ok(do_take_notifications());
+    recorder::command(
+        "pending_notifications",
+        serde_json::json!({}),
+        notifications,
+    )
+    .unwrap_or_default()
+}
+
+#[tauri::command]
+pub async fn list_indexed_documents(
+    index: Option<String>,
+    page: Option<usize>,
+) -> Result<DocumentsMessage, String> {
+    let args = serde_json::json!({ "index": index, "page": page });
+    let documents =
+        run_blocking(move || do_list_indexed_documents(index.as_deref(), page.unwrap_or(0))).await;
+    recorder::command("list_indexed_documents", args, documents)
+}
+
+#[tauri::command]
+pub async fn list_indexes() -> Result<Vec<IndexInfo>, String> {
+    let indexes = run_blocking(do_list_indexes).await;
+    recorder::command("list_indexes", serde_json::json!({}), indexes)
+}
+
+#[tauri::command]
+pub async fn create_index(
+    name: String,
+    directories: Vec<String>,
+    chunking: Option<ChunkingSection>,
+    embedding_model: Option<String>,
+) -> Result<Vec<IndexInfo>, String> {
+    let args = serde_json::json!({
+        "name": name,
+        "directories": directories,
+        "chunking": chunking,
+        "embedding_model": embedding_model,
+    });
+    let indexes = run_blocking(move || {
+        do_create_index(
+            &name,
+            &directories,
+            chunking.as_ref(),
+            embedding_model.as_deref(),
+        )
+    })
+    .await;
+    recorder::command("create_index", args, indexes)
+}
+
+#[tauri::command]
+pub async fn delete_index(name: String) -> Result<Vec<IndexInfo>, String> {
+    let args = serde_json::json!({ "name": name });
+    let indexes = run_blocking(move || do_delete_index(&name)).await;
+    recorder::command("delete_index", args, indexes)
+}
+
+#[tauri::command]
+pub async fn get_document_chunks(path: String) -> Result<ChunksMessage, String> {
+    let args = serde_json::json!({ "path": path });
+    let chunks = run_blocking(move || do_get_document_chunks(&path)).await;
+    recorder::command("get_document_chunks", args, chunks)
+}
+
+#[tauri::command]
+pub async fn get_document_links(path: String) -> Result<LinksMessage, String> {
+    let args = serde_json::json!({ "path": path });
+    let links = run_blocking(move || do_get_document_links(&path)).await;
+    recorder::command("get_document_links", args, links)
+}
+
+#[tauri::command]
+pub async fn send_query(
+    app: AppHandle,
+    question: String,
+    index: Option<String>,
+    verify: Option<bool>,
+    options: Option<QueryOptions>,
+    followups: Option<bool>,
+    filters: Option<QueryFilters>,
+) -> Result<ChatReply, String> {
+    let args = serde_json::json!({
+        "question": question,
+        "index": index,
+        "verify": verify,
+        "options": options,
+        "followups": followups,
+        "filters": filters,
+    });
+    let reply = run_blocking(move || {
+        let msg = QueryMessage::new(&question, index.as_deref())
+            .with_verify(verify.unwrap_or(false))
+            .with_options(options.unwrap_or_default())
+            .with_filters(filters.unwrap_or_default());
+        let mut reply = do_send_query_message(&msg, |chunk| emit(&app, CHAT_CHUNK_EVENT, chunk))?;
+        if followups.unwrap_or(false) {
+            do_suggest_followups(&question, &mut reply);
         }
-    }
+        Ok(reply)
+    })
+    .await;
+    recorder::command("send_query", args, reply)
+}
+
+#[tauri::command]
+pub fn stop_query() {
+    do_stop_query();
+    let _ = recorder::command("stop_query", serde_json::json!({}), Ok(()));
+}
+
+#[tauri::command]
+pub async fn edit_query(
+    app: AppHandle,
+    turn_id: TurnId,
+    question: String,
+    index: Option<String>,
+) -> Result<ChatReply, String> {
+    let args = serde_json::json!({ "turn_id": turn_id, "question": question, "index": index });
+    let reply = run_blocking(move || {
+        do_edit_query(turn_id, &question, index.as_deref(), |chunk| {
+            emit(&app, CHAT_CHUNK_EVENT, chunk)
+        })
+    })
+    .await;
+    recorder::command("edit_query", args, reply)
+}
+
+#[tauri::command]
+pub async fn regenerate(
+    app: AppHandle,
+    turn_id: TurnId,
+    index: Option<String>,
+) -> Result<ChatReply, String> {
+    let args = serde_json::json!({ "turn_id": turn_id, "index": index });
+    let reply = run_blocking(move || {
+        do_regenerate(turn_id, index.as_deref(), |chunk| {
+            emit(&app, CHAT_CHUNK_EVENT, chunk)
+        })
+    })
+    .await;
+    recorder::command("regenerate", args, reply)
+}
+
+#[tauri::command]
+pub async fn compare_query(
+    app: AppHandle,
+    question: String,
+    index: Option<String>,
+    left: QueryOptions,
+    right: QueryOptions,
+) -> Result<ComparisonReply, String> {
+    let args = serde_json::json!({
+        "question": question,
+        "index": index,
+        "left": left,
+        "right": right,
+    });
+    let reply = run_blocking(move || {
+        do_compare_query(&question, index.as_deref(), left, right, |chunk| {
+            emit(&app, COMPARE_CHUNK_EVENT, chunk)
+        })
+    })
+    .await;
+    recorder::command("compare_query", args, reply)
+}
+
+#[tauri::command]
+pub fn conversation_cost() -> Result<UsageTotals, String> {
+    recorder::command(
+        "conversation_cost",
+        serde_json::json!({}),
+        do_conversation_cost(),
+    )
+}
+
+#[tauri::command]
+pub fn list_branches() -> Result<Vec<Branch>, String> {
+    recorder::command("list_branches", serde_json::json!({}), do_list_branches())
+}
+
+#[tauri::command]
+pub fn switch_branch(leaf: TurnId) -> Result<Vec<Turn>, String> {
+    let args = serde_json::json!({ "leaf": leaf });
+    recorder::command("switch_branch", args, do_switch_branch(leaf))
+}
+
+#[tauri::command]
+pub fn export_conversation() -> Result<String, String> {
+    let markdown = do_export_conversation();
+    recorder::command("export_conversation", serde_json::json!({}), markdown)
+}
+
+#[tauri::command]
+pub fn export_as(format: String) -> Result<String, String> {
+    let args = serde_json::json!({ "format": format });
+    recorder::command("export_as", args, do_export_as(&format))
+}
+
+#[tauri::command]
+pub fn clear_history() {
+    do_clear_history();
+    let _ = recorder::command("clear_history", serde_json::json!({}), Ok(()));
+}
+
+#[tauri::command]
+pub fn rate_answer(turn_id: TurnId, helpful: bool) -> Result<(), String> {
+    let args = serde_json::json!({ "turn_id": turn_id, "helpful": helpful });
+    recorder::command("rate_answer", args, do_rate_answer(turn_id, helpful))
+}
+
+#[tauri::command]
+pub fn report_gaps() -> Result<Vec<Gap>, String> {
+    recorder::command("report_gaps", serde_json::json!({}), do_report_gaps())
+}
+
+#[tauri::command]
+pub async fn connection_status() -> Result<ConnectionStatus, String> {
+    let status = run_blocking(|| Ok(do_connection_status(LIVENESS_TIMEOUT))).await;
+    recorder::command("connection_status", serde_json::json!({}), status)
+}
+
+/// Keep the unsent input of `session` (see `do_queue_draft_save`).
+#[tauri::command]
+pub fn save_draft(session: String, text: String) {
+    do_queue_draft_save(&session, &text);
+    let args = serde_json::json!({ "session": session, "text": text });
+    let _ = recorder::command("save_draft", args, Ok(()));
+}
+
+#[tauri::command]
+pub fn load_draft(session: String) -> Result<Option<Draft>, String> {
+    let args = serde_json::json!({ "session": session });
+    recorder::command("load_draft", args, do_load_draft(&session))
+}
+
+#[tauri::command]
+pub fn list_drafts() -> Result<Vec<Draft>, String> {
+    recorder::command("list_drafts", serde_json::json!({}), do_list_drafts())
 }
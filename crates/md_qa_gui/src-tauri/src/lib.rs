@@ -1,17 +1,83 @@
 //! Tauri application library. Config UI and chat panel are added in later tasks.
 
 pub mod commands;
+pub mod recorder;
+pub mod replay;
+
+/// Start the GUI. With `safe_mode` (`md_qa_gui --safe-mode`) the config file
+/// is moved aside and the query log and drafts are left alone, so a broken
+/// config or history file cannot keep the app from opening (see
+/// `commands::do_enter_safe_mode`).
+pub fn run(safe_mode: bool) {
+    let config_path = commands::resolve_config_path(None).ok();
+    if safe_mode {
+        match commands::do_enter_safe_mode(config_path.as_deref()) {
+            Ok(commands::SafeMode {
+                config_backup: Some(backup),
+            }) => eprintln!("Safe mode: config moved to {}", backup),
+            Ok(_) => {}
+            Err(e) => eprintln!("Safe mode: {}", e),
+        }
+    } else {
+        let query_log = config_path
+            .as_deref()
+            .map(md_qa_client::querylog::path_for_config);
+        commands::set_query_log_path(query_log);
+        commands::set_drafts_path(
+            config_path
+                .as_deref()
+                .map(md_qa_client::drafts::path_for_config),
+        );
+    }
+    if let Some(path) = std::env::var_os(recorder::RECORD_ENV) {
+        if let Err(e) = recorder::start(std::path::Path::new(&path)) {
+            eprintln!("Not recording the session: {}", e);
+        }
+    }
 
-pub fn run() {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             commands::get_config_path,
+            commands::get_safe_mode,
             commands::load_config,
             commands::save_config,
+            commands::template_variables,
+            commands::create_config_from_template,
+            commands::startup_check,
             commands::connect_server,
             commands::disconnect_server,
             commands::connection_status,
+            commands::server_status,
+            commands::reload_index,
+            commands::index_estimate,
+            commands::index_stats,
+            commands::get_server_summary,
+            commands::get_capabilities,
+            commands::get_index_progress,
+            commands::tail_server_logs,
+            commands::pending_notifications,
+            commands::list_indexed_documents,
+            commands::list_indexes,
+            commands::create_index,
+            commands::delete_index,
+            commands::get_document_chunks,
+            commands::get_document_links,
             commands::send_query,
+            commands::stop_query,
+            commands::edit_query,
+            commands::regenerate,
+            commands::compare_query,
+            commands::conversation_cost,
+            commands::list_branches,
+            commands::switch_branch,
+            commands::export_conversation,
+            commands::export_as,
+            commands::clear_history,
+            commands::rate_answer,
+            commands::report_gaps,
+            commands::save_draft,
+            commands::load_draft,
+            commands::list_drafts,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -2,10 +2,51 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `md_qa_gui --replay <recording>` replays a session recorded with
+    // MD_QA_RECORD instead of opening the window; `--safe-mode` opens it
+    // with default settings when the config keeps it from starting.
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, flag, path] = &args[..] {
+        if flag == "--replay" {
+            std::process::exit(replay(path));
+        }
+    }
+    let safe_mode = args.iter().skip(1).any(|arg| arg == "--safe-mode");
+
     #[cfg(target_os = "linux")]
     sanitize_gtk_environment();
 
-    md_qa_gui_lib::run();
+    md_qa_gui_lib::run(safe_mode);
+}
+
+/// Replay the recording at `path` and print what differed. Exit code 1 if
+/// anything did, 2 if it could not be replayed.
+fn replay(path: &str) -> i32 {
+    use md_qa_gui_lib::{recorder, replay};
+
+    let report = recorder::load(std::path::Path::new(path)).and_then(|e| replay::replay(&e));
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 2;
+        }
+    };
+    println!(
+        "Replayed {} commands, {} differed",
+        report.replayed,
+        report.mismatches.len()
+    );
+    if !report.skipped.is_empty() {
+        println!("Not replayed: {}", report.skipped.join(", "));
+    }
+    for mismatch in &report.mismatches {
+        println!(
+            "\n#{} {}\n  recorded: {}\n  replayed: {}",
+            mismatch.entry, mismatch.command, mismatch.recorded, mismatch.replayed
+        );
+    }
+    i32::from(!report.mismatches.is_empty())
 }
 
 /// Strip GTK modules that trigger the Gdk-CRITICAL assertion
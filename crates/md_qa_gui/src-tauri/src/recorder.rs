@@ -0,0 +1,152 @@
+//! Opt-in session recorder for bug reports. With `MD_QA_RECORD=<file>` set,
+//! every command the frontend invokes (arguments and result), every event
+//! emitted to it and every protocol message exchanged with the server is
+//! appended to the file as one JSON line, with milliseconds since the
+//! recording started. `replay` feeds a recording back through the commands.
+
+use md_qa_client::{FrameDirection, FrameTap};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Environment variable naming the file to record the session to.
+pub const RECORD_ENV: &str = "MD_QA_RECORD";
+
+/// One recorded step of a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Milliseconds since the recording started.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub kind: EntryKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EntryKind {
+    /// A command invoked by the frontend, with its arguments by name and
+    /// its result: `{"ok": ...}` or `{"err": "..."}`.
+    Command {
+        name: String,
+        args: serde_json::Value,
+        result: serde_json::Value,
+    },
+    /// An event emitted to the frontend.
+    Event {
+        name: String,
+        payload: serde_json::Value,
+    },
+    /// A protocol message sent to or received from the server.
+    Frame {
+        direction: FrameDirection,
+        text: String,
+    },
+}
+
+struct Recorder {
+    file: File,
+    started: Instant,
+}
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+/// Start recording to `path`, replacing the file.
+pub fn start(path: &Path) -> Result<(), String> {
+    let file =
+        File::create(path).map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+    let recorder = Recorder {
+        file,
+        started: Instant::now(),
+    };
+    *RECORDER.lock().map_err(|e| e.to_string())? = Some(recorder);
+    Ok(())
+}
+
+/// Stop recording. What was recorded stays in the file.
+pub fn stop() {
+    if let Ok(mut recorder) = RECORDER.lock() {
+        *recorder = None;
+    }
+}
+
+pub fn is_recording() -> bool {
+    RECORDER.lock().is_ok_and(|recorder| recorder.is_some())
+}
+
+fn record(kind: EntryKind) {
+    let Ok(mut guard) = RECORDER.lock() else {
+        return;
+    };
+    let Some(recorder) = guard.as_mut() else {
+        return;
+    };
+    let entry = Entry {
+        at_ms: recorder.started.elapsed().as_millis() as u64,
+        kind,
+    };
+    if let Ok(line) = serde_json::to_string(&entry) {
+        // Written line by line, so a crash keeps everything before it.
+        let _ = writeln!(recorder.file, "{}", line);
+    }
+}
+
+/// Record command `name`, called with `args`, and pass its result through.
+pub fn command<T: Serialize>(
+    name: &str,
+    args: serde_json::Value,
+    result: Result<T, String>,
+) -> Result<T, String> {
+    if is_recording() {
+        let recorded = match &result {
+            Ok(value) => serde_json::json!({ "ok": value }),
+            Err(e) => serde_json::json!({ "err": e }),
+        };
+        record(EntryKind::Command {
+            name: name.to_string(),
+            args,
+            result: recorded,
+        });
+    }
+    result
+}
+
+/// Record event `name` emitted with `payload`.
+pub fn event<T: Serialize>(name: &str, payload: &T) {
+    if is_recording() {
+        record(EntryKind::Event {
+            name: name.to_string(),
+            payload: serde_json::to_value(payload).unwrap_or_default(),
+        });
+    }
+}
+
+/// Tap recording the protocol messages of a connection, while recording.
+pub fn frame_tap() -> Option<FrameTap> {
+    is_recording().then(|| {
+        FrameTap::new(|direction, text| {
+            record(EntryKind::Frame {
+                direction,
+                text: text.to_string(),
+            })
+        })
+    })
+}
+
+/// Read a recording written by `start`.
+pub fn load(path: &Path) -> Result<Vec<Entry>, String> {
+    let file = File::open(path).map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+    let mut entries = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
@@ -0,0 +1,363 @@
+//! Replay of a session recorded by `recorder`, to reproduce a bug report
+//! without the reporter's server or documents. A stand-in server answers
+//! each request with the messages the real one sent back at the time, and
+//! the recorded commands run again in order through the same `do_*`
+//! functions the GUI calls. Results that differ from the recording are
+//! reported; events and commands that depend on timing or local files are
+//! not replayed.
+
+use crate::commands::{
+    do_clear_history, do_compare_query, do_connect, do_connection_status, do_conversation_cost,
+    do_create_index, do_delete_index, do_disconnect, do_edit_query, do_export_as,
+    do_export_conversation, do_get_document_chunks, do_get_document_links, do_index_estimate,
+    do_index_stats, do_list_branches, do_list_indexed_documents, do_list_indexes, do_rate_answer,
+    do_regenerate, do_reload_index, do_send_query_message, do_server_capabilities,
+    do_server_status, do_suggest_followups, do_switch_branch, do_tail_server_logs,
+    LIVENESS_TIMEOUT,
+};
+use crate::recorder::{Entry, EntryKind};
+use md_qa_client::config::ChunkingSection;
+use md_qa_client::messages::{QueryFilters, QueryMessage, QueryOptions};
+use md_qa_client::FrameDirection;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Outcome of `replay`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayReport {
+    /// Commands run again.
+    pub replayed: usize,
+    /// Commands left out, by name.
+    pub skipped: Vec<String>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// A replayed command whose result differs from the recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mismatch {
+    /// Position of the command among the recording's entries.
+    pub entry: usize,
+    pub command: String,
+    pub recorded: serde_json::Value,
+    pub replayed: serde_json::Value,
+}
+
+/// A request the client sent and the messages the server sent after it,
+/// up to the next request.
+struct Exchange {
+    request: Option<serde_json::Value>,
+    replies: Vec<String>,
+}
+
+/// Group the recorded protocol messages into exchanges. Messages received
+/// before the first request are sent on connect.
+fn exchanges(entries: &[Entry]) -> VecDeque<Exchange> {
+    let mut exchanges = VecDeque::new();
+    for entry in entries {
+        let EntryKind::Frame { direction, text } = &entry.kind else {
+            continue;
+        };
+        match direction {
+            FrameDirection::Sent => exchanges.push_back(Exchange {
+                request: serde_json::from_str(text).ok(),
+                replies: Vec::new(),
+            }),
+            FrameDirection::Received => {
+                if exchanges.is_empty() {
+                    exchanges.push_back(Exchange {
+                        request: None,
+                        replies: Vec::new(),
+                    });
+                }
+                if let Some(last) = exchanges.back_mut() {
+                    last.replies.push(text.clone());
+                }
+            }
+        }
+    }
+    exchanges
+}
+
+/// The replies recorded for `request`: those of the first pending exchange
+/// of the same type (background status checks may come at other times
+/// than in the recording). Query ids are per process, so the recorded id
+/// is replaced by the one of `request`.
+fn replies_to(pending: &Mutex<VecDeque<Exchange>>, request: &str) -> Vec<String> {
+    let Ok(request) = serde_json::from_str::<serde_json::Value>(request) else {
+        return Vec::new();
+    };
+    let Ok(mut pending) = pending.lock() else {
+        return Vec::new();
+    };
+    let Some(position) = pending.iter().position(|exchange| {
+        exchange
+            .request
+            .as_ref()
+            .is_some_and(|recorded| recorded["type"] == request["type"])
+    }) else {
+        return Vec::new();
+    };
+    let Some(exchange) = pending.remove(position) else {
+        return Vec::new();
+    };
+    let recorded_id = exchange.request.as_ref().and_then(|r| r.get("id")).cloned();
+    exchange
+        .replies
+        .into_iter()
+        .map(|reply| match (&recorded_id, request.get("id")) {
+            (Some(old), Some(new)) => {
+                let mut value: serde_json::Value = match serde_json::from_str(&reply) {
+                    Ok(value) => value,
+                    Err(_) => return reply,
+                };
+                if value.get("id") == Some(old) {
+                    value["id"] = new.clone();
+                }
+                value.to_string()
+            }
+            _ => reply,
+        })
+        .collect()
+}
+
+/// Stand-in server playing back `exchanges`, across reconnects, until
+/// dropped.
+struct ReplayServer {
+    url: String,
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl ReplayServer {
+    fn start(exchanges: VecDeque<Exchange>) -> Result<Self, String> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string())?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let url = format!("ws://{}", listener.local_addr().map_err(|e| e.to_string())?);
+        let (shutdown, mut stopped) = tokio::sync::oneshot::channel();
+        let pending = Arc::new(Mutex::new(exchanges));
+        let thread = std::thread::spawn(move || {
+            let Ok(rt) = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            else {
+                return;
+            };
+            rt.block_on(async move {
+                let Ok(listener) = tokio::net::TcpListener::from_std(listener) else {
+                    return;
+                };
+                loop {
+                    let tcp = tokio::select! {
+                        accepted = listener.accept() => match accepted {
+                            Ok((tcp, _)) => tcp,
+                            Err(_) => return,
+                        },
+                        _ = &mut stopped => return,
+                    };
+                    tokio::spawn(serve(tcp, pending.clone()));
+                }
+            });
+        });
+        Ok(Self {
+            url,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for ReplayServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+async fn serve(tcp: tokio::net::TcpStream, pending: Arc<Mutex<VecDeque<Exchange>>>) {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let Ok(mut ws) = tokio_tungstenite::accept_async(tcp).await else {
+        return;
+    };
+    let on_connect = pending
+        .lock()
+        .ok()
+        .and_then(|mut pending| match pending.front() {
+            Some(exchange) if exchange.request.is_none() => pending.pop_front(),
+            _ => None,
+        });
+    for reply in on_connect
+        .map(|exchange| exchange.replies)
+        .unwrap_or_default()
+    {
+        if ws.send(Message::Text(reply)).await.is_err() {
+            return;
+        }
+    }
+    while let Some(Ok(message)) = ws.next().await {
+        let Message::Text(request) = message else {
+            continue;
+        };
+        for reply in replies_to(&pending, &request) {
+            if ws.send(Message::Text(reply)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Argument `name` of a recorded command; missing means `null`.
+fn arg<T: DeserializeOwned>(args: &serde_json::Value, name: &str) -> Result<T, String> {
+    let value = args.get(name).cloned().unwrap_or_default();
+    serde_json::from_value(value).map_err(|e| format!("argument `{}`: {}", name, e))
+}
+
+fn to_json<T: Serialize>(result: Result<T, String>) -> serde_json::Value {
+    match result {
+        Ok(value) => serde_json::json!({ "ok": value }),
+        Err(e) => serde_json::json!({ "err": e }),
+    }
+}
+
+/// Run recorded command `name` again, connecting to `url` instead of the
+/// recorded server. `None` when the command is not replayed.
+fn run(name: &str, args: &serde_json::Value, url: &str) -> Option<serde_json::Value> {
+    let result = match name {
+        "connect_server" => to_json(do_connect(url)),
+        "disconnect_server" => {
+            do_disconnect();
+            to_json(Ok(()))
+        }
+        "connection_status" => to_json(Ok(do_connection_status(LIVENESS_TIMEOUT))),
+        "server_status" => to_json(do_server_status()),
+        "reload_index" => to_json(
+            arg::<Option<String>>(args, "index")
+                .and_then(|index| do_reload_index(index.as_deref())),
+        ),
+        "index_estimate" => to_json(
+            arg::<Option<String>>(args, "index")
+                .and_then(|index| do_index_estimate(index.as_deref())),
+        ),
+        "index_stats" => to_json(
+            arg::<Option<String>>(args, "index").and_then(|index| do_index_stats(index.as_deref())),
+        ),
+        "get_capabilities" => to_json(do_server_capabilities()),
+        "tail_server_logs" => to_json((|| {
+            do_tail_server_logs(arg(args, "lines")?, arg(args, "follow")?, |_| {})
+        })()),
+        "list_indexed_documents" => to_json((|| {
+            let index: Option<String> = arg(args, "index")?;
+            let page: Option<usize> = arg(args, "page")?;
+            do_list_indexed_documents(index.as_deref(), page.unwrap_or(0))
+        })()),
+        "list_indexes" => to_json(do_list_indexes()),
+        "create_index" => to_json((|| {
+            let name: String = arg(args, "name")?;
+            let directories: Vec<String> = arg(args, "directories")?;
+            let chunking: Option<ChunkingSection> = arg(args, "chunking")?;
+            let embedding_model: Option<String> = arg(args, "embedding_model")?;
+            do_create_index(
+                &name,
+                &directories,
+                chunking.as_ref(),
+                embedding_model.as_deref(),
+            )
+        })()),
+        "delete_index" => {
+            to_json(arg::<String>(args, "name").and_then(|name| do_delete_index(&name)))
+        }
+        "get_document_chunks" => {
+            to_json(arg::<String>(args, "path").and_then(|path| do_get_document_chunks(&path)))
+        }
+        "get_document_links" => {
+            to_json(arg::<String>(args, "path").and_then(|path| do_get_document_links(&path)))
+        }
+        "send_query" => to_json((|| {
+            let question: String = arg(args, "question")?;
+            let index: Option<String> = arg(args, "index")?;
+            let verify: Option<bool> = arg(args, "verify")?;
+            let options: Option<QueryOptions> = arg(args, "options")?;
+            let followups: Option<bool> = arg(args, "followups")?;
+            let filters: Option<QueryFilters> = arg(args, "filters")?;
+            let msg = QueryMessage::new(&question, index.as_deref())
+                .with_verify(verify.unwrap_or(false))
+                .with_options(options.unwrap_or_default())
+                .with_filters(filters.unwrap_or_default());
+            let mut reply = do_send_query_message(&msg, |_| {})?;
+            if followups.unwrap_or(false) {
+                do_suggest_followups(&question, &mut reply);
+            }
+            Ok(reply)
+        })()),
+        "edit_query" => to_json((|| {
+            let question: String = arg(args, "question")?;
+            let index: Option<String> = arg(args, "index")?;
+            do_edit_query(arg(args, "turn_id")?, &question, index.as_deref(), |_| {})
+        })()),
+        "regenerate" => to_json((|| {
+            let index: Option<String> = arg(args, "index")?;
+            do_regenerate(arg(args, "turn_id")?, index.as_deref(), |_| {})
+        })()),
+        "compare_query" => to_json((|| {
+            let question: String = arg(args, "question")?;
+            let index: Option<String> = arg(args, "index")?;
+            let (left, right) = (arg(args, "left")?, arg(args, "right")?);
+            do_compare_query(&question, index.as_deref(), left, right, |_| {})
+        })()),
+        "conversation_cost" => to_json(do_conversation_cost()),
+        "list_branches" => to_json(do_list_branches()),
+        "switch_branch" => to_json(arg(args, "leaf").and_then(do_switch_branch)),
+        "export_conversation" => to_json(do_export_conversation()),
+        "export_as" => to_json(arg::<String>(args, "format").and_then(|f| do_export_as(&f))),
+        "clear_history" => {
+            do_clear_history();
+            to_json(Ok(()))
+        }
+        "rate_answer" => to_json((|| {
+            do_rate_answer(arg(args, "turn_id")?, arg(args, "helpful")?)
+        })()),
+        // Config files, the startup check and background state depend on
+        // the reporter's machine and timing; stopping depends on timing.
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Replay `entries` (from `recorder::load`) from an empty conversation, like
+/// a freshly started GUI, and compare each command's result with the
+/// recorded one.
+pub fn replay(entries: &[Entry]) -> Result<ReplayReport, String> {
+    let server = ReplayServer::start(exchanges(entries))?;
+    do_disconnect();
+    do_clear_history();
+    let mut report = ReplayReport::default();
+    for (position, entry) in entries.iter().enumerate() {
+        let EntryKind::Command { name, args, result } = &entry.kind else {
+            continue;
+        };
+        match run(name, args, &server.url) {
+            Some(replayed) => {
+                report.replayed += 1;
+                if replayed != *result {
+                    report.mismatches.push(Mismatch {
+                        entry: position,
+                        command: name.clone(),
+                        recorded: result.clone(),
+                        replayed,
+                    });
+                }
+            }
+            None => report.skipped.push(name.clone()),
+        }
+    }
+    do_disconnect();
+    drop(server);
+    Ok(report)
+}
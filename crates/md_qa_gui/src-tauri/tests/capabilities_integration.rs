@@ -0,0 +1,53 @@
+//! Integration test for the server's optional features: asked for once per
+//! connection so the frontend can hide what the server can't do.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_server_capabilities};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn capabilities_are_asked_for_once() {
+    let port = free_port();
+    let (request_tx, request_rx) = std::sync::mpsc::channel();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                request_tx.send(text.to_string()).unwrap();
+                ws.send(Message::Text(
+                    r#"{"type":"capabilities","features":["history","compression"]}"#.into(),
+                ))
+                .await
+                .unwrap();
+            }
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    assert!(do_server_capabilities().is_err(), "not connected yet");
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    for _ in 0..2 {
+        let capabilities = do_server_capabilities()
+            .unwrap()
+            .expect("server lists its features");
+        assert_eq!(capabilities.features, vec!["history", "compression"]);
+        assert!(!capabilities.supports("cancel"));
+    }
+    assert_eq!(request_rx.try_iter().count(), 1, "answer should be kept");
+    do_disconnect();
+}
@@ -0,0 +1,68 @@
+//! Integration test for citation markers: the chat reply splits the answer
+//! into text and citation spans linking to their sources. Kept in its own
+//! test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_send_query};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server whose answer cites a source with a `[1]` marker.
+fn spawn_stream_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let (mut write, mut read) = ws.split();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let _ = read.next().await;
+
+            for frame in [
+                r#"{"type":"stream_start"}"#,
+                r#"{"type":"stream_chunk","chunk":"Hello "}"#,
+                r#"{"type":"stream_chunk","chunk":"world [1]!"}"#,
+                r#"{"type":"stream_end","sources":["/x.md","/y.md"],"citations":{"1":"/x.md"}}"#,
+            ] {
+                write.send(Message::Text(frame.into())).await.unwrap();
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    })
+}
+
+#[test]
+fn chat_reply_links_citation_markers() {
+    let port = free_port();
+    let _server = spawn_stream_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let status = do_connect(&url).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let reply = do_send_query("What is this?", None).expect("query should succeed");
+
+    assert_eq!(reply.answer, "Hello world [1]!");
+    // The frontend gets the citation marker as a link to its source.
+    let json = serde_json::to_value(&reply).unwrap();
+    let spans = json["answer_spans"].as_array().unwrap();
+    assert_eq!(spans.len(), 3);
+    assert_eq!(spans[1]["kind"], "citation");
+    assert_eq!(spans[1]["number"], 1);
+    assert_eq!(spans[1]["source"]["path"], "/x.md");
+
+    do_disconnect();
+}
@@ -0,0 +1,65 @@
+//! Integration test for coded server errors: the chat reply carries the
+//! error's machine-readable code next to its message. Kept in its own test
+//! binary because the GUI holds a single global connection.
+
+use md_qa_client::messages::ErrorCode;
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_send_query};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that replies with an `auth_failed` error.
+fn spawn_auth_failed_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let (mut write, mut read) = ws.split();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let _ = read.next().await;
+
+            write
+                .send(Message::Text(
+                    r#"{"type":"error","message":"Invalid API key","code":"auth_failed"}"#.into(),
+                ))
+                .await
+                .unwrap();
+
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    })
+}
+
+#[test]
+fn chat_reports_error_code() {
+    let port = free_port();
+    let _server = spawn_auth_failed_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let status = do_connect(&url).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let reply = do_send_query("test", None).expect("query should succeed");
+
+    assert!(
+        reply.error.as_deref().unwrap().contains("Invalid API key"),
+        "error should contain server message, got: {:?}",
+        reply.error
+    );
+    assert_eq!(reply.error_code, Some(ErrorCode::AuthFailed));
+
+    do_disconnect();
+}
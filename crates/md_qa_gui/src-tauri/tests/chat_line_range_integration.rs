@@ -0,0 +1,72 @@
+//! Integration test for detailed sources: the chat reply passes each source's
+//! heading and line range on to the frontend. Kept in its own test binary
+//! because the GUI holds a single global connection.
+
+use md_qa_client::messages::Source;
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_send_query};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that answers with one bare and one detailed source.
+fn spawn_stream_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let (mut write, mut read) = ws.split();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let _ = read.next().await;
+
+            for frame in [
+                r#"{"type":"stream_start"}"#,
+                r#"{"type":"stream_chunk","chunk":"Hello world!"}"#,
+                r#"{"type":"stream_end","sources":["/x.md",
+                    {"path":"/y.md","heading":"Setup","start_line":3,"end_line":9}]}"#,
+            ] {
+                write.send(Message::Text(frame.into())).await.unwrap();
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    })
+}
+
+#[test]
+fn chat_reply_keeps_source_line_ranges() {
+    let port = free_port();
+    let _server = spawn_stream_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let status = do_connect(&url).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let reply = do_send_query("What is this?", None).expect("query should succeed");
+
+    let setup = Source {
+        heading: Some("Setup".into()),
+        start_line: Some(3),
+        end_line: Some(9),
+        ..Source::from("/y.md")
+    };
+    assert_eq!(reply.sources, vec![Source::from("/x.md"), setup]);
+    // The frontend gets the line range to jump to.
+    let json = serde_json::to_value(&reply).unwrap();
+    assert_eq!(json["sources"][1]["start_line"], 3);
+    assert_eq!(json["sources"][1]["end_line"], 9);
+
+    do_disconnect();
+}
@@ -0,0 +1,79 @@
+//! Integration tests for progressive chat replies: the backend reports each
+//! answer chunk as it arrives, then returns the assembled reply. Kept in its
+//! own test binary because the GUI holds a single global connection.
+
+use md_qa_client::messages::{QueryMessage, Source};
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_send_query_message, ChatChunk};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that streams some thinking, two chunks and STREAM_END.
+fn spawn_stream_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let (mut write, mut read) = ws.split();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let _ = read.next().await;
+
+            for frame in [
+                r#"{"type":"stream_start"}"#,
+                r#"{"type":"stream_thinking","chunk":"A greeting."}"#,
+                r#"{"type":"stream_chunk","chunk":"Hello "}"#,
+                r#"{"type":"stream_chunk","chunk":"world!"}"#,
+                r#"{"type":"stream_end","sources":["/x.md"]}"#,
+            ] {
+                write.send(Message::Text(frame.into())).await.unwrap();
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    })
+}
+
+#[test]
+fn chat_reports_each_chunk_as_it_arrives() {
+    let port = free_port();
+    let _server = spawn_stream_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let status = do_connect(&url).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let mut chunks: Vec<ChatChunk> = Vec::new();
+    let reply = do_send_query_message(&QueryMessage::new("What is this?", None), |c| {
+        chunks.push(c.clone())
+    })
+    .expect("query should succeed");
+
+    let texts: Vec<&str> = chunks.iter().map(|c| c.chunk.as_str()).collect();
+    assert_eq!(texts, vec!["Hello ", "world!"]);
+    assert_eq!(chunks[0].words, 1);
+    assert_eq!(chunks[0].tokens, 2);
+    assert_eq!(chunks[0].tokens_per_sec, 0.0);
+    // "Hello world!" is 12 characters, about 3 tokens.
+    assert_eq!(chunks[1].tokens, 3);
+    assert!(chunks[1].elapsed_ms >= chunks[0].elapsed_ms);
+    assert!(chunks[1].tokens_per_sec >= 0.0);
+    assert_eq!(reply.answer, "Hello world!");
+    // Thinking is kept apart, not streamed as answer chunks.
+    assert_eq!(reply.reasoning, "A greeting.");
+    assert_eq!(reply.sources, vec![Source::from("/x.md")]);
+
+    do_disconnect();
+}
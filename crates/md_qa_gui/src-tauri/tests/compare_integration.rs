@@ -0,0 +1,109 @@
+//! Integration test for asking two models the same question side by side.
+//! Kept in its own test binary because the GUI holds a single global
+//! connection.
+
+use md_qa_client::messages::QueryOptions;
+use md_qa_client::Side;
+use md_qa_gui_lib::commands::{
+    do_compare_query, do_connect, do_disconnect, is_connected, ComparisonChunk, INTERRUPTED,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that answers each query with the requested model's
+/// name, with an error for the model "missing", and hangs up mid-answer for
+/// the model "gone".
+fn spawn_model_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            for _ in 0..3 {
+                let (tcp, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    use futures_util::{SinkExt, StreamExt};
+                    use tokio_tungstenite::tungstenite::Message;
+
+                    let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                    while let Some(Ok(query)) = ws.next().await {
+                        let query: serde_json::Value =
+                            serde_json::from_str(query.to_text().unwrap()).unwrap();
+                        let frames = match query["model"].as_str().unwrap() {
+                            "missing" => vec![serde_json::json!({
+                                "type": "error",
+                                "message": "unknown model: missing"
+                            })],
+                            "gone" => {
+                                let chunk =
+                                    serde_json::json!({"type": "stream_chunk", "chunk": "Part"});
+                                ws.send(Message::Text(chunk.to_string())).await.unwrap();
+                                ws.close(None).await.unwrap();
+                                return;
+                            }
+                            model => vec![
+                                serde_json::json!({"type": "stream_chunk", "chunk": model}),
+                                serde_json::json!({"type": "stream_end", "sources": ["/x.md"]}),
+                            ],
+                        };
+                        for frame in frames {
+                            ws.send(Message::Text(frame.to_string())).await.unwrap();
+                        }
+                    }
+                });
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        });
+    })
+}
+
+fn model(name: &str) -> QueryOptions {
+    QueryOptions {
+        model: Some(name.to_string()),
+        ..QueryOptions::default()
+    }
+}
+
+#[test]
+fn comparison_returns_both_answers_tagged() {
+    let port = free_port();
+    let _server = spawn_model_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let mut chunks: Vec<ComparisonChunk> = Vec::new();
+    let reply = do_compare_query("Which?", None, model("small"), model("missing"), |c| {
+        chunks.push(c.clone())
+    })
+    .expect("comparison should succeed");
+
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].side, Side::Left);
+    assert_eq!(chunks[0].chunk.chunk, "small");
+    assert_eq!(reply.left.answer, "small");
+    assert_eq!(reply.left.error, None);
+    assert_eq!(reply.left.turn_id, None);
+    assert_eq!(reply.right.answer, "");
+    assert_eq!(reply.right.error.as_deref(), Some("unknown model: missing"));
+
+    // The server going away cuts the left answer off; the right one is
+    // answered on its own connection.
+    let reply = do_compare_query("Which?", None, model("gone"), model("large"), |_| {})
+        .expect("comparison should succeed");
+    assert_eq!(reply.left.answer, "Part");
+    assert_eq!(reply.left.error.as_deref(), Some(INTERRUPTED));
+    assert_eq!(reply.right.answer, "large");
+    assert_eq!(reply.right.error, None);
+    assert!(!is_connected());
+
+    do_disconnect();
+}
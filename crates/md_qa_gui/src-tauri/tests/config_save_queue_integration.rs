@@ -0,0 +1,58 @@
+//! Integration test for the config save queue: saves made while the form
+//! autosaves are coalesced into one write of the last form.
+
+use md_qa_gui_lib::commands::{do_load_config, do_queue_config_save, ConfigForm};
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn form(llm_model: &str) -> ConfigForm {
+    ConfigForm {
+        api_base_url: "https://api.example.com/v1".into(),
+        api_key: "key".into(),
+        embedding_model: "embed".into(),
+        llm_model: llm_model.into(),
+        server_port: 8765,
+        directories: vec!["/docs".into()],
+        reload_interval: 300,
+        index_name: "default".into(),
+    }
+}
+
+#[test]
+fn queued_saves_write_the_last_form_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    let path = path.to_str().unwrap();
+    let (saved_tx, saved) = mpsc::channel();
+
+    let outcomes: Vec<_> = ["g", "gp", "gpt"]
+        .into_iter()
+        .map(|model| {
+            let saved_tx = saved_tx.clone();
+            do_queue_config_save(path, form(model), move |s| saved_tx.send(s).unwrap())
+        })
+        .collect();
+    for outcome in outcomes {
+        assert_eq!(outcome.blocking_recv().unwrap(), Ok(()));
+    }
+    let event = saved.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event.path, path);
+    assert_eq!(event.error, None);
+    assert!(
+        saved.recv_timeout(Duration::from_millis(500)).is_err(),
+        "replaced saves are not written"
+    );
+    assert_eq!(do_load_config(path).unwrap().llm_model, "gpt");
+
+    // A save after the debounce is written on its own.
+    let saved_tx = saved_tx.clone();
+    let outcome = do_queue_config_save(path, form("gpt-4o"), move |s| saved_tx.send(s).unwrap());
+    assert_eq!(outcome.blocking_recv().unwrap(), Ok(()));
+    assert!(saved.recv_timeout(Duration::from_secs(5)).is_ok());
+    assert_eq!(do_load_config(path).unwrap().llm_model, "gpt-4o");
+
+    let blocked = dir.path().join("file").join("config.yaml");
+    std::fs::write(dir.path().join("file"), "").unwrap();
+    let outcome = do_queue_config_save(blocked.to_str().unwrap(), form("x"), |_| {});
+    assert!(outcome.blocking_recv().unwrap().is_err());
+}
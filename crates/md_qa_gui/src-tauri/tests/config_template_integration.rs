@@ -0,0 +1,35 @@
+//! Integration tests for onboarding from a team config template.
+
+use md_qa_gui_lib::commands::{do_create_config_from_template, do_template_variables};
+
+#[test]
+fn onboarding_creates_config_from_template() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("team.yaml.tmpl");
+    std::fs::write(
+        &template,
+        "api:\n  base_url: https://llm.example.com/v1\n  api_key: \"{{ key }}\"\nserver:\n  port: 9000\n  directories: [\"{{repo}}/docs\"]\n",
+    )
+    .unwrap();
+    let template = template.to_str().unwrap();
+    let path = dir.path().join("config.yaml");
+    let path = path.to_str().unwrap();
+
+    assert_eq!(do_template_variables(template).unwrap(), ["key", "repo"]);
+
+    let missing = do_create_config_from_template(template, &[], path, false);
+    assert!(missing.unwrap_err().contains("key, repo"));
+
+    let vars = vec![
+        ("key".to_string(), "k1".to_string()),
+        ("repo".to_string(), "/src/app".to_string()),
+    ];
+    let form = do_create_config_from_template(template, &vars, path, false).unwrap();
+    assert_eq!(form.api_key, "k1");
+    assert_eq!(form.server_port, 9000);
+    assert_eq!(form.directories, vec!["/src/app/docs"]);
+
+    let again = do_create_config_from_template(template, &vars, path, false);
+    assert!(again.unwrap_err().contains("already exists"));
+    assert!(do_create_config_from_template(template, &vars, path, true).is_ok());
+}
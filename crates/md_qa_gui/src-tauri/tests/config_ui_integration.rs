@@ -93,6 +93,31 @@ fn round_trip_preserves_form_values() {
     assert_eq!(loaded, original);
 }
 
+/// Saving the form keeps server settings the form doesn't show.
+#[test]
+fn save_keeps_server_settings_not_in_the_form() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    std::fs::write(
+        &path,
+        "server:\n  warmup: true\n  max_file_size_mb: 512\n  docs_language: en\n  index_drafts: true\n",
+    )
+    .unwrap();
+
+    let form = ConfigForm {
+        api_base_url: "https://api.test.com".into(),
+        api_key: "key-123".into(),
+        ..ConfigForm::default()
+    };
+    do_save_config(path.to_str().unwrap(), &form).expect("save should succeed");
+
+    let saved = md_qa_client::config::load(&path).unwrap();
+    assert!(saved.server.warmup);
+    assert_eq!(saved.server.max_file_size_mb, Some(512));
+    assert_eq!(saved.server.docs_language.as_deref(), Some("en"));
+    assert!(saved.server.index_drafts);
+}
+
 /// Load from non-existent file returns an error (not a panic).
 #[test]
 fn load_missing_file_returns_error() {
@@ -0,0 +1,48 @@
+//! Integration test for connection lifecycle events forwarded to the frontend.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_watch_connection};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn watch_connection_reports_disconnect() {
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::StreamExt;
+            while ws.next().await.is_some() {}
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    assert!(do_watch_connection(|_| {}).is_err(), "nothing to watch yet");
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let (status_tx, status_rx) = std::sync::mpsc::channel();
+    do_watch_connection(move |status| {
+        let _ = status_tx.send(status);
+    })
+    .unwrap();
+    do_disconnect();
+
+    let status = status_rx
+        .recv_timeout(std::time::Duration::from_secs(2))
+        .expect("watcher should see the disconnect");
+    assert_eq!(status.state, "disconnected");
+    assert_eq!(status.message.as_deref(), Some("closed by client"));
+}
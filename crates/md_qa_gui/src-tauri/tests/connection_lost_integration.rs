@@ -0,0 +1,60 @@
+//! Integration test for a server hanging up mid-answer: the partial answer
+//! is kept as an interrupted turn and the GUI drops the dead connection. Kept in its own test binary because the
+//! GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{
+    do_connect, do_export_conversation, do_send_query, is_connected, INTERRUPTED,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that sends STREAM_START and then closes the socket.
+fn spawn_hangup_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let _ = ws.next().await;
+            for frame in [
+                r#"{"type":"stream_start"}"#,
+                r#"{"type":"stream_chunk","chunk":"Partial"}"#,
+            ] {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            ws.close(None).await.unwrap();
+        });
+    })
+}
+
+#[test]
+fn hangup_mid_answer_drops_connection() {
+    let port = free_port();
+    let _server = spawn_hangup_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let reply = do_send_query("What is this?", None).expect("reply should be kept");
+    assert_eq!(reply.error.as_deref(), Some(INTERRUPTED));
+    assert_eq!(reply.answer, "Partial");
+    assert!(reply.turn_id.is_some());
+    assert!(do_export_conversation()
+        .unwrap()
+        .contains("Partial _(interrupted)_"));
+    assert!(!is_connected());
+}
@@ -0,0 +1,77 @@
+//! Integration test for the per-chat cost: token usage reported with each
+//! answer is kept on its turn (regenerated ones included) and priced with
+//! the `prices` table of the config.
+//! Kept in its own test binary because the GUI holds a single global
+//! connection and conversation history.
+
+use md_qa_gui_lib::commands::{
+    do_clear_history, do_connect, do_conversation_cost, do_disconnect, do_regenerate, do_send_query,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn conversation_cost_sums_usage_of_every_answer() {
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            for usage in [
+                r#"{"prompt_tokens":1000000,"completion_tokens":100000}"#,
+                r#"{"prompt_tokens":500000,"completion_tokens":0,"model":"other"}"#,
+            ] {
+                let _ = ws.next().await;
+                for frame in [
+                    r#"{"type":"stream_chunk","chunk":"Answer."}"#.to_string(),
+                    format!(r#"{{"type":"stream_end","sources":[],"usage":{}}}"#, usage),
+                ] {
+                    ws.send(Message::Text(frame)).await.unwrap();
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        "api:\n  llm_model: small\nprices:\n  small:\n    prompt: 0.5\n    completion: 2.0\n",
+    )
+    .unwrap();
+    std::env::set_var("MD_QA_CONFIG", &config_path);
+
+    do_clear_history();
+    assert_eq!(do_conversation_cost().unwrap().answers, 0);
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    let reply = do_send_query("What is this?", None).expect("query should succeed");
+    assert_eq!(reply.stats.usage.as_ref().unwrap().prompt_tokens, 1_000_000);
+    do_regenerate(reply.turn_id.unwrap(), None, |_| {}).expect("regenerate should succeed");
+
+    let totals = do_conversation_cost().unwrap();
+    assert_eq!(totals.answers, 2, "the replaced answer still counts");
+    assert_eq!(totals.prompt_tokens, 1_500_000);
+    assert_eq!(totals.completion_tokens, 100_000);
+    assert_eq!(totals.unpriced, 1, "no price for `other`");
+    assert!((totals.cost - 0.7).abs() < 1e-9, "{}", totals.cost);
+
+    do_clear_history();
+    assert_eq!(do_conversation_cost().unwrap().answers, 0);
+    do_disconnect();
+}
@@ -0,0 +1,48 @@
+//! Integration test for disconnecting with a WebSocket close handshake.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, is_connected};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn disconnect_sends_normal_close_frame() {
+    let port = free_port();
+    let (close_tx, close_rx) = std::sync::mpsc::channel();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::StreamExt;
+            use tokio_tungstenite::tungstenite::Message;
+
+            while let Some(Ok(message)) = ws.next().await {
+                if let Message::Close(frame) = message {
+                    close_tx.send(frame.map(|f| u16::from(f.code))).unwrap();
+                }
+            }
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    do_disconnect();
+    assert!(!is_connected());
+    let code = close_rx
+        .recv_timeout(std::time::Duration::from_secs(2))
+        .expect("server should see a Close frame");
+    assert_eq!(code, Some(1000));
+}
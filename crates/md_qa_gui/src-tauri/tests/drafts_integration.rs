@@ -0,0 +1,49 @@
+//! Integration test for chat drafts: unsent input is written after the
+//! debounce, restored from the drafts file and dropped once sent.
+
+use md_qa_client::drafts;
+use md_qa_gui_lib::commands::{
+    do_list_drafts, do_load_draft, do_queue_draft_save, set_drafts_path, DRAFT_SAVE_DEBOUNCE,
+};
+use std::time::Duration;
+
+fn settle() {
+    std::thread::sleep(DRAFT_SAVE_DEBOUNCE + Duration::from_millis(500));
+}
+
+#[test]
+fn drafts_are_written_after_the_debounce_and_restored() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = drafts::path_for_config(&dir.path().join("config.yaml"));
+    set_drafts_path(Some(path.clone()));
+
+    for text in ["How", "How do I", "How do I rotate the keys?"] {
+        do_queue_draft_save("chat", text);
+    }
+    // Not written yet, but already restorable.
+    assert!(drafts::load(&path).unwrap().is_empty());
+    let pending = do_load_draft("chat").unwrap().unwrap();
+    assert_eq!(pending.text, "How do I rotate the keys?");
+
+    settle();
+    let written = drafts::get(&path, "chat").unwrap().unwrap();
+    assert_eq!(written.text, "How do I rotate the keys?");
+
+    // Drafts of every session are listed.
+    do_queue_draft_save("compare", "Which model?");
+    settle();
+    let sessions: Vec<String> = do_list_drafts()
+        .unwrap()
+        .into_iter()
+        .map(|d| d.session)
+        .collect();
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.contains(&"chat".to_string()));
+
+    // Sending the question clears its draft.
+    do_queue_draft_save("chat", "");
+    assert_eq!(do_load_draft("chat").unwrap(), None);
+    settle();
+    assert_eq!(drafts::get(&path, "chat").unwrap(), None);
+    assert_eq!(drafts::load(&path).unwrap().len(), 1);
+}
@@ -0,0 +1,78 @@
+//! Integration test for exporting the chat as a pre-filled issue body or a
+//! Markdown snippet, with the templates of the config's `export` section.
+//! Kept in its own test binary because the GUI holds a single global
+//! connection and conversation history.
+
+use md_qa_gui_lib::commands::{
+    do_clear_history, do_connect, do_disconnect, do_export_as, do_send_query,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn export_as_renders_the_active_conversation() {
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let _ = ws.next().await;
+            for frame in [
+                r#"{"type":"stream_chunk","chunk":"Run make deploy."}"#,
+                r#"{"type":"stream_end","sources":["/docs/deploy.md"]}"#,
+            ] {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("snippet.md");
+    std::fs::write(&template, "{{ question }} -> {{ answer }} ({{ sources }})").unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        format!("export:\n  snippet_template: {}\n", template.display()),
+    )
+    .unwrap();
+    std::env::set_var("MD_QA_CONFIG", &config_path);
+
+    do_clear_history();
+    assert_eq!(do_export_as("issue").unwrap_err(), "Nothing to export yet");
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    do_send_query("How do I deploy?", None).expect("query should succeed");
+
+    let issue = do_export_as("issue").unwrap();
+    assert!(
+        issue.starts_with("### Question\n\nHow do I deploy?\n\n### Answer\n\nRun make deploy.\n"),
+        "{}",
+        issue
+    );
+    assert!(issue.contains("- /docs/deploy.md"));
+    assert_eq!(
+        do_export_as("snippet").unwrap(),
+        "How do I deploy? -> Run make deploy. (- /docs/deploy.md)"
+    );
+    assert!(do_export_as("pdf")
+        .unwrap_err()
+        .contains("invalid export format"));
+
+    do_clear_history();
+    do_disconnect();
+}
@@ -0,0 +1,71 @@
+//! Integration test for conversation-aware retrieval: follow-up questions
+//! carry the recent turns in `retrieval_query`. Kept in its own test binary
+//! because the GUI holds a single global connection and history.
+
+use md_qa_gui_lib::commands::{do_connect, do_edit_query, do_send_query};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that answers every query and reports each query's
+/// `retrieval_query` on `seen`.
+fn spawn_recording_server(
+    port: u16,
+    seen: std::sync::mpsc::Sender<Option<String>>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let query: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let retrieval_query = query["retrieval_query"].as_str().map(str::to_string);
+                seen.send(retrieval_query).unwrap();
+                for frame in [
+                    r#"{"type":"stream_start"}"#,
+                    r#"{"type":"stream_chunk","chunk":"Foo is a tool."}"#,
+                    r#"{"type":"stream_end","sources":["/docs/foo.md"]}"#,
+                ] {
+                    ws.send(Message::Text(frame.into())).await.unwrap();
+                }
+            }
+        });
+    })
+}
+
+#[test]
+fn follow_ups_are_retrieved_with_recent_turns() {
+    let port = free_port();
+    let (seen_tx, seen_rx) = std::sync::mpsc::channel();
+    let _server = spawn_recording_server(port, seen_tx);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let first = do_send_query("What is foo?", None).unwrap();
+    assert_eq!(seen_rx.recv().unwrap(), None);
+
+    do_send_query("How do I install it?", None).unwrap();
+    assert_eq!(
+        seen_rx.recv().unwrap().as_deref(),
+        Some("Q: What is foo?\nA: Foo is a tool.\nQ: How do I install it?")
+    );
+
+    // Editing the first turn has no earlier turns to draw on.
+    do_edit_query(first.turn_id.unwrap(), "What is bar?", None, |_| {}).unwrap();
+    assert_eq!(seen_rx.recv().unwrap(), None);
+}
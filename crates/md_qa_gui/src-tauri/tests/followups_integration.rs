@@ -0,0 +1,84 @@
+//! Integration test for follow-up suggestions under a chat answer. Kept in
+//! its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_send_query, do_suggest_followups};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that answers queries, suggests follow-ups once and
+/// rejects later suggestion requests like a server without the feature.
+fn spawn_followups_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let mut suggested = false;
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let replies = match request["type"].as_str().unwrap() {
+                    "query" => vec![
+                        r#"{"type":"stream_start"}"#.to_string(),
+                        r#"{"type":"stream_chunk","chunk":"Foo is a tool."}"#.to_string(),
+                        r#"{"type":"stream_end","sources":["/docs/foo.md"]}"#.to_string(),
+                    ],
+                    "suggest_followups" if !suggested => {
+                        suggested = true;
+                        assert_eq!(request["answer"], "Foo is a tool.");
+                        assert_eq!(request["sources"], serde_json::json!(["/docs/foo.md"]));
+                        vec![serde_json::json!({
+                            "type": "followups",
+                            "questions": ["How do I install foo?", "Who maintains foo?"],
+                        })
+                        .to_string()]
+                    }
+                    other => vec![serde_json::json!({
+                        "type": "error",
+                        "message": format!("Unknown message type: {}", other),
+                    })
+                    .to_string()],
+                };
+                for reply in replies {
+                    ws.send(Message::Text(reply)).await.unwrap();
+                }
+            }
+        });
+    })
+}
+
+#[test]
+fn followups_are_suggested_when_the_server_offers_them() {
+    let port = free_port();
+    let _server = spawn_followups_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let mut reply = do_send_query("What is foo?", None).unwrap();
+    assert!(reply.followups.is_empty());
+    do_suggest_followups("What is foo?", &mut reply);
+    assert_eq!(
+        reply.followups,
+        vec!["How do I install foo?", "Who maintains foo?"]
+    );
+
+    // Without suggestions from the server the answer simply has none.
+    let mut reply = do_send_query("How do I install foo?", None).unwrap();
+    do_suggest_followups("How do I install foo?", &mut reply);
+    assert!(reply.followups.is_empty());
+    assert!(reply.error.is_none());
+}
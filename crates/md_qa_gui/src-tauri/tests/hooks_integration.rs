@@ -0,0 +1,68 @@
+//! Integration test for config hooks in the GUI: a failing `pre_query` hook
+//! under `on_failure: warn` is reported on the reply and the query still
+//! runs, and `post_answer` gets the answer.
+#![cfg(unix)]
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_send_query};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn failing_hook_is_reported_as_a_warning() {
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let _ = ws.next().await;
+            for frame in [
+                r#"{"type":"stream_chunk","chunk":"Hello"}"#,
+                r#"{"type":"stream_end","sources":["/x.md"]}"#,
+            ] {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let dir = tempfile::tempdir().unwrap();
+    let answer_log = dir.path().join("answer.json");
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "hooks:\n  pre_query: \"echo nope >&2; exit 3\"\n  post_answer: \"cat > '{}'\"\n",
+            answer_log.display()
+        ),
+    )
+    .unwrap();
+    std::env::set_var("MD_QA_CONFIG", &config_path);
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    let reply = do_send_query("What is this?", None).expect("query should succeed");
+
+    assert_eq!(reply.answer, "Hello");
+    assert_eq!(reply.warnings.len(), 1, "{:?}", reply.warnings);
+    assert!(reply.warnings[0].contains("pre_query hook failed"));
+    assert!(reply.warnings[0].contains("nope"));
+    let logged: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&answer_log).unwrap()).unwrap();
+    assert_eq!(logged["question"], "What is this?");
+    assert_eq!(logged["answer"], "Hello");
+
+    do_disconnect();
+}
@@ -0,0 +1,73 @@
+//! Integration test for a `pre_query` hook rewriting the question in the GUI:
+//! the rewritten question is sent, logged and recorded in the history.
+//! Kept in its own test binary because the GUI holds a single global connection.
+#![cfg(unix)]
+
+use md_qa_gui_lib::commands::{
+    do_connect, do_disconnect, do_list_branches, do_send_query, set_query_log_path,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn rewritten_question_is_sent_logged_and_recorded() {
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let request = ws.next().await.unwrap().unwrap();
+            let request: serde_json::Value =
+                serde_json::from_str(request.to_text().unwrap()).unwrap();
+            assert_eq!(request["question"], "What is the server?");
+            for frame in [
+                r#"{"type":"stream_chunk","chunk":"Hello"}"#,
+                r#"{"type":"stream_end","sources":["/x.md"]}"#,
+            ] {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(
+        &config_path,
+        "hooks:\n  pre_query: \"sed 's/this/the server/'\"\n",
+    )
+    .unwrap();
+    std::env::set_var("MD_QA_CONFIG", &config_path);
+    set_query_log_path(Some(dir.path().join("queries.jsonl")));
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    let reply = do_send_query("What is this?", None).expect("query should succeed");
+    assert_eq!(reply.answer, "Hello");
+    assert!(reply.warnings.is_empty(), "{:?}", reply.warnings);
+
+    let branches = do_list_branches().unwrap();
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].question, "What is the server?");
+    let log = std::fs::read_to_string(dir.path().join("queries.jsonl")).unwrap();
+    assert!(
+        log.contains(r#""question":"What is the server?""#),
+        "{}",
+        log
+    );
+
+    do_disconnect();
+}
@@ -0,0 +1,102 @@
+//! Integration test for the index browser commands against an in-process
+//! server. Kept in its own test binary because the GUI holds a single global
+//! connection.
+
+use md_qa_gui_lib::commands::{
+    do_connect, do_get_document_chunks, do_get_document_links, do_list_indexed_documents,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that answers `list_documents` (echoing the requested
+/// index and page) and `get_chunks` requests.
+fn spawn_index_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let reply = match request["type"].as_str().unwrap() {
+                    "list_documents" => serde_json::json!({
+                        "type": "documents",
+                        "index": request["index"],
+                        "page": request["page"],
+                        "total": 1,
+                        "documents": [
+                            {"path": "/docs/a.md", "chunks": 2, "indexed_at": 1700000000}
+                        ],
+                    }),
+                    "get_chunks" if request["path"] == "/docs/a.md" => serde_json::json!({
+                        "type": "chunks",
+                        "path": "/docs/a.md",
+                        "chunks": [
+                            {"text": "Intro.", "heading": "A"},
+                            {"text": "More."}
+                        ],
+                    }),
+                    "get_links" if request["path"] == "/docs/a.md" => serde_json::json!({
+                        "type": "links",
+                        "path": "/docs/a.md",
+                        "outbound": ["/docs/b.md"],
+                        "inbound": ["/docs/c.md"],
+                    }),
+                    _ => serde_json::json!({"type": "error", "message": "Document not indexed"}),
+                };
+                ws.send(Message::Text(reply.to_string())).await.unwrap();
+            }
+        });
+    })
+}
+
+#[test]
+fn index_browser_lists_documents_and_chunks() {
+    assert_eq!(
+        do_list_indexed_documents(None, 0).unwrap_err(),
+        "Not connected"
+    );
+
+    let port = free_port();
+    let _server = spawn_index_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let page = do_list_indexed_documents(Some("default"), 1).expect("list should succeed");
+    assert_eq!(page.index.as_deref(), Some("default"));
+    assert_eq!(page.page, 1);
+    assert_eq!(page.total, 1);
+    assert_eq!(page.documents[0].path, "/docs/a.md");
+    assert_eq!(page.documents[0].chunks, 2);
+    assert_eq!(page.documents[0].indexed_at, Some(1700000000));
+
+    let chunks = do_get_document_chunks("/docs/a.md").expect("chunks should succeed");
+    assert_eq!(chunks.chunks.len(), 2);
+    assert_eq!(chunks.chunks[0].heading.as_deref(), Some("A"));
+    assert_eq!(chunks.chunks[1].heading, None);
+
+    let err = do_get_document_chunks("/docs/missing.md").expect_err("should fail");
+    assert!(
+        err.contains("Document not indexed"),
+        "unexpected error: {err}"
+    );
+
+    let links = do_get_document_links("/docs/a.md").expect("links should succeed");
+    assert_eq!(links.outbound, vec!["/docs/b.md"]);
+    assert_eq!(links.inbound, vec!["/docs/c.md"]);
+}
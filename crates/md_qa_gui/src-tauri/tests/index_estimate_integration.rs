@@ -0,0 +1,60 @@
+//! Integration test for the estimate the Reindex button confirms: what a
+//! reload would embed, priced with the `prices` table of the config.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_index_estimate};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn index_estimate_prices_the_tokens_a_reload_would_embed() {
+    assert_eq!(do_index_estimate(None).unwrap_err(), "Not connected");
+
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let request = ws.next().await.unwrap().unwrap();
+            assert_eq!(
+                request.to_text().unwrap(),
+                r#"{"type":"index_plan","estimate":true}"#
+            );
+            ws.send(Message::Text(
+                r#"{"type":"index_plan","files":["/docs/a.md"],"ignored":[],"estimate":{"embedding_model":"embedder","full_rebuild":true,"files":[{"path":"/docs/a.md","chunks":4000,"tokens":2000000}],"chunks":4000,"tokens":2000000,"cached_chunks":0,"seconds":400.0}}"#.into(),
+            ))
+            .await
+            .unwrap();
+            let _ = ws.next().await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(&config_path, "prices:\n  embedder:\n    prompt: 0.02\n").unwrap();
+    std::env::set_var("MD_QA_CONFIG", &config_path);
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    let estimate = do_index_estimate(None)
+        .expect("estimate should succeed")
+        .expect("the server sent an estimate");
+    assert_eq!(estimate.estimate.tokens, 2_000_000);
+    assert!(estimate.estimate.full_rebuild);
+    assert!((estimate.cost.unwrap() - 0.04).abs() < 1e-9);
+    assert!(estimate.large);
+    do_disconnect();
+}
@@ -0,0 +1,79 @@
+//! Integration test for the index picker: listing, creating and deleting
+//! named indexes on the settings page.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{
+    do_connect, do_create_index, do_delete_index, do_disconnect, do_list_indexes,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+const DEFAULT_INDEX: &str = r#"{"name":"docs","directories":["/srv/docs"],"embedding_model":"text-embedding-3-small","default":true,"status":"ready"}"#;
+const NOTES_INDEX: &str = r#"{"name":"notes","directories":["/home/me/notes"],"embedding_model":"local:bge-small","default":false,"status":"indexing"}"#;
+
+#[test]
+fn named_indexes_are_listed_created_and_deleted() {
+    assert_eq!(do_list_indexes().unwrap_err(), "Not connected");
+
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            for (expected, indexes) in [
+                (
+                    r#"{"type":"list_indexes"}"#,
+                    format!("[{}]", DEFAULT_INDEX),
+                ),
+                (
+                    r#"{"type":"index_create","name":"notes","directories":["/home/me/notes"],"embedding_model":"local:bge-small"}"#,
+                    format!("[{},{}]", DEFAULT_INDEX, NOTES_INDEX),
+                ),
+                (
+                    r#"{"type":"index_delete","name":"notes"}"#,
+                    format!("[{}]", DEFAULT_INDEX),
+                ),
+            ] {
+                let request = ws.next().await.unwrap().unwrap();
+                assert_eq!(request.to_text().unwrap(), expected);
+                ws.send(Message::Text(
+                    format!(r#"{{"type":"indexes","indexes":{}}}"#, indexes).into(),
+                ))
+                .await
+                .unwrap();
+            }
+            let _ = ws.next().await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    let indexes = do_list_indexes().expect("listing indexes should succeed");
+    assert_eq!(indexes.len(), 1);
+    assert!(indexes[0].default);
+    let indexes = do_create_index(
+        "notes",
+        &["/home/me/notes".to_string()],
+        None,
+        Some("local:bge-small"),
+    )
+    .expect("creating an index should succeed");
+    assert_eq!(indexes[1].name, "notes");
+    assert_eq!(indexes[1].status, "indexing");
+    let indexes = do_delete_index("notes").expect("deleting an index should succeed");
+    assert_eq!(indexes.len(), 1);
+    do_disconnect();
+}
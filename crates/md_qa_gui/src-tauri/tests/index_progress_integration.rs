@@ -0,0 +1,75 @@
+//! Integration test for server-pushed index build progress reaching the
+//! GUI. Kept in its own test binary because the GUI holds a single global
+//! connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_index_progress, do_watch_index_progress};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that reports rebuilding a three-document index, one
+/// of which it could not read, shortly after the client connects.
+fn spawn_indexing_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            for frame in [
+                r#"{"type":"index_progress","done":1,"total":3,"path":"/docs/a.md"}"#,
+                r#"{"type":"index_progress","done":2,"total":3,"path":"/docs/b.md"}"#,
+                r#"{"type":"index_progress","done":3,"total":3,"chunks":5,"embedded":5,"finished":true,"failed":[{"path":"/docs/c.md","issue":"failed to read: permission denied"}]}"#,
+            ] {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            while ws.next().await.is_some() {}
+        });
+    })
+}
+
+#[test]
+fn index_progress_is_available_to_the_frontend() {
+    assert_eq!(do_index_progress(), None);
+
+    let port = free_port();
+    let _server = spawn_indexing_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    do_watch_index_progress(move |progress| {
+        let _ = tx.send(progress);
+    })
+    .unwrap();
+
+    let next = || {
+        rx.recv_timeout(std::time::Duration::from_secs(5))
+            .expect("no progress reported")
+    };
+    assert_eq!(next().done, 1);
+    let progress = next();
+    assert_eq!(progress.done, 2);
+    assert_eq!(progress.total, 3);
+    assert_eq!(progress.path.as_deref(), Some("/docs/b.md"));
+    let report = next();
+    assert!(report.finished);
+    assert_eq!(report.chunks, Some(5));
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].path, "/docs/c.md");
+    assert_eq!(do_index_progress(), Some(report));
+}
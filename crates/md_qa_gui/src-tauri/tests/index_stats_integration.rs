@@ -0,0 +1,49 @@
+//! Integration test for the index statistics shown on the settings page.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_index_stats};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn index_stats_are_fetched_from_the_server() {
+    assert_eq!(do_index_stats(None).unwrap_err(), "Not connected");
+
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let request = ws.next().await.unwrap().unwrap();
+            assert_eq!(request.to_text().unwrap(), r#"{"type":"index_stats"}"#);
+            ws.send(Message::Text(
+                r#"{"type":"index_stats","index":"default","documents":3,"chunks":41,"size_bytes":65536}"#.into(),
+            ))
+            .await
+            .unwrap();
+            let _ = ws.next().await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    let stats = do_index_stats(None).expect("index stats should succeed");
+    assert_eq!(stats.documents, 3);
+    assert_eq!(stats.chunks, 41);
+    assert_eq!(stats.size_bytes, Some(65536));
+    assert_eq!(stats.embedding_model, None);
+    do_disconnect();
+}
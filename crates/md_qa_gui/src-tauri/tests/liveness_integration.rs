@@ -0,0 +1,58 @@
+//! Integration test for the liveness probe of `connection_status`: a server
+//! that stops answering pings is reported (and dropped) as disconnected. Kept
+//! in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_connection_status, is_connected};
+use std::time::Duration;
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that completes the handshake, answers pings for
+/// `responsive_for`, then stops reading so later pings go unanswered.
+fn spawn_fading_server(port: u16, responsive_for: Duration) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::StreamExt;
+
+            // Reading lets tungstenite answer each ping with a pong.
+            let _ = tokio::time::timeout(responsive_for, async {
+                while let Some(Ok(_)) = ws.next().await {}
+            })
+            .await;
+            std::future::pending::<()>().await;
+        });
+    })
+}
+
+#[test]
+fn connection_status_detects_unresponsive_server() {
+    let port = free_port();
+    let _server = spawn_fading_server(port, Duration::from_millis(500));
+    std::thread::sleep(Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+    let status = do_connection_status(Duration::from_secs(5));
+    assert_eq!(status.state, "connected");
+    assert!(is_connected());
+
+    std::thread::sleep(Duration::from_millis(600));
+    let status = do_connection_status(Duration::from_millis(200));
+    assert_eq!(status.state, "disconnected");
+    let message = status.message.expect("disconnect reason");
+    assert!(message.contains("ping"), "unexpected reason: {message}");
+    assert!(!is_connected());
+}
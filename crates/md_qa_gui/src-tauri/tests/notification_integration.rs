@@ -0,0 +1,62 @@
+//! Integration test for server-pushed document notifications reaching the
+//! GUI. Kept in its own test binary because the GUI holds a single global
+//! connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_send_query, do_take_notifications};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that pushes a notification ahead of its answer.
+fn spawn_notifying_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let _ = ws.next().await;
+            for frame in [
+                r#"{"type":"notification","documents":["/docs/a.md"],"message":"Index updated"}"#,
+                r#"{"type":"stream_start"}"#,
+                r#"{"type":"stream_chunk","chunk":"Answer."}"#,
+                r#"{"type":"stream_end","sources":["/docs/a.md"]}"#,
+            ] {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            let _ = ws.next().await;
+        });
+    })
+}
+
+#[test]
+fn notifications_are_collected_for_the_frontend() {
+    assert!(do_take_notifications().is_empty());
+
+    let port = free_port();
+    let _server = spawn_notifying_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let reply = do_send_query("What changed?", None).unwrap();
+    assert_eq!(reply.answer, "Answer.");
+
+    let notifications = do_take_notifications();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].documents, ["/docs/a.md"]);
+    assert_eq!(notifications[0].message.as_deref(), Some("Index updated"));
+    assert!(do_take_notifications().is_empty());
+}
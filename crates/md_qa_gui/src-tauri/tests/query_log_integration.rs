@@ -0,0 +1,72 @@
+//! Integration tests for the GUI query log: answered queries and ratings are
+//! recorded, and the gaps report lists unanswered or unhelpful questions.
+//! Kept in its own test binary because the GUI holds a single global
+//! connection and query log path.
+
+use md_qa_gui_lib::commands::{
+    do_connect, do_disconnect, do_rate_answer, do_report_gaps, do_send_query, set_query_log_path,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that answers each query with `sources` from `replies`, in order.
+fn spawn_server(port: u16, replies: Vec<&'static str>) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let (mut write, mut read) = ws.split();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            for sources in replies {
+                let _ = read.next().await;
+                for frame in [
+                    r#"{"type":"stream_start"}"#.to_string(),
+                    r#"{"type":"stream_chunk","chunk":"Answer."}"#.to_string(),
+                    format!(r#"{{"type":"stream_end","sources":{}}}"#, sources),
+                ] {
+                    write.send(Message::Text(frame)).await.unwrap();
+                }
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    })
+}
+
+#[test]
+fn unanswered_and_unhelpful_questions_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    set_query_log_path(Some(dir.path().join("queries.jsonl")));
+
+    let port = free_port();
+    let _server = spawn_server(port, vec!["[]", r#"["/x.md"]"#, r#"["/y.md"]"#]);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    do_send_query("How do I deploy?", None).expect("query should succeed");
+    let rated = do_send_query("What is X?", None).expect("query should succeed");
+    do_send_query("What is Y?", None).expect("query should succeed");
+    do_rate_answer(rated.turn_id.unwrap(), false).expect("rating should be logged");
+
+    let gaps = do_report_gaps().expect("report should load");
+    let questions: Vec<&str> = gaps.iter().map(|g| g.question.as_str()).collect();
+    assert_eq!(questions.len(), 2);
+    assert!(questions.contains(&"How do I deploy?"));
+    assert!(questions.contains(&"What is X?"));
+
+    do_disconnect();
+}
@@ -0,0 +1,47 @@
+//! Integration test for re-indexing from the GUI after Markdown edits.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_reload_index};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn reload_index_reports_the_started_reload() {
+    assert_eq!(do_reload_index(None).unwrap_err(), "Not connected");
+
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let request = ws.next().await.unwrap().unwrap();
+            assert_eq!(request.to_text().unwrap(), r#"{"type":"reload"}"#);
+            ws.send(Message::Text(
+                r#"{"type":"status","status":"indexing","message":"Reindexing started"}"#.into(),
+            ))
+            .await
+            .unwrap();
+            let _ = ws.next().await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    let status = do_reload_index(None).expect("reload should succeed");
+    assert_eq!(status.status, "indexing");
+    assert_eq!(status.message.as_deref(), Some("Reindexing started"));
+    do_disconnect();
+}
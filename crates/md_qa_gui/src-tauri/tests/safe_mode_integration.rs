@@ -0,0 +1,45 @@
+//! Integration test for safe mode: the config file is moved aside, the GUI
+//! neither checks nor watches the server and leaves the drafts file alone.
+
+use md_qa_client::drafts;
+use md_qa_gui_lib::commands::{
+    do_enter_safe_mode, do_queue_draft_save, do_startup_check, do_watch_connection, safe_mode,
+    set_drafts_path, DRAFT_SAVE_DEBOUNCE,
+};
+
+#[test]
+fn safe_mode_moves_the_config_aside_and_skips_the_startup_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    std::fs::write(&path, "server: [not: a mapping").unwrap();
+    std::fs::write(dir.path().join("config.yaml.bad"), "older backup").unwrap();
+    assert_eq!(safe_mode(), None);
+
+    let mode = do_enter_safe_mode(Some(&path)).unwrap();
+    let backup = dir.path().join("config.yaml.bad");
+    assert_eq!(mode.config_backup, Some(backup.display().to_string()));
+    assert!(!path.exists());
+    assert_eq!(
+        std::fs::read_to_string(&backup).unwrap(),
+        "server: [not: a mapping"
+    );
+    assert_eq!(safe_mode(), Some(mode));
+
+    let steps = do_startup_check();
+    assert_eq!(steps.len(), 4);
+    assert!(steps.iter().all(|s| s.state == "skipped"), "{:?}", steps);
+    // No connection, but nothing to watch either.
+    assert_eq!(do_watch_connection(|_| {}), Ok(()));
+
+    // Drafts stay in memory even if a drafts file is set.
+    let drafts_path = drafts::path_for_config(&path);
+    set_drafts_path(Some(drafts_path.clone()));
+    do_queue_draft_save("chat", "Unsent question");
+    std::thread::sleep(DRAFT_SAVE_DEBOUNCE + std::time::Duration::from_millis(500));
+    assert!(!drafts_path.exists());
+
+    // Without a config file there is nothing to move.
+    let mode = do_enter_safe_mode(Some(&path)).unwrap();
+    assert_eq!(mode.config_backup, None);
+    assert!(backup.exists());
+}
@@ -0,0 +1,66 @@
+//! Integration test for tailing the server log from the GUI.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_client::messages::LogLevel;
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_tail_server_logs};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn server_log_lines_are_returned_then_followed() {
+    assert_eq!(
+        do_tail_server_logs(10, false, |_| {}).unwrap_err(),
+        "Not connected"
+    );
+
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let request = ws.next().await.unwrap().unwrap();
+            assert_eq!(
+                request.to_text().unwrap(),
+                r#"{"type":"tail_logs","lines":20,"follow":true}"#
+            );
+            let frames = [
+                r#"{"type":"logs","lines":["[t] p1 {server.py:1} WARNING - Server started without valid directories configured"]}"#,
+                r#"{"type":"log","line":"[t] p1 {server.py:2} INFO - Server ready"}"#,
+            ];
+            for frame in frames {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            let _ = ws.next().await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    let (line_tx, line_rx) = std::sync::mpsc::channel();
+    let recent = do_tail_server_logs(20, true, move |line| {
+        let _ = line_tx.send(line);
+    })
+    .expect("tail should succeed");
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].level, Some(LogLevel::Warning));
+
+    let line = line_rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .expect("followed line should arrive");
+    assert_eq!(line.level, Some(LogLevel::Info));
+    assert!(line.text.ends_with("Server ready"));
+    do_disconnect();
+}
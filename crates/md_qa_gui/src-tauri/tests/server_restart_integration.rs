@@ -0,0 +1,94 @@
+//! Integration test for a server restarting during a session: the answer it
+//! cut off is kept as an interrupted turn, the watcher reconnects and checks
+//! the server's status again, and reports it all as one status.
+//! Kept in its own test binary because the GUI holds a single global
+//! connection and conversation history.
+
+use md_qa_gui_lib::commands::{
+    do_clear_history, do_connect, do_disconnect, do_send_query, do_server_status,
+    do_watch_connection, is_connected, INTERRUPTED,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that starts an answer on its first connection and
+/// hangs up, then answers status requests on the next one.
+fn spawn_restarting_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let _ = ws.next().await;
+            for frame in [
+                r#"{"type":"stream_start"}"#,
+                r#"{"type":"stream_chunk","chunk":"Partial"}"#,
+            ] {
+                ws.send(Message::Text(frame.into())).await.unwrap();
+            }
+            ws.close(None).await.unwrap();
+            drop(ws);
+
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            while let Some(Ok(message)) = ws.next().await {
+                if message.to_text().is_ok_and(|text| text.contains("status")) {
+                    let status = r#"{"type":"status","status":"ready","documents":3}"#;
+                    ws.send(Message::Text(status.into())).await.unwrap();
+                }
+            }
+        });
+    })
+}
+
+#[test]
+fn server_restart_is_reported_once_after_reconnecting() {
+    let port = free_port();
+    let _server = spawn_restarting_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    do_clear_history();
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+    let (status_tx, status_rx) = std::sync::mpsc::channel();
+    do_watch_connection(move |status| {
+        let _ = status_tx.send(status);
+    })
+    .unwrap();
+
+    let reply = do_send_query("What is this?", None).expect("reply should be kept");
+    assert_eq!(reply.error.as_deref(), Some(INTERRUPTED));
+    assert_eq!(reply.answer, "Partial");
+
+    let status = status_rx
+        .recv_timeout(std::time::Duration::from_secs(10))
+        .expect("watcher should report the restart");
+    assert_eq!(status.state, "connected");
+    let restart = status.restart.expect("restart details");
+    assert_eq!(restart.interrupted, vec![reply.turn_id.unwrap()]);
+    let summary = restart.summary.expect("status checked after reconnecting");
+    assert!(summary.ready);
+    assert_eq!(summary.documents, Some(3));
+    assert!(
+        status_rx.try_recv().is_err(),
+        "one status for the whole restart"
+    );
+
+    assert!(is_connected());
+    assert_eq!(do_server_status().unwrap().status, "ready");
+
+    do_disconnect();
+    do_clear_history();
+}
@@ -0,0 +1,56 @@
+//! Integration test for the connection panel's server status query.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_server_status};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server that answers one status request.
+fn spawn_status_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let request = ws.next().await.unwrap().unwrap();
+            assert_eq!(request.to_text().unwrap(), r#"{"type":"status"}"#);
+            ws.send(Message::Text(
+                r#"{"type":"status","status":"ready","message":"Server ready","documents":42}"#
+                    .into(),
+            ))
+            .await
+            .unwrap();
+            let _ = ws.next().await;
+        });
+    })
+}
+
+#[test]
+fn server_status_reports_index_state() {
+    assert_eq!(do_server_status().unwrap_err(), "Not connected");
+
+    let port = free_port();
+    let _server = spawn_status_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    let status = do_server_status().expect("status should succeed");
+    assert!(status.is_ready());
+    assert_eq!(status.documents, Some(42));
+    assert_eq!(status.message.as_deref(), Some("Server ready"));
+}
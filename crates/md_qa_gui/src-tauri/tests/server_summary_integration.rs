@@ -0,0 +1,69 @@
+//! Integration test for the status bar summary kept by the background status poll.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_server_summary};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn server_summary_reports_polled_status() {
+    let port = free_port();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            while let Some(Ok(message)) = ws.next().await {
+                if message.is_text() {
+                    ws.send(Message::Text(
+                        r#"{"type":"status","status":"ready","documents":7}"#.into(),
+                    ))
+                    .await
+                    .unwrap();
+                }
+            }
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(&config_path, "client:\n  status_interval: 1\n").unwrap();
+    std::env::set_var("MD_QA_CONFIG", &config_path);
+
+    assert_eq!(do_server_summary(), None);
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+
+    // The first check runs one `client.status_interval` after connecting.
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(3);
+    let summary = loop {
+        if let Some(summary) = do_server_summary() {
+            break summary;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "no status summary yet"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    };
+    assert!(summary.ready);
+    assert_eq!(summary.documents, Some(7));
+    assert_eq!(summary.index_age_secs, None);
+
+    do_disconnect();
+    assert_eq!(do_server_summary(), None);
+}
@@ -0,0 +1,89 @@
+//! Integration test for session recording and replay: a session recorded
+//! against a real server replays without it, against the recorded replies.
+//! Kept in its own test binary because the GUI holds a single global
+//! connection, conversation history and recorder.
+
+use md_qa_client::messages::{QueryMessage, ServerMessage};
+use md_qa_client::FrameDirection;
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_export_as, do_send_query_message};
+use md_qa_gui_lib::recorder::{self, EntryKind};
+use md_qa_gui_lib::replay;
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn recorded_session_replays_without_the_server() {
+    let port = free_port();
+    let server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let Some(Ok(Message::Text(query))) = ws.next().await else {
+                panic!("expected the query");
+            };
+            let id = serde_json::from_str::<serde_json::Value>(&query).unwrap()["id"]
+                .as_u64()
+                .unwrap();
+            let end = ServerMessage::stream_end(["/a.md"]);
+            for message in ServerMessage::stream(["Port 8765."], end) {
+                let frame = message.reply_to(id).to_text();
+                ws.send(Message::Text(frame)).await.unwrap();
+            }
+            let _ = ws.next().await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let dir = tempfile::tempdir().unwrap();
+    let recording = dir.path().join("session.jsonl");
+    recorder::start(&recording).unwrap();
+    let url = format!("ws://127.0.0.1:{}", port);
+    let args = serde_json::json!({ "url": url });
+    recorder::command("connect_server", args, do_connect(&url)).unwrap();
+    let question = "Which port?";
+    let reply = do_send_query_message(&QueryMessage::new(question, None), |_| {});
+    let args = serde_json::json!({ "question": question });
+    let reply = recorder::command("send_query", args, reply).unwrap();
+    assert_eq!(reply.answer, "Port 8765.");
+    let args = serde_json::json!({ "format": "snippet" });
+    recorder::command("export_as", args, do_export_as("snippet")).unwrap();
+    recorder::command("get_config_path", serde_json::json!({}), Ok("/x")).unwrap();
+    recorder::stop();
+    do_disconnect();
+    server.join().unwrap();
+
+    let entries = recorder::load(&recording).unwrap();
+    assert!(entries.windows(2).all(|w| w[0].at_ms <= w[1].at_ms));
+    let frames: Vec<_> = entries
+        .iter()
+        .filter_map(|entry| match &entry.kind {
+            EntryKind::Frame { direction, text } => Some((*direction, text.as_str())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(frames.len(), 4, "{frames:?}");
+    assert_eq!(frames[0].0, FrameDirection::Sent);
+    assert!(frames[0].1.contains("Which port?"));
+    assert!(frames[1..]
+        .iter()
+        .all(|(d, _)| *d == FrameDirection::Received));
+
+    // The original server is gone; the replay answers from the recording.
+    let report = replay::replay(&entries).unwrap();
+    assert_eq!(report.replayed, 3);
+    assert_eq!(report.skipped, ["get_config_path"]);
+    assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+}
@@ -0,0 +1,107 @@
+//! Integration test for the startup self-check checklist.
+//! Kept in its own test binary because the GUI holds a single global
+//! connection and the check reads the config named by `MD_QA_CONFIG`.
+
+use md_qa_gui_lib::commands::{do_startup_check, CheckStep};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Spawn a test server whose index is ready.
+fn spawn_ready_server(port: u16) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            while let Some(Ok(message)) = ws.next().await {
+                if message.is_text() {
+                    ws.send(Message::Text(
+                        r#"{"type":"status","status":"ready","documents":3}"#.into(),
+                    ))
+                    .await
+                    .unwrap();
+                }
+            }
+        });
+    })
+}
+
+fn states(steps: &[CheckStep]) -> Vec<(&str, &str)> {
+    steps
+        .iter()
+        .map(|s| (s.step.as_str(), s.state.as_str()))
+        .collect()
+}
+
+#[test]
+fn startup_check_reports_the_failing_step() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::env::set_var("MD_QA_CONFIG", &config_path);
+
+    let steps = do_startup_check();
+    assert_eq!(
+        states(&steps),
+        [
+            ("config", "failed"),
+            ("directories", "skipped"),
+            ("server", "skipped"),
+            ("index", "skipped"),
+        ]
+    );
+    assert!(steps[0].message.as_deref().unwrap().contains("config.yaml"));
+
+    let port = free_port();
+    let notes = dir.path().join("notes");
+    let config = |notes: &std::path::Path| {
+        format!(
+            "server:\n  port: {}\n  directories:\n    - {}\n  index_name: a\n  index_name: b\n",
+            port,
+            notes.display()
+        )
+    };
+    std::fs::write(&config_path, config(&notes)).unwrap();
+    let steps = do_startup_check();
+    assert_eq!(
+        states(&steps),
+        [
+            ("config", "ok"),
+            ("directories", "failed"),
+            ("server", "skipped"),
+            ("index", "skipped"),
+        ]
+    );
+    assert!(steps[1].message.as_deref().unwrap().contains("notes"));
+    assert_eq!(
+        steps[0].message.as_deref(),
+        Some("duplicate key `server.index_name`; the last value is used")
+    );
+
+    std::fs::create_dir(&notes).unwrap();
+    let _server = spawn_ready_server(port);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    let steps = do_startup_check();
+    assert_eq!(
+        states(&steps),
+        [
+            ("config", "ok"),
+            ("directories", "ok"),
+            ("server", "ok"),
+            ("index", "ok"),
+        ]
+    );
+    assert_eq!(steps[3].message.as_deref(), Some("3 documents"));
+}
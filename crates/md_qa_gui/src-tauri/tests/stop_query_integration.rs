@@ -0,0 +1,81 @@
+//! Integration test for the stop button: `do_stop_query` sends a cancel for
+//! the running query and returns the partial answer, and the connection
+//! stays usable for the next question.
+
+use md_qa_client::messages::QueryMessage;
+use md_qa_gui_lib::commands::{
+    do_connect, do_disconnect, do_send_query, do_send_query_message, do_stop_query, STOPPED,
+};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn stop_cancels_the_running_query() {
+    let port = free_port();
+    let (cancel_tx, cancel_rx) = std::sync::mpsc::channel();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let query = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            let id = serde_json::from_str::<serde_json::Value>(&query).unwrap()["id"].clone();
+            let chunk = serde_json::json!({"type": "stream_chunk", "id": id, "chunk": "Partial"});
+            ws.send(Message::Text(chunk.to_string())).await.unwrap();
+            let cancel = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            cancel_tx.send((id.clone(), cancel)).unwrap();
+
+            // The reply to the cancel arrives after the next query was sent.
+            let next = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            let next_id = serde_json::from_str::<serde_json::Value>(&next).unwrap()["id"].clone();
+            for frame in [
+                serde_json::json!({"type": "error", "id": id, "message": "Query cancelled"}),
+                serde_json::json!({"type": "stream_chunk", "id": next_id, "chunk": "Done"}),
+                serde_json::json!({"type": "stream_end", "id": next_id, "sources": []}),
+            ] {
+                ws.send(Message::Text(frame.to_string())).await.unwrap();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    let (chunk_tx, chunk_rx) = std::sync::mpsc::channel();
+    let query = std::thread::spawn(move || {
+        do_send_query_message(&QueryMessage::new("A long question?", None), |chunk| {
+            let _ = chunk_tx.send(chunk.chunk.clone());
+        })
+    });
+    assert_eq!(chunk_rx.recv().unwrap(), "Partial");
+    do_stop_query();
+
+    let reply = query.join().unwrap().expect("stopped query should return");
+    assert_eq!(reply.answer, "Partial");
+    assert_eq!(reply.error.as_deref(), Some(STOPPED));
+    assert!(reply.turn_id.is_none());
+    let (id, cancel) = cancel_rx.recv().unwrap();
+    let cancel: serde_json::Value = serde_json::from_str(&cancel).unwrap();
+    assert_eq!(
+        cancel,
+        serde_json::json!({"type": "cancel", "request_id": id})
+    );
+
+    let reply = do_send_query("Next?", None).unwrap();
+    assert_eq!(reply.answer, "Done");
+    assert!(reply.error.is_none());
+
+    do_disconnect();
+}
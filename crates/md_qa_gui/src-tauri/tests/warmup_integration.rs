@@ -0,0 +1,71 @@
+//! Integration test for the warm-up retrieval sent after connecting when the
+//! config sets `server.warmup`, and its timing in the connection status.
+//! Kept in its own test binary because the GUI holds a single global connection.
+
+use md_qa_gui_lib::commands::{do_connect, do_disconnect, do_watch_connection};
+
+fn free_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+#[test]
+fn connect_warms_up_the_server() {
+    let port = free_port();
+    let (request_tx, request_rx) = std::sync::mpsc::channel();
+    let _server = std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            use futures_util::{SinkExt, StreamExt};
+            use tokio_tungstenite::tungstenite::Message;
+
+            let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+                .await
+                .unwrap();
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                request_tx.send(text.to_string()).unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                ws.send(Message::Text(
+                    r#"{"type":"status","status":"ready","message":"Warm-up complete"}"#.into(),
+                ))
+                .await
+                .unwrap();
+            }
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(&config_path, "server:\n  warmup: true\n").unwrap();
+    std::env::set_var("MD_QA_CONFIG", &config_path);
+
+    let status = do_connect(&format!("ws://127.0.0.1:{}", port)).unwrap();
+    assert_eq!(status.state, "connected");
+    let (status_tx, status_rx) = std::sync::mpsc::channel();
+    do_watch_connection(move |status| {
+        let _ = status_tx.send(status);
+    })
+    .unwrap();
+
+    let request = request_rx
+        .recv_timeout(std::time::Duration::from_secs(2))
+        .expect("connect should send a warm-up");
+    assert_eq!(request, r#"{"type":"warmup"}"#);
+    let status = status_rx
+        .recv_timeout(std::time::Duration::from_secs(2))
+        .expect("watcher should see the warm-up");
+    assert_eq!(status.state, "connected");
+    let message = status.message.unwrap();
+    assert!(
+        message.starts_with("warmed up in ") && message.ends_with(" ms"),
+        "{}",
+        message
+    );
+    do_disconnect();
+}
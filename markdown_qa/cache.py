@@ -3,18 +3,25 @@
 from pathlib import Path
 from typing import Optional
 
+from markdown_qa.encryption import IndexCipher
+
 
 class CacheManager:
     """Manages centralized cache directory for indexes and embeddings."""
 
     DEFAULT_CACHE_DIR = Path.home() / ".md-qa" / "cache"
 
-    def __init__(self, cache_dir: Optional[Path] = None):
+    def __init__(self, cache_dir: Optional[Path] = None, encryption: str = "off"):
         """
         Initialize cache manager.
 
         Args:
             cache_dir: Custom cache directory. If None, uses default.
+            encryption: ``passphrase`` or ``keyring`` to keep the indexes and
+                embeddings encrypted (server.encryption), ``off`` not to.
+
+        Raises:
+            ValueError: If the encryption key is missing or not the cache's.
         """
         self.cache_dir = cache_dir or self.DEFAULT_CACHE_DIR
         self.cache_dir.mkdir(parents=True, exist_ok=True)
@@ -25,6 +32,9 @@ class CacheManager:
         self.index_dir.mkdir(exist_ok=True)
         self.embedding_dir.mkdir(exist_ok=True)
 
+        # Encrypts the files of the indexes and embeddings, or None
+        self.cipher = IndexCipher.for_workspace(encryption, self.cache_dir)
+
     def get_index_path(self, index_name: str) -> tuple[Path, Path]:
         """
         Get paths for an index (FAISS and metadata files).
@@ -39,6 +49,18 @@ class CacheManager:
         metadata_path = self.index_dir / f"{index_name}.pkl"
         return faiss_path, metadata_path
 
+    def get_ann_path(self, index_name: str) -> Path:
+        """
+        Get the path of an index's HNSW graph, saved with retrieval.ann: hnsw.
+
+        Args:
+            index_name: Name of the index.
+
+        Returns:
+            Path of the graph file.
+        """
+        return self.index_dir / f"{index_name}.hnsw"
+
     def index_exists(self, index_name: str) -> bool:
         """
         Check if an index exists.
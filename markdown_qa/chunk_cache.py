@@ -0,0 +1,101 @@
+"""In-memory LRU of recently retrieved chunks and their neighbors."""
+
+import threading
+from collections import OrderedDict
+from typing import Any, Dict, Optional, Tuple
+
+# Chunks kept, unless configured
+DEFAULT_CHUNK_CACHE_SIZE = 256
+
+# Chunk IDs hold the chunk's index within its file in the low 16 bits (see
+# loader.generate_chunk_id), so a chunk's neighbors are the IDs next to it.
+_INDEX_MASK = 0xFFFF
+
+
+def neighbor_ids(chunk_id: int) -> Tuple[int, ...]:
+    """IDs of the chunks before and after ``chunk_id`` in the same file."""
+    index = chunk_id & _INDEX_MASK
+    neighbors = []
+    if index > 0:
+        neighbors.append(chunk_id - 1)
+    if index < _INDEX_MASK:
+        neighbors.append(chunk_id + 1)
+    return tuple(neighbors)
+
+
+class ChunkCache:
+    """
+    Least recently used (text, metadata) of chunks by chunk ID, so follow-up
+    questions about the same documents are answered from memory. When a
+    chunk is looked up and missed, it is added with its neighbors in the
+    same file, which follow-ups often retrieve next.
+
+    Shared by the queries of one index and safe to use from several
+    threads; cleared whenever the index changes.
+    """
+
+    def __init__(self, capacity: int = DEFAULT_CHUNK_CACHE_SIZE):
+        """
+        Initialize chunk cache.
+
+        Args:
+            capacity: Most chunks to keep.
+        """
+        self.capacity = capacity
+        self._chunks: "OrderedDict[int, Tuple[str, Dict[str, Any]]]" = OrderedDict()
+        self._lock = threading.Lock()
+        self.hits = 0
+        self.misses = 0
+
+    def get(self, chunk_id: int) -> Optional[Tuple[str, Dict[str, Any]]]:
+        """
+        Look up a chunk, counting a hit or a miss.
+
+        Returns:
+            The chunk's (text, metadata), or None if it is not cached.
+        """
+        with self._lock:
+            chunk = self._chunks.get(chunk_id)
+            if chunk is None:
+                self.misses += 1
+                return None
+            self._chunks.move_to_end(chunk_id)
+            self.hits += 1
+            return chunk
+
+    def put(self, chunk_id: int, text: str, metadata: Dict[str, Any]) -> None:
+        """Add a chunk, dropping the least recently used one when full."""
+        with self._lock:
+            self._chunks[chunk_id] = (text, metadata)
+            self._chunks.move_to_end(chunk_id)
+            while len(self._chunks) > self.capacity:
+                self._chunks.popitem(last=False)
+
+    def __contains__(self, chunk_id: object) -> bool:
+        """Whether a chunk is cached, without counting a lookup."""
+        with self._lock:
+            return chunk_id in self._chunks
+
+    def clear(self) -> None:
+        """Drop every chunk, e.g. when the index changes. Counts are kept."""
+        with self._lock:
+            self._chunks.clear()
+
+    def stats(self) -> Dict[str, Any]:
+        """
+        Summarize the cache for index stats.
+
+        Returns:
+            ``size`` and ``capacity`` in chunks, ``hits`` and ``misses``
+            since the server started, and ``hit_rate`` (0-1, or None before
+            the first lookup).
+        """
+        with self._lock:
+            lookups = self.hits + self.misses
+            return {
+                "size": len(self._chunks),
+                "capacity": self.capacity,
+                "hits": self.hits,
+                "misses": self.misses,
+                "hit_rate": round(self.hits / lookups, 4) if lookups else None,
+            }
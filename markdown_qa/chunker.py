@@ -1,29 +1,295 @@
 """Text chunking module using LangChain's MarkdownTextSplitter with metadata preservation."""
 
+import math
+import re
+from dataclasses import dataclass, field, fields, replace
 from pathlib import Path
-from typing import Any, Dict, List, Tuple
+from typing import Any, Dict, Iterable, List, Optional, Tuple
 
-from langchain_text_splitters import MarkdownTextSplitter
+import yaml
+from langchain_text_splitters import MarkdownTextSplitter, RecursiveCharacterTextSplitter
+
+from markdown_qa.links import extract_links
+
+# Chunk size and overlap, in estimated tokens, when retrieval.chunking sets
+# only one of them (about the 1000/200 characters of English text used
+# otherwise).
+DEFAULT_TARGET_TOKENS = 250
+DEFAULT_OVERLAP_TOKENS = 50
+
+# Characters that are about one token each: CJK ideographs and punctuation,
+# kana, Hangul and full-width forms.
+_ONE_TOKEN_CHARACTER = re.compile(
+    "[\u3000-\u30ff\u3400-\u4dbf\u4e00-\u9fff\uac00-\ud7af\uff00-\uffef]"
+)
+
+# A fenced code block: its language tag and its whole text, fences included.
+# Unclosed fences are left in the prose.
+_CODE_BLOCK = re.compile(
+    r"^(?P<fence>`{3,}|~{3,})[ \t]*(?P<language>[^\s`{]*)[^\n]*\n.*?^(?P=fence)[ \t]*$\n?",
+    re.MULTILINE | re.DOTALL,
+)
+
+# A markdown heading line
+_HEADING = re.compile(r"^#{1,6}[ \t]+(.+?)[ \t#]*$", re.MULTILINE)
+
+
+def split_code_blocks(content: str) -> Tuple[str, List[Tuple[str, str, str]]]:
+    """
+    Take the fenced code blocks out of a markdown text.
+
+    Args:
+        content: Markdown text.
+
+    Returns:
+        Tuple of (prose, blocks): the text without its code blocks, and for
+        each block in order its (language, text, section), where language
+        is the fence's tag lowercased ("" if untagged), text is the block
+        with its fences, and section is the last heading before it ("" if
+        none).
+    """
+    blocks = []
+    for match in _CODE_BLOCK.finditer(content):
+        headings = _HEADING.findall(content, 0, match.start())
+        blocks.append(
+            (
+                match.group("language").lower(),
+                match.group(0).rstrip("\n"),
+                headings[-1].strip() if headings else "",
+            )
+        )
+    if not blocks:
+        return content, []
+    return _CODE_BLOCK.sub("", content), blocks
+
+
+def estimate_tokens(text: str) -> int:
+    """
+    Estimate how many tokens a text is, without a tokenizer.
+
+    CJK characters count as one token each and other text as one token per
+    four characters, so a token target gives CJK documents chunks with
+    about as much content as English ones.
+
+    Args:
+        text: Text to measure.
+
+    Returns:
+        Estimated token count.
+    """
+    cjk = len(_ONE_TOKEN_CHARACTER.findall(text))
+    return cjk + math.ceil((len(text) - cjk) / 4)
+
+
+@dataclass(frozen=True)
+class ChunkSettings:
+    """Chunking parameters of retrieval.chunking; None means not set."""
+
+    target_tokens: Optional[int] = None
+    overlap_tokens: Optional[int] = None
+    # Split at headings of this level or higher (1-6) before anything else
+    heading_level: Optional[int] = None
+
+    @classmethod
+    def from_dict(cls, data: Any, where: str) -> "ChunkSettings":
+        """
+        Read chunking parameters from a config mapping.
+
+        Args:
+            data: Mapping with ``target_tokens``, ``overlap_tokens`` and
+                ``heading_level``, all optional.
+            where: Config path of the mapping, for error messages.
+
+        Returns:
+            The parameters.
+
+        Raises:
+            ValueError: If a value is not a valid integer for its key.
+        """
+        if not isinstance(data, dict):
+            raise ValueError(f"{where} must be a mapping")
+        values: Dict[str, int] = {}
+        for setting in fields(cls):
+            value = data.get(setting.name)
+            if value is None:
+                continue
+            low = 0 if setting.name == "overlap_tokens" else 1
+            high = 6 if setting.name == "heading_level" else None
+            if (
+                not isinstance(value, int)
+                or isinstance(value, bool)
+                or value < low
+                or (high is not None and value > high)
+            ):
+                bounds = f"between {low} and {high}" if high else f"at least {low}"
+                raise ValueError(f"{where}.{setting.name} must be an integer {bounds}")
+            values[setting.name] = value
+        settings = cls(**values)
+        settings.check_sizes(where)
+        return settings
+
+    def check_sizes(self, where: str) -> None:
+        """
+        Check that chunks overlap by less than their size.
+
+        Raises:
+            ValueError: If ``overlap_tokens`` is not less than the target.
+        """
+        target, overlap = self.token_sizes()
+        if overlap >= target:
+            raise ValueError(f"{where}.overlap_tokens must be less than target_tokens")
+
+    def token_sizes(self) -> Tuple[int, int]:
+        """Chunk size and overlap in estimated tokens, with defaults filled in."""
+        target = self.target_tokens or DEFAULT_TARGET_TOKENS
+        overlap = self.overlap_tokens
+        if overlap is None:
+            overlap = min(DEFAULT_OVERLAP_TOKENS, target // 5)
+        return target, overlap
+
+    def merged(self, override: "ChunkSettings") -> "ChunkSettings":
+        """These settings with the ones ``override`` sets replacing them."""
+        changes = {
+            f.name: getattr(override, f.name)
+            for f in fields(override)
+            if getattr(override, f.name) is not None
+        }
+        return replace(self, **changes)
+
+
+@dataclass(frozen=True)
+class ChunkingConfig:
+    """
+    The retrieval.chunking config section: chunking parameters, and the
+    ones that differ for files under some directories (e.g. CJK documents
+    or API reference pages).
+    """
+
+    defaults: ChunkSettings = field(default_factory=ChunkSettings)
+    directories: Tuple[Tuple[Path, ChunkSettings], ...] = ()
+
+    @classmethod
+    def from_dict(cls, data: Any) -> "ChunkingConfig":
+        """
+        Read the retrieval.chunking section.
+
+        Args:
+            data: The section: chunking parameters and an optional
+                ``directories`` mapping from a directory to the parameters
+                that differ for files under it. None means not set.
+
+        Returns:
+            The chunking config.
+
+        Raises:
+            ValueError: If the section or a value in it is invalid.
+        """
+        if data is None:
+            return cls()
+        defaults = ChunkSettings.from_dict(data, "retrieval.chunking")
+        overrides = data.get("directories") or {}
+        if not isinstance(overrides, dict):
+            raise ValueError("retrieval.chunking.directories must be a mapping")
+        directories = []
+        for directory, settings in overrides.items():
+            where = f"retrieval.chunking.directories.{directory}"
+            merged = defaults.merged(ChunkSettings.from_dict(settings, where))
+            merged.check_sizes(where)
+            directories.append((Path(str(directory)).expanduser().resolve(), merged))
+        # Most specific directory first
+        directories.sort(key=lambda entry: len(entry[0].parts), reverse=True)
+        return cls(defaults, tuple(directories))
+
+    def settings_for(self, file_path: Path) -> ChunkSettings:
+        """
+        The chunking parameters of a file: those of the innermost directory
+        override it is under, else the section's own.
+
+        Args:
+            file_path: Markdown file being chunked.
+
+        Returns:
+            Its chunking parameters.
+        """
+        if self.directories:
+            resolved = Path(file_path).resolve()
+            for directory, settings in self.directories:
+                if resolved.is_relative_to(directory):
+                    return settings
+        return self.defaults
 
 
 class MarkdownChunker:
     """Chunks markdown content while preserving structural metadata."""
 
-    def __init__(self, chunk_size: int = 1000, chunk_overlap: int = 200):
+    def __init__(
+        self,
+        chunk_size: int = 1000,
+        chunk_overlap: int = 200,
+        include_drafts: bool = False,
+        chunking: Optional[ChunkingConfig] = None,
+    ):
         """
         Initialize the markdown chunker.
 
         Args:
             chunk_size: Maximum size of each chunk in characters (default: 1000).
             chunk_overlap: Overlap between adjacent chunks in characters (default: 200).
+            include_drafts: Chunk files whose front matter sets ``draft: true``
+                (default: False, they are skipped by chunk_files).
+            chunking: The retrieval.chunking config section. Files it sets
+                no parameters for use ``chunk_size`` and ``chunk_overlap``.
         """
         self.splitter = MarkdownTextSplitter(
             chunk_size=chunk_size,
             chunk_overlap=chunk_overlap,
         )
+        self.include_drafts = include_drafts
+        self.chunking = chunking or ChunkingConfig()
+        self._chunk_size = chunk_size
+        self._chunk_overlap = chunk_overlap
+        self._splitters: Dict[ChunkSettings, Any] = {ChunkSettings(): self.splitter}
+
+    def _splitter_for(self, file_path: Path) -> Any:
+        """The text splitter for a file's chunking parameters."""
+        settings = self.chunking.settings_for(file_path)
+        splitter = self._splitters.get(settings)
+        if splitter is not None:
+            return splitter
+        kwargs: Dict[str, Any] = {
+            "chunk_size": self._chunk_size,
+            "chunk_overlap": self._chunk_overlap,
+        }
+        if settings.target_tokens is not None or settings.overlap_tokens is not None:
+            target, overlap = settings.token_sizes()
+            kwargs = {
+                "chunk_size": target,
+                "chunk_overlap": overlap,
+                "length_function": estimate_tokens,
+            }
+        if settings.heading_level is None:
+            splitter = MarkdownTextSplitter(**kwargs)
+        else:
+            splitter = RecursiveCharacterTextSplitter(
+                separators=[
+                    f"\n#{{1,{settings.heading_level}}} ",
+                    "\n\n",
+                    "\n",
+                    " ",
+                    "",
+                ],
+                is_separator_regex=True,
+                **kwargs,
+            )
+        self._splitters[settings] = splitter
+        return splitter
 
     def chunk_file(
-        self, file_path: Path, content: str
+        self,
+        file_path: Path,
+        content: str,
+        tags: Optional[List[str]] = None,
+        details: Optional[Dict[str, str]] = None,
     ) -> List[Dict[str, Any]]:
         """
         Chunk a markdown file while preserving metadata.
@@ -31,20 +297,43 @@ class MarkdownChunker:
         Args:
             file_path: Path to the markdown file.
             content: Content of the markdown file.
+            tags: Front matter tags of the file, when ``content`` is a later
+                segment of it. If None, read from ``content``.
+            details: Front matter title and date of the file, likewise.
+
+        Fenced code blocks are chunked on their own, after the prose, so
+        questions about code identifiers can match them directly.
 
         Returns:
             List of dictionaries, each containing:
             - 'text': The chunk text content
-            - 'metadata': Dictionary with 'file_path' and 'section' information
+            - 'metadata': Dictionary with 'file_path' and 'section' information,
+              plus 'tags', 'title' and 'date' when the file's front matter
+              sets them, and the chunk's relative 'links' to other markdown
+              files and 'wikilinks' targets when it has any (see
+              links.extract_links). Chunks of code blocks have
+              'code_language' instead of links: the fence's language tag,
+              or "" if it has none.
         """
+        if tags is None:
+            tags = self._extract_front_matter_tags(content)
+        if details is None:
+            details = self._extract_front_matter_details(content)
+
+        prose, blocks = split_code_blocks(content)
+
         # Split the markdown content
-        chunks = self.splitter.create_documents([content])
+        splitter = self._splitter_for(file_path)
+        chunks = splitter.create_documents([prose]) if prose.strip() else []
 
         # Extract metadata from chunks and add file path
         result = []
         for chunk in chunks:
             metadata = chunk.metadata.copy()
             metadata["file_path"] = str(file_path)
+            if tags:
+                metadata["tags"] = tags
+            metadata.update(details)
 
             # Extract section information from metadata if available
             # LangChain's MarkdownTextSplitter may include section headers in metadata
@@ -53,6 +342,12 @@ class MarkdownChunker:
                 # This is a fallback - LangChain may already provide this
                 metadata["section"] = self._extract_section_from_chunk(chunk.page_content)
 
+            links, wikilinks = extract_links(chunk.page_content, file_path)
+            if links:
+                metadata["links"] = links
+            if wikilinks:
+                metadata["wikilinks"] = wikilinks
+
             result.append(
                 {
                     "text": chunk.page_content,
@@ -60,8 +355,86 @@ class MarkdownChunker:
                 }
             )
 
+        for language, block, section in blocks:
+            # A block longer than a chunk is split like any text
+            for chunk in splitter.create_documents([block]):
+                metadata = chunk.metadata.copy()
+                metadata["file_path"] = str(file_path)
+                if tags:
+                    metadata["tags"] = tags
+                metadata.update(details)
+                metadata["section"] = section
+                metadata["code_language"] = language
+                result.append({"text": chunk.page_content, "metadata": metadata})
+
         return result
 
+    def _read_front_matter(self, content: str) -> Dict[str, Any]:
+        """
+        Read the YAML front matter block at the start of a markdown file.
+
+        Args:
+            content: Content of the markdown file.
+
+        Returns:
+            The front matter mapping, or an empty dict if the file has none or
+            it is not a valid YAML mapping.
+        """
+        if not content.startswith("---"):
+            return {}
+        end = content.find("\n---", 3)
+        if end == -1:
+            return {}
+        try:
+            front_matter = yaml.safe_load(content[3:end])
+        except yaml.YAMLError:
+            return {}
+        if not isinstance(front_matter, dict):
+            return {}
+        return front_matter
+
+    def _is_draft(self, content: str) -> bool:
+        """Whether a file's front matter sets ``draft: true``."""
+        return self._read_front_matter(content).get("draft") is True
+
+    def _extract_front_matter_details(self, content: str) -> Dict[str, str]:
+        """
+        Extract the title and date of a YAML front matter block.
+
+        Args:
+            content: Content of the markdown file.
+
+        Returns:
+            ``title`` and ``date`` (as written, e.g. ``2024-05-01``) for those
+            the front matter sets, as strings.
+        """
+        front_matter = self._read_front_matter(content)
+        details = {}
+        for key in ("title", "date"):
+            value = front_matter.get(key)
+            if value is not None and str(value).strip():
+                details[key] = str(value).strip()
+        return details
+
+    def _extract_front_matter_tags(self, content: str) -> List[str]:
+        """
+        Extract the tags listed in a YAML front matter block.
+
+        Args:
+            content: Content of the markdown file.
+
+        Returns:
+            Tags from a ``tags`` list or comma-separated string, or an empty
+            list if the file has no front matter or no tags.
+        """
+        tags = self._read_front_matter(content).get("tags")
+        if isinstance(tags, str):
+            tags = tags.split(",")
+        if not isinstance(tags, list):
+            return []
+        tags = [str(tag).strip() for tag in tags if tag is not None]
+        return [tag for tag in tags if tag]
+
     def _extract_section_from_chunk(self, chunk_text: str) -> str:
         """
         Extract section header from chunk text if available.
@@ -81,19 +454,36 @@ class MarkdownChunker:
         return ""
 
     def chunk_files(
-        self, files: List[Tuple[Path, str]]
+        self, files: Iterable[Tuple[Path, str]]
     ) -> List[Dict[str, Any]]:
         """
         Chunk multiple markdown files.
 
+        Consecutive entries with the same path are segments of one large file
+        (see loader.read_segments); they share the first segment's front
+        matter. Drafts (``draft: true``) are skipped unless ``include_drafts``
+        is set.
+
         Args:
-            files: List of tuples containing (file_path, content).
+            files: Tuples containing (file_path, content), e.g. a generator
+                reading one segment at a time.
 
         Returns:
             List of all chunks from all files with metadata.
         """
         all_chunks = []
+        previous_path: Optional[Path] = None
+        tags: List[str] = []
+        details: Dict[str, str] = {}
+        skipped = False
         for file_path, content in files:
-            chunks = self.chunk_file(file_path, content)
+            if file_path != previous_path:
+                previous_path = file_path
+                skipped = not self.include_drafts and self._is_draft(content)
+                tags = self._extract_front_matter_tags(content)
+                details = self._extract_front_matter_details(content)
+            if skipped:
+                continue
+            chunks = self.chunk_file(file_path, content, tags=tags, details=details)
             all_chunks.extend(chunks)
         return all_chunks
@@ -234,8 +234,9 @@ class MarkdownQAClient:
                 response = json.loads(response_text)
                 msg_type = response.get("type")
 
-                if msg_type == MessageType.STREAM_START:
-                    # Stream starting, nothing to display yet
+                if msg_type in (MessageType.STATUS, MessageType.STREAM_START):
+                    # Progress before the answer (e.g. "retrieving") or the
+                    # stream starting, nothing to display yet
                     continue
                 elif msg_type == MessageType.STREAM_CHUNK:
                     # Print chunk immediately without newline
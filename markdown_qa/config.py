@@ -6,6 +6,9 @@ from typing import Optional
 
 import yaml
 
+from markdown_qa.local_embeddings import local_model_name
+from markdown_qa.providers import PROVIDERS
+
 try:
     import tomli  # type: ignore[import-not-found]
 except ImportError:
@@ -19,6 +22,17 @@ class APIConfig:
     DEFAULT_CONFIG_YAML = DEFAULT_CONFIG_DIR / "config.yaml"
     DEFAULT_CONFIG_TOML = DEFAULT_CONFIG_DIR / "config.toml"
 
+    # Embedding request settings and whether each must be a whole number;
+    # each can be set in the api section or as MARKDOWN_QA_<NAME>
+    EMBEDDING_SETTINGS = {
+        "embedding_batch_size": True,
+        "embedding_concurrency": True,
+        "embedding_attempts": True,
+        "embedding_max_backoff": False,
+        "max_concurrent_requests": True,
+        "requests_per_minute": True,
+    }
+
     def __init__(self, config_file: Optional[Path] = None):
         """
         Initialize API configuration.
@@ -31,6 +45,24 @@ class APIConfig:
         self.api_key: Optional[str] = None
         self.embedding_model: Optional[str] = None
         self.llm_model: Optional[str] = None
+        # Who generates answers: an OpenAI-compatible API (base_url), or
+        # Anthropic or Ollama at llm_base_url with llm_api_key. Embeddings
+        # always use base_url.
+        self.provider: Optional[str] = None
+        self.llm_base_url: Optional[str] = None
+        self.llm_api_key: Optional[str] = None
+        # Embedding requests while indexing: texts per request, requests in
+        # flight at once, attempts per request and the longest wait between
+        # attempts, in seconds. None means the embedding generator's default.
+        self.embedding_batch_size: Optional[int] = None
+        self.embedding_concurrency: Optional[int] = None
+        self.embedding_attempts: Optional[int] = None
+        self.embedding_max_backoff: Optional[float] = None
+        # Limits of the embedding API at base_url, for every request the
+        # server sends it at once: requests in flight and requests started a
+        # minute. None means no limit.
+        self.max_concurrent_requests: Optional[int] = None
+        self.requests_per_minute: Optional[int] = None
 
         # Try to load from config file first
         if config_file:
@@ -51,6 +83,17 @@ class APIConfig:
             self.embedding_model = os.environ.get("MARKDOWN_QA_EMBEDDING_MODEL")
         if not self.llm_model:
             self.llm_model = os.environ.get("MARKDOWN_QA_LLM_MODEL")
+        if not self.provider:
+            self.provider = os.environ.get("MARKDOWN_QA_LLM_PROVIDER")
+        if not self.llm_base_url:
+            self.llm_base_url = os.environ.get("MARKDOWN_QA_LLM_BASE_URL")
+        if not self.llm_api_key:
+            self.llm_api_key = os.environ.get("MARKDOWN_QA_LLM_API_KEY")
+        for setting in self.EMBEDDING_SETTINGS:
+            env_value = os.environ.get(f"MARKDOWN_QA_{setting.upper()}")
+            if getattr(self, setting) is None and env_value:
+                setattr(self, setting, env_value)
+        self._validate_embedding_settings()
 
         # Set default embedding model if not specified
         if not self.embedding_model:
@@ -60,14 +103,46 @@ class APIConfig:
         if not self.llm_model:
             self.llm_model = "qwen-flash"
 
+        self.provider = (self.provider or "openai").lower()
+        if self.provider not in PROVIDERS:
+            raise ValueError(
+                f"Unknown api.provider: {self.provider} (expected one of {', '.join(PROVIDERS)})"
+            )
+
         # Validate that we have required configuration
-        if not self.base_url or not self.api_key:
+        if self.uses_base_url() and (not self.base_url or not self.api_key):
             raise ValueError(
                 "API configuration is missing. Please set either:\n"
                 "- Config file at ~/.md-qa/config.yaml or config.toml with 'api.base_url' and 'api.api_key'\n"
                 "- Environment variables MARKDOWN_QA_API_BASE_URL and MARKDOWN_QA_API_KEY"
             )
 
+    def uses_base_url(self) -> bool:
+        """
+        Whether base_url and api_key are needed: for embeddings, unless
+        embedding_model is a ``local:<model>``, or for answers from an
+        OpenAI-compatible API without an llm_base_url and llm_api_key of its own.
+        """
+        if local_model_name(self.embedding_model) is None:
+            return True
+        return self.provider == "openai" and not (self.llm_base_url and self.llm_api_key)
+
+    def _validate_embedding_settings(self) -> None:
+        """Check the embedding request settings, converting ones read from the environment."""
+        for setting, whole in self.EMBEDDING_SETTINGS.items():
+            value = getattr(self, setting)
+            if value is None:
+                continue
+            try:
+                number = -1.0 if isinstance(value, bool) else float(value)
+            except (TypeError, ValueError):
+                number = -1.0
+            if whole and not (1 <= number < float("inf") and number == int(number)):
+                raise ValueError(f"api.{setting} must be an integer at least 1, not {value!r}")
+            if not whole and not number >= 0:
+                raise ValueError(f"api.{setting} must be a number of seconds, 0 or more, not {value!r}")
+            setattr(self, setting, int(number) if whole else number)
+
     def _load_from_file(self, config_path: Path) -> None:
         """Load configuration from YAML or TOML file."""
         if not config_path.exists():
@@ -91,6 +166,12 @@ class APIConfig:
                 self.api_key = config["api"].get("api_key") or self.api_key
                 self.embedding_model = config["api"].get("embedding_model") or self.embedding_model
                 self.llm_model = config["api"].get("llm_model") or self.llm_model
+                self.provider = config["api"].get("provider") or self.provider
+                self.llm_base_url = config["api"].get("llm_base_url") or self.llm_base_url
+                self.llm_api_key = config["api"].get("llm_api_key") or self.llm_api_key
+                for setting in self.EMBEDDING_SETTINGS:
+                    if config["api"].get(setting) is not None:
+                        setattr(self, setting, config["api"][setting])
 
     def _load_from_toml(self, config_path: Path) -> None:
         """Load configuration from TOML file."""
@@ -100,4 +181,10 @@ class APIConfig:
                 self.base_url = config["api"].get("base_url") or self.base_url
                 self.api_key = config["api"].get("api_key") or self.api_key
                 self.embedding_model = config["api"].get("embedding_model") or self.embedding_model
-                self.llm_model = config["api"].get("llm_model") or self.llm_model
\ No newline at end of file
+                self.llm_model = config["api"].get("llm_model") or self.llm_model
+                self.provider = config["api"].get("provider") or self.provider
+                self.llm_base_url = config["api"].get("llm_base_url") or self.llm_base_url
+                self.llm_api_key = config["api"].get("llm_api_key") or self.llm_api_key
+                for setting in self.EMBEDDING_SETTINGS:
+                    if config["api"].get(setting) is not None:
+                        setattr(self, setting, config["api"][setting])
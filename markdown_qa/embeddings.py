@@ -3,23 +3,47 @@
 import hashlib
 import json
 import time
+from concurrent.futures import ThreadPoolExecutor
 from pathlib import Path
-from typing import List, Optional, Tuple
+from types import SimpleNamespace
+from typing import Any, Callable, List, Optional, Tuple
 
 from openai import OpenAI
 from tenacity import (
     retry,
     stop_after_attempt,
-    wait_exponential,
 )
 
 from markdown_qa.config import APIConfig
+from markdown_qa.encryption import IndexCipher, read_file, write_file
+from markdown_qa.local_embeddings import LocalEmbeddings, local_model_name
 from markdown_qa.logger import get_server_logger
+from markdown_qa.rate_limit import limiter_for, retry_after
 
-# Maximum number of texts to send in a single batch API call
+# Maximum number of texts to send in a single batch API call, unless
+# api.embedding_batch_size is set
 # Some APIs (e.g., Alibaba) only support batch sizes up to 10
 DEFAULT_BATCH_SIZE = 10
 
+# Batch API calls in flight at once, unless api.embedding_concurrency is set
+DEFAULT_CONCURRENCY = 1
+
+# Attempts per API call, and the shortest and longest waits between them in
+# seconds (doubling from the shortest), unless api.embedding_attempts and
+# api.embedding_max_backoff are set. A rate-limited (HTTP 429) call waits the
+# longest, or as long as its Retry-After header asks.
+DEFAULT_ATTEMPTS = 3
+MIN_BACKOFF = 2.0
+DEFAULT_MAX_BACKOFF = 10.0
+
+
+def _setting(api_config: Any, name: str, default: Any) -> Any:
+    """An embedding request setting of the API config, or its default if unset."""
+    value = getattr(api_config, name, None)
+    if isinstance(value, (int, float)) and not isinstance(value, bool):
+        return value
+    return default
+
 
 class EmbeddingGenerator:
     """Generates embeddings using OpenAI-compatible API with retry logic and caching."""
@@ -29,7 +53,8 @@ class EmbeddingGenerator:
         api_config: Optional[APIConfig] = None,
         cache_dir: Optional[Path] = None,
         embedding_model: Optional[str] = None,
-        batch_size: int = DEFAULT_BATCH_SIZE,
+        batch_size: Optional[int] = None,
+        cipher: Optional[IndexCipher] = None,
     ):
         """
         Initialize the embedding generator.
@@ -38,29 +63,84 @@ class EmbeddingGenerator:
             api_config: API configuration. If None, will create from defaults.
             cache_dir: Directory for caching embeddings. If None, uses default cache.
             embedding_model: Embedding model name. If None, uses model from api_config or default.
-            batch_size: Maximum number of texts to send in a single batch API call.
+            batch_size: Maximum number of texts to send in a single batch API
+                call. If None, api_config's embedding_batch_size or
+                DEFAULT_BATCH_SIZE.
+            cipher: Encrypts the cached embeddings (server.encryption), or None.
         """
         if api_config is None:
             api_config = APIConfig()
 
         self.api_config = api_config
-        self.client = OpenAI(
-            base_url=api_config.base_url,
-            api_key=api_config.api_key,
-        )
         # Use provided model, or from api_config, or default
         self.embedding_model = embedding_model or api_config.embedding_model or "text-embedding-3-small"
-        self.batch_size = batch_size
+        # A local:<model> runs on this machine, through the same calls as the API
+        self.local_model = local_model_name(self.embedding_model)
+        if self.local_model is not None:
+            self.client: Any = SimpleNamespace(embeddings=LocalEmbeddings(self.local_model))
+        else:
+            self.client = OpenAI(
+                base_url=api_config.base_url,
+                api_key=api_config.api_key,
+            )
+        self.batch_size = batch_size or _setting(
+            api_config, "embedding_batch_size", DEFAULT_BATCH_SIZE
+        )
+        self.concurrency = _setting(api_config, "embedding_concurrency", DEFAULT_CONCURRENCY)
+        self.attempts = _setting(api_config, "embedding_attempts", DEFAULT_ATTEMPTS)
+        self.max_backoff = _setting(api_config, "embedding_max_backoff", DEFAULT_MAX_BACKOFF)
+
+        # Requests to the embedding API from all generators, e.g. an index
+        # build's and the questions', within api.max_concurrent_requests and
+        # api.requests_per_minute; a local model has no limits
+        if self.local_model is not None:
+            self.limiter = limiter_for(self.embedding_model)
+        else:
+            max_concurrent = _setting(api_config, "max_concurrent_requests", None)
+            self.limiter = limiter_for(
+                api_config.base_url or "",
+                max_concurrent,
+                _setting(api_config, "requests_per_minute", None),
+            )
+            if max_concurrent is not None:
+                self.concurrency = min(self.concurrency, max_concurrent)
+
+        # Retry API calls with exponential backoff
+        retrying = retry(stop=stop_after_attempt(self.attempts), wait=self._backoff)
+        self._generate_embedding_with_retry = retrying(self._generate_embedding)
+        self._generate_embeddings_batch_with_retry = retrying(self._generate_embeddings_batch)
 
         # Set up cache directory
         if cache_dir is None:
             cache_dir = Path.home() / ".md-qa" / "cache" / "embeddings"
         self.cache_dir = cache_dir
         self.cache_dir.mkdir(parents=True, exist_ok=True)
+        self.cipher = cipher
         self.logger = get_server_logger()
 
+    def _backoff(self, retry_state: Any) -> float:
+        """
+        Seconds to wait before the next attempt of an API call: MIN_BACKOFF,
+        then doubling up to ``max_backoff``. After a rate-limited attempt,
+        ``max_backoff`` or as long as the API asks, if longer.
+
+        Args:
+            retry_state: The tenacity state of the failed call.
+        """
+        backoff = min(self.max_backoff, max(MIN_BACKOFF, 2.0 ** (retry_state.attempt_number - 1)))
+        outcome = retry_state.outcome
+        asked = retry_after(outcome.exception() if outcome is not None else None)
+        if asked is None:
+            return backoff
+        return max(self.max_backoff, asked)
+
     def _get_cache_key(self, text: str) -> str:
-        """Generate a cache key for a text string."""
+        """
+        Generate a cache key for a text string. A local model's embeddings
+        are cached apart from the API's, which keep their text-only keys.
+        """
+        if self.local_model is not None:
+            text = f"{self.embedding_model}\n{text}"
         return hashlib.sha256(text.encode("utf-8")).hexdigest()
 
     def _get_cache_path(self, cache_key: str) -> Path:
@@ -72,11 +152,10 @@ class EmbeddingGenerator:
         cache_path = self._get_cache_path(cache_key)
         if cache_path.exists():
             try:
-                with open(cache_path) as f:
-                    data = json.load(f)
-                    embedding = data.get("embedding")
-                    if isinstance(embedding, list) and all(isinstance(x, (int, float)) for x in embedding):
-                        return [float(x) for x in embedding]
+                data = json.loads(read_file(cache_path, self.cipher))
+                embedding = data.get("embedding")
+                if isinstance(embedding, list) and all(isinstance(x, (int, float)) for x in embedding):
+                    return [float(x) for x in embedding]
             except Exception:
                 # If cache file is corrupted, ignore it
                 return None
@@ -86,19 +165,25 @@ class EmbeddingGenerator:
         """Save embedding to cache."""
         cache_path = self._get_cache_path(cache_key)
         try:
-            with open(cache_path, "w") as f:
-                json.dump({"embedding": embedding, "text": text[:100]}, f)
+            data = json.dumps({"embedding": embedding, "text": text[:100]})
+            write_file(cache_path, data.encode("utf-8"), self.cipher)
         except Exception:
             # If cache write fails, continue without caching
             pass
 
-    @retry(
-        stop=stop_after_attempt(3),
-        wait=wait_exponential(multiplier=1, min=2, max=10),
-    )
-    def _generate_embedding_with_retry(self, text: str) -> List[float]:
+    def cache_file_name(self, text: str) -> str:
+        """Name of the file caching the embedding of a text, e.g. to snapshot it."""
+        return self._get_cache_path(self._get_cache_key(text)).name
+
+    def is_cached(self, text: str) -> bool:
+        """Whether the embedding of a text is cached, so it costs no API call."""
+        return self._get_cache_path(self._get_cache_key(text)).exists()
+
+    def _generate_embedding(self, text: str) -> List[float]:
         """
-        Generate embedding with retry logic and exponential backoff.
+        Generate embedding in one API call; called through
+        ``_generate_embedding_with_retry``, which retries it with
+        exponential backoff.
 
         Args:
             text: Text to generate embedding for.
@@ -107,22 +192,21 @@ class EmbeddingGenerator:
             List of floats representing the embedding vector.
         """
         try:
-            response = self.client.embeddings.create(
-                model=self.embedding_model,
-                input=text,
-            )
+            with self.limiter.request():
+                response = self.client.embeddings.create(
+                    model=self.embedding_model,
+                    input=text,
+                )
             return response.data[0].embedding
         except Exception as e:
             # Log the error and re-raise for retry logic
             raise Exception(f"Failed to generate embedding: {e}") from e
 
-    @retry(
-        stop=stop_after_attempt(3),
-        wait=wait_exponential(multiplier=1, min=2, max=10),
-    )
-    def _generate_embeddings_batch_with_retry(self, texts: List[str]) -> List[List[float]]:
+    def _generate_embeddings_batch(self, texts: List[str]) -> List[List[float]]:
         """
-        Generate embeddings for multiple texts in a single batch API call.
+        Generate embeddings for multiple texts in a single batch API call;
+        called through ``_generate_embeddings_batch_with_retry``, which
+        retries it with exponential backoff.
 
         Args:
             texts: List of texts to generate embeddings for.
@@ -134,10 +218,11 @@ class EmbeddingGenerator:
             return []
 
         try:
-            response = self.client.embeddings.create(
-                model=self.embedding_model,
-                input=texts,
-            )
+            with self.limiter.request():
+                response = self.client.embeddings.create(
+                    model=self.embedding_model,
+                    input=texts,
+                )
             # OpenAI returns embeddings with an index field that indicates
             # the position in the input list. Sort by index to ensure correct order.
             sorted_data = sorted(response.data, key=lambda x: x.index)
@@ -198,7 +283,10 @@ class EmbeddingGenerator:
         return cache_hits, cache_misses
 
     def generate_embeddings(
-        self, texts: List[str], show_progress: bool = False
+        self,
+        texts: List[str],
+        show_progress: bool = False,
+        on_progress: Optional[Callable[[int, int], None]] = None,
     ) -> List[List[float]]:
         """
         Generate embeddings for multiple texts using batch API calls.
@@ -212,6 +300,8 @@ class EmbeddingGenerator:
         Args:
             texts: List of texts to generate embeddings for.
             show_progress: Whether to show progress indicators.
+            on_progress: Called with (embedded, total) after the cache check
+                and after each batch.
 
         Returns:
             List of embedding vectors in the same order as input texts.
@@ -237,6 +327,8 @@ class EmbeddingGenerator:
         # Fill in cached embeddings
         for idx, embedding in cache_hits:
             embeddings[idx] = embedding
+        if on_progress:
+            on_progress(len(cache_hits), total)
 
         # Step 2: Generate embeddings for cache misses in batches
         if cache_misses:
@@ -245,38 +337,44 @@ class EmbeddingGenerator:
             miss_texts = [item[1] for item in cache_misses]
             miss_cache_keys = [item[2] for item in cache_misses]
 
-            # Process in batches
+            # Process in batches, up to ``concurrency`` API calls at once
             num_batches = (len(miss_texts) + self.batch_size - 1) // self.batch_size
             generated_count = 0
-
-            for batch_idx in range(num_batches):
-                batch_start = batch_idx * self.batch_size
-                batch_end = min(batch_start + self.batch_size, len(miss_texts))
-
-                batch_texts = miss_texts[batch_start:batch_end]
-                batch_indices = miss_indices[batch_start:batch_end]
-                batch_cache_keys = miss_cache_keys[batch_start:batch_end]
-
-                # Generate batch embeddings
-                batch_embeddings = self._generate_embeddings_batch_with_retry(batch_texts)
-
-                # Step 3: Save to cache and fill in results
-                for i, (idx, embedding, cache_key, text) in enumerate(
-                    zip(batch_indices, batch_embeddings, batch_cache_keys, batch_texts)
-                ):
-                    self._save_to_cache(cache_key, embedding, text)
-                    embeddings[idx] = embedding
-                    generated_count += 1
-
-                # Show progress if requested
-                if show_progress and len(cache_misses) > 10:
-                    elapsed = time.time() - start_time
-                    if elapsed > 2.0 or generated_count == len(cache_misses):
-                        progress = generated_count / len(cache_misses) * 100
-                        self.logger.info(
-                            f"Generating embeddings: {generated_count}/{len(cache_misses)} "
-                            f"({progress:.1f}%) [batch {batch_idx + 1}/{num_batches}]"
-                        )
+            batches = [
+                miss_texts[start : start + self.batch_size]
+                for start in range(0, len(miss_texts), self.batch_size)
+            ]
+
+            with ThreadPoolExecutor(max_workers=self.concurrency) as executor:
+                # Results come back in batch order as each batch finishes
+                results = executor.map(self._generate_embeddings_batch_with_retry, batches)
+                for batch_idx, batch_embeddings in enumerate(results):
+                    batch_start = batch_idx * self.batch_size
+                    batch_end = min(batch_start + self.batch_size, len(miss_texts))
+
+                    batch_texts = miss_texts[batch_start:batch_end]
+                    batch_indices = miss_indices[batch_start:batch_end]
+                    batch_cache_keys = miss_cache_keys[batch_start:batch_end]
+
+                    # Step 3: Save to cache and fill in results
+                    for idx, embedding, cache_key, text in zip(
+                        batch_indices, batch_embeddings, batch_cache_keys, batch_texts
+                    ):
+                        self._save_to_cache(cache_key, embedding, text)
+                        embeddings[idx] = embedding
+                        generated_count += 1
+                    if on_progress:
+                        on_progress(len(cache_hits) + generated_count, total)
+
+                    # Show progress if requested
+                    if show_progress and len(cache_misses) > 10:
+                        elapsed = time.time() - start_time
+                        if elapsed > 2.0 or generated_count == len(cache_misses):
+                            progress = generated_count / len(cache_misses) * 100
+                            self.logger.info(
+                                f"Generating embeddings: {generated_count}/{len(cache_misses)} "
+                                f"({progress:.1f}%) [batch {batch_idx + 1}/{num_batches}]"
+                            )
 
         # All embeddings should be filled in now
         return embeddings  # type: ignore[return-value]
@@ -0,0 +1,192 @@
+"""Encryption of the index at rest, for ``server.encryption``.
+
+With ``passphrase`` or ``keyring``, the vector store, chunk texts, HNSW
+graph and cached embeddings are written encrypted with AES-256-GCM, under
+a key of the cache directory (the workspace): derived from the passphrase
+in MARKDOWN_QA_INDEX_PASSPHRASE, or generated once and kept in the
+system keyring. Needs the cryptography package (and keyring for
+``keyring``), which are optional dependencies.
+"""
+
+import base64
+import json
+import os
+import secrets
+from pathlib import Path
+from typing import Any, Optional
+
+# Values of server.encryption: no encryption, a key derived from a
+# passphrase, or a key kept in the system keyring
+ENCRYPTION_KINDS = ("off", "passphrase", "keyring")
+
+# Environment variable holding the passphrase of server.encryption: passphrase
+PASSPHRASE_ENV = "MARKDOWN_QA_INDEX_PASSPHRASE"
+
+# Keyring service the keys of server.encryption: keyring are kept under,
+# one per cache directory
+KEYRING_SERVICE = "md-qa"
+
+# File in the cache directory holding the salt of the passphrase's key and
+# a value encrypted with the key, so a wrong key is caught at startup
+KEY_CHECK_FILE = "encryption.json"
+
+# Start of every encrypted file, telling it apart from a plain one
+MAGIC = b"MDQA-AES256GCM\n"
+
+# PBKDF2-SHA256 iterations deriving a key from a passphrase
+PBKDF2_ITERATIONS = 600_000
+
+_NONCE_SIZE = 12
+_CHECK_VALUE = b"md-qa"
+
+
+def parse_encryption(value: object) -> str:
+    """
+    Read server.encryption: ``off`` (the default), ``passphrase`` or ``keyring``.
+
+    Raises:
+        ValueError: If the value is none of them.
+    """
+    if value is None or value is False:
+        return "off"
+    if not isinstance(value, str) or value.strip().lower() not in ENCRYPTION_KINDS:
+        raise ValueError(
+            f"server.encryption must be one of {', '.join(ENCRYPTION_KINDS)}, not {value!r}"
+        )
+    return value.strip().lower()
+
+
+def _aesgcm() -> Any:
+    try:
+        from cryptography.hazmat.primitives.ciphers.aead import AESGCM  # type: ignore[import-not-found]
+    except ImportError:
+        raise ImportError(
+            "server.encryption needs cryptography. Install it with: pip install cryptography"
+        ) from None
+    return AESGCM
+
+
+def _derive_key(passphrase: str, salt: bytes) -> bytes:
+    from cryptography.hazmat.primitives import hashes  # type: ignore[import-not-found]
+    from cryptography.hazmat.primitives.kdf.pbkdf2 import PBKDF2HMAC  # type: ignore[import-not-found]
+
+    kdf = PBKDF2HMAC(
+        algorithm=hashes.SHA256(), length=32, salt=salt, iterations=PBKDF2_ITERATIONS
+    )
+    return kdf.derive(passphrase.encode("utf-8"))
+
+
+def _keyring_key(cache_dir: Path) -> bytes:
+    """The key of a cache directory in the system keyring, created on first use."""
+    try:
+        import keyring  # type: ignore[import-not-found]
+    except ImportError:
+        raise ImportError(
+            "server.encryption: keyring needs keyring. Install it with: pip install keyring"
+        ) from None
+    account = str(cache_dir.resolve())
+    stored = keyring.get_password(KEYRING_SERVICE, account)
+    if stored is None:
+        stored = base64.b64encode(secrets.token_bytes(32)).decode("ascii")
+        keyring.set_password(KEYRING_SERVICE, account, stored)
+    return base64.b64decode(stored)
+
+
+class IndexCipher:
+    """Encrypts and decrypts the files of an index with one AES-256-GCM key."""
+
+    def __init__(self, key: bytes):
+        """
+        Initialize index cipher.
+
+        Args:
+            key: 32-byte key.
+        """
+        self._aead = _aesgcm()(key)
+
+    @classmethod
+    def for_workspace(cls, encryption: str, cache_dir: Path) -> Optional["IndexCipher"]:
+        """
+        The cipher of a cache directory under server.encryption.
+
+        Args:
+            encryption: ``off``, ``passphrase`` or ``keyring``.
+            cache_dir: The cache directory; its salt and key check are kept there.
+
+        Returns:
+            The cipher, or None if encryption is off.
+
+        Raises:
+            ValueError: If the passphrase is missing, or the key isn't the
+                one the cache directory was encrypted with.
+            ImportError: If cryptography (or keyring) is not installed.
+        """
+        if encryption == "off":
+            return None
+        _aesgcm()
+        check_path = cache_dir / KEY_CHECK_FILE
+        check = json.loads(check_path.read_text()) if check_path.exists() else {}
+
+        if encryption == "passphrase":
+            passphrase = os.environ.get(PASSPHRASE_ENV)
+            if not passphrase:
+                raise ValueError(f"server.encryption: passphrase needs {PASSPHRASE_ENV} to be set")
+            salt = base64.b64decode(check["salt"]) if "salt" in check else secrets.token_bytes(16)
+            cipher = cls(_derive_key(passphrase, salt))
+            check["salt"] = base64.b64encode(salt).decode("ascii")
+        else:
+            cipher = cls(_keyring_key(cache_dir))
+
+        if "check" in check:
+            try:
+                cipher.decrypt(base64.b64decode(check["check"]))
+            except ValueError:
+                raise ValueError(
+                    f"The {encryption} key is not the one the index in {cache_dir} "
+                    "was encrypted with"
+                ) from None
+        else:
+            check["check"] = base64.b64encode(cipher.encrypt(_CHECK_VALUE)).decode("ascii")
+            check_path.write_text(json.dumps(check))
+        return cipher
+
+    def encrypt(self, data: bytes) -> bytes:
+        """Encrypt data, starting with MAGIC."""
+        nonce = secrets.token_bytes(_NONCE_SIZE)
+        return MAGIC + nonce + self._aead.encrypt(nonce, data, None)
+
+    def decrypt(self, data: bytes) -> bytes:
+        """
+        Decrypt data encrypted with ``encrypt``.
+
+        Raises:
+            ValueError: If the data isn't encrypted, or not with this key.
+        """
+        if not data.startswith(MAGIC):
+            raise ValueError("Data is not encrypted")
+        body = data[len(MAGIC) :]
+        try:
+            return self._aead.decrypt(body[:_NONCE_SIZE], body[_NONCE_SIZE:], None)
+        except Exception:
+            raise ValueError("Data was encrypted with another key or is corrupted") from None
+
+
+def write_file(path: Path, data: bytes, cipher: Optional[IndexCipher]) -> None:
+    """Write a file of the index, encrypted if there is a cipher."""
+    path.write_bytes(cipher.encrypt(data) if cipher is not None else data)
+
+
+def read_file(path: Path, cipher: Optional[IndexCipher]) -> bytes:
+    """
+    Read a file of the index written with ``write_file``.
+
+    Raises:
+        ValueError: If the file is encrypted and there is no cipher, or it
+            is plain and there is one (so the index is built again).
+    """
+    data = path.read_bytes()
+    if cipher is not None:
+        return cipher.decrypt(data)
+    if data.startswith(MAGIC):
+        raise ValueError(f"{path.name} is encrypted; set server.encryption to read it")
+    return data
@@ -0,0 +1,71 @@
+"""Estimates of the tokens and time an index build spends on embeddings,
+so clients can confirm a large build before it starts."""
+
+import math
+from typing import Callable, Dict, Optional
+
+# Characters per token when tiktoken is not installed; about right for
+# English prose with OpenAI's tokenizers
+CHARS_PER_TOKEN = 4
+
+# Seconds one batch embedding request takes, for time estimates
+SECONDS_PER_REQUEST = 1.0
+
+# Token counters by embedding model, once looked up
+_counters: Dict[str, Optional[Callable[[str], int]]] = {}
+
+
+def _tiktoken_counter(model: str) -> Optional[Callable[[str], int]]:
+    """The exact token counter of an embedding model, if tiktoken knows it."""
+    if model not in _counters:
+        try:
+            import tiktoken  # type: ignore[import-not-found]
+
+            encoding = tiktoken.encoding_for_model(model)
+            _counters[model] = lambda text: len(encoding.encode(text, disallowed_special=()))
+        except Exception:
+            # tiktoken is not installed or doesn't know the model
+            _counters[model] = None
+    return _counters[model]
+
+
+def count_tokens(text: str, model: str) -> int:
+    """
+    Tokens the embedding API bills for a text: exact with tiktoken for
+    OpenAI models, else estimated from its length.
+
+    Args:
+        text: Text to embed.
+        model: Embedding model name.
+
+    Returns:
+        Token count.
+    """
+    counter = _tiktoken_counter(model)
+    if counter is not None:
+        return counter(text)
+    return math.ceil(len(text) / CHARS_PER_TOKEN)
+
+
+def estimate_seconds(
+    chunks: int, batch_size: int, concurrency: int, per_minute: Optional[int] = None
+) -> float:
+    """
+    Rough time to embed chunks with the configured batching.
+
+    Args:
+        chunks: Chunks to embed.
+        batch_size: Chunks per request (api.embedding_batch_size).
+        concurrency: Requests in flight at once (api.embedding_concurrency).
+        per_minute: Requests started a minute (api.requests_per_minute), or
+            None for no limit.
+
+    Returns:
+        Seconds.
+    """
+    requests = math.ceil(chunks / max(1, batch_size))
+    seconds = math.ceil(requests / max(1, concurrency)) * SECONDS_PER_REQUEST
+    if per_minute:
+        seconds = max(seconds, requests * 60.0 / per_minute)
+    return seconds
+
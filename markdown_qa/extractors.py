@@ -0,0 +1,365 @@
+"""Extractors turning documents of other formats into markdown for indexing.
+
+Each format is off unless listed in ``server.formats``; markdown files are
+always indexed.
+"""
+
+import io
+import json
+import re
+import shutil
+import subprocess
+from html.parser import HTMLParser
+from typing import Any, Callable, Dict, Iterable, List, Optional, Tuple
+
+# File extensions indexed without any format enabled
+MARKDOWN_EXTENSIONS: Tuple[str, ...] = (".md",)
+
+# Seconds pdftotext may take for one file
+PDF_TIMEOUT = 120
+
+
+def _decode(data: bytes) -> str:
+    """Text of a document that declares no encoding we can rely on."""
+    if data.startswith(b"\xef\xbb\xbf"):
+        data = data[3:]
+    try:
+        return data.decode("utf-8")
+    except UnicodeDecodeError:
+        return data.decode("cp1252", errors="replace")
+
+
+class _HTMLToMarkdown(HTMLParser):
+    """
+    Readability-style HTML to markdown: keeps the ``<main>`` or
+    ``<article>`` content when the page has one, drops scripts, styles,
+    navigation and forms, and keeps headings, paragraphs, list items and
+    preformatted code.
+    """
+
+    SKIPPED = {"script", "style", "noscript", "template", "svg", "nav", "aside", "form", "iframe"}
+    # Page chrome, dropped outside the main content only (an article's
+    # header often holds its title)
+    CHROME = {"header", "footer"}
+    BLOCKS = {
+        "p", "div", "section", "article", "main", "table", "tr", "ul", "ol",
+        "blockquote", "dl", "dt", "dd", "figure", "figcaption", "hr",
+    }
+    HEADINGS = {"h1": 1, "h2": 2, "h3": 3, "h4": 4, "h5": 5, "h6": 6}
+
+    def __init__(self) -> None:
+        super().__init__(convert_charrefs=True)
+        # Text parts, each with whether it is inside <main> or <article>
+        self.parts: List[Tuple[str, bool]] = []
+        self.title: List[str] = []
+        self._skipping: List[str] = []
+        self._main = 0
+        self._pre = 0
+        self._in_title = False
+
+    def _emit(self, text: str) -> None:
+        self.parts.append((text, self._main > 0))
+
+    def handle_starttag(self, tag: str, attrs: List[Tuple[str, Optional[str]]]) -> None:
+        if self._skipping:
+            if tag == self._skipping[-1]:
+                self._skipping.append(tag)
+            return
+        if tag in self.SKIPPED or (tag in self.CHROME and not self._main):
+            self._skipping.append(tag)
+        elif tag == "title":
+            self._in_title = True
+        elif tag in ("main", "article"):
+            self._main += 1
+            self._emit("\n\n")
+        elif tag in self.HEADINGS:
+            self._emit("\n\n" + "#" * self.HEADINGS[tag] + " ")
+        elif tag == "li":
+            self._emit("\n- ")
+        elif tag == "pre":
+            self._pre += 1
+            self._emit("\n\n```\n")
+        elif tag == "br":
+            self._emit("\n")
+        elif tag == "code" and not self._pre:
+            self._emit("`")
+        elif tag in ("td", "th"):
+            self._emit(" ")
+        elif tag in self.BLOCKS:
+            self._emit("\n\n")
+
+    def handle_endtag(self, tag: str) -> None:
+        if self._skipping:
+            if tag == self._skipping[-1]:
+                self._skipping.pop()
+            return
+        if tag == "title":
+            self._in_title = False
+        elif tag in ("main", "article"):
+            self._emit("\n\n")
+            self._main = max(0, self._main - 1)
+        elif tag == "pre":
+            self._pre = max(0, self._pre - 1)
+            self._emit("\n```\n\n")
+        elif tag == "code" and not self._pre:
+            self._emit("`")
+        elif tag in self.HEADINGS or tag in self.BLOCKS:
+            self._emit("\n\n")
+
+    def handle_data(self, data: str) -> None:
+        if self._skipping:
+            return
+        if self._in_title:
+            self.title.append(data)
+        elif self._pre:
+            self._emit(data)
+        else:
+            self._emit(re.sub(r"\s+", " ", data))
+
+    def markdown(self) -> str:
+        """The extracted text as markdown."""
+        parts = self.parts
+        if any(inside for _, inside in parts):
+            parts = [part for part in parts if part[1]]
+        text = "".join(text for text, _ in parts)
+        text = re.sub(r"[ \t]+\n", "\n", text)
+        text = re.sub(r"\n[ \t]+(?=\S)", "\n", text)
+        text = re.sub(r"\n{3,}", "\n\n", text).strip()
+        title = re.sub(r"\s+", " ", "".join(self.title)).strip()
+        if title and not text.startswith("# "):
+            text = f"# {title}\n\n{text}"
+        return text + "\n"
+
+
+def extract_html(data: bytes) -> str:
+    """
+    Extract the main content of an HTML page as markdown.
+
+    Args:
+        data: Raw file content.
+
+    Returns:
+        The page's main text, with its title as the first heading.
+    """
+    parser = _HTMLToMarkdown()
+    parser.feed(_decode(data))
+    parser.close()
+    return parser.markdown()
+
+
+def _pdf_pages(data: bytes) -> List[str]:
+    """Text of each page of a PDF, with poppler's pdftotext or else pypdf."""
+    pdftotext = shutil.which("pdftotext")
+    if pdftotext is not None:
+        result = subprocess.run(
+            [pdftotext, "-enc", "UTF-8", "-", "-"],
+            input=data,
+            capture_output=True,
+            timeout=PDF_TIMEOUT,
+            check=False,
+        )
+        if result.returncode != 0:
+            error = result.stderr.decode(errors="replace").strip()
+            raise ValueError(f"pdftotext failed: {error}")
+        # Pages end with a form feed
+        return result.stdout.decode("utf-8", errors="replace").split("\f")
+    try:
+        import pypdf  # type: ignore[import-not-found]
+    except ImportError:
+        raise ValueError(
+            "PDF support needs poppler's pdftotext or the pypdf package"
+        ) from None
+    reader = pypdf.PdfReader(io.BytesIO(data))
+    return [page.extract_text() or "" for page in reader.pages]
+
+
+def extract_pdf(data: bytes) -> str:
+    """
+    Extract the text of a PDF as markdown, one section per page, so answers
+    can cite the page.
+
+    Args:
+        data: Raw file content.
+
+    Returns:
+        ``## Page N`` headings, each followed by that page's text. Pages
+        without text are left out.
+    """
+    sections = [
+        f"## Page {number}\n\n{text.strip()}\n"
+        for number, text in enumerate(_pdf_pages(data), start=1)
+        if text.strip()
+    ]
+    return "\n".join(sections)
+
+
+# A markdown heading line
+_HEADING = re.compile(r"^(#{1,6})[ \t]+(.*)$", re.MULTILINE)
+
+
+def _cell_source(cell: Dict[str, Any]) -> str:
+    """A notebook cell's text, stored as one string or a list of lines."""
+    source = cell.get("source", "")
+    if isinstance(source, list):
+        source = "".join(str(line) for line in source)
+    return str(source).strip()
+
+
+def extract_notebook(data: bytes) -> str:
+    """
+    Extract the markdown and code cells of a Jupyter notebook as markdown.
+
+    Each cell starts a section named after its number (1-based, in notebook
+    order), so answers cite the cell; a markdown cell's own headings keep
+    their level and get the number in front, e.g. ``## Cell 3: Setup``.
+    Code cells become fenced blocks in the notebook's language. Outputs and
+    raw cells are left out.
+
+    Args:
+        data: Raw file content.
+
+    Returns:
+        The notebook as markdown.
+
+    Raises:
+        ValueError: If the file is not a notebook.
+    """
+    try:
+        notebook = json.loads(_decode(data))
+    except json.JSONDecodeError as e:
+        raise ValueError(f"Not a Jupyter notebook: {e}") from None
+    if not isinstance(notebook, dict) or not isinstance(notebook.get("cells"), list):
+        raise ValueError("Not a Jupyter notebook: no cells")
+    metadata = notebook.get("metadata") or {}
+    language = (
+        (metadata.get("kernelspec") or {}).get("language")
+        or (metadata.get("language_info") or {}).get("name")
+        or "python"
+    )
+
+    sections = []
+    for number, cell in enumerate(notebook["cells"], start=1):
+        source = _cell_source(cell) if isinstance(cell, dict) else ""
+        if not source:
+            continue
+        kind = cell.get("cell_type")
+        if kind == "markdown":
+            text = _HEADING.sub(
+                lambda m, n=number: f"{m.group(1)} Cell {n}: {m.group(2).strip()}", source
+            )
+            if not _HEADING.match(text):
+                text = f"## Cell {number}\n\n{text}"
+        elif kind == "code":
+            text = f"## Cell {number}\n\n```{language}\n{source}\n```"
+        else:
+            continue
+        sections.append(text + "\n")
+    return "\n".join(sections)
+
+
+# Fenced code blocks, which MDX leaves as they are
+_FENCE = re.compile(r"^(`{3,}|~{3,}).*?^\1[^\n]*$", re.MULTILINE | re.DOTALL)
+
+# ESM statements at the start of a line
+_ESM = re.compile(r"^(?:import|export)\b")
+
+# JSX comments, {/* ... */}
+_JSX_COMMENT = re.compile(r"\{/\*.*?\*/\}", re.DOTALL)
+
+# Component tags: <Tabs>, </Tabs>, <Note type="info" /> and the like (lower
+# case tags are HTML, which markdown allows)
+_JSX_TAG = re.compile(r"</?[A-Z][\w.]*(?:\s(?:[^<>{}]|\{[^{}]*\})*)?/?>")
+
+# A line holding only a JSX expression, e.g. {props.children}
+_JSX_EXPRESSION = re.compile(r"^[ \t]*\{[^{}\n]*\}[ \t]*$", re.MULTILINE)
+
+
+def _strip_esm(text: str) -> str:
+    """Drop import and export statements, including ones spanning lines."""
+    lines = []
+    # Brackets a statement has left open, which continue it on the next line
+    depth = 0
+    for line in text.split("\n"):
+        if depth > 0 or _ESM.match(line):
+            opened = sum(line.count(c) for c in "{([")
+            closed = sum(line.count(c) for c in "})]")
+            depth = max(0, depth + opened - closed)
+            continue
+        lines.append(line)
+    return "\n".join(lines)
+
+
+def _strip_jsx(text: str) -> str:
+    """Drop the JSX of MDX text outside code blocks, keeping the text inside components."""
+    text = _strip_esm(text)
+    text = _JSX_COMMENT.sub("", text)
+    text = _JSX_TAG.sub("", text)
+    text = _JSX_EXPRESSION.sub("", text)
+    text = re.sub(r"^[ \t]+$", "", text, flags=re.MULTILINE)
+    return re.sub(r"\n{3,}", "\n\n", text)
+
+
+def extract_mdx(data: bytes) -> str:
+    """
+    Extract the markdown of an MDX file: import and export statements,
+    component tags, JSX comments and expression lines are dropped, and the
+    text and markdown between component tags is kept. Code blocks are left
+    as they are.
+
+    Args:
+        data: Raw file content.
+
+    Returns:
+        The file as plain markdown.
+    """
+    text = _decode(data).replace("\r\n", "\n")
+    parts = []
+    start = 0
+    for match in _FENCE.finditer(text):
+        parts.append(_strip_jsx(text[start : match.start()]))
+        parts.append(match.group(0))
+        start = match.end()
+    parts.append(_strip_jsx(text[start:]))
+    return "".join(parts).strip() + "\n"
+
+
+# Formats server.formats may enable, with their file extensions
+FORMATS: Dict[str, Tuple[str, ...]] = {
+    "html": (".html", ".htm"),
+    "pdf": (".pdf",),
+    "ipynb": (".ipynb",),
+    "mdx": (".mdx",),
+}
+
+# Extractor of each extension that is not plain markdown
+EXTRACTORS: Dict[str, Callable[[bytes], str]] = {
+    ".html": extract_html,
+    ".htm": extract_html,
+    ".pdf": extract_pdf,
+    ".ipynb": extract_notebook,
+    ".mdx": extract_mdx,
+}
+
+
+def extensions_for(formats: Iterable[str]) -> Tuple[str, ...]:
+    """
+    File extensions to index: markdown plus those of the enabled formats.
+
+    Args:
+        formats: Names of enabled formats, e.g. ``["html", "pdf"]``.
+
+    Returns:
+        The extensions, markdown first.
+
+    Raises:
+        ValueError: If a format is unknown.
+    """
+    extensions = list(MARKDOWN_EXTENSIONS)
+    for name in formats:
+        if name not in FORMATS:
+            raise ValueError(
+                f"Unknown format in server.formats: {name} "
+                f"(expected one of {', '.join(FORMATS)})"
+            )
+        extensions.extend(ext for ext in FORMATS[name] if ext not in extensions)
+    return tuple(extensions)
@@ -0,0 +1,150 @@
+"""Ignore files that keep documents out of an index.
+
+Each configured directory may hold a ``.gitignore`` and a ``.mdqaignore``
+with gitignore-style patterns; rules in ``.mdqaignore`` come last, so they
+can re-include (``!pattern``) what ``.gitignore`` leaves out. Only the files
+at the top of the configured directory are read, and patterns are relative
+to it.
+"""
+
+import re
+from pathlib import Path
+from typing import List, Optional, Pattern, Tuple
+
+# Read in this order; later rules win.
+IGNORE_FILES = (".gitignore", ".mdqaignore")
+
+
+def _translate(pattern: str) -> Pattern[str]:
+    """
+    Compile one gitignore pattern (without ``!`` or a trailing ``/``) to a
+    regex matched against a path relative to the ignore file.
+    """
+    anchored = "/" in pattern.rstrip("/")
+    pattern = pattern.lstrip("/")
+    parts: List[str] = []
+    i = 0
+    while i < len(pattern):
+        if pattern.startswith("**/", i):
+            parts.append("(?:.*/)?")
+            i += 3
+        elif pattern.startswith("**", i):
+            parts.append(".*")
+            i += 2
+        elif pattern[i] == "*":
+            parts.append("[^/]*")
+            i += 1
+        elif pattern[i] == "?":
+            parts.append("[^/]")
+            i += 1
+        elif pattern[i] == "[" and "]" in pattern[i + 2 :]:
+            end = pattern.index("]", i + 2)
+            body = pattern[i + 1 : end].replace("\\", "\\\\")
+            if body.startswith("!"):
+                body = "^" + body[1:]
+            parts.append(f"[{body}]")
+            i = end + 1
+        elif pattern[i] == "\\" and i + 1 < len(pattern):
+            parts.append(re.escape(pattern[i + 1]))
+            i += 2
+        else:
+            parts.append(re.escape(pattern[i]))
+            i += 1
+    prefix = "" if anchored else "(?:.*/)?"
+    return re.compile(prefix + "".join(parts))
+
+
+def _parse_line(line: str) -> Optional[Tuple[Pattern[str], bool, bool]]:
+    """
+    Parse one line of an ignore file.
+
+    Returns:
+        (regex, negated, directories only), or None for blank lines and
+        comments.
+    """
+    line = line.rstrip("\n").rstrip("\r")
+    if not line.endswith("\\ "):
+        line = line.rstrip(" ")
+    if not line or line.startswith("#"):
+        return None
+    negated = line.startswith("!")
+    if negated:
+        line = line[1:]
+    elif line.startswith("\\#") or line.startswith("\\!"):
+        line = line[1:]
+    dir_only = line.endswith("/")
+    line = line.rstrip("/")
+    if not line:
+        return None
+    return _translate(line), negated, dir_only
+
+
+class IgnoreRules:
+    """The ignore rules of one configured directory."""
+
+    def __init__(self, root: Path, rules: List[Tuple[Pattern[str], bool, bool]]):
+        """
+        Initialize ignore rules.
+
+        Args:
+            root: Directory the patterns are relative to.
+            rules: Parsed patterns, in the order they apply.
+        """
+        self.root = root
+        self.rules = rules
+
+    @classmethod
+    def load(cls, directory: Path) -> "IgnoreRules":
+        """
+        Read the ignore files at the top of a configured directory.
+
+        Args:
+            directory: Configured directory.
+
+        Returns:
+            Its rules; none if it has no ignore files.
+        """
+        rules: List[Tuple[Pattern[str], bool, bool]] = []
+        for name in IGNORE_FILES:
+            try:
+                text = (directory / name).read_text(encoding="utf-8", errors="replace")
+            except OSError:
+                continue
+            for line in text.splitlines():
+                rule = _parse_line(line)
+                if rule is not None:
+                    rules.append(rule)
+        return cls(directory, rules)
+
+    def _matches(self, relative: str, is_dir: bool) -> bool:
+        """Whether the last rule matching ``relative`` ignores it."""
+        ignored = False
+        for regex, negated, dir_only in self.rules:
+            if dir_only and not is_dir:
+                continue
+            if regex.fullmatch(relative):
+                ignored = not negated
+        return ignored
+
+    def is_ignored(self, path: Path) -> bool:
+        """
+        Check whether a file under the directory is ignored.
+
+        A file in an ignored directory stays ignored, as with git.
+
+        Args:
+            path: File under ``root``.
+
+        Returns:
+            True if the rules leave the file out.
+        """
+        if not self.rules:
+            return False
+        try:
+            parts = path.relative_to(self.root).parts
+        except ValueError:
+            return False
+        for depth in range(1, len(parts)):
+            if self._matches("/".join(parts[:depth]), is_dir=True):
+                return True
+        return self._matches("/".join(parts), is_dir=False)
@@ -1,22 +1,34 @@
 """In-memory index manager module."""
 
+import json
 import threading
+import time
 from dataclasses import dataclass, field
 from pathlib import Path
-from typing import Any, Dict, List, Optional, Tuple
+from typing import Any, Callable, Dict, List, Optional, Sequence, Tuple
 
 from markdown_qa.cache import CacheManager
+from markdown_qa.chunk_cache import ChunkCache
+from markdown_qa.chunker import ChunkingConfig, MarkdownChunker
 from markdown_qa.config import APIConfig
+from markdown_qa.embeddings import EmbeddingGenerator
+from markdown_qa.encryption import read_file, write_file
+from markdown_qa.estimate import count_tokens, estimate_seconds
+from markdown_qa.extractors import MARKDOWN_EXTENSIONS
 from markdown_qa.index_validator import IndexValidator
 from markdown_qa.loader import (
+    DEFAULT_MAX_FILE_SIZE_MB,
     FileBeingEditedError,
+    FileTooLargeError,
     compute_directories_checksum,
     generate_chunk_id,
     get_file_mtimes,
-    load_single_file,
+    iter_single_file,
+    plan_markdown_files,
 )
 from markdown_qa.logger import get_server_logger
 from markdown_qa.manifest import Manifest
+from markdown_qa.snapshot import MANIFEST_MEMBER, read_snapshot, write_snapshot
 from markdown_qa.vector_store import VectorStore
 
 
@@ -43,6 +55,11 @@ class IndexManager:
         self,
         cache_manager: Optional[CacheManager] = None,
         api_config: Optional[APIConfig] = None,
+        max_file_size_mb: float = DEFAULT_MAX_FILE_SIZE_MB,
+        index_drafts: bool = False,
+        chunking: Optional[ChunkingConfig] = None,
+        extensions: Sequence[str] = MARKDOWN_EXTENSIONS,
+        ann: str = "exact",
     ):
         """
         Initialize index manager.
@@ -50,9 +67,28 @@ class IndexManager:
         Args:
             cache_manager: Cache manager instance. If None, creates default.
             api_config: API configuration. If None, creates from defaults.
+            max_file_size_mb: Markdown files larger than this are not indexed.
+            index_drafts: Index files whose front matter sets ``draft: true``.
+            chunking: Chunk size, overlap and heading splits (the
+                retrieval.chunking config section). If None, the chunker's
+                defaults.
+            extensions: File extensions to index: ``.md`` and those of the
+                formats enabled in server.formats.
+            ann: Nearest-neighbor search of the indexes (retrieval.ann).
         """
         self.cache_manager = cache_manager or CacheManager()
         self.api_config = api_config or APIConfig()
+        self.max_file_size_mb = max_file_size_mb
+        self.index_drafts = index_drafts
+        self.chunking = chunking or ChunkingConfig()
+        self.extensions = tuple(extensions)
+        self.ann = ann
+        # Called with index_progress message bodies while (re)indexing, e.g.
+        # to pass them on to connected clients; may run in a worker thread
+        self.on_progress: Optional[Callable[[Dict[str, Any]], None]] = None
+        # Recently retrieved chunks, shared by queries; cleared whenever the
+        # index changes
+        self.chunk_cache = ChunkCache()
 
         # Current index (used for queries)
         self._index: Optional[VectorStore] = None
@@ -84,13 +120,15 @@ class IndexManager:
                         vector_store = VectorStore(
                             cache_manager=self.cache_manager,
                             api_config=self.api_config,
+                            ann=self.ann,
                         )
                         vector_store.load_index(index_name)
                         self._index = vector_store
+                        self.chunk_cache.clear()
 
                         # Ensure checksum is stored (for indexes created before checksum support)
                         if self.manifest.get_index_checksum(index_name) is None:
-                            checksum = compute_directories_checksum(directories)
+                            checksum = compute_directories_checksum(directories, self.extensions)
                             self.update_checksum(index_name, directories, checksum)
 
                         # Ensure per-file metadata exists (for indexes created before incremental support)
@@ -124,11 +162,27 @@ class IndexManager:
         """
         with self._index_lock:
             self._index = new_index
+            self.chunk_cache.clear()
+
+    def set_ann(self, ann: str) -> None:
+        """
+        Switch the nearest-neighbor search of the current and future indexes,
+        building or dropping the current one's HNSW graph.
+
+        Args:
+            ann: ``exact`` or ``hnsw`` (retrieval.ann).
+        """
+        self.ann = ann
+        index = self.get_index()
+        if index is not None:
+            index.ann = ann
+            index.prepare_ann()
 
     def clear_index(self) -> None:
         """Clear the current in-memory index (thread-safe)."""
         with self._index_lock:
             self._index = None
+            self.chunk_cache.clear()
 
     def rebuild_index(
         self, index_name: str, directories: list[str]
@@ -146,7 +200,11 @@ class IndexManager:
         # Build new index (this doesn't affect current index)
         vector_store = VectorStore(
             cache_manager=self.cache_manager,
+            chunker=MarkdownChunker(include_drafts=self.index_drafts, chunking=self.chunking),
             api_config=self.api_config,
+            max_file_size_mb=self.max_file_size_mb,
+            extensions=self.extensions,
+            ann=self.ann,
         )
         vector_store.build_index(
             directories, index_name=index_name, show_progress=True
@@ -163,6 +221,392 @@ class IndexManager:
         with self._index_lock:
             return self._index is not None and self._index.is_valid()
 
+    def index_stats(self, index_name: str) -> Optional[Dict[str, Any]]:
+        """
+        Summarize the current index, so users can sanity-check their setup.
+
+        Args:
+            index_name: Name of the index, for its files on disk.
+
+        Returns:
+            Document and chunk counts, embedding model, Unix time of the last
+            write to disk, size on disk, the files that had problems
+            reading (see loader.decode_markdown) and the chunk cache's hit
+            rate (see ChunkCache.stats), or None if no index is loaded.
+        """
+        with self._index_lock:
+            if self._index is None:
+                return None
+            metadata = list(self._index.metadata)
+
+        documents = {
+            str(meta.get("file_path", "") or meta.get("source", ""))
+            for meta in metadata
+        }
+        documents.discard("")
+        files = [p for p in self.cache_manager.get_index_path(index_name) if p.exists()]
+        return {
+            "documents": len(documents),
+            "chunks": len(metadata),
+            "embedding_model": self.api_config.embedding_model,
+            "built_at": int(max(p.stat().st_mtime for p in files)) if files else None,
+            "size_bytes": sum(p.stat().st_size for p in files) if files else None,
+            "issues": [
+                {"path": path, "issue": issue}
+                for path, issue in sorted(self.manifest.get_file_issues(index_name).items())
+            ],
+            "chunk_cache": self.chunk_cache.stats(),
+        }
+
+    def list_documents(
+        self, index_name: str, page: int, page_size: int
+    ) -> Optional[Dict[str, Any]]:
+        """
+        Get one page of the indexed documents, sorted by path.
+
+        Args:
+            index_name: Name of the index, for when each file was indexed.
+            page: Zero-based page number.
+            page_size: Documents per page.
+
+        Returns:
+            The number of documents in the index (``total``) and the page's
+            ``documents`` with their path, chunk count and, when the manifest
+            recorded it, the Unix time they were indexed at, or None if no
+            index is loaded.
+        """
+        with self._index_lock:
+            if self._index is None:
+                return None
+            metadata = list(self._index.metadata)
+
+        chunks: Dict[str, int] = {}
+        for meta in metadata:
+            source = str(meta.get("file_path", "") or meta.get("source", ""))
+            if source:
+                chunks[source] = chunks.get(source, 0) + 1
+        paths = sorted(chunks)
+        files = self.manifest.get_all_file_metadata(index_name)
+        documents = []
+        for path in paths[page * page_size : (page + 1) * page_size]:
+            document: Dict[str, Any] = {"path": path, "chunks": chunks[path]}
+            indexed_at = files.get(path, {}).get("indexed_at")
+            if indexed_at is not None:
+                document["indexed_at"] = indexed_at
+            documents.append(document)
+        return {"total": len(paths), "documents": documents}
+
+    def document_chunks(self, path: str) -> Optional[List[Dict[str, str]]]:
+        """
+        Get the chunks of one indexed document, in document order.
+
+        Args:
+            path: Path of the document, as indexed.
+
+        Returns:
+            The chunks' ``text`` and, when the chunker recorded one, the
+            ``heading`` they fall under, or None if no index is loaded.
+
+        Raises:
+            KeyError: If the document is not indexed.
+        """
+        with self._index_lock:
+            if self._index is None:
+                return None
+            metadata = list(self._index.metadata)
+            texts = list(self._index.texts)
+
+        chunks = []
+        for idx, meta in enumerate(metadata):
+            source = str(meta.get("file_path", "") or meta.get("source", ""))
+            if source != path:
+                continue
+            chunk = {"text": texts[idx] if idx < len(texts) else ""}
+            if meta.get("section"):
+                chunk["heading"] = str(meta["section"])
+            chunks.append(chunk)
+        if not chunks:
+            raise KeyError(path)
+        return chunks
+
+    def document_links(self, path: str) -> Optional[Dict[str, Any]]:
+        """
+        Get the links of one indexed document (see links.LinkGraph).
+
+        Args:
+            path: Path of the document, as indexed or the end of it (see
+                LinkGraph.find).
+
+        Returns:
+            The document's ``path`` as indexed, the documents it links to
+            (``outbound``) and the ones linking to it (``inbound``), or None
+            if no index is loaded.
+
+        Raises:
+            KeyError: If no indexed document (or more than one) has that path.
+        """
+        with self._index_lock:
+            if self._index is None:
+                return None
+            graph = self._index.link_graph()
+        document = graph.find(path)
+        if document is None:
+            raise KeyError(path)
+        outbound, inbound = graph.links(document)
+        return {"path": document, "outbound": outbound, "inbound": inbound}
+
+    def verify_index(
+        self, index_name: str, directories: List[str], remove: bool = False
+    ) -> Optional[Dict[str, Any]]:
+        """
+        Find chunks of files that are no longer indexed: deleted or renamed
+        ones, and ones an ignore file or the directories config now leaves
+        out, which would otherwise keep turning up as sources.
+
+        Args:
+            index_name: Name of the index, to save it after removing chunks.
+            directories: Directories the index should cover.
+            remove: Remove the orphaned chunks and their manifest entries.
+
+        Returns:
+            The orphaned files with their chunk counts and reasons
+            ("missing" or "excluded"), and whether they were removed, or
+            None if no index is loaded.
+        """
+        current = get_file_mtimes(directories, self.extensions)
+        with self._index_lock:
+            index = self._index
+            if index is None:
+                return None
+            orphans: Dict[str, List[int]] = {}
+            for idx, meta in enumerate(index.metadata):
+                source = str(meta.get("file_path", "") or meta.get("source", ""))
+                if source and source not in current and idx < len(index.chunk_ids):
+                    orphans.setdefault(source, []).append(index.chunk_ids[idx])
+
+            removed = remove and bool(orphans)
+            if removed:
+                index.remove_chunks([i for ids in orphans.values() for i in ids])
+                self.chunk_cache.clear()
+                for file_path in orphans:
+                    self.manifest.remove_file_metadata(index_name, file_path)
+                    self.manifest.set_file_issue(index_name, file_path, None)
+                index.save_index(index_name)
+
+        return {
+            "orphans": [
+                {
+                    "path": path,
+                    "chunks": len(ids),
+                    "reason": "excluded" if Path(path).exists() else "missing",
+                }
+                for path, ids in sorted(orphans.items())
+            ],
+            "removed": removed,
+        }
+
+    def _embedding_generator(self) -> EmbeddingGenerator:
+        """An embedding generator using the cache of the indexes."""
+        return EmbeddingGenerator(
+            api_config=self.api_config,
+            cache_dir=self.cache_manager.embedding_dir,
+            cipher=self.cache_manager.cipher,
+        )
+
+    def snapshot_index(
+        self, index_name: str, directories: List[str], path: Path
+    ) -> Optional[Dict[str, Any]]:
+        """
+        Write the current index to a snapshot archive (see snapshot.py), so
+        teammates can restore it instead of embedding the same documents.
+
+        Args:
+            index_name: Name of the index.
+            directories: Directories the index covers.
+            path: Archive to write; its name picks the compression.
+
+        Returns:
+            The chunks, the embedding model and the size of the archive in
+            bytes, or None if no index is loaded.
+
+        Raises:
+            ValueError: If the file name has no known archive ending.
+        """
+        generator = self._embedding_generator()
+        cipher = self.cache_manager.cipher
+        faiss_path, metadata_path = self.cache_manager.get_index_path(index_name)
+        ann_path = self.cache_manager.get_ann_path(index_name)
+        with self._index_lock:
+            index = self._index
+            if index is None:
+                return None
+            members = {
+                "index.faiss": read_file(faiss_path, cipher),
+                "index.pkl": read_file(metadata_path, cipher),
+            }
+            if ann_path.exists():
+                members["index.hnsw"] = read_file(ann_path, cipher)
+            members[MANIFEST_MEMBER] = json.dumps(
+                self.manifest.get_index_entry(index_name) or {"directories": directories}
+            ).encode("utf-8")
+            for text, chunk_id in zip(index.texts, index.chunk_ids):
+                entry = {"embedding": index.get_embedding(chunk_id), "text": text[:100]}
+                members[f"embeddings/{generator.cache_file_name(text)}"] = json.dumps(
+                    entry
+                ).encode("utf-8")
+            chunks = len(index.chunk_ids)
+
+        size_bytes = write_snapshot(
+            path,
+            {
+                "index": index_name,
+                "embedding_model": generator.embedding_model,
+                "chunks": chunks,
+                "directories": directories,
+            },
+            members,
+        )
+        return {
+            "chunks": chunks,
+            "embedding_model": generator.embedding_model,
+            "size_bytes": size_bytes,
+        }
+
+    def restore_snapshot(self, index_name: str, directories: List[str], path: Path) -> Dict[str, Any]:
+        """
+        Replace an index with a snapshot's and load it. The snapshot's
+        embeddings are added to the embedding cache, so documents the next
+        reload finds changed (e.g. checked out at other paths) are chunked
+        again without embedding API calls.
+
+        Args:
+            index_name: Name to restore the index as.
+            directories: Directories the index covers, if the snapshot
+                doesn't say.
+            path: Archive written by ``snapshot_index``.
+
+        Returns:
+            The chunks, the embedding model and the size of the archive in
+            bytes.
+
+        Raises:
+            FileNotFoundError: If there is no such file.
+            ValueError: If it is not a snapshot, or was embedded with
+                another model than this server's.
+        """
+        info, members = read_snapshot(path)
+        generator = self._embedding_generator()
+        if info.get("embedding_model") != generator.embedding_model:
+            raise ValueError(
+                f"The snapshot was embedded with {info.get('embedding_model')}, "
+                f"but this server embeds with {generator.embedding_model}"
+            )
+
+        cipher = self.cache_manager.cipher
+        for name, data in members.items():
+            if name.startswith("embeddings/"):
+                write_file(self.cache_manager.embedding_dir / Path(name).name, data, cipher)
+
+        faiss_path, metadata_path = self.cache_manager.get_index_path(index_name)
+        ann_path = self.cache_manager.get_ann_path(index_name)
+        entry = (
+            json.loads(members[MANIFEST_MEMBER])
+            if MANIFEST_MEMBER in members
+            else {"directories": directories}
+        )
+        with self._index_lock:
+            write_file(faiss_path, members["index.faiss"], cipher)
+            write_file(metadata_path, members["index.pkl"], cipher)
+            if "index.hnsw" in members:
+                write_file(ann_path, members["index.hnsw"], cipher)
+            else:
+                ann_path.unlink(missing_ok=True)
+            self.manifest.set_index_entry(index_name, entry)
+
+            vector_store = VectorStore(
+                cache_manager=self.cache_manager,
+                api_config=self.api_config,
+                ann=self.ann,
+            )
+            vector_store.load_index(index_name)
+            self.swap_index(vector_store)
+
+        return {
+            "chunks": len(vector_store.chunk_ids),
+            "embedding_model": generator.embedding_model,
+            "size_bytes": path.stat().st_size,
+        }
+
+    def estimate_reload(self, index_name: str, directories: List[str]) -> Dict[str, Any]:
+        """
+        Estimate what a reload would embed, so a client can confirm a large
+        build first: the files incremental_update would read (all of them
+        when it would fall back to a full rebuild), chunked the same way,
+        leaving out chunks whose embeddings are cached.
+
+        Args:
+            index_name: Name of the index.
+            directories: Directories the index covers.
+
+        Returns:
+            The embedding model, whether the reload is a full rebuild, each
+            file with the chunks and tokens it would embed, their totals,
+            the cached chunks and a rough time in seconds.
+        """
+        full_rebuild = (
+            self.get_index() is None
+            or not self.validator.index_exists(index_name)
+            or not self.manifest.has_per_file_metadata(index_name)
+        )
+        if full_rebuild:
+            paths, _ = plan_markdown_files(directories, self.extensions)
+        else:
+            added, modified, _ = self.manifest.detect_file_changes(
+                index_name, directories, self.extensions
+            )
+            paths = sorted(added | modified)
+
+        generator = self._embedding_generator()
+        chunker = MarkdownChunker(include_drafts=self.index_drafts, chunking=self.chunking)
+        files: List[Dict[str, Any]] = []
+        cached = 0
+        for file_path in paths:
+            try:
+                chunks = chunker.chunk_files(
+                    iter_single_file(file_path, max_file_size_mb=self.max_file_size_mb)
+                )
+            except Exception:
+                # Skipped by the build too, which reports why
+                continue
+            texts = [chunk["text"] for chunk in chunks]
+            uncached = [text for text in texts if not generator.is_cached(text)]
+            cached += len(texts) - len(uncached)
+            files.append(
+                {
+                    "path": file_path,
+                    "chunks": len(uncached),
+                    "tokens": sum(
+                        count_tokens(text, generator.embedding_model) for text in uncached
+                    ),
+                }
+            )
+
+        chunk_total = sum(file["chunks"] for file in files)
+        return {
+            "embedding_model": generator.embedding_model,
+            "full_rebuild": full_rebuild,
+            "files": files,
+            "chunks": chunk_total,
+            "tokens": sum(file["tokens"] for file in files),
+            "cached_chunks": cached,
+            "seconds": estimate_seconds(
+                chunk_total,
+                generator.batch_size,
+                generator.concurrency,
+                generator.limiter.per_minute,
+            ),
+        }
+
     def has_changes(self, index_name: str, directories: list[str]) -> Tuple[bool, str]:
         """
         Check if directories have changed since last index build.
@@ -174,7 +618,7 @@ class IndexManager:
         Returns:
             Tuple of (has_changes, current_checksum).
         """
-        current_checksum = compute_directories_checksum(directories)
+        current_checksum = compute_directories_checksum(directories, self.extensions)
         stored_checksum = self.manifest.get_index_checksum(index_name)
 
         if stored_checksum is None:
@@ -232,7 +676,7 @@ class IndexManager:
 
         # Detect file changes
         added, modified, deleted = self.manifest.detect_file_changes(
-            index_name, directories
+            index_name, directories, self.extensions
         )
 
         result = IncrementalUpdateResult(
@@ -254,8 +698,7 @@ class IndexManager:
             )
 
         # Perform incremental update
-        from markdown_qa.chunker import MarkdownChunker
-        chunker = MarkdownChunker()
+        chunker = MarkdownChunker(include_drafts=self.index_drafts, chunking=self.chunking)
 
         # 1. Remove chunks for deleted files (always safe to remove)
         chunks_to_remove: List[int] = []
@@ -263,6 +706,7 @@ class IndexManager:
             chunk_ids = self.manifest.get_chunk_ids_for_file(index_name, file_path)
             chunks_to_remove.extend(chunk_ids)
             self.manifest.remove_file_metadata(index_name, file_path)
+            self.manifest.set_file_issue(index_name, file_path, None)
 
         # 2. Track old chunk IDs for modified files before processing
         # We'll only remove them after successfully loading new content
@@ -275,13 +719,27 @@ class IndexManager:
         # 3. Process added and modified files
         new_chunks: List[Dict[str, Any]] = []
         new_chunk_ids: List[int] = []
-        file_mtimes = get_file_mtimes(directories)
+        file_mtimes = get_file_mtimes(directories, self.extensions)
         successfully_processed_modified: List[str] = []
+        files_to_process = result.added_files + result.modified_files
+        failed: List[Dict[str, str]] = []
 
-        for file_path in result.added_files + result.modified_files:
+        for done, file_path in enumerate(files_to_process, start=1):
+            error: Optional[str] = None
             try:
-                path, content = load_single_file(file_path)
-                file_chunks = chunker.chunk_files([(path, content)])
+                file_issues: List[str] = []
+                file_chunks = chunker.chunk_files(
+                    iter_single_file(
+                        file_path,
+                        max_file_size_mb=self.max_file_size_mb,
+                        issues=file_issues,
+                    )
+                )
+                if file_issues:
+                    self.logger.warning(f"{file_path}: {'; '.join(file_issues)}")
+                self.manifest.set_file_issue(
+                    index_name, file_path, "; ".join(file_issues) or None
+                )
 
                 file_chunk_ids: List[int] = []
                 for idx, chunk in enumerate(file_chunks):
@@ -294,6 +752,7 @@ class IndexManager:
                 self.manifest.set_file_metadata(index_name, file_path, {
                     "mtime": file_mtimes.get(file_path, 0),
                     "chunk_ids": file_chunk_ids,
+                    "indexed_at": int(time.time()),
                 })
 
                 # Track successfully processed modified files for chunk removal
@@ -305,12 +764,27 @@ class IndexManager:
                 self.logger.debug(
                     f"Skipping {file_path}: file appears to be actively being edited"
                 )
-                continue
+            except FileTooLargeError as e:
+                # For modified files, keep old chunks in place (don't remove them)
+                self.logger.warning(f"Skipping {file_path}: {e}")
+                error = f"skipped: {e}"
             except Exception as e:
                 # Skip files that can't be processed for other reasons
                 # For modified files, keep old chunks in place (don't remove them)
                 self.logger.warning(f"Failed to process file {file_path}: {e}")
-                continue
+                error = f"failed to read: {e}"
+            if error is not None:
+                failed.append({"path": file_path, "issue": error})
+                self.manifest.set_file_issue(index_name, file_path, error)
+            if self.on_progress:
+                update: Dict[str, Any] = {
+                    "done": done,
+                    "total": len(files_to_process),
+                    "path": file_path,
+                }
+                if error is not None:
+                    update["error"] = error
+                self.on_progress(update)
 
         # 4. Remove old chunks for successfully processed modified files
         for file_path in successfully_processed_modified:
@@ -323,12 +797,24 @@ class IndexManager:
 
         if new_chunks:
             current_index.add_chunks_with_ids(new_chunks, new_chunk_ids)
+        self.chunk_cache.clear()
 
         # Save the updated index
         current_index.save_index(index_name)
+        if self.on_progress and files_to_process:
+            self.on_progress(
+                {
+                    "done": len(files_to_process),
+                    "total": len(files_to_process),
+                    "chunks": len(new_chunks),
+                    "embedded": len(new_chunks),
+                    "finished": True,
+                    "failed": failed,
+                }
+            )
 
         # Update overall checksum
-        checksum = compute_directories_checksum(directories)
+        checksum = compute_directories_checksum(directories, self.extensions)
         self.update_checksum(index_name, directories, checksum)
 
         return result
@@ -337,23 +823,35 @@ class IndexManager:
         """Perform a full index rebuild and store per-file metadata."""
         vector_store = VectorStore(
             cache_manager=self.cache_manager,
+            chunker=MarkdownChunker(include_drafts=self.index_drafts, chunking=self.chunking),
             api_config=self.api_config,
+            max_file_size_mb=self.max_file_size_mb,
+            extensions=self.extensions,
+            ann=self.ann,
+        )
+        issues: Dict[str, str] = {}
+        vector_store.build_index(
+            directories,
+            index_name=index_name,
+            show_progress=True,
+            issues=issues,
+            progress=self.on_progress,
         )
-        vector_store.build_index(directories, index_name=index_name, show_progress=True)
         self.swap_index(vector_store)
 
         # Update checksum first to ensure index exists in manifest
-        checksum = compute_directories_checksum(directories)
+        checksum = compute_directories_checksum(directories, self.extensions)
         self.update_checksum(index_name, directories, checksum)
 
         # Store per-file metadata for future incremental updates
         self._store_per_file_metadata(index_name, directories, vector_store)
+        self.manifest.set_file_issues(index_name, issues)
 
     def _store_per_file_metadata(
         self, index_name: str, directories: List[str], vector_store: VectorStore
     ) -> None:
         """Store per-file metadata after building an index."""
-        file_mtimes = get_file_mtimes(directories)
+        file_mtimes = get_file_mtimes(directories, self.extensions)
 
         # Group chunk IDs by source file
         # Note: Chunker stores file path as "file_path" in metadata
@@ -366,8 +864,10 @@ class IndexManager:
                 file_to_chunks[source].append(vector_store.chunk_ids[idx])
 
         # Store metadata for each file
+        indexed_at = int(time.time())
         for file_path, chunk_ids in file_to_chunks.items():
             self.manifest.set_file_metadata(index_name, file_path, {
                 "mtime": file_mtimes.get(file_path, 0),
                 "chunk_ids": chunk_ids,
+                "indexed_at": indexed_at,
             })
@@ -0,0 +1,188 @@
+"""Wiki-link and relative-link extraction, and the graph of linked documents."""
+
+import os
+import re
+from collections import defaultdict
+from pathlib import Path
+from typing import Any, Dict, Iterable, List, Optional, Set, Tuple
+from urllib.parse import unquote
+
+# [[Target]], [[Target|alias]], [[Target#Heading]] and embeds ![[Target]]
+_WIKILINK = re.compile(r"!?\[\[([^\[\]|#]*)(?:#[^\[\]|]*)?(?:\|[^\[\]]*)?\]\]")
+
+# [text](target) and [text](<target> "title"), but not images
+_MARKDOWN_LINK = re.compile(r"(?<!!)\[[^\]]*\]\(\s*(?:<([^>]*)>|([^)\s]+))[^)]*\)")
+
+# Fenced code blocks and inline code, whose brackets are not links
+_CODE = re.compile(r"^(`{3,}|~{3,}).*?^\1|`[^`\n]*`", re.MULTILINE | re.DOTALL)
+
+# A URL scheme such as "https:" or "mailto:"
+_SCHEME = re.compile(r"^[A-Za-z][A-Za-z0-9+.-]*:")
+
+
+def extract_links(content: str, file_path: Path) -> Tuple[List[str], List[str]]:
+    """
+    Find the links of a markdown text to other markdown documents.
+
+    Args:
+        content: Markdown text, e.g. one chunk of a file.
+        file_path: File the text is from, to resolve relative links against.
+
+    Returns:
+        Tuple of (paths, wikilinks): the normalized paths of relative links to
+        ``.md`` files, and the targets of ``[[wikilinks]]`` as written
+        (without heading or alias), which are resolved by name against the
+        indexed documents (see LinkGraph). Both in order of first appearance.
+    """
+    content = _CODE.sub("", content)
+    directory = os.path.dirname(str(file_path))
+
+    paths: Dict[str, None] = {}
+    for match in _MARKDOWN_LINK.finditer(content):
+        target = match.group(1) or match.group(2) or ""
+        if not target or target.startswith("#") or _SCHEME.match(target):
+            continue
+        target = unquote(target.split("#", 1)[0].split("?", 1)[0])
+        if target.lower().endswith(".md"):
+            paths[os.path.normpath(os.path.join(directory, target))] = None
+
+    wikilinks: Dict[str, None] = {}
+    for match in _WIKILINK.finditer(content):
+        target = match.group(1).strip()
+        if target:
+            wikilinks[target] = None
+
+    return list(paths), list(wikilinks)
+
+
+class LinkGraph:
+    """
+    Links between the documents of an index, from the ``links`` and
+    ``wikilinks`` the chunker stores in chunk metadata. Links to files that
+    are not indexed are left out.
+    """
+
+    def __init__(
+        self, outbound: Dict[str, List[str]], documents: Optional[Iterable[str]] = None
+    ):
+        """
+        Initialize the graph.
+
+        Args:
+            outbound: For each document, the documents it links to.
+            documents: Every indexed document, linked or not. If None, the
+                documents in ``outbound``.
+        """
+        self.outbound = outbound
+        self.documents = set(outbound) if documents is None else set(documents)
+        for targets in outbound.values():
+            self.documents.update(targets)
+        inbound: Dict[str, Set[str]] = defaultdict(set)
+        for source, targets in outbound.items():
+            for target in targets:
+                inbound[target].add(source)
+        self.inbound = {target: sorted(sources) for target, sources in inbound.items()}
+
+    @classmethod
+    def from_metadata(cls, metadata: Iterable[Dict[str, Any]]) -> "LinkGraph":
+        """
+        Build the graph from chunk metadata.
+
+        A wikilink names a document by its path relative to the linking
+        file, or else by file name; when several documents have that name,
+        the one with the shortest path wins, as in Obsidian.
+
+        Args:
+            metadata: Metadata of every chunk in the index.
+
+        Returns:
+            The link graph.
+        """
+        paths: Dict[str, Set[str]] = defaultdict(set)
+        wikilinks: Dict[str, Set[str]] = defaultdict(set)
+        for meta in metadata:
+            source = str(meta.get("file_path", "") or meta.get("source", ""))
+            if not source:
+                continue
+            paths[source].update(meta.get("links") or [])
+            wikilinks[source].update(meta.get("wikilinks") or [])
+
+        documents = set(paths)
+        by_name: Dict[str, List[str]] = defaultdict(list)
+        for document in documents:
+            by_name[Path(document).stem.lower()].append(document)
+        for candidates in by_name.values():
+            candidates.sort(key=lambda path: (len(Path(path).parts), path))
+
+        def resolve(source: str, name: str) -> List[str]:
+            if not name.lower().endswith(".md"):
+                name += ".md"
+            relative = os.path.normpath(os.path.join(os.path.dirname(source), name))
+            if relative in documents:
+                return [relative]
+            suffix = "/" + name.lower().replace("\\", "/").lstrip("./")
+            matches = [
+                document
+                for document in by_name.get(Path(name).stem.lower(), [])
+                if document.lower().replace("\\", "/").endswith(suffix)
+            ]
+            return matches[:1]
+
+        outbound: Dict[str, List[str]] = {}
+        for source in documents:
+            targets = {path for path in paths[source] if path in documents}
+            for name in wikilinks[source]:
+                targets.update(resolve(source, name))
+            targets.discard(source)
+            if targets:
+                outbound[source] = sorted(targets)
+        return cls(outbound, documents)
+
+    def __bool__(self) -> bool:
+        """Whether any document links to another."""
+        return bool(self.outbound)
+
+    def __contains__(self, path: object) -> bool:
+        """Whether ``path`` is an indexed document."""
+        return path in self.documents
+
+    def find(self, path: str) -> Optional[str]:
+        """
+        Find an indexed document by its path as indexed, or by the end of
+        it (e.g. ``docs/guide.md`` for ``/home/me/vault/docs/guide.md``)
+        when exactly one document's path ends that way.
+
+        Args:
+            path: Path of the document.
+
+        Returns:
+            The document's path as indexed, or None if there is no such
+            document or more than one.
+        """
+        if path in self.documents:
+            return path
+        suffix = "/" + path.replace("\\", "/").lstrip("./")
+        matches = [
+            document
+            for document in self.documents
+            if document.replace("\\", "/").endswith(suffix)
+        ]
+        return matches[0] if len(matches) == 1 else None
+
+    def links(self, path: str) -> Tuple[List[str], List[str]]:
+        """
+        The links of one document.
+
+        Args:
+            path: Path of the document, as indexed.
+
+        Returns:
+            Tuple of (outbound, inbound): the documents it links to and the
+            ones linking to it, sorted.
+        """
+        return list(self.outbound.get(path, [])), list(self.inbound.get(path, []))
+
+    def neighbors(self, path: str) -> Set[str]:
+        """Documents linked with ``path`` in either direction."""
+        outbound, inbound = self.links(path)
+        return set(outbound) | set(inbound)
@@ -1,10 +1,30 @@
 """Markdown file loader module for loading markdown files from directories."""
 
+import codecs
 import hashlib
+import mmap
+import re
 import time
 import warnings
 from pathlib import Path
-from typing import Dict, List, Tuple
+from typing import Callable, Dict, Iterator, List, Optional, Sequence, Tuple
+
+from markdown_qa.extractors import EXTRACTORS, MARKDOWN_EXTENSIONS
+from markdown_qa.ignore import IGNORE_FILES, IgnoreRules
+
+# Default per-file size cap (server.max_file_size_mb); larger files are
+# skipped with a warning.
+DEFAULT_MAX_FILE_SIZE_MB = 256
+
+# Files larger than this are read through a memory map and chunked one
+# segment at a time, so a multi-hundred-MB export is never held whole.
+SEGMENT_SIZE = 4 * 1024 * 1024
+
+# Characters GBK text decodes to: CJK ideographs and punctuation, kana,
+# Hangul and full-width forms.
+_CJK_TEXT = re.compile(
+    "[\u3000-\u30ff\u3400-\u4dbf\u4e00-\u9fff\uac00-\ud7af\uff00-\uffef]"
+)
 
 
 class FileBeingEditedError(Exception):
@@ -13,12 +33,76 @@ class FileBeingEditedError(Exception):
     pass
 
 
-def count_markdown_files(directory: str) -> int:
+class FileTooLargeError(Exception):
+    """Raised when a file is larger than the per-file size cap."""
+
+    pass
+
+
+def find_markdown_files(
+    directory: Path,
+    ignored: Optional[List[Path]] = None,
+    extensions: Sequence[str] = MARKDOWN_EXTENSIONS,
+) -> List[Path]:
+    """
+    Find the markdown files under a directory that its ignore files (see
+    markdown_qa.ignore) don't leave out.
+
+    Args:
+        directory: Configured directory to search recursively.
+        ignored: If given, extended with the markdown files that were left
+            out.
+        extensions: File extensions to index: ``.md`` and those of the
+            formats enabled in server.formats (see markdown_qa.extractors).
+
+    Returns:
+        The markdown files to index, sorted.
+    """
+    rules = IgnoreRules.load(directory)
+    found: List[Path] = []
+    candidates = {f for ext in extensions for f in directory.rglob(f"*{ext}")}
+    for md_file in sorted(candidates):
+        if rules.is_ignored(md_file):
+            if ignored is not None:
+                ignored.append(md_file)
+        else:
+            found.append(md_file)
+    return found
+
+
+def plan_markdown_files(
+    directories: List[str], extensions: Sequence[str] = MARKDOWN_EXTENSIONS
+) -> Tuple[List[str], List[str]]:
+    """
+    List the markdown files an index of the directories would hold, to test
+    ignore files without indexing anything.
+
+    Args:
+        directories: Configured directories.
+        extensions: File extensions to index (see find_markdown_files).
+
+    Returns:
+        Tuple of (files to index, files left out by ignore files).
+    """
+    files: List[Path] = []
+    ignored: List[Path] = []
+    for directory_str in directories:
+        directory = Path(directory_str)
+        if directory.is_dir():
+            files.extend(find_markdown_files(directory, ignored, extensions))
+    return [str(f) for f in files], [str(f) for f in ignored]
+
+
+def count_markdown_files(
+    directory: str, extensions: Sequence[str] = MARKDOWN_EXTENSIONS
+) -> int:
     """
-    Count the number of markdown files in a directory recursively.
+    Count the number of markdown files in a directory recursively, leaving
+    out ignored ones.
 
     Args:
         directory: Path to directory to count markdown files in.
+        extensions: File extensions to index (see find_markdown_files).
 
     Returns:
         Number of markdown files found, or 0 if directory doesn't exist.
@@ -26,7 +110,7 @@ def count_markdown_files(directory: str) -> int:
     dir_path = Path(directory)
     if not dir_path.exists() or not dir_path.is_dir():
         return 0
-    return len(list(dir_path.rglob("*.md")))
+    return len(find_markdown_files(dir_path, extensions=extensions))
 
 
 def is_file_stable(file_path: Path, stability_window: float = 2.0) -> bool:
@@ -58,21 +142,189 @@ def is_file_stable(file_path: Path, stability_window: float = 2.0) -> bool:
         return False
 
 
-def load_markdown_files(directories: List[str]) -> List[Tuple[Path, str]]:
+def check_file_size(file_path: Path, max_file_size_mb: float) -> None:
     """
-    Load all markdown files from specified directories recursively.
+    Check a file against the per-file size cap.
 
     Args:
-        directories: List of directory paths to search for markdown files.
+        file_path: Path to the file to check.
+        max_file_size_mb: Largest file size to accept, in megabytes.
+
+    Raises:
+        FileTooLargeError: If the file is larger than the cap.
+    """
+    size = file_path.stat().st_size
+    if size > max_file_size_mb * 1024 * 1024:
+        raise FileTooLargeError(
+            f"file is {size / (1024 * 1024):.1f} MB, "
+            f"over the {max_file_size_mb} MB limit (server.max_file_size_mb)"
+        )
+
+
+def _segment_end(data: mmap.mmap, start: int, segment_size: int) -> int:
+    """
+    End of the segment starting at ``start``: before the last heading within
+    ``segment_size`` bytes, else after the last line break, else at a UTF-8
+    character boundary.
+    """
+    end = start + segment_size
+    if end >= len(data):
+        return len(data)
+    heading = data.rfind(b"\n#", start + 1, end)
+    if heading != -1:
+        return heading + 1
+    newline = data.rfind(b"\n", start, end)
+    if newline != -1:
+        return newline + 1
+    while (data[end] & 0xC0) == 0x80:
+        end -= 1
+    return end
+
+
+def decode_markdown(data: bytes) -> Tuple[str, Optional[str]]:
+    """
+    Decode the bytes of a markdown file, whatever encoding it was saved in.
+
+    A byte order mark picks UTF-8 or UTF-16 and is dropped. Otherwise UTF-8
+    is tried first, then GBK (if the text comes out as CJK), then Latin-1
+    and Windows-1252; bytes none of them fit are replaced with U+FFFD.
+    Line endings are normalized to ``\\n``.
+
+    Args:
+        data: Raw file content.
 
     Returns:
-        List of tuples containing (file_path, content) for each markdown file.
+        Tuple of (text, issue), where issue says how the file was decoded
+        if it was not UTF-8, or None.
+    """
+    issue = None
+    if data.startswith(codecs.BOM_UTF8):
+        data, encoding = data[len(codecs.BOM_UTF8):], "utf-8"
+    elif data.startswith((codecs.BOM_UTF16_LE, codecs.BOM_UTF16_BE)):
+        encoding = "utf-16"
+    else:
+        encoding = None
+    try:
+        text = data.decode(encoding or "utf-8")
+    except UnicodeDecodeError:
+        if encoding is None:
+            text, issue = _decode_legacy(data)
+        else:
+            text = data.decode(encoding, errors="replace")
+            issue = f"not valid {encoding.upper()}; undecodable bytes were replaced"
+    return text.replace("\r\n", "\n").replace("\r", "\n"), issue
+
+
+def _decode_legacy(data: bytes) -> Tuple[str, str]:
+    """Decode bytes that are not UTF-8 as GBK, Latin-1 or Windows-1252, else lossily."""
+    try:
+        text = data.decode("gbk")
+    except UnicodeDecodeError:
+        text = ""
+    # Latin-1 text is often valid GBK too, but an accented letter followed by
+    # an ASCII one decodes to a character whose second byte is ASCII; real
+    # GBK text is mostly CJK characters with both bytes above 0x80
+    non_ascii = [c for c in text if ord(c) >= 0x80]
+    cjk = [c for c in non_ascii if _CJK_TEXT.match(c) and c.encode("gbk")[-1] >= 0x80]
+    if non_ascii and len(cjk) >= 0.9 * len(non_ascii):
+        return text, "not UTF-8; decoded as GBK"
+    # Latin-1 decodes any byte, but 0x80-0x9f are control characters in it;
+    # Windows-1252 has quotes and dashes there instead
+    if not any(0x80 <= b <= 0x9F for b in data):
+        return data.decode("latin-1"), "not UTF-8; decoded as Latin-1"
+    try:
+        return data.decode("cp1252"), "not UTF-8; decoded as Windows-1252"
+    except UnicodeDecodeError:
+        pass
+    return (
+        data.decode("utf-8", errors="replace"),
+        "not UTF-8 or a known legacy encoding; undecodable bytes were replaced",
+    )
+
+
+def read_segments(
+    file_path: Path,
+    segment_size: int = SEGMENT_SIZE,
+    issues: Optional[List[str]] = None,
+) -> Iterator[str]:
+    """
+    Read a markdown file in segments of at most ``segment_size`` bytes.
+
+    Files up to ``segment_size`` are read in one go; larger ones are read
+    through a memory map and cut at headings where possible, so each segment
+    can be chunked on its own. Each segment is decoded with decode_markdown.
+    Files of other formats (see markdown_qa.extractors) are read whole and
+    yielded as one markdown segment.
+
+    Args:
+        file_path: Path to the markdown file.
+        segment_size: Largest segment to read, in bytes.
+        issues: If given, how the file was decoded is appended to it when it
+            was not UTF-8 (once per distinct issue).
+
+    Yields:
+        Consecutive parts of the file's content, with line endings normalized.
+    """
+    def decode(data: bytes) -> str:
+        text, issue = decode_markdown(data)
+        if issue is not None and issues is not None and issue not in issues:
+            issues.append(issue)
+        return text
+
+    extractor = EXTRACTORS.get(file_path.suffix.lower())
+    if extractor is not None:
+        yield extractor(file_path.read_bytes())
+        return
+
+    if file_path.stat().st_size <= segment_size:
+        yield decode(file_path.read_bytes())
+        return
+
+    with open(file_path, "rb") as f:
+        with mmap.mmap(f.fileno(), 0, access=mmap.ACCESS_READ) as data:
+            start = 0
+            while start < len(data):
+                end = _segment_end(data, start, segment_size)
+                yield decode(data[start:end])
+                start = end
+
+
+def iter_markdown_files(
+    directories: List[str],
+    max_file_size_mb: float = DEFAULT_MAX_FILE_SIZE_MB,
+    issues: Optional[Dict[str, str]] = None,
+    progress: Optional[Callable[[int, int, Path, Optional[str]], None]] = None,
+    extensions: Sequence[str] = MARKDOWN_EXTENSIONS,
+) -> Iterator[Tuple[Path, str]]:
+    """
+    Read all markdown files from specified directories recursively, one
+    segment at a time.
+
+    Files left out by the directory's ignore files are not read. Files
+    larger than ``SEGMENT_SIZE`` are yielded as several consecutive
+    (file_path, segment) pairs; files over the size cap, files being edited
+    and unreadable files are skipped with a warning. Files that are not
+    UTF-8 are decoded anyway (see decode_markdown), also with a warning.
+
+    Args:
+        directories: List of directory paths to search for markdown files.
+        max_file_size_mb: Per-file size cap, in megabytes.
+        issues: If given, filled with how each file that was not UTF-8 was
+            decoded, or why it was not indexed, by file path, for the index
+            report.
+        progress: Called after each file with (files done, files found,
+            path, why the file was not indexed or None).
+        extensions: File extensions to index (see find_markdown_files).
+
+    Yields:
+        Tuples containing (file_path, content) for each file segment.
 
     Raises:
-        ValueError: If a directory doesn't exist or no markdown files are found.
+        ValueError: If a directory doesn't exist and no markdown files are found.
     """
-    markdown_files: List[Tuple[Path, str]] = []
+    loaded = False
     errors: List[str] = []
+    md_files: List[Path] = []
 
     for directory_str in directories:
         directory = Path(directory_str)
@@ -85,49 +337,83 @@ def load_markdown_files(directories: List[str]) -> List[Tuple[Path, str]]:
             continue
 
         # Find all .md files recursively
-        md_files = list(directory.rglob("*.md"))
-        if not md_files:
+        found = find_markdown_files(directory, extensions=extensions)
+        if not found:
             warnings.warn(f"No markdown files found in directory: {directory}")
             continue
-
-        # Load content from each markdown file
-        for md_file in md_files:
-            try:
-                # Skip files that appear to be actively being edited
-                if not is_file_stable(md_file):
-                    warnings.warn(
-                        f"Skipping {md_file}: file appears to be actively being edited"
-                    )
-                    continue
-
-                content = md_file.read_text(encoding="utf-8")
-                markdown_files.append((md_file, content))
-            except FileBeingEditedError:
-                warnings.warn(
-                    f"Skipping {md_file}: file appears to be actively being edited"
-                )
-                continue
-            except Exception as e:
-                warnings.warn(f"Failed to read file {md_file}: {e}")
-                continue
-
-    if not markdown_files and errors:
+        md_files.extend(found)
+
+    # Load content from each markdown file
+    for done, md_file in enumerate(md_files, start=1):
+        error: Optional[str] = None
+        try:
+            # Skip files that appear to be actively being edited
+            if not is_file_stable(md_file):
+                raise FileBeingEditedError()
+
+            check_file_size(md_file, max_file_size_mb)
+            file_issues: List[str] = []
+            for segment in read_segments(md_file, issues=file_issues):
+                yield md_file, segment
+                loaded = True
+            if file_issues:
+                warnings.warn(f"{md_file}: {'; '.join(file_issues)}")
+                if issues is not None:
+                    issues[str(md_file)] = "; ".join(file_issues)
+        except FileTooLargeError as e:
+            warnings.warn(f"Skipping {md_file}: {e}")
+            error = f"skipped: {e}"
+        except FileBeingEditedError:
+            warnings.warn(
+                f"Skipping {md_file}: file appears to be actively being edited"
+            )
+        except Exception as e:
+            warnings.warn(f"Failed to read file {md_file}: {e}")
+            error = f"failed to read: {e}"
+        if error is not None and issues is not None:
+            issues[str(md_file)] = error
+        if progress is not None:
+            progress(done, len(md_files), md_file, error)
+
+    if not loaded and errors:
         raise ValueError(
             f"Failed to load any markdown files. Errors: {'; '.join(errors)}"
         )
 
-    return markdown_files
+
+def load_markdown_files(
+    directories: List[str], max_file_size_mb: float = DEFAULT_MAX_FILE_SIZE_MB
+) -> List[Tuple[Path, str]]:
+    """
+    Load all markdown files from specified directories recursively.
+
+    Args:
+        directories: List of directory paths to search for markdown files.
+        max_file_size_mb: Per-file size cap, in megabytes.
+
+    Returns:
+        List of tuples containing (file_path, content) for each markdown file,
+        or for each segment of a file larger than ``SEGMENT_SIZE``.
+
+    Raises:
+        ValueError: If a directory doesn't exist or no markdown files are found.
+    """
+    return list(iter_markdown_files(directories, max_file_size_mb))
 
 
-def compute_directories_checksum(directories: List[str]) -> str:
+def compute_directories_checksum(
+    directories: List[str], extensions: Sequence[str] = MARKDOWN_EXTENSIONS
+) -> str:
     """
     Compute a checksum for markdown files in directories.
 
     The checksum is based on file paths and modification times, so it will
-    change when files are added, removed, or modified.
+    change when files are added, removed, or modified, and when an ignore
+    file changes which files are indexed.
 
     Args:
         directories: List of directory paths to compute checksum for.
+        extensions: File extensions to index (see find_markdown_files).
 
     Returns:
         A hex digest string representing the current state of markdown files.
@@ -139,8 +425,10 @@ def compute_directories_checksum(directories: List[str]) -> str:
         if not directory.exists() or not directory.is_dir():
             continue
 
-        # Find all .md files recursively
-        for md_file in directory.rglob("*.md"):
+        # Find all .md files recursively, and the ignore files
+        ignore_files = [directory / name for name in IGNORE_FILES]
+        found = find_markdown_files(directory, extensions=extensions)
+        for md_file in found + ignore_files:
             try:
                 mtime = md_file.stat().st_mtime
                 # Use relative path from directory for consistency
@@ -160,12 +448,16 @@ def compute_directories_checksum(directories: List[str]) -> str:
     return hasher.hexdigest()
 
 
-def get_file_mtimes(directories: List[str]) -> Dict[str, float]:
+def get_file_mtimes(
+    directories: List[str], extensions: Sequence[str] = MARKDOWN_EXTENSIONS
+) -> Dict[str, float]:
     """
-    Get modification times for all markdown files in directories.
+    Get modification times for the markdown files in directories that are
+    not ignored.
 
     Args:
         directories: List of directory paths to scan.
+        extensions: File extensions to index (see find_markdown_files).
 
     Returns:
         Dict mapping absolute file paths to their mtime.
@@ -177,7 +469,7 @@ def get_file_mtimes(directories: List[str]) -> Dict[str, float]:
         if not directory.exists() or not directory.is_dir():
             continue
 
-        for md_file in directory.rglob("*.md"):
+        for md_file in find_markdown_files(directory, extensions=extensions):
             try:
                 file_mtimes[str(md_file)] = md_file.stat().st_mtime
             except OSError:
@@ -214,26 +506,62 @@ def generate_chunk_id(file_path: str, chunk_index: int) -> int:
     return chunk_id
 
 
-def load_single_file(file_path: str, check_stability: bool = True) -> Tuple[Path, str]:
+def load_single_file(
+    file_path: str,
+    check_stability: bool = True,
+    max_file_size_mb: Optional[float] = None,
+) -> Tuple[Path, str]:
     """
-    Load a single markdown file.
+    Load a single markdown file, or a file of another format as markdown.
 
     Args:
         file_path: Path to the markdown file.
         check_stability: If True, check if file is stable before reading.
+        max_file_size_mb: Per-file size cap, in megabytes. None means no cap.
 
     Returns:
         Tuple of (Path, content).
 
     Raises:
         FileNotFoundError: If file doesn't exist.
-        ValueError: If file is not a markdown file.
+        ValueError: If file is not a markdown file or of a format with an
+            extractor.
         FileBeingEditedError: If file appears to be actively being edited.
+        FileTooLargeError: If file is larger than ``max_file_size_mb``.
+    """
+    path = _check_single_file(file_path, check_stability, max_file_size_mb)
+    return path, "".join(read_segments(path))
+
+
+def iter_single_file(
+    file_path: str,
+    check_stability: bool = True,
+    max_file_size_mb: Optional[float] = None,
+    issues: Optional[List[str]] = None,
+) -> Iterator[Tuple[Path, str]]:
+    """
+    Read a single markdown file one segment at a time (see read_segments).
+
+    The file is checked before the first segment is read; the other
+    arguments and exceptions are those of load_single_file, and ``issues``
+    is passed on to read_segments.
+
+    Yields:
+        Tuples containing (Path, content) for each file segment.
     """
+    path = _check_single_file(file_path, check_stability, max_file_size_mb)
+    for segment in read_segments(path, issues=issues):
+        yield path, segment
+
+
+def _check_single_file(
+    file_path: str, check_stability: bool, max_file_size_mb: Optional[float]
+) -> Path:
+    """Check that a single file can be loaded and return its path."""
     path = Path(file_path)
     if not path.exists():
         raise FileNotFoundError(f"File not found: {file_path}")
-    if path.suffix != ".md":
+    if path.suffix != ".md" and path.suffix.lower() not in EXTRACTORS:
         raise ValueError(f"Not a markdown file: {file_path}")
 
     # Check if file is stable (not being actively edited)
@@ -241,6 +569,6 @@ def load_single_file(file_path: str, check_stability: bool = True) -> Tuple[Path
         raise FileBeingEditedError(
             f"File {file_path} appears to be actively being edited"
         )
-
-    content = path.read_text(encoding="utf-8")
-    return path, content
+    if max_file_size_mb is not None:
+        check_file_size(path, max_file_size_mb)
+    return path
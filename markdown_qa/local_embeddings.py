@@ -0,0 +1,80 @@
+"""Embeddings computed on this machine, for ``api.embedding_model: local:<model>``.
+
+The model, e.g. ``local:BAAI/bge-small-en-v1.5``, is run with
+sentence-transformers (an optional dependency), so neither indexing nor
+questions need an embedding API and the documents never leave the machine.
+"""
+
+import threading
+from importlib.util import find_spec
+from types import SimpleNamespace
+from typing import Any, List, Optional, Union
+
+# Prefix of api.embedding_model naming a local model
+LOCAL_PREFIX = "local:"
+
+
+def local_model_name(embedding_model: Optional[str]) -> Optional[str]:
+    """The model of a ``local:<model>`` embedding model, or None for an API model."""
+    if embedding_model and embedding_model.startswith(LOCAL_PREFIX):
+        return embedding_model[len(LOCAL_PREFIX) :].strip() or None
+    return None
+
+
+def _load_model(name: str) -> Any:
+    """Load a sentence-transformers model, downloading it on first use."""
+    from sentence_transformers import SentenceTransformer  # type: ignore[import-not-found]
+
+    return SentenceTransformer(name)
+
+
+class LocalEmbeddings:
+    """
+    Computes embeddings with a local model, answering in the shape of the
+    OpenAI client's ``client.embeddings.create(model=..., input=...)``.
+    """
+
+    def __init__(self, name: str):
+        """
+        Initialize local embeddings. The model is loaded on first use.
+
+        Args:
+            name: sentence-transformers model name or path.
+
+        Raises:
+            ImportError: If sentence-transformers is not installed.
+        """
+        if find_spec("sentence_transformers") is None:
+            raise ImportError(
+                "Local embedding models need sentence-transformers. "
+                "Install it with: pip install sentence-transformers"
+            )
+        self.name = name
+        self._model: Any = None
+        self._lock = threading.Lock()
+
+    def _loaded(self) -> Any:
+        with self._lock:
+            if self._model is None:
+                self._model = _load_model(self.name)
+            return self._model
+
+    def create(self, model: str, input: Union[str, List[str]]) -> SimpleNamespace:
+        """
+        Embed one text or several, normalized to unit length.
+
+        Args:
+            model: Ignored; the model is the one given when created.
+            input: Text or texts to embed.
+
+        Returns:
+            ``data`` with the ``index`` and ``embedding`` of each text.
+        """
+        texts = [input] if isinstance(input, str) else list(input)
+        vectors = self._loaded().encode(texts, normalize_embeddings=True)
+        return SimpleNamespace(
+            data=[
+                SimpleNamespace(index=i, embedding=[float(x) for x in vector])
+                for i, vector in enumerate(vectors)
+            ]
+        )
@@ -2,11 +2,16 @@
 
 import logging
 import sys
+import threading
 import time
+from collections import deque
 from contextlib import contextmanager
 from logging.handlers import RotatingFileHandler
 from pathlib import Path
-from typing import Generator, Optional
+from typing import Callable, Generator, Optional
+
+LOG_FORMAT = "[%(asctime)s] p%(process)s {%(pathname)s:%(lineno)d} %(levelname)s - %(message)s"
+LOG_DATE_FORMAT = "%Y-%m-%d %H:%M:%S"
 
 
 def setup_logger(
@@ -36,10 +41,7 @@ def setup_logger(
     logger.handlers.clear()
 
     # Create formatter
-    formatter = logging.Formatter(
-        fmt="[%(asctime)s] p%(process)s {%(pathname)s:%(lineno)d} %(levelname)s - %(message)s",
-        datefmt="%Y-%m-%d %H:%M:%S",
-    )
+    formatter = logging.Formatter(fmt=LOG_FORMAT, datefmt=LOG_DATE_FORMAT)
 
     # Add stdout handler
     stdout_handler = logging.StreamHandler(sys.stdout)
@@ -70,9 +72,67 @@ def setup_logger(
     return logger
 
 
+class LogTail(logging.Handler):
+    """Keeps the latest formatted log lines and passes each new one to
+    listeners, so clients can tail the server log over the protocol."""
+
+    def __init__(self, capacity: int = 1000) -> None:
+        """
+        Initialize log tail.
+
+        Args:
+            capacity: Number of latest lines kept.
+        """
+        super().__init__()
+        self.setFormatter(logging.Formatter(fmt=LOG_FORMAT, datefmt=LOG_DATE_FORMAT))
+        self._lines: deque[str] = deque(maxlen=capacity)
+        self._listeners: list[Callable[[str], None]] = []
+        self._lines_lock = threading.Lock()
+
+    def emit(self, record: logging.LogRecord) -> None:
+        """Keep the formatted record and pass it to the listeners."""
+        try:
+            line = self.format(record)
+        except Exception:
+            self.handleError(record)
+            return
+        with self._lines_lock:
+            self._lines.append(line)
+            listeners = list(self._listeners)
+        for listener in listeners:
+            listener(line)
+
+    def lines(self, count: int) -> list[str]:
+        """
+        Get the latest lines.
+
+        Args:
+            count: Number of lines wanted.
+
+        Returns:
+            Up to ``count`` latest lines, oldest first.
+        """
+        if count <= 0:
+            return []
+        with self._lines_lock:
+            return list(self._lines)[-count:]
+
+    def add_listener(self, listener: Callable[[str], None]) -> None:
+        """Call ``listener`` with every line logged from now on."""
+        with self._lines_lock:
+            self._listeners.append(listener)
+
+    def remove_listener(self, listener: Callable[[str], None]) -> None:
+        """Stop calling ``listener``; does nothing if it was not added."""
+        with self._lines_lock:
+            if listener in self._listeners:
+                self._listeners.remove(listener)
+
+
 # Module-level loggers for client and server
 _client_logger: Optional[logging.Logger] = None
 _server_logger: Optional[logging.Logger] = None
+_server_log_tail: Optional[LogTail] = None
 
 
 def get_client_logger() -> logging.Logger:
@@ -98,9 +158,23 @@ def get_server_logger() -> logging.Logger:
     global _server_logger
     if _server_logger is None:
         _server_logger = setup_logger("server")
+        _server_logger.addHandler(get_server_log_tail())
     return _server_logger
 
 
+def get_server_log_tail() -> LogTail:
+    """
+    Get or create the tail of the server log.
+
+    Returns:
+        Log tail attached to the server logger.
+    """
+    global _server_log_tail
+    if _server_log_tail is None:
+        _server_log_tail = LogTail()
+    return _server_log_tail
+
+
 class LatencyTracker:
     """Tracks latency for multiple operations within a request."""
 
@@ -2,7 +2,10 @@
 
 import json
 from pathlib import Path
-from typing import Any, Dict, List, Optional
+from typing import Any, Dict, List, Optional, Sequence
+
+from markdown_qa.extractors import MARKDOWN_EXTENSIONS
+from markdown_qa.loader import find_markdown_files
 
 
 class Manifest:
@@ -91,6 +94,18 @@ class Manifest:
             return checksum
         return None
 
+    def get_index_entry(self, index_name: str) -> Optional[Dict[str, Any]]:
+        """Everything recorded for an index (directories, checksum, files), e.g. to snapshot it."""
+        entry = self.read()["indexes"].get(index_name)
+        return entry if isinstance(entry, dict) else None
+
+    def set_index_entry(self, index_name: str, entry: Dict[str, Any]) -> None:
+        """Replace everything recorded for an index, e.g. when restoring a snapshot."""
+        self.create()
+        data = self.read()
+        data["indexes"][index_name] = entry
+        self._write(data)
+
     def list_indexes(self) -> List[str]:
         """List all index names in the manifest."""
         data = self.read()
@@ -207,15 +222,77 @@ class Manifest:
         files = data["indexes"][index_name].get("files", {})
         return len(files) > 0
 
+    # Index report: problems met while reading files, by file path
+
+    def set_file_issues(self, index_name: str, issues: Dict[str, str]) -> None:
+        """
+        Replace the recorded file issues of an index, after a full build.
+
+        Args:
+            index_name: Name of the index.
+            issues: Problem met while reading each file, by file path.
+        """
+        self.create()
+        data = self.read()
+        if index_name not in data["indexes"]:
+            raise ValueError(f"Index '{index_name}' does not exist")
+        data["indexes"][index_name]["issues"] = issues
+        self._write(data)
+
+    def set_file_issue(
+        self, index_name: str, file_path: str, issue: Optional[str]
+    ) -> None:
+        """
+        Record or clear the issue of one file, after reading it again.
+
+        Args:
+            index_name: Name of the index.
+            file_path: Absolute path to the file.
+            issue: Problem met while reading the file, or None if there was none.
+        """
+        data = self.read()
+        if index_name not in data["indexes"]:
+            return
+        issues = data["indexes"][index_name].get("issues", {})
+        if issue is None and file_path not in issues:
+            return
+        if issue is None:
+            del issues[file_path]
+        else:
+            issues[file_path] = issue
+        data["indexes"][index_name]["issues"] = issues
+        self._write(data)
+
+    def get_file_issues(self, index_name: str) -> Dict[str, str]:
+        """
+        Get the recorded file issues of an index.
+
+        Args:
+            index_name: Name of the index.
+
+        Returns:
+            Dict mapping file paths to the problem met while reading them.
+        """
+        data = self.read()
+        if index_name not in data["indexes"]:
+            return {}
+        return data["indexes"][index_name].get("issues", {})
+
     def detect_file_changes(
-        self, index_name: str, directories: List[str]
+        self,
+        index_name: str,
+        directories: List[str],
+        extensions: Sequence[str] = MARKDOWN_EXTENSIONS,
     ) -> tuple[set[str], set[str], set[str]]:
         """
-        Detect which files have been added, modified, or deleted.
+        Detect which files have been added, modified, or deleted. A file
+        an ignore file now leaves out counts as deleted.
 
         Args:
             index_name: Name of the index.
             directories: List of directories to scan.
+            extensions: File extensions to index (see
+                loader.find_markdown_files).
 
         Returns:
             Tuple of (added, modified, deleted) file path sets.
@@ -230,7 +307,7 @@ class Manifest:
             dir_obj = Path(dir_path)
             if not dir_obj.exists() or not dir_obj.is_dir():
                 continue
-            for md_file in dir_obj.rglob("*.md"):
+            for md_file in find_markdown_files(dir_obj, extensions=extensions):
                 try:
                     current_files[str(md_file)] = md_file.stat().st_mtime
                 except OSError:
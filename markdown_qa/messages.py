@@ -13,6 +13,41 @@ class MessageType:
     STREAM_START = "stream_start"
     STREAM_CHUNK = "stream_chunk"
     STREAM_END = "stream_end"
+    CANCEL = "cancel"
+    RELOAD = "reload"
+    WARMUP = "warmup"
+    CAPABILITIES = "capabilities"
+    INDEX_STATS = "index_stats"
+    INDEX_PROGRESS = "index_progress"
+    NOTIFICATION = "notification"
+    INDEX_PLAN = "index_plan"
+    INDEX_VERIFY = "index_verify"
+    INDEX_SNAPSHOT = "index_snapshot"
+    INDEX_RESTORE = "index_restore"
+    LIST_INDEXES = "list_indexes"
+    INDEX_CREATE = "index_create"
+    INDEX_DELETE = "index_delete"
+    INDEXES = "indexes"
+    LIST_DOCUMENTS = "list_documents"
+    DOCUMENTS = "documents"
+    GET_CHUNKS = "get_chunks"
+    CHUNKS = "chunks"
+    GET_LINKS = "get_links"
+    SUGGEST_FOLLOWUPS = "suggest_followups"
+    FOLLOWUPS = "followups"
+    LINKS = "links"
+    TAIL_LOGS = "tail_logs"
+    LOGS = "logs"
+    LOG = "log"
+
+
+class ErrorCode:
+    """Machine-readable error codes, so clients can tell retryable errors apart."""
+
+    INDEX_NOT_READY = "index_not_ready"
+    RATE_LIMITED = "rate_limited"
+    AUTH_FAILED = "auth_failed"
+    INTERNAL = "internal"
 
 
 def _deduplicate_paths(paths: List[str]) -> List[str]:
@@ -63,30 +98,398 @@ def create_response_message(
     }
 
 
-def create_error_message(message: str) -> Dict[str, Any]:
+def create_error_message(message: str, code: Optional[str] = None) -> Dict[str, Any]:
     """
     Create an error message.
 
     Args:
         message: Error message text.
+        code: Optional ErrorCode value.
 
     Returns:
         Error message dictionary.
     """
-    return {
+    error: Dict[str, Any] = {
         "type": MessageType.ERROR,
         "message": message,
     }
+    if code is not None:
+        error["code"] = code
+    return error
+
+
+def create_capabilities_message(
+    features: List[str], encoding: Optional[str] = None
+) -> Dict[str, Any]:
+    """
+    Create a capabilities message.
+
+    Args:
+        features: Optional protocol features the server supports (e.g.
+                  "cancel", "history").
+        encoding: Binary frame encoding picked from the client's offer, if any.
+
+    Returns:
+        Capabilities message dictionary.
+    """
+    message: Dict[str, Any] = {"type": MessageType.CAPABILITIES, "features": features}
+    if encoding is not None:
+        message["encoding"] = encoding
+    return message
+
+
+def create_index_stats_message(
+    index: str,
+    documents: int,
+    chunks: int,
+    embedding_model: Optional[str] = None,
+    built_at: Optional[int] = None,
+    size_bytes: Optional[int] = None,
+    issues: Optional[List[Dict[str, str]]] = None,
+    chunk_cache: Optional[Dict[str, Any]] = None,
+) -> Dict[str, Any]:
+    """
+    Create an index stats message.
+
+    Args:
+        index: Name of the index.
+        documents: Number of indexed documents.
+        chunks: Number of indexed chunks.
+        embedding_model: Embedding model the index was built with.
+        built_at: Unix time the index was last written to disk.
+        size_bytes: Size of the index files on disk.
+        issues: Files that had problems reading, as ``{"path", "issue"}``.
+        chunk_cache: Size, capacity, hits, misses and hit rate of the
+            cache of retrieved chunks.
+
+    Returns:
+        Index stats message dictionary.
+    """
+    message: Dict[str, Any] = {
+        "type": MessageType.INDEX_STATS,
+        "index": index,
+        "documents": documents,
+        "chunks": chunks,
+    }
+    optional = {
+        "embedding_model": embedding_model,
+        "built_at": built_at,
+        "size_bytes": size_bytes,
+        "issues": issues or None,
+        "chunk_cache": chunk_cache,
+    }
+    message.update({k: v for k, v in optional.items() if v is not None})
+    return message
+
+
+def create_index_plan_message(
+    index: str,
+    files: List[str],
+    ignored: List[str],
+    estimate: Optional[Dict[str, Any]] = None,
+) -> Dict[str, Any]:
+    """
+    Create an index plan message, listing what an index build would read.
+
+    Args:
+        index: Name of the index.
+        files: Markdown files that would be indexed.
+        ignored: Markdown files left out by ignore files.
+        estimate: What a reload would embed (see
+            IndexManager.estimate_reload), if the client asked for it.
+
+    Returns:
+        Index plan message dictionary.
+    """
+    message: Dict[str, Any] = {
+        "type": MessageType.INDEX_PLAN,
+        "index": index,
+        "files": files,
+        "ignored": ignored,
+    }
+    if estimate is not None:
+        message["estimate"] = estimate
+    return message
+
+
+def create_index_verify_message(
+    index: str, orphans: List[Dict[str, Any]], removed: bool
+) -> Dict[str, Any]:
+    """
+    Create an index verify message, listing chunks of files that are no
+    longer indexed.
+
+    Args:
+        index: Name of the index.
+        orphans: Files with orphaned chunks: path, chunk count and reason.
+        removed: Whether the orphaned chunks were removed.
+
+    Returns:
+        Index verify message dictionary.
+    """
+    return {
+        "type": MessageType.INDEX_VERIFY,
+        "index": index,
+        "orphans": orphans,
+        "removed": removed,
+    }
+
+
+def create_index_snapshot_message(
+    index: str, path: str, chunks: int, embedding_model: str, size_bytes: int
+) -> Dict[str, Any]:
+    """
+    Create an index snapshot message, describing the snapshot archive written.
+
+    Args:
+        index: Name of the index.
+        path: Archive written.
+        chunks: Chunks it holds.
+        embedding_model: Embedding model they were embedded with.
+        size_bytes: Size of the archive.
+
+    Returns:
+        Index snapshot message dictionary.
+    """
+    return {
+        "type": MessageType.INDEX_SNAPSHOT,
+        "index": index,
+        "path": path,
+        "chunks": chunks,
+        "embedding_model": embedding_model,
+        "size_bytes": size_bytes,
+    }
+
+
+def create_index_restore_message(
+    index: str,
+    path: str,
+    chunks: int,
+    embedding_model: str,
+    size_bytes: int,
+    reindexing: bool,
+) -> Dict[str, Any]:
+    """
+    Create an index restore message, describing the snapshot restored.
+
+    Args:
+        index: Name of the index it replaced.
+        path: Archive read.
+        chunks: Chunks it holds.
+        embedding_model: Embedding model they were embedded with.
+        size_bytes: Size of the archive.
+        reindexing: Whether a reload was started to pick up documents that
+            changed since the snapshot.
+
+    Returns:
+        Index restore message dictionary.
+    """
+    return {
+        "type": MessageType.INDEX_RESTORE,
+        "index": index,
+        "path": path,
+        "chunks": chunks,
+        "embedding_model": embedding_model,
+        "size_bytes": size_bytes,
+        "reindexing": reindexing,
+    }
+
+
+def create_indexes_message(indexes: List[Dict[str, Any]]) -> Dict[str, Any]:
+    """
+    Create an indexes message, listing the indexes a query can name.
+
+    Args:
+        indexes: Each index as ``name``, ``directories``,
+            ``embedding_model``, ``default`` (whether it is the configured
+            index, which can't be deleted) and ``status`` (``ready``,
+            ``indexing`` or ``not_ready``).
+
+    Returns:
+        Indexes message dictionary.
+    """
+    return {"type": MessageType.INDEXES, "indexes": indexes}
+
+
+def create_documents_message(
+    index: str, page: int, total: int, documents: List[Dict[str, Any]]
+) -> Dict[str, Any]:
+    """
+    Create a documents message, one page of the reply to ``list_documents``.
+
+    Args:
+        index: Name of the index.
+        page: Zero-based page number.
+        total: Number of documents in the whole index.
+        documents: The page's documents: path, chunk count and, if known,
+            the Unix time they were indexed at (``indexed_at``).
+
+    Returns:
+        Documents message dictionary.
+    """
+    return {
+        "type": MessageType.DOCUMENTS,
+        "index": index,
+        "page": page,
+        "total": total,
+        "documents": documents,
+    }
+
+
+def create_chunks_message(path: str, chunks: List[Dict[str, str]]) -> Dict[str, Any]:
+    """
+    Create a chunks message, the reply to ``get_chunks``.
+
+    Args:
+        path: Path of the document.
+        chunks: Its chunks in document order: text and, if known, heading.
+
+    Returns:
+        Chunks message dictionary.
+    """
+    return {"type": MessageType.CHUNKS, "path": path, "chunks": chunks}
+
+
+def create_links_message(
+    path: str, outbound: List[str], inbound: List[str]
+) -> Dict[str, Any]:
+    """
+    Create a links message, the reply to ``get_links``.
+
+    Args:
+        path: Path of the document.
+        outbound: Indexed documents it links to.
+        inbound: Indexed documents that link to it.
+
+    Returns:
+        Links message dictionary.
+    """
+    return {
+        "type": MessageType.LINKS,
+        "path": path,
+        "outbound": outbound,
+        "inbound": inbound,
+    }
+
+
+def create_followups_message(questions: List[str]) -> Dict[str, Any]:
+    """
+    Create a followups message, the reply to ``suggest_followups``.
+
+    Args:
+        questions: Suggested follow-up questions.
+
+    Returns:
+        Followups message dictionary.
+    """
+    return {"type": MessageType.FOLLOWUPS, "questions": questions}
+
+
+def create_logs_message(lines: List[str]) -> Dict[str, Any]:
+    """
+    Create a logs message, the reply to ``tail_logs``.
+
+    Args:
+        lines: Latest formatted log lines, oldest first.
+
+    Returns:
+        Logs message dictionary.
+    """
+    return {"type": MessageType.LOGS, "lines": lines}
+
+
+def create_log_message(line: str) -> Dict[str, Any]:
+    """
+    Create a log message for a client following the log.
+
+    Args:
+        line: One formatted log record (with its traceback, if any).
+
+    Returns:
+        Log message dictionary.
+    """
+    return {"type": MessageType.LOG, "line": line}
+
+
+def create_index_progress_message(
+    done: int,
+    total: int,
+    path: Optional[str] = None,
+    error: Optional[str] = None,
+    chunks: Optional[int] = None,
+    embedded: Optional[int] = None,
+    finished: bool = False,
+    failed: Optional[List[Dict[str, str]]] = None,
+) -> Dict[str, Any]:
+    """
+    Create an index progress message, sent to every client while indexing.
+
+    Args:
+        done: Files read so far.
+        total: Files to read in this build.
+        path: File just read.
+        error: Why that file could not be indexed, if it couldn't.
+        chunks: Chunks to embed, once the files are read.
+        embedded: Chunks embedded so far.
+        finished: Whether this is the last message of the build.
+        failed: Files that could not be indexed, as ``{"path", "issue"}``,
+            in the last message.
+
+    Returns:
+        Index progress message dictionary.
+    """
+    message: Dict[str, Any] = {
+        "type": MessageType.INDEX_PROGRESS,
+        "done": done,
+        "total": total,
+    }
+    optional = {
+        "path": path,
+        "error": error,
+        "chunks": chunks,
+        "embedded": embedded,
+    }
+    message.update({k: v for k, v in optional.items() if v is not None})
+    if finished:
+        message["finished"] = True
+        message["failed"] = failed or []
+    return message
+
+
+def create_notification_message(
+    documents: List[str], message: Optional[str] = None
+) -> Dict[str, Any]:
+    """
+    Create a notification message, sent to every client after reindexing
+    changed documents.
+
+    Args:
+        documents: Paths of the documents added, changed or removed.
+        message: Human-readable summary of the change.
+
+    Returns:
+        Notification message dictionary.
+    """
+    notification: Dict[str, Any] = {
+        "type": MessageType.NOTIFICATION,
+        "documents": documents,
+    }
+    if message is not None:
+        notification["message"] = message
+    return notification
 
 
 def create_status_message(
-    status: Literal["ready", "indexing", "not_ready"], message: Optional[str] = None
+    status: Literal["ready", "indexing", "not_ready", "translating", "retrieving"],
+    message: Optional[str] = None,
 ) -> Dict[str, Any]:
     """
     Create a status message.
 
     Args:
-        status: Status value ("ready", "indexing", or "not_ready").
+        status: Status value ("ready", "indexing", or "not_ready"), or
+                "translating" / "retrieving" for query progress before
+                ``stream_start``.
         message: Optional status message text.
 
     Returns:
@@ -108,33 +511,62 @@ def create_stream_start_message() -> Dict[str, Any]:
     return {"type": MessageType.STREAM_START}
 
 
-def create_stream_chunk_message(chunk: str) -> Dict[str, Any]:
+def create_stream_chunk_message(
+    chunk: str, candidate: Optional[int] = None
+) -> Dict[str, Any]:
     """
     Create a stream chunk message.
 
     Args:
         chunk: Text chunk of the answer.
+        candidate: Index of the candidate answer the text belongs to, when
+                   the query asked for candidates (0 is the answer).
 
     Returns:
         Stream chunk message dictionary.
     """
-    return {"type": MessageType.STREAM_CHUNK, "chunk": chunk}
-
-
-def create_stream_end_message(sources: List[str]) -> Dict[str, Any]:
+    message: Dict[str, Any] = {"type": MessageType.STREAM_CHUNK, "chunk": chunk}
+    if candidate is not None:
+        message["candidate"] = candidate
+    return message
+
+
+def create_stream_end_message(
+    sources: List[str],
+    usage: Optional[Dict[str, Any]] = None,
+    elapsed_ms: Optional[float] = None,
+    citations: Optional[Dict[str, Any]] = None,
+    grounding: Optional[Dict[str, Any]] = None,
+) -> Dict[str, Any]:
     """
     Create a stream end message.
 
     Args:
         sources: List of source file paths.
+        usage: Token counts of the answer ("prompt_tokens",
+               "completion_tokens", "model"), when known.
+        elapsed_ms: Time spent on the query so far, in milliseconds.
+        citations: Sources of the answer's ``[N]`` markers, keyed by the
+                   marker number as a string.
+        grounding: Result of the answer's grounding check ("score",
+                   "unsupported"), when the query asked for one.
 
     Returns:
         Stream end message dictionary.
     """
-    return {
+    message: Dict[str, Any] = {
         "type": MessageType.STREAM_END,
         "sources": _deduplicate_paths(sources),
     }
+    if usage is not None:
+        message["usage"] = usage
+    if elapsed_ms is not None:
+        message["elapsed_ms"] = round(elapsed_ms)
+    if citations:
+        message["citations"] = citations
+    if grounding is not None:
+        message["grounding"] = grounding
+    return message
 
 
 def validate_query_message(message: Dict[str, Any]) -> tuple[bool, Optional[str]]:
@@ -0,0 +1,139 @@
+"""Named indexes: indexes created over the protocol besides the configured one.
+
+Each has its own directories, chunking and embedding model (index_create),
+is built into its own directory of the cache, and answers queries whose
+``index`` field names it. Their settings are kept in named_indexes.json in
+the cache directory, so they are loaded again when the server restarts.
+"""
+
+import copy
+import json
+import re
+from dataclasses import asdict, dataclass
+from pathlib import Path
+from typing import Any, Dict, List, Optional
+
+from markdown_qa.chunker import ChunkingConfig
+from markdown_qa.config import APIConfig
+from markdown_qa.index_manager import IndexManager
+from markdown_qa.query_handler import QueryHandler
+
+# File in the cache directory listing the named indexes and their settings
+REGISTRY_FILE = "named_indexes.json"
+
+# Directory of the cache directory holding one cache per named index
+NAMED_INDEX_DIR = "named"
+
+# Names of indexes, which name their cache directory too
+INDEX_NAME = re.compile(r"[A-Za-z0-9][A-Za-z0-9_.-]{0,63}")
+
+
+@dataclass
+class NamedIndexSettings:
+    """What index_create sets for a named index."""
+
+    name: str
+    directories: List[str]
+    # The retrieval.chunking section of this index; None means the defaults
+    chunking: Optional[Dict[str, Any]] = None
+    # None means the server's api.embedding_model
+    embedding_model: Optional[str] = None
+
+    @classmethod
+    def from_message(cls, message: Dict[str, Any]) -> "NamedIndexSettings":
+        """
+        Read the settings of an index_create message.
+
+        Args:
+            message: The message, with ``name``, ``directories`` and the
+                optional ``chunking`` and ``embedding_model``.
+
+        Returns:
+            The settings, with the directories made absolute.
+
+        Raises:
+            ValueError: If a field is missing or invalid, or a directory
+                doesn't exist.
+        """
+        name = message.get("name")
+        if not isinstance(name, str) or not INDEX_NAME.fullmatch(name):
+            raise ValueError(
+                "Field 'name' must be 1-64 letters, digits, '_', '-' or '.', "
+                "starting with a letter or digit"
+            )
+        directories = message.get("directories")
+        if (
+            not isinstance(directories, list)
+            or not directories
+            or not all(isinstance(d, str) and d for d in directories)
+        ):
+            raise ValueError("Field 'directories' must be a non-empty list of paths")
+        resolved = []
+        for directory in directories:
+            path = Path(directory).expanduser().resolve()
+            if not path.is_dir():
+                raise ValueError(f"Not a directory: {directory}")
+            resolved.append(str(path))
+        chunking = message.get("chunking")
+        # Checked here, so a bad section is an error rather than a failed build
+        ChunkingConfig.from_dict(chunking)
+        embedding_model = message.get("embedding_model")
+        if embedding_model is not None and (
+            not isinstance(embedding_model, str) or not embedding_model.strip()
+        ):
+            raise ValueError("Field 'embedding_model' must be a model name")
+        return cls(
+            name=name,
+            directories=resolved,
+            chunking=chunking,
+            embedding_model=embedding_model.strip() if embedding_model else None,
+        )
+
+
+@dataclass
+class NamedIndex:
+    """A named index: its settings, and the index manager and query handler serving it."""
+
+    settings: NamedIndexSettings
+    index_manager: IndexManager
+    query_handler: QueryHandler
+
+
+def index_api_config(api_config: APIConfig, embedding_model: Optional[str]) -> APIConfig:
+    """The server's API config with a named index's embedding model, if it sets one."""
+    config = copy.copy(api_config)
+    if embedding_model:
+        config.embedding_model = embedding_model
+    return config
+
+
+def load_registry(cache_dir: Path) -> Dict[str, NamedIndexSettings]:
+    """
+    Read the settings of the named indexes kept in a cache directory.
+
+    Args:
+        cache_dir: The server's cache directory.
+
+    Returns:
+        The settings by index name; none if there is no registry yet.
+    """
+    path = cache_dir / REGISTRY_FILE
+    if not path.exists():
+        return {}
+    data = json.loads(path.read_text())
+    return {
+        entry["name"]: NamedIndexSettings(**entry) for entry in data.get("indexes", [])
+    }
+
+
+def save_registry(cache_dir: Path, indexes: Dict[str, NamedIndexSettings]) -> None:
+    """
+    Write the settings of the named indexes to a cache directory.
+
+    Args:
+        cache_dir: The server's cache directory.
+        indexes: The settings by index name.
+    """
+    path = cache_dir / REGISTRY_FILE
+    data = {"indexes": [asdict(settings) for settings in indexes.values()]}
+    path.write_text(json.dumps(data, indent=2))
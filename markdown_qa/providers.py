@@ -0,0 +1,231 @@
+"""LLM providers for answer generation, selected by ``api.provider``.
+
+The question answerer talks to every provider through the subset of the
+OpenAI client it uses, ``client.chat.completions.create(...)``, so the
+Anthropic and Ollama clients here answer in that shape: ``choices[0].message
+.content`` for a reply, and chunks with ``choices[0].delta.content`` plus a
+last one with ``usage`` when streaming.
+"""
+
+import json
+import urllib.error
+import urllib.request
+from types import SimpleNamespace
+from typing import Any, Dict, Iterator, List, Optional, Tuple
+
+# Providers api.provider may name; "openai" covers any OpenAI-compatible API
+PROVIDERS = ("openai", "anthropic", "ollama")
+
+# Endpoints used when api.llm_base_url is not set
+DEFAULT_ANTHROPIC_URL = "https://api.anthropic.com"
+DEFAULT_OLLAMA_URL = "http://localhost:11434"
+
+ANTHROPIC_VERSION = "2023-06-01"
+
+# Anthropic requires a limit on the answer's length
+DEFAULT_MAX_TOKENS = 1024
+
+# Seconds to wait for a provider to start answering
+REQUEST_TIMEOUT = 120.0
+
+
+class ProviderError(Exception):
+    """A failed provider request, with its HTTP status when there was one."""
+
+    def __init__(self, message: str, status_code: Optional[int] = None):
+        super().__init__(message)
+        # Read by the query handler to tell rate limits and auth failures apart
+        self.status_code = status_code
+
+
+def _reply(content: str, usage: Optional[Tuple[int, int]]) -> SimpleNamespace:
+    """A chat completion in the OpenAI client's shape."""
+    return SimpleNamespace(
+        choices=[SimpleNamespace(message=SimpleNamespace(content=content))],
+        usage=_usage(usage),
+    )
+
+
+def _delta(content: str) -> SimpleNamespace:
+    """A streamed chunk of the answer in the OpenAI client's shape."""
+    return SimpleNamespace(
+        choices=[SimpleNamespace(delta=SimpleNamespace(content=content))], usage=None
+    )
+
+
+def _usage(usage: Optional[Tuple[int, int]]) -> Optional[SimpleNamespace]:
+    """Token counts in the OpenAI client's shape."""
+    if usage is None:
+        return None
+    return SimpleNamespace(prompt_tokens=usage[0], completion_tokens=usage[1])
+
+
+class _HTTPClient:
+    """JSON-over-HTTP client shared by the providers, exposing ``chat.completions.create``."""
+
+    def __init__(self, base_url: str):
+        self.base_url = base_url.rstrip("/")
+        self.chat = SimpleNamespace(completions=SimpleNamespace(create=self.create))
+
+    def _headers(self) -> Dict[str, str]:
+        return {"Content-Type": "application/json"}
+
+    def _open(self, path: str, body: Dict[str, Any]) -> Any:
+        """POST a JSON body and return the open response."""
+        request = urllib.request.Request(
+            self.base_url + path, data=json.dumps(body).encode(), headers=self._headers()
+        )
+        try:
+            return urllib.request.urlopen(request, timeout=REQUEST_TIMEOUT)
+        except urllib.error.HTTPError as e:
+            detail = e.read().decode(errors="replace")
+            raise ProviderError(f"HTTP {e.code}: {detail}", e.code) from e
+
+    def create(self, **kwargs: Any) -> Any:
+        raise NotImplementedError
+
+
+class AnthropicClient(_HTTPClient):
+    """Answers with Anthropic's Messages API."""
+
+    def __init__(self, api_key: str, base_url: Optional[str] = None):
+        super().__init__(base_url or DEFAULT_ANTHROPIC_URL)
+        self.api_key = api_key
+
+    def _headers(self) -> Dict[str, str]:
+        return {
+            "Content-Type": "application/json",
+            "x-api-key": self.api_key,
+            "anthropic-version": ANTHROPIC_VERSION,
+        }
+
+    def create(
+        self,
+        model: str,
+        messages: List[Dict[str, str]],
+        temperature: Optional[float] = None,
+        max_tokens: Optional[int] = None,
+        stream: bool = False,
+        **_: Any,
+    ) -> Any:
+        """Send OpenAI-style chat messages; system messages become the system prompt."""
+        body: Dict[str, Any] = {
+            "model": model,
+            "max_tokens": max_tokens or DEFAULT_MAX_TOKENS,
+            "messages": [m for m in messages if m["role"] != "system"],
+        }
+        system = "\n\n".join(m["content"] for m in messages if m["role"] == "system")
+        if system:
+            body["system"] = system
+        if temperature is not None:
+            # Anthropic's range is 0-1, OpenAI's 0-2
+            body["temperature"] = min(temperature, 1.0)
+        if stream:
+            body["stream"] = True
+            return self._stream(body)
+
+        with self._open("/v1/messages", body) as response:
+            reply = json.loads(response.read())
+        text = "".join(
+            block.get("text", "") for block in reply.get("content", []) if block.get("type") == "text"
+        )
+        usage = reply.get("usage") or {}
+        return _reply(text, (usage.get("input_tokens", 0), usage.get("output_tokens", 0)))
+
+    def _stream(self, body: Dict[str, Any]) -> Iterator[SimpleNamespace]:
+        """Read the server-sent events of a streamed message."""
+        prompt_tokens = completion_tokens = 0
+        with self._open("/v1/messages", body) as response:
+            for raw in response:
+                line = raw.decode().strip()
+                if not line.startswith("data:"):
+                    continue
+                event = json.loads(line[len("data:") :])
+                kind = event.get("type")
+                if kind == "message_start":
+                    usage = event.get("message", {}).get("usage") or {}
+                    prompt_tokens = usage.get("input_tokens", 0)
+                elif kind == "content_block_delta":
+                    text = event.get("delta", {}).get("text")
+                    if text:
+                        yield _delta(text)
+                elif kind == "message_delta":
+                    completion_tokens = (event.get("usage") or {}).get("output_tokens", 0)
+                elif kind == "error":
+                    raise ProviderError(str(event.get("error")))
+        yield SimpleNamespace(choices=[], usage=_usage((prompt_tokens, completion_tokens)))
+
+
+class OllamaClient(_HTTPClient):
+    """Answers with a local Ollama server's chat API."""
+
+    def __init__(self, base_url: Optional[str] = None):
+        super().__init__(base_url or DEFAULT_OLLAMA_URL)
+
+    def create(
+        self,
+        model: str,
+        messages: List[Dict[str, str]],
+        temperature: Optional[float] = None,
+        max_tokens: Optional[int] = None,
+        stream: bool = False,
+        **_: Any,
+    ) -> Any:
+        """Send OpenAI-style chat messages, which Ollama takes as they are."""
+        options: Dict[str, Any] = {}
+        if temperature is not None:
+            options["temperature"] = temperature
+        if max_tokens is not None:
+            options["num_predict"] = max_tokens
+        body: Dict[str, Any] = {"model": model, "messages": messages, "stream": stream}
+        if options:
+            body["options"] = options
+        if stream:
+            return self._stream(body)
+
+        with self._open("/api/chat", body) as response:
+            reply = json.loads(response.read())
+        return _reply(
+            reply.get("message", {}).get("content", ""),
+            (reply.get("prompt_eval_count", 0), reply.get("eval_count", 0)),
+        )
+
+    def _stream(self, body: Dict[str, Any]) -> Iterator[SimpleNamespace]:
+        """Read the JSON lines of a streamed reply."""
+        with self._open("/api/chat", body) as response:
+            for raw in response:
+                if not raw.strip():
+                    continue
+                part = json.loads(raw)
+                if part.get("error"):
+                    raise ProviderError(str(part["error"]))
+                text = part.get("message", {}).get("content")
+                if text:
+                    yield _delta(text)
+                if part.get("done"):
+                    yield SimpleNamespace(
+                        choices=[],
+                        usage=_usage((part.get("prompt_eval_count", 0), part.get("eval_count", 0))),
+                    )
+
+
+def create_chat_client(provider: str, api_key: Optional[str], base_url: Optional[str]) -> Any:
+    """
+    The client for a provider other than an OpenAI-compatible one.
+
+    Args:
+        provider: ``"anthropic"`` or ``"ollama"``.
+        api_key: API key (Anthropic).
+        base_url: Endpoint, if not the provider's default.
+
+    Returns:
+        A client with ``chat.completions.create``.
+
+    Raises:
+        ValueError: If the provider is unknown.
+    """
+    if provider == "anthropic":
+        return AnthropicClient(api_key or "", base_url)
+    if provider == "ollama":
+        return OllamaClient(base_url)
+    raise ValueError(f"Unknown LLM provider: {provider} (expected one of {', '.join(PROVIDERS)})")
@@ -1,10 +1,12 @@
 """Question answering module with LLM integration."""
 
-from typing import Any, Generator, List, Optional, Tuple
+import re
+from typing import Any, Dict, Generator, List, Optional, Tuple
 
 from openai import OpenAI
 
 from markdown_qa.config import APIConfig
+from markdown_qa.providers import create_chat_client
 from markdown_qa.retrieval import RetrievalEngine
 
 
@@ -16,6 +18,9 @@ class QuestionAnswerer:
         retrieval_engine: RetrievalEngine,
         api_config: Optional[APIConfig] = None,
         model: Optional[str] = None,
+        history: Optional[List[Tuple[str, str]]] = None,
+        attachment: Optional[str] = None,
+        answer_language: Optional[str] = None,
     ):
         """
         Initialize question answerer.
@@ -25,16 +30,39 @@ class QuestionAnswerer:
             api_config: API configuration. If None, creates from defaults.
             model: LLM model name to use for answering questions.
                    If None, uses the model from api_config.
+            history: Earlier (question, answer) pairs of the conversation,
+                     oldest first, included in the prompt for follow-ups.
+            attachment: Material the question is about (e.g. a diff),
+                        included in the prompt next to the context.
+            answer_language: Language to write the answer in (e.g. "de"),
+                             when it differs from the documentation's.
         """
         self.retrieval_engine = retrieval_engine
         if api_config is None:
             api_config = APIConfig()
         self.api_config = api_config
-        self.client = OpenAI(
-            base_url=api_config.base_url,
-            api_key=api_config.api_key,
-        )
+        provider = getattr(api_config, "provider", "openai")
+        if provider == "openai":
+            self.client = OpenAI(
+                base_url=getattr(api_config, "llm_base_url", None) or api_config.base_url,
+                api_key=getattr(api_config, "llm_api_key", None) or api_config.api_key,
+            )
+        else:
+            # The embedding API's key is not sent to another provider
+            self.client = create_chat_client(
+                provider,
+                getattr(api_config, "llm_api_key", None),
+                getattr(api_config, "llm_base_url", None),
+            )
         self.model = model if model is not None else api_config.llm_model
+        self.history = history or []
+        self.attachment = attachment
+        self.answer_language = answer_language
+        # Token counts of the last streamed answer, when the API reports them
+        self.last_usage: Optional[Dict[str, Any]] = None
+        # Sources of the last retrieved context parts, keyed by the number
+        # the prompt asks the answer to cite them with
+        self.citations: Dict[str, Any] = {}
 
     def answer(
         self, question: str, k: int = 5, min_relevance_threshold: float = 0.0
@@ -69,23 +97,59 @@ class QuestionAnswerer:
                 "No relevant content found in the loaded markdown files to answer this question."
             )
 
-        # Extract sources (file paths only)
+        context, sources = self._build_context(filtered_results)
+
+        # Generate answer using LLM
+        prompt = self._build_prompt(question, context)
+        answer = self._generate_answer(prompt)
+
+        return answer, sources
+
+    def _build_context(
+        self, results: List[Tuple[str, Dict[str, Any], float]]
+    ) -> Tuple[str, List[str]]:
+        """
+        Build the context from retrieved chunks, numbering each part so the
+        answer can cite it as ``[N]``, and remember the numbers in
+        ``citations``.
+
+        Args:
+            results: Retrieved (text, metadata, distance) tuples.
+
+        Returns:
+            Tuple of (context, sources) where sources is a list of file paths.
+        """
         sources = []
         context_parts = []
-        for text, metadata, distance in filtered_results:
+        self.citations = {}
+        for number, (text, metadata, _distance) in enumerate(results, start=1):
             file_path = metadata.get("file_path", "")
             if file_path:
                 sources.append(file_path)
-            context_parts.append(f"Source: {file_path}\n{text}")
-
-        # Build context from retrieved chunks
-        context = "\n\n---\n\n".join(context_parts)
+                section = metadata.get("section")
+                self.citations[str(number)] = (
+                    {"path": file_path, "heading": section} if section else file_path
+                )
+            context_parts.append(f"[{number}] {self._source_header(metadata)}\n{text}")
+        return "\n\n---\n\n".join(context_parts), sources
+
+    def _source_header(self, metadata: Dict[str, Any]) -> str:
+        """
+        Build the line citing a chunk's source in the context.
 
-        # Generate answer using LLM
-        prompt = self._build_prompt(question, context)
-        answer = self._generate_answer(prompt)
+        Args:
+            metadata: Chunk metadata, with the front matter title and tags
+                when the file sets them.
 
-        return answer, sources
+        Returns:
+            ``Source: <path>``, followed by the title and tags if known.
+        """
+        header = f"Source: {metadata.get('file_path', '')}"
+        if metadata.get("title"):
+            header += f" (title: {metadata['title']})"
+        if metadata.get("tags"):
+            header += f" [tags: {', '.join(metadata['tags'])}]"
+        return header
 
     def _build_prompt(self, question: str, context: str) -> str:
         """
@@ -98,14 +162,24 @@ class QuestionAnswerer:
         Returns:
             Formatted prompt string.
         """
+        conversation = ""
+        if self.history:
+            turns = "\n\n".join(f"Q: {q}\nA: {a}" for q, a in self.history)
+            conversation = f"Conversation so far:\n{turns}\n\n"
+        attachment = ""
+        if self.attachment:
+            attachment = f"Material the question is about:\n{self.attachment}\n\n"
+        language = ""
+        if self.answer_language:
+            language = f" Write the answer in the language with code {self.answer_language}, translating what you use from the context."
         return f"""You are a helpful assistant that answers questions based on the provided context from markdown documentation files.
 
 Context from documentation:
 {context}
 
-Question: {question}
+{attachment}{conversation}Question: {question}
 
-Please provide a clear and concise answer based on the context above. If the context does not contain enough information to answer the question, say so explicitly. Do not make up information that is not in the context."""
+Please provide a clear and concise answer based on the context above. Each context part starts with its number in square brackets; cite the parts you use with those numbers, e.g. [1]. If the context does not contain enough information to answer the question, say so explicitly. Do not make up information that is not in the context.{language}"""
 
     def _generate_answer(self, prompt: str) -> str:
         """
@@ -131,6 +205,145 @@ Please provide a clear and concise answer based on the context above. If the con
         except Exception as e:
             raise RuntimeError(f"Failed to generate answer: {e}") from e
 
+    def translate(self, text: str, language: str) -> Tuple[str, Optional[str]]:
+        """
+        Translate a question (or retrieval text) into the documentation's
+        language, so retrieval compares texts in the same language.
+
+        Args:
+            text: The text to translate.
+            language: Language code of the documentation (e.g. "en").
+
+        Returns:
+            Tuple of (text in ``language``, code of the language ``text`` was
+            written in). The text is returned unchanged, with None as its
+            language, when it already is in ``language``.
+        """
+        prompt = f"""Translate the text below into the language with code {language}.
+
+Reply with exactly two parts: on the first line the ISO 639-1 code of the language the text is written in, then on the following lines the translated text (the text unchanged if it is already in that language). Do not add anything else.
+
+Text:
+{text}"""
+        try:
+            response = self.client.chat.completions.create(
+                model=self.model,
+                messages=[
+                    {"role": "system", "content": "You are a translator."},
+                    {"role": "user", "content": prompt},
+                ],
+                temperature=0.0,
+            )
+            reply = (response.choices[0].message.content or "").strip()
+        except Exception as e:
+            raise RuntimeError(f"Failed to translate the question: {e}") from e
+
+        detected, _, translation = reply.partition("\n")
+        detected = detected.strip().lower()
+        translation = translation.strip()
+        if not translation or detected.split("-")[0] == language.lower().split("-")[0]:
+            return text, None
+        return translation, detected
+
+    def suggest_followups(
+        self, question: str, answer: str, context: str, count: int
+    ) -> List[str]:
+        """
+        Suggest follow-up questions to an answer that the documents it drew
+        on can answer.
+
+        Args:
+            question: The question that was answered.
+            answer: The answer.
+            context: Text of the answer's sources.
+            count: Number of questions wanted.
+
+        Returns:
+            Up to ``count`` questions, without numbering or bullets.
+        """
+        prompt = f"""A user asked a question about some documentation and got the answer below.
+
+Documentation the answer is based on:
+{context}
+
+Question: {question}
+
+Answer: {answer}
+
+Suggest {count} short follow-up questions the user might ask next that the documentation above can answer. Reply with one question per line and nothing else."""
+        try:
+            response = self.client.chat.completions.create(
+                model=self.model,
+                messages=[
+                    {"role": "system", "content": "You are a helpful assistant."},
+                    {"role": "user", "content": prompt},
+                ],
+                temperature=0.7,
+                max_tokens=200,
+            )
+            reply = response.choices[0].message.content or ""
+        except Exception as e:
+            raise RuntimeError(f"Failed to suggest follow-up questions: {e}") from e
+
+        questions = []
+        for line in reply.splitlines():
+            line = re.sub(r"^\s*(?:[-*•]|\d+[.)])\s*", "", line).strip()
+            if line:
+                questions.append(line)
+        return questions[:count]
+
+    def check_grounding(self, answer: str, context: str) -> Tuple[float, List[str]]:
+        """
+        Ask the LLM which sentences of an answer the retrieved context does
+        not support.
+
+        Args:
+            answer: The answer.
+            context: The context the answer was generated from.
+
+        Returns:
+            Tuple of (score, unsupported): the share of the answer's
+            sentences that are supported (1.0 for an answer without
+            sentences), and the unsupported sentences as written.
+        """
+        sentences = [
+            sentence.strip()
+            for line in answer.splitlines()
+            for sentence in re.split(r"(?<=[.!?])\s+", line)
+            if re.search(r"\w", sentence)
+        ]
+        if not sentences:
+            return 1.0, []
+
+        numbered = "\n".join(f"{i}. {sentence}" for i, sentence in enumerate(sentences, 1))
+        prompt = f"""Check each numbered sentence of an answer against the context it was written from.
+
+Context:
+{context}
+
+Sentences:
+{numbered}
+
+Reply with the numbers of the sentences whose claims the context does not support, separated by commas, or NONE if the context supports them all. Do not add anything else."""
+        try:
+            response = self.client.chat.completions.create(
+                model=self.model,
+                messages=[
+                    {"role": "system", "content": "You are a careful fact checker."},
+                    {"role": "user", "content": prompt},
+                ],
+                temperature=0.0,
+            )
+            reply = response.choices[0].message.content or ""
+        except Exception as e:
+            raise RuntimeError(f"Failed to check the answer's grounding: {e}") from e
+
+        numbers = {int(n) for n in re.findall(r"\d+", reply)}
+        unsupported = [
+            sentence for i, sentence in enumerate(sentences, 1) if i in numbers
+        ]
+        return 1 - len(unsupported) / len(sentences), unsupported
+
     def retrieve(
         self, question: str, k: int = 5, min_relevance_threshold: float = 0.0
     ) -> Tuple[str, List[str]]:
@@ -164,19 +377,7 @@ Please provide a clear and concise answer based on the context above. If the con
                 "No relevant content found in the loaded markdown files to answer this question."
             )
 
-        # Extract sources
-        sources = []
-        context_parts = []
-        for text, metadata, distance in filtered_results:
-            file_path = metadata.get("file_path", "")
-            if file_path:
-                sources.append(file_path)
-            context_parts.append(f"Source: {file_path}\n{text}")
-
-        # Build context
-        context = "\n\n---\n\n".join(context_parts)
-
-        return context, sources
+        return self._build_context(filtered_results)
 
     def answer_stream(
         self, question: str, k: int = 5, min_relevance_threshold: float = 0.0
@@ -232,9 +433,19 @@ Please provide a clear and concise answer based on the context above. If the con
                 temperature=0.7,
                 max_tokens=500,
                 stream=True,
+                stream_options={"include_usage": True},
             )
 
+            self.last_usage = None
             for chunk in stream:
+                # The usage chunk comes last, with no choices
+                usage = getattr(chunk, "usage", None)
+                if usage is not None:
+                    self.last_usage = {
+                        "prompt_tokens": usage.prompt_tokens,
+                        "completion_tokens": usage.completion_tokens,
+                        "model": self.model,
+                    }
                 if chunk.choices and chunk.choices[0].delta.content:
                     content = chunk.choices[0].delta.content
                     yield (content, None)
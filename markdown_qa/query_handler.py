@@ -1,23 +1,150 @@
 """Query handler module for processing queries."""
 
-from typing import Any, Dict, Generator, Optional
+import re
+from typing import Any, Dict, Generator, List, Optional, Tuple
 
 from markdown_qa.embeddings import EmbeddingGenerator
 from markdown_qa.formatter import ResponseFormatter
 from markdown_qa.index_manager import IndexManager
 from markdown_qa.logger import LatencyTracker, get_server_logger
 from markdown_qa.messages import (
+    ErrorCode,
     create_error_message,
+    create_followups_message,
     create_response_message,
+    create_status_message,
     create_stream_start_message,
     create_stream_chunk_message,
     create_stream_end_message,
 )
 from markdown_qa.qa import QuestionAnswerer
-from markdown_qa.retrieval import RetrievalEngine
+from markdown_qa.rerank import RerankConfig, Reranker
+from markdown_qa.retrieval import PREFERENCES, RetrievalEngine
 
 logger = get_server_logger()
 
+# Text embedded by a warm-up retrieval; any short text loads the same caches.
+WARMUP_QUERY = "warm-up"
+
+# A citation marker in an answer, e.g. "[2]".
+_CITATION_MARKER = re.compile(r"\[(\d+)\]")
+
+# Most follow-up questions suggested at once, and chunks of each source
+# shown to the LLM when suggesting them
+MAX_FOLLOWUPS = 10
+FOLLOWUP_CHUNKS_PER_SOURCE = 2
+
+# Most answers generated for one query that asks for candidates
+MAX_CANDIDATES = 5
+
+
+def _retrieval_query(message: Dict[str, Any], question: str) -> str:
+    """
+    Text to retrieve chunks with: the client's ``retrieval_query`` (question
+    plus condensed conversation history) when given, else the question.
+    """
+    retrieval_query = message.get("retrieval_query")
+    if isinstance(retrieval_query, str) and retrieval_query.strip():
+        return retrieval_query.strip()
+    return question
+
+
+def _history(message: Dict[str, Any]) -> List[Tuple[str, str]]:
+    """
+    Earlier (question, answer) pairs sent with a follow-up in ``history``.
+    Malformed entries are skipped.
+    """
+    history = message.get("history")
+    if not isinstance(history, list):
+        return []
+    return [
+        (turn["question"], turn["answer"])
+        for turn in history
+        if isinstance(turn, dict)
+        and isinstance(turn.get("question"), str)
+        and isinstance(turn.get("answer"), str)
+    ]
+
+
+def _attachment(message: Dict[str, Any]) -> Optional[str]:
+    """Material sent with the question in ``attachment`` (e.g. a git diff), if any."""
+    attachment = message.get("attachment")
+    if isinstance(attachment, str) and attachment.strip():
+        return attachment
+    return None
+
+
+def _filters(message: Dict[str, Any]) -> Optional[Dict[str, List[str]]]:
+    """
+    Directory, tag and glob filters sent in ``filters``, if any. Kinds other
+    than ``directories``, ``tags`` and ``globs`` and non-string entries are
+    skipped.
+    """
+    filters = message.get("filters")
+    if not isinstance(filters, dict):
+        return None
+    kept = {}
+    for kind in ("directories", "tags", "globs"):
+        values = filters.get(kind)
+        if isinstance(values, list):
+            values = [value for value in values if isinstance(value, str) and value]
+            if values:
+                kept[kind] = values
+    return kept or None
+
+
+def _options_error(message: Dict[str, Any]) -> Optional[Dict[str, Any]]:
+    """
+    An error message if ``prefer`` is set to something other than a
+    preference, or ``candidates`` to something other than a positive integer.
+    """
+    if message.get("prefer", "auto") not in PREFERENCES:
+        return create_error_message(
+            "Field 'prefer' must be \"code\", \"prose\" or \"auto\""
+        )
+    candidates = message.get("candidates", 1)
+    if not isinstance(candidates, int) or isinstance(candidates, bool) or candidates < 1:
+        return create_error_message("Field 'candidates' must be a positive integer")
+    return None
+
+
+def _add_usage(
+    total: Optional[Dict[str, Any]], usage: Optional[Dict[str, Any]]
+) -> Optional[Dict[str, Any]]:
+    """Token counts of two answers together, e.g. of several candidates."""
+    if total is None or usage is None:
+        return usage if total is None else total
+    return {
+        **total,
+        "prompt_tokens": total["prompt_tokens"] + usage["prompt_tokens"],
+        "completion_tokens": total["completion_tokens"] + usage["completion_tokens"],
+    }
+
+
+def _cited(answer: str, citations: Dict[str, Any]) -> Dict[str, Any]:
+    """
+    The entries of ``citations`` the answer's ``[N]`` markers refer to.
+    Markers for numbers the context did not have are left out.
+    """
+    return {
+        number: citations[number]
+        for number in dict.fromkeys(_CITATION_MARKER.findall(answer))
+        if number in citations
+    }
+
+
+def _error_code(error: Exception) -> str:
+    """
+    ErrorCode for a failed LLM or embedding API call, from the HTTP status
+    the API client attached to the exception (429, 401/403), else internal.
+    """
+    status = getattr(error, "status_code", None)
+    if status == 429:
+        return ErrorCode.RATE_LIMITED
+    if status in (401, 403):
+        return ErrorCode.AUTH_FAILED
+    return ErrorCode.INTERNAL
+
 
 class QueryHandler:
     """Handles query processing using in-memory indexes."""
@@ -26,6 +153,8 @@ class QueryHandler:
         self,
         index_manager: IndexManager,
         api_config: Optional[Any] = None,
+        docs_language: Optional[str] = None,
+        rerank: Optional[RerankConfig] = None,
     ):
         """
         Initialize query handler.
@@ -33,9 +162,57 @@ class QueryHandler:
         Args:
             index_manager: Index manager instance.
             api_config: API configuration.
+            docs_language: Primary language of the documents (e.g. "en").
+                           When set, questions in other languages are
+                           translated into it before retrieval and answered
+                           in their own language.
+            rerank: Rerank API the retrieved chunks of a query are
+                    reordered with before answering, if any.
         """
         self.index_manager = index_manager
         self.api_config = api_config
+        self.docs_language = docs_language
+        self.rerank = rerank
+
+    def _embedding_generator(self, vector_store: Any) -> EmbeddingGenerator:
+        """
+        An embedding generator for questions, caching their embeddings with
+        the index's, and like them encrypted under server.encryption.
+        """
+        cache_manager = vector_store.cache_manager
+        return EmbeddingGenerator(
+            api_config=self.api_config,
+            cache_dir=cache_manager.embedding_dir,
+            cipher=cache_manager.cipher,
+        )
+
+    def _translate(self, answerer: QuestionAnswerer, retrieval_query: str) -> str:
+        """
+        Translate the retrieval text into ``docs_language`` and have the
+        answer written in the language it was in. When the translation
+        fails, the text is retrieved with as it is.
+        """
+        try:
+            translated, language = answerer.translate(retrieval_query, self.docs_language or "")
+        except RuntimeError as e:
+            logger.warning(f"Retrieving with the untranslated question: {e}")
+            return retrieval_query
+        answerer.answer_language = language
+        return translated
+
+    def _grounding(
+        self, answerer: QuestionAnswerer, answer: str, context: str
+    ) -> Optional[Dict[str, Any]]:
+        """
+        The grounding check result of an answer for ``stream_end``, or None
+        when the check fails (the answer has been sent already).
+        """
+        try:
+            score, unsupported = answerer.check_grounding(answer, context)
+        except RuntimeError as e:
+            logger.warning(f"Sending the answer without a grounding check: {e}")
+            return None
+        return {"score": round(score, 2), "unsupported": unsupported}
 
     def handle_query(self, message: Dict[str, Any]) -> Dict[str, Any]:
         """
@@ -53,13 +230,17 @@ class QueryHandler:
         # Check if server is ready
         if not self.index_manager.is_ready():
             return create_error_message(
-                "Server is not ready. Indexes are still loading."
+                "Server is not ready. Indexes are still loading.",
+                ErrorCode.INDEX_NOT_READY,
             )
 
         # Get question
         question = message.get("question", "").strip()
         if not question:
             return create_error_message("Question cannot be empty")
+        options_error = _options_error(message)
+        if options_error is not None:
+            return options_error
 
         # Get index name (optional)
         index_name = message.get("index")
@@ -68,17 +249,35 @@ class QueryHandler:
             # Get current index
             vector_store = self.index_manager.get_index()
             if vector_store is None:
-                return create_error_message("No index available")
+                return create_error_message("No index available", ErrorCode.INDEX_NOT_READY)
 
             # Create retrieval engine and question answerer
             with latency.track("embedding_init"):
-                embedding_gen = EmbeddingGenerator(api_config=self.api_config)
-            retrieval_engine = RetrievalEngine(vector_store, embedding_gen)
-            answerer = QuestionAnswerer(retrieval_engine, api_config=self.api_config)
+                embedding_gen = self._embedding_generator(vector_store)
+            retrieval_engine = RetrievalEngine(
+                vector_store,
+                embedding_gen,
+                filters=_filters(message),
+                link_graph=vector_store.link_graph(),
+                prefer=message.get("prefer", "auto"),
+                reranker=Reranker(self.rerank) if self.rerank else None,
+                chunk_cache=getattr(self.index_manager, "chunk_cache", None),
+            )
+            answerer = QuestionAnswerer(
+                retrieval_engine,
+                api_config=self.api_config,
+                history=_history(message),
+                attachment=_attachment(message),
+            )
+
+            retrieval_query = _retrieval_query(message, question)
+            if self.docs_language:
+                with latency.track("translation"):
+                    retrieval_query = self._translate(answerer, retrieval_query)
 
             # Retrieve context (includes query embedding + vector search)
             with latency.track("retrieval"):
-                context, sources = answerer.retrieve(question)
+                context, sources = answerer.retrieve(retrieval_query)
 
             # Generate answer using LLM
             with latency.track("llm"):
@@ -102,7 +301,9 @@ class QueryHandler:
         except Exception as e:
             # Handle other errors
             logger.info(latency.format_log("query_error"))
-            return create_error_message(f"Error processing query: {str(e)}")
+            return create_error_message(
+                f"Error processing query: {str(e)}", _error_code(e)
+            )
 
     def handle_query_stream(
         self, message: Dict[str, Any]
@@ -122,7 +323,8 @@ class QueryHandler:
         # Check if server is ready
         if not self.index_manager.is_ready():
             yield create_error_message(
-                "Server is not ready. Indexes are still loading."
+                "Server is not ready. Indexes are still loading.",
+                ErrorCode.INDEX_NOT_READY,
             )
             return
 
@@ -131,41 +333,91 @@ class QueryHandler:
         if not question:
             yield create_error_message("Question cannot be empty")
             return
+        options_error = _options_error(message)
+        if options_error is not None:
+            yield options_error
+            return
 
         try:
             # Get current index
             vector_store = self.index_manager.get_index()
             if vector_store is None:
-                yield create_error_message("No index available")
+                yield create_error_message("No index available", ErrorCode.INDEX_NOT_READY)
                 return
 
             # Create retrieval engine and question answerer
             with latency.track("embedding_init"):
-                embedding_gen = EmbeddingGenerator(api_config=self.api_config)
-            retrieval_engine = RetrievalEngine(vector_store, embedding_gen)
-            answerer = QuestionAnswerer(retrieval_engine, api_config=self.api_config)
+                embedding_gen = self._embedding_generator(vector_store)
+            retrieval_engine = RetrievalEngine(
+                vector_store,
+                embedding_gen,
+                filters=_filters(message),
+                link_graph=vector_store.link_graph(),
+                prefer=message.get("prefer", "auto"),
+                reranker=Reranker(self.rerank) if self.rerank else None,
+                chunk_cache=getattr(self.index_manager, "chunk_cache", None),
+            )
+            answerer = QuestionAnswerer(
+                retrieval_engine,
+                api_config=self.api_config,
+                history=_history(message),
+                attachment=_attachment(message),
+            )
 
-            # Retrieve context (includes query embedding + vector search)
+            retrieval_query = _retrieval_query(message, question)
+            if self.docs_language:
+                yield create_status_message("translating", "Translating the question")
+                with latency.track("translation"):
+                    retrieval_query = self._translate(answerer, retrieval_query)
+
+            # Retrieve context (includes query embedding + vector search),
+            # telling the client first so a slow search doesn't look frozen
+            yield create_status_message("retrieving", "Searching the documents")
             with latency.track("retrieval"):
-                context, sources = answerer.retrieve(question)
+                context, sources = answerer.retrieve(retrieval_query)
 
             # Signal stream start
             yield create_stream_start_message()
 
-            # Stream the answer from LLM
+            # Stream the answer from LLM, then any alternative answers asked
+            # for, each tagged with its candidate index
             first_chunk_time: Optional[float] = None
+            answer_parts: List[str] = []
+            candidates = min(message.get("candidates", 1), MAX_CANDIDATES)
+            end_sources: Optional[List[str]] = None
+            usage: Optional[Dict[str, Any]] = None
 
             with latency.track("llm_stream"):
-                for chunk, final_sources in answerer.stream_with_context(
-                    question, context, sources
-                ):
-                    if final_sources is not None:
-                        # Final message with sources
-                        yield create_stream_end_message(final_sources)
-                    elif chunk:
-                        if first_chunk_time is None:
-                            first_chunk_time = latency.get_total_ms()
-                        yield create_stream_chunk_message(chunk)
+                for candidate in range(candidates):
+                    for chunk, final_sources in answerer.stream_with_context(
+                        question, context, sources
+                    ):
+                        if final_sources is not None:
+                            if candidate == 0:
+                                end_sources = final_sources
+                        elif chunk:
+                            if first_chunk_time is None:
+                                first_chunk_time = latency.get_total_ms()
+                            if candidate == 0:
+                                answer_parts.append(chunk)
+                            yield create_stream_chunk_message(
+                                chunk, candidate if candidates > 1 else None
+                            )
+                    usage = _add_usage(usage, answerer.last_usage)
+
+            if end_sources is not None:
+                grounding = None
+                if message.get("verify") is True:
+                    with latency.track("grounding"):
+                        grounding = self._grounding(answerer, "".join(answer_parts), context)
+                # Final message with sources
+                yield create_stream_end_message(
+                    end_sources,
+                    usage=usage,
+                    elapsed_ms=latency.get_total_ms(),
+                    citations=_cited("".join(answer_parts), answerer.citations),
+                    grounding=grounding,
+                )
 
             # Log latency metrics with time-to-first-chunk
             log_msg = latency.format_log("query_stream_completed")
@@ -180,4 +432,104 @@ class QueryHandler:
         except Exception as e:
             # Handle other errors
             logger.info(latency.format_log("query_stream_error"))
-            yield create_error_message(f"Error processing query: {str(e)}")
+            yield create_error_message(
+                f"Error processing query: {str(e)}", _error_code(e)
+            )
+
+    def handle_suggest_followups(self, message: Dict[str, Any]) -> Dict[str, Any]:
+        """
+        Handle a suggest_followups message: ask the LLM for follow-up
+        questions to a finished answer, showing it the first chunks of the
+        answer's sources so the questions stay within them.
+
+        Args:
+            message: Suggest followups message dictionary.
+
+        Returns:
+            Followups message, or error message.
+        """
+        latency = LatencyTracker()
+        latency.start()
+
+        question = message.get("question")
+        answer = message.get("answer")
+        count = message.get("count")
+        sources = message.get("sources")
+        if not isinstance(question, str) or not question.strip():
+            return create_error_message("Field 'question' must be a non-empty string")
+        if not isinstance(answer, str):
+            return create_error_message("Field 'answer' must be a string")
+        if not isinstance(count, int) or isinstance(count, bool) or count < 1:
+            return create_error_message("Field 'count' must be a positive integer")
+        if not isinstance(sources, list):
+            sources = []
+        paths = {source for source in sources if isinstance(source, str)}
+
+        vector_store = self.index_manager.get_index()
+        if vector_store is None:
+            return create_error_message("No index available", ErrorCode.INDEX_NOT_READY)
+
+        try:
+            with latency.track("embedding_init"):
+                embedding_gen = self._embedding_generator(vector_store)
+            answerer = QuestionAnswerer(
+                RetrievalEngine(vector_store, embedding_gen), api_config=self.api_config
+            )
+            shown: Dict[str, int] = {}
+            context_parts = []
+            for text, metadata in zip(vector_store.texts, vector_store.metadata):
+                path = str(metadata.get("file_path", ""))
+                if path in paths and shown.get(path, 0) < FOLLOWUP_CHUNKS_PER_SOURCE:
+                    shown[path] = shown.get(path, 0) + 1
+                    context_parts.append(f"{answerer._source_header(metadata)}\n{text}")
+            with latency.track("llm"):
+                questions = answerer.suggest_followups(
+                    question,
+                    answer,
+                    "\n\n---\n\n".join(context_parts),
+                    min(count, MAX_FOLLOWUPS),
+                )
+        except Exception as e:
+            logger.info(latency.format_log("followups_error"))
+            return create_error_message(
+                f"Error suggesting follow-up questions: {str(e)}", _error_code(e)
+            )
+
+        logger.info(latency.format_log("followups_completed"))
+        return create_followups_message(questions)
+
+    def handle_warmup(self) -> Dict[str, Any]:
+        """
+        Handle a warmup message: run one retrieval (query embedding + vector
+        search) without calling the LLM, so the first real question doesn't
+        pay for cold caches.
+
+        Returns:
+            Status message when the retrieval ran, error message otherwise.
+        """
+        latency = LatencyTracker()
+        latency.start()
+
+        if not self.index_manager.is_ready():
+            return create_error_message(
+                "Server is not ready. Indexes are still loading.",
+                ErrorCode.INDEX_NOT_READY,
+            )
+
+        vector_store = self.index_manager.get_index()
+        if vector_store is None:
+            return create_error_message("No index available", ErrorCode.INDEX_NOT_READY)
+
+        try:
+            with latency.track("embedding_init"):
+                embedding_gen = self._embedding_generator(vector_store)
+            with latency.track("retrieval"):
+                RetrievalEngine(vector_store, embedding_gen).retrieve(WARMUP_QUERY, k=1)
+        except Exception as e:
+            logger.info(latency.format_log("warmup_error"))
+            return create_error_message(
+                f"Error during warm-up: {str(e)}", _error_code(e)
+            )
+
+        logger.info(latency.format_log("warmup_completed"))
+        return create_status_message("ready", "Warm-up complete")
@@ -0,0 +1,106 @@
+"""Limits on the requests sent to an API, shared by everything calling it."""
+
+import threading
+import time
+from contextlib import contextmanager
+from typing import Dict, Iterator, Optional, Tuple
+
+# Longest wait a rate-limited (HTTP 429) request honors from its Retry-After
+# header, in seconds
+MAX_RETRY_AFTER = 60.0
+
+
+class RequestLimiter:
+    """
+    Lets at most ``max_concurrent`` requests be in flight at once and starts
+    at most ``per_minute`` of them a minute, spread evenly. None means no
+    limit.
+    """
+
+    def __init__(self, max_concurrent: Optional[int] = None, per_minute: Optional[int] = None):
+        """
+        Initialize request limiter.
+
+        Args:
+            max_concurrent: Requests in flight at once (api.max_concurrent_requests).
+            per_minute: Requests started a minute (api.requests_per_minute).
+        """
+        self.max_concurrent = max_concurrent
+        self.per_minute = per_minute
+        self._slots = threading.BoundedSemaphore(max_concurrent) if max_concurrent else None
+        self._interval = 60.0 / per_minute if per_minute else 0.0
+        self._lock = threading.Lock()
+        self._next_start = 0.0
+
+    @contextmanager
+    def request(self) -> Iterator[None]:
+        """Hold one request's slot, waiting for a free one and for its turn to start."""
+        if self._slots is not None:
+            self._slots.acquire()
+        try:
+            self._wait_turn()
+            yield
+        finally:
+            if self._slots is not None:
+                self._slots.release()
+
+    def _wait_turn(self) -> None:
+        """Wait until ``per_minute`` allows another request to start."""
+        if not self._interval:
+            return
+        with self._lock:
+            now = time.monotonic()
+            start = max(now, self._next_start)
+            self._next_start = start + self._interval
+        if start > now:
+            time.sleep(start - now)
+
+
+_limiters: Dict[Tuple[str, Optional[int], Optional[int]], RequestLimiter] = {}
+_limiters_lock = threading.Lock()
+
+
+def limiter_for(
+    api: str, max_concurrent: Optional[int] = None, per_minute: Optional[int] = None
+) -> RequestLimiter:
+    """
+    The limiter of an API, shared by every caller with the same limits, so
+    e.g. an index build and the questions asked meanwhile count together.
+
+    Args:
+        api: What identifies the API, e.g. its base URL.
+        max_concurrent: Requests in flight at once, or None for no limit.
+        per_minute: Requests started a minute, or None for no limit.
+
+    Returns:
+        The limiter.
+    """
+    key = (api, max_concurrent, per_minute)
+    with _limiters_lock:
+        limiter = _limiters.get(key)
+        if limiter is None:
+            limiter = _limiters[key] = RequestLimiter(max_concurrent, per_minute)
+        return limiter
+
+
+def retry_after(error: Optional[BaseException]) -> Optional[float]:
+    """
+    How long a request that failed with HTTP 429 (rate limited) should wait
+    before it is retried.
+
+    Args:
+        error: The request's error, or an error raised from it.
+
+    Returns:
+        The seconds of its Retry-After header (0 without one, at most
+        MAX_RETRY_AFTER), or None if the request was not rate limited.
+    """
+    while error is not None:
+        if getattr(error, "status_code", None) == 429:
+            headers = getattr(getattr(error, "response", None), "headers", None) or {}
+            try:
+                return min(max(0.0, float(headers.get("retry-after"))), MAX_RETRY_AFTER)
+            except (TypeError, ValueError):
+                return 0.0
+        error = error.__cause__
+    return None
@@ -1,8 +1,72 @@
 """Periodic reload scheduler module."""
 
+import re
 import threading
 import time
-from typing import Callable, Optional
+from datetime import datetime
+from datetime import time as dtime
+from pathlib import Path
+from typing import Callable, List, Optional, Sequence, Tuple
+
+# A reload window in local time, e.g. (22:00, 07:00); it may wrap past midnight
+ReloadWindow = Tuple[dtime, dtime]
+
+_WINDOW = re.compile(r"^\s*(\d{1,2}):(\d{2})\s*-\s*(\d{1,2}):(\d{2})\s*$")
+
+# Where Linux lists power supplies (AC adapters have type "Mains")
+POWER_SUPPLY_DIR = Path("/sys/class/power_supply")
+
+
+def parse_window(text: str) -> ReloadWindow:
+    """
+    Read a reload window written as ``HH:MM-HH:MM``.
+
+    Args:
+        text: The window, e.g. ``"22:00-07:00"``.
+
+    Returns:
+        Its start and end time.
+
+    Raises:
+        ValueError: If the text is not a window of valid times.
+    """
+    match = _WINDOW.match(text) if isinstance(text, str) else None
+    if match is None:
+        raise ValueError(f"Invalid reload window: {text!r} (expected HH:MM-HH:MM)")
+    hours = (int(match.group(1)), int(match.group(3)))
+    minutes = (int(match.group(2)), int(match.group(4)))
+    if max(hours) > 23 or max(minutes) > 59:
+        raise ValueError(f"Invalid reload window: {text!r} (expected HH:MM-HH:MM)")
+    return dtime(hours[0], minutes[0]), dtime(hours[1], minutes[1])
+
+
+def in_window(now: dtime, window: ReloadWindow) -> bool:
+    """Whether a time of day is in a window; a window ending before it starts wraps past midnight."""
+    start, end = window
+    if start <= end:
+        return start <= now < end
+    return now >= start or now < end
+
+
+def on_ac_power() -> Optional[bool]:
+    """
+    Whether the machine runs on AC power, as Linux reports it.
+
+    Returns:
+        True or False when an AC adapter is listed, None when there is none
+        (e.g. a desktop) or the platform does not say.
+    """
+    try:
+        supplies = list(POWER_SUPPLY_DIR.iterdir())
+    except OSError:
+        return None
+    for supply in supplies:
+        try:
+            if (supply / "type").read_text().strip() == "Mains":
+                return (supply / "online").read_text().strip() == "1"
+        except OSError:
+            continue
+    return None
 
 
 class ReloadScheduler:
@@ -12,6 +76,9 @@ class ReloadScheduler:
         self,
         reload_func: Callable[[], None],
         interval: int = 300,  # 5 minutes default
+        windows: Sequence[ReloadWindow] = (),
+        reload_on_ac_power: bool = False,
+        power_check: Callable[[], Optional[bool]] = on_ac_power,
     ):
         """
         Initialize reload scheduler.
@@ -19,9 +86,19 @@ class ReloadScheduler:
         Args:
             reload_func: Function to call for reloading indexes.
             interval: Reload interval in seconds (default: 300).
+            windows: Times of day (local) scheduled reloads may run in,
+                e.g. when the machine is usually idle. Empty means any time.
+                Triggered reloads run regardless.
+            reload_on_ac_power: Also run scheduled reloads outside the
+                windows while the machine is on AC power.
+            power_check: Platform hook telling whether the machine is on AC
+                power (None: unknown, treated as not).
         """
         self.reload_func = reload_func
         self.interval = interval
+        self.windows: List[ReloadWindow] = list(windows)
+        self.reload_on_ac_power = reload_on_ac_power
+        self.power_check = power_check
         self._stop_event = threading.Event()
         self._thread: Optional[threading.Thread] = None
         self._is_reloading = False
@@ -48,6 +125,8 @@ class ReloadScheduler:
             # Wait for interval or until stop event
             if self._stop_event.wait(self.interval):
                 break
+            if not self.may_reload():
+                continue
 
             # Perform reload if not already reloading
             with self._reload_lock:
@@ -61,6 +140,47 @@ class ReloadScheduler:
                     finally:
                         self._is_reloading = False
 
+    def may_reload(self, now: Optional[datetime] = None) -> bool:
+        """
+        Whether a scheduled reload may run now: in a reload window, on AC
+        power when that is allowed, or always when no windows are set.
+
+        Args:
+            now: Local time to check (default: now).
+        """
+        if not self.windows:
+            return True
+        moment = (now or datetime.now()).time()
+        if any(in_window(moment, window) for window in self.windows):
+            return True
+        return self.reload_on_ac_power and self.power_check() is True
+
+    def trigger(self) -> bool:
+        """
+        Start a reload in a background thread now, outside the schedule
+        and its windows (the manual override).
+
+        Returns:
+            True if a reload was started, False if one is already running.
+        """
+        with self._reload_lock:
+            if self._is_reloading:
+                return False
+            self._is_reloading = True
+        threading.Thread(target=self._run_triggered, daemon=True).start()
+        return True
+
+    def _run_triggered(self) -> None:
+        """Run a triggered reload and mark it finished."""
+        try:
+            self.reload_func()
+        except Exception:
+            # Errors are logged by the reload function
+            pass
+        finally:
+            with self._reload_lock:
+                self._is_reloading = False
+
     def is_reloading(self) -> bool:
         """
         Check if a reload is currently in progress.
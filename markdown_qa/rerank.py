@@ -0,0 +1,132 @@
+"""Reranking of retrieved chunks with a Cohere/Voyage-compatible rerank API."""
+
+import json
+import urllib.request
+from dataclasses import dataclass
+from typing import Any, Dict, List, Optional, Tuple
+
+from markdown_qa.logger import get_server_logger
+
+logger = get_server_logger()
+
+# Chunks retrieved for the reranker to choose from, unless configured
+DEFAULT_RERANK_CANDIDATES = 20
+
+# Seconds to wait for the rerank API before keeping the retrieval order
+DEFAULT_RERANK_TIMEOUT = 10.0
+
+
+@dataclass(frozen=True)
+class RerankConfig:
+    """The retrieval.rerank config section."""
+
+    url: str
+    model: str
+    api_key: Optional[str] = None
+    candidates: int = DEFAULT_RERANK_CANDIDATES
+    timeout: float = DEFAULT_RERANK_TIMEOUT
+
+    @classmethod
+    def from_dict(cls, data: Any) -> Optional["RerankConfig"]:
+        """
+        Read the retrieval.rerank section.
+
+        Args:
+            data: Mapping with ``url`` and ``model``, and optional
+                ``api_key``, ``candidates`` and ``timeout``. None means not
+                set.
+
+        Returns:
+            The rerank config, or None if reranking is not configured.
+
+        Raises:
+            ValueError: If the section or a value in it is invalid.
+        """
+        if data is None:
+            return None
+        if not isinstance(data, dict):
+            raise ValueError("retrieval.rerank must be a mapping")
+        for key in ("url", "model"):
+            if not isinstance(data.get(key), str) or not data[key].strip():
+                raise ValueError(f"retrieval.rerank.{key} must be a non-empty string")
+        api_key = data.get("api_key")
+        if api_key is not None and not isinstance(api_key, str):
+            raise ValueError("retrieval.rerank.api_key must be a string")
+        candidates = data.get("candidates", DEFAULT_RERANK_CANDIDATES)
+        if not isinstance(candidates, int) or isinstance(candidates, bool) or candidates < 1:
+            raise ValueError("retrieval.rerank.candidates must be an integer at least 1")
+        timeout = data.get("timeout", DEFAULT_RERANK_TIMEOUT)
+        if not isinstance(timeout, (int, float)) or isinstance(timeout, bool) or timeout <= 0:
+            raise ValueError("retrieval.rerank.timeout must be a positive number")
+        return cls(
+            url=data["url"].strip(),
+            model=data["model"].strip(),
+            api_key=api_key or None,
+            candidates=candidates,
+            timeout=float(timeout),
+        )
+
+
+class Reranker:
+    """Orders retrieved chunks by a rerank model's relevance scores."""
+
+    def __init__(self, config: RerankConfig):
+        """
+        Initialize reranker.
+
+        Args:
+            config: The retrieval.rerank config section.
+        """
+        self.config = config
+
+    @property
+    def candidates(self) -> int:
+        """Number of chunks to retrieve for reranking."""
+        return self.config.candidates
+
+    def _scores(self, query: str, documents: List[str]) -> Dict[int, float]:
+        """
+        Ask the rerank API how relevant each document is to the query.
+
+        Returns:
+            Relevance score by document index.
+        """
+        body = json.dumps(
+            {"model": self.config.model, "query": query, "documents": documents}
+        ).encode()
+        headers = {"Content-Type": "application/json"}
+        if self.config.api_key:
+            headers["Authorization"] = f"Bearer {self.config.api_key}"
+        request = urllib.request.Request(self.config.url, data=body, headers=headers)
+        with urllib.request.urlopen(request, timeout=self.config.timeout) as response:
+            reply = json.loads(response.read())
+        # Cohere lists the scores in "results", Voyage in "data"
+        results = reply.get("results") or reply.get("data") or []
+        return {int(result["index"]): float(result["relevance_score"]) for result in results}
+
+    def rerank(
+        self, query: str, results: List[Tuple[str, Dict[str, Any], float]]
+    ) -> List[Tuple[str, Dict[str, Any], float]]:
+        """
+        Order retrieved chunks by relevance to the query, most relevant
+        first. Distances are returned as searched. When the rerank API
+        fails, the chunks are returned in retrieval order.
+
+        Args:
+            query: The text the chunks were retrieved with.
+            results: Retrieved (text, metadata, distance) tuples.
+
+        Returns:
+            The same tuples, reordered.
+        """
+        if len(results) < 2:
+            return results
+        try:
+            scores = self._scores(query, [text for text, _, _ in results])
+        except Exception as e:
+            logger.warning(f"Keeping the retrieval order, reranking failed: {e}")
+            return results
+        order = sorted(
+            range(len(results)), key=lambda i: (-scores.get(i, float("-inf")), i)
+        )
+        return [results[i] for i in order]
@@ -1,10 +1,126 @@
 """Retrieval module for finding relevant chunks."""
 
-from typing import Any, Dict, List, Tuple
+import re
+from fnmatch import fnmatchcase
+from pathlib import PurePosixPath
+from typing import Any, Dict, List, Optional, Sequence, Set, Tuple
 
+import numpy as np
+
+from markdown_qa.chunk_cache import ChunkCache, neighbor_ids
 from markdown_qa.embeddings import EmbeddingGenerator
+from markdown_qa.links import LinkGraph
+from markdown_qa.rerank import Reranker
 from markdown_qa.vector_store import VectorStore
 
+# How many more chunks to search for when filters are set, since most of the
+# nearest chunks may be filtered out.
+FILTER_OVERFETCH = 10
+
+# How many more chunks to search for when documents link to each other, so
+# chunks of documents linked with the best match can move up into the top k.
+LINK_OVERFETCH = 2
+
+# Factor applied to the distance of chunks from documents linked with the
+# best match's document (lower distance = more relevant).
+LINK_BOOST = 0.9
+
+# Retrieval preferences a query may send in ``prefer``
+PREFERENCES = ("code", "prose", "auto")
+
+# How many more chunks to search for when code or prose is preferred, and
+# the factor applied to the distance of chunks of the preferred kind.
+PREFER_OVERFETCH = 2
+PREFER_BOOST = 0.8
+
+# Signs that a question is about code: `inline code`, a call such as foo(),
+# snake_case or camelCase identifiers, paths like os.path or std::fs, and
+# error names such as TypeError.
+_CODE_IN_QUESTION = re.compile(
+    r"`[^`]+`"
+    r"|\b[A-Za-z_]\w*\("
+    r"|\b[a-z0-9]+_[a-z0-9_]+\b"
+    r"|\b[a-z]+[A-Z]\w*\b"
+    r"|\b[A-Za-z_]\w+(?:\.|::)[A-Za-z_]\w+"
+    r"|\b\w+(?:Error|Exception)\b"
+)
+
+
+def looks_like_code(question: str) -> bool:
+    """Whether a question mentions code identifiers or error names."""
+    return bool(_CODE_IN_QUESTION.search(question))
+
+
+def _in_directory(path: PurePosixPath, directory: str) -> bool:
+    """
+    Whether ``path`` is in ``directory``. A relative directory matches at any
+    depth, so ``docs/api`` matches ``/repo/docs/api/auth.md``.
+    """
+    prefix = PurePosixPath(directory.replace("\\", "/")).parts
+    if not prefix:
+        return True
+    parents = path.parts[:-1]
+    if directory.startswith("/"):
+        return parents[: len(prefix)] == prefix
+    return any(
+        parents[i : i + len(prefix)] == prefix
+        for i in range(len(parents) - len(prefix) + 1)
+    )
+
+
+def nearest(distances: Sequence[float], k: int) -> List[int]:
+    """
+    Positions of the ``k`` smallest distances, smallest first. Only those
+    are sorted: np.argpartition picks them out of the rest in linear time.
+
+    Args:
+        distances: Distances, e.g. of retrieved chunks.
+        k: How many to pick.
+
+    Returns:
+        Their positions in ``distances``, in order of distance; equal
+        distances keep their order.
+    """
+    values = np.asarray(distances, dtype=np.float64)
+    if k <= 0 or not len(values):
+        return []
+    if k < len(values):
+        picked = np.argpartition(values, k - 1)[:k]
+        # Order the picked by position first, so that ties stay in order
+        picked.sort()
+    else:
+        picked = np.arange(len(values))
+    return [int(i) for i in picked[np.argsort(values[picked], kind="stable")]]
+
+
+def matches_filters(metadata: Dict[str, Any], filters: Dict[str, List[str]]) -> bool:
+    """
+    Whether a chunk passes a query's filters.
+
+    Each kind that is set must match: the chunk's file is in one of
+    ``directories``, its front matter lists one of ``tags``, and its path or
+    file name matches one of ``globs``.
+
+    Args:
+        metadata: Chunk metadata with ``file_path`` and optional ``tags``.
+        filters: Lists of ``directories``, ``tags`` and ``globs``.
+    """
+    path = PurePosixPath(str(metadata.get("file_path", "")).replace("\\", "/"))
+
+    directories = filters.get("directories") or []
+    if directories and not any(_in_directory(path, d) for d in directories):
+        return False
+
+    tags = filters.get("tags") or []
+    if tags and not set(tags) & set(metadata.get("tags") or []):
+        return False
+
+    globs = filters.get("globs") or []
+    if globs and not any(fnmatchcase(path.name, g) or path.match(g) for g in globs):
+        return False
+
+    return True
+
 
 class RetrievalEngine:
     """Engine for retrieving relevant chunks from vector store."""
@@ -13,6 +129,11 @@ class RetrievalEngine:
         self,
         vector_store: VectorStore,
         embedding_generator: EmbeddingGenerator,
+        filters: Optional[Dict[str, List[str]]] = None,
+        link_graph: Optional[LinkGraph] = None,
+        prefer: str = "auto",
+        reranker: Optional[Reranker] = None,
+        chunk_cache: Optional[ChunkCache] = None,
     ):
         """
         Initialize retrieval engine.
@@ -20,9 +141,25 @@ class RetrievalEngine:
         Args:
             vector_store: Vector store instance.
             embedding_generator: Embedding generator instance.
+            filters: Only retrieve chunks that pass these filters
+                (see matches_filters).
+            link_graph: Links between the indexed documents; chunks of
+                documents linked with the best match's are ranked higher.
+            prefer: Rank chunks of fenced code blocks ("code") or the
+                others ("prose") higher; "auto" prefers code when the
+                question looks like it is about code (see looks_like_code).
+            reranker: Reorders the best ``reranker.candidates`` chunks by a
+                rerank model's relevance before the top k are taken.
+            chunk_cache: Recently retrieved chunks and their neighbors, to
+                look found chunks up in first.
         """
         self.vector_store = vector_store
         self.embedding_generator = embedding_generator
+        self.filters = filters
+        self.link_graph = link_graph
+        self.prefer = prefer
+        self.reranker = reranker
+        self.chunk_cache = chunk_cache
 
     def retrieve(
         self, query: str, k: int = 5
@@ -40,7 +177,95 @@ class RetrievalEngine:
         # Generate embedding for query
         query_embedding = self.embedding_generator.generate_embedding(query)
 
-        # Search vector store (returns text, metadata, distance)
-        results = self.vector_store.search(query_embedding, k=k)
+        prefer: Optional[str] = self.prefer
+        if prefer == "auto":
+            prefer = "code" if looks_like_code(query) else None
+
+        wanted = k
+        if self.reranker is not None:
+            wanted = max(k, self.reranker.candidates)
+
+        if not self.filters and not self.link_graph and not prefer:
+            # Search vector store (returns text, metadata, distance)
+            results = self._search(query_embedding, wanted)
+        else:
+            fetch = wanted
+            if self.filters:
+                fetch *= FILTER_OVERFETCH
+            if self.link_graph:
+                fetch *= LINK_OVERFETCH
+            if prefer:
+                fetch *= PREFER_OVERFETCH
+            results = self._search(query_embedding, fetch)
+            if self.filters:
+                results = [
+                    result
+                    for result in results
+                    if matches_filters(result[1], self.filters)
+                ]
+            if self.link_graph or prefer:
+                results = self._boost(results, self.link_graph, prefer, wanted)
+            results = results[:wanted]
+
+        if self.reranker is not None:
+            results = self.reranker.rerank(query, results)
+        return results[:k]
+
+    def _search(
+        self, query_embedding: List[float], k: int
+    ) -> List[Tuple[str, Dict[str, Any], float]]:
+        """
+        Search the vector store, looking the found chunks up in the chunk
+        cache first. A chunk that is not cached is added with its
+        neighbors.
+        """
+        cache = self.chunk_cache
+        if cache is None:
+            return self.vector_store.search(query_embedding, k=k)
 
+        results = []
+        for chunk_id, distance in self.vector_store.search_ids(query_embedding, k=k):
+            chunk = cache.get(chunk_id)
+            if chunk is None:
+                chunk = self.vector_store.get_chunk(chunk_id)
+                if chunk is None:
+                    continue
+                cache.put(chunk_id, *chunk)
+                for neighbor_id in neighbor_ids(chunk_id):
+                    if neighbor_id not in cache:
+                        neighbor = self.vector_store.get_chunk(neighbor_id)
+                        if neighbor is not None:
+                            cache.put(neighbor_id, *neighbor)
+            results.append((chunk[0], chunk[1], distance))
         return results
+
+    def _boost(
+        self,
+        results: List[Tuple[str, Dict[str, Any], float]],
+        link_graph: Optional[LinkGraph],
+        prefer: Optional[str],
+        k: int,
+    ) -> List[Tuple[str, Dict[str, Any], float]]:
+        """
+        The best ``k`` chunks, ranking chunks of documents linked with the
+        best match's document as if they were LINK_BOOST times as distant,
+        and chunks of the preferred kind (code or prose) as if PREFER_BOOST
+        times. Distances are returned as searched.
+        """
+        if not results:
+            return results
+        neighbors: Set[str] = set()
+        if link_graph:
+            neighbors = link_graph.neighbors(str(results[0][1].get("file_path", "")))
+        if not neighbors and not prefer:
+            return results[:k]
+
+        def rank(result: Tuple[str, Dict[str, Any], float]) -> float:
+            distance = result[2]
+            if str(result[1].get("file_path", "")) in neighbors:
+                distance *= LINK_BOOST
+            if prefer and ("code_language" in result[1]) == (prefer == "code"):
+                distance *= PREFER_BOOST
+            return distance
+
+        return [results[i] for i in nearest([rank(result) for result in results], k)]
@@ -1,27 +1,71 @@
 """WebSocket server module for markdown Q&A system."""
 
 import asyncio
-import json
+import shutil
 import signal
+import sys
 import time
 from pathlib import Path
-from typing import Optional
+from typing import Any, Dict, List, Optional, Tuple
 
 import websockets
 from websockets.server import ServerConnection
 
+from markdown_qa.cache import CacheManager
+from markdown_qa.chunker import ChunkingConfig
 from markdown_qa.config_watcher import ConfigWatcher
 from markdown_qa.index_manager import IndexManager
-from markdown_qa.logger import get_server_logger
+from markdown_qa.loader import plan_markdown_files
+from markdown_qa.logger import get_server_log_tail, get_server_logger
 from markdown_qa.messages import (
+    ErrorCode,
     MessageType,
+    create_capabilities_message,
+    create_chunks_message,
+    create_documents_message,
     create_error_message,
+    create_index_plan_message,
+    create_index_progress_message,
+    create_index_restore_message,
+    create_index_snapshot_message,
+    create_index_stats_message,
+    create_index_verify_message,
+    create_indexes_message,
+    create_links_message,
+    create_log_message,
+    create_logs_message,
+    create_notification_message,
     create_status_message,
     validate_query_message,
 )
+from markdown_qa.named_indexes import (
+    NAMED_INDEX_DIR,
+    NamedIndex,
+    NamedIndexSettings,
+    index_api_config,
+    load_registry,
+    save_registry,
+)
 from markdown_qa.query_handler import QueryHandler
 from markdown_qa.reload_scheduler import ReloadScheduler
 from markdown_qa.server_config import ServerConfig
+from markdown_qa.wire import JSON, decode, encode, pick_encoding
+
+# Optional protocol features reported in reply to a capabilities message:
+# cancel messages, conversation history on queries, permessage-deflate
+# (on by default in websockets.serve), tail_logs requests, and named indexes
+# (list_indexes, index_create, index_delete)
+SERVER_FEATURES = ["cancel", "history", "compression", "logs", "list_indexes"]
+
+# Lines sent in reply to a tail_logs request that doesn't say how many
+DEFAULT_TAIL_LINES = 100
+
+# Followed log lines queued for a slow client before new ones are dropped
+MAX_PENDING_LOG_LINES = 1000
+
+# Shortest time between index_progress messages; the ones in between are
+# dropped, except those reporting a file that failed and the last one
+INDEX_PROGRESS_INTERVAL = 0.25
 
 
 class MarkdownQAServer:
@@ -36,15 +80,144 @@ class MarkdownQAServer:
         """
         self.config = config
         self.logger = get_server_logger()
-        self.index_manager = IndexManager(api_config=config.api_config)
+        self.log_tail = get_server_log_tail()
+        # Shared by every index manager, so the encryption key is derived once
+        self.cache_manager = CacheManager(encryption=config.encryption)
+        self.index_manager = IndexManager(
+            cache_manager=self.cache_manager,
+            api_config=config.api_config,
+            max_file_size_mb=config.max_file_size_mb,
+            index_drafts=config.index_drafts,
+            chunking=config.chunking,
+            extensions=config.extensions,
+            ann=config.ann,
+        )
+        self.index_manager.on_progress = self._report_index_progress
         self.query_handler = QueryHandler(
-            self.index_manager, api_config=config.api_config
+            self.index_manager,
+            api_config=config.api_config,
+            docs_language=config.docs_language,
+            rerank=config.rerank,
         )
+        # Indexes created with index_create, by name; loaded in start
+        self.named_indexes: Dict[str, NamedIndex] = {
+            name: self._new_named_index(settings)
+            for name, settings in load_registry(self.cache_manager.cache_dir).items()
+        }
+        # Builds of named indexes just created, by name
+        self._index_builds: Dict[str, asyncio.Task[None]] = {}
         self.reload_scheduler: Optional[ReloadScheduler] = None
         self.config_watcher: Optional[ConfigWatcher] = None
         self._server: Optional[websockets.server.Server] = None  # type: ignore[assignment]
         self._shutdown_event = asyncio.Event()
         self._config_file_path: Optional[Path] = None
+        # Frame encoding picked in each connection's capabilities handshake;
+        # connections not listed use JSON text frames
+        self._encodings: dict[Any, str] = {}
+        # Tasks sending new log lines to the connections following the log
+        self._log_followers: dict[Any, asyncio.Task[None]] = {}
+        # Open connections, which are sent index_progress messages
+        self._connections: set[Any] = set()
+        self._loop: Optional[asyncio.AbstractEventLoop] = None
+        self._last_progress_at = 0.0
+
+    async def _send(self, websocket: ServerConnection, message: dict) -> None:  # type: ignore[type-arg]
+        """Send a message in the connection's frame encoding."""
+        encoding = self._encodings.get(websocket, JSON)
+        await websocket.send(encode(message, encoding))  # type: ignore[attr-defined]
+
+    def _new_named_index(self, settings: NamedIndexSettings) -> NamedIndex:
+        """The index manager and query handler of a named index, with a cache of its own."""
+        api_config = index_api_config(self.config.api_config, settings.embedding_model)
+        index_manager = IndexManager(
+            cache_manager=CacheManager(
+                self.cache_manager.cache_dir / NAMED_INDEX_DIR / settings.name,
+                encryption=self.config.encryption,
+            ),
+            api_config=api_config,
+            max_file_size_mb=self.config.max_file_size_mb,
+            index_drafts=self.config.index_drafts,
+            chunking=ChunkingConfig.from_dict(settings.chunking),
+            extensions=self.config.extensions,
+            ann=self.config.ann,
+        )
+        query_handler = QueryHandler(
+            index_manager,
+            api_config=api_config,
+            docs_language=self.config.docs_language,
+            rerank=self.config.rerank,
+        )
+        return NamedIndex(settings, index_manager, query_handler)
+
+    def _lookup_index(
+        self, index: Optional[str]
+    ) -> Optional[Tuple[str, List[str], IndexManager]]:
+        """
+        The index a message's ``index`` field names: the configured one when
+        unset, else a named one.
+
+        Returns:
+            Its name, directories and index manager, or None if there is no
+            such index.
+        """
+        if index is None or index == self.config.index_name:
+            return self.config.index_name, self.config.directories, self.index_manager
+        named = self.named_indexes.get(index)
+        if named is None:
+            return None
+        return index, named.settings.directories, named.index_manager
+
+    def _index_listing(self) -> List[Dict[str, Any]]:
+        """The configured index and the named ones, for an indexes message."""
+        reloading = bool(self.reload_scheduler and self.reload_scheduler.is_reloading())
+
+        def entry(
+            name: str, directories: List[str], manager: IndexManager, building: bool
+        ) -> Dict[str, Any]:
+            if manager.is_ready():
+                status = "ready"
+            elif building or reloading:
+                status = "indexing"
+            else:
+                status = "not_ready"
+            return {
+                "name": name,
+                "directories": directories,
+                "embedding_model": manager.api_config.embedding_model
+                or "text-embedding-3-small",
+                "default": name == self.config.index_name,
+                "status": status,
+            }
+
+        listing = [
+            entry(self.config.index_name, self.config.directories, self.index_manager, False)
+        ]
+        for name, named in sorted(self.named_indexes.items()):
+            listing.append(
+                entry(
+                    name,
+                    named.settings.directories,
+                    named.index_manager,
+                    name in self._index_builds,
+                )
+            )
+        return listing
+
+    def _build_named_index(self, named: NamedIndex) -> None:
+        """Load a named index, building it if it has not been built (in a worker thread)."""
+        name = named.settings.name
+        try:
+            named.index_manager.load_index(name, named.settings.directories)
+            self.logger.info(f"Index {name} loaded")
+        except Exception as e:
+            self.logger.error(f"Error building index {name}: {e}", exc_info=True)
+
+    def _save_named_indexes(self) -> None:
+        """Keep the settings of the named indexes for the next start."""
+        save_registry(
+            self.cache_manager.cache_dir,
+            {name: named.settings for name, named in self.named_indexes.items()},
+        )
 
     async def _handle_client(self, websocket: ServerConnection) -> None:  # type: ignore[type-arg]
         """
@@ -53,23 +226,131 @@ class MarkdownQAServer:
         Args:
             websocket: WebSocket connection.
         """
-        # Handle messages
+        # A query is answered in a task so that a cancel message for it can
+        # be read while it streams; other messages wait for it to finish
+        running: Optional[tuple[Any, asyncio.Task[None]]] = None
+        self._connections.add(websocket)
         try:
             async for message in websocket:  # type: ignore[attr-defined]
                 try:
-                    data = json.loads(message)
-                    await self._process_message(websocket, data)
-                except json.JSONDecodeError:
-                    await websocket.send(  # type: ignore[attr-defined]
-                        json.dumps(create_error_message("Invalid JSON format"))
+                    data = decode(message, self._encodings.get(websocket, JSON))
+                    if data.get("type") == MessageType.CANCEL:
+                        if running and running[0] == data.get("request_id"):
+                            running[1].cancel()
+                        continue
+                    if running:
+                        await asyncio.wait([running[1]])
+                        running = None
+                    if data.get("type") == MessageType.QUERY:
+                        task = asyncio.create_task(
+                            self._process_message(websocket, data)
+                        )
+                        running = (data.get("id"), task)
+                    else:
+                        await self._process_message(websocket, data)
+                except ValueError:
+                    await self._send(
+                        websocket, create_error_message("Invalid JSON format")
                     )
                 except Exception as e:
-                    await websocket.send(  # type: ignore[attr-defined]
-                        json.dumps(create_error_message(f"Error: {str(e)}"))
+                    await self._send(
+                        websocket, create_error_message(f"Error: {str(e)}")
                     )
         except websockets.exceptions.ConnectionClosed:
             # Client disconnected, this is normal
             pass
+        finally:
+            if running:
+                running[1].cancel()
+            self._encodings.pop(websocket, None)
+            self._unfollow_logs(websocket)
+            self._connections.discard(websocket)
+
+    def _report_index_progress(self, progress: dict) -> None:  # type: ignore[type-arg]
+        """
+        Send index build progress to every connected client.
+
+        Called by the index manager, often from the reload scheduler's thread.
+
+        Args:
+            progress: Arguments of create_index_progress_message.
+        """
+        now = time.monotonic()
+        urgent = progress.get("finished") or progress.get("error")
+        if not urgent and now - self._last_progress_at < INDEX_PROGRESS_INTERVAL:
+            return
+        self._last_progress_at = now
+        self._broadcast_threadsafe(create_index_progress_message(**progress))
+
+    def _notify_documents_changed(self, documents: list, message: str) -> None:  # type: ignore[type-arg]
+        """
+        Tell every connected client which documents reindexing changed.
+
+        Args:
+            documents: Paths of the documents added, changed or removed.
+            message: Summary of the change.
+        """
+        if documents:
+            self._broadcast_threadsafe(
+                create_notification_message(sorted(set(documents)), message)
+            )
+
+    def _broadcast_threadsafe(self, message: dict) -> None:  # type: ignore[type-arg]
+        """Send a message to every connected client from any thread."""
+        loop = self._loop
+        if loop is None or loop.is_closed():
+            return
+        try:
+            asyncio.run_coroutine_threadsafe(self._broadcast(message), loop)
+        except RuntimeError:
+            # The event loop is closed
+            pass
+
+    async def _broadcast(self, message: dict) -> None:  # type: ignore[type-arg]
+        """Send a message to every connected client."""
+        for websocket in list(self._connections):
+            try:
+                await self._send(websocket, message)
+            except websockets.exceptions.ConnectionClosed:
+                pass
+
+    def _follow_logs(self, websocket: ServerConnection) -> None:  # type: ignore[type-arg]
+        """Send every line logged from now on to the connection as a log message."""
+        if websocket in self._log_followers:
+            return
+        loop = asyncio.get_running_loop()
+        pending: asyncio.Queue[str] = asyncio.Queue(maxsize=MAX_PENDING_LOG_LINES)
+
+        def offer(line: str) -> None:
+            if not pending.full():
+                pending.put_nowait(line)
+
+        def listener(line: str) -> None:
+            # Lines are logged from worker threads too
+            try:
+                loop.call_soon_threadsafe(offer, line)
+            except RuntimeError:
+                # The event loop is closed
+                pass
+
+        async def forward() -> None:
+            try:
+                while True:
+                    line = await pending.get()
+                    await self._send(websocket, create_log_message(line))
+            except websockets.exceptions.ConnectionClosed:
+                pass
+            finally:
+                self.log_tail.remove_listener(listener)
+
+        self.log_tail.add_listener(listener)
+        self._log_followers[websocket] = asyncio.create_task(forward())
+
+    def _unfollow_logs(self, websocket: ServerConnection) -> None:  # type: ignore[type-arg]
+        """Stop sending log lines to the connection, if it follows the log."""
+        task = self._log_followers.pop(websocket, None)
+        if task is not None:
+            task.cancel()
 
     async def _process_message(
         self,
@@ -88,19 +369,31 @@ class MarkdownQAServer:
         self.logger.info(f"Received message: {message}")
 
         if msg_type == MessageType.QUERY:
+            # Echo the query's id on every reply so the client can tell late
+            # replies to an earlier query apart
+            query_id = message.get("id")
+
+            def tagged(response: dict) -> dict:  # type: ignore[type-arg]
+                if query_id is not None:
+                    response["id"] = query_id
+                return response
+
             # Validate query message
             is_valid, error = validate_query_message(message)
             if not is_valid:
-                await websocket.send(  # type: ignore[attr-defined]
-                    json.dumps(create_error_message(error or "Invalid query"))
+                await self._send(
+                    websocket, tagged(create_error_message(error or "Invalid query"))
                 )
                 return
 
-            # Handle query with streaming response
+            # Handle query with streaming response; a named index's own
+            # handler answers queries naming it
+            named = self.named_indexes.get(message.get("index"))  # type: ignore[arg-type]
+            query_handler = named.query_handler if named else self.query_handler
             chunk_count = 0
             try:
-                for response in self.query_handler.handle_query_stream(message):
-                    await websocket.send(json.dumps(response))  # type: ignore[attr-defined]
+                for response in query_handler.handle_query_stream(message):
+                    await self._send(websocket, tagged(response))
                     if response.get("type") == MessageType.STREAM_CHUNK:
                         chunk_count += 1
                         self.logger.debug(
@@ -111,12 +404,24 @@ class MarkdownQAServer:
                 self.logger.info(
                     f"request_completed type=query request_ms={request_ms:.2f} chunks={chunk_count}"
                 )
+            except asyncio.CancelledError:
+                # The client sent a cancel message; end its stream
+                request_ms = (time.perf_counter() - request_start) * 1000
+                self.logger.info(
+                    f"request_cancelled type=query request_ms={request_ms:.2f} chunks={chunk_count}"
+                )
+                try:
+                    await self._send(
+                        websocket, tagged(create_error_message("Query cancelled"))
+                    )
+                except websockets.exceptions.ConnectionClosed:
+                    pass
             except Exception as e:
                 # If query handling fails, send error response
                 error_response = create_error_message(
-                    f"Error processing query: {str(e)}"
+                    f"Error processing query: {str(e)}", ErrorCode.INTERNAL
                 )
-                await websocket.send(json.dumps(error_response))  # type: ignore[attr-defined]
+                await self._send(websocket, tagged(error_response))
                 request_ms = (time.perf_counter() - request_start) * 1000
                 self.logger.error(
                     f"request_error type=query request_ms={request_ms:.2f} error={e}",
@@ -138,15 +443,387 @@ class MarkdownQAServer:
                 else:
                     msg = "Server started without valid directories configured"
 
-            await websocket.send(json.dumps(create_status_message(status, msg)))  # type: ignore[attr-defined]
+            await self._send(websocket, create_status_message(status, msg))
             request_ms = (time.perf_counter() - request_start) * 1000
             self.logger.info(
                 f"request_completed type=status request_ms={request_ms:.2f}"
             )
 
+        elif msg_type == MessageType.RELOAD:
+            # Client asking to re-index now, e.g. after editing markdown files
+            index = message.get("index")
+            target = self._lookup_index(index)
+            if target is None:
+                reply = create_error_message(f"Unknown index: {index}")
+            elif not target[1]:
+                reply = create_error_message("No directories configured")
+            elif self.reload_scheduler is None:
+                reply = create_error_message("Server is not running")
+            elif self.reload_scheduler.trigger():
+                reply = create_status_message("indexing", "Reindexing started")
+            else:
+                reply = create_status_message("indexing", "Server reloading indexes")
+
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=reload request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.WARMUP:
+            # Client warming up caches after connecting; no LLM call
+            reply = await asyncio.to_thread(self.query_handler.handle_warmup)
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=warmup request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.SUGGEST_FOLLOWUPS:
+            # Client offering follow-up questions to the answer it just got
+            reply = await asyncio.to_thread(
+                self.query_handler.handle_suggest_followups, message
+            )
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=suggest_followups request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.CAPABILITIES:
+            # Client asking which optional features it can use
+            # and, if it offered binary frame encodings, which one to use.
+            # The reply is still in the old encoding; later frames use the
+            # picked one both ways
+            encoding = pick_encoding(message.get("encodings"))
+            reply = create_capabilities_message(SERVER_FEATURES, encoding)
+            await self._send(websocket, reply)
+            if encoding is not None:
+                self._encodings[websocket] = encoding
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=capabilities request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.INDEX_STATS:
+            # Client sanity-checking the index: counts, model, age and size
+            index = message.get("index")
+            target = self._lookup_index(index)
+            if target is None:
+                reply = create_error_message(f"Unknown index: {index}")
+            else:
+                name, _, manager = target
+                stats = await asyncio.to_thread(manager.index_stats, name)
+                if stats is None:
+                    reply = create_error_message(
+                        "Index not loaded", ErrorCode.INDEX_NOT_READY
+                    )
+                else:
+                    reply = create_index_stats_message(name, **stats)
+
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=index_stats request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.INDEX_PLAN:
+            # Client testing its ignore files: which files a build would read;
+            # with estimate, also what a reload would embed, to confirm it
+            index = message.get("index")
+            target = self._lookup_index(index)
+            if target is None:
+                reply = create_error_message(f"Unknown index: {index}")
+            else:
+                name, directories, manager = target
+                files, ignored = await asyncio.to_thread(
+                    plan_markdown_files,
+                    directories,
+                    self.config.extensions,
+                )
+                estimate = None
+                if message.get("estimate"):
+                    estimate = await asyncio.to_thread(
+                        manager.estimate_reload, name, directories
+                    )
+                reply = create_index_plan_message(name, files, ignored, estimate)
+
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=index_plan request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.INDEX_VERIFY:
+            # Client checking for chunks of deleted or excluded files, and
+            # with remove, dropping them
+            index = message.get("index")
+            target = self._lookup_index(index)
+            if target is None:
+                reply = create_error_message(f"Unknown index: {index}")
+            else:
+                name, directories, manager = target
+                report = await asyncio.to_thread(
+                    manager.verify_index,
+                    name,
+                    directories,
+                    bool(message.get("remove")),
+                )
+                if report is None:
+                    reply = create_error_message(
+                        "Index not loaded", ErrorCode.INDEX_NOT_READY
+                    )
+                else:
+                    reply = create_index_verify_message(name, **report)
+
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=index_verify request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type in (MessageType.INDEX_SNAPSHOT, MessageType.INDEX_RESTORE):
+            # Client packaging the index into an archive on this machine for
+            # teammates, or replacing the index with one
+            index = message.get("index")
+            path = message.get("path")
+            target = self._lookup_index(index)
+            if target is None:
+                reply = create_error_message(f"Unknown index: {index}")
+            elif not isinstance(path, str) or not path:
+                reply = create_error_message("Missing 'path' field")
+            elif not Path(path).is_absolute():
+                reply = create_error_message("Field 'path' must be an absolute path")
+            elif msg_type == MessageType.INDEX_SNAPSHOT:
+                name, directories, manager = target
+                try:
+                    snapshot = await asyncio.to_thread(
+                        manager.snapshot_index, name, directories, Path(path)
+                    )
+                except (ValueError, OSError) as e:
+                    reply = create_error_message(f"Snapshot failed: {e}")
+                else:
+                    if snapshot is None:
+                        reply = create_error_message(
+                            "Index not loaded", ErrorCode.INDEX_NOT_READY
+                        )
+                    else:
+                        reply = create_index_snapshot_message(name, path, **snapshot)
+            elif self.reload_scheduler and self.reload_scheduler.is_reloading():
+                reply = create_error_message(
+                    "Server reloading indexes", ErrorCode.INDEX_NOT_READY
+                )
+            else:
+                name, directories, manager = target
+                try:
+                    restored = await asyncio.to_thread(
+                        manager.restore_snapshot, name, directories, Path(path)
+                    )
+                except (ValueError, OSError) as e:
+                    reply = create_error_message(f"Restore failed: {e}")
+                else:
+                    # Pick up documents that differ from the snapshot's; their
+                    # embeddings are mostly in the restored cache
+                    reindexing = bool(
+                        directories
+                        and self.reload_scheduler is not None
+                        and self.reload_scheduler.trigger()
+                    )
+                    reply = create_index_restore_message(
+                        name, path, reindexing=reindexing, **restored
+                    )
+
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type={msg_type} request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type in (
+            MessageType.LIST_INDEXES,
+            MessageType.INDEX_CREATE,
+            MessageType.INDEX_DELETE,
+        ):
+            # Client picking an index to query, or managing the named ones;
+            # each is answered with the indexes there are now
+            name = message.get("name")
+            reply = None
+            if msg_type == MessageType.INDEX_CREATE:
+                try:
+                    settings = NamedIndexSettings.from_message(message)
+                except ValueError as e:
+                    reply = create_error_message(str(e))
+                else:
+                    if settings.name == self.config.index_name or (
+                        settings.name in self.named_indexes
+                    ):
+                        reply = create_error_message(f"Index {settings.name} already exists")
+                    else:
+                        try:
+                            named = await asyncio.to_thread(self._new_named_index, settings)
+                        except (ValueError, ImportError) as e:
+                            reply = create_error_message(f"Cannot create index: {e}")
+                        else:
+                            self.named_indexes[settings.name] = named
+                            self._save_named_indexes()
+                            # Built in the background; its status says when it is ready
+                            build = asyncio.create_task(
+                                asyncio.to_thread(self._build_named_index, named)
+                            )
+                            self._index_builds[settings.name] = build
+                            build.add_done_callback(
+                                lambda _, name=settings.name: self._index_builds.pop(name, None)
+                            )
+                            self.logger.info(
+                                f"Index {settings.name} created for {settings.directories}"
+                            )
+            elif msg_type == MessageType.INDEX_DELETE:
+                if name == self.config.index_name:
+                    reply = create_error_message("The configured index can't be deleted")
+                elif name not in self.named_indexes:
+                    reply = create_error_message(f"Unknown index: {name}")
+                elif name in self._index_builds or (
+                    self.reload_scheduler and self.reload_scheduler.is_reloading()
+                ):
+                    reply = create_error_message(
+                        f"Index {name} is being built", ErrorCode.INDEX_NOT_READY
+                    )
+                else:
+                    named = self.named_indexes.pop(name)
+                    self._save_named_indexes()
+                    named.index_manager.clear_index()
+                    await asyncio.to_thread(
+                        shutil.rmtree, named.index_manager.cache_manager.cache_dir, True
+                    )
+                    self.logger.info(f"Index {name} deleted")
+            if reply is None:
+                reply = create_indexes_message(self._index_listing())
+
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type={msg_type} request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.LIST_DOCUMENTS:
+            # Client browsing the index, one page of documents at a time
+            index = message.get("index")
+            page = message.get("page")
+            page_size = message.get("page_size")
+            target = self._lookup_index(index)
+            if target is None:
+                reply = create_error_message(f"Unknown index: {index}")
+            elif not isinstance(page, int) or isinstance(page, bool) or page < 0:
+                reply = create_error_message(
+                    "Field 'page' must be a non-negative integer"
+                )
+            elif (
+                not isinstance(page_size, int)
+                or isinstance(page_size, bool)
+                or page_size < 1
+            ):
+                reply = create_error_message(
+                    "Field 'page_size' must be a positive integer"
+                )
+            else:
+                name, _, manager = target
+                listing = await asyncio.to_thread(
+                    manager.list_documents, name, page, page_size
+                )
+                if listing is None:
+                    reply = create_error_message(
+                        "Index not loaded", ErrorCode.INDEX_NOT_READY
+                    )
+                else:
+                    reply = create_documents_message(name, page, **listing)
+
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=list_documents request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.GET_CHUNKS:
+            # Client looking at how one document was chunked
+            index = message.get("index")
+            path = message.get("path")
+            target = self._lookup_index(index)
+            if target is None:
+                reply = create_error_message(f"Unknown index: {index}")
+            elif not isinstance(path, str) or not path:
+                reply = create_error_message("Missing 'path' field")
+            else:
+                try:
+                    chunks = await asyncio.to_thread(target[2].document_chunks, path)
+                except KeyError:
+                    reply = create_error_message(f"Document not indexed: {path}")
+                else:
+                    if chunks is None:
+                        reply = create_error_message(
+                            "Index not loaded", ErrorCode.INDEX_NOT_READY
+                        )
+                    else:
+                        reply = create_chunks_message(path, chunks)
+
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=get_chunks request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.GET_LINKS:
+            # Client navigating the vault: what a document links to and what
+            # links to it
+            index = message.get("index")
+            path = message.get("path")
+            target = self._lookup_index(index)
+            if target is None:
+                reply = create_error_message(f"Unknown index: {index}")
+            elif not isinstance(path, str) or not path:
+                reply = create_error_message("Missing 'path' field")
+            else:
+                try:
+                    links = await asyncio.to_thread(target[2].document_links, path)
+                except KeyError:
+                    reply = create_error_message(f"Document not indexed: {path}")
+                else:
+                    if links is None:
+                        reply = create_error_message(
+                            "Index not loaded", ErrorCode.INDEX_NOT_READY
+                        )
+                    else:
+                        reply = create_links_message(**links)
+
+            await self._send(websocket, reply)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=get_links request_ms={request_ms:.2f}"
+            )
+
+        elif msg_type == MessageType.TAIL_LOGS:
+            # Client showing the server log, e.g. to find out why the index
+            # is not ready; with follow, new lines keep coming as log messages
+            lines = message.get("lines", DEFAULT_TAIL_LINES)
+            if not isinstance(lines, int) or isinstance(lines, bool) or lines < 0:
+                reply = create_error_message(
+                    "Field 'lines' must be a non-negative integer"
+                )
+            else:
+                reply = create_logs_message(self.log_tail.lines(lines))
+            await self._send(websocket, reply)
+            if reply["type"] == MessageType.LOGS:
+                if message.get("follow"):
+                    self._follow_logs(websocket)
+                else:
+                    self._unfollow_logs(websocket)
+            request_ms = (time.perf_counter() - request_start) * 1000
+            self.logger.info(
+                f"request_completed type=tail_logs request_ms={request_ms:.2f}"
+            )
+
         else:
-            await websocket.send(  # type: ignore[attr-defined]
-                json.dumps(create_error_message(f"Unknown message type: {msg_type}"))
+            await self._send(
+                websocket, create_error_message(f"Unknown message type: {msg_type}")
             )
             request_ms = (time.perf_counter() - request_start) * 1000
             self.logger.warning(
@@ -155,7 +832,29 @@ class MarkdownQAServer:
 
     def _reload_indexes(self, force: bool = False) -> None:
         """
-        Reload indexes (called by scheduler).
+        Reload the configured index and the named ones (called by scheduler).
+
+        Args:
+            force: If True, rebuild even if no changes detected.
+        """
+        self._reload_configured_index(force)
+        for name, named in list(self.named_indexes.items()):
+            if name in self._index_builds:
+                continue
+            try:
+                if force:
+                    named.index_manager._do_full_rebuild(name, named.settings.directories)
+                else:
+                    named.index_manager.incremental_update(name, named.settings.directories)
+                named.index_manager.verify_index(
+                    name, named.settings.directories, remove=True
+                )
+            except Exception as e:
+                self.logger.error(f"Error reloading index {name}: {e}", exc_info=True)
+
+    def _reload_configured_index(self, force: bool = False) -> None:
+        """
+        Reload the configured index.
 
         Uses incremental updates when possible to only process changed files.
         Falls back to full rebuild when incremental update is not possible.
@@ -183,6 +882,7 @@ class MarkdownQAServer:
                     self.config.index_name, self.config.directories
                 )
                 self.logger.info("Full index rebuild completed successfully")
+                self._notify_rebuilt()
                 return
 
             # Try incremental update
@@ -193,8 +893,26 @@ class MarkdownQAServer:
             # Check if we fell back to full rebuild
             if result.fallback_to_full_rebuild:
                 self.logger.info(f"Performed full rebuild (reason: {result.reason})")
+                self._notify_rebuilt()
                 return
 
+            # Drop chunks incremental updates missed, e.g. of files that
+            # vanished while the index was built
+            report = self.index_manager.verify_index(
+                self.config.index_name, self.config.directories, remove=True
+            )
+            orphans = []
+            if report and report["removed"]:
+                orphans = [orphan["path"] for orphan in report["orphans"]]
+                self.logger.warning(f"Removed orphaned chunks of {orphans}")
+
+            self._notify_documents_changed(
+                result.added_files + result.modified_files + result.deleted_files + orphans,
+                f"{len(result.added_files)} added, "
+                f"{len(result.modified_files)} modified, "
+                f"{len(result.deleted_files) + len(orphans)} removed",
+            )
+
             # Log incremental update results
             if not result.has_changes:
                 self.logger.debug("No changes detected, skipping reload")
@@ -217,6 +935,24 @@ class MarkdownQAServer:
             # Log error but don't crash
             self.logger.error(f"Error reloading indexes: {e}", exc_info=True)
 
+    def _new_reload_scheduler(self) -> ReloadScheduler:
+        """A reload scheduler with the configured interval and reload windows."""
+        return ReloadScheduler(
+            self._reload_indexes,
+            interval=self.config.reload_interval,
+            windows=self.config.reload_windows,
+            reload_on_ac_power=self.config.reload_on_ac_power,
+        )
+
+    def _notify_rebuilt(self) -> None:
+        """Tell every connected client the index was rebuilt, with its documents."""
+        listing = self.index_manager.list_documents(self.config.index_name, 0, sys.maxsize)
+        if listing:
+            self._notify_documents_changed(
+                [document["path"] for document in listing["documents"]],
+                "Index rebuilt",
+            )
+
     def _reload_config(self) -> None:
         """Reload configuration from file (called by config watcher)."""
         try:
@@ -236,17 +972,49 @@ class MarkdownQAServer:
             )
 
             if result.requires_restart:
+                settings = " and ".join(s for s in ("port", "encryption") if s in result.changed)
                 self.logger.warning(
-                    "Port change detected. Server restart required for port change to take effect."
+                    f"Changed {settings}. Server restart required for the change to take effect."
                 )
                 return
 
+            # Named indexes share every setting but the chunking
+            index_managers = [self.index_manager] + [
+                named.index_manager for named in self.named_indexes.values()
+            ]
+            query_handlers = [self.query_handler] + [
+                named.query_handler for named in self.named_indexes.values()
+            ]
+            if "max_file_size_mb" in result.changed:
+                for index_manager in index_managers:
+                    index_manager.max_file_size_mb = self.config.max_file_size_mb
+
+            if "index_drafts" in result.changed:
+                for index_manager in index_managers:
+                    index_manager.index_drafts = self.config.index_drafts
+
+            if "chunking" in result.changed:
+                self.index_manager.chunking = self.config.chunking
+
+            if "formats" in result.changed:
+                for index_manager in index_managers:
+                    index_manager.extensions = self.config.extensions
+
+            for query_handler in query_handlers:
+                if "docs_language" in result.changed:
+                    query_handler.docs_language = self.config.docs_language
+                if "rerank" in result.changed:
+                    query_handler.rerank = self.config.rerank
+            if "ann" in result.changed:
+                for index_manager in index_managers:
+                    index_manager.set_ann(self.config.ann)
+
             # Handle hot-reloadable changes
             if "directories" in result.changed or "index_name" in result.changed:
                 # If index_name changed, always do full rebuild
                 if "index_name" in result.changed:
                     self.logger.info("Index name changed, performing full rebuild...")
-                    self._reload_indexes(force=True)
+                    self._reload_configured_index(force=True)
                 elif "directories" in result.changed:
                     # Check if only directories were added (not removed)
                     # Normalize paths to absolute for comparison
@@ -263,7 +1031,7 @@ class MarkdownQAServer:
                             f"Directories removed: {removed_directories}. "
                             "Performing full rebuild..."
                         )
-                        self._reload_indexes(force=True)
+                        self._reload_configured_index(force=True)
                     elif added_directories:
                         # Only new directories added, can use incremental update
                         self.logger.info(
@@ -294,27 +1062,81 @@ class MarkdownQAServer:
                         self.logger.info(
                             "Directories reordered, performing full rebuild..."
                         )
-                        self._reload_indexes(force=True)
+                        self._reload_configured_index(force=True)
 
-            if "reload_interval" in result.changed:
-                # Restart reload scheduler with new interval
+            if {"reload_interval", "reload_windows", "reload_on_ac_power"} & set(
+                result.changed
+            ):
+                # Restart reload scheduler with new interval and windows
                 if self.reload_scheduler:
                     self.reload_scheduler.stop()
-                self.reload_scheduler = ReloadScheduler(
-                    self._reload_indexes, interval=self.config.reload_interval
-                )
+                self.reload_scheduler = self._new_reload_scheduler()
                 self.reload_scheduler.start()
                 self.logger.info(
                     f"Reload scheduler updated (new interval: {self.config.reload_interval}s)"
                 )
 
+            if "max_file_size_mb" in result.changed and not {
+                "index_name",
+                "api_config",
+            } & set(result.changed):
+                # Re-index so files newly over or under the cap are dropped or added
+                self.logger.info(
+                    f"Max file size changed to {self.config.max_file_size_mb} MB, "
+                    "performing full rebuild..."
+                )
+                self._reload_indexes(force=True)
+            elif "index_drafts" in result.changed and not {
+                "index_name",
+                "api_config",
+            } & set(result.changed):
+                # Re-index so drafts are dropped or added
+                self.logger.info(
+                    f"Draft indexing {'enabled' if self.config.index_drafts else 'disabled'}, "
+                    "performing full rebuild..."
+                )
+                self._reload_indexes(force=True)
+            elif "chunking" in result.changed and not {
+                "index_name",
+                "api_config",
+            } & set(result.changed):
+                # Re-chunk every file with the new parameters
+                self.logger.info("Chunking changed, performing full rebuild...")
+                self._reload_configured_index(force=True)
+            elif "formats" in result.changed and not {
+                "index_name",
+                "api_config",
+            } & set(result.changed):
+                # Index files of newly enabled formats, drop those of disabled ones
+                self.logger.info(
+                    f"Formats changed to {', '.join(self.config.formats) or 'markdown only'}, "
+                    "performing full rebuild..."
+                )
+                self._reload_indexes(force=True)
+
             if "api_config" in result.changed:
                 # Recreate index manager and query handler with new API config
                 self.logger.info("Updating API configuration...")
-                self.index_manager = IndexManager(api_config=self.config.api_config)
+                self.index_manager = IndexManager(
+                    cache_manager=self.cache_manager,
+                    api_config=self.config.api_config,
+                    max_file_size_mb=self.config.max_file_size_mb,
+                    index_drafts=self.config.index_drafts,
+                    chunking=self.config.chunking,
+                    extensions=self.config.extensions,
+                    ann=self.config.ann,
+                )
+                self.index_manager.on_progress = self._report_index_progress
                 self.query_handler = QueryHandler(
-                    self.index_manager, api_config=self.config.api_config
+                    self.index_manager,
+                    api_config=self.config.api_config,
+                    docs_language=self.config.docs_language,
+                    rerank=self.config.rerank,
                 )
+                self.named_indexes = {
+                    name: self._new_named_index(named.settings)
+                    for name, named in self.named_indexes.items()
+                }
                 # Reload index with new API config
                 self.logger.info("Reloading indexes with new API configuration...")
                 self._reload_indexes(force=True)
@@ -324,6 +1146,7 @@ class MarkdownQAServer:
 
     async def start(self) -> None:
         """Start the server."""
+        self._loop = asyncio.get_running_loop()
         if self.config.directories:
             # Load indexes at startup
             self.logger.info(f"Loading indexes for directories: {self.config.directories}")
@@ -348,10 +1171,12 @@ class MarkdownQAServer:
                 "Starting server without indexed content."
             )
 
+        for name, named in self.named_indexes.items():
+            self.logger.info(f"Loading index {name} for directories: {named.settings.directories}")
+            await asyncio.to_thread(self._build_named_index, named)
+
         # Start reload scheduler
-        self.reload_scheduler = ReloadScheduler(
-            self._reload_indexes, interval=self.config.reload_interval
-        )
+        self.reload_scheduler = self._new_reload_scheduler()
         self.reload_scheduler.start()
         self.logger.info(
             f"Reload scheduler started (interval: {self.config.reload_interval}s)"
@@ -459,6 +1284,7 @@ Config file format (YAML):
       - /path/to/docs1
       - /path/to/docs2
     reload_interval: 300
+    reload_windows: ["22:00-07:00"]   # optional; scheduled reloads only then
     index_name: "default"
         """,
     )
@@ -3,13 +3,19 @@
 import os
 from dataclasses import dataclass, field
 from pathlib import Path
-from typing import List, Optional
+from typing import List, Optional, Tuple
 
 import yaml
 
+from markdown_qa.chunker import ChunkingConfig
 from markdown_qa.config import APIConfig
-from markdown_qa.loader import count_markdown_files
+from markdown_qa.encryption import parse_encryption
+from markdown_qa.extractors import extensions_for
+from markdown_qa.loader import DEFAULT_MAX_FILE_SIZE_MB, count_markdown_files
 from markdown_qa.logger import get_server_logger
+from markdown_qa.reload_scheduler import ReloadWindow, parse_window
+from markdown_qa.rerank import RerankConfig
+from markdown_qa.vector_store import parse_ann
 
 try:
     import tomli  # type: ignore[import-not-found]
@@ -17,6 +23,41 @@ except ImportError:
     tomli = None
 
 
+def parse_reload_windows(value: object) -> List[ReloadWindow]:
+    """
+    Read server.reload_windows: a list of ``HH:MM-HH:MM`` windows, or one.
+
+    Raises:
+        ValueError: If a window is invalid.
+    """
+    if value is None:
+        return []
+    if isinstance(value, str):
+        value = [value]
+    if not isinstance(value, list):
+        raise ValueError("server.reload_windows must be a list of HH:MM-HH:MM windows")
+    return [parse_window(window) for window in value]
+
+
+def parse_formats(value: object) -> List[str]:
+    """
+    Read server.formats: the formats besides markdown to index, e.g.
+    ``[html, pdf]``, or a comma-separated string.
+
+    Raises:
+        ValueError: If a format is unknown.
+    """
+    if value is None:
+        return []
+    if isinstance(value, str):
+        value = [name.strip() for name in value.split(",") if name.strip()]
+    if not isinstance(value, list) or not all(isinstance(name, str) for name in value):
+        raise ValueError("server.formats must be a list of format names")
+    formats = [name.strip().lower() for name in value]
+    extensions_for(formats)
+    return formats
+
+
 @dataclass
 class ConfigReloadResult:
     """Result of a configuration reload operation."""
@@ -88,6 +129,27 @@ class ServerConfig:
         self.index_name = (
             index_name if index_name is not None else (config_data.get("index_name") or "default")
         )
+        self.max_file_size_mb = (
+            config_data.get("max_file_size_mb") or DEFAULT_MAX_FILE_SIZE_MB
+        )
+        # Questions in other languages are translated to this one (opt-in)
+        self.docs_language: Optional[str] = config_data.get("docs_language") or None
+        # Files whose front matter sets draft: true are skipped unless enabled
+        self.index_drafts = config_data.get("index_drafts", False)
+        # Chunk size, overlap and heading splits, per directory if set
+        self.chunking = ChunkingConfig.from_dict(config_data.get("chunking"))
+        # Scheduled reloads only run in these windows (any time if none),
+        # or also on AC power if enabled
+        self.reload_windows = parse_reload_windows(config_data.get("reload_windows"))
+        self.reload_on_ac_power = config_data.get("reload_on_ac_power", False)
+        # Rerank API for retrieved chunks (off unless configured)
+        self.rerank = RerankConfig.from_dict(config_data.get("rerank"))
+        # Nearest-neighbor search over the chunks (retrieval.ann)
+        self.ann = parse_ann(config_data.get("ann"))
+        # Formats besides markdown to extract and index (off unless listed)
+        self.formats = parse_formats(config_data.get("formats"))
+        # Encryption of the indexes and embeddings on disk (off unless configured)
+        self.encryption = parse_encryption(config_data.get("encryption"))
 
         if api_config is None:
             api_config = APIConfig(config_file=config_file)
@@ -150,6 +212,27 @@ class ServerConfig:
                         config_data["reload_interval"] = server_config["reload_interval"]
                     if "index_name" in server_config:
                         config_data["index_name"] = server_config["index_name"]
+                    if "max_file_size_mb" in server_config:
+                        config_data["max_file_size_mb"] = server_config["max_file_size_mb"]
+                    if "docs_language" in server_config:
+                        config_data["docs_language"] = server_config["docs_language"]
+                    if "index_drafts" in server_config:
+                        config_data["index_drafts"] = server_config["index_drafts"]
+                    if "reload_windows" in server_config:
+                        config_data["reload_windows"] = server_config["reload_windows"]
+                    if "reload_on_ac_power" in server_config:
+                        config_data["reload_on_ac_power"] = server_config["reload_on_ac_power"]
+                    if "formats" in server_config:
+                        config_data["formats"] = server_config["formats"]
+                    if "encryption" in server_config:
+                        config_data["encryption"] = server_config["encryption"]
+                retrieval_config = (config or {}).get("retrieval")
+                if isinstance(retrieval_config, dict) and "chunking" in retrieval_config:
+                    config_data["chunking"] = retrieval_config["chunking"]
+                if isinstance(retrieval_config, dict) and "rerank" in retrieval_config:
+                    config_data["rerank"] = retrieval_config["rerank"]
+                if isinstance(retrieval_config, dict) and "ann" in retrieval_config:
+                    config_data["ann"] = retrieval_config["ann"]
         except Exception:
             # If loading fails, return empty dict
             pass
@@ -178,6 +261,27 @@ class ServerConfig:
                         config_data["reload_interval"] = server_config["reload_interval"]
                     if "index_name" in server_config:
                         config_data["index_name"] = server_config["index_name"]
+                    if "max_file_size_mb" in server_config:
+                        config_data["max_file_size_mb"] = server_config["max_file_size_mb"]
+                    if "docs_language" in server_config:
+                        config_data["docs_language"] = server_config["docs_language"]
+                    if "index_drafts" in server_config:
+                        config_data["index_drafts"] = server_config["index_drafts"]
+                    if "reload_windows" in server_config:
+                        config_data["reload_windows"] = server_config["reload_windows"]
+                    if "reload_on_ac_power" in server_config:
+                        config_data["reload_on_ac_power"] = server_config["reload_on_ac_power"]
+                    if "formats" in server_config:
+                        config_data["formats"] = server_config["formats"]
+                    if "encryption" in server_config:
+                        config_data["encryption"] = server_config["encryption"]
+                retrieval_config = (config or {}).get("retrieval")
+                if isinstance(retrieval_config, dict) and "chunking" in retrieval_config:
+                    config_data["chunking"] = retrieval_config["chunking"]
+                if isinstance(retrieval_config, dict) and "rerank" in retrieval_config:
+                    config_data["rerank"] = retrieval_config["rerank"]
+                if isinstance(retrieval_config, dict) and "ann" in retrieval_config:
+                    config_data["ann"] = retrieval_config["ann"]
         except Exception:
             # If loading fails, return empty dict
             pass
@@ -218,7 +322,7 @@ class ServerConfig:
                 continue
 
             # Count markdown files in this directory
-            file_count = count_markdown_files(directory)
+            file_count = count_markdown_files(directory, self.extensions)
 
             if file_count > 1000:
                 logger.error(
@@ -249,10 +353,28 @@ class ServerConfig:
         if self.reload_interval < 1:
             raise ValueError(f"Invalid reload interval: {self.reload_interval}")
 
+        if not isinstance(self.max_file_size_mb, (int, float)) or self.max_file_size_mb <= 0:
+            raise ValueError(f"Invalid max file size: {self.max_file_size_mb}")
+
+        if self.docs_language is not None and not isinstance(self.docs_language, str):
+            raise ValueError(f"Invalid docs language: {self.docs_language}")
+
+        if not isinstance(self.index_drafts, bool):
+            raise ValueError(f"Invalid index drafts setting: {self.index_drafts}")
+
+        if not isinstance(self.reload_on_ac_power, bool):
+            raise ValueError(f"Invalid reload on AC power setting: {self.reload_on_ac_power}")
+
         # Validate API configuration
-        if not self.api_config.base_url or not self.api_config.api_key:
+        uses_base_url = getattr(self.api_config, "uses_base_url", lambda: True)
+        if uses_base_url() and (not self.api_config.base_url or not self.api_config.api_key):
             raise ValueError("API configuration is missing")
 
+    @property
+    def extensions(self) -> Tuple[str, ...]:
+        """File extensions to index: ``.md`` and those of server.formats."""
+        return extensions_for(self.formats)
+
     def get_config_file_path(self) -> Optional[Path]:
         """
         Get the path to the config file being used.
@@ -281,6 +403,16 @@ class ServerConfig:
             "reload_interval": self.reload_interval,
             "index_name": self.index_name,
             "port": self.port,
+            "max_file_size_mb": self.max_file_size_mb,
+            "docs_language": self.docs_language,
+            "index_drafts": self.index_drafts,
+            "chunking": self.chunking,
+            "reload_windows": self.reload_windows,
+            "reload_on_ac_power": self.reload_on_ac_power,
+            "rerank": self.rerank,
+            "formats": self.formats,
+            "ann": self.ann,
+            "encryption": self.encryption,
         }
 
         # Reload from config file
@@ -289,6 +421,15 @@ class ServerConfig:
             return ConfigReloadResult()
 
         config_data = self._load_config_file(config_file)
+        try:
+            new_chunking = ChunkingConfig.from_dict(config_data.get("chunking"))
+            new_reload_windows = parse_reload_windows(config_data.get("reload_windows"))
+            new_rerank = RerankConfig.from_dict(config_data.get("rerank"))
+            new_formats = parse_formats(config_data.get("formats"))
+            new_ann = parse_ann(config_data.get("ann"))
+            new_encryption = parse_encryption(config_data.get("encryption"))
+        except ValueError as e:
+            raise ValueError(f"Configuration reload failed validation: {e}")
 
         # Update values (respect preserve_cli_overrides)
         changed = []
@@ -312,6 +453,12 @@ class ServerConfig:
                 if should_update("port"):
                     self.port = new_port
 
+        # Encryption changes require restart (the index is written again then)
+        if new_encryption != self.encryption:
+            changed.append("encryption")
+            requires_restart = True
+            self.encryption = new_encryption
+
         # Directories can be hot-reloaded
         if "directories" in config_data:
             new_directories = config_data.get("directories") or self._get_directories_from_env()
@@ -336,13 +483,70 @@ class ServerConfig:
                 if should_update("index_name"):
                     self.index_name = new_index_name
 
+        # File size cap can be hot-reloaded (applies from the next rebuild)
+        if "max_file_size_mb" in config_data:
+            new_max_file_size_mb = config_data.get("max_file_size_mb") or DEFAULT_MAX_FILE_SIZE_MB
+            if new_max_file_size_mb != self.max_file_size_mb:
+                changed.append("max_file_size_mb")
+                self.max_file_size_mb = new_max_file_size_mb
+
+        # Question translation can be hot-reloaded (applies from the next query)
+        new_docs_language = config_data.get("docs_language") or None
+        if new_docs_language != self.docs_language:
+            changed.append("docs_language")
+            self.docs_language = new_docs_language
+
+        # Draft indexing can be hot-reloaded (applies from the next rebuild)
+        new_index_drafts = config_data.get("index_drafts", False)
+        if new_index_drafts != self.index_drafts:
+            changed.append("index_drafts")
+            self.index_drafts = new_index_drafts
+
+        # Chunking can be hot-reloaded (applies from the next rebuild)
+        if new_chunking != self.chunking:
+            changed.append("chunking")
+            self.chunking = new_chunking
+
+        # Reload windows can be hot-reloaded (the scheduler is restarted)
+        if new_reload_windows != self.reload_windows:
+            changed.append("reload_windows")
+            self.reload_windows = new_reload_windows
+        new_reload_on_ac_power = config_data.get("reload_on_ac_power", False)
+        if new_reload_on_ac_power != self.reload_on_ac_power:
+            changed.append("reload_on_ac_power")
+            self.reload_on_ac_power = new_reload_on_ac_power
+
+        # Reranking can be hot-reloaded (applies from the next query)
+        if new_rerank != self.rerank:
+            changed.append("rerank")
+            self.rerank = new_rerank
+
+        # Formats can be hot-reloaded (applies from the next rebuild)
+        if new_formats != self.formats:
+            changed.append("formats")
+            self.formats = new_formats
+
+        # Nearest-neighbor search can be hot-reloaded (the graph is built or dropped)
+        if new_ann != self.ann:
+            changed.append("ann")
+            self.ann = new_ann
+
         # Reload API config
         if config_file:
             try:
                 new_api_config = APIConfig(config_file=config_file)
-                if (
-                    new_api_config.base_url != self.api_config.base_url
-                    or new_api_config.api_key != self.api_config.api_key
+                if any(
+                    getattr(new_api_config, setting, None)
+                    != getattr(self.api_config, setting, None)
+                    for setting in (
+                        "base_url",
+                        "api_key",
+                        "embedding_model",
+                        "provider",
+                        "llm_base_url",
+                        "llm_api_key",
+                        *APIConfig.EMBEDDING_SETTINGS,
+                    )
                 ):
                     changed.append("api_config")
                     if should_update("api_config"):
@@ -361,6 +565,16 @@ class ServerConfig:
                 self.reload_interval = old_config["reload_interval"]
                 self.index_name = old_config["index_name"]
                 self.port = old_config["port"]
+                self.max_file_size_mb = old_config["max_file_size_mb"]
+                self.docs_language = old_config["docs_language"]
+                self.index_drafts = old_config["index_drafts"]
+                self.chunking = old_config["chunking"]
+                self.reload_windows = old_config["reload_windows"]
+                self.reload_on_ac_power = old_config["reload_on_ac_power"]
+                self.rerank = old_config["rerank"]
+                self.formats = old_config["formats"]
+                self.ann = old_config["ann"]
+                self.encryption = old_config["encryption"]
                 raise ValueError(f"Configuration reload failed validation: {e}")
 
         return ConfigReloadResult(changed=changed, requires_restart=requires_restart)
@@ -0,0 +1,152 @@
+"""Snapshots of an index: one archive to hand a pre-built index to teammates.
+
+A snapshot holds the index files (vector store, chunk texts, HNSW graph),
+the index's manifest entry, and the embedding of every chunk in the form
+of the embedding cache. Restored on another machine, documents at other
+paths or with other modification times are chunked again on the next
+reload, but their embeddings come from the cache instead of the API.
+Snapshots are never encrypted; server.encryption applies once restored.
+"""
+
+import io
+import json
+import pickle
+import re
+import sys
+import tarfile
+import time
+from pathlib import Path
+from typing import Any, Dict, Tuple
+
+# Format of snapshot.json; snapshots of other versions are refused
+SNAPSHOT_VERSION = 1
+
+# Archive formats by file name ending; zstd needs Python 3.14
+SNAPSHOT_SUFFIXES = {
+    ".tar.zst": "zst",
+    ".tzst": "zst",
+    ".tar.gz": "gz",
+    ".tgz": "gz",
+    ".tar": "",
+}
+
+# Members besides snapshot.json: the index files, the manifest entry and
+# embeddings/<cache key>.json
+INDEX_MEMBERS = ("index.faiss", "index.pkl", "index.hnsw")
+MANIFEST_MEMBER = "manifest.json"
+_EMBEDDING_MEMBER = re.compile(r"embeddings/[0-9a-f]{64}\.json")
+
+# Classes the chunk metadata of a snapshot may hold besides plain values,
+# e.g. front matter dates
+_METADATA_CLASSES = {
+    ("builtins", "set"),
+    ("builtins", "frozenset"),
+    ("datetime", "date"),
+    ("datetime", "datetime"),
+    ("datetime", "time"),
+    ("datetime", "timedelta"),
+    ("datetime", "timezone"),
+}
+
+
+class _MetadataUnpickler(pickle.Unpickler):
+    """Reads chunk metadata, refusing anything that could run code."""
+
+    def find_class(self, module: str, name: str) -> Any:
+        if (module, name) in _METADATA_CLASSES:
+            return super().find_class(module, name)
+        raise pickle.UnpicklingError(f"{module}.{name} is not allowed in a snapshot")
+
+
+def _compression(path: Path) -> str:
+    """The tarfile compression of a snapshot path, from its name."""
+    name = path.name.lower()
+    for suffix, compression in SNAPSHOT_SUFFIXES.items():
+        if name.endswith(suffix):
+            if compression == "zst" and sys.version_info < (3, 14):
+                raise ValueError(".tar.zst snapshots need Python 3.14; use .tar.gz")
+            return compression
+    raise ValueError(
+        f"Snapshot file name must end in {', '.join(SNAPSHOT_SUFFIXES)}: {path.name}"
+    )
+
+
+def write_snapshot(path: Path, info: Dict[str, Any], members: Dict[str, bytes]) -> int:
+    """
+    Write a snapshot archive.
+
+    Args:
+        path: Archive to write; its name picks the compression.
+        info: What snapshot.json describes: index name, embedding model,
+            chunks, directories.
+        members: Archive member names and contents.
+
+    Returns:
+        Size of the archive in bytes.
+
+    Raises:
+        ValueError: If the file name has no known archive ending.
+    """
+    compression = _compression(path)
+    info = {"version": SNAPSHOT_VERSION, "created_at": int(time.time()), **info}
+    path.parent.mkdir(parents=True, exist_ok=True)
+    with tarfile.open(path, f"w:{compression}" if compression else "w") as tar:  # type: ignore[call-overload]
+        for name, data in [("snapshot.json", json.dumps(info).encode("utf-8")), *members.items()]:
+            member = tarfile.TarInfo(name)
+            member.size = len(data)
+            member.mtime = info["created_at"]
+            tar.addfile(member, io.BytesIO(data))
+    return path.stat().st_size
+
+
+def read_snapshot(path: Path) -> Tuple[Dict[str, Any], Dict[str, bytes]]:
+    """
+    Read a snapshot archive. Only regular members with expected names are
+    read; nothing is extracted to disk.
+
+    Args:
+        path: Archive written by ``write_snapshot``.
+
+    Returns:
+        snapshot.json and the other members by name.
+
+    Raises:
+        FileNotFoundError: If there is no such file.
+        ValueError: If it is not a snapshot of this version.
+    """
+    compression = _compression(path)
+    if not path.is_file():
+        raise FileNotFoundError(f"Snapshot not found: {path}")
+    info: Dict[str, Any] = {}
+    members: Dict[str, bytes] = {}
+    try:
+        with tarfile.open(path, f"r:{compression}" if compression else "r") as tar:  # type: ignore[call-overload]
+            for member in tar:
+                if not member.isfile():
+                    continue
+                name = member.name
+                if name == "snapshot.json":
+                    info = json.loads(tar.extractfile(member).read())  # type: ignore[union-attr]
+                elif (
+                    name in INDEX_MEMBERS
+                    or name == MANIFEST_MEMBER
+                    or _EMBEDDING_MEMBER.fullmatch(name)
+                ):
+                    members[name] = tar.extractfile(member).read()  # type: ignore[union-attr]
+    except (tarfile.TarError, OSError, EOFError, json.JSONDecodeError) as e:
+        raise ValueError(f"Not a snapshot archive: {path.name}: {e}") from e
+
+    if info.get("version") != SNAPSHOT_VERSION:
+        raise ValueError(
+            f"Unsupported snapshot version {info.get('version')!r} (expected {SNAPSHOT_VERSION})"
+        )
+    missing = [name for name in ("index.faiss", "index.pkl") if name not in members]
+    if missing:
+        raise ValueError(f"Snapshot is missing {', '.join(missing)}")
+    # The chunk texts and metadata are a pickle, which is read again when
+    # the index is loaded; one from someone else's snapshot may only hold data
+    try:
+        _MetadataUnpickler(io.BytesIO(members["index.pkl"])).load()
+    except Exception as e:
+        raise ValueError(f"Snapshot index.pkl is not plain chunk data: {e}") from e
+    return info, members
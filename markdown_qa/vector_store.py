@@ -2,7 +2,7 @@
 
 import pickle
 from pathlib import Path
-from typing import Any, Dict, List, Optional, Tuple
+from typing import Any, Callable, Dict, List, Optional, Sequence, Tuple
 
 import faiss
 import numpy as np
@@ -11,9 +11,44 @@ from markdown_qa.cache import CacheManager
 from markdown_qa.chunker import MarkdownChunker
 from markdown_qa.config import APIConfig
 from markdown_qa.embeddings import EmbeddingGenerator
-from markdown_qa.loader import generate_chunk_id, load_markdown_files
+from markdown_qa.encryption import read_file, write_file
+from markdown_qa.extractors import MARKDOWN_EXTENSIONS
+from markdown_qa.links import LinkGraph
+from markdown_qa.loader import (
+    DEFAULT_MAX_FILE_SIZE_MB,
+    generate_chunk_id,
+    iter_markdown_files,
+)
 from markdown_qa.logger import get_server_logger
 
+# Nearest-neighbor search of retrieval.ann: exact (brute force over every
+# chunk) or an HNSW graph, approximate but much faster on large indexes
+ANN_KINDS = ("exact", "hnsw")
+
+# Chunks below which searching them all is about as fast as an HNSW graph,
+# so search stays exact
+ANN_MIN_CHUNKS = 10000
+
+# Neighbors of each node in the HNSW graph, and candidates kept while
+# building it and while searching it (more: better recall, but slower)
+HNSW_M = 32
+HNSW_EF_CONSTRUCTION = 80
+HNSW_EF_SEARCH = 64
+
+
+def parse_ann(value: object) -> str:
+    """
+    Read retrieval.ann: ``exact`` (the default) or ``hnsw``.
+
+    Raises:
+        ValueError: If the value is neither.
+    """
+    if value is None:
+        return "exact"
+    if not isinstance(value, str) or value.strip().lower() not in ANN_KINDS:
+        raise ValueError(f"retrieval.ann must be one of {', '.join(ANN_KINDS)}, not {value!r}")
+    return value.strip().lower()
+
 
 class VectorStore:
     """Manages vector store initialization and document indexing."""
@@ -24,6 +59,9 @@ class VectorStore:
         embedding_generator: Optional[EmbeddingGenerator] = None,
         chunker: Optional[MarkdownChunker] = None,
         api_config: Optional[APIConfig] = None,
+        max_file_size_mb: float = DEFAULT_MAX_FILE_SIZE_MB,
+        extensions: Sequence[str] = MARKDOWN_EXTENSIONS,
+        ann: str = "exact",
     ):
         """
         Initialize vector store.
@@ -33,19 +71,34 @@ class VectorStore:
             embedding_generator: Embedding generator instance. If None, creates default.
             chunker: Chunker instance. If None, creates default.
             api_config: API configuration. If None and embedding_generator is None, creates default.
+            max_file_size_mb: Markdown files larger than this are skipped when building.
+            extensions: File extensions to index when building (see
+                loader.find_markdown_files).
+            ann: ``hnsw`` to search an HNSW graph of the chunks once there
+                are ANN_MIN_CHUNKS of them, ``exact`` to search them all.
         """
         self.cache_manager = cache_manager or CacheManager()
         self.embedding_generator = embedding_generator or EmbeddingGenerator(
             api_config=api_config,
-            cache_dir=self.cache_manager.embedding_dir
+            cache_dir=self.cache_manager.embedding_dir,
+            cipher=self.cache_manager.cipher,
         )
         self.chunker = chunker or MarkdownChunker()
+        self.max_file_size_mb = max_file_size_mb
+        self.extensions = tuple(extensions)
 
         self.index: Optional[faiss.Index] = None  # type: ignore[possibly-missing-attribute]
         self.metadata: List[Dict[str, Any]] = []
         self.texts: List[str] = []
         self.chunk_ids: List[int] = []  # Track chunk IDs for incremental updates
         self._id_to_idx: Dict[int, int] = {}  # Map chunk_id -> index in metadata/texts
+        self._link_graph: Optional[LinkGraph] = None  # Built on first use
+        self.ann = ann
+        # HNSW graph over the chunks of ``index``, by position, and the chunk
+        # ID at each position. Built or loaded with the index and dropped
+        # when the chunks change; until it is built again, search is exact.
+        self._ann_index: Optional[faiss.Index] = None  # type: ignore[possibly-missing-attribute]
+        self._ann_ids: Optional[np.ndarray] = None
         self.logger = get_server_logger()
 
     def build_index(
@@ -53,6 +106,8 @@ class VectorStore:
         directories: List[str],
         index_name: str = "default",
         show_progress: bool = False,
+        issues: Optional[Dict[str, str]] = None,
+        progress: Optional[Callable[[Dict[str, Any]], None]] = None,
     ) -> "VectorStore":
         """
         Build a vector index from markdown files in directories.
@@ -61,6 +116,12 @@ class VectorStore:
             directories: List of directory paths containing markdown files.
             index_name: Name for the index.
             show_progress: Whether to show progress indicators.
+            issues: If given, filled with how each file that was not UTF-8
+                was decoded, by file path (see loader.iter_markdown_files).
+            progress: Called with an ``index_progress`` message body (see
+                messages.create_index_progress_message) after each file is
+                read, after each embedding batch, and once at the end with
+                ``finished`` and the files that could not be read.
 
         Returns:
             Self for method chaining.
@@ -69,20 +130,50 @@ class VectorStore:
 
         start_time = time.time()
 
-        # Load markdown files
+        # Load and chunk markdown files, one file segment at a time
         if show_progress:
-            self.logger.info(f"Loading markdown files from {len(directories)} directory(ies)...")
-        files = load_markdown_files(directories)
-
-        if not files:
-            raise ValueError("No markdown files found in specified directories")
-
-        # Chunk files
-        if show_progress:
-            self.logger.info(f"Chunking {len(files)} markdown file(s)...")
+            self.logger.info(
+                f"Loading and chunking markdown files from {len(directories)} directory(ies)..."
+            )
+        file_issues: Dict[str, str] = {} if issues is None else issues
+        failed: List[Dict[str, str]] = []
+        files_read = {"done": 0, "total": 0}
+
+        def file_read(done: int, total: int, path: Path, error: Optional[str]) -> None:
+            files_read.update(done=done, total=total)
+            if error is not None:
+                failed.append({"path": str(path), "issue": error})
+            if progress:
+                update: Dict[str, Any] = {"done": done, "total": total, "path": str(path)}
+                if error is not None:
+                    update["error"] = error
+                progress(update)
+
+        files = iter_markdown_files(
+            directories,
+            self.max_file_size_mb,
+            issues=file_issues,
+            progress=file_read,
+            extensions=self.extensions,
+        )
         chunks = self.chunker.chunk_files(files)
+        for file_path, issue in file_issues.items():
+            self.logger.warning(f"{file_path}: {issue}")
+
+        def finished() -> None:
+            if progress:
+                progress(
+                    {
+                        **files_read,
+                        "chunks": len(chunks),
+                        "embedded": len(chunks),
+                        "finished": True,
+                        "failed": failed,
+                    }
+                )
 
         if not chunks:
+            finished()
             raise ValueError("No chunks created from markdown files")
 
         # Generate chunk IDs based on file path and chunk index
@@ -100,8 +191,13 @@ class VectorStore:
         if show_progress:
             self.logger.info(f"Generating embeddings for {len(chunks)} chunk(s)...")
         texts = [chunk["text"] for chunk in chunks]
+
+        def embedded(done: int, total: int) -> None:
+            if progress:
+                progress({**files_read, "chunks": total, "embedded": done})
+
         embeddings = self.embedding_generator.generate_embeddings(
-            texts, show_progress=show_progress
+            texts, show_progress=show_progress, on_progress=embedded
         )
 
         # Convert to numpy arrays
@@ -122,6 +218,7 @@ class VectorStore:
 
         # Save to disk
         self.save_index(index_name)
+        finished()
 
         elapsed = time.time() - start_time
         if show_progress and elapsed > 2.0:
@@ -130,8 +227,45 @@ class VectorStore:
         return self
 
     def _rebuild_id_map(self) -> None:
-        """Rebuild the chunk_id -> index mapping."""
+        """Rebuild the chunk_id -> index mapping after the chunks changed."""
         self._id_to_idx = {cid: idx for idx, cid in enumerate(self.chunk_ids)}
+        self._link_graph = None
+        self._ann_index = None
+        self._ann_ids = None
+
+    def _uses_ann(self) -> bool:
+        """Whether searches go through the HNSW graph rather than every chunk."""
+        return (
+            self.ann == "hnsw"
+            and self.index is not None
+            and self.index.ntotal >= ANN_MIN_CHUNKS  # type: ignore[possibly-missing-attribute]
+        )
+
+    def _position_ids(self) -> np.ndarray:
+        """Chunk IDs of the vectors in ``index``, in the order they are stored."""
+        return faiss.vector_to_array(self.index.id_map)  # type: ignore[possibly-missing-attribute]
+
+    def _build_ann(self) -> None:
+        """Build the HNSW graph from the vectors of ``index``."""
+        flat = faiss.downcast_index(self.index.index)  # type: ignore[possibly-missing-attribute]
+        vectors = flat.reconstruct_n(0, flat.ntotal)
+        graph = faiss.IndexHNSWFlat(flat.d, HNSW_M)  # type: ignore[possibly-missing-attribute]
+        graph.hnsw.efConstruction = HNSW_EF_CONSTRUCTION
+        graph.add(vectors)
+        self._ann_index = graph
+        self._ann_ids = self._position_ids()
+
+    def link_graph(self) -> LinkGraph:
+        """
+        Get the links between the indexed documents, built from the chunk
+        metadata on first use after the chunks changed.
+
+        Returns:
+            The link graph.
+        """
+        if self._link_graph is None:
+            self._link_graph = LinkGraph.from_metadata(self.metadata)
+        return self._link_graph
 
     def load_index(self, index_name: str) -> "VectorStore":
         """
@@ -154,25 +288,43 @@ class VectorStore:
             )
 
         # Load FAISS index
-        self.index = faiss.read_index(str(faiss_path))  # type: ignore[possibly-missing-attribute]
+        self.index = self._read_faiss(faiss_path)
 
         # Load metadata, texts, and chunk_ids
-        with open(metadata_path, "rb") as f:
-            data = pickle.load(f)
-            if isinstance(data, dict):
-                # New format with texts and chunk_ids
-                self.metadata = data.get("metadata", [])
-                self.texts = data.get("texts", [])
-                self.chunk_ids = data.get("chunk_ids", [])
-            else:
-                # Old format (backward compatibility)
-                self.metadata = data
-                self.texts = []
-                self.chunk_ids = []
+        data = pickle.loads(read_file(metadata_path, self.cache_manager.cipher))
+        if isinstance(data, dict):
+            # New format with texts and chunk_ids
+            self.metadata = data.get("metadata", [])
+            self.texts = data.get("texts", [])
+            self.chunk_ids = data.get("chunk_ids", [])
+        else:
+            # Old format (backward compatibility)
+            self.metadata = data
+            self.texts = []
+            self.chunk_ids = []
 
         self._rebuild_id_map()
+        ann_path = self.cache_manager.get_ann_path(index_name)
+        if self._uses_ann() and ann_path.exists():
+            graph = self._read_faiss(ann_path)
+            # A graph saved with other chunks is built again below
+            if graph.ntotal == self.index.ntotal:  # type: ignore[possibly-missing-attribute]
+                self._ann_index = graph
+                self._ann_ids = self._position_ids()
+        self.prepare_ann()
         return self
 
+    def prepare_ann(self) -> None:
+        """
+        Build the HNSW graph if retrieval.ann asks for one and it isn't
+        built yet, e.g. after ``ann`` was changed; drop it if not.
+        """
+        if not self._uses_ann():
+            self._ann_index = None
+            self._ann_ids = None
+        elif self._ann_index is None:
+            self._build_ann()
+
     def save_index(self, index_name: str) -> None:
         """
         Save the current index to disk.
@@ -186,15 +338,36 @@ class VectorStore:
         faiss_path, metadata_path = self.cache_manager.get_index_path(index_name)
 
         # Save FAISS index
-        faiss.write_index(self.index, str(faiss_path))  # type: ignore[possibly-missing-attribute]
+        self._write_faiss(self.index, faiss_path)
 
         # Save metadata, texts, and chunk_ids
-        with open(metadata_path, "wb") as f:
-            pickle.dump({
+        write_file(
+            metadata_path,
+            pickle.dumps({
                 "metadata": self.metadata,
                 "texts": self.texts,
                 "chunk_ids": self.chunk_ids,
-            }, f)
+            }),
+            self.cache_manager.cipher,
+        )
+
+        # Save the HNSW graph, so a restart doesn't have to build it again
+        ann_path = self.cache_manager.get_ann_path(index_name)
+        self.prepare_ann()
+        if self._ann_index is not None:
+            self._write_faiss(self._ann_index, ann_path)
+        else:
+            ann_path.unlink(missing_ok=True)
+
+    def _read_faiss(self, path: Path) -> "faiss.Index":  # type: ignore[possibly-missing-attribute]
+        """Read a FAISS index file, decrypting it under server.encryption."""
+        data = read_file(path, self.cache_manager.cipher)
+        return faiss.deserialize_index(np.frombuffer(data, dtype=np.uint8))  # type: ignore[possibly-missing-attribute]
+
+    def _write_faiss(self, index: "faiss.Index", path: Path) -> None:  # type: ignore[possibly-missing-attribute]
+        """Write a FAISS index file, encrypting it under server.encryption."""
+        data = faiss.serialize_index(index).tobytes()  # type: ignore[possibly-missing-attribute]
+        write_file(path, data, self.cache_manager.cipher)
 
     def search(
         self, query_embedding: List[float], k: int = 5
@@ -209,34 +382,83 @@ class VectorStore:
         Returns:
             List of tuples containing (text, metadata, distance) for each result.
         """
+        results = []
+        for chunk_id, distance in self.search_ids(query_embedding, k):
+            chunk = self.get_chunk(chunk_id)
+            if chunk is not None:
+                results.append((chunk[0], chunk[1], distance))
+        return results
+
+    def search_ids(
+        self, query_embedding: List[float], k: int = 5
+    ) -> List[Tuple[int, float]]:
+        """
+        Search for similar chunks, without looking up their text.
+
+        Args:
+            query_embedding: Query embedding vector.
+            k: Number of results to return.
+
+        Returns:
+            List of (chunk_id, distance) tuples, nearest first.
+        """
         if self.index is None:
             raise ValueError("Index not loaded or built")
 
         query_array = np.array([query_embedding], dtype=np.float32)
-        distances, ids = self.index.search(query_array, k)  # type: ignore[missing-argument]
+        graph, graph_ids = self._ann_index, self._ann_ids
+        if graph is None or graph_ids is None or not self._uses_ann():
+            # Exact: every chunk is compared
+            distances, ids = self.index.search(query_array, k)  # type: ignore[missing-argument]
+        else:
+            graph.hnsw.efSearch = max(HNSW_EF_SEARCH, k)
+            distances, positions = graph.search(query_array, k)
+            ids = np.where(positions >= 0, graph_ids[positions], -1)
+
+        # -1 means no result found
+        return [
+            (int(chunk_id), float(distances[0][i]))
+            for i, chunk_id in enumerate(ids[0])
+            if chunk_id != -1
+        ]
+
+    def get_chunk(self, chunk_id: int) -> Optional[Tuple[str, Dict[str, Any]]]:
+        """
+        Look up a chunk's text and metadata by its ID.
 
-        results = []
-        for i, chunk_id in enumerate(ids[0]):
-            if chunk_id == -1:
-                # No result found
-                continue
-
-            # For IndexIDMap2, search returns chunk IDs, not indices
-            # Look up the index in our metadata arrays
-            if chunk_id in self._id_to_idx:
-                idx = self._id_to_idx[chunk_id]
-                text = self.texts[idx] if idx < len(self.texts) else ""
-                results.append(
-                    (text, self.metadata[idx], float(distances[0][i]))
-                )
-            elif chunk_id < len(self.metadata):
-                # Fallback for old indexes without ID mapping
-                text = self.texts[chunk_id] if chunk_id < len(self.texts) else ""
-                results.append(
-                    (text, self.metadata[chunk_id], float(distances[0][i]))
-                )
+        Args:
+            chunk_id: ID of the chunk, as returned by search_ids.
 
-        return results
+        Returns:
+            Tuple of (text, metadata), or None if the index has no such chunk.
+        """
+        # For IndexIDMap2, search returns chunk IDs, not indices
+        # Look up the index in our metadata arrays
+        if chunk_id in self._id_to_idx:
+            idx = self._id_to_idx[chunk_id]
+        elif 0 <= chunk_id < len(self.metadata):
+            # Fallback for old indexes without ID mapping
+            idx = chunk_id
+        else:
+            return None
+        text = self.texts[idx] if idx < len(self.texts) else ""
+        return text, self.metadata[idx]
+
+    def get_embedding(self, chunk_id: int) -> List[float]:
+        """
+        The embedding stored for a chunk.
+
+        Args:
+            chunk_id: ID of the chunk.
+
+        Returns:
+            The embedding vector.
+        """
+        if self.index is None:
+            raise ValueError("No index loaded")
+        embedding = np.zeros(self.get_embedding_dim(), dtype=np.float32)
+        self.index.reconstruct(chunk_id, embedding)  # type: ignore[possibly-missing-attribute]
+        return embedding.tolist()
 
     def is_valid(self) -> bool:
         """Check if the index is valid and ready to use."""
@@ -369,6 +591,7 @@ class VectorStore:
             cache_manager=self.cache_manager,
             embedding_generator=self.embedding_generator,
             chunker=self.chunker,
+            ann=self.ann,
         )
 
         if self.index is not None:
@@ -0,0 +1,83 @@
+"""Frame encodings a client can negotiate in the capabilities handshake.
+
+Messages are JSON text frames unless the client offers a binary encoding the
+server can write; MessagePack and CBOR need the optional ``msgpack`` and
+``cbor2`` packages.
+"""
+
+import json
+from typing import Any, Dict, List, Optional, Union
+
+try:
+    import msgpack  # type: ignore[import-not-found]
+except ImportError:  # pragma: no cover - optional dependency
+    msgpack = None
+
+try:
+    import cbor2  # type: ignore[import-not-found]
+except ImportError:  # pragma: no cover - optional dependency
+    cbor2 = None
+
+JSON = "json"
+MSGPACK = "msgpack"
+CBOR = "cbor"
+
+
+def available_encodings() -> List[str]:
+    """Return the binary encodings whose packages are installed."""
+    encodings = []
+    if msgpack is not None:
+        encodings.append(MSGPACK)
+    if cbor2 is not None:
+        encodings.append(CBOR)
+    return encodings
+
+
+def pick_encoding(offered: Any) -> Optional[str]:
+    """
+    Pick the first encoding the client offered that the server can write.
+
+    Args:
+        offered: The ``encodings`` field of a capabilities request.
+
+    Returns:
+        The picked encoding, or None to keep JSON text frames.
+    """
+    if not isinstance(offered, list):
+        return None
+    available = available_encodings()
+    for encoding in offered:
+        if encoding in available:
+            return encoding  # type: ignore[no-any-return]
+    return None
+
+
+def encode(message: Dict[str, Any], encoding: str = JSON) -> Union[str, bytes]:
+    """Encode a message as a text frame (JSON) or a binary frame."""
+    if encoding == MSGPACK:
+        return msgpack.packb(message)  # type: ignore[no-any-return,union-attr]
+    if encoding == CBOR:
+        return cbor2.dumps(message)  # type: ignore[no-any-return,union-attr]
+    return json.dumps(message)
+
+
+def decode(frame: Union[str, bytes], encoding: str = JSON) -> Any:
+    """
+    Decode a frame sent by the client.
+
+    Text frames are always JSON, so a client can keep sending text until it
+    has read the capabilities reply.
+
+    Raises:
+        ValueError: If the frame is not valid in its encoding.
+    """
+    if isinstance(frame, str) or encoding == JSON:
+        return json.loads(frame)
+    try:
+        if encoding == MSGPACK:
+            return msgpack.unpackb(frame)  # type: ignore[union-attr]
+        return cbor2.loads(frame)  # type: ignore[union-attr]
+    except ValueError:
+        raise
+    except Exception as e:
+        raise ValueError(f"Invalid {encoding} frame: {e}") from e
@@ -0,0 +1,101 @@
+"""Tests for approximate nearest-neighbor search (retrieval.ann)."""
+
+from unittest.mock import MagicMock, patch
+
+import faiss
+import numpy as np
+import pytest
+
+from markdown_qa import vector_store as vector_store_module
+from markdown_qa.cache import CacheManager
+from markdown_qa.vector_store import VectorStore, parse_ann
+
+
+def _store(tmp_path, vectors, ann):
+    """A vector store holding one chunk per vector, with chunk IDs 1000, 1001, ..."""
+    generator = MagicMock()
+    generator.generate_embeddings.return_value = vectors.tolist()
+    store = VectorStore(
+        cache_manager=CacheManager(tmp_path / "cache"),
+        embedding_generator=generator,
+        ann=ann,
+    )
+    store.index = faiss.IndexIDMap2(faiss.IndexFlatL2(vectors.shape[1]))
+    chunks = [
+        {"text": f"chunk {i}", "metadata": {"file_path": f"/docs/{i}.md"}}
+        for i in range(len(vectors))
+    ]
+    store.add_chunks_with_ids(chunks, [1000 + i for i in range(len(vectors))])
+    return store
+
+
+def _vectors(count, dim=32, seed=7):
+    return np.random.default_rng(seed).random((count, dim), dtype=np.float32)
+
+
+def test_parse_ann():
+    """Test reading retrieval.ann."""
+    assert parse_ann(None) == "exact"
+    assert parse_ann("HNSW") == "hnsw"
+    with pytest.raises(ValueError, match="retrieval.ann"):
+        parse_ann("ivf")
+
+
+def test_hnsw_search_finds_nearly_all_exact_neighbors(tmp_path):
+    """Test the recall of HNSW search against exact search."""
+    vectors = _vectors(2000)
+    queries = _vectors(50, seed=11)
+    with patch.object(vector_store_module, "ANN_MIN_CHUNKS", 100):
+        exact = _store(tmp_path / "exact", vectors, "exact")
+        approximate = _store(tmp_path / "hnsw", vectors, "hnsw")
+        approximate.prepare_ann()
+        assert approximate._ann_index is not None
+
+        found = 0
+        for query in queries:
+            expected = {chunk_id for chunk_id, _ in exact.search_ids(query.tolist(), k=10)}
+            got = [chunk_id for chunk_id, _ in approximate.search_ids(query.tolist(), k=10)]
+            assert len(got) == 10
+            found += len(expected & set(got))
+
+    assert found / (10 * len(queries)) >= 0.95
+
+
+def test_small_or_changed_indexes_are_searched_exactly(tmp_path):
+    """Test the fallback to exact search."""
+    vectors = _vectors(50)
+    store = _store(tmp_path, vectors, "hnsw")
+    # Below ANN_MIN_CHUNKS no graph is built
+    store.prepare_ann()
+    assert store._ann_index is None
+    assert store.search_ids(vectors[3].tolist(), k=1)[0][0] == 1003
+
+    with patch.object(vector_store_module, "ANN_MIN_CHUNKS", 10):
+        store.prepare_ann()
+        assert store._ann_index is not None
+        # Changed chunks drop the graph until it is built again
+        store.remove_chunks([1003])
+        assert store._ann_index is None
+        assert store.search_ids(vectors[4].tolist(), k=1)[0][0] == 1004
+        assert 1003 not in {chunk_id for chunk_id, _ in store.search_ids(vectors[3].tolist(), k=5)}
+
+
+def test_hnsw_graph_is_saved_and_loaded_with_the_index(tmp_path):
+    """Test that the graph is persisted, and removed once search is exact again."""
+    vectors = _vectors(200)
+    with patch.object(vector_store_module, "ANN_MIN_CHUNKS", 100):
+        store = _store(tmp_path, vectors, "hnsw")
+        cache_manager = store.cache_manager
+        store.save_index("docs")
+        assert cache_manager.get_ann_path("docs").exists()
+
+        loaded = VectorStore(
+            cache_manager=cache_manager, embedding_generator=MagicMock(), ann="hnsw"
+        ).load_index("docs")
+        assert loaded._ann_index is not None
+        assert loaded._ann_index.ntotal == 200
+        assert loaded.search_ids(vectors[42].tolist(), k=1)[0][0] == 1042
+
+        loaded.ann = "exact"
+        loaded.save_index("docs")
+        assert not cache_manager.get_ann_path("docs").exists()
@@ -81,6 +81,92 @@ api:
             del os.environ["MARKDOWN_QA_API_BASE_URL"]
             del os.environ["MARKDOWN_QA_API_KEY"]
 
+    def test_read_llm_provider(self):
+        """Test reading the answer provider, and that an unknown one is an error."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            config_path = Path(tmpdir) / "config.yaml"
+            config_path.write_text(
+                """
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "embedding-key"
+  provider: "anthropic"
+  llm_api_key: "anthropic-key"
+  llm_model: "claude-sonnet-4-5"
+"""
+            )
+            config = APIConfig(config_file=config_path)
+            assert config.provider == "anthropic"
+            assert config.llm_api_key == "anthropic-key"
+            assert config.llm_base_url is None
+
+            config_path.write_text(
+                """
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "embedding-key"
+  provider: "llamafile"
+"""
+            )
+            with pytest.raises(ValueError, match="Unknown api.provider"):
+                APIConfig(config_file=config_path)
+
+    def test_local_embeddings_need_no_api(self, monkeypatch):
+        """Test that base_url and api_key are optional when nothing is sent there."""
+        monkeypatch.delenv("MARKDOWN_QA_API_BASE_URL", raising=False)
+        monkeypatch.delenv("MARKDOWN_QA_API_KEY", raising=False)
+        with tempfile.TemporaryDirectory() as tmpdir:
+            config_path = Path(tmpdir) / "config.yaml"
+            config_path.write_text(
+                """
+api:
+  embedding_model: "local:BAAI/bge-small-en-v1.5"
+  provider: "ollama"
+  llm_model: "llama3.1"
+"""
+            )
+            config = APIConfig(config_file=config_path)
+            assert config.embedding_model == "local:BAAI/bge-small-en-v1.5"
+            assert not config.uses_base_url()
+
+            # Answers from an OpenAI-compatible API still need it
+            config_path.write_text(
+                """
+api:
+  embedding_model: "local:BAAI/bge-small-en-v1.5"
+"""
+            )
+            with pytest.raises(ValueError, match="API configuration is missing"):
+                APIConfig(config_file=config_path)
+
+    def test_read_embedding_settings(self, monkeypatch):
+        """Test reading the embedding request settings, from the file or the environment."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            config_path = Path(tmpdir) / "config.yaml"
+            config_path.write_text(
+                """
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+  embedding_batch_size: 25
+  embedding_concurrency: 4
+  max_concurrent_requests: 8
+"""
+            )
+            monkeypatch.setenv("MARKDOWN_QA_EMBEDDING_MAX_BACKOFF", "2.5")
+            monkeypatch.setenv("MARKDOWN_QA_REQUESTS_PER_MINUTE", "500")
+            config = APIConfig(config_file=config_path)
+            assert config.embedding_batch_size == 25
+            assert config.embedding_concurrency == 4
+            assert config.embedding_attempts is None
+            assert config.embedding_max_backoff == 2.5
+            assert config.max_concurrent_requests == 8
+            assert config.requests_per_minute == 500
+
+            monkeypatch.setenv("MARKDOWN_QA_EMBEDDING_ATTEMPTS", "0")
+            with pytest.raises(ValueError, match="api.embedding_attempts must be an integer"):
+                APIConfig(config_file=config_path)
+
     def test_missing_config_raises_error(self, monkeypatch):
         """Test that missing API configuration raises an error."""
         # Use a non-existent path to avoid loading user's default config
@@ -0,0 +1,199 @@
+"""Tests for embedding generation request settings."""
+
+import tempfile
+import threading
+import time
+from pathlib import Path
+from types import SimpleNamespace
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.embeddings import DEFAULT_BATCH_SIZE, EmbeddingGenerator
+
+
+def _api_config(**settings):
+    """An API config with the given embedding request settings."""
+    config = SimpleNamespace(
+        base_url="https://api.example.com/v1",
+        api_key="test-key",
+        embedding_model=None,
+        embedding_batch_size=None,
+        embedding_concurrency=None,
+        embedding_attempts=None,
+        embedding_max_backoff=None,
+        max_concurrent_requests=None,
+        requests_per_minute=None,
+    )
+    for name, value in settings.items():
+        setattr(config, name, value)
+    return config
+
+
+class _FakeEmbeddings:
+    """Embeddings API answering each text with [its length], recording the batch sizes."""
+
+    def __init__(self, failures=0, delay=0.0):
+        self.batch_sizes = []
+        self.failures = failures
+        self.delay = delay
+        self.in_flight = 0
+        self.most_in_flight = 0
+        self._lock = threading.Lock()
+
+    def create(self, model, input):
+        with self._lock:
+            if self.failures:
+                self.failures -= 1
+                raise RuntimeError("rate limited")
+            self.batch_sizes.append(len(input))
+            self.in_flight += 1
+            self.most_in_flight = max(self.most_in_flight, self.in_flight)
+        time.sleep(self.delay)
+        with self._lock:
+            self.in_flight -= 1
+        # Answer out of order, as the API may; the index field gives the position
+        data = [
+            SimpleNamespace(index=i, embedding=[float(len(text))]) for i, text in enumerate(input)
+        ]
+        return SimpleNamespace(data=list(reversed(data)))
+
+
+def _generator(embeddings, tmpdir, **settings):
+    with patch("markdown_qa.embeddings.OpenAI") as mock_openai:
+        mock_openai.return_value = MagicMock(embeddings=embeddings)
+        return EmbeddingGenerator(api_config=_api_config(**settings), cache_dir=Path(tmpdir))
+
+
+class TestEmbeddingGenerator:
+    """Test the configurable batch size, concurrency and retries."""
+
+    def test_defaults_apply_when_unset(self):
+        """Test that unset settings fall back to the defaults."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            generator = _generator(_FakeEmbeddings(), tmpdir)
+            assert generator.batch_size == DEFAULT_BATCH_SIZE
+            assert generator.concurrency == 1
+
+    def test_batches_are_pipelined_and_kept_in_order(self):
+        """Test that texts are sent in batches of the configured size, several at once."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            embeddings = _FakeEmbeddings()
+            generator = _generator(
+                embeddings, tmpdir, embedding_batch_size=2, embedding_concurrency=2
+            )
+            texts = ["a", "bb", "ccc", "dddd", "eeeee"]
+            progress = []
+
+            result = generator.generate_embeddings(
+                texts, on_progress=lambda done, total: progress.append((done, total))
+            )
+
+            assert result == [[1.0], [2.0], [3.0], [4.0], [5.0]]
+            assert sorted(embeddings.batch_sizes) == [1, 2, 2]
+            assert progress == [(0, 5), (2, 5), (4, 5), (5, 5)]
+
+    def test_failed_requests_are_retried(self):
+        """Test that a failed request is retried up to the configured attempts."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            embeddings = _FakeEmbeddings(failures=1)
+            generator = _generator(
+                embeddings, tmpdir, embedding_attempts=2, embedding_max_backoff=0
+            )
+            assert generator.generate_embeddings(["a", "bb"]) == [[1.0], [2.0]]
+
+            embeddings = _FakeEmbeddings(failures=2)
+            generator = _generator(
+                embeddings, tmpdir, embedding_attempts=2, embedding_max_backoff=0
+            )
+            with pytest.raises(Exception):
+                generator.generate_embeddings(["ccc"])
+
+    def test_requests_stay_within_max_concurrent_requests(self):
+        """Test that api.max_concurrent_requests caps the batches in flight."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            embeddings = _FakeEmbeddings(delay=0.05)
+            generator = _generator(
+                embeddings,
+                tmpdir,
+                base_url="https://capped.example.com/v1",
+                embedding_batch_size=1,
+                embedding_concurrency=4,
+                max_concurrent_requests=2,
+            )
+            assert generator.concurrency == 2
+
+            generator.generate_embeddings(["a", "bb", "ccc", "dddd", "eeeee", "ffffff"])
+
+            assert embeddings.most_in_flight == 2
+
+    def test_rate_limited_requests_back_off_as_asked(self):
+        """Test that a 429 waits the longest backoff, or its Retry-After if longer."""
+
+        class RateLimited(Exception):
+            status_code = 429
+
+            def __init__(self, retry_after):
+                super().__init__("rate limited")
+                headers = {} if retry_after is None else {"retry-after": retry_after}
+                self.response = SimpleNamespace(headers=headers)
+
+        def state(attempt, error):
+            outcome = SimpleNamespace(exception=lambda: error)
+            return SimpleNamespace(attempt_number=attempt, outcome=outcome)
+
+        def wrapped(error):
+            try:
+                raise Exception("Failed to generate batch embeddings") from error
+            except Exception as e:
+                return e
+
+        with tempfile.TemporaryDirectory() as tmpdir:
+            generator = _generator(_FakeEmbeddings(), tmpdir, embedding_max_backoff=10)
+            assert generator._backoff(state(1, RuntimeError("boom"))) == 2.0
+            assert generator._backoff(state(4, RuntimeError("boom"))) == 8.0
+            assert generator._backoff(state(1, wrapped(RateLimited(None)))) == 10.0
+            assert generator._backoff(state(1, wrapped(RateLimited("30")))) == 30.0
+            assert generator._backoff(state(1, wrapped(RateLimited("3600")))) == 60.0
+
+    def test_local_models_need_no_api(self):
+        """Test that a local:<model> embeds on this machine, cached apart from the API's."""
+
+        class FakeModel:
+            def __init__(self):
+                self.calls = []
+
+            def encode(self, texts, normalize_embeddings):
+                self.calls.append(list(texts))
+                return [[float(len(text)), 1.0] for text in texts]
+
+        model = FakeModel()
+        with tempfile.TemporaryDirectory() as tmpdir, patch(
+            "markdown_qa.local_embeddings.find_spec", return_value=object()
+        ), patch("markdown_qa.local_embeddings._load_model", return_value=model) as load:
+            api = _generator(_FakeEmbeddings(), tmpdir)
+            with patch("markdown_qa.embeddings.OpenAI") as mock_openai:
+                local = EmbeddingGenerator(
+                    api_config=_api_config(
+                        base_url=None, api_key=None, embedding_model="local:bge-small"
+                    ),
+                    cache_dir=Path(tmpdir),
+                )
+                mock_openai.assert_not_called()
+
+            assert local.generate_embeddings(["a", "bb"]) == [[1.0, 1.0], [2.0, 1.0]]
+            assert local.generate_embedding("bb") == [2.0, 1.0]
+            load.assert_called_once_with("bge-small")
+            assert model.calls == [["a", "bb"]]
+            assert not api.is_cached("a")
+
+    def test_local_models_need_sentence_transformers(self):
+        """Test that a missing sentence-transformers is reported up front."""
+        with tempfile.TemporaryDirectory() as tmpdir, patch(
+            "markdown_qa.local_embeddings.find_spec", return_value=None
+        ):
+            with pytest.raises(ImportError, match="sentence-transformers"):
+                EmbeddingGenerator(
+                    api_config=_api_config(embedding_model="local:bge-small"),
+                    cache_dir=Path(tmpdir),
+                )
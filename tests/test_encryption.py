@@ -0,0 +1,144 @@
+"""Tests for encryption of the index at rest (server.encryption)."""
+
+import sys
+from types import SimpleNamespace
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+pytest.importorskip("cryptography")
+
+import faiss  # noqa: E402
+import numpy as np  # noqa: E402
+
+from markdown_qa import encryption  # noqa: E402
+from markdown_qa.cache import CacheManager  # noqa: E402
+from markdown_qa.embeddings import EmbeddingGenerator  # noqa: E402
+from markdown_qa.encryption import MAGIC, PASSPHRASE_ENV, parse_encryption  # noqa: E402
+from markdown_qa.vector_store import VectorStore  # noqa: E402
+
+
+@pytest.fixture(autouse=True)
+def fast_key_derivation():
+    """Derive passphrase keys quickly; the iterations don't change the outcome."""
+    with patch.object(encryption, "PBKDF2_ITERATIONS", 1000):
+        yield
+
+
+def _save_store(cache_manager):
+    """Save an index of two chunks with cache_manager, returning its vectors."""
+    vectors = np.random.default_rng(3).random((2, 8), dtype=np.float32)
+    store = VectorStore(cache_manager=cache_manager, embedding_generator=MagicMock())
+    store.embedding_generator.generate_embeddings.return_value = vectors.tolist()
+    store.index = faiss.IndexIDMap2(faiss.IndexFlatL2(8))
+    chunks = [
+        {"text": "The launch code is 0000", "metadata": {"file_path": "/docs/secret.md"}},
+        {"text": "Nothing to see here", "metadata": {"file_path": "/docs/plain.md"}},
+    ]
+    store.add_chunks_with_ids(chunks, [1000, 1001])
+    store.save_index("docs")
+    return vectors
+
+
+def test_parse_encryption():
+    """Test reading server.encryption."""
+    assert parse_encryption(None) == "off"
+    assert parse_encryption(False) == "off"
+    assert parse_encryption("Passphrase") == "passphrase"
+    with pytest.raises(ValueError, match="server.encryption"):
+        parse_encryption("rot13")
+
+
+def test_passphrase_encrypted_index_round_trips(tmp_path, monkeypatch):
+    """Test that the saved index holds no plain text and loads with the same passphrase."""
+    monkeypatch.setenv(PASSPHRASE_ENV, "correct horse")
+    vectors = _save_store(CacheManager(tmp_path, encryption="passphrase"))
+
+    for path in (tmp_path / "indexes").iterdir():
+        data = path.read_bytes()
+        assert data.startswith(MAGIC)
+        assert b"launch code" not in data
+
+    store = VectorStore(
+        cache_manager=CacheManager(tmp_path, encryption="passphrase"),
+        embedding_generator=MagicMock(),
+    )
+    store.load_index("docs")
+    assert store.texts == ["The launch code is 0000", "Nothing to see here"]
+    assert store.search_ids(vectors[1].tolist(), k=1)[0][0] == 1001
+
+
+def test_wrong_or_missing_passphrase_is_rejected(tmp_path, monkeypatch):
+    """Test that a cache encrypted with one passphrase can't be opened with another."""
+    monkeypatch.setenv(PASSPHRASE_ENV, "correct horse")
+    CacheManager(tmp_path, encryption="passphrase")
+
+    monkeypatch.setenv(PASSPHRASE_ENV, "battery staple")
+    with pytest.raises(ValueError, match="not the one"):
+        CacheManager(tmp_path, encryption="passphrase")
+
+    monkeypatch.delenv(PASSPHRASE_ENV)
+    with pytest.raises(ValueError, match=PASSPHRASE_ENV):
+        CacheManager(tmp_path, encryption="passphrase")
+
+
+def test_switching_encryption_makes_the_index_unreadable(tmp_path, monkeypatch):
+    """Test that an index saved with the other setting fails to load, so it is rebuilt."""
+    monkeypatch.setenv(PASSPHRASE_ENV, "correct horse")
+    _save_store(CacheManager(tmp_path / "plain"))
+    _save_store(CacheManager(tmp_path / "encrypted", encryption="passphrase"))
+
+    plain_with_key = VectorStore(
+        cache_manager=CacheManager(tmp_path / "plain", encryption="passphrase"),
+        embedding_generator=MagicMock(),
+    )
+    with pytest.raises(ValueError, match="not encrypted"):
+        plain_with_key.load_index("docs")
+
+    encrypted_without_key = VectorStore(
+        cache_manager=CacheManager(tmp_path / "encrypted"), embedding_generator=MagicMock()
+    )
+    with pytest.raises(ValueError, match="is encrypted"):
+        encrypted_without_key.load_index("docs")
+
+
+def test_keyring_key_is_created_once_per_cache(tmp_path):
+    """Test that server.encryption: keyring keeps one key per cache directory."""
+    passwords = {}
+    keyring = SimpleNamespace(
+        get_password=lambda service, account: passwords.get((service, account)),
+        set_password=lambda service, account, value: passwords.__setitem__(
+            (service, account), value
+        ),
+    )
+    with patch.dict(sys.modules, {"keyring": keyring}):
+        _save_store(CacheManager(tmp_path / "a", encryption="keyring"))
+        CacheManager(tmp_path / "b", encryption="keyring")
+        assert len(passwords) == 2
+
+        store = VectorStore(
+            cache_manager=CacheManager(tmp_path / "a", encryption="keyring"),
+            embedding_generator=MagicMock(),
+        )
+        store.load_index("docs")
+        assert store.texts[0] == "The launch code is 0000"
+
+
+def test_cached_embeddings_are_encrypted(tmp_path, monkeypatch):
+    """Test that the embedding cache holds no plain text when encryption is on."""
+    monkeypatch.setenv(PASSPHRASE_ENV, "correct horse")
+    cache_manager = CacheManager(tmp_path, encryption="passphrase")
+    api_config = SimpleNamespace(base_url="https://api.example.com/v1", api_key="key")
+    with patch("markdown_qa.embeddings.OpenAI"):
+        generator = EmbeddingGenerator(
+            api_config=api_config,
+            cache_dir=cache_manager.embedding_dir,
+            embedding_model="embedder",
+            cipher=cache_manager.cipher,
+        )
+    generator._save_to_cache("key", [0.5, 0.25], "The launch code is 0000")
+
+    data = (cache_manager.embedding_dir / "key.json").read_bytes()
+    assert data.startswith(MAGIC)
+    assert b"launch code" not in data
+    assert generator._load_from_cache("key") == [0.5, 0.25]
@@ -0,0 +1,147 @@
+"""Tests for extracting HTML, PDF, notebook and MDX documents as markdown."""
+
+import json
+import subprocess
+from unittest.mock import patch
+
+import pytest
+
+from markdown_qa.extractors import (
+    extensions_for,
+    extract_html,
+    extract_mdx,
+    extract_notebook,
+    extract_pdf,
+)
+
+PAGE = b"""<!DOCTYPE html>
+<html>
+<head><title>Deploy guide</title><style>body { color: red; }</style></head>
+<body>
+  <header><a href="/">Home</a></header>
+  <nav><ul><li>Menu item</li></ul></nav>
+  <main>
+    <h2>Install</h2>
+    <p>Run the   installer with <code>--quiet</code>.</p>
+    <ul><li>Linux</li><li>macOS</li></ul>
+    <pre>make install
+make check</pre>
+    <script>track();</script>
+  </main>
+  <footer>Copyright</footer>
+</body>
+</html>
+"""
+
+
+def test_html_keeps_the_main_content_as_markdown():
+    """Test that headings, lists and code are kept, and page chrome is dropped."""
+    text = extract_html(PAGE)
+
+    assert text == (
+        "# Deploy guide\n\n"
+        "## Install\n\n"
+        "Run the installer with `--quiet`.\n\n"
+        "- Linux\n- macOS\n\n"
+        "```\nmake install\nmake check\n```\n"
+    )
+
+
+def test_html_without_main_content_keeps_the_body():
+    """Test that a page without <main> or <article> keeps its whole body."""
+    text = extract_html(b"<h1>Notes</h1><p>First</p><nav>Skip</nav><p>Second</p>")
+
+    assert text == "# Notes\n\nFirst\n\nSecond\n"
+
+
+def test_pdf_pages_become_sections():
+    """Test that each PDF page with text becomes a "Page N" section."""
+    result = subprocess.CompletedProcess([], 0, stdout=b"Intro text\n\fBlank\f\n\fLast page\n", stderr=b"")
+    with patch("markdown_qa.extractors.shutil.which", return_value="/usr/bin/pdftotext"), patch(
+        "markdown_qa.extractors.subprocess.run", return_value=result
+    ) as run:
+        text = extract_pdf(b"%PDF-1.7")
+
+    assert run.call_args.kwargs["input"] == b"%PDF-1.7"
+    assert text == (
+        "## Page 1\n\nIntro text\n\n"
+        "## Page 2\n\nBlank\n\n"
+        "## Page 4\n\nLast page\n"
+    )
+
+
+def test_pdf_without_an_extractor_is_an_error():
+    """Test that PDFs fail to read, rather than index empty, without pdftotext or pypdf."""
+    with patch("markdown_qa.extractors.shutil.which", return_value=None), patch.dict(
+        "sys.modules", {"pypdf": None}
+    ):
+        with pytest.raises(ValueError, match="pdftotext or the pypdf package"):
+            extract_pdf(b"%PDF-1.7")
+
+
+def test_notebook_cells_become_numbered_sections():
+    """Test that markdown and code cells are kept under their cell numbers, and outputs are not."""
+    notebook = {
+        "metadata": {"kernelspec": {"language": "python"}},
+        "cells": [
+            {"cell_type": "markdown", "source": ["# Training\n", "Fit the model.\n", "## Data\n", "Load it."]},
+            {"cell_type": "code", "source": "df = load()", "outputs": [{"text": "42"}]},
+            {"cell_type": "raw", "source": "raw text"},
+            {"cell_type": "markdown", "source": "A note"},
+        ],
+    }
+
+    text = extract_notebook(json.dumps(notebook).encode())
+
+    assert text == (
+        "# Cell 1: Training\nFit the model.\n## Cell 1: Data\nLoad it.\n\n"
+        "## Cell 2\n\n```python\ndf = load()\n```\n\n"
+        "## Cell 4\n\nA note\n"
+    )
+
+
+def test_file_that_is_not_a_notebook_is_an_error():
+    """Test that a malformed notebook fails to read."""
+    with pytest.raises(ValueError, match="Not a Jupyter notebook"):
+        extract_notebook(b"{not json")
+
+
+def test_mdx_drops_jsx_but_keeps_text_and_code():
+    """Test that imports, exports, component tags and JSX comments are dropped outside code blocks."""
+    mdx = b"""import { Tabs } from "@site/components";
+export const meta = {
+  author: "me",
+};
+
+# Setup
+
+{/* hidden */}
+<Tabs>
+<Tab label="npm">Run **npm install**.</Tab>
+</Tabs>
+
+<Note type="info" onClick={() => go(1)} />
+
+```jsx
+<Tabs>{x}</Tabs>
+```
+
+{props.footer}
+Press <kbd>Ctrl</kbd>.
+"""
+
+    assert extract_mdx(mdx) == (
+        "# Setup\n\n"
+        "Run **npm install**.\n\n"
+        "```jsx\n<Tabs>{x}</Tabs>\n```\n\n"
+        "Press <kbd>Ctrl</kbd>.\n"
+    )
+
+
+def test_extensions_of_enabled_formats():
+    """Test that markdown is always indexed and unknown formats are rejected."""
+    assert extensions_for([]) == (".md",)
+    assert extensions_for(["html", "pdf"]) == (".md", ".html", ".htm", ".pdf")
+    assert extensions_for(["ipynb", "mdx"]) == (".md", ".ipynb", ".mdx")
+    with pytest.raises(ValueError, match="Unknown format in server.formats: docx"):
+        extensions_for(["docx"])
@@ -221,6 +221,36 @@ class TestFileChangeDetection:
             assert len(modified) == 0
             assert len(deleted) == 0
 
+    def test_detect_newly_ignored_files_as_deleted(self):
+        """Test that a file an ignore file now leaves out counts as deleted."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            manifest_path = Path(tmpdir) / "cache" / "indexes.json"
+            manifest = Manifest(manifest_path)
+            manifest.create()
+
+            docs_dir = Path(tmpdir) / "docs"
+            (docs_dir / "build").mkdir(parents=True)
+            kept = docs_dir / "kept.md"
+            kept.write_text("# Kept")
+            built = docs_dir / "build" / "out.md"
+            built.write_text("# Generated")
+
+            manifest.add_index("default", [str(docs_dir)])
+            for path in (kept, built):
+                manifest.set_file_metadata("default", str(path), {
+                    "mtime": path.stat().st_mtime,
+                    "chunk_ids": [1001]
+                })
+            (docs_dir / ".mdqaignore").write_text("build/\n")
+
+            added, modified, deleted = manifest.detect_file_changes(
+                "default", [str(docs_dir)]
+            )
+
+            assert added == set()
+            assert modified == set()
+            assert deleted == {str(built)}
+
 
 class TestManifestPerFileMetadata:
     """Test manifest per-file metadata storage."""
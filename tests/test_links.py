@@ -0,0 +1,57 @@
+"""Tests for link extraction and the document link graph."""
+
+from pathlib import Path
+
+from markdown_qa.links import LinkGraph, extract_links
+
+
+def test_extract_links_resolves_relative_markdown_links():
+    """Test that relative .md links are resolved against the file's directory."""
+    content = (
+        "See [setup](../guide/setup.md#install), [api](<api ref.md> \"API\") "
+        "and [the same](api%20ref.md).\n"
+        "Not these: [site](https://example.com/a.md), [top](#top), "
+        "![diagram](img/flow.md), [notes](notes.txt).\n"
+    )
+
+    paths, wikilinks = extract_links(content, Path("/vault/docs/index.md"))
+
+    assert paths == ["/vault/guide/setup.md", "/vault/docs/api ref.md"]
+    assert wikilinks == []
+
+
+def test_extract_links_reads_wikilinks_and_skips_code():
+    """Test wikilink targets without heading or alias, and that code is skipped."""
+    content = (
+        "Read [[Setup]], [[Setup#Install|how to install]] and ![[Diagram]].\n"
+        "```\n[[InCode]] [x](in-code.md)\n```\n"
+        "Also `[[Inline]]` and [[daily/2024-05-01]].\n"
+    )
+
+    paths, wikilinks = extract_links(content, Path("/vault/index.md"))
+
+    assert paths == []
+    assert wikilinks == ["Setup", "Diagram", "daily/2024-05-01"]
+
+
+def test_link_graph_resolves_wikilinks_by_path_then_name():
+    """Test that wikilinks resolve relative to the file, then by shortest path."""
+    graph = LinkGraph.from_metadata(
+        [
+            {"file_path": "/vault/index.md", "wikilinks": ["Setup", "daily/today", "Missing"]},
+            {"file_path": "/vault/index.md", "links": ["/vault/outside.md"]},
+            {"file_path": "/vault/setup.md"},
+            {"file_path": "/vault/old/setup.md", "links": ["/vault/index.md"]},
+            {"file_path": "/vault/daily/today.md", "wikilinks": ["today"]},
+        ]
+    )
+
+    assert graph.links("/vault/index.md") == (
+        ["/vault/daily/today.md", "/vault/setup.md"],
+        ["/vault/old/setup.md"],
+    )
+    # Links to itself and to files that are not indexed are left out
+    assert graph.links("/vault/daily/today.md") == ([], ["/vault/index.md"])
+    assert graph.neighbors("/vault/setup.md") == {"/vault/index.md"}
+    assert graph.links("/vault/unknown.md") == ([], [])
+    assert not LinkGraph.from_metadata([{"file_path": "/vault/a.md"}])
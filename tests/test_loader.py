@@ -0,0 +1,294 @@
+"""Tests for loading large markdown files."""
+
+import os
+import time
+import warnings
+from pathlib import Path
+from unittest.mock import MagicMock
+
+import pytest
+
+from markdown_qa.chunker import (
+    ChunkingConfig,
+    ChunkSettings,
+    MarkdownChunker,
+    estimate_tokens,
+)
+from markdown_qa.extractors import extensions_for
+from markdown_qa.loader import (
+    compute_directories_checksum,
+    count_markdown_files,
+    decode_markdown,
+    FileTooLargeError,
+    iter_markdown_files,
+    iter_single_file,
+    plan_markdown_files,
+    read_segments,
+)
+
+
+def _write_stable(path: Path, content: str) -> None:
+    """Write a file with an mtime outside the stability window."""
+    path.write_text(content, encoding="utf-8")
+    old = time.time() - 60
+    os.utime(path, (old, old))
+
+
+def test_read_segments_cuts_large_files_at_headings(tmp_path):
+    """Test that a large file is read in bounded segments starting at headings."""
+    sections = [f"# Section {i}\n\n" + "Some text. ü\n" * 20 for i in range(10)]
+    path = tmp_path / "large.md"
+    path.write_text("".join(sections), encoding="utf-8")
+
+    segments = list(read_segments(path, segment_size=1024))
+
+    assert "".join(segments) == "".join(sections)
+    assert len(segments) > 1
+    assert all(len(segment.encode("utf-8")) <= 1024 for segment in segments)
+    assert all(segment.startswith("# Section") for segment in segments)
+
+
+def test_read_segments_without_line_breaks_keeps_characters_whole(tmp_path):
+    """Test that a hard cut never splits a multi-byte character."""
+    path = tmp_path / "one-line.md"
+    path.write_text("äöü" * 1000, encoding="utf-8")
+
+    segments = list(read_segments(path, segment_size=101))
+
+    assert "".join(segments) == "äöü" * 1000
+    assert all(len(segment.encode("utf-8")) <= 101 for segment in segments)
+
+
+def test_read_segments_reads_small_files_whole(tmp_path):
+    """Test that a file below the segment size is a single segment."""
+    path = tmp_path / "small.md"
+    path.write_text("# Small\n\nText.\n", encoding="utf-8")
+
+    assert list(read_segments(path)) == ["# Small\n\nText.\n"]
+
+
+def test_files_over_the_size_cap_are_skipped_with_a_warning(tmp_path):
+    """Test that files larger than max_file_size_mb are skipped, not loaded."""
+    _write_stable(tmp_path / "small.md", "# Small\n")
+    _write_stable(tmp_path / "export.md", "# Export\n" + "x" * (1024 * 1024))
+
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter("always")
+        files = list(iter_markdown_files([str(tmp_path)], max_file_size_mb=1))
+
+    assert [path.name for path, _ in files] == ["small.md"]
+    assert any("export.md" in str(w.message) and "1 MB" in str(w.message) for w in caught)
+
+
+def test_single_file_over_the_size_cap_raises(tmp_path):
+    """Test that incremental loading reports an oversized file before reading it."""
+    path = tmp_path / "export.md"
+    _write_stable(path, "x" * (1024 * 1024 + 1))
+
+    with pytest.raises(FileTooLargeError):
+        next(iter_single_file(str(path), max_file_size_mb=1))
+
+
+def test_segments_of_one_file_share_its_front_matter_tags():
+    """Test that later segments of a file keep the tags from its front matter."""
+    chunker = MarkdownChunker()
+    chunker._extract_front_matter_tags = MagicMock(side_effect=[["howto"], ["other"]])
+    chunker.splitter = MagicMock()
+    chunker.splitter.create_documents.side_effect = lambda texts: [
+        MagicMock(page_content=texts[0], metadata={})
+    ]
+    path = Path("/repo/export.md")
+
+    chunks = chunker.chunk_files(
+        [(path, "---\ntags: [howto]\n---\n# A\n"), (path, "# B\n"), (Path("/repo/b.md"), "# C\n")]
+    )
+
+    assert [chunk["metadata"]["tags"] for chunk in chunks] == [["howto"], ["howto"], ["other"]]
+
+
+def test_segments_of_one_file_share_its_front_matter_title_and_date():
+    """Test that chunks carry the title and date from the file's front matter."""
+    chunker = MarkdownChunker()
+    chunker.splitter = MagicMock()
+    chunker.splitter.create_documents.side_effect = lambda texts: [
+        MagicMock(page_content=texts[0], metadata={})
+    ]
+    path = Path("/repo/export.md")
+
+    chunks = chunker.chunk_files(
+        [(path, "---\ntitle: Export guide\ndate: 2024-05-01\n---\n# A\n"), (path, "# B\n")]
+    )
+
+    assert [chunk["metadata"]["title"] for chunk in chunks] == ["Export guide"] * 2
+    assert [chunk["metadata"]["date"] for chunk in chunks] == ["2024-05-01"] * 2
+
+
+def test_cjk_characters_count_as_one_token_each():
+    """Test that token estimates give CJK text smaller chunks by characters."""
+    assert estimate_tokens("abcdefgh") == 2
+    assert estimate_tokens("索引文件") == 4
+    assert estimate_tokens("索引 index") == 4
+
+
+def test_headings_up_to_the_configured_level_start_new_chunks():
+    """Test that heading_level decides which headings chunks are split at."""
+    paragraph = "alpha " * 8
+    content = f"# Guide\n\n{paragraph}\n## Setup\n\n{paragraph}"
+
+    def chunks(level: int) -> list:
+        settings = ChunkSettings(target_tokens=20, overlap_tokens=0, heading_level=level)
+        chunker = MarkdownChunker(chunking=ChunkingConfig(settings))
+        return chunker.chunk_file(Path("a.md"), content)
+
+    assert [chunk["metadata"]["section"] for chunk in chunks(2)] == ["Guide", "Setup"]
+    assert not any(chunk["text"].startswith("## Setup") for chunk in chunks(1))
+
+
+def test_drafts_are_skipped_unless_included():
+    """Test that every segment of a draft is left out unless drafts are included."""
+    files = [
+        (Path("/repo/draft.md"), "---\ndraft: true\n---\n# A\n"),
+        (Path("/repo/draft.md"), "# B\n"),
+        (Path("/repo/done.md"), "---\ndraft: false\n---\n# C\n"),
+    ]
+    chunked = []
+    for include_drafts in (False, True):
+        chunker = MarkdownChunker(include_drafts=include_drafts)
+        chunker.splitter = MagicMock()
+        chunker.splitter.create_documents.side_effect = lambda texts: [
+            MagicMock(page_content=texts[0], metadata={})
+        ]
+        chunked.append([chunk["metadata"]["file_path"] for chunk in chunker.chunk_files(files)])
+
+    assert chunked[0] == ["/repo/done.md"]
+    assert chunked[1] == ["/repo/draft.md", "/repo/draft.md", "/repo/done.md"]
+
+
+@pytest.mark.parametrize(
+    "data, text, issue",
+    [
+        (b"\xef\xbb\xbf---\r\ntags: [a]\r\n---\r\n", "---\ntags: [a]\n---\n", None),
+        ("# Hi\r\n".encode("utf-16"), "# Hi\n", None),
+        ("# 标题\r\n中文内容，测试。\n".encode("gbk"), "# 标题\n中文内容，测试。\n", "decoded as GBK"),
+        ("# Café réseau\nÜber\n".encode("latin-1"), "# Café réseau\nÜber\n", "decoded as Latin-1"),
+        ("“Quoted” – é".encode("cp1252"), "“Quoted” – é", "decoded as Windows-1252"),
+        (b"# A \x81\x8d\xff\n", "# A \ufffd\ufffd\ufffd\n", "bytes were replaced"),
+    ],
+)
+def test_decode_markdown_handles_boms_line_endings_and_legacy_encodings(data, text, issue):
+    """Test that files are decoded whatever their encoding, saying how when not UTF-8."""
+    decoded, found = decode_markdown(data)
+
+    assert decoded == text
+    if issue is None:
+        assert found is None
+    else:
+        assert issue in found
+
+
+def test_files_that_are_not_utf8_are_indexed_and_reported(tmp_path):
+    """Test that a Latin-1 file is read rather than skipped, and its issue recorded."""
+    (tmp_path / "utf8.md").write_text("# Plain\n", encoding="utf-8")
+    (tmp_path / "legacy.md").write_bytes("# Café\r\n".encode("latin-1"))
+    old = time.time() - 10
+    for path in tmp_path.iterdir():
+        os.utime(path, (old, old))
+    issues = {}
+
+    with warnings.catch_warnings(record=True):
+        warnings.simplefilter("always")
+        files = dict(iter_markdown_files([str(tmp_path)], issues=issues))
+
+    assert files[tmp_path / "legacy.md"] == "# Café\n"
+    assert files[tmp_path / "utf8.md"] == "# Plain\n"
+    assert issues == {str(tmp_path / "legacy.md"): "not UTF-8; decoded as Latin-1"}
+
+
+def test_ignore_files_leave_documents_out(tmp_path):
+    """Test that .gitignore and .mdqaignore rules apply, .mdqaignore last."""
+    for name in [
+        "README.md",
+        "guide/intro.md",
+        "build/out.md",
+        "guide/build/nested.md",
+        "vendor/lib.md",
+        "vendor/keep.md",
+        "drafts/todo.md",
+    ]:
+        (tmp_path / name).parent.mkdir(parents=True, exist_ok=True)
+        _write_stable(tmp_path / name, "# Doc\n")
+    (tmp_path / ".gitignore").write_text("# build output\nbuild/\nvendor/*.md\n")
+    (tmp_path / ".mdqaignore").write_text("!vendor/keep.md\n/drafts\n")
+
+    files, ignored = plan_markdown_files([str(tmp_path)])
+
+    relative = lambda paths: [str(Path(p).relative_to(tmp_path)) for p in paths]
+    assert relative(files) == ["README.md", "guide/intro.md", "vendor/keep.md"]
+    assert relative(ignored) == [
+        "build/out.md",
+        "drafts/todo.md",
+        "guide/build/nested.md",
+        "vendor/lib.md",
+    ]
+    indexed = {path for path, _ in iter_markdown_files([str(tmp_path)])}
+    assert sorted(relative(indexed)) == relative(files)
+    assert count_markdown_files(str(tmp_path)) == 3
+
+
+def test_checksum_follows_ignore_files_but_not_ignored_documents(tmp_path):
+    """Test that editing an ignored file doesn't trigger a reload, but editing an ignore file does."""
+    _write_stable(tmp_path / "a.md", "# A\n")
+    _write_stable(tmp_path / "scratch.md", "# Scratch\n")
+    _write_stable(tmp_path / ".mdqaignore", "scratch.md\n")
+    before = compute_directories_checksum([str(tmp_path)])
+
+    os.utime(tmp_path / "scratch.md", (time.time(), time.time()))
+    assert compute_directories_checksum([str(tmp_path)]) == before
+
+    (tmp_path / ".mdqaignore").write_text("")
+    assert compute_directories_checksum([str(tmp_path)]) != before
+
+
+def test_progress_is_reported_per_file_with_the_ones_that_failed(tmp_path):
+    """Test that every file found is reported once, with why it was not indexed."""
+    _write_stable(tmp_path / "a.md", "# A\n")
+    broken = tmp_path / "broken.md"
+    broken.mkdir()
+    old = time.time() - 60
+    os.utime(broken, (old, old))
+    reported = []
+    issues = {}
+
+    with warnings.catch_warnings(record=True):
+        warnings.simplefilter("always")
+        files = list(
+            iter_markdown_files(
+                [str(tmp_path)],
+                issues=issues,
+                progress=lambda *update: reported.append(update),
+            )
+        )
+
+    assert [path.name for path, _ in files] == ["a.md"]
+    assert [(done, total) for done, total, _, _ in reported] == [(1, 2), (2, 2)]
+    errors = {path.name: error for _, _, path, error in reported}
+    assert errors["a.md"] is None
+    assert errors["broken.md"].startswith("failed to read:")
+    assert issues == {str(broken): errors["broken.md"]}
+
+
+def test_other_formats_are_indexed_only_when_enabled(tmp_path):
+    """Test that HTML files are read as markdown once their format is enabled."""
+    _write_stable(tmp_path / "a.md", "# A\n")
+    _write_stable(tmp_path / "page.html", "<title>Page</title><p>Body</p>")
+
+    files, _ = plan_markdown_files([str(tmp_path)])
+    assert [Path(f).name for f in files] == ["a.md"]
+
+    extensions = extensions_for(["html"])
+    indexed = dict(iter_markdown_files([str(tmp_path)], extensions=extensions))
+    assert indexed[tmp_path / "page.html"] == "# Page\n\nBody\n"
+    assert count_markdown_files(str(tmp_path), extensions) == 2
+    assert compute_directories_checksum([str(tmp_path)]) != compute_directories_checksum(
+        [str(tmp_path)], extensions
+    )
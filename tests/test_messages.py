@@ -3,8 +3,12 @@
 import pytest
 
 from markdown_qa.messages import (
+    ErrorCode,
     MessageType,
+    create_capabilities_message,
     create_error_message,
+    create_index_progress_message,
+    create_index_stats_message,
     create_query_message,
     create_response_message,
     create_status_message,
@@ -42,6 +46,12 @@ class TestMessages:
         msg = create_error_message("Something went wrong")
         assert msg["type"] == MessageType.ERROR
         assert msg["message"] == "Something went wrong"
+        assert "code" not in msg
+
+    def test_create_error_message_with_code(self):
+        """Test creating an error message with a machine-readable code."""
+        msg = create_error_message("Slow down", ErrorCode.RATE_LIMITED)
+        assert msg["code"] == "rate_limited"
 
     def test_create_status_message_ready(self):
         """Test creating a ready status message."""
@@ -57,6 +67,35 @@ class TestMessages:
         assert msg["status"] == "indexing"
         assert "message" not in msg
 
+    def test_create_capabilities_message(self):
+        """Test creating a capabilities message."""
+        msg = create_capabilities_message(["cancel", "history"])
+        assert msg == {"type": MessageType.CAPABILITIES, "features": ["cancel", "history"]}
+
+    def test_create_capabilities_message_with_encoding(self):
+        """Test a picked frame encoding is included in capabilities."""
+        msg = create_capabilities_message(["cancel"], "msgpack")
+        assert msg["encoding"] == "msgpack"
+
+    def test_create_index_stats_message_leaves_out_unknown_fields(self):
+        """Test creating an index stats message without disk details."""
+        msg = create_index_stats_message("docs", 2, 3, "text-embedding-3-small")
+        assert msg == {
+            "type": "index_stats",
+            "index": "docs",
+            "documents": 2,
+            "chunks": 3,
+            "embedding_model": "text-embedding-3-small",
+        }
+
+    def test_create_index_progress_message_lists_failed_files_when_finished(self):
+        """Test that only the last progress message carries the failed files."""
+        msg = create_index_progress_message(1, 2, path="/docs/a.md")
+        assert msg == {"type": "index_progress", "done": 1, "total": 2, "path": "/docs/a.md"}
+        msg = create_index_progress_message(2, 2, chunks=4, embedded=4, finished=True)
+        assert msg["finished"] is True
+        assert msg["failed"] == []
+
     def test_create_stream_end_message_deduplicates_sources(self):
         """Test stream-end sources are deduplicated in original order."""
         msg = create_stream_end_message(
@@ -65,6 +104,33 @@ class TestMessages:
         assert msg["type"] == MessageType.STREAM_END
         assert msg["sources"] == ["/path/to/a.md", "/path/to/b.md"]
 
+    def test_create_stream_end_message_with_usage(self):
+        """Test token usage is included in stream end only when known."""
+        usage = {"prompt_tokens": 120, "completion_tokens": 30, "model": "qwen-flash"}
+        msg = create_stream_end_message(["/path/to/a.md"], usage=usage)
+        assert msg["usage"] == usage
+        assert "usage" not in create_stream_end_message(["/path/to/a.md"])
+
+    def test_create_stream_end_message_with_elapsed_ms(self):
+        """Test the query time is sent as whole milliseconds when given."""
+        msg = create_stream_end_message(["/path/to/a.md"], elapsed_ms=2349.6)
+        assert msg["elapsed_ms"] == 2350
+        assert "elapsed_ms" not in create_stream_end_message(["/path/to/a.md"])
+
+    def test_create_stream_end_message_with_citations(self):
+        """Test citations are included in stream end only when there are any."""
+        citations = {"1": "/path/to/a.md", "2": {"path": "/path/to/b.md", "heading": "Setup"}}
+        msg = create_stream_end_message(["/path/to/a.md"], citations=citations)
+        assert msg["citations"] == citations
+        assert "citations" not in create_stream_end_message(["/path/to/a.md"], citations={})
+
+    def test_create_stream_end_message_with_grounding(self):
+        """Test the grounding check result is included in stream end when given."""
+        grounding = {"score": 1.0, "unsupported": []}
+        msg = create_stream_end_message(["/path/to/a.md"], grounding=grounding)
+        assert msg["grounding"] == grounding
+        assert "grounding" not in create_stream_end_message(["/path/to/a.md"])
+
     def test_validate_query_message_valid(self):
         """Test validating a valid query message."""
         msg = {"type": MessageType.QUERY, "question": "What is Python?"}
@@ -0,0 +1,120 @@
+"""Tests for the Anthropic and Ollama answer providers."""
+
+import io
+import json
+import urllib.error
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.providers import (
+    AnthropicClient,
+    OllamaClient,
+    ProviderError,
+    create_chat_client,
+)
+
+MESSAGES = [
+    {"role": "system", "content": "You are a helpful assistant."},
+    {"role": "user", "content": "How do I install it?"},
+]
+
+
+def _response(body: bytes) -> MagicMock:
+    """A urlopen result reading ``body``, whole or line by line."""
+    response = MagicMock()
+    response.__enter__.return_value = response
+    response.read.return_value = body
+    response.__iter__.return_value = iter(io.BytesIO(body).readlines())
+    return response
+
+
+def test_anthropic_reply_and_request():
+    """Test that system messages become the system prompt and usage is read."""
+    client = AnthropicClient("anthropic-key")
+    reply = {
+        "content": [{"type": "text", "text": "Run the installer."}],
+        "usage": {"input_tokens": 12, "output_tokens": 4},
+    }
+
+    with patch(
+        "markdown_qa.providers.urllib.request.urlopen",
+        return_value=_response(json.dumps(reply).encode()),
+    ) as urlopen:
+        response = client.chat.completions.create(
+            model="claude-sonnet-4-5", messages=MESSAGES, temperature=1.5, max_tokens=200
+        )
+
+    assert response.choices[0].message.content == "Run the installer."
+    assert (response.usage.prompt_tokens, response.usage.completion_tokens) == (12, 4)
+    request = urlopen.call_args.args[0]
+    assert request.full_url == "https://api.anthropic.com/v1/messages"
+    assert request.get_header("X-api-key") == "anthropic-key"
+    assert json.loads(request.data) == {
+        "model": "claude-sonnet-4-5",
+        "max_tokens": 200,
+        "system": "You are a helpful assistant.",
+        "messages": [{"role": "user", "content": "How do I install it?"}],
+        "temperature": 1.0,
+    }
+
+
+def test_anthropic_stream_yields_text_then_usage():
+    """Test reading text deltas and token counts from server-sent events."""
+    events = [
+        {"type": "message_start", "message": {"usage": {"input_tokens": 12}}},
+        {"type": "content_block_delta", "delta": {"type": "text_delta", "text": "Run "}},
+        {"type": "content_block_delta", "delta": {"type": "text_delta", "text": "it."}},
+        {"type": "message_delta", "usage": {"output_tokens": 3}},
+    ]
+    body = "".join(f"event: {e['type']}\ndata: {json.dumps(e)}\n\n" for e in events).encode()
+
+    with patch("markdown_qa.providers.urllib.request.urlopen", return_value=_response(body)):
+        chunks = list(
+            AnthropicClient("k").chat.completions.create(
+                model="m", messages=MESSAGES, stream=True
+            )
+        )
+
+    assert [c.choices[0].delta.content for c in chunks if c.choices] == ["Run ", "it."]
+    assert (chunks[-1].usage.prompt_tokens, chunks[-1].usage.completion_tokens) == (12, 3)
+
+
+def test_ollama_stream_yields_text_then_usage():
+    """Test reading Ollama's JSON lines, with options for temperature and length."""
+    lines = [
+        {"message": {"content": "Run "}, "done": False},
+        {"message": {"content": "it."}, "done": False},
+        {"message": {"content": ""}, "done": True, "prompt_eval_count": 9, "eval_count": 2},
+    ]
+    body = "".join(json.dumps(line) + "\n" for line in lines).encode()
+
+    with patch(
+        "markdown_qa.providers.urllib.request.urlopen", return_value=_response(body)
+    ) as urlopen:
+        chunks = list(
+            OllamaClient().chat.completions.create(
+                model="llama3.2", messages=MESSAGES, temperature=0.7, max_tokens=500, stream=True
+            )
+        )
+
+    assert [c.choices[0].delta.content for c in chunks if c.choices] == ["Run ", "it."]
+    assert (chunks[-1].usage.prompt_tokens, chunks[-1].usage.completion_tokens) == (9, 2)
+    request = urlopen.call_args.args[0]
+    assert request.full_url == "http://localhost:11434/api/chat"
+    assert json.loads(request.data)["options"] == {"temperature": 0.7, "num_predict": 500}
+
+
+def test_http_errors_keep_their_status():
+    """Test that a failed request reports its HTTP status, e.g. for rate limits."""
+    error = urllib.error.HTTPError(
+        "https://api.anthropic.com/v1/messages", 429, "Too Many Requests", {}, io.BytesIO(b"slow down")
+    )
+
+    with patch("markdown_qa.providers.urllib.request.urlopen", side_effect=error):
+        with pytest.raises(ProviderError) as raised:
+            AnthropicClient("k").chat.completions.create(model="m", messages=MESSAGES)
+
+    assert raised.value.status_code == 429
+    with pytest.raises(ValueError, match="Unknown LLM provider"):
+        create_chat_client("llamafile", None, None)
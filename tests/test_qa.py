@@ -137,6 +137,52 @@ class TestQuestionAnswerer:
             assert sources[0] == "/path/to/doc1.md"
             assert sources[1] == "/path/to/doc2.md"
 
+    def test_context_cites_front_matter_title_and_tags(self):
+        """Test that each context part names the file's title and tags when known."""
+        retrieval_engine = MagicMock(spec=RetrievalEngine)
+        retrieval_engine.retrieve.return_value = [
+            (
+                "Content 1.",
+                {"file_path": "/path/to/doc1.md", "title": "Setup", "tags": ["howto", "cli"]},
+                0.3,
+            ),
+            ("Content 2.", {"file_path": "/path/to/doc2.md"}, 0.4),
+        ]
+        api_config = MagicMock(spec=APIConfig)
+        api_config.base_url = "https://api.example.com"
+        api_config.api_key = "test-key"
+        api_config.llm_model = "test-model"
+
+        answerer = QuestionAnswerer(retrieval_engine, api_config=api_config)
+        context, sources = answerer.retrieve("Question?", k=2)
+
+        assert "Source: /path/to/doc1.md (title: Setup) [tags: howto, cli]\nContent 1." in context
+        assert "Source: /path/to/doc2.md\nContent 2." in context
+        assert sources == ["/path/to/doc1.md", "/path/to/doc2.md"]
+
+    def test_context_numbers_parts_for_citations(self):
+        """Test that context parts are numbered and their sources kept by number."""
+        retrieval_engine = MagicMock(spec=RetrievalEngine)
+        retrieval_engine.retrieve.return_value = [
+            ("Content 1.", {"file_path": "/path/to/doc1.md", "section": "Install"}, 0.3),
+            ("Content 2.", {"file_path": "/path/to/doc2.md", "section": ""}, 0.4),
+        ]
+        api_config = MagicMock(spec=APIConfig)
+        api_config.base_url = "https://api.example.com"
+        api_config.api_key = "test-key"
+        api_config.llm_model = "test-model"
+
+        answerer = QuestionAnswerer(retrieval_engine, api_config=api_config)
+        context, _ = answerer.retrieve("Question?", k=2)
+
+        assert "[1] Source: /path/to/doc1.md\nContent 1." in context
+        assert "[2] Source: /path/to/doc2.md\nContent 2." in context
+        assert answerer.citations == {
+            "1": {"path": "/path/to/doc1.md", "heading": "Install"},
+            "2": "/path/to/doc2.md",
+        }
+        assert "e.g. [1]" in answerer._build_prompt("Question?", context)
+
     def test_build_prompt_includes_context(self):
         """Test that prompt includes retrieved context."""
         retrieval_engine = MagicMock(spec=RetrievalEngine)
@@ -151,3 +197,179 @@ class TestQuestionAnswerer:
         assert "What is Python?" in prompt
         assert "Python is a language." in prompt
         assert "Context from documentation:" in prompt
+
+    def test_build_prompt_includes_history(self):
+        """Test that prompt includes earlier turns of the conversation."""
+        retrieval_engine = MagicMock(spec=RetrievalEngine)
+        api_config = MagicMock(spec=APIConfig)
+        api_config.base_url = "https://api.example.com"
+        api_config.api_key = "test-key"
+        api_config.llm_model = "test-model"
+
+        answerer = QuestionAnswerer(
+            retrieval_engine,
+            api_config=api_config,
+            history=[("How do I set it up?", "1. Install. 2. Configure.")],
+        )
+        prompt = answerer._build_prompt("Expand on point 2", "Configuration docs.")
+
+        assert "Conversation so far:" in prompt
+        assert "Q: How do I set it up?\nA: 1. Install. 2. Configure." in prompt
+        assert prompt.index("Conversation so far:") < prompt.index("Question: Expand on point 2")
+
+    def test_build_prompt_includes_attachment(self):
+        """Test that prompt includes material attached to the question."""
+        retrieval_engine = MagicMock(spec=RetrievalEngine)
+        api_config = MagicMock(spec=APIConfig)
+        api_config.base_url = "https://api.example.com"
+        api_config.api_key = "test-key"
+        api_config.llm_model = "test-model"
+
+        answerer = QuestionAnswerer(
+            retrieval_engine,
+            api_config=api_config,
+            attachment="-Cache for 5 minutes.\n+Cache for 1 hour.",
+        )
+        prompt = answerer._build_prompt("Does this contradict the docs?", "Caching docs.")
+
+        assert "Material the question is about:\n-Cache for 5 minutes." in prompt
+        assert prompt.index("Caching docs.") < prompt.index("Material the question is about:")
+        assert prompt.index("+Cache for 1 hour.") < prompt.index("Question: Does this")
+
+    def test_build_prompt_asks_for_answer_language(self):
+        """Test that prompt asks for the answer in the question's language."""
+        retrieval_engine = MagicMock(spec=RetrievalEngine)
+        api_config = MagicMock(spec=APIConfig)
+        api_config.base_url = "https://api.example.com"
+        api_config.api_key = "test-key"
+        api_config.llm_model = "test-model"
+
+        answerer = QuestionAnswerer(retrieval_engine, api_config=api_config)
+        assert "Write the answer in" not in answerer._build_prompt("Wie?", "Docs.")
+
+        answerer.answer_language = "de"
+        prompt = answerer._build_prompt("Wie melde ich mich an?", "Login docs.")
+        assert "Write the answer in the language with code de" in prompt
+
+    def test_suggest_followups_strips_numbering_and_keeps_count(self):
+        """Test that suggested questions are read one per line, up to count."""
+        retrieval_engine = MagicMock(spec=RetrievalEngine)
+        api_config = MagicMock(spec=APIConfig)
+        api_config.base_url = "https://api.example.com"
+        api_config.api_key = "test-key"
+        api_config.llm_model = "test-model"
+
+        with patch("markdown_qa.qa.OpenAI") as mock_openai_class:
+            mock_client = MagicMock()
+            mock_openai_class.return_value = mock_client
+            response = MagicMock()
+            response.choices = [
+                MagicMock(
+                    message=MagicMock(
+                        content="1. How do I upgrade?\n\n- Where are logs kept?\n3) Can I undo it?"
+                    )
+                )
+            ]
+            mock_client.chat.completions.create.return_value = response
+
+            answerer = QuestionAnswerer(retrieval_engine, api_config=api_config)
+            questions = answerer.suggest_followups(
+                "How do I install it?", "Run the installer.", "Install docs.", 2
+            )
+
+        assert questions == ["How do I upgrade?", "Where are logs kept?"]
+        prompt = mock_client.chat.completions.create.call_args.kwargs["messages"][1]["content"]
+        assert "Install docs." in prompt
+        assert "Run the installer." in prompt
+        assert "Suggest 2" in prompt
+
+    def test_check_grounding_reports_unsupported_sentences(self):
+        """Test that the sentences the LLM names are reported with a score."""
+        retrieval_engine = MagicMock(spec=RetrievalEngine)
+        api_config = MagicMock(spec=APIConfig)
+        api_config.base_url = "https://api.example.com"
+        api_config.api_key = "test-key"
+        api_config.llm_model = "test-model"
+
+        with patch("markdown_qa.qa.OpenAI") as mock_openai_class:
+            mock_client = MagicMock()
+            mock_openai_class.return_value = mock_client
+            response = MagicMock()
+            response.choices = [MagicMock(message=MagicMock(content="2, 4"))]
+            mock_client.chat.completions.create.return_value = response
+
+            answerer = QuestionAnswerer(retrieval_engine, api_config=api_config)
+            score, unsupported = answerer.check_grounding(
+                "Run the installer [1]. It is free!\n\n- Works offline.\n- Needs Python 3.",
+                "Install docs.",
+            )
+            prompt = mock_client.chat.completions.create.call_args.kwargs["messages"][1]["content"]
+
+            assert answerer.check_grounding("", "Install docs.") == (1.0, [])
+
+        assert score == 0.5
+        assert unsupported == ["It is free!", "- Needs Python 3."]
+        assert "Install docs." in prompt
+        assert "1. Run the installer [1]." in prompt
+        assert "3. - Works offline." in prompt
+
+    def test_provider_selects_the_answer_client(self):
+        """Test that api.provider picks the client, without the embedding API's key."""
+        retrieval_engine = MagicMock(spec=RetrievalEngine)
+        api_config = type("MockAPIConfig", (), {
+            "base_url": "https://api.example.com/v1",
+            "api_key": "embedding-key",
+            "llm_model": "llama3.2",
+            "provider": "ollama",
+            "llm_base_url": "http://gpu-box:11434",
+            "llm_api_key": None,
+        })()
+
+        with patch("markdown_qa.qa.OpenAI") as mock_openai_class, \
+             patch("markdown_qa.qa.create_chat_client") as mock_create:
+            answerer = QuestionAnswerer(retrieval_engine, api_config=api_config)
+
+        mock_openai_class.assert_not_called()
+        mock_create.assert_called_once_with("ollama", None, "http://gpu-box:11434")
+        assert answerer.client is mock_create.return_value
+
+    def test_translate_returns_translation_and_question_language(self):
+        """Test that a question is translated into the docs' language."""
+        retrieval_engine = MagicMock(spec=RetrievalEngine)
+        api_config = MagicMock(spec=APIConfig)
+        api_config.base_url = "https://api.example.com"
+        api_config.api_key = "test-key"
+        api_config.llm_model = "test-model"
+
+        with patch("markdown_qa.qa.OpenAI") as mock_openai_class:
+            mock_client = MagicMock()
+            mock_openai_class.return_value = mock_client
+
+            def reply(content):
+                response = MagicMock()
+                response.choices = [MagicMock(message=MagicMock(content=content))]
+                return response
+
+            answerer = QuestionAnswerer(retrieval_engine, api_config=api_config)
+            mock_client.chat.completions.create.return_value = reply(
+                "DE\nHow do I log in?\n"
+            )
+            assert answerer.translate("Wie melde ich mich an?", "en") == (
+                "How do I log in?",
+                "de",
+            )
+            prompt = mock_client.chat.completions.create.call_args.kwargs["messages"][1]["content"]
+            assert "Wie melde ich mich an?" in prompt
+
+            # Already in the docs' language: kept as it is
+            mock_client.chat.completions.create.return_value = reply(
+                "en\nHow do I log in, please?"
+            )
+            assert answerer.translate("How do I log in?", "en-US") == (
+                "How do I log in?",
+                None,
+            )
+
+            mock_client.chat.completions.create.side_effect = Exception("timeout")
+            with pytest.raises(RuntimeError, match="translate"):
+                answerer.translate("Wie?", "en")
@@ -5,7 +5,7 @@ from unittest.mock import MagicMock, patch
 import pytest
 
 from markdown_qa.index_manager import IndexManager
-from markdown_qa.messages import MessageType
+from markdown_qa.messages import ErrorCode, MessageType
 from markdown_qa.query_handler import QueryHandler
 
 
@@ -22,6 +22,7 @@ class TestQueryHandler:
 
         assert response["type"] == MessageType.ERROR
         assert "not ready" in response["message"].lower()
+        assert response["code"] == ErrorCode.INDEX_NOT_READY
 
     def test_handle_query_empty_question(self):
         """Test handling query with empty question."""
@@ -83,3 +84,449 @@ class TestQueryHandler:
             assert response["type"] == MessageType.RESPONSE
             assert "answer" in response
             assert "sources" in response
+
+    def test_handle_query_retrieves_with_retrieval_query(self):
+        """Test that a follow-up's retrieval_query is used for retrieval only."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa, \
+             patch("markdown_qa.query_handler.ResponseFormatter") as mock_fmt:
+
+            mock_answerer = MagicMock()
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md"])
+            mock_answerer._build_prompt.return_value = "Formatted prompt"
+            mock_answerer._generate_answer.return_value = "Answer text"
+            mock_qa.return_value = mock_answerer
+            mock_fmt.return_value.format_response.return_value = {
+                "answer": "Answer text",
+                "sources": ["/a.md"],
+            }
+
+            handler = QueryHandler(index_manager)
+            handler.handle_query({
+                "type": MessageType.QUERY,
+                "question": "How do I configure it?",
+                "retrieval_query": "Q: What is foo?\nA: A tool.\nQ: How do I configure it?",
+            })
+
+            mock_answerer.retrieve.assert_called_once_with(
+                "Q: What is foo?\nA: A tool.\nQ: How do I configure it?"
+            )
+            mock_answerer._build_prompt.assert_called_once_with(
+                "How do I configure it?", "Retrieved context"
+            )
+
+    def test_handle_query_passes_history_to_answerer(self):
+        """Test that a follow-up's history reaches the answerer's prompt."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa, \
+             patch("markdown_qa.query_handler.ResponseFormatter") as mock_fmt:
+
+            mock_answerer = MagicMock()
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md"])
+            mock_answerer._generate_answer.return_value = "Answer text"
+            mock_qa.return_value = mock_answerer
+            mock_fmt.return_value.format_response.return_value = {
+                "answer": "Answer text",
+                "sources": ["/a.md"],
+            }
+
+            handler = QueryHandler(index_manager)
+            handler.handle_query({
+                "type": MessageType.QUERY,
+                "question": "Expand on point 2",
+                "history": [
+                    {"question": "How do I set it up?", "answer": "1. Install. 2. Configure."},
+                    {"question": "malformed"},
+                ],
+            })
+
+            assert mock_qa.call_args.kwargs["history"] == [
+                ("How do I set it up?", "1. Install. 2. Configure.")
+            ]
+
+    def test_handle_query_passes_attachment_to_answerer(self):
+        """Test that material attached to a query reaches the answerer."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa, \
+             patch("markdown_qa.query_handler.ResponseFormatter") as mock_fmt:
+
+            mock_answerer = MagicMock()
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md"])
+            mock_answerer._generate_answer.return_value = "Answer text"
+            mock_qa.return_value = mock_answerer
+            mock_fmt.return_value.format_response.return_value = {
+                "answer": "Answer text",
+                "sources": ["/a.md"],
+            }
+
+            handler = QueryHandler(index_manager)
+            handler.handle_query({
+                "type": MessageType.QUERY,
+                "question": "Does this contradict the docs?",
+                "attachment": "diff --git a/notes.md b/notes.md",
+            })
+            assert mock_qa.call_args.kwargs["attachment"] == "diff --git a/notes.md b/notes.md"
+
+            handler.handle_query({"type": MessageType.QUERY, "question": "Plain?"})
+            assert mock_qa.call_args.kwargs["attachment"] is None
+
+    def test_handle_query_passes_filters_to_retrieval(self):
+        """Test that a query's filters scope the retrieval engine."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine") as mock_engine, \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa, \
+             patch("markdown_qa.query_handler.ResponseFormatter") as mock_fmt:
+
+            mock_answerer = MagicMock()
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md"])
+            mock_answerer._generate_answer.return_value = "Answer text"
+            mock_qa.return_value = mock_answerer
+            mock_fmt.return_value.format_response.return_value = {
+                "answer": "Answer text",
+                "sources": ["/a.md"],
+            }
+
+            handler = QueryHandler(index_manager)
+            handler.handle_query({
+                "type": MessageType.QUERY,
+                "question": "How do I log in?",
+                "filters": {"directories": ["docs/api"], "tags": "auth", "globs": [1, "*.md"]},
+            })
+            assert mock_engine.call_args.kwargs["filters"] == {
+                "directories": ["docs/api"],
+                "globs": ["*.md"],
+            }
+
+            handler.handle_query({"type": MessageType.QUERY, "question": "Plain?"})
+            assert mock_engine.call_args.kwargs["filters"] is None
+
+    def test_handle_query_stream_reports_retrieval_before_stream_start(self):
+        """Test that a streamed answer starts with a retrieving status."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa:
+
+            mock_answerer = MagicMock()
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md"])
+            mock_answerer.stream_with_context.return_value = iter(
+                [("Answer", None), ("", ["/a.md"])]
+            )
+            mock_answerer.last_usage = None
+            mock_qa.return_value = mock_answerer
+
+            handler = QueryHandler(index_manager)
+            messages = list(handler.handle_query_stream(
+                {"type": MessageType.QUERY, "question": "How do I log in?"}
+            ))
+
+        assert [m["type"] for m in messages] == [
+            MessageType.STATUS,
+            MessageType.STREAM_START,
+            MessageType.STREAM_CHUNK,
+            MessageType.STREAM_END,
+        ]
+        assert messages[0]["status"] == "retrieving"
+
+    def test_handle_query_stream_sends_citations_of_markers(self):
+        """Test that stream end maps the answer's [N] markers to their sources."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa:
+
+            mock_answerer = MagicMock()
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md", "/b.md"])
+            mock_answerer.stream_with_context.return_value = iter(
+                [("Run it [", None), ("2]. See [9].", None), ("", ["/a.md", "/b.md"])]
+            )
+            mock_answerer.last_usage = None
+            mock_answerer.citations = {"1": "/a.md", "2": {"path": "/b.md", "heading": "Run"}}
+            mock_qa.return_value = mock_answerer
+
+            handler = QueryHandler(index_manager)
+            messages = list(handler.handle_query_stream(
+                {"type": MessageType.QUERY, "question": "How do I run it?"}
+            ))
+
+        end = messages[-1]
+        assert end["type"] == MessageType.STREAM_END
+        # Only cited parts are listed; [9] was not in the context
+        assert end["citations"] == {"2": {"path": "/b.md", "heading": "Run"}}
+
+    def test_handle_query_stream_checks_grounding_when_asked(self):
+        """Test that with verify the stream end carries the grounding check."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa:
+
+            mock_answerer = MagicMock()
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md"])
+            mock_answerer.stream_with_context.side_effect = lambda *args: iter(
+                [("Run it. ", None), ("It is free.", None), ("", ["/a.md"])]
+            )
+            mock_answerer.last_usage = None
+            mock_answerer.citations = {}
+            mock_answerer.check_grounding.return_value = (0.5, ["It is free."])
+            mock_qa.return_value = mock_answerer
+
+            handler = QueryHandler(index_manager)
+            query = {"type": MessageType.QUERY, "question": "How do I run it?"}
+            verified = list(handler.handle_query_stream({**query, "verify": True}))
+            unverified = list(handler.handle_query_stream(query))
+
+        assert verified[-1]["grounding"] == {"score": 0.5, "unsupported": ["It is free."]}
+        mock_answerer.check_grounding.assert_called_once_with(
+            "Run it. It is free.", "Retrieved context"
+        )
+        assert "grounding" not in unverified[-1]
+
+    def test_handle_query_stream_streams_candidate_answers(self):
+        """Test that alternative answers follow the answer, tagged by candidate."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa:
+
+            mock_answerer = MagicMock()
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md"])
+            answers = iter([[("Run it [1].", None)], [("Start it.", None)]])
+            mock_answerer.stream_with_context.side_effect = lambda *args: iter(
+                next(answers) + [("", ["/a.md"])]
+            )
+            mock_answerer.last_usage = {"prompt_tokens": 10, "completion_tokens": 3, "model": "m"}
+            mock_answerer.citations = {"1": "/a.md"}
+            mock_qa.return_value = mock_answerer
+
+            handler = QueryHandler(index_manager)
+            messages = list(handler.handle_query_stream(
+                {"type": MessageType.QUERY, "question": "How do I run it?", "candidates": 2}
+            ))
+
+        chunks = [m for m in messages if m["type"] == MessageType.STREAM_CHUNK]
+        assert chunks == [
+            {"type": MessageType.STREAM_CHUNK, "chunk": "Run it [1].", "candidate": 0},
+            {"type": MessageType.STREAM_CHUNK, "chunk": "Start it.", "candidate": 1},
+        ]
+        end = messages[-1]
+        assert end["type"] == MessageType.STREAM_END
+        assert end["citations"] == {"1": "/a.md"}
+        assert end["usage"] == {"prompt_tokens": 20, "completion_tokens": 6, "model": "m"}
+
+    def test_handle_query_stream_rejects_invalid_candidates(self):
+        """Test that candidates must be a positive integer."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        handler = QueryHandler(index_manager)
+
+        for candidates in (0, "2", True):
+            assert list(handler.handle_query_stream(
+                {"type": MessageType.QUERY, "question": "What?", "candidates": candidates}
+            )) == [{
+                "type": MessageType.ERROR,
+                "message": "Field 'candidates' must be a positive integer",
+            }]
+
+    def test_handle_query_stream_translates_before_retrieval(self):
+        """Test that with docs_language the question is retrieved in it."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa:
+
+            mock_answerer = MagicMock()
+            mock_answerer.translate.return_value = ("How do I log in?", "de")
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md"])
+            mock_answerer.stream_with_context.return_value = iter(
+                [("Antwort", None), ("", ["/a.md"])]
+            )
+            mock_answerer.last_usage = None
+            mock_qa.return_value = mock_answerer
+
+            handler = QueryHandler(index_manager, docs_language="en")
+            messages = list(handler.handle_query_stream(
+                {"type": MessageType.QUERY, "question": "Wie melde ich mich an?"}
+            ))
+
+        assert [m.get("status") for m in messages[:2]] == ["translating", "retrieving"]
+        mock_answerer.translate.assert_called_once_with("Wie melde ich mich an?", "en")
+        mock_answerer.retrieve.assert_called_once_with("How do I log in?")
+        assert mock_answerer.answer_language == "de"
+        # The answer is generated for the question as it was asked
+        assert mock_answerer.stream_with_context.call_args.args[0] == "Wie melde ich mich an?"
+
+    def test_handle_query_retrieves_untranslated_when_translation_fails(self):
+        """Test that a failed translation does not fail the query."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa:
+
+            mock_answerer = MagicMock()
+            mock_answerer.translate.side_effect = RuntimeError("Failed to translate")
+            mock_answerer.retrieve.return_value = ("Retrieved context", ["/a.md"])
+            mock_answerer._generate_answer.return_value = "Antwort"
+            mock_qa.return_value = mock_answerer
+
+            handler = QueryHandler(index_manager, docs_language="en")
+            response = handler.handle_query(
+                {"type": MessageType.QUERY, "question": "Wie melde ich mich an?"}
+            )
+
+        assert response["type"] == MessageType.RESPONSE
+        mock_answerer.retrieve.assert_called_once_with("Wie melde ich mich an?")
+
+    def test_handle_query_reports_rate_limit_code(self):
+        """Test that an API rate limit (HTTP 429) is reported as rate_limited."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        index_manager.get_index.return_value = MagicMock()
+
+        class RateLimitError(Exception):
+            status_code = 429
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa:
+            mock_qa.return_value.retrieve.side_effect = RateLimitError("Too many requests")
+
+            handler = QueryHandler(index_manager)
+            response = handler.handle_query({"type": MessageType.QUERY, "question": "Test?"})
+
+        assert response["type"] == MessageType.ERROR
+        assert response["code"] == ErrorCode.RATE_LIMITED
+
+    def test_handle_query_rejects_unknown_preference(self):
+        """Test that a prefer value other than code, prose or auto is an error."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        handler = QueryHandler(index_manager)
+        message = {"type": MessageType.QUERY, "question": "What?", "prefer": "tables"}
+        error = {
+            "type": MessageType.ERROR,
+            "message": "Field 'prefer' must be \"code\", \"prose\" or \"auto\"",
+        }
+
+        assert handler.handle_query(message) == error
+        assert list(handler.handle_query_stream(message)) == [error]
+        index_manager.get_index.assert_not_called()
+
+    def test_handle_suggest_followups_shows_the_sources_to_the_llm(self):
+        """Test that follow-ups are suggested from the answer's sources only."""
+        index_manager = MagicMock(spec=IndexManager)
+        vector_store = MagicMock()
+        vector_store.texts = ["A1", "B1", "A2", "A3"]
+        vector_store.metadata = [
+            {"file_path": "/a.md"},
+            {"file_path": "/b.md"},
+            {"file_path": "/a.md"},
+            {"file_path": "/a.md"},
+        ]
+        index_manager.get_index.return_value = vector_store
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine"), \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa:
+            mock_answerer = mock_qa.return_value
+            mock_answerer._source_header.side_effect = lambda m: f"Source: {m['file_path']}"
+            mock_answerer.suggest_followups.return_value = ["How do I upgrade?"]
+
+            handler = QueryHandler(index_manager)
+            response = handler.handle_suggest_followups({
+                "type": MessageType.SUGGEST_FOLLOWUPS,
+                "question": "How do I install it?",
+                "answer": "Run the installer.",
+                "sources": ["/a.md"],
+                "count": 50,
+            })
+
+        assert response == {"type": MessageType.FOLLOWUPS, "questions": ["How do I upgrade?"]}
+        question, answer, context, count = mock_answerer.suggest_followups.call_args.args
+        assert (question, answer, count) == ("How do I install it?", "Run the installer.", 10)
+        assert context == "Source: /a.md\nA1\n\n---\n\nSource: /a.md\nA2"
+
+    def test_handle_suggest_followups_validates_the_request(self):
+        """Test that a malformed request is an error, without calling the LLM."""
+        index_manager = MagicMock(spec=IndexManager)
+        handler = QueryHandler(index_manager)
+
+        response = handler.handle_suggest_followups(
+            {"type": MessageType.SUGGEST_FOLLOWUPS, "question": "Why?", "answer": "So.", "count": 0}
+        )
+
+        assert response == {
+            "type": MessageType.ERROR,
+            "message": "Field 'count' must be a positive integer",
+        }
+        index_manager.get_index.assert_not_called()
+
+    def test_handle_warmup_retrieves_without_llm(self):
+        """Test that a warm-up runs one retrieval and never asks the LLM."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = True
+        vector_store = MagicMock()
+        index_manager.get_index.return_value = vector_store
+
+        with patch("markdown_qa.query_handler.EmbeddingGenerator"), \
+             patch("markdown_qa.query_handler.RetrievalEngine") as mock_ret, \
+             patch("markdown_qa.query_handler.QuestionAnswerer") as mock_qa:
+
+            handler = QueryHandler(index_manager)
+            response = handler.handle_warmup()
+
+            assert response == {
+                "type": MessageType.STATUS,
+                "status": "ready",
+                "message": "Warm-up complete",
+            }
+            assert mock_ret.call_args.args[0] is vector_store
+            mock_ret.return_value.retrieve.assert_called_once()
+            mock_qa.assert_not_called()
+
+    def test_handle_warmup_when_not_ready(self):
+        """Test that a warm-up before the index is loaded is an error."""
+        index_manager = MagicMock(spec=IndexManager)
+        index_manager.is_ready.return_value = False
+
+        response = QueryHandler(index_manager).handle_warmup()
+
+        assert response["type"] == MessageType.ERROR
+        assert "not ready" in response["message"].lower()
@@ -0,0 +1,50 @@
+"""Tests for the limits on API requests."""
+
+import threading
+import time
+
+from markdown_qa.rate_limit import RequestLimiter, limiter_for
+
+
+class TestRequestLimiter:
+    """Test requests in flight and per minute."""
+
+    def test_requests_in_flight_are_capped(self):
+        """Test that at most max_concurrent requests hold a slot at once."""
+        limiter = RequestLimiter(max_concurrent=2)
+        lock = threading.Lock()
+        counts = {"in_flight": 0, "most": 0}
+
+        def request():
+            with limiter.request():
+                with lock:
+                    counts["in_flight"] += 1
+                    counts["most"] = max(counts["most"], counts["in_flight"])
+                time.sleep(0.05)
+                with lock:
+                    counts["in_flight"] -= 1
+
+        threads = [threading.Thread(target=request) for _ in range(5)]
+        for thread in threads:
+            thread.start()
+        for thread in threads:
+            thread.join()
+
+        assert counts["most"] == 2
+
+    def test_requests_are_spread_over_the_minute(self):
+        """Test that per_minute spaces the starts of requests evenly."""
+        limiter = RequestLimiter(per_minute=1200)
+        started = time.monotonic()
+        for _ in range(3):
+            with limiter.request():
+                pass
+        # 1200 a minute is one every 50 ms; the first starts right away
+        assert time.monotonic() - started >= 0.1
+
+    def test_callers_of_one_api_share_its_limiter(self):
+        """Test that limiters are shared by API and limits."""
+        limiter = limiter_for("https://api.example.com/v1", 4, None)
+        assert limiter_for("https://api.example.com/v1", 4, None) is limiter
+        assert limiter_for("https://api.example.com/v1", 2, None) is not limiter
+        assert limiter_for("https://other.example.com/v1", 4, None) is not limiter
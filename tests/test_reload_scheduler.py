@@ -1,11 +1,13 @@
 """Tests for periodic reload scheduler."""
 
+import threading
 import time
+from datetime import datetime
 from unittest.mock import MagicMock
 
 import pytest
 
-from markdown_qa.reload_scheduler import ReloadScheduler
+from markdown_qa.reload_scheduler import ReloadScheduler, parse_window
 
 
 class TestReloadScheduler:
@@ -68,3 +70,66 @@ class TestReloadScheduler:
         assert thread1 == thread2
 
         scheduler.stop()
+
+    def test_trigger_reloads_now_once(self):
+        """Test that trigger starts one reload at a time, outside the schedule."""
+        started = threading.Event()
+        release = threading.Event()
+
+        def slow_reload():
+            started.set()
+            release.wait(5)
+
+        scheduler = ReloadScheduler(slow_reload, interval=3600)
+
+        assert scheduler.trigger()
+        assert started.wait(5)
+        assert scheduler.is_reloading()
+        assert not scheduler.trigger()
+
+        release.set()
+        for _ in range(100):
+            if not scheduler.is_reloading():
+                break
+            time.sleep(0.01)
+        assert not scheduler.is_reloading()
+
+    def test_reloads_only_in_windows_or_on_ac_power(self):
+        """Test that scheduled reloads wait for a window, or AC power if allowed."""
+        power = MagicMock(return_value=True)
+        scheduler = ReloadScheduler(
+            MagicMock(), windows=[parse_window("22:00-07:00")], power_check=power
+        )
+
+        assert scheduler.may_reload(datetime(2026, 1, 5, 23, 30))
+        assert scheduler.may_reload(datetime(2026, 1, 5, 6, 59))
+        assert not scheduler.may_reload(datetime(2026, 1, 5, 7, 0))
+        assert not scheduler.may_reload(datetime(2026, 1, 5, 14, 0))
+        power.assert_not_called()
+
+        scheduler.reload_on_ac_power = True
+        assert scheduler.may_reload(datetime(2026, 1, 5, 14, 0))
+        power.return_value = None
+        assert not scheduler.may_reload(datetime(2026, 1, 5, 14, 0))
+
+        # Without windows, any time will do
+        assert ReloadScheduler(MagicMock()).may_reload(datetime(2026, 1, 5, 14, 0))
+
+    def test_scheduled_reloads_skip_outside_windows_but_trigger_does_not(self):
+        """Test that the schedule waits for a window while trigger overrides it."""
+        reload_func = MagicMock()
+        now = datetime.now().time()
+        closed = f"{(now.hour + 2) % 24:02d}:00-{(now.hour + 3) % 24:02d}:00"
+        scheduler = ReloadScheduler(reload_func, interval=1, windows=[parse_window(closed)])
+
+        scheduler.start()
+        time.sleep(1.5)
+        scheduler.stop()
+        reload_func.assert_not_called()
+
+        assert scheduler.trigger()
+        for _ in range(100):
+            if reload_func.called:
+                break
+            time.sleep(0.01)
+        reload_func.assert_called_once()
@@ -0,0 +1,88 @@
+"""Tests for reranking retrieved chunks with a rerank API."""
+
+import json
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.rerank import DEFAULT_RERANK_CANDIDATES, RerankConfig, Reranker
+from markdown_qa.retrieval import RetrievalEngine
+
+
+def _reply(body: dict) -> MagicMock:  # type: ignore[type-arg]
+    """A urlopen result whose body is ``body`` as JSON."""
+    response = MagicMock()
+    response.__enter__.return_value.read.return_value = json.dumps(body).encode()
+    return response
+
+
+RESULTS = [
+    ("install", {"file_path": "/docs/install.md"}, 0.1),
+    ("faq", {"file_path": "/docs/faq.md"}, 0.2),
+    ("upgrade", {"file_path": "/docs/upgrade.md"}, 0.3),
+]
+
+
+def test_rerank_config_from_dict():
+    """Test reading retrieval.rerank, with defaults and validation."""
+    assert RerankConfig.from_dict(None) is None
+    config = RerankConfig.from_dict(
+        {"url": "https://api.cohere.com/v2/rerank", "model": "rerank-v3.5", "api_key": "k"}
+    )
+    assert config == RerankConfig(
+        url="https://api.cohere.com/v2/rerank", model="rerank-v3.5", api_key="k"
+    )
+    assert config.candidates == DEFAULT_RERANK_CANDIDATES
+
+    with pytest.raises(ValueError, match="retrieval.rerank.model"):
+        RerankConfig.from_dict({"url": "https://example.com/rerank"})
+    with pytest.raises(ValueError, match="retrieval.rerank.candidates"):
+        RerankConfig.from_dict({"url": "https://x/rerank", "model": "m", "candidates": 0})
+
+
+def test_rerank_orders_chunks_by_relevance_score():
+    """Test the request sent and that chunks come back most relevant first."""
+    reranker = Reranker(
+        RerankConfig(url="https://api.cohere.com/v2/rerank", model="rerank-v3.5", api_key="k")
+    )
+    reply = {"results": [{"index": 2, "relevance_score": 0.9}, {"index": 0, "relevance_score": 0.4}]}
+
+    with patch("markdown_qa.rerank.urllib.request.urlopen", return_value=_reply(reply)) as urlopen:
+        reranked = reranker.rerank("how do I upgrade?", RESULTS)
+
+    # Chunks the API left out go last, in retrieval order; distances are kept
+    assert reranked == [RESULTS[2], RESULTS[0], RESULTS[1]]
+    request = urlopen.call_args.args[0]
+    assert request.full_url == "https://api.cohere.com/v2/rerank"
+    assert request.get_header("Authorization") == "Bearer k"
+    assert json.loads(request.data) == {
+        "model": "rerank-v3.5",
+        "query": "how do I upgrade?",
+        "documents": ["install", "faq", "upgrade"],
+    }
+
+
+def test_rerank_reads_voyage_replies_and_keeps_order_on_failure():
+    """Test Voyage's "data" list, and that a failing API changes nothing."""
+    reranker = Reranker(RerankConfig(url="https://api.voyageai.com/v1/rerank", model="rerank-2"))
+    reply = {"data": [{"index": 1, "relevance_score": 0.8}, {"index": 0, "relevance_score": 0.1}]}
+
+    with patch("markdown_qa.rerank.urllib.request.urlopen", return_value=_reply(reply)):
+        assert reranker.rerank("faq?", RESULTS)[0] == RESULTS[1]
+    with patch("markdown_qa.rerank.urllib.request.urlopen", side_effect=OSError("timed out")):
+        assert reranker.rerank("faq?", RESULTS) == RESULTS
+
+
+def test_retrieve_reranks_the_candidates_before_taking_k():
+    """Test that retrieval searches the reranker's candidates and keeps the top k."""
+    vector_store = MagicMock()
+    vector_store.search.return_value = RESULTS
+    reranker = MagicMock()
+    reranker.candidates = 3
+    reranker.rerank.side_effect = lambda query, results: list(reversed(results))
+    engine = RetrievalEngine(vector_store, MagicMock(), prefer="prose", reranker=reranker)
+
+    results = engine.retrieve("how do I upgrade?", k=2)
+
+    assert [text for text, _, _ in results] == ["upgrade", "faq"]
+    reranker.rerank.assert_called_once_with("how do I upgrade?", RESULTS)
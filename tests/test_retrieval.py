@@ -0,0 +1,225 @@
+"""Tests for retrieval filters."""
+
+from pathlib import Path
+from unittest.mock import MagicMock
+
+from markdown_qa.chunk_cache import ChunkCache, neighbor_ids
+from markdown_qa.chunker import MarkdownChunker
+from markdown_qa.links import LinkGraph
+from markdown_qa.retrieval import (
+    FILTER_OVERFETCH,
+    LINK_OVERFETCH,
+    PREFER_OVERFETCH,
+    RetrievalEngine,
+    looks_like_code,
+    matches_filters,
+    nearest,
+)
+
+
+def test_matches_directory_filters_at_path_boundaries():
+    """Test that directory filters match whole path segments at any depth."""
+    metadata = {"file_path": "/repo/docs/api/auth.md"}
+
+    assert matches_filters(metadata, {"directories": ["docs/api"]})
+    assert matches_filters(metadata, {"directories": ["/repo/docs/"]})
+    assert matches_filters(metadata, {"directories": ["guides", "api"]})
+    assert not matches_filters(metadata, {"directories": ["docs/ap"]})
+    assert not matches_filters(metadata, {"directories": ["/docs"]})
+    assert not matches_filters(metadata, {"directories": ["auth.md"]})
+
+
+def test_matches_tag_and_glob_filters():
+    """Test tag and glob filters, and that every kind that is set must match."""
+    metadata = {"file_path": "/repo/docs/setup.md", "tags": ["howto", "install"]}
+
+    assert matches_filters(metadata, {"tags": ["install"]})
+    assert not matches_filters(metadata, {"tags": ["reference"]})
+    assert not matches_filters({"file_path": "/repo/a.md"}, {"tags": ["install"]})
+    assert matches_filters(metadata, {"globs": ["set*.md"]})
+    assert matches_filters(metadata, {"globs": ["docs/*.md"]})
+    assert not matches_filters(metadata, {"globs": ["*.txt"]})
+    assert matches_filters(metadata, {"tags": ["howto"], "globs": ["*.md"]})
+    assert not matches_filters(metadata, {"tags": ["howto"], "directories": ["src"]})
+    assert matches_filters(metadata, {})
+
+
+def test_retrieve_with_filters_searches_more_and_keeps_matches():
+    """Test that filtered retrieval over-fetches and trims to k matching chunks."""
+    vector_store = MagicMock()
+    vector_store.search.return_value = [
+        ("a", {"file_path": "/repo/blog/a.md"}, 0.1),
+        ("b", {"file_path": "/repo/docs/b.md"}, 0.2),
+        ("c", {"file_path": "/repo/docs/c.md"}, 0.3),
+        ("d", {"file_path": "/repo/docs/d.md"}, 0.4),
+    ]
+    engine = RetrievalEngine(
+        vector_store, MagicMock(), filters={"directories": ["docs"]}
+    )
+
+    results = engine.retrieve("question", k=2)
+
+    assert [text for text, _, _ in results] == ["b", "c"]
+    assert vector_store.search.call_args.kwargs["k"] == 2 * FILTER_OVERFETCH
+
+
+def test_retrieve_ranks_chunks_of_linked_documents_higher():
+    """Test that chunks of documents linked with the best match move up."""
+    vector_store = MagicMock()
+    vector_store.search.return_value = [
+        ("a", {"file_path": "/vault/a.md"}, 0.10),
+        ("b", {"file_path": "/vault/b.md"}, 0.20),
+        ("c", {"file_path": "/vault/c.md"}, 0.21),
+    ]
+    graph = LinkGraph({"/vault/c.md": ["/vault/a.md"]})
+    engine = RetrievalEngine(vector_store, MagicMock(), link_graph=graph)
+
+    results = engine.retrieve("question", k=2)
+
+    assert [text for text, _, _ in results] == ["a", "c"]
+    # Distances are reported as searched
+    assert results[1][2] == 0.21
+    assert vector_store.search.call_args.kwargs["k"] == 2 * LINK_OVERFETCH
+
+
+def test_retrieve_ranks_chunks_of_the_preferred_kind_higher():
+    """Test that code or prose chunks move up as the query prefers."""
+    vector_store = MagicMock()
+    vector_store.search.return_value = [
+        ("prose", {"file_path": "/docs/a.md"}, 0.10),
+        ("code", {"file_path": "/docs/a.md", "code_language": "python"}, 0.12),
+    ]
+
+    engine = RetrievalEngine(vector_store, MagicMock(), prefer="code")
+    results = engine.retrieve("how do I connect?", k=2)
+    assert [text for text, _, _ in results] == ["code", "prose"]
+    assert results[0][2] == 0.12
+    assert vector_store.search.call_args.kwargs["k"] == 2 * PREFER_OVERFETCH
+
+    engine = RetrievalEngine(vector_store, MagicMock(), prefer="prose")
+    assert [text for text, _, _ in engine.retrieve("connect()", k=2)] == ["prose", "code"]
+
+    # auto prefers code only for questions that look like code
+    engine = RetrievalEngine(vector_store, MagicMock())
+    assert [text for text, _, _ in engine.retrieve("what does connect() do?", k=2)] == [
+        "code",
+        "prose",
+    ]
+    assert [text for text, _, _ in engine.retrieve("how do I connect?", k=2)] == [
+        "prose",
+        "code",
+    ]
+    assert vector_store.search.call_args.kwargs["k"] == 2
+
+
+def test_nearest_picks_the_k_smallest_distances_in_order():
+    """Test the partial sort behind boosted rankings."""
+    distances = [0.5, 0.1, 0.9, 0.3, 0.1, 0.7]
+    assert nearest(distances, 3) == [1, 4, 3]
+    assert nearest(distances, 10) == [1, 4, 3, 0, 5, 2]
+    assert nearest(distances, 0) == []
+    assert nearest([], 3) == []
+
+    # Picks the same as a full sort on a large input
+    many = [((i * 7919) % 10007) / 10007 for i in range(5000)]
+    assert nearest(many, 20) == sorted(range(len(many)), key=lambda i: many[i])[:20]
+
+
+def test_chunk_cache_serves_repeat_lookups_and_neighbors_from_memory():
+    """Test that retrieved chunks and their neighbors are cached, and hits and misses counted."""
+    base = 7 << 16
+    chunks = {base + i: (f"chunk {i}", {"file_path": "/docs/a.md"}) for i in range(4)}
+    vector_store = MagicMock()
+    vector_store.get_chunk.side_effect = chunks.get
+    cache = ChunkCache(capacity=3)
+    engine = RetrievalEngine(vector_store, MagicMock(), chunk_cache=cache)
+
+    vector_store.search_ids.return_value = [(base + 1, 0.1)]
+    assert engine.retrieve("question", k=1) == [("chunk 1", {"file_path": "/docs/a.md"}, 0.1)]
+    # The follow-up finds the chunk after it, cached as a neighbor
+    vector_store.search_ids.return_value = [(base + 2, 0.2), (base + 1, 0.3)]
+    assert [text for text, _, _ in engine.retrieve("follow-up", k=2)] == ["chunk 2", "chunk 1"]
+
+    assert vector_store.get_chunk.call_count == 3
+    assert cache.stats() == {"size": 3, "capacity": 3, "hits": 2, "misses": 1, "hit_rate": 0.6667}
+    vector_store.search.assert_not_called()
+
+
+def test_chunk_cache_drops_the_least_recently_used_chunk():
+    """Test that a full cache drops the chunk looked up longest ago."""
+    cache = ChunkCache(capacity=2)
+    cache.put(1, "one", {})
+    cache.put(2, "two", {})
+    assert cache.get(1) == ("one", {})
+    cache.put(3, "three", {})
+
+    assert 1 in cache and 3 in cache and 2 not in cache
+    assert neighbor_ids(5 << 16) == ((5 << 16) + 1,)
+    cache.clear()
+    assert cache.stats()["size"] == 0
+
+
+def test_looks_like_code():
+    """Test the signs of a question about code."""
+    for question in [
+        "what does `retry` do?",
+        "when is open_session called",
+        "why does parseConfig fail",
+        "what raises KeyError here?",
+        "how is std::fs used",
+        "what does run() return",
+    ]:
+        assert looks_like_code(question), question
+    for question in ["how do I install it?", "What is the release policy, e.g. for fixes?"]:
+        assert not looks_like_code(question), question
+
+
+def test_code_blocks_are_chunked_with_their_language():
+    """Test that fenced code blocks become chunks of their own."""
+    chunker = MarkdownChunker()
+    content = (
+        "# Setup\n\nInstall it.\n\n"
+        "```Python title=x\nimport app\napp.run()\n```\n\n"
+        "## Shell\n\n~~~\nmake\n~~~\n\nDone.\n"
+    )
+
+    chunks = chunker.chunk_file(Path("/repo/setup.md"), content)
+
+    prose = [chunk for chunk in chunks if "code_language" not in chunk["metadata"]]
+    code = [chunk for chunk in chunks if "code_language" in chunk["metadata"]]
+    assert all("import app" not in chunk["text"] for chunk in prose)
+    assert "Done." in "".join(chunk["text"] for chunk in prose)
+    assert [chunk["text"] for chunk in code] == [
+        "```Python title=x\nimport app\napp.run()\n```",
+        "~~~\nmake\n~~~",
+    ]
+    assert [
+        (chunk["metadata"]["code_language"], chunk["metadata"]["section"]) for chunk in code
+    ] == [("python", "Setup"), ("", "Shell")]
+
+
+def test_chunks_record_their_links():
+    """Test that chunks carry the relative links and wikilinks they contain."""
+    chunker = MarkdownChunker()
+    content = "# Index\n\nSee [setup](guide/setup.md) and [[FAQ|questions]].\n"
+
+    chunks = chunker.chunk_file(Path("/vault/index.md"), content)
+
+    assert chunks[0]["metadata"]["links"] == ["/vault/guide/setup.md"]
+    assert chunks[0]["metadata"]["wikilinks"] == ["FAQ"]
+    chunks = chunker.chunk_file(Path("/vault/plain.md"), "# Plain\n\nNo links.\n")
+    assert "links" not in chunks[0]["metadata"]
+
+
+def test_chunks_carry_front_matter_tags():
+    """Test that tags from a file's front matter are added to its chunks."""
+    chunker = MarkdownChunker()
+    content = "---\ntitle: Setup\ntags: [howto, install]\n---\n# Setup\n\nRun it.\n"
+
+    chunks = chunker.chunk_file(Path("/repo/setup.md"), content)
+    assert chunks
+    assert all(chunk["metadata"]["tags"] == ["howto", "install"] for chunk in chunks)
+
+    chunks = chunker.chunk_file(Path("/repo/plain.md"), "# Plain\n\nNo tags.\n")
+    assert all("tags" not in chunk["metadata"] for chunk in chunks)
+    assert chunker._extract_front_matter_tags("---\ntags: a, b\n---\n") == ["a", "b"]
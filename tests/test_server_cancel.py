@@ -0,0 +1,102 @@
+"""Tests for cancelling a running query with a cancel message."""
+
+import asyncio
+import json
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+    })()
+
+
+class _FakeWebSocket:
+    """Feeds client messages to the server and records its replies."""
+
+    def __init__(self) -> None:
+        self.incoming: asyncio.Queue = asyncio.Queue()  # type: ignore[type-arg]
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+        self.chunk_sent = asyncio.Event()
+
+    def __aiter__(self) -> "_FakeWebSocket":
+        return self
+
+    async def __anext__(self) -> str:
+        message = await self.incoming.get()
+        if message is None:
+            raise StopAsyncIteration
+        return message
+
+    async def send(self, message: str) -> None:
+        data = json.loads(message)
+        self.sent.append(data)
+        if data["type"] == "stream_chunk":
+            self.chunk_sent.set()
+        await asyncio.sleep(0)
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+@pytest.mark.asyncio
+async def test_cancel_stops_the_running_query():
+    """A cancel message for the running query ends its stream with an error."""
+    config = ServerConfig(directories=[], api_config=_mock_api_config())
+    server = MarkdownQAServer(config)
+
+    def long_answer(message):  # type: ignore[no-untyped-def]
+        yield {"type": "stream_start"}
+        for _ in range(1000):
+            yield {"type": "stream_chunk", "chunk": "word "}
+        yield {"type": "stream_end", "sources": []}
+
+    ws = _FakeWebSocket()
+    with patch.object(server.query_handler, "handle_query_stream", side_effect=long_answer):
+        handler = asyncio.create_task(server._handle_client(ws))  # type: ignore[arg-type]
+        await ws.incoming.put(json.dumps({"type": "query", "id": 7, "question": "Why?"}))
+        await ws.chunk_sent.wait()
+        await ws.incoming.put(json.dumps({"type": "cancel", "request_id": 7}))
+        while ws.sent[-1]["type"] != "error":
+            await asyncio.sleep(0)
+        await ws.incoming.put(None)
+        await handler
+
+    assert ws.sent[-1] == {"type": "error", "message": "Query cancelled", "id": 7}
+    assert all(m["type"] != "stream_end" for m in ws.sent)
+    assert len(ws.sent) < 1000
+
+
+@pytest.mark.asyncio
+async def test_cancel_for_another_query_is_ignored():
+    """A cancel naming a query that is not running leaves the answer alone."""
+    config = ServerConfig(directories=[], api_config=_mock_api_config())
+    server = MarkdownQAServer(config)
+
+    def answer(message):  # type: ignore[no-untyped-def]
+        yield {"type": "stream_chunk", "chunk": "Hello"}
+        yield {"type": "stream_end", "sources": []}
+
+    ws = _FakeWebSocket()
+    with patch.object(server.query_handler, "handle_query_stream", side_effect=answer):
+        handler = asyncio.create_task(server._handle_client(ws))  # type: ignore[arg-type]
+        await ws.incoming.put(json.dumps({"type": "query", "id": 8, "question": "Why?"}))
+        await ws.incoming.put(json.dumps({"type": "cancel", "request_id": 3}))
+        while not ws.sent or ws.sent[-1]["type"] != "stream_end":
+            await asyncio.sleep(0)
+        await ws.incoming.put(None)
+        await handler
+
+    assert [m["type"] for m in ws.sent] == ["stream_chunk", "stream_end"]
@@ -0,0 +1,76 @@
+"""Tests for the capabilities handshake message."""
+
+import json
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa import wire
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+@pytest.mark.asyncio
+async def test_capabilities_lists_server_features():
+    """A capabilities message is answered with the supported features."""
+    config = ServerConfig(directories=[], api_config=_mock_api_config())
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "capabilities"})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {
+            "type": "capabilities",
+            "features": ["cancel", "history", "compression", "logs", "list_indexes"],
+        }
+    ]
+
+
+@pytest.mark.asyncio
+async def test_capabilities_picks_an_offered_encoding():
+    """An offered binary encoding is picked only if the server can write it."""
+    config = ServerConfig(directories=[], api_config=_mock_api_config())
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    with patch.object(wire, "available_encodings", return_value=[]):
+        await server._process_message(  # type: ignore[arg-type]
+            ws, {"type": "capabilities", "encodings": ["msgpack"]}
+        )
+    assert "encoding" not in ws.sent[0]
+    assert ws not in server._encodings
+
+    with patch.object(wire, "available_encodings", return_value=["cbor"]):
+        await server._process_message(  # type: ignore[arg-type]
+            ws, {"type": "capabilities", "encodings": ["msgpack", "cbor"]}
+        )
+    # The reply itself is still a JSON text frame
+    assert ws.sent[1]["encoding"] == "cbor"
+    assert server._encodings[ws] == "cbor"
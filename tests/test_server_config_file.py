@@ -2,11 +2,13 @@
 
 import os
 import tempfile
+from datetime import time as dtime
 from pathlib import Path
 from unittest.mock import patch
 
 import pytest
 
+from markdown_qa.chunker import ChunkSettings
 from markdown_qa.config import APIConfig
 from markdown_qa.server_config import ServerConfig
 
@@ -90,6 +92,9 @@ server:
     - "{}"
   reload_interval: 600
   index_name: "custom"
+  max_file_size_mb: 512
+  docs_language: "en"
+  index_drafts: true
 """.format(
                     str(doc_dir)
                 )
@@ -102,6 +107,136 @@ server:
             assert config.directories == [str(doc_dir)]
             assert config.reload_interval == 600
             assert config.index_name == "custom"
+            assert config.max_file_size_mb == 512
+            assert config.docs_language == "en"
+            assert config.index_drafts is True
+
+    def test_load_retrieval_chunking_with_directory_overrides(self):
+        """Test that directory overrides of retrieval.chunking inherit what they don't set."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            doc_dir = Path(tmpdir) / "docs"
+            cjk_dir = doc_dir / "zh"
+            cjk_dir.mkdir(parents=True)
+
+            config_file = Path(tmpdir) / "config.yaml"
+            config_file.write_text(
+                """
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+server:
+  directories:
+    - "{0}"
+retrieval:
+  chunking:
+    target_tokens: 400
+    heading_level: 2
+    directories:
+      "{1}":
+        target_tokens: 150
+        overlap_tokens: 20
+""".format(
+                    str(doc_dir), str(cjk_dir)
+                )
+            )
+
+            api_config = APIConfig(config_file=config_file)
+            config = ServerConfig(config_file=config_file, api_config=api_config)
+
+            assert config.chunking.settings_for(doc_dir / "a.md") == ChunkSettings(
+                target_tokens=400, heading_level=2
+            )
+            assert config.chunking.settings_for(cjk_dir / "guide" / "b.md") == ChunkSettings(
+                target_tokens=150, overlap_tokens=20, heading_level=2
+            )
+
+    def test_invalid_retrieval_chunking_is_rejected(self):
+        """Test that an overlap as large as the chunk size is an error."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            config_file = Path(tmpdir) / "config.yaml"
+            config_file.write_text(
+                """
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+retrieval:
+  chunking:
+    target_tokens: 100
+    overlap_tokens: 100
+"""
+            )
+
+            api_config = APIConfig(config_file=config_file)
+            with pytest.raises(ValueError, match="overlap_tokens"):
+                ServerConfig(config_file=config_file, api_config=api_config)
+
+    def test_load_reload_windows(self):
+        """Test reading reload windows, and that a malformed one is an error."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            config_file = Path(tmpdir) / "config.yaml"
+            config_file.write_text(
+                """
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+server:
+  reload_windows: ["22:00-07:00", "12:00-13:30"]
+  reload_on_ac_power: true
+"""
+            )
+
+            api_config = APIConfig(config_file=config_file)
+            config = ServerConfig(config_file=config_file, api_config=api_config)
+
+            assert config.reload_windows == [
+                (dtime(22, 0), dtime(7, 0)),
+                (dtime(12, 0), dtime(13, 30)),
+            ]
+            assert config.reload_on_ac_power is True
+
+            config_file.write_text(
+                """
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+server:
+  reload_windows: ["25:00-07:00"]
+"""
+            )
+            with pytest.raises(ValueError, match="Invalid reload window"):
+                ServerConfig(config_file=config_file, api_config=api_config)
+
+    def test_load_formats(self):
+        """Test reading the enabled formats, and that an unknown one is an error."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            config_file = Path(tmpdir) / "config.yaml"
+            config_file.write_text(
+                """
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+server:
+  formats: [html, PDF]
+"""
+            )
+
+            api_config = APIConfig(config_file=config_file)
+            config = ServerConfig(config_file=config_file, api_config=api_config)
+
+            assert config.formats == ["html", "pdf"]
+            assert config.extensions == (".md", ".html", ".htm", ".pdf")
+
+            config_file.write_text(
+                """
+api:
+  base_url: "https://api.example.com/v1"
+  api_key: "test-key"
+server:
+  formats: [docx]
+"""
+            )
+            with pytest.raises(ValueError, match="Unknown format"):
+                ServerConfig(config_file=config_file, api_config=api_config)
 
     def test_cli_args_override_config_file(self):
         """Test that CLI arguments override config file values."""
@@ -193,6 +193,38 @@ class TestServerConfigReload:
                 assert "port" in result.changed
                 assert result.requires_restart is True
 
+    def test_reload_encryption_requires_restart(self):
+        """Test that turning on encryption requires restart."""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            config_dir = Path(tmpdir)
+            config_file = config_dir / "config.yaml"
+            doc_dir = Path(tmpdir) / "docs"
+            doc_dir.mkdir()
+
+            def write_config(server):
+                with open(config_file, "w") as f:
+                    yaml.dump({
+                        "api": {"base_url": "https://api.example.com/v1", "api_key": "test-key"},
+                        "server": {"directories": [str(doc_dir)], **server},
+                    }, f)
+
+            write_config({})
+            with patch("markdown_qa.server_config.ServerConfig.DEFAULT_CONFIG_DIR", config_dir), \
+                 patch("markdown_qa.server_config.ServerConfig.DEFAULT_CONFIG_YAML", config_file):
+                api_config = APIConfig(config_file=config_file)
+                config = ServerConfig(config_file=config_file, api_config=api_config)
+                assert config.encryption == "off"
+
+                write_config({"encryption": "keyring"})
+                result = config.reload(preserve_cli_overrides=False)
+                assert "encryption" in result.changed
+                assert result.requires_restart is True
+                assert config.encryption == "keyring"
+
+                write_config({"encryption": "rot13"})
+                with pytest.raises(ValueError, match="server.encryption"):
+                    config.reload(preserve_cli_overrides=False)
+
     def test_reload_preserves_cli_overrides(self):
         """Test that CLI overrides are preserved when reloading."""
         with tempfile.TemporaryDirectory() as tmpdir:
@@ -0,0 +1,139 @@
+"""Tests for the list_documents and get_chunks messages."""
+
+import json
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.cache import CacheManager
+from markdown_qa.manifest import Manifest
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+        "embedding_model": "text-embedding-3-small",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+def _server_with_index(tmp_path) -> MarkdownQAServer:
+    """A server with a loaded index of three documents."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    manager = server.index_manager
+    manager.cache_manager = CacheManager(tmp_path / "cache")
+    manager.manifest = Manifest(manager.cache_manager.get_manifest_path())
+    manager.manifest.add_index("docs", [str(tmp_path)])
+    manager.manifest.set_file_metadata(
+        "docs", "/docs/b.md", {"mtime": 0, "chunk_ids": [1, 2], "indexed_at": 1700000000}
+    )
+    index = MagicMock()
+    index.metadata = [
+        {"file_path": "/docs/b.md", "section": "Install"},
+        {"file_path": "/docs/c.md", "section": ""},
+        {"file_path": "/docs/b.md", "section": "Upgrade"},
+        {"source": "/docs/a.md"},
+    ]
+    index.texts = ["# Install\nRun it.", "Notes.", "# Upgrade\nRun it again.", "Old."]
+    manager.swap_index(index)
+    return server
+
+
+@pytest.mark.asyncio
+async def test_list_documents_pages_through_documents_by_path(tmp_path):
+    """Documents are listed once, with their chunk counts and indexing time."""
+    server = _server_with_index(tmp_path)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "list_documents", "page": 0, "page_size": 2})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "list_documents", "index": "docs", "page": 1, "page_size": 2})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {
+            "type": "documents",
+            "index": "docs",
+            "page": 0,
+            "total": 3,
+            "documents": [
+                {"path": "/docs/a.md", "chunks": 1},
+                {"path": "/docs/b.md", "chunks": 2, "indexed_at": 1700000000},
+            ],
+        },
+        {
+            "type": "documents",
+            "index": "docs",
+            "page": 1,
+            "total": 3,
+            "documents": [{"path": "/docs/c.md", "chunks": 1}],
+        },
+    ]
+
+
+@pytest.mark.asyncio
+async def test_list_documents_rejects_bad_requests(tmp_path):
+    """The index must be the server's and the page fields valid integers."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "list_documents", "page": 0, "page_size": 10})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "list_documents", "index": "other", "page": 0, "page_size": 10})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "list_documents", "page": -1, "page_size": 10})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "list_documents", "page": 0, "page_size": 0})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {"type": "error", "message": "Index not loaded", "code": "index_not_ready"},
+        {"type": "error", "message": "Unknown index: other"},
+        {"type": "error", "message": "Field 'page' must be a non-negative integer"},
+        {"type": "error", "message": "Field 'page_size' must be a positive integer"},
+    ]
+
+
+@pytest.mark.asyncio
+async def test_get_chunks_returns_a_documents_chunks_in_order(tmp_path):
+    """Chunks come with the heading they fall under, when there is one."""
+    server = _server_with_index(tmp_path)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "get_chunks", "path": "/docs/b.md"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "get_chunks", "path": "/docs/c.md"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "get_chunks", "path": "/docs/x.md"})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {
+            "type": "chunks",
+            "path": "/docs/b.md",
+            "chunks": [
+                {"text": "# Install\nRun it.", "heading": "Install"},
+                {"text": "# Upgrade\nRun it again.", "heading": "Upgrade"},
+            ],
+        },
+        {"type": "chunks", "path": "/docs/c.md", "chunks": [{"text": "Notes."}]},
+        {"type": "error", "message": "Document not indexed: /docs/x.md"},
+    ]
@@ -0,0 +1,111 @@
+"""Tests for the index_plan message."""
+
+import json
+import os
+import time
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+        "embedding_model": "text-embedding-3-small",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+@pytest.mark.asyncio
+async def test_index_plan_lists_indexed_and_ignored_files(tmp_path):
+    """The plan applies the directory's ignore files without indexing."""
+    (tmp_path / "node_modules" / "pkg").mkdir(parents=True)
+    (tmp_path / "node_modules" / "pkg" / "README.md").write_text("# Vendored\n")
+    (tmp_path / "guide.md").write_text("# Guide\n")
+    (tmp_path / ".gitignore").write_text("node_modules\n")
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_plan"})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {
+            "type": "index_plan",
+            "index": "docs",
+            "files": [str(tmp_path / "guide.md")],
+            "ignored": [str(tmp_path / "node_modules" / "pkg" / "README.md")],
+        }
+    ]
+
+
+@pytest.mark.asyncio
+async def test_index_plan_estimates_what_a_reload_would_embed(tmp_path):
+    """With estimate, the plan also counts the uncached chunks and tokens to embed."""
+    guide = tmp_path / "guide.md"
+    guide.write_text("# Guide\n\nInstall the package, then run it.\n")
+    # Files written in the last moments count as being edited and are skipped
+    os.utime(guide, (time.time() - 60, time.time() - 60))
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    with patch("markdown_qa.index_manager.EmbeddingGenerator") as mock_generator:
+        generator = mock_generator.return_value
+        generator.embedding_model = "text-embedding-3-small"
+        generator.batch_size = 10
+        generator.concurrency = 1
+        generator.limiter.per_minute = None
+        generator.is_cached.return_value = False
+        await server._process_message(ws, {"type": "index_plan", "estimate": True})  # type: ignore[arg-type]
+
+    estimate = ws.sent[0]["estimate"]
+    # No index is loaded, so a reload builds it from every file
+    assert estimate["full_rebuild"] is True
+    assert estimate["embedding_model"] == "text-embedding-3-small"
+    assert [file["path"] for file in estimate["files"]] == [str(guide)]
+    assert estimate["chunks"] == estimate["files"][0]["chunks"] >= 1
+    assert estimate["tokens"] == estimate["files"][0]["tokens"] > 0
+    assert estimate["cached_chunks"] == 0
+    assert estimate["seconds"] == 1.0
+
+
+@pytest.mark.asyncio
+async def test_index_plan_for_an_unknown_index_is_an_error(tmp_path):
+    """Only the server's own index can be planned."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_plan", "index": "other"})  # type: ignore[arg-type]
+
+    assert ws.sent[0]["type"] == "error"
+    assert "Unknown index: other" in ws.sent[0]["message"]
@@ -0,0 +1,71 @@
+"""Tests for index_progress messages sent while the server (re)indexes."""
+
+import asyncio
+import json
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+@pytest.mark.asyncio
+async def test_progress_from_the_indexing_thread_reaches_every_client():
+    """Updates close together are dropped, but not failed files or the last one."""
+    config = ServerConfig(directories=[], api_config=_mock_api_config())
+    server = MarkdownQAServer(config)
+    server._loop = asyncio.get_running_loop()
+    clients = [_RecordingWebSocket(), _RecordingWebSocket()]
+    server._connections.update(clients)
+    failed = [{"path": "/docs/b.md", "issue": "failed to read: Permission denied"}]
+
+    def index() -> None:
+        report = server.index_manager.on_progress
+        report({"done": 1, "total": 3, "path": "/docs/a.md"})
+        report({"done": 2, "total": 3, "path": "/docs/b.md", "error": failed[0]["issue"]})
+        report({"done": 3, "total": 3, "path": "/docs/c.md"})
+        report({"done": 3, "total": 3, "chunks": 5, "embedded": 5, "finished": True, "failed": failed})
+
+    await asyncio.to_thread(index)
+    for _ in range(10):
+        await asyncio.sleep(0.01)
+
+    for client in clients:
+        assert [message["done"] for message in client.sent] == [1, 2, 3]
+        assert client.sent[1]["error"] == failed[0]["issue"]
+        assert client.sent[2] == {
+            "type": "index_progress",
+            "done": 3,
+            "total": 3,
+            "chunks": 5,
+            "embedded": 5,
+            "finished": True,
+            "failed": failed,
+        }
@@ -0,0 +1,131 @@
+"""Tests for the index_stats message."""
+
+import json
+import os
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.cache import CacheManager
+from markdown_qa.manifest import Manifest
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+        "embedding_model": "text-embedding-3-small",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+@pytest.mark.asyncio
+async def test_index_stats_summarizes_the_loaded_index(tmp_path):
+    """Documents are counted once however many chunks they have."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    manager = server.index_manager
+    manager.cache_manager = CacheManager(tmp_path / "cache")
+    manager.manifest = Manifest(manager.cache_manager.get_manifest_path())
+    faiss_path, metadata_path = manager.cache_manager.get_index_path("docs")
+    faiss_path.write_bytes(b"x" * 1000)
+    metadata_path.write_bytes(b"x" * 24)
+    os.utime(faiss_path, (1700000000, 1700000000))
+    os.utime(metadata_path, (1700000100, 1700000100))
+    index = MagicMock()
+    index.metadata = [
+        {"file_path": "/docs/a.md"},
+        {"file_path": "/docs/a.md"},
+        {"source": "/docs/b.md"},
+    ]
+    manager.swap_index(index)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_stats", "index": "docs"})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {
+            "type": "index_stats",
+            "index": "docs",
+            "documents": 2,
+            "chunks": 3,
+            "embedding_model": "text-embedding-3-small",
+            "built_at": 1700000100,
+            "size_bytes": 1024,
+            "chunk_cache": {
+                "size": 0,
+                "capacity": 256,
+                "hits": 0,
+                "misses": 0,
+                "hit_rate": None,
+            },
+        }
+    ]
+
+
+@pytest.mark.asyncio
+async def test_index_stats_reports_files_with_encoding_issues(tmp_path):
+    """Files that were not UTF-8 when the index was built are listed by path."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    manager = server.index_manager
+    manager.cache_manager = CacheManager(tmp_path / "cache")
+    manager.manifest = Manifest(manager.cache_manager.get_manifest_path())
+    manager.manifest.add_index("docs", [str(tmp_path)])
+    manager.manifest.set_file_issues(
+        "docs",
+        {"/docs/b.md": "not UTF-8; decoded as GBK", "/docs/a.md": "not UTF-8; decoded as Latin-1"},
+    )
+    manager.manifest.set_file_issue("docs", "/docs/b.md", None)
+    index = MagicMock()
+    index.metadata = [{"file_path": "/docs/a.md"}]
+    manager.swap_index(index)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_stats", "index": "docs"})  # type: ignore[arg-type]
+
+    assert ws.sent[0]["issues"] == [
+        {"path": "/docs/a.md", "issue": "not UTF-8; decoded as Latin-1"}
+    ]
+
+
+@pytest.mark.asyncio
+async def test_index_stats_without_an_index_is_an_error(tmp_path):
+    """Stats are only reported for the server's own, loaded index."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_stats"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "index_stats", "index": "other"})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {"type": "error", "message": "Index not loaded", "code": "index_not_ready"},
+        {"type": "error", "message": "Unknown index: other"},
+    ]
@@ -0,0 +1,117 @@
+"""Tests for the index_verify message."""
+
+import json
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.cache import CacheManager
+from markdown_qa.manifest import Manifest
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+        "embedding_model": "text-embedding-3-small",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+def _server_with_index(tmp_path):
+    """A server whose index holds chunks of a kept, a deleted and an ignored file."""
+    docs = tmp_path / "docs"
+    docs.mkdir()
+    (docs / "kept.md").write_text("# Kept\n")
+    (docs / "draft.md").write_text("# Draft\n")
+    (docs / ".mdqaignore").write_text("draft.md\n")
+    config = ServerConfig(
+        directories=[str(docs)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    manager = server.index_manager
+    manager.cache_manager = CacheManager(tmp_path / "cache")
+    manager.manifest = Manifest(manager.cache_manager.get_manifest_path())
+    manager.manifest.add_index("docs", [str(docs)])
+    paths = [str(docs / "kept.md"), str(docs / "gone.md"), str(docs / "gone.md"), str(docs / "draft.md")]
+    for chunk_id, path in enumerate(paths):
+        manager.manifest.set_file_metadata("docs", path, {"mtime": 0, "chunk_ids": [chunk_id]})
+    index = MagicMock()
+    index.metadata = [{"file_path": path} for path in paths]
+    index.chunk_ids = [0, 1, 2, 3]
+    manager.swap_index(index)
+    return server, docs, index
+
+
+@pytest.mark.asyncio
+async def test_index_verify_reports_chunks_of_deleted_and_ignored_files(tmp_path):
+    """Orphans are listed by file, and nothing is removed without remove."""
+    server, docs, index = _server_with_index(tmp_path)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_verify"})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {
+            "type": "index_verify",
+            "index": "docs",
+            "orphans": [
+                {"path": str(docs / "draft.md"), "chunks": 1, "reason": "excluded"},
+                {"path": str(docs / "gone.md"), "chunks": 2, "reason": "missing"},
+            ],
+            "removed": False,
+        }
+    ]
+    index.remove_chunks.assert_not_called()
+
+
+@pytest.mark.asyncio
+async def test_index_verify_with_remove_drops_orphaned_chunks(tmp_path):
+    """Removing deletes the chunks, their manifest entries and saves the index."""
+    server, docs, index = _server_with_index(tmp_path)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_verify", "remove": True})  # type: ignore[arg-type]
+
+    assert ws.sent[0]["removed"] is True
+    assert sorted(index.remove_chunks.call_args[0][0]) == [1, 2, 3]
+    index.save_index.assert_called_once_with("docs")
+    manifest = server.index_manager.manifest
+    assert list(manifest.get_all_file_metadata("docs")) == [str(docs / "kept.md")]
+
+
+@pytest.mark.asyncio
+async def test_index_verify_without_an_index_is_an_error(tmp_path):
+    """Only the server's own, loaded index can be verified."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_verify"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "index_verify", "index": "other"})  # type: ignore[arg-type]
+
+    assert ws.sent[0]["type"] == "error"
+    assert ws.sent[0]["code"] == "index_not_ready"
+    assert "Unknown index: other" in ws.sent[1]["message"]
@@ -0,0 +1,96 @@
+"""Tests for the get_links message."""
+
+import json
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.links import LinkGraph
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+        "embedding_model": "text-embedding-3-small",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+@pytest.mark.asyncio
+async def test_get_links_lists_outbound_and_inbound_links(tmp_path):
+    """Links are read from the loaded index's link graph, by full or partial path."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    index = MagicMock()
+    index.link_graph.return_value = LinkGraph.from_metadata(
+        [
+            {"file_path": "/docs/a.md", "wikilinks": ["b"]},
+            {"file_path": "/docs/b.md", "links": ["/docs/c.md"]},
+            {"file_path": "/docs/c.md", "links": ["/docs/b.md"]},
+        ]
+    )
+    server.index_manager.swap_index(index)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "get_links", "path": "/docs/b.md"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "get_links", "path": "docs/a.md"})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {
+            "type": "links",
+            "path": "/docs/b.md",
+            "outbound": ["/docs/c.md"],
+            "inbound": ["/docs/a.md", "/docs/c.md"],
+        },
+        {"type": "links", "path": "/docs/a.md", "outbound": ["/docs/b.md"], "inbound": []},
+    ]
+
+
+@pytest.mark.asyncio
+async def test_get_links_of_unknown_document_is_an_error(tmp_path):
+    """Only indexed documents have links; a partial path must name just one."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "get_links", "path": "/docs/a.md"})  # type: ignore[arg-type]
+    index = MagicMock()
+    index.link_graph.return_value = LinkGraph.from_metadata(
+        [{"file_path": "/docs/a.md"}, {"file_path": "/blog/a.md"}]
+    )
+    server.index_manager.swap_index(index)
+    await server._process_message(ws, {"type": "get_links", "path": "/docs/x.md"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "get_links", "path": "a.md"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "get_links"})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {"type": "error", "message": "Index not loaded", "code": "index_not_ready"},
+        {"type": "error", "message": "Document not indexed: /docs/x.md"},
+        {"type": "error", "message": "Document not indexed: a.md"},
+        {"type": "error", "message": "Missing 'path' field"},
+    ]
@@ -0,0 +1,112 @@
+"""Tests for tailing the server log with tail_logs messages."""
+
+import asyncio
+import json
+import logging
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.logger import LogTail
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+def _tailed_logger(tail: LogTail) -> logging.Logger:
+    """A logger writing only to ``tail``."""
+    logger = logging.getLogger("test_server_logs")
+    logger.handlers = [tail]
+    logger.setLevel(logging.INFO)
+    logger.propagate = False
+    return logger
+
+
+def test_log_tail_keeps_the_latest_lines():
+    """Lines past the capacity drop the oldest; the format names the level."""
+    tail = LogTail(capacity=2)
+    logger = _tailed_logger(tail)
+    for i in range(3):
+        logger.warning(f"line {i}")
+
+    lines = tail.lines(5)
+    assert [line.split(" - ")[-1] for line in lines] == ["line 1", "line 2"]
+    assert " WARNING - " in lines[0]
+    assert tail.lines(1) == lines[1:]
+    assert tail.lines(0) == []
+
+
+@pytest.mark.asyncio
+async def test_tail_logs_sends_recent_lines_then_follows():
+    """With follow, lines logged later arrive as log messages until unfollowed."""
+    config = ServerConfig(directories=[], api_config=_mock_api_config())
+    server = MarkdownQAServer(config)
+    server.log_tail = LogTail()
+    logger = _tailed_logger(server.log_tail)
+    logger.info("Server started")
+    logger.error("Index build failed")
+    ws = _RecordingWebSocket()
+
+    await server._process_message(  # type: ignore[arg-type]
+        ws, {"type": "tail_logs", "lines": 1, "follow": True}
+    )
+    assert ws.sent[0]["type"] == "logs"
+    assert len(ws.sent[0]["lines"]) == 1
+    assert ws.sent[0]["lines"][0].endswith("ERROR - Index build failed")
+
+    logger.warning("No documents found")
+    for _ in range(10):
+        await asyncio.sleep(0)
+    assert ws.sent[1]["type"] == "log"
+    assert ws.sent[1]["line"].endswith("WARNING - No documents found")
+
+    await server._process_message(  # type: ignore[arg-type]
+        ws, {"type": "tail_logs", "lines": 0}
+    )
+    assert ws.sent[2] == {"type": "logs", "lines": []}
+    logger.info("Not followed")
+    for _ in range(10):
+        await asyncio.sleep(0)
+    assert len(ws.sent) == 3
+    assert ws not in server._log_followers
+
+
+@pytest.mark.asyncio
+async def test_tail_logs_rejects_invalid_line_counts():
+    """A line count that is not a non-negative integer is an error."""
+    config = ServerConfig(directories=[], api_config=_mock_api_config())
+    server = MarkdownQAServer(config)
+    ws = _RecordingWebSocket()
+
+    for lines in (-1, "10", True):
+        await server._process_message(  # type: ignore[arg-type]
+            ws, {"type": "tail_logs", "lines": lines, "follow": True}
+        )
+    assert [m["type"] for m in ws.sent] == ["error"] * 3
+    assert ws.sent[0]["message"] == "Field 'lines' must be a non-negative integer"
+    assert server._log_followers == {}
@@ -0,0 +1,194 @@
+"""Tests for named indexes: list_indexes, index_create, index_delete."""
+
+import json
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.cache import CacheManager
+from markdown_qa.index_manager import IndexManager
+from markdown_qa.named_indexes import REGISTRY_FILE
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+        "embedding_model": "text-embedding-3-small",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+def _fake_load_index(self, index_name, directories):
+    """Load an index of one chunk per directory instead of embedding anything."""
+    index = MagicMock()
+    index.metadata = [{"file_path": directory} for directory in directories]
+    index.chunk_ids = list(range(len(directories)))
+    self.swap_index(index)
+
+
+def _server(tmp_path):
+    """A server of the configured index "docs", its cache under tmp_path/cache."""
+    docs = tmp_path / "docs"
+    docs.mkdir(exist_ok=True)
+    config = ServerConfig(
+        directories=[str(docs)], api_config=_mock_api_config(), index_name="docs"
+    )
+    with patch.object(CacheManager, "DEFAULT_CACHE_DIR", tmp_path / "cache"):
+        return MarkdownQAServer(config)
+
+
+async def _create_notes(server, tmp_path, ws):
+    """Create the named index "notes" over tmp_path/notes and wait for its build."""
+    notes = tmp_path / "notes"
+    notes.mkdir(exist_ok=True)
+    await server._process_message(  # type: ignore[arg-type]
+        ws,
+        {
+            "type": "index_create",
+            "name": "notes",
+            "directories": [str(notes)],
+            "embedding_model": "local:bge-small",
+            "chunking": {"target_tokens": 200},
+        },
+    )
+    await server._index_builds["notes"]
+    return notes
+
+
+@pytest.mark.asyncio
+async def test_created_indexes_are_built_listed_and_kept(tmp_path):
+    """A created index has its own settings and cache, and outlives a restart."""
+    server = _server(tmp_path)
+    ws = _RecordingWebSocket()
+
+    with patch.object(IndexManager, "load_index", _fake_load_index):
+        notes = await _create_notes(server, tmp_path, ws)
+    await server._process_message(ws, {"type": "list_indexes"})  # type: ignore[arg-type]
+
+    assert ws.sent[0]["indexes"][1]["status"] == "indexing"
+    assert ws.sent[1] == {
+        "type": "indexes",
+        "indexes": [
+            {
+                "name": "docs",
+                "directories": [str(tmp_path / "docs")],
+                "embedding_model": "text-embedding-3-small",
+                "default": True,
+                "status": "not_ready",
+            },
+            {
+                "name": "notes",
+                "directories": [str(notes)],
+                "embedding_model": "local:bge-small",
+                "default": False,
+                "status": "ready",
+            },
+        ],
+    }
+    named = server.named_indexes["notes"]
+    assert named.index_manager.chunking.defaults.target_tokens == 200
+    assert named.index_manager.cache_manager.cache_dir == tmp_path / "cache" / "named" / "notes"
+    # The configured index keeps the server's embedding model
+    assert server.index_manager.api_config.embedding_model == "text-embedding-3-small"
+
+    restarted = _server(tmp_path)
+    assert list(restarted.named_indexes) == ["notes"]
+    assert restarted.named_indexes["notes"].settings.directories == [str(notes)]
+
+
+@pytest.mark.asyncio
+async def test_queries_and_index_requests_go_to_the_named_index(tmp_path):
+    """The index field of a query or an index request picks the index."""
+    server = _server(tmp_path)
+    ws = _RecordingWebSocket()
+    with patch.object(IndexManager, "load_index", _fake_load_index):
+        notes = await _create_notes(server, tmp_path, ws)
+    named = server.named_indexes["notes"]
+    named.query_handler.handle_query_stream = MagicMock(
+        return_value=iter([{"type": "stream_start"}])
+    )
+    server.query_handler.handle_query_stream = MagicMock(return_value=iter([]))
+
+    await server._process_message(  # type: ignore[arg-type]
+        ws, {"type": "query", "question": "What is new?", "index": "notes"}
+    )
+    await server._process_message(  # type: ignore[arg-type]
+        ws, {"type": "index_verify", "index": "notes"}
+    )
+    await server._process_message(  # type: ignore[arg-type]
+        ws, {"type": "index_verify", "index": "drafts"}
+    )
+
+    named.query_handler.handle_query_stream.assert_called_once()
+    server.query_handler.handle_query_stream.assert_not_called()
+    assert ws.sent[1] == {"type": "stream_start"}
+    assert ws.sent[2]["type"] == "index_verify"
+    assert ws.sent[2]["index"] == "notes"
+    assert ws.sent[2]["orphans"][0]["path"] == str(notes)
+    assert ws.sent[3]["message"] == "Unknown index: drafts"
+
+
+@pytest.mark.asyncio
+async def test_invalid_creates_are_refused(tmp_path):
+    """Names, directories and chunking are checked, and names are unique."""
+    server = _server(tmp_path)
+    ws = _RecordingWebSocket()
+    (tmp_path / "notes").mkdir()
+    notes = str(tmp_path / "notes")
+
+    for message in [
+        {"name": "../up", "directories": [notes]},
+        {"name": "notes", "directories": []},
+        {"name": "notes", "directories": [str(tmp_path / "missing")]},
+        {"name": "notes", "directories": [notes], "chunking": {"target_tokens": 0}},
+        {"name": "docs", "directories": [notes]},
+    ]:
+        await server._process_message(ws, {"type": "index_create", **message})  # type: ignore[arg-type]
+
+    assert [reply["type"] for reply in ws.sent] == ["error"] * 5
+    assert "Field 'name'" in ws.sent[0]["message"]
+    assert "Field 'directories'" in ws.sent[1]["message"]
+    assert "Not a directory" in ws.sent[2]["message"]
+    assert "target_tokens" in ws.sent[3]["message"]
+    assert ws.sent[4]["message"] == "Index docs already exists"
+    assert server.named_indexes == {}
+
+
+@pytest.mark.asyncio
+async def test_deleting_drops_the_index_and_its_files(tmp_path):
+    """A named index can be deleted with its cache; the configured one can't."""
+    server = _server(tmp_path)
+    ws = _RecordingWebSocket()
+    with patch.object(IndexManager, "load_index", _fake_load_index):
+        await _create_notes(server, tmp_path, ws)
+
+    await server._process_message(ws, {"type": "index_delete", "name": "docs"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "index_delete", "name": "drafts"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "index_delete", "name": "notes"})  # type: ignore[arg-type]
+
+    assert ws.sent[1]["message"] == "The configured index can't be deleted"
+    assert ws.sent[2]["message"] == "Unknown index: drafts"
+    assert [index["name"] for index in ws.sent[3]["indexes"]] == ["docs"]
+    assert not (tmp_path / "cache" / "named" / "notes").exists()
+    assert json.loads((tmp_path / "cache" / REGISTRY_FILE).read_text()) == {"indexes": []}
@@ -0,0 +1,103 @@
+"""Tests for notification messages sent after reindexing changed documents."""
+
+import asyncio
+import json
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.index_manager import IncrementalUpdateResult
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+async def _reload(server: MarkdownQAServer, force: bool = False) -> None:
+    """Reload on a worker thread, as the scheduler does, and let sends run."""
+    await asyncio.to_thread(server._reload_indexes, force)
+    for _ in range(10):
+        await asyncio.sleep(0.01)
+
+
+@pytest.mark.asyncio
+async def test_reload_with_changes_notifies_every_client(tmp_path):
+    """Changed and removed documents are listed; a reload without changes is quiet."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    server._loop = asyncio.get_running_loop()
+    clients = [_RecordingWebSocket(), _RecordingWebSocket()]
+    server._connections.update(clients)
+    server.index_manager = MagicMock()
+    server.index_manager.incremental_update.return_value = IncrementalUpdateResult(
+        added_files=["/docs/new.md"],
+        modified_files=["/docs/guide.md"],
+        deleted_files=["/docs/old.md"],
+    )
+    server.index_manager.verify_index.return_value = {
+        "removed": True,
+        "orphans": [{"path": "/docs/gone.md", "reason": "deleted"}],
+    }
+
+    await _reload(server)
+    server.index_manager.incremental_update.return_value = IncrementalUpdateResult()
+    server.index_manager.verify_index.return_value = {"removed": False, "orphans": []}
+    await _reload(server)
+
+    for client in clients:
+        assert client.sent == [
+            {
+                "type": "notification",
+                "documents": ["/docs/gone.md", "/docs/guide.md", "/docs/new.md", "/docs/old.md"],
+                "message": "1 added, 1 modified, 2 removed",
+            }
+        ]
+
+
+@pytest.mark.asyncio
+async def test_full_rebuild_notifies_with_every_document(tmp_path):
+    """After a forced rebuild, every indexed document may have changed."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    server._loop = asyncio.get_running_loop()
+    client = _RecordingWebSocket()
+    server._connections.add(client)
+    server.index_manager = MagicMock()
+    server.index_manager.list_documents.return_value = {
+        "total": 2,
+        "documents": [{"path": "/docs/a.md", "chunks": 1}, {"path": "/docs/b.md", "chunks": 2}],
+    }
+
+    await _reload(server, force=True)
+
+    assert client.sent == [
+        {"type": "notification", "documents": ["/docs/a.md", "/docs/b.md"], "message": "Index rebuilt"}
+    ]
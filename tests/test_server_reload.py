@@ -0,0 +1,102 @@
+"""Tests for re-indexing on request with a reload message."""
+
+import json
+import threading
+from unittest.mock import MagicMock, patch
+
+import pytest
+
+from markdown_qa.reload_scheduler import ReloadScheduler
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+
+
+def _mock_api_config() -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers():
+    """Mock loggers used by server and server config."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()):
+        yield
+
+
+@pytest.mark.asyncio
+async def test_reload_starts_reindexing(tmp_path):
+    """A reload message starts a reload once and reports it as indexing."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    reloaded = threading.Event()
+    release = threading.Event()
+
+    def reload() -> None:
+        reloaded.set()
+        release.wait(5)
+
+    server.reload_scheduler = ReloadScheduler(reload, interval=3600)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "reload", "index": "docs"})  # type: ignore[arg-type]
+    assert reloaded.wait(5)
+    await server._process_message(ws, {"type": "reload"})  # type: ignore[arg-type]
+    release.set()
+
+    assert ws.sent == [
+        {"type": "status", "status": "indexing", "message": "Reindexing started"},
+        {"type": "status", "status": "indexing", "message": "Server reloading indexes"},
+    ]
+
+
+@pytest.mark.asyncio
+async def test_reload_of_unknown_index_is_an_error(tmp_path):
+    """Only the server's own index can be reloaded."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    server.reload_scheduler = ReloadScheduler(MagicMock(), interval=3600)
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "reload", "index": "other"})  # type: ignore[arg-type]
+
+    assert ws.sent == [{"type": "error", "message": "Unknown index: other"}]
+
+
+def test_scheduled_reload_removes_orphaned_chunks(tmp_path):
+    """Each reload drops chunks of files an incremental update missed."""
+    config = ServerConfig(
+        directories=[str(tmp_path)], api_config=_mock_api_config(), index_name="docs"
+    )
+    server = MarkdownQAServer(config)
+    server.index_manager = MagicMock()
+    server.index_manager.incremental_update.return_value = MagicMock(
+        fallback_to_full_rebuild=False, has_changes=False
+    )
+    server.index_manager.verify_index.return_value = {
+        "orphans": [{"path": "/docs/gone.md", "chunks": 2, "reason": "missing"}],
+        "removed": True,
+    }
+
+    server._reload_indexes()
+
+    server.index_manager.verify_index.assert_called_once_with(
+        "docs", [str(tmp_path)], remove=True
+    )
@@ -0,0 +1,203 @@
+"""Tests for the index_snapshot and index_restore messages."""
+
+import io
+import json
+import os
+import pickle
+import tarfile
+from unittest.mock import MagicMock, patch
+
+import faiss
+import numpy as np
+import pytest
+
+from markdown_qa.cache import CacheManager
+from markdown_qa.manifest import Manifest
+from markdown_qa.server import MarkdownQAServer
+from markdown_qa.server_config import ServerConfig
+from markdown_qa.snapshot import read_snapshot, write_snapshot
+from markdown_qa.vector_store import VectorStore
+
+
+def _mock_api_config(embedding_model: str = "text-embedding-3-small") -> object:
+    """Create a minimal API config object for server tests."""
+    return type("MockAPIConfig", (), {
+        "base_url": "https://api.example.com/v1",
+        "api_key": "test-key",
+        "embedding_model": embedding_model,
+    })()
+
+
+class _RecordingWebSocket:
+    """Records the messages the server sends."""
+
+    def __init__(self) -> None:
+        self.sent: list[dict] = []  # type: ignore[type-arg]
+
+    async def send(self, message: str) -> None:
+        self.sent.append(json.loads(message))
+
+
+@pytest.fixture(autouse=True)
+def mock_loggers_and_api():
+    """Mock loggers used by server and server config, and the embeddings API."""
+    with patch("markdown_qa.server.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.server_config.get_server_logger", return_value=MagicMock()), \
+         patch("markdown_qa.embeddings.OpenAI"):
+        yield
+
+
+def _server(tmp_path, name, embedding_model="text-embedding-3-small"):
+    """A server of the index "docs" with its own cache under tmp_path/name."""
+    docs = tmp_path / name / "docs"
+    docs.mkdir(parents=True)
+    config = ServerConfig(
+        directories=[str(docs)],
+        api_config=_mock_api_config(embedding_model),
+        index_name="docs",
+    )
+    server = MarkdownQAServer(config)
+    manager = server.index_manager
+    manager.cache_manager = CacheManager(tmp_path / name / "cache")
+    manager.manifest = Manifest(manager.cache_manager.get_manifest_path())
+    return server, docs
+
+
+def _build_index(server, docs):
+    """Save and load an index of two chunks of one document, returning its vectors."""
+    manager = server.index_manager
+    path = str(docs / "guide.md")
+    vectors = np.random.default_rng(5).random((2, 8), dtype=np.float32)
+    store = VectorStore(cache_manager=manager.cache_manager, embedding_generator=MagicMock())
+    store.embedding_generator.generate_embeddings.return_value = vectors.tolist()
+    store.index = faiss.IndexIDMap2(faiss.IndexFlatL2(8))
+    chunks = [
+        {"text": "Install with pip", "metadata": {"file_path": path}},
+        {"text": "Configure the server", "metadata": {"file_path": path}},
+    ]
+    store.add_chunks_with_ids(chunks, [1000, 1001])
+    store.save_index("docs")
+    manager.manifest.add_index("docs", [str(docs)])
+    manager.manifest.set_file_metadata("docs", path, {"mtime": 1.0, "chunk_ids": [1000, 1001]})
+    manager.swap_index(store)
+    return vectors
+
+
+@pytest.mark.asyncio
+async def test_snapshot_restores_on_another_server(tmp_path):
+    """A snapshot carries the index, its manifest entry and the chunks' embeddings."""
+    server, docs = _server(tmp_path, "alice")
+    vectors = _build_index(server, docs)
+    archive = tmp_path / "docs.tar.gz"
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_snapshot", "path": str(archive)})  # type: ignore[arg-type]
+
+    assert ws.sent == [
+        {
+            "type": "index_snapshot",
+            "index": "docs",
+            "path": str(archive),
+            "chunks": 2,
+            "embedding_model": "text-embedding-3-small",
+            "size_bytes": os.path.getsize(archive),
+        }
+    ]
+
+    teammate, _ = _server(tmp_path, "bob")
+    await teammate._process_message(ws, {"type": "index_restore", "path": str(archive)})  # type: ignore[arg-type]
+
+    assert ws.sent[1]["type"] == "index_restore"
+    assert ws.sent[1]["chunks"] == 2
+    assert ws.sent[1]["reindexing"] is False
+    manager = teammate.index_manager
+    restored = manager.get_index()
+    assert restored.texts == ["Install with pip", "Configure the server"]
+    assert restored.search_ids(vectors[1].tolist(), k=1)[0][0] == 1001
+    assert manager.manifest.get_file_metadata("docs", str(docs / "guide.md"))["chunk_ids"] == [
+        1000,
+        1001,
+    ]
+    cached = manager._embedding_generator()._load_from_cache(
+        manager._embedding_generator()._get_cache_key("Configure the server")
+    )
+    assert cached == pytest.approx(vectors[1].tolist())
+
+
+@pytest.mark.asyncio
+async def test_restore_refuses_another_embedding_model(tmp_path):
+    """Vectors of another model can't be searched with this server's query embeddings."""
+    server, docs = _server(tmp_path, "alice")
+    _build_index(server, docs)
+    archive = tmp_path / "docs.tar"
+    ws = _RecordingWebSocket()
+    await server._process_message(ws, {"type": "index_snapshot", "path": str(archive)})  # type: ignore[arg-type]
+
+    teammate, _ = _server(tmp_path, "bob", embedding_model="text-embedding-3-large")
+    await teammate._process_message(ws, {"type": "index_restore", "path": str(archive)})  # type: ignore[arg-type]
+
+    assert ws.sent[1]["type"] == "error"
+    assert "text-embedding-3-small" in ws.sent[1]["message"]
+    assert teammate.index_manager.get_index() is None
+
+
+@pytest.mark.asyncio
+async def test_snapshot_paths_are_checked(tmp_path):
+    """Paths must be absolute archive names, and there must be an index to snapshot."""
+    server, _ = _server(tmp_path, "alice")
+    ws = _RecordingWebSocket()
+
+    await server._process_message(ws, {"type": "index_snapshot"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "index_snapshot", "path": "docs.tar"})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "index_restore", "path": str(tmp_path / "a.zip")})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "index_snapshot", "path": str(tmp_path / "a.tar")})  # type: ignore[arg-type]
+    await server._process_message(ws, {"type": "index_restore", "path": str(tmp_path / "b.tar")})  # type: ignore[arg-type]
+
+    messages = [reply["message"] for reply in ws.sent]
+    assert messages[0] == "Missing 'path' field"
+    assert messages[1] == "Field 'path' must be an absolute path"
+    assert "must end in" in messages[2]
+    assert messages[3] == "Index not loaded"
+    assert "Snapshot not found" in messages[4]
+
+
+def test_snapshots_may_not_run_code(tmp_path):
+    """A chunk metadata pickle referencing anything but plain data is refused."""
+
+    class Payload:
+        def __reduce__(self):
+            return (os.system, ("echo pwned",))
+
+    archive = tmp_path / "evil.tar"
+    members = {"index.faiss": b"", "index.pkl": pickle.dumps({"texts": [Payload()]})}
+    write_snapshot(archive, {"embedding_model": "text-embedding-3-small"}, members)
+
+    with pytest.raises(ValueError, match="not plain chunk data"):
+        read_snapshot(archive)
+
+
+def test_snapshots_read_only_known_members(tmp_path):
+    """Other members, such as paths escaping the cache, are skipped, and versions checked."""
+    archive = tmp_path / "odd.tar"
+    with tarfile.open(archive, "w") as tar:
+        for name, data in [
+            ("snapshot.json", json.dumps({"version": 1}).encode()),
+            ("index.faiss", b"faiss"),
+            ("index.pkl", pickle.dumps({"texts": ["a"], "metadata": [{}]})),
+            ("../../.bashrc", b"rm -rf ~"),
+        ]:
+            member = tarfile.TarInfo(name)
+            member.size = len(data)
+            tar.addfile(member, io.BytesIO(data))
+
+    _, members = read_snapshot(archive)
+    assert sorted(members) == ["index.faiss", "index.pkl"]
+
+    write_snapshot(archive, {}, {"index.faiss": b"", "index.pkl": pickle.dumps({})})
+    with tarfile.open(archive, "a") as tar:
+        data = json.dumps({"version": 99}).encode()
+        member = tarfile.TarInfo("snapshot.json")
+        member.size = len(data)
+        tar.addfile(member, io.BytesIO(data))
+    with pytest.raises(ValueError, match="Unsupported snapshot version 99"):
+        read_snapshot(archive)